@@ -0,0 +1,516 @@
+//! Resource table parser.
+//!
+//! Parses the compiled `resources.arsc` file of the application, so that resources needed by the
+//! analysis, such as strings, are available even if the resource decoding failed. Only the parts
+//! of the format needed to resolve simple values are decoded.
+
+use std::{fs, path::Path};
+
+use failure::{bail, Error};
+
+/// String pool chunk type.
+pub(crate) const RES_STRING_POOL_TYPE: u16 = 0x0001;
+/// Resource table chunk type.
+const RES_TABLE_TYPE: u16 = 0x0002;
+/// Package chunk type.
+const RES_TABLE_PACKAGE_TYPE: u16 = 0x0200;
+/// Type chunk type.
+const RES_TABLE_TYPE_TYPE: u16 = 0x0201;
+/// String pool flag indicating UTF-8 encoded strings.
+pub(crate) const UTF8_FLAG: u32 = 0x0100;
+/// Type chunk flag indicating sparse entry offsets.
+const FLAG_SPARSE: u8 = 0x01;
+/// Entry flag indicating a complex (map) value.
+const FLAG_COMPLEX: u16 = 0x0001;
+/// Offset marking a missing entry.
+const NO_ENTRY: u32 = 0xFFFF_FFFF;
+/// Value data type for references to the global string pool.
+pub(crate) const TYPE_STRING: u8 = 0x03;
+
+/// Resource table of an application.
+#[derive(Debug, Default)]
+pub struct ResourceTable {
+    /// Resource entries, for every configuration.
+    entries: Vec<Entry>,
+}
+
+/// Resource entry for a given configuration.
+#[derive(Debug)]
+struct Entry {
+    /// Resource ID of the entry.
+    id: u32,
+    /// Name of the type of the resource, such as `string`.
+    type_name: String,
+    /// Name of the resource.
+    name: String,
+    /// Locale of the configuration, empty for the default one.
+    locale: String,
+    /// Value of the resource, if it's a string.
+    value: Option<String>,
+}
+
+impl ResourceTable {
+    /// Loads the resource table from the given file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Parses the resource table from its binary representation.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (kind, header_size, table) = chunk(data, 0)?;
+        if kind != RES_TABLE_TYPE {
+            bail!("the file is not a resource table");
+        }
+
+        let mut strings = Vec::new();
+        let mut entries = Vec::new();
+        let mut offset = header_size;
+        while offset < table.len() {
+            let (kind, _, chunk) = chunk(table, offset)?;
+            match kind {
+                RES_STRING_POOL_TYPE => strings = parse_string_pool(chunk)?,
+                RES_TABLE_PACKAGE_TYPE => parse_package(chunk, &strings, &mut entries)?,
+                _ => {}
+            }
+            offset += chunk.len();
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Gets the value of the string resource with the given name.
+    ///
+    /// The English translation is preferred, then the default configuration and finally any
+    /// other locale.
+    pub fn string<S: AsRef<str>>(&self, name: S) -> Option<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.type_name == "string" && e.name == name.as_ref() && e.value.is_some())
+            .min_by_key(|e| match e.locale.as_str() {
+                "en" => 0,
+                "" => 1,
+                l if l.starts_with("en-") => 2,
+                _ => 3,
+            })
+            .and_then(|e| e.value.as_ref().map(String::as_str))
+    }
+
+    /// Gets the path of the file resource of the given type with the given name.
+    ///
+    /// File resources, such as XML or raw resources, store the path of their file in the
+    /// package as their value, which is the only way to find them when their paths have been
+    /// obfuscated.
+    pub fn file<T: AsRef<str>, N: AsRef<str>>(&self, type_name: T, name: N) -> Option<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.type_name == type_name.as_ref() && e.name == name.as_ref())
+            .filter_map(|e| e.value.as_ref())
+            .map(String::as_str)
+            .next()
+    }
+
+    /// Gets the path of the file resource with the given resource ID.
+    pub fn file_by_id(&self, id: u32) -> Option<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.id == id)
+            .filter_map(|e| e.value.as_ref())
+            .map(String::as_str)
+            .next()
+    }
+
+    /// Gets the paths of all the file resources of the given type.
+    pub fn files<T: AsRef<str>>(&self, type_name: T) -> Vec<&str> {
+        let mut files = self
+            .entries
+            .iter()
+            .filter(|e| e.type_name == type_name.as_ref())
+            .filter_map(|e| e.value.as_ref())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        files.sort();
+        files.dedup();
+        files
+    }
+}
+
+/// Parses a package chunk, adding its entries to the given vector.
+fn parse_package(data: &[u8], strings: &[String], entries: &mut Vec<Entry>) -> Result<(), Error> {
+    let header_size = read_u16(data, 2)? as usize;
+    let package_id = read_u32(data, 8)?;
+    let type_strings = parse_string_pool(chunk(data, read_u32(data, 268)? as usize)?.2)?;
+    let key_strings = parse_string_pool(chunk(data, read_u32(data, 276)? as usize)?.2)?;
+
+    let mut offset = header_size;
+    while offset < data.len() {
+        let (kind, _, chunk) = chunk(data, offset)?;
+        if kind == RES_TABLE_TYPE_TYPE {
+            parse_type(
+                chunk,
+                package_id,
+                strings,
+                (&type_strings, &key_strings),
+                entries,
+            )?;
+        }
+        offset += chunk.len();
+    }
+
+    Ok(())
+}
+
+/// Parses a type chunk, adding its entries to the given vector.
+fn parse_type(
+    data: &[u8],
+    package_id: u32,
+    strings: &[String],
+    (type_strings, key_strings): (&[String], &[String]),
+    entries: &mut Vec<Entry>,
+) -> Result<(), Error> {
+    let header_size = read_u16(data, 2)? as usize;
+    let type_id = read_u8(data, 8)? as usize;
+    let flags = read_u8(data, 9)?;
+    let entry_count = read_u32(data, 12)? as usize;
+    let entries_start = read_u32(data, 16)? as usize;
+    let locale = locale(data, 20)?;
+    let type_name = match type_id.checked_sub(1).and_then(|i| type_strings.get(i)) {
+        Some(name) => name,
+        None => bail!("invalid resource type ID {}", type_id),
+    };
+
+    for i in 0..entry_count {
+        let (index, offset) = if flags & FLAG_SPARSE == 0 {
+            let offset = read_u32(data, header_size + 4 * i)?;
+            if offset == NO_ENTRY {
+                continue;
+            }
+            (i as u32, offset as usize)
+        } else {
+            (
+                u32::from(read_u16(data, header_size + 4 * i)?),
+                read_u16(data, header_size + 4 * i + 2)? as usize * 4,
+            )
+        };
+
+        let position = entries_start + offset;
+        let entry_flags = read_u16(data, position + 2)?;
+        let key = read_u32(data, position + 4)? as usize;
+        let value =
+            if entry_flags & FLAG_COMPLEX == 0 && read_u8(data, position + 11)? == TYPE_STRING {
+                strings
+                    .get(read_u32(data, position + 12)? as usize)
+                    .cloned()
+            } else {
+                None
+            };
+
+        entries.push(Entry {
+            id: (package_id << 24) | ((type_id as u32) << 16) | index,
+            type_name: type_name.clone(),
+            name: key_strings.get(key).cloned().unwrap_or_default(),
+            locale: locale.clone(),
+            value,
+        });
+    }
+
+    Ok(())
+}
+
+/// Parses a string pool chunk.
+fn parse_string_pool(data: &[u8]) -> Result<Vec<String>, Error> {
+    let header_size = read_u16(data, 2)? as usize;
+    let count = read_u32(data, 8)? as usize;
+    let flags = read_u32(data, 16)?;
+    let strings_start = read_u32(data, 20)? as usize;
+
+    (0..count)
+        .map(|i| {
+            let position = strings_start + read_u32(data, header_size + 4 * i)? as usize;
+            if flags & UTF8_FLAG == 0 {
+                read_utf16_string(data, position)
+            } else {
+                read_utf8_string(data, position)
+            }
+        })
+        .collect()
+}
+
+/// Gets the locale of the configuration starting at the given offset, as `language-REGION`.
+fn locale(data: &[u8], offset: usize) -> Result<String, Error> {
+    let language = [read_u8(data, offset + 8)?, read_u8(data, offset + 9)?];
+    let country = [read_u8(data, offset + 10)?, read_u8(data, offset + 11)?];
+
+    let mut locale = unpack_locale_code(language, b'a');
+    if !locale.is_empty() && country[0] & 0x80 == 0 {
+        let country = unpack_locale_code(country, b'0');
+        if !country.is_empty() {
+            locale.push('-');
+            locale.push_str(&country);
+        }
+    }
+    Ok(locale)
+}
+
+/// Unpacks a language or country code, that can be packed in two bytes if it has 3 letters.
+fn unpack_locale_code(code: [u8; 2], base: u8) -> String {
+    if code[0] == 0 {
+        String::new()
+    } else if code[0] & 0x80 == 0 {
+        String::from_utf8_lossy(&code).into_owned()
+    } else {
+        let letters = [
+            code[1] & 0x1f,
+            ((code[1] & 0xe0) >> 5) | ((code[0] & 0x03) << 3),
+            (code[0] & 0x7c) >> 2,
+        ];
+        letters.iter().map(|l| char::from(base + l)).collect()
+    }
+}
+
+/// Reads a UTF-8 string from the string pool.
+pub(crate) fn read_utf8_string(data: &[u8], position: usize) -> Result<String, Error> {
+    // The number of characters comes first, followed by the number of bytes.
+    let (_, chars_size) = read_utf8_length(data, position)?;
+    let (length, bytes_size) = read_utf8_length(data, position + chars_size)?;
+    let start = position + chars_size + bytes_size;
+
+    match data.get(start..start + length) {
+        Some(bytes) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        None => bail!("string out of bounds at offset {}", start),
+    }
+}
+
+/// Reads the length of a UTF-8 string, returning it with the number of bytes it occupied.
+fn read_utf8_length(data: &[u8], position: usize) -> Result<(usize, usize), Error> {
+    let first = read_u8(data, position)? as usize;
+    if first & 0x80 == 0 {
+        Ok((first, 1))
+    } else {
+        Ok((
+            ((first & 0x7f) << 8) | read_u8(data, position + 1)? as usize,
+            2,
+        ))
+    }
+}
+
+/// Reads a UTF-16 string from the string pool.
+pub(crate) fn read_utf16_string(data: &[u8], position: usize) -> Result<String, Error> {
+    let first = read_u16(data, position)? as usize;
+    let (length, start) = if first & 0x8000 == 0 {
+        (first, position + 2)
+    } else {
+        (
+            ((first & 0x7fff) << 16) | read_u16(data, position + 2)? as usize,
+            position + 4,
+        )
+    };
+
+    let units = (0..length)
+        .map(|i| read_u16(data, start + 2 * i))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(String::from_utf16_lossy(&units))
+}
+
+/// Gets the chunk at the given offset, returning its type, its header size and its data.
+pub(crate) fn chunk(data: &[u8], offset: usize) -> Result<(u16, usize, &[u8]), Error> {
+    let kind = read_u16(data, offset)?;
+    let header_size = read_u16(data, offset + 2)? as usize;
+    let size = read_u32(data, offset + 4)? as usize;
+    if size < 8 || header_size < 8 || header_size > size {
+        bail!("invalid chunk header at offset {}", offset);
+    }
+
+    match data.get(offset..offset + size) {
+        Some(chunk) => Ok((kind, header_size, chunk)),
+        None => bail!("chunk out of bounds at offset {}", offset),
+    }
+}
+
+/// Reads a byte at the given offset.
+pub(crate) fn read_u8(data: &[u8], offset: usize) -> Result<u8, Error> {
+    match data.get(offset) {
+        Some(b) => Ok(*b),
+        None => bail!("unexpected end of data at offset {}", offset),
+    }
+}
+
+/// Reads a little endian `u16` at the given offset.
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    Ok(u16::from(read_u8(data, offset)?) | u16::from(read_u8(data, offset + 1)?) << 8)
+}
+
+/// Reads a little endian `u32` at the given offset.
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    Ok(u32::from(read_u16(data, offset)?) | u32::from(read_u16(data, offset + 2)?) << 16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{unpack_locale_code, ResourceTable};
+
+    /// Appends a little endian `u16` to the buffer.
+    fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+        buffer.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+    }
+
+    /// Appends a little endian `u32` to the buffer.
+    fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+        put_u16(buffer, value as u16);
+        put_u16(buffer, (value >> 16) as u16);
+    }
+
+    /// Builds a UTF-8 string pool chunk.
+    fn string_pool(strings: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for s in strings {
+            offsets.push(data.len() as u32);
+            data.push(s.len() as u8);
+            data.push(s.len() as u8);
+            data.extend_from_slice(s.as_bytes());
+            data.push(0);
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+
+        let header_size = 28;
+        let strings_start = header_size + 4 * strings.len();
+        let mut chunk = Vec::new();
+        put_u16(&mut chunk, 0x0001);
+        put_u16(&mut chunk, header_size as u16);
+        put_u32(&mut chunk, (strings_start + data.len()) as u32);
+        put_u32(&mut chunk, strings.len() as u32);
+        put_u32(&mut chunk, 0);
+        put_u32(&mut chunk, 0x0100);
+        put_u32(&mut chunk, strings_start as u32);
+        put_u32(&mut chunk, 0);
+        for offset in offsets {
+            put_u32(&mut chunk, offset);
+        }
+        chunk.extend_from_slice(&data);
+        chunk
+    }
+
+    /// Builds a type chunk with string entries, referencing the global string pool.
+    fn type_chunk(language: &[u8; 2], values: &[(u32, u32)]) -> Vec<u8> {
+        let header_size = 20 + 36;
+        let entries_start = header_size + 4 * values.len();
+        let mut chunk = Vec::new();
+        put_u16(&mut chunk, 0x0201);
+        put_u16(&mut chunk, header_size as u16);
+        put_u32(&mut chunk, (entries_start + 16 * values.len()) as u32);
+        chunk.extend_from_slice(&[1, 0, 0, 0]);
+        put_u32(&mut chunk, values.len() as u32);
+        put_u32(&mut chunk, entries_start as u32);
+        put_u32(&mut chunk, 36);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk.extend_from_slice(language);
+        chunk.extend_from_slice(&[0; 26]);
+        for i in 0..values.len() {
+            put_u32(&mut chunk, 16 * i as u32);
+        }
+        for &(key, string) in values {
+            put_u16(&mut chunk, 8);
+            put_u16(&mut chunk, 0);
+            put_u32(&mut chunk, key);
+            put_u16(&mut chunk, 8);
+            chunk.extend_from_slice(&[0, 0x03]);
+            put_u32(&mut chunk, string);
+        }
+        chunk
+    }
+
+    /// Builds a resource table with an `app_name` and a `description` string.
+    fn resource_table() -> Vec<u8> {
+        let type_strings = string_pool(&["string"]);
+        let key_strings = string_pool(&["app_name", "description"]);
+        let default_type = type_chunk(&[0, 0], &[(0, 0), (1, 1)]);
+        let english_type = type_chunk(b"en", &[(0, 2)]);
+
+        let package_header = 288;
+        let mut package = Vec::new();
+        put_u16(&mut package, 0x0200);
+        put_u16(&mut package, package_header as u16);
+        put_u32(
+            &mut package,
+            (package_header
+                + type_strings.len()
+                + key_strings.len()
+                + default_type.len()
+                + english_type.len()) as u32,
+        );
+        put_u32(&mut package, 0x7f);
+        package.extend_from_slice(&[0; 256]);
+        put_u32(&mut package, package_header as u32);
+        put_u32(&mut package, 0);
+        put_u32(&mut package, (package_header + type_strings.len()) as u32);
+        put_u32(&mut package, 0);
+        put_u32(&mut package, 0);
+        package.extend_from_slice(&type_strings);
+        package.extend_from_slice(&key_strings);
+        package.extend_from_slice(&default_type);
+        package.extend_from_slice(&english_type);
+
+        let strings = string_pool(&["App", "A test application", "English app"]);
+        let mut table = Vec::new();
+        put_u16(&mut table, 0x0002);
+        put_u16(&mut table, 12);
+        put_u32(&mut table, (12 + strings.len() + package.len()) as u32);
+        put_u32(&mut table, 1);
+        table.extend_from_slice(&strings);
+        table.extend_from_slice(&package);
+        table
+    }
+
+    /// Checks that string resources are resolved, preferring the English translation.
+    #[test]
+    fn it_string() {
+        let table = ResourceTable::parse(&resource_table()).unwrap();
+
+        assert_eq!(table.string("app_name"), Some("English app"));
+        assert_eq!(table.string("description"), Some("A test application"));
+        assert_eq!(table.string("unknown"), None);
+    }
+
+    /// Checks that resources are found by name and by resource ID, with their IDs built from the
+    /// package, type and entry indexes.
+    #[test]
+    fn it_file() {
+        let table = ResourceTable::parse(&resource_table()).unwrap();
+
+        assert_eq!(
+            table.file("string", "description"),
+            Some("A test application")
+        );
+        assert_eq!(table.file("xml", "description"), None);
+        assert_eq!(table.file_by_id(0x7f01_0001), Some("A test application"));
+        assert_eq!(table.file_by_id(0x7f01_0000), Some("App"));
+        assert_eq!(table.file_by_id(0x7f02_0000), None);
+        assert_eq!(
+            table.files("string"),
+            vec!["A test application", "App", "English app"]
+        );
+        assert!(table.files("raw").is_empty());
+    }
+
+    /// Checks that truncated tables are rejected instead of panicking.
+    #[test]
+    fn it_truncated() {
+        let mut data = resource_table();
+        let len = data.len();
+        data.truncate(len - 10);
+
+        assert!(ResourceTable::parse(&data).is_err());
+        assert!(ResourceTable::parse(&[0x02, 0x00]).is_err());
+    }
+
+    /// Checks the unpacking of locale codes.
+    #[test]
+    fn it_unpack_locale_code() {
+        assert_eq!(unpack_locale_code([0, 0], b'a'), "");
+        assert_eq!(unpack_locale_code(*b"en", b'a'), "en");
+        // "fil" packed in two bytes.
+        assert_eq!(unpack_locale_code([0xad, 0x05], b'a'), "fil");
+    }
+}
@@ -0,0 +1,251 @@
+//! Audit log module.
+//!
+//! When the results of an analysis are used as formal evidence, it must be possible to tell what
+//! the analyzer did to produce them. If an audit log is configured, every action of each analysis
+//! is appended to it as a JSON line: the files extracted from the package, the external tools
+//! invoked with their arguments, the hashes computed and the reports written. The log is never
+//! truncated, so it keeps the records of the previous runs.
+//!
+//! Each line is a JSON object with the time of the action, the analyzed package, the action and
+//! its details:
+//!
+//! ```json
+//! {"time":"2019-03-01T10:20:30+01:00","package":"app.apk","action":"report_written","details":{}}
+//! ```
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use chrono::Local;
+use failure::{Error, ResultExt};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{print_warning, Config};
+
+lazy_static! {
+    /// Audit log of the current run, if one is configured.
+    static ref AUDIT_LOG: Mutex<Option<AuditLog>> = Mutex::new(None);
+}
+
+/// Audit log file, with the package being analyzed.
+#[derive(Debug)]
+struct AuditLog {
+    /// Log file, opened in append mode.
+    file: File,
+    /// Package being analyzed, if any.
+    package: Option<String>,
+}
+
+impl AuditLog {
+    /// Opens the audit log at the given path, creating it if it does not exist.
+    fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())?;
+        Ok(Self {
+            file,
+            package: None,
+        })
+    }
+
+    /// Appends an action to the log.
+    ///
+    /// The line is written with a single call, so that it's never interleaved with the lines
+    /// written from other threads or processes.
+    fn write(&mut self, action: &str, details: Value) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&json!({
+            "time": Local::now().to_rfc3339(),
+            "package": self.package,
+            "action": action,
+            "details": details,
+        }))?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+/// Locks the audit log of the current run.
+fn lock() -> MutexGuard<'static, Option<AuditLog>> {
+    // A panic while writing a line must not disable the log for the rest of the applications.
+    AUDIT_LOG.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Opens the audit log of the configuration, if any.
+pub fn open(config: &Config) -> Result<(), Error> {
+    if let Some(path) = config.audit_log() {
+        let log = AuditLog::open(path).context(format!(
+            "the audit log at {} could not be opened",
+            path.display()
+        ))?;
+        *lock() = Some(log);
+    }
+    Ok(())
+}
+
+/// Returns true if the actions are being recorded in an audit log.
+pub fn is_enabled() -> bool {
+    lock().is_some()
+}
+
+/// Records an action of the current analysis, with its details.
+///
+/// Errors writing the log are printed as warnings, since they should not stop the analysis.
+pub fn record(action: &str, details: Value) {
+    if let Some(ref mut log) = *lock() {
+        if let Err(e) = log.write(action, details) {
+            print_warning(format!(
+                "the {} action could not be recorded in the audit log: {}",
+                action, e
+            ));
+        }
+    }
+}
+
+/// Records an action on a file, adding its path, its size and its SHA-256 hash to the details.
+pub fn record_file<P: AsRef<Path>>(action: &str, path: P, details: Value) {
+    if !is_enabled() {
+        return;
+    }
+
+    let mut details = match details {
+        Value::Object(details) => details,
+        Value::Null => serde_json::Map::new(),
+        other => {
+            let mut details = serde_json::Map::new();
+            let _ = details.insert("details".to_owned(), other);
+            details
+        }
+    };
+    let _ = details.insert(
+        "path".to_owned(),
+        json!(path.as_ref().to_string_lossy().replace('\\', "/")),
+    );
+    match fs::read(path.as_ref()) {
+        Ok(content) => {
+            let _ = details.insert("size".to_owned(), json!(content.len()));
+            let _ = details.insert(
+                "sha256".to_owned(),
+                json!(hex::encode(Sha256::digest(&content))),
+            );
+        }
+        Err(e) => {
+            let _ = details.insert("error".to_owned(), json!(e.to_string()));
+        }
+    }
+    record(action, Value::Object(details));
+}
+
+/// Records an action on every file of a folder, in path order.
+pub fn record_folder<P: AsRef<Path>>(action: &str, folder: P, details: &Value) {
+    if !is_enabled() {
+        return;
+    }
+
+    let mut files = Vec::new();
+    if let Err(e) = list_files(folder.as_ref(), &mut files) {
+        print_warning(format!(
+            "the files of {} could not be recorded in the audit log: {}",
+            folder.as_ref().display(),
+            e
+        ));
+    }
+    files.sort();
+    for file in files {
+        record_file(action, file, details.clone());
+    }
+}
+
+/// Lists the files of a folder and its subfolders.
+fn list_files(folder: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Records the start of the analysis of a package, with the hash of the package.
+///
+/// The following actions are recorded for this package, until the analysis finishes.
+pub fn start_analysis<P: AsRef<Path>>(package: P) {
+    if let Some(ref mut log) = *lock() {
+        log.package = Some(package.as_ref().to_string_lossy().into_owned());
+    }
+    record_file("analysis_started", package, Value::Null);
+}
+
+/// Records the end of the analysis of the current package, with its outcome: `completed`,
+/// `failed`, `crashed` or `cancelled`, and the error, if any.
+pub fn finish_analysis(outcome: &str, error: Option<String>) {
+    record(
+        "analysis_finished",
+        json!({
+            "outcome": outcome,
+            "error": error,
+        }),
+    );
+    if let Some(ref mut log) = *lock() {
+        log.package = None;
+    }
+}
+
+/// Audit log testing module.
+#[cfg(test)]
+mod test {
+    use std::{fs, io::Write};
+
+    use serde_json::{json, Value};
+
+    use super::AuditLog;
+
+    /// Checks that the actions are appended to the log as JSON lines.
+    #[test]
+    fn it_write() {
+        let path = "target/audit_test/audit.jsonl";
+        let _ = fs::remove_file(path);
+
+        let mut log = AuditLog::open(path).unwrap();
+        log.write("analysis_started", Value::Null).unwrap();
+        log.package = Some("app.apk".to_owned());
+        log.write("tool_invoked", json!({ "command": "jadx", "status": 0 }))
+            .unwrap();
+        drop(log);
+
+        // Reopening the log must keep the previous lines.
+        let mut log = AuditLog::open(path).unwrap();
+        log.write("report_written", json!({})).unwrap();
+        log.file.flush().unwrap();
+
+        let content = fs::read_to_string(path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["action"], "analysis_started");
+        assert!(lines[0]["package"].is_null());
+        assert_eq!(lines[1]["package"], "app.apk");
+        assert_eq!(lines[1]["details"]["command"], "jadx");
+        assert_eq!(lines[1]["details"]["status"], 0);
+        assert_eq!(lines[2]["action"], "report_written");
+        assert!(lines[2]["time"].is_string());
+
+        fs::remove_dir_all("target/audit_test").unwrap();
+    }
+}
@@ -0,0 +1,340 @@
+//! Binary XML recovery module.
+//!
+//! Best-effort parser for compiled XML files, used when the binary manifest of an application
+//! could not be decoded, usually because it was deliberately malformed to break analysis tools.
+//! Chunks that can't be parsed are skipped, and attributes are identified by their resource ID
+//! when possible, since their names in the string pool can be tampered with.
+
+use std::fmt;
+
+use failure::Error;
+use xml::{attribute::OwnedAttribute, name::OwnedName};
+
+use crate::arsc::{
+    chunk, read_u16, read_u32, read_u8, read_utf16_string, read_utf8_string, RES_STRING_POOL_TYPE,
+    TYPE_STRING, UTF8_FLAG,
+};
+
+/// Resource ID map chunk type.
+const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+/// Start element chunk type.
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+/// Index marking a missing string.
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+/// Value data type for references to other resources.
+const TYPE_REFERENCE: u8 = 0x01;
+/// Value data type for decimal integers.
+const TYPE_INT_DEC: u8 = 0x10;
+/// Value data type for hexadecimal integers.
+const TYPE_INT_HEX: u8 = 0x11;
+/// Value data type for booleans.
+const TYPE_INT_BOOLEAN: u8 = 0x12;
+/// Minimum size of an attribute in a start element chunk.
+const ATTRIBUTE_SIZE: usize = 20;
+
+/// Element recovered from a binary XML file.
+#[derive(Debug)]
+pub struct Element {
+    /// Name of the element.
+    name: String,
+    /// Attributes of the element.
+    attributes: Vec<OwnedAttribute>,
+}
+
+impl Element {
+    /// Gets the name of the element.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the attributes of the element.
+    pub fn attributes(&self) -> &[OwnedAttribute] {
+        &self.attributes
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for attr in &self.attributes {
+            write!(f, " {}=\"{}\"", attr.name, attr.value)?;
+        }
+        write!(f, ">")
+    }
+}
+
+/// Checks if the given data is a binary XML file.
+pub fn is_binary_xml(data: &[u8]) -> bool {
+    data.starts_with(&[0x03, 0x00])
+}
+
+/// Recovers the elements of a binary XML file, in order.
+pub fn recover_elements(data: &[u8]) -> Vec<Element> {
+    let mut strings = Vec::new();
+    let mut resource_ids = Vec::new();
+    let mut elements = Vec::new();
+
+    // The declared size of the XML header is not trusted, so it's skipped directly.
+    let mut offset = 8;
+    while offset + 8 <= data.len() {
+        if let Ok((kind, header_size, chunk)) = chunk(data, offset) {
+            match kind {
+                RES_STRING_POOL_TYPE => strings = recover_string_pool(chunk),
+                RES_XML_RESOURCE_MAP_TYPE => {
+                    resource_ids = (header_size..chunk.len())
+                        .step_by(4)
+                        .filter_map(|o| read_u32(chunk, o).ok())
+                        .collect()
+                }
+                RES_XML_START_ELEMENT_TYPE => {
+                    if let Ok(element) =
+                        parse_start_element(chunk, header_size, &strings, &resource_ids)
+                    {
+                        elements.push(element);
+                    }
+                }
+                _ => {}
+            }
+            offset += chunk.len();
+        } else {
+            // Malformed chunk: look for the next valid chunk in the following aligned positions.
+            offset += 4;
+        }
+    }
+
+    elements
+}
+
+/// Recovers the strings of a string pool, leaving empty the ones that can't be read.
+fn recover_string_pool(data: &[u8]) -> Vec<String> {
+    let header_size = read_u16(data, 2).unwrap_or(28) as usize;
+    let count = read_u32(data, 8).unwrap_or(0) as usize;
+    let flags = read_u32(data, 16).unwrap_or(0);
+    let strings_start = read_u32(data, 20).unwrap_or(0) as usize;
+
+    // The count can't be trusted, but there can't be more strings than offsets.
+    let count = count.min(data.len().saturating_sub(header_size) / 4);
+    (0..count)
+        .map(|i| {
+            read_u32(data, header_size + 4 * i)
+                .and_then(|offset| {
+                    let position = strings_start + offset as usize;
+                    if flags & UTF8_FLAG == 0 {
+                        read_utf16_string(data, position)
+                    } else {
+                        read_utf8_string(data, position)
+                    }
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Parses a start element chunk.
+fn parse_start_element(
+    data: &[u8],
+    header_size: usize,
+    strings: &[String],
+    resource_ids: &[u32],
+) -> Result<Element, Error> {
+    let name = string(strings, read_u32(data, header_size + 4)?);
+    let attribute_start = read_u16(data, header_size + 8)? as usize;
+    let attribute_size = (read_u16(data, header_size + 10)? as usize).max(ATTRIBUTE_SIZE);
+    let attribute_count = read_u16(data, header_size + 12)? as usize;
+
+    let mut attributes = Vec::new();
+    for i in 0..attribute_count {
+        let position = header_size + attribute_start + i * attribute_size;
+        let namespace = read_u32(data, position)?;
+        let name_index = read_u32(data, position + 4)?;
+        let raw_value = read_u32(data, position + 8)?;
+        let data_type = read_u8(data, position + 15)?;
+        let value_data = read_u32(data, position + 16)?;
+
+        let name = resource_ids
+            .get(name_index as usize)
+            .and_then(|id| attribute_name(*id))
+            .map_or_else(|| string(strings, name_index), str::to_owned);
+        if name.is_empty() {
+            continue;
+        }
+
+        let value = match data_type {
+            TYPE_STRING => string(strings, value_data),
+            TYPE_INT_DEC | TYPE_INT_HEX if name == "installLocation" => match value_data {
+                0 => "auto".to_owned(),
+                1 => "internalOnly".to_owned(),
+                2 => "preferExternal".to_owned(),
+                _ => value_data.to_string(),
+            },
+            TYPE_INT_DEC | TYPE_INT_HEX if name == "launchMode" => match value_data {
+                0 => "standard".to_owned(),
+                1 => "singleTop".to_owned(),
+                2 => "singleTask".to_owned(),
+                3 => "singleInstance".to_owned(),
+                4 => "singleInstancePerTask".to_owned(),
+                _ => value_data.to_string(),
+            },
+            TYPE_INT_DEC | TYPE_INT_HEX => value_data.to_string(),
+            TYPE_INT_BOOLEAN => (value_data != 0).to_string(),
+            TYPE_REFERENCE => format!("@{:08X}", value_data),
+            _ if raw_value != NO_INDEX => string(strings, raw_value),
+            _ => value_data.to_string(),
+        };
+
+        let mut name = OwnedName::local(name);
+        if namespace != NO_INDEX {
+            name.prefix = Some("android".to_owned());
+        }
+        attributes.push(OwnedAttribute::new(name, value));
+    }
+
+    Ok(Element { name, attributes })
+}
+
+/// Gets the string at the given index of the pool, or an empty string if it does not exist.
+fn string(strings: &[String], index: u32) -> String {
+    strings.get(index as usize).cloned().unwrap_or_default()
+}
+
+/// Gets the name of the Android attribute with the given resource ID, if it's a known one.
+fn attribute_name(id: u32) -> Option<&'static str> {
+    match id {
+        0x0101_0001 => Some("label"),
+        0x0101_0003 => Some("name"),
+        0x0101_0006 => Some("permission"),
+        0x0101_000f => Some("debuggable"),
+        0x0101_0010 => Some("exported"),
+        0x0101_0012 => Some("taskAffinity"),
+        0x0101_001d => Some("launchMode"),
+        0x0101_020c => Some("minSdkVersion"),
+        0x0101_021b => Some("versionCode"),
+        0x0101_021c => Some("versionName"),
+        0x0101_0270 => Some("targetSdkVersion"),
+        0x0101_0280 => Some("allowBackup"),
+        0x0101_02b7 => Some("installLocation"),
+        0x0101_035a => Some("largeHeap"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_binary_xml, recover_elements};
+
+    /// Appends a little endian `u16` to the buffer.
+    fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+        buffer.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+    }
+
+    /// Appends a little endian `u32` to the buffer.
+    fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+        put_u16(buffer, value as u16);
+        put_u16(buffer, (value >> 16) as u16);
+    }
+
+    /// Builds a binary manifest with a `manifest` and an `application` element, with a corrupted
+    /// chunk in between.
+    fn binary_manifest() -> Vec<u8> {
+        // Strings: tampered attribute name, "package", "manifest", "com.example", "application".
+        let strings = ["", "package", "manifest", "com.example", "application"];
+        let mut pool = Vec::new();
+        put_u16(&mut pool, 0x0001);
+        put_u16(&mut pool, 28);
+        let size_position = pool.len();
+        put_u32(&mut pool, 0);
+        put_u32(&mut pool, strings.len() as u32);
+        put_u32(&mut pool, 0);
+        put_u32(&mut pool, 0x0100);
+        put_u32(&mut pool, 28 + 4 * strings.len() as u32);
+        put_u32(&mut pool, 0);
+        let mut data = Vec::new();
+        for s in &strings {
+            put_u32(&mut pool, data.len() as u32);
+            data.push(s.len() as u8);
+            data.push(s.len() as u8);
+            data.extend_from_slice(s.as_bytes());
+            data.push(0);
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        pool.extend_from_slice(&data);
+        let pool_len = pool.len() as u32;
+        pool[size_position..size_position + 4].copy_from_slice(&[
+            pool_len as u8,
+            (pool_len >> 8) as u8,
+            0,
+            0,
+        ]);
+
+        // Resource map: string 0 is `debuggable`.
+        let mut map = Vec::new();
+        put_u16(&mut map, 0x0180);
+        put_u16(&mut map, 8);
+        put_u32(&mut map, 12);
+        put_u32(&mut map, 0x0101_000f);
+
+        let element = |name: u32, attributes: &[(u32, u32, u8, u32)]| {
+            let mut chunk = Vec::new();
+            put_u16(&mut chunk, 0x0102);
+            put_u16(&mut chunk, 16);
+            put_u32(&mut chunk, 36 + 20 * attributes.len() as u32);
+            put_u32(&mut chunk, 1);
+            put_u32(&mut chunk, 0xFFFF_FFFF);
+            put_u32(&mut chunk, 0xFFFF_FFFF);
+            put_u32(&mut chunk, name);
+            put_u16(&mut chunk, 20);
+            put_u16(&mut chunk, 20);
+            put_u16(&mut chunk, attributes.len() as u16);
+            put_u16(&mut chunk, 0);
+            put_u16(&mut chunk, 0);
+            put_u16(&mut chunk, 0);
+            for &(namespace, name, data_type, value) in attributes {
+                put_u32(&mut chunk, namespace);
+                put_u32(&mut chunk, name);
+                put_u32(&mut chunk, 0xFFFF_FFFF);
+                put_u16(&mut chunk, 8);
+                chunk.extend_from_slice(&[0, data_type]);
+                put_u32(&mut chunk, value);
+            }
+            chunk
+        };
+
+        let mut xml = Vec::new();
+        put_u16(&mut xml, 0x0003);
+        put_u16(&mut xml, 8);
+        // Deliberately wrong size.
+        put_u32(&mut xml, 0xFFFF_FFFF);
+        xml.extend_from_slice(&pool);
+        xml.extend_from_slice(&map);
+        xml.extend_from_slice(&element(2, &[(0xFFFF_FFFF, 1, 0x03, 3)]));
+        // Corrupted chunk.
+        xml.extend_from_slice(&[0xFF; 12]);
+        xml.extend_from_slice(&element(4, &[(5, 0, 0x12, 0xFFFF_FFFF)]));
+        xml
+    }
+
+    /// Checks that the elements are recovered from a corrupted binary manifest.
+    #[test]
+    fn it_recover_elements() {
+        let data = binary_manifest();
+        assert!(is_binary_xml(&data));
+        assert!(!is_binary_xml(b"<?xml version=\"1.0\"?>"));
+
+        let elements = recover_elements(&data);
+        assert_eq!(elements.len(), 2);
+
+        assert_eq!(elements[0].name(), "manifest");
+        assert_eq!(elements[0].attributes()[0].name.local_name, "package");
+        assert_eq!(elements[0].attributes()[0].value, "com.example");
+
+        assert_eq!(elements[1].name(), "application");
+        assert_eq!(elements[1].attributes()[0].name.local_name, "debuggable");
+        assert_eq!(elements[1].attributes()[0].value, "true");
+        assert_eq!(
+            elements[1].to_string(),
+            "<application android:debuggable=\"true\">"
+        );
+    }
+}
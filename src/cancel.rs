@@ -0,0 +1,97 @@
+//! Cancellation module.
+//!
+//! Handles `SIGINT` and `SIGTERM` (or Ctrl+C on Windows) during an analysis. The first signal
+//! cancels the analysis: the external tools that are running are killed, the code analysis stops,
+//! the partial results are written with a `cancelled` marker and the decompiled files of an
+//! incomplete decompilation are removed, so that the next run does not reuse them. A second
+//! signal exits immediately.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use failure::{Error, ResultExt};
+
+use crate::{
+    error::{self, ExitCode},
+    print_warning,
+};
+
+/// Whether the analysis has been cancelled.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the handler of the termination signals, that cancels the analysis.
+pub fn install_handler() -> Result<(), Error> {
+    ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            process::exit(ExitCode::Cancelled.code());
+        }
+        print_warning(
+            "cancelling the analysis, the partial results will be written. Send the signal again \
+             to exit immediately",
+        );
+    })
+    .context("the signal handler could not be installed")?;
+    Ok(())
+}
+
+/// Returns true if the analysis has been cancelled.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Returns a `Cancelled` error if the analysis has been cancelled.
+pub(crate) fn check() -> Result<(), Error> {
+    if is_cancelled() {
+        Err(error::Kind::Cancelled.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Folder whose contents are only valid once it's complete, such as the decompiled files of an
+/// application.
+///
+/// If the analysis is cancelled before the folder is marked as complete, it is removed when the
+/// guard is dropped.
+#[derive(Debug)]
+pub(crate) struct PartialFolder {
+    /// Path of the folder, until it's complete.
+    path: Option<PathBuf>,
+}
+
+impl PartialFolder {
+    /// Creates a guard for the given folder.
+    pub(crate) fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Marks the folder as complete, so that it is kept.
+    pub(crate) fn complete(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for PartialFolder {
+    fn drop(&mut self) {
+        if !is_cancelled() {
+            return;
+        }
+        if let Some(path) = self.path.take() {
+            if path.exists() {
+                if let Err(e) = fs::remove_dir_all(&path) {
+                    print_warning(format!(
+                        "the incomplete folder {} could not be removed: {}",
+                        path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+    }
+}
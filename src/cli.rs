@@ -4,17 +4,21 @@
 //! for the SUPER launcher. It's also used to generate command line completion scripts in the
 //! `build.rs` file.
 
-use clap::{crate_version, App, Arg};
+use clap::{crate_version, App, AppSettings, Arg, SubCommand};
 
 /// Generates the command line interface.
 pub fn generate() -> App<'static, 'static> {
     App::new("SUPER Android Analyzer")
         .version(crate_version!())
         .author("SUPER Team <contact@superanalyzer.rocks>")
-        .about("Audits Android apps (.apk files) for vulnerabilities")
+        .about("Audits Android apps (.apk files) and bare .dex or .jar files for vulnerabilities")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("package")
-                .help("The package string of the application to test")
+                .help(
+                    "The package string of the application to test, or the name of a .dex or \
+                     .jar file in the downloads directory",
+                )
                 .value_name("package")
                 .required_unless("test-all")
                 .conflicts_with("test-all")
@@ -52,6 +56,12 @@ pub fn generate() -> App<'static, 'static> {
                 .conflicts_with("verbose")
                 .help("If you'd like a zen auditor that won't output anything in stdout"),
         )
+        .arg(
+            Arg::with_name("summary-live")
+                .long("summary-live")
+                .conflicts_with("verbose")
+                .help("Keeps a status line with the progress of the analysis, even in quiet mode"),
+        )
         .arg(
             Arg::with_name("open")
                 .long("open")
@@ -68,12 +78,139 @@ pub fn generate() -> App<'static, 'static> {
                 .long("html")
                 .help("Generates the results in HTML format"),
         )
+        .arg(
+            Arg::with_name("vscode")
+                .long("vscode")
+                .help("Prints the results in a format consumable by VS Code problem matchers"),
+        )
+        .arg(
+            Arg::with_name("markdown")
+                .long("markdown")
+                .help("Generates the results in Markdown format"),
+        )
+        .arg(
+            Arg::with_name("junit")
+                .long("junit")
+                .help("Generates the results in JUnit XML format, for CI systems"),
+        )
+        .arg(
+            Arg::with_name("sbom")
+                .long("sbom")
+                .help("Generates a CycloneDX software bill of materials of the application"),
+        )
+        .arg(
+            Arg::with_name("single-file-html")
+                .long("single-file-html")
+                .help("Generates the HTML report as a single self-contained file"),
+        )
+        .arg(
+            Arg::with_name("screenshots")
+                .long("screenshots")
+                .help("Embeds screenshots of the HTML assets of the findings in the HTML report"),
+        )
         .arg(
             Arg::with_name("min_criticality")
                 .long("min-criticality")
                 .help("Set a minimum criticality to analyze (Critical, High, Medium, Low)")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .help(
+                    "Configuration file to load, instead of the config.toml file of the working \
+                     directory or /etc/super-analyzer/config.toml",
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .help(
+                    "Analysis profile of a compliance framework (masvs-l1, masvs-l2, pci-mobile \
+                     or privacy), that selects the rules to check and sets the minimum \
+                     criticality and the policy of the framework, or a named profile of the \
+                     configuration file",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("enable")
+                .long("enable")
+                .help(
+                    "Runs only the given analysis checks, and the manifest analysis if they need \
+                     it. Run `super list-checks` to see the available checks",
+                )
+                .value_name("CHECK")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("disable")
+                .long("disable")
+                .help("Skips the given analysis checks, even if they are enabled")
+                .value_name("CHECK")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("data-safety")
+                .long("data-safety")
+                .help(
+                    "Data safety declaration of the application, exported from the Play \
+                     Console, to cross-check with its permissions",
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("suppressions")
+                .long("suppressions")
+                .help(
+                    "File with the rule IDs, files and fingerprints of the findings to \
+                     suppress, by default the .superignore file of the working directory",
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("audit-log")
+                .long("audit-log")
+                .help(
+                    "File where every action of the analyses is appended as a JSON line, for \
+                     the chain of custody of the results",
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("escalation-hook")
+                .long("escalation-hook")
+                .help(
+                    "Command notified of the permissions and exported components that no \
+                     previous version of the application had",
+                )
+                .value_name("COMMAND")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("print")
+                .long("print")
+                .help(
+                    "Prints only the selected facts of the results after the analysis, one per \
+                     line",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .number_of_values(1)
+                .possible_values(&["fingerprint", "package", "score", "counts"]),
+        )
         .arg(
             Arg::with_name("threads")
                 .short("t")
@@ -84,6 +221,26 @@ pub fn generate() -> App<'static, 'static> {
                 )
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("decompilation-shards")
+                .long("decompilation-shards")
+                .value_name("N")
+                .help(
+                    "Number of shards in which the classes are split to decompile them in \
+                     parallel, up to the maximum number of external processes",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-findings-in-memory")
+                .long("max-findings-in-memory")
+                .value_name("N")
+                .help(
+                    "Maximum number of findings kept in memory, the rest are spilled to a \
+                     temporary folder. 0 for no limit",
+                )
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("downloads")
                 .long("downloads")
@@ -102,18 +259,90 @@ pub fn generate() -> App<'static, 'static> {
                 .help("Folder where to store the results")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("tmp")
+                .long("tmp")
+                .value_name("FOLDER")
+                .help(
+                    "Folder where the temporary workspace of each analysis is created, by \
+                     default the temporary folder of the system",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .help(
+                    "Folder where to write the reports of the application, instead of a \
+                     folder named after it in the results folder",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("report-file-name")
+                .long("report-file-name")
+                .help("Name of the JSON and Markdown report files, without the extension")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("per-format-folders")
+                .long("per-format-folders")
+                .help("Writes each report format in its own subfolder"),
+        )
+        .arg(
+            Arg::with_name("no-library-cache")
+                .long("no-library-cache")
+                .help(
+                    "Analyzes the code of known SDKs again, even if the same version was \
+                     already analyzed",
+                ),
+        )
         .arg(
             Arg::with_name("dex2jar")
                 .long("dex2jar")
                 .help("Where to store the jar files")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("dex2jar-skip-exceptions")
+                .long("dex2jar-skip-exceptions")
+                .help("Skips the methods that Dex2jar can't translate instead of failing"),
+        )
         .arg(
             Arg::with_name("jd-cmd")
                 .long("jd-cmd")
                 .help("Path to the jd-cmd file")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("apktool")
+                .long("apktool")
+                .help("Path to the Apktool JAR file, used to decode the applications")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("apktool-framework")
+                .long("apktool-framework")
+                .help("Folder with the framework files used by Apktool")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("apktool-no-res")
+                .long("apktool-no-res")
+                .help("Does not decode the resources with Apktool"),
+        )
+        .arg(
+            Arg::with_name("apktool-force-manifest")
+                .long("apktool-force-manifest")
+                .help("Decodes the manifest with Apktool even if the resources are not decoded"),
+        )
+        .arg(
+            Arg::with_name("apktool-api")
+                .long("apktool-api")
+                .value_name("LEVEL")
+                .help("API level used by Apktool to decode the applications")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("template")
                 .long("template")
@@ -123,7 +352,216 @@ pub fn generate() -> App<'static, 'static> {
         .arg(
             Arg::with_name("rules")
                 .long("rules")
-                .help("Path to a JSON rules file")
+                .help(
+                    "Path to a JSON or YAML rules file. It can be repeated, and later files \
+                     override or disable the rules of the previous ones by ID",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("rules-dir")
+                .long("rules-dir")
+                .help(
+                    "Directory of JSON or YAML rules files, loaded in path order after the rules \
+                     files. It can be repeated, and a rule ID can only be defined once in each \
+                     directory",
+                )
+                .value_name("DIR")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("source-url")
+                .long("source-url")
+                .help(
+                    "Base URL or path where the decompiled sources are published, used to link \
+                     findings to their source files",
+                )
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("title")
+                .long("title")
+                .help("Title of the reports, for the engagement that the analysis is part of")
+                .value_name("TITLE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("client")
+                .long("client")
+                .help("Client of the engagement that the analysis is part of")
+                .value_name("CLIENT")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("engagement-id")
+                .long("engagement-id")
+                .help("Identifier of the engagement that the analysis is part of")
+                .value_name("ID")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("rpc")
+                .about("Serves JSON-RPC analysis requests over stdin and stdout (experimental)"),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Generates the reports of a previous analysis from its JSON results")
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .arg(
+                    Arg::with_name("results-file")
+                        .help(
+                            "Path to the results.json file of the analysis, more than one \
+                             can be given to merge them",
+                        )
+                        .value_name("results.json")
+                        .required(true)
+                        .multiple(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Generates the results in JSON format"),
+                )
+                .arg(
+                    Arg::with_name("html")
+                        .long("html")
+                        .help("Generates the results in HTML format"),
+                )
+                .arg(
+                    Arg::with_name("vscode")
+                        .long("vscode")
+                        .help("Prints the results for VS Code problem matchers"),
+                )
+                .arg(
+                    Arg::with_name("markdown")
+                        .long("markdown")
+                        .help("Generates the results in Markdown format"),
+                )
+                .arg(
+                    Arg::with_name("junit")
+                        .long("junit")
+                        .help("Generates the results in JUnit XML format, for CI systems"),
+                )
+                .arg(
+                    Arg::with_name("sbom")
+                        .long("sbom")
+                        .help("Generates a CycloneDX software bill of materials"),
+                )
+                .arg(
+                    Arg::with_name("single-file-html")
+                        .long("single-file-html")
+                        .help("Generates the HTML report as a single self-contained file"),
+                )
+                .arg(
+                    Arg::with_name("screenshots")
+                        .long("screenshots")
+                        .help("Embeds screenshots of the HTML assets of the findings"),
+                )
+                .subcommand(
+                    SubCommand::with_name("annotate")
+                        .about(
+                            "Attaches the notes of an analyst to the results of a previous \
+                             analysis, to be shown in its reports",
+                        )
+                        .arg(
+                            Arg::with_name("results-file")
+                                .help("Path to the results.json file of the analysis")
+                                .value_name("results.json")
+                                .required(true)
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("note")
+                                .long("note")
+                                .short("n")
+                                .help("Text of the notes")
+                                .value_name("TEXT")
+                                .takes_value(true)
+                                .conflicts_with("notes-file")
+                                .required_unless("notes-file"),
+                        )
+                        .arg(
+                            Arg::with_name("notes-file")
+                                .long("notes-file")
+                                .help("Path to a text or Markdown file with the notes")
+                                .value_name("FILE")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("append")
+                                .long("append")
+                                .help("Appends the notes to the current ones"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test-rules")
+                .about(
+                    "Tests the rules against the positive and negative code examples declared \
+                     in their `examples` attribute",
+                )
+                .arg(
+                    Arg::with_name("rules-file")
+                        .help(
+                            "Path to a JSON or YAML rules file to test, instead of the configured \
+                             ones. More than one can be given, as with the --rules option",
+                        )
+                        .value_name("rules.json")
+                        .multiple(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-checks")
+                .about("Lists the analysis checks that can be enabled or disabled by name"),
+        )
+        .subcommand(
+            SubCommand::with_name("template")
+                .about("Helps writing custom report templates")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("vars")
+                        .about(
+                            "Prints the variables available to the report templates, as \
+                             documented JSON",
+                        )
+                        .arg(
+                            Arg::with_name("results-file")
+                                .help(
+                                    "Path to the results.json file of an analysis to take the \
+                                     variables from, instead of a sample analysis",
+                                )
+                                .value_name("results.json")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compares the JSON results of two analyses of the same application")
+                .arg(
+                    Arg::with_name("old-results-file")
+                        .help("Path to the results.json file of the old analysis")
+                        .value_name("old-results.json")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("new-results-file")
+                        .help("Path to the results.json file of the new analysis")
+                        .value_name("new-results.json")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Prints the comparison in JSON format"),
+                ),
+        )
 }
@@ -4,9 +4,9 @@
 
 use std::{
     cmp::{Ordering, PartialOrd},
-    collections::{btree_set::Iter, BTreeSet},
+    collections::{btree_set::Iter, BTreeMap, BTreeSet},
     convert::From,
-    fs, i64,
+    env, fs, i64, iter,
     path::{Path, PathBuf},
     slice::Iter as VecIter,
     str::FromStr,
@@ -15,18 +15,30 @@ use std::{
 
 use clap::ArgMatches;
 use colored::Colorize;
-use failure::{format_err, Error, ResultExt};
+use failure::{bail, format_err, Error, ResultExt};
 use num_cpus;
 use serde::{de, Deserialize, Deserializer};
-use toml::{self, value::Value};
+use toml::{
+    self,
+    value::{Table, Value},
+};
 
-use crate::{criticality::Criticality, print_warning, static_analysis::manifest};
+use crate::{
+    criticality::Criticality,
+    print_warning,
+    profile::Profile,
+    results::{HashAlgorithm, Policy, PrintSelector, DEFAULT_SUPPRESSIONS_FILE},
+    static_analysis::{
+        artifacts::DebugArtifact, broadcasts::SensitiveBroadcast, category::Category, check::Check,
+        manifest, scanner::PackageScope,
+    },
+};
 
 /// Config structure.
 ///
 /// Contains configuration related fields. It is used for storing the configuration parameters and
 /// checking their values. Implements the `Default` trait.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct Config {
     /// Application packages to analyze.
@@ -35,6 +47,10 @@ pub struct Config {
     verbose: bool,
     /// Boolean to represent `--quiet` mode.
     quiet: bool,
+    /// Boolean to represent `--summary-live` mode.
+    summary_live: bool,
+    /// Facts of the results printed after the analysis.
+    print: Vec<PrintSelector>,
     /// Boolean to represent overall `--force` mode.
     overall_force: bool,
     /// Boolean to represent current `--force` mode.
@@ -47,36 +63,204 @@ pub struct Config {
     json: bool,
     /// Boolean to represent `--html` mode.
     html: bool,
+    /// Boolean to represent `--vscode` mode.
+    vscode: bool,
+    /// Boolean to represent `--markdown` mode.
+    markdown: bool,
+    /// Boolean to represent `--junit` mode.
+    junit: bool,
+    /// Boolean to represent `--sbom` mode.
+    sbom: bool,
+    /// Boolean to represent `--single-file-html` mode.
+    single_file_html: bool,
+    /// Boolean to represent `--screenshots` mode.
+    screenshots: bool,
+    /// Headless browser used to render the screenshots of the HTML assets.
+    screenshot_renderer: PathBuf,
     /// Minimum criticality to analyze
     min_criticality: Criticality,
     /// Number of threads.
     #[serde(deserialize_with = "ConfigDeserializer::deserialize_threads")]
     threads: usize,
+    /// Maximum number of external tools running at the same time.
+    #[serde(deserialize_with = "ConfigDeserializer::deserialize_max_external_processes")]
+    max_external_processes: usize,
+    /// Number of shards in which the classes are split to decompile them in parallel.
+    #[serde(deserialize_with = "ConfigDeserializer::deserialize_decompilation_shards")]
+    decompilation_shards: usize,
+    /// Maximum number of findings kept in memory, `0` for no limit.
+    max_findings_in_memory: usize,
     /// Folder where the applications are stored.
     downloads_folder: PathBuf,
     /// Folder with files from analyzed applications.
     dist_folder: PathBuf,
     /// Folder to store the results of analysis.
     results_folder: PathBuf,
+    /// Folder where the temporary workspaces of the analyses are created.
+    tmp_folder: PathBuf,
+    /// Temporary workspace of the current analysis, if any.
+    #[serde(skip)]
+    workspace: Option<PathBuf>,
+    /// Folder where the reports are written, instead of the application folder in the results
+    /// folder.
+    output_folder: Option<PathBuf>,
+    /// Name of the JSON and Markdown report files, without the extension.
+    report_file_name: String,
+    /// Whether each report format is written in its own subfolder.
+    per_format_folders: bool,
     /// Path to the _Dex2jar_ binaries.
     dex2jar_folder: PathBuf,
+    /// Whether _Dex2jar_ should overwrite the existing `.jar` files.
+    dex2jar_force: bool,
+    /// Whether _Dex2jar_ should skip the methods it can't translate instead of failing.
+    dex2jar_skip_exceptions: bool,
     /// Path to the _JD\_CMD_ binary.
     jd_cmd_file: PathBuf,
-    /// Path to the `rules.json` file.
-    rules_json: PathBuf,
+    /// Path to the _Apktool_ JAR file, used to decode the applications if set.
+    apktool_file: Option<PathBuf>,
+    /// Folder with the framework files used by _Apktool_.
+    apktool_framework_folder: Option<PathBuf>,
+    /// Whether _Apktool_ should leave the resources undecoded.
+    apktool_no_res: bool,
+    /// Whether _Apktool_ should decode the manifest even if the resources are not decoded.
+    apktool_force_manifest: bool,
+    /// API level used by _Apktool_ to decode the application.
+    apktool_api_level: Option<u32>,
+    /// Command used to wrap the external tools, such as _Firejail_.
+    tool_wrapper: Vec<String>,
+    /// Environment variables that external tools inherit.
+    tool_environment: Vec<String>,
+    /// Paths to the rules files, loaded in order.
+    #[serde(deserialize_with = "ConfigDeserializer::deserialize_rules_json")]
+    rules_json: Vec<PathBuf>,
     /// The folder where the templates are stored.
     templates_folder: PathBuf,
     /// The name of the template to use.
     template: String,
+    /// Base URL or path where the decompiled sources are published, used to link findings.
+    source_url: Option<String>,
+    /// Title of the reports, for the engagement that the analysis is part of.
+    report_title: Option<String>,
+    /// Client of the engagement that the analysis is part of.
+    client: Option<String>,
+    /// Identifier of the engagement that the analysis is part of.
+    engagement_id: Option<String>,
+    /// Hash algorithms used to fingerprint the applications.
+    fingerprints: BTreeSet<HashAlgorithm>,
+    /// Kinds of debug artifacts to search for.
+    debug_artifacts: BTreeSet<DebugArtifact>,
+    /// Words of the identifiers of sensitive data, that must not be logged.
+    sensitive_identifiers: Vec<String>,
+    /// Criticality of the missing screenshot, tapjacking and clipboard protections.
+    ui_protection_criticality: Criticality,
+    /// Whether findings in the code of known SDKs are lowered one criticality level.
+    downrank_sdk_findings: bool,
+    /// Whether the rule matches in the code of known SDKs are cached and reused across analyses.
+    library_cache: bool,
+    /// Criticality levels added to the rules with each tag, by application category.
+    category_weights: BTreeMap<String, BTreeMap<String, i8>>,
+    /// Points subtracted from the security score for each vulnerability, by criticality.
+    score_weights: BTreeMap<Criticality, u32>,
+    /// Policies evaluated over the results of each analysis.
+    policies: Vec<Policy>,
+    /// Minimum target SDK version allowed by the organization, if any.
+    min_allowed_target_sdk: Option<u32>,
+    /// Minimum `minSdkVersion` allowed by the organization, if any.
+    min_allowed_min_sdk: Option<u32>,
+    /// Criticality of the findings of the applications below the allowed SDK versions.
+    sdk_policy_criticality: Criticality,
+    /// Analysis profile, that selects the rules and thresholds of a compliance framework.
+    profile: Option<Profile>,
+    /// Named profiles, with the settings that they override when they are selected.
+    profiles: BTreeMap<String, Table>,
+    /// Checks to run, or every check if empty.
+    enabled_checks: BTreeSet<Check>,
+    /// Checks to skip, even if they are enabled.
+    disabled_checks: BTreeSet<Check>,
+    /// Java packages where each code check searches for issues.
+    check_scopes: BTreeMap<Check, PackageScope>,
+    /// Data safety declaration of the application, exported from the Play Console.
+    data_safety: Option<PathBuf>,
+    /// Command notified of the permissions and exported components that are new in a version.
+    escalation_hook: Option<PathBuf>,
+    /// File with the findings to suppress.
+    suppressions_file: Option<PathBuf>,
+    /// File where the actions of the analyses are appended, as JSON lines.
+    audit_log: Option<PathBuf>,
     /// Represents an unknown permission.
     #[serde(deserialize_with = "ConfigDeserializer::deserialize_unknown_permission")]
     unknown_permission: (Criticality, String),
     /// List of permissions to analyze.
     permissions: BTreeSet<Permission>,
+    /// List of sensitive broadcasts to analyze.
+    broadcasts: Vec<SensitiveBroadcast>,
     /// Checker for the loaded files
     loaded_files: Vec<PathBuf>,
 }
 
+/// Prefix of the environment variables that override the settings of the configuration file.
+pub const ENV_PREFIX: &str = "SUPER_";
+
+/// Gets the settings overridden by the `SUPER_*` variables of the given environment, by name.
+fn env_overrides<I>(environment: I) -> Table
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    environment
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.len() > ENV_PREFIX.len())
+        .map(|(name, value)| {
+            let value = format!("value = {}", value)
+                .parse::<Value>()
+                .ok()
+                .and_then(|table| table.get("value").cloned())
+                .unwrap_or_else(|| Value::String(value));
+            (name[ENV_PREFIX.len()..].to_lowercase(), value)
+        })
+        .collect()
+}
+
+/// Moves the named profiles declared as `[profile.<name>]` tables to the `profiles` table.
+///
+/// The `profile` key is a string when it selects a compliance profile, and a table when it
+/// declares named profiles. The `[profiles.<name>]` tables take precedence if a profile is
+/// declared in both.
+fn merge_named_profiles(table: &mut Table) {
+    let named = match table.remove("profile") {
+        Some(Value::Table(named)) => named,
+        Some(profile) => {
+            let _ = table.insert("profile".to_owned(), profile);
+            return;
+        }
+        None => return,
+    };
+    if let Value::Table(profiles) = table
+        .entry("profiles".to_owned())
+        .or_insert_with(|| Value::Table(Table::new()))
+    {
+        for (name, settings) in named {
+            let _ = profiles.entry(name).or_insert(settings);
+        }
+    }
+}
+
+/// Parses the given check names, failing if any of them doesn't exist.
+fn parse_checks<'n, I>(names: I) -> Result<BTreeSet<Check>, Error>
+where
+    I: IntoIterator<Item = &'n str>,
+{
+    names
+        .into_iter()
+        .map(|name| match Check::from_name(name) {
+            Some(check) => Ok(check),
+            None => bail!(
+                "the {} check does not exist, run `super list-checks` to see the available checks",
+                name
+            ),
+        })
+        .collect()
+}
+
 /// Helper struct that handles some specific field deserialization for `Config` struct
 struct ConfigDeserializer;
 
@@ -116,6 +300,63 @@ impl ConfigDeserializer {
         }
     }
 
+    /// Deserialize `max_external_processes` field and checks that at least one is allowed.
+    pub fn deserialize_max_external_processes<'de, D>(de: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let processes = usize::deserialize(de)?;
+        if processes > 0 {
+            Ok(processes)
+        } else {
+            Err(de::Error::custom(
+                "max_external_processes must allow at least one process",
+            ))
+        }
+    }
+
+    /// Deserialize `decompilation_shards` field and checks that there is at least one shard.
+    pub fn deserialize_decompilation_shards<'de, D>(de: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let shards = usize::deserialize(de)?;
+        if shards > 0 {
+            Ok(shards)
+        } else {
+            Err(de::Error::custom(
+                "decompilation_shards must have at least one shard",
+            ))
+        }
+    }
+
+    /// Deserialize `rules_json` field, either a single rules file or a list of them.
+    pub fn deserialize_rules_json<'de, D>(de: D) -> Result<Vec<PathBuf>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deserialize_result: Value = Deserialize::deserialize(de)?;
+
+        #[allow(clippy::use_debug)]
+        match deserialize_result {
+            Value::String(path) => Ok(vec![PathBuf::from(path)]),
+            Value::Array(paths) => paths
+                .into_iter()
+                .map(|path| match path {
+                    Value::String(path) => Ok(PathBuf::from(path)),
+                    _ => Err(de::Error::custom(format!(
+                        "rules files must be paths, found: {:?}",
+                        path
+                    ))),
+                })
+                .collect(),
+            _ => Err(de::Error::custom(format!(
+                "unexpected value: {:?}",
+                deserialize_result
+            ))),
+        }
+    }
+
     /// Deserialize `unknown_permission` field
     pub fn deserialize_unknown_permission<'de, D>(de: D) -> Result<CriticalityString, D::Error>
     where
@@ -159,54 +400,187 @@ impl ConfigDeserializer {
 impl Config {
     /// Creates a new `Config` struct.
     pub fn from_file<P: AsRef<Path>>(config_path: P) -> Result<Self, Error> {
-        let cfg_result: Result<Self, Error> = fs::read_to_string(config_path.as_ref())
-            .context("could not open configuration file")
-            .map_err(Error::from)
-            .and_then(|file_content| {
-                Ok(toml::from_str(&file_content).context(format_err!(
+        Self::from_sources(Some(config_path), None, iter::empty())
+    }
+
+    /// Creates a new `Config` struct from the given configuration file, if any, with its settings
+    /// overridden by the given named profile of the file and by the `SUPER_*` variables of the
+    /// given environment.
+    ///
+    /// The named profiles are the `[profile.<name>]` tables of the file, with any of its
+    /// settings. They can also be declared as `[profiles.<name>]` tables, so that the `profile`
+    /// key can select a compliance profile. Profiles that are not in the file are not applied
+    /// here, since the name can be a compliance profile, but loading the CLI options fails if the
+    /// profile is neither.
+    ///
+    /// Each variable overrides the setting named like it, without the prefix and in lowercase,
+    /// such as `SUPER_DIST_FOLDER` for `dist_folder`. The values are parsed as TOML values, such
+    /// as `4` or `["rules.json", "extra.json"]`, and are taken as strings if they are not valid
+    /// TOML values.
+    pub fn from_sources<P, I>(
+        config_path: Option<P>,
+        profile: Option<&str>,
+        environment: I,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut table = match config_path {
+            Some(ref config_path) => {
+                let file_content = fs::read_to_string(config_path.as_ref())
+                    .context("could not open configuration file")?;
+                match toml::from_str::<Value>(&file_content).context(format_err!(
                     "could not decode config file: {}, using default",
                     config_path.as_ref().to_string_lossy()
-                ))?)
-            })
-            .and_then(|mut new_config: Self| {
-                new_config
-                    .loaded_files
-                    .push(config_path.as_ref().to_path_buf());
+                ))? {
+                    Value::Table(table) => table,
+                    _ => Table::new(),
+                }
+            }
+            None => Table::new(),
+        };
+        merge_named_profiles(&mut table);
+        let profile_settings = profile.and_then(|profile| {
+            match table
+                .get("profiles")
+                .and_then(|profiles| profiles.get(profile))
+            {
+                Some(Value::Table(settings)) => Some(settings.clone()),
+                _ => None,
+            }
+        });
+        if let Some(settings) = profile_settings {
+            table.extend(settings);
+        }
+        let overrides = env_overrides(environment);
+        let mut context = match config_path {
+            Some(ref config_path) => format!(
+                "could not decode config file: {}",
+                config_path.as_ref().to_string_lossy()
+            ),
+            None => "could not decode the configuration".to_owned(),
+        };
+        if !overrides.is_empty() {
+            let variables = overrides
+                .keys()
+                .map(|name| format!("{}{}", ENV_PREFIX, name.to_uppercase()))
+                .collect::<Vec<_>>();
+            context.push_str(&format!(", overridden by {}", variables.join(", ")));
+        }
+        table.extend(overrides);
 
-                Ok(new_config)
-            });
+        let mut config: Self = Value::Table(table).try_into().context(context)?;
+        if let Some(config_path) = config_path {
+            config.loaded_files.push(config_path.as_ref().to_path_buf());
+        }
 
-        cfg_result
+        Ok(config)
     }
 
     /// Decorates the loaded config with the given flags from CLI
     pub fn decorate_with_cli(&mut self, cli: &ArgMatches<'static>) -> Result<(), Error> {
-        self.set_options(cli);
+        self.set_options(cli)?;
 
         self.verbose = cli.is_present("verbose");
         self.quiet = cli.is_present("quiet");
+        self.summary_live = cli.is_present("summary-live");
+        if let Some(selectors) = cli.values_of("print") {
+            self.print = selectors.filter_map(PrintSelector::from_name).collect();
+        }
+        // The selected facts must be the only output, so that they can be used in pipelines.
+        self.quiet |= !self.print.is_empty();
         self.overall_force = cli.is_present("force");
         self.force = self.overall_force;
         self.bench = cli.is_present("bench");
         self.open = cli.is_present("open");
-        self.json = cli.is_present("json");
-        self.html = cli.is_present("html");
+        self.json |= cli.is_present("json");
+        self.html |= cli.is_present("html");
+        self.vscode |= cli.is_present("vscode");
+        self.markdown |= cli.is_present("markdown");
+        self.junit |= cli.is_present("junit");
+        self.sbom |= cli.is_present("sbom");
+        self.single_file_html |= cli.is_present("single-file-html");
+        self.screenshots |= cli.is_present("screenshots");
+        if let Some(report) = cli.subcommand_matches("report") {
+            self.json |= report.is_present("json");
+            self.html |= report.is_present("html");
+            self.vscode |= report.is_present("vscode");
+            self.markdown |= report.is_present("markdown");
+            self.junit |= report.is_present("junit");
+            self.sbom |= report.is_present("sbom");
+            self.single_file_html |= report.is_present("single-file-html");
+            self.screenshots |= report.is_present("screenshots");
+        }
+
+        if let Some(test_rules) = cli.subcommand_matches("test-rules") {
+            if let Some(rules_files) = test_rules.values_of("rules-file") {
+                self.rules_json = rules_files.map(PathBuf::from).collect();
+            }
+        }
 
         if cli.is_present("test-all") {
             self.read_apks()
                 .context("error loading all the downloaded APKs")?;
-        } else {
-            self.add_app_package(
-                cli.value_of("package")
-                    .expect("expected a value for the package CLI attribute"),
-            );
+        } else if let Some(package) = cli.value_of("package") {
+            self.add_app_package(package);
         }
 
         Ok(())
     }
 
     /// Modifies the options from the CLI.
-    fn set_options(&mut self, cli: &ArgMatches<'static>) {
+    ///
+    /// It fails if the selected profile is neither a compliance profile nor a named profile of
+    /// the configuration file, since the analysis would run without the policy of the profile,
+    /// and if an enabled or disabled check doesn't exist.
+    fn set_options(&mut self, cli: &ArgMatches<'static>) -> Result<(), Error> {
+        if let Some(profile) = cli.value_of("profile") {
+            match Profile::from_name(profile) {
+                Some(profile) => self.profile = Some(profile),
+                // The settings of the named profiles are applied when the file is loaded.
+                None if self.profiles.contains_key(profile) => {}
+                None => bail!(
+                    "the {} profile does not exist, it must be one of {} or a named profile of \
+                     the configuration file",
+                    profile,
+                    Profile::all()
+                        .iter()
+                        .map(|profile| profile.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+        // The thresholds of the profile can still be changed in the command line.
+        if let Some(profile) = self.profile {
+            self.min_criticality = profile.min_criticality();
+            if self
+                .policies
+                .iter()
+                .all(|policy| policy.name() != profile.name())
+            {
+                self.policies.push(profile.policy());
+            }
+        }
+        if let Some(checks) = cli.values_of("enable") {
+            self.enabled_checks = parse_checks(checks)?;
+        }
+        if let Some(checks) = cli.values_of("disable") {
+            self.disabled_checks = parse_checks(checks)?;
+        }
+        if self.disabled_checks.contains(&Check::Manifest) {
+            for check in self
+                .enabled_checks
+                .iter()
+                .filter(|check| check.needs_manifest())
+            {
+                print_warning(format!(
+                    "the {} check needs the manifest check, that is disabled, so it will not run",
+                    check.name()
+                ));
+            }
+        }
         if let Some(min_criticality) = cli.value_of("min_criticality") {
             if let Ok(m) = min_criticality.parse() {
                 self.min_criticality = m;
@@ -235,6 +609,24 @@ impl Config {
                 }
             }
         }
+        if let Some(shards) = cli.value_of("decompilation-shards") {
+            match shards.parse() {
+                Ok(shards) if shards > 0 => self.decompilation_shards = shards,
+                _ => print_warning(format!(
+                    "The decompilation shards option must be an integer between 1 and {}",
+                    usize::max_value()
+                )),
+            }
+        }
+        if let Some(max_findings) = cli.value_of("max-findings-in-memory") {
+            match max_findings.parse() {
+                Ok(max_findings) => self.max_findings_in_memory = max_findings,
+                Err(_) => print_warning(
+                    "the maximum number of findings in memory must be a positive integer, or 0 for \
+                     no limit",
+                ),
+            }
+        }
         if let Some(downloads_folder) = cli.value_of("downloads") {
             self.downloads_folder = PathBuf::from(downloads_folder);
         }
@@ -244,18 +636,75 @@ impl Config {
         if let Some(results_folder) = cli.value_of("results") {
             self.results_folder = PathBuf::from(results_folder);
         }
+        if let Some(tmp_folder) = cli.value_of("tmp") {
+            self.tmp_folder = PathBuf::from(tmp_folder);
+        }
+        if let Some(output_folder) = cli.value_of("output") {
+            self.output_folder = Some(PathBuf::from(output_folder));
+        }
+        if let Some(report_file_name) = cli.value_of("report-file-name") {
+            self.report_file_name = report_file_name.to_owned();
+        }
+        self.per_format_folders |= cli.is_present("per-format-folders");
+        if cli.is_present("no-library-cache") {
+            self.library_cache = false;
+        }
         if let Some(dex2jar_folder) = cli.value_of("dex2jar") {
             self.dex2jar_folder = PathBuf::from(dex2jar_folder);
         }
+        self.dex2jar_skip_exceptions |= cli.is_present("dex2jar-skip-exceptions");
         if let Some(jd_cmd_file) = cli.value_of("jd-cmd") {
             self.jd_cmd_file = PathBuf::from(jd_cmd_file);
         }
+        if let Some(data_safety) = cli.value_of("data-safety") {
+            self.data_safety = Some(PathBuf::from(data_safety));
+        }
+        if let Some(escalation_hook) = cli.value_of("escalation-hook") {
+            self.escalation_hook = Some(PathBuf::from(escalation_hook));
+        }
+        if let Some(suppressions_file) = cli.value_of("suppressions") {
+            self.suppressions_file = Some(PathBuf::from(suppressions_file));
+        }
+        if let Some(audit_log) = cli.value_of("audit-log") {
+            self.audit_log = Some(PathBuf::from(audit_log));
+        }
+        if let Some(apktool_file) = cli.value_of("apktool") {
+            self.apktool_file = Some(PathBuf::from(apktool_file));
+        }
+        if let Some(framework_folder) = cli.value_of("apktool-framework") {
+            self.apktool_framework_folder = Some(PathBuf::from(framework_folder));
+        }
+        self.apktool_no_res |= cli.is_present("apktool-no-res");
+        self.apktool_force_manifest |= cli.is_present("apktool-force-manifest");
+        if let Some(api_level) = cli.value_of("apktool-api") {
+            match api_level.parse() {
+                Ok(api_level) => self.apktool_api_level = Some(api_level),
+                Err(_) => print_warning("the apktool API level must be a positive integer"),
+            }
+        }
         if let Some(template_name) = cli.value_of("template") {
             self.template = template_name.to_owned();
         }
-        if let Some(rules_json) = cli.value_of("rules") {
-            self.rules_json = PathBuf::from(rules_json);
+        if let Some(rules_json) = cli.values_of("rules") {
+            self.rules_json = rules_json.map(PathBuf::from).collect();
+        }
+        if let Some(rules_dirs) = cli.values_of("rules-dir") {
+            self.rules_json.extend(rules_dirs.map(PathBuf::from));
+        }
+        if let Some(source_url) = cli.value_of("source-url") {
+            self.source_url = Some(source_url.to_owned());
+        }
+        if let Some(report_title) = cli.value_of("title") {
+            self.report_title = Some(report_title.to_owned());
+        }
+        if let Some(client) = cli.value_of("client") {
+            self.client = Some(client.to_owned());
+        }
+        if let Some(engagement_id) = cli.value_of("engagement-id") {
+            self.engagement_id = Some(engagement_id.to_owned());
         }
+
+        Ok(())
     }
 
     /// Reads all the apk files in the downloads folder and adds them to the configuration.
@@ -296,7 +745,8 @@ impl Config {
             && self.dex2jar_folder.exists()
             && self.jd_cmd_file.exists()
             && self.template_path().exists()
-            && self.rules_json.exists();
+            && self.rules_json.iter().all(|rules| rules.exists())
+            && (self.output_folder.is_none() || self.app_packages.len() <= 1);
         if check {
             for package in &self.app_packages {
                 if !package.exists() {
@@ -309,6 +759,12 @@ impl Config {
         }
     }
 
+    /// Returns true if the external tools needed for the decompilation, Dex2Jar and jd-cmd, are
+    /// where the configuration says.
+    pub fn has_tools(&self) -> bool {
+        self.dex2jar_folder.exists() && self.jd_cmd_file.exists()
+    }
+
     /// Returns the folders and files that do not exist.
     pub fn errors(&self) -> Vec<String> {
         let mut errors = Vec::new();
@@ -351,10 +807,17 @@ impl Config {
                 self.templates_folder.display()
             ));
         }
-        if !self.rules_json.exists() {
-            errors.push(format!(
-                "The `{}` rule file does not exist",
-                self.rules_json.display()
+        for rules in &self.rules_json {
+            if !rules.exists() {
+                errors.push(format!(
+                    "The `{}` rule file does not exist",
+                    rules.display()
+                ));
+            }
+        }
+        if self.output_folder.is_some() && self.app_packages.len() > 1 {
+            errors.push(String::from(
+                "The output folder can only be set when analyzing a single application",
             ));
         }
         errors
@@ -379,11 +842,12 @@ impl Config {
                 updated,
                 "did not update package path extension, no file name"
             );
-        } else if package_path
-            .extension()
-            .expect("expected extension in package path")
-            != "apk"
-        {
+        } else if !["apk", "dex", "jar"].iter().any(|extension| {
+            package_path
+                .extension()
+                .expect("expected extension in package path")
+                == *extension
+        }) {
             let mut file_name = package_path
                 .file_name()
                 .expect("expected file name in package path")
@@ -401,11 +865,24 @@ impl Config {
         self.verbose
     }
 
+    /// Returns the facts of the results that have to be printed after the analysis.
+    pub fn print_selectors(&self) -> &[PrintSelector] {
+        &self.print
+    }
+
     /// Returns true if the application is running in `--quiet` mode, false otherwise.
     pub fn is_quiet(&self) -> bool {
         self.quiet
     }
 
+    /// Returns true if the application is running in `--summary-live` mode, false otherwise.
+    ///
+    /// In this mode a status line with the progress of the analysis is kept updated, even in
+    /// `--quiet` mode.
+    pub fn is_summary_live(&self) -> bool {
+        self.summary_live
+    }
+
     /// Returns true if the application is running in `--force` mode, false otherwise.
     pub fn is_force(&self) -> bool {
         self.force
@@ -437,8 +914,48 @@ impl Config {
     }
 
     /// Returns true if the application has to generate result in HTML format.
+    ///
+    /// The HTML report is generated by default, unless other report formats are selected.
     pub fn has_to_generate_html(&self) -> bool {
-        !self.json || self.html
+        !(self.json || self.markdown || self.junit || self.sbom) || self.html
+    }
+
+    /// Returns true if the application has to generate result in Markdown format.
+    pub fn has_to_generate_markdown(&self) -> bool {
+        self.markdown
+    }
+
+    /// Returns true if the application has to generate result in JUnit XML format.
+    pub fn has_to_generate_junit(&self) -> bool {
+        self.junit
+    }
+
+    /// Returns true if the application has to generate a CycloneDX software bill of materials.
+    pub fn has_to_generate_sbom(&self) -> bool {
+        self.sbom
+    }
+
+    /// Returns true if the HTML report has to be generated as a single self-contained file, with
+    /// the template assets inlined and without the source code tree.
+    pub fn is_single_file_html(&self) -> bool {
+        self.single_file_html
+    }
+
+    /// Returns true if the HTML report has to include the screenshots of the HTML assets of the
+    /// findings.
+    pub fn has_to_take_screenshots(&self) -> bool {
+        self.screenshots
+    }
+
+    /// Returns the headless browser used to render the screenshots of the HTML assets.
+    pub fn screenshot_renderer(&self) -> &Path {
+        &self.screenshot_renderer
+    }
+
+    /// Returns true if the application has to print the results in the VS Code problem matcher
+    /// format.
+    pub fn has_to_generate_vscode(&self) -> bool {
+        self.vscode
     }
 
     /// Returns the `min_criticality` field.
@@ -451,6 +968,30 @@ impl Config {
         self.threads
     }
 
+    /// Returns the maximum number of external tools that can run at the same time.
+    ///
+    /// External tools are memory hungry, so this is independent from the number of threads.
+    pub fn max_external_processes(&self) -> usize {
+        self.max_external_processes
+    }
+
+    /// Returns the number of shards in which the classes are split to decompile them in
+    /// parallel.
+    ///
+    /// Each shard is decompiled by its own external process, so the shards only run in parallel
+    /// up to the maximum number of external processes.
+    pub fn decompilation_shards(&self) -> usize {
+        self.decompilation_shards
+    }
+
+    /// Returns the maximum number of findings kept in memory, if any.
+    ///
+    /// The findings above the limit are spilled to disk, and read back when generating the
+    /// reports.
+    pub fn max_findings_in_memory(&self) -> Option<usize> {
+        Some(self.max_findings_in_memory).filter(|max| *max > 0)
+    }
+
     /// Returns the path to the `dist_folder`.
     pub fn dist_folder(&self) -> &Path {
         &self.dist_folder
@@ -461,16 +1002,115 @@ impl Config {
         &self.results_folder
     }
 
+    /// Returns the folder where the temporary workspaces of the analyses are created.
+    pub fn tmp_folder(&self) -> &Path {
+        &self.tmp_folder
+    }
+
+    /// Returns the temporary workspace of the current analysis, where the external tools write
+    /// their scratch files, or the temporary folder if no analysis is running.
+    pub fn workspace_folder(&self) -> &Path {
+        self.workspace.as_ref().unwrap_or(&self.tmp_folder)
+    }
+
+    /// Sets the temporary workspace of the current analysis.
+    pub fn set_workspace<P: Into<PathBuf>>(&mut self, workspace: P) {
+        self.workspace = Some(workspace.into());
+    }
+
+    /// Resets the temporary workspace, once the current analysis ends.
+    pub fn reset_workspace(&mut self) {
+        self.workspace = None;
+    }
+
+    /// Returns the folder where the reports of the given application package are written.
+    ///
+    /// It's the output folder, if set, or the folder named after the package in the results
+    /// folder otherwise.
+    pub fn report_folder<S: AsRef<str>>(&self, package: S) -> PathBuf {
+        match self.output_folder {
+            Some(ref output_folder) => output_folder.clone(),
+            None => self.results_folder.join(package.as_ref()),
+        }
+    }
+
+    /// Returns the folder where the reports in the given format are written, inside the given
+    /// report folder.
+    ///
+    /// The format is the name of the subfolder if each format is written in its own subfolder,
+    /// such as `json` or `html`, and reports are written directly in the report folder otherwise.
+    pub fn report_format_folder<P: AsRef<Path>>(&self, report_folder: P, format: &str) -> PathBuf {
+        if self.per_format_folders {
+            report_folder.as_ref().join(format)
+        } else {
+            report_folder.as_ref().to_path_buf()
+        }
+    }
+
+    /// Returns the name of the JSON and Markdown report files, without the extension.
+    pub fn report_file_name(&self) -> &str {
+        &self.report_file_name
+    }
+
     /// Returns the path to the `dex2jar_folder`.
     pub fn dex2jar_folder(&self) -> &Path {
         &self.dex2jar_folder
     }
 
+    /// Returns whether _Dex2jar_ should overwrite the existing `.jar` files.
+    pub fn is_dex2jar_force(&self) -> bool {
+        self.dex2jar_force
+    }
+
+    /// Returns whether _Dex2jar_ should skip the methods it can't translate.
+    pub fn is_dex2jar_skip_exceptions(&self) -> bool {
+        self.dex2jar_skip_exceptions
+    }
+
     /// Returns the path to the `jd_cmd_file`.
     pub fn jd_cmd_file(&self) -> &Path {
         &self.jd_cmd_file
     }
 
+    /// Returns the path to the `apktool_file`.
+    ///
+    /// If it's not set, the applications are decoded without _Apktool_.
+    pub fn apktool_file(&self) -> Option<&Path> {
+        self.apktool_file.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns the folder with the framework files used by _Apktool_, if set.
+    pub fn apktool_framework_folder(&self) -> Option<&Path> {
+        self.apktool_framework_folder.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns whether _Apktool_ should leave the resources undecoded.
+    pub fn is_apktool_no_res(&self) -> bool {
+        self.apktool_no_res
+    }
+
+    /// Returns whether _Apktool_ should always decode the manifest.
+    pub fn is_apktool_force_manifest(&self) -> bool {
+        self.apktool_force_manifest
+    }
+
+    /// Returns the API level used by _Apktool_ to decode the applications, if set.
+    pub fn apktool_api_level(&self) -> Option<u32> {
+        self.apktool_api_level
+    }
+
+    /// Returns the command, with its arguments, that wraps the execution of external tools.
+    ///
+    /// It will be empty if the tools have to be executed directly.
+    pub fn tool_wrapper(&self) -> &[String] {
+        &self.tool_wrapper
+    }
+
+    /// Returns the environment variables that external tools inherit.
+    pub fn tool_environment(&self) -> &[String] {
+        &self.tool_environment
+    }
+
     /// Gets the path to the template.
     pub fn template_path(&self) -> PathBuf {
         self.templates_folder.join(&self.template)
@@ -486,8 +1126,160 @@ impl Config {
         &self.template
     }
 
-    /// Returns the path to the `rules_json`.
-    pub fn rules_json(&self) -> &Path {
+    /// Gets the base URL or path where the decompiled sources of the applications are published.
+    ///
+    /// If it's not set, findings will link to the local distribution folder.
+    pub fn source_url(&self) -> Option<&str> {
+        self.source_url.as_ref().map(String::as_str)
+    }
+
+    /// Gets the title of the reports, if set.
+    pub fn report_title(&self) -> Option<&str> {
+        self.report_title.as_ref().map(String::as_str)
+    }
+
+    /// Gets the client of the engagement that the analysis is part of, if set.
+    pub fn client(&self) -> Option<&str> {
+        self.client.as_ref().map(String::as_str)
+    }
+
+    /// Gets the identifier of the engagement that the analysis is part of, if set.
+    pub fn engagement_id(&self) -> Option<&str> {
+        self.engagement_id.as_ref().map(String::as_str)
+    }
+
+    /// Returns the hash algorithms used to fingerprint the applications.
+    pub fn fingerprints(&self) -> &BTreeSet<HashAlgorithm> {
+        &self.fingerprints
+    }
+
+    /// Returns the kinds of debug artifacts to search for in the applications.
+    pub fn debug_artifacts(&self) -> &BTreeSet<DebugArtifact> {
+        &self.debug_artifacts
+    }
+
+    /// Returns the words of the identifiers of sensitive data, that must not be logged.
+    pub fn sensitive_identifiers(&self) -> &[String] {
+        &self.sensitive_identifiers
+    }
+
+    /// Returns true if the given check has to run.
+    ///
+    /// Checks run if no check is explicitly enabled or if they are enabled, unless they are
+    /// disabled. The manifest check also runs if any enabled check needs the manifest.
+    pub fn is_check_enabled(&self, check: Check) -> bool {
+        let enabled = self.enabled_checks.is_empty()
+            || self.enabled_checks.contains(&check)
+            || (check == Check::Manifest
+                && self
+                    .enabled_checks
+                    .iter()
+                    .any(|check| check.needs_manifest()));
+        enabled && !self.disabled_checks.contains(&check)
+    }
+
+    /// Returns the Java packages where the given check searches for issues, if it's scoped.
+    pub fn check_scope(&self, check: Check) -> Option<&PackageScope> {
+        self.check_scopes.get(&check)
+    }
+
+    /// Returns true if any of the checks that have to run reads the decompiled code.
+    pub fn is_code_needed(&self) -> bool {
+        Check::all()
+            .iter()
+            .any(|check| check.needs_code() && self.is_check_enabled(*check))
+    }
+
+    /// Returns the criticality of the missing screenshot, tapjacking and clipboard protections.
+    pub fn ui_protection_criticality(&self) -> Criticality {
+        self.ui_protection_criticality
+    }
+
+    /// Returns true if the findings in the code of known SDKs and libraries have to be lowered
+    /// one criticality level.
+    pub fn downrank_sdk_findings(&self) -> bool {
+        self.downrank_sdk_findings
+    }
+
+    /// Returns true if the rule matches in the code of known SDKs and libraries have to be cached
+    /// in the results folder, and reused in the applications that bundle the same SDK version.
+    pub fn library_cache(&self) -> bool {
+        self.library_cache
+    }
+
+    /// Returns the number of criticality levels that the rules with the given tag are raised for
+    /// applications of the given category.
+    ///
+    /// Negative weights lower the criticality of the rules.
+    pub fn category_weight(&self, category: Category, tag: &str) -> i8 {
+        self.category_weights
+            .get(category.name())
+            .and_then(|weights| weights.get(tag))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Returns the points subtracted from the security score for each vulnerability of the given
+    /// criticality.
+    pub fn score_weight(&self, criticality: Criticality) -> u32 {
+        self.score_weights.get(&criticality).cloned().unwrap_or(0)
+    }
+
+    /// Returns the policies evaluated over the results of each analysis.
+    pub fn policies(&self) -> &[Policy] {
+        &self.policies
+    }
+
+    /// Returns the minimum target SDK version allowed by the organization, if any.
+    pub fn min_allowed_target_sdk(&self) -> Option<u32> {
+        self.min_allowed_target_sdk
+    }
+
+    /// Returns the minimum `minSdkVersion` allowed by the organization, if any.
+    pub fn min_allowed_min_sdk(&self) -> Option<u32> {
+        self.min_allowed_min_sdk
+    }
+
+    /// Returns the criticality of the findings of the applications below the allowed SDK
+    /// versions.
+    pub fn sdk_policy_criticality(&self) -> Criticality {
+        self.sdk_policy_criticality
+    }
+
+    /// Returns the analysis profile, if any.
+    pub fn profile(&self) -> Option<Profile> {
+        self.profile
+    }
+
+    /// Returns the data safety declaration cross-checked with the permissions, if any.
+    pub fn data_safety(&self) -> Option<&Path> {
+        self.data_safety.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns the command notified of the permissions and exported components that are new in
+    /// an analyzed version, if any.
+    pub fn escalation_hook(&self) -> Option<&Path> {
+        self.escalation_hook.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns the path to the file with the findings to suppress, if any.
+    ///
+    /// If no file is configured, the `.superignore` file of the working directory is used, if it
+    /// exists.
+    pub fn suppressions_file(&self) -> Option<&Path> {
+        self.suppressions_file
+            .as_ref()
+            .map(PathBuf::as_path)
+            .or_else(|| Some(Path::new(DEFAULT_SUPPRESSIONS_FILE)).filter(|path| path.exists()))
+    }
+
+    /// Returns the path to the audit log, where the actions of the analyses are appended, if any.
+    pub fn audit_log(&self) -> Option<&Path> {
+        self.audit_log.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns the paths to the rules files, in the order they are loaded.
+    pub fn rules_json(&self) -> &[PathBuf] {
         &self.rules_json
     }
 
@@ -506,28 +1298,134 @@ impl Config {
         self.permissions.iter()
     }
 
+    /// Returns the loaded sensitive `broadcasts`.
+    pub fn broadcasts(&self) -> &[SensitiveBroadcast] {
+        &self.broadcasts
+    }
+
     /// Returns the default `Config` struct.
     fn local_default() -> Self {
         Self {
             app_packages: Vec::new(),
             verbose: false,
             quiet: false,
+            summary_live: false,
+            print: Vec::new(),
             overall_force: false,
             force: false,
             bench: false,
             open: false,
             json: false,
             html: false,
+            vscode: false,
+            markdown: false,
+            junit: false,
+            sbom: false,
+            single_file_html: false,
+            screenshots: false,
+            screenshot_renderer: PathBuf::from("chromium"),
             threads: num_cpus::get(),
+            max_external_processes: 1,
+            decompilation_shards: 1,
+            max_findings_in_memory: 100_000,
             min_criticality: Criticality::Warning,
             downloads_folder: PathBuf::from("."),
             dist_folder: PathBuf::from("dist"),
             results_folder: PathBuf::from("results"),
+            tmp_folder: env::temp_dir(),
+            workspace: None,
+            output_folder: None,
+            report_file_name: String::from("results"),
+            per_format_folders: false,
             dex2jar_folder: Path::new("vendor").join("dex2jar-2.1-SNAPSHOT"),
+            dex2jar_force: true,
+            dex2jar_skip_exceptions: false,
             jd_cmd_file: Path::new("vendor").join("jd-cmd.jar"),
+            apktool_file: None,
+            apktool_framework_folder: None,
+            apktool_no_res: false,
+            apktool_force_manifest: false,
+            apktool_api_level: None,
+            tool_wrapper: Vec::new(),
+            tool_environment: [
+                "PATH",
+                "JAVA_HOME",
+                "LANG",
+                "TMPDIR",
+                "TEMP",
+                "TMP",
+                "SYSTEMROOT",
+            ]
+            .iter()
+            .map(|var| (*var).to_owned())
+            .collect(),
             templates_folder: PathBuf::from("templates"),
             template: String::from("super"),
-            rules_json: PathBuf::from("rules.json"),
+            source_url: None,
+            report_title: None,
+            client: None,
+            engagement_id: None,
+            fingerprints: [
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Sha256,
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            debug_artifacts: DebugArtifact::all().iter().cloned().collect(),
+            sensitive_identifiers: [
+                "password",
+                "passwd",
+                "pwd",
+                "passphrase",
+                "token",
+                "secret",
+                "credential",
+                "apikey",
+                "privatekey",
+                "sessionid",
+                "cvv",
+            ]
+            .iter()
+            .map(|word| (*word).to_owned())
+            .collect(),
+            ui_protection_criticality: Criticality::Warning,
+            downrank_sdk_findings: false,
+            library_cache: true,
+            category_weights: {
+                // Banking applications get stricter cryptography and storage scoring.
+                let mut banking = BTreeMap::new();
+                let _ = banking.insert("crypto".to_owned(), 1);
+                let _ = banking.insert("storage".to_owned(), 1);
+                let mut category_weights = BTreeMap::new();
+                let _ = category_weights.insert(Category::Banking.name().to_owned(), banking);
+                category_weights
+            },
+            score_weights: [
+                (Criticality::Critical, 20),
+                (Criticality::High, 10),
+                (Criticality::Medium, 4),
+                (Criticality::Low, 1),
+                (Criticality::Warning, 0),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            policies: Vec::new(),
+            min_allowed_target_sdk: None,
+            min_allowed_min_sdk: None,
+            sdk_policy_criticality: Criticality::Medium,
+            profile: None,
+            profiles: BTreeMap::new(),
+            enabled_checks: BTreeSet::new(),
+            disabled_checks: BTreeSet::new(),
+            check_scopes: BTreeMap::new(),
+            data_safety: None,
+            escalation_hook: None,
+            suppressions_file: None,
+            audit_log: None,
+            rules_json: vec![PathBuf::from("rules.json")],
             unknown_permission: (
                 Criticality::Low,
                 String::from(
@@ -537,6 +1435,7 @@ impl Config {
                 ),
             ),
             permissions: BTreeSet::new(),
+            broadcasts: Vec::new(),
             loaded_files: Vec::new(),
         }
     }
@@ -549,7 +1448,7 @@ impl Default for Config {
         let mut config = Self::local_default();
         let etc_rules = PathBuf::from("/etc/super-analyzer/rules.json");
         if etc_rules.exists() {
-            config.rules_json = etc_rules;
+            config.rules_json = vec![etc_rules];
         }
         let share_path = Path::new(if cfg!(target_os = "macos") {
             "/usr/local/super-analyzer"
@@ -575,7 +1474,7 @@ impl Default for Config {
 ///
 /// Represents a Permission with all its fields. Implements the `PartialEq` and `PartialOrd`
 /// traits.
-#[derive(Debug, Ord, Eq, Deserialize)]
+#[derive(Debug, Clone, Ord, Eq, Deserialize)]
 pub struct Permission {
     /// Permission name.
     name: manifest::Permission,
@@ -625,14 +1524,21 @@ impl Permission {
 #[cfg(test)]
 mod tests {
     use std::{
-        fs,
+        env, fs, iter,
         path::{Path, PathBuf},
+        process,
     };
 
     use num_cpus;
 
     use super::Config;
-    use crate::{criticality::Criticality, static_analysis::manifest};
+    use crate::{
+        cli,
+        criticality::Criticality,
+        profile::Profile,
+        results::HashAlgorithm,
+        static_analysis::{artifacts::DebugArtifact, category::Category, check::Check, manifest},
+    };
 
     /// Test for the default configuration function.
     #[allow(clippy::cyclomatic_complexity)]
@@ -645,14 +1551,60 @@ mod tests {
         assert!(config.app_packages().is_empty());
         assert!(!config.is_verbose());
         assert!(!config.is_quiet());
+        assert!(!config.is_summary_live());
         assert!(!config.is_force());
         assert!(!config.is_bench());
         assert!(!config.is_open());
         assert_eq!(config.threads(), num_cpus::get());
+        assert_eq!(config.max_external_processes(), 1);
+        assert_eq!(config.decompilation_shards(), 1);
+        assert_eq!(config.max_findings_in_memory(), Some(100_000));
         assert_eq!(config.downloads_folder, Path::new("."));
         assert_eq!(config.dist_folder(), Path::new("dist"));
         assert_eq!(config.results_folder(), Path::new("results"));
+        assert_eq!(config.tmp_folder(), env::temp_dir());
+        assert_eq!(config.workspace_folder(), env::temp_dir());
         assert_eq!(config.template_name(), "super");
+        assert_eq!(config.source_url(), None);
+        assert_eq!(config.report_title(), None);
+        assert_eq!(config.client(), None);
+        assert_eq!(config.engagement_id(), None);
+        assert!(!config.has_to_take_screenshots());
+        assert_eq!(config.screenshot_renderer(), Path::new("chromium"));
+        assert_eq!(
+            config.fingerprints().iter().cloned().collect::<Vec<_>>(),
+            vec![
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Sha256
+            ]
+        );
+        assert_eq!(config.debug_artifacts().len(), DebugArtifact::all().len());
+        assert!(config
+            .sensitive_identifiers()
+            .iter()
+            .any(|word| word == "password"));
+        assert_eq!(config.ui_protection_criticality(), Criticality::Warning);
+        assert!(!config.downrank_sdk_findings());
+        assert!(config.library_cache());
+        assert_eq!(config.category_weight(Category::Banking, "crypto"), 1);
+        assert_eq!(config.category_weight(Category::Game, "crypto"), 0);
+        assert!(config.is_dex2jar_force());
+        assert!(!config.is_dex2jar_skip_exceptions());
+        assert!(config.apktool_file().is_none());
+        assert!(config.data_safety().is_none());
+        assert_eq!(config.min_allowed_target_sdk(), None);
+        assert_eq!(config.min_allowed_min_sdk(), None);
+        assert_eq!(config.sdk_policy_criticality(), Criticality::Medium);
+        assert!(config.escalation_hook().is_none());
+        assert!(config.suppressions_file().is_none());
+        assert!(config.audit_log().is_none());
+        assert!(config.apktool_framework_folder().is_none());
+        assert!(!config.is_apktool_no_res());
+        assert!(!config.is_apktool_force_manifest());
+        assert_eq!(config.apktool_api_level(), None);
+        assert!(config.tool_wrapper().is_empty());
+        assert!(config.tool_environment().iter().any(|var| var == "PATH"));
         let share_path = Path::new(if cfg!(target_os = "macos") {
             "/usr/local/super-analyzer"
         } else if cfg!(target_family = "windows") {
@@ -681,10 +1633,10 @@ mod tests {
         if cfg!(target_family = "unix") && Path::new("/etc/super-analyzer/rules.json").exists() {
             assert_eq!(
                 config.rules_json(),
-                Path::new("/etc/super-analyzer/rules.json")
+                &[PathBuf::from("/etc/super-analyzer/rules.json")]
             );
         } else {
-            assert_eq!(config.rules_json(), Path::new("rules.json"));
+            assert_eq!(config.rules_json(), &[PathBuf::from("rules.json")]);
         }
         assert_eq!(config.unknown_permission_criticality(), Criticality::Low);
         assert_eq!(
@@ -693,6 +1645,7 @@ mod tests {
              since it can lead to misunderstanding between developers."
         );
         assert_eq!(config.permissions().next(), None);
+        assert!(config.broadcasts().is_empty());
 
         if !config.downloads_folder.exists() {
             fs::create_dir(&config.downloads_folder).unwrap();
@@ -742,6 +1695,27 @@ mod tests {
         fs::remove_file(&packages[0]).unwrap();
     }
 
+    /// Checks the paths of the packages to analyze.
+    #[test]
+    fn it_add_app_package() {
+        let mut config = Config::default();
+        config.add_app_package("com.example");
+        config.add_app_package("com.example.v2");
+        config.add_app_package("payload.dex");
+        config.add_app_package("analytics-sdk.jar");
+
+        let downloads_folder = config.downloads_folder.clone();
+        assert_eq!(
+            config.app_packages(),
+            vec![
+                downloads_folder.join("com.example.apk"),
+                downloads_folder.join("com.example.v2.apk"),
+                downloads_folder.join("payload.dex"),
+                downloads_folder.join("analytics-sdk.jar"),
+            ]
+        );
+    }
+
     /// Test for the `config.toml.sample` sample configuration file.
     #[test]
     fn it_config_sample() {
@@ -773,7 +1747,7 @@ mod tests {
         assert_eq!(config.template_name(), "super");
         assert_eq!(
             config.rules_json(),
-            Path::new("/etc/super-analyzer/rules.json")
+            &[PathBuf::from("/etc/super-analyzer/rules.json")]
         );
         assert_eq!(config.unknown_permission_criticality(), Criticality::Low);
         assert_eq!(
@@ -796,6 +1770,22 @@ mod tests {
              internet, so this permission is not required to send data to the internet. \
              Check if the permission is actually needed."
         );
+
+        let broadcast = &config.broadcasts()[0];
+        assert_eq!(
+            broadcast.action(),
+            "android.provider.Telephony.SMS_RECEIVED"
+        );
+        assert_eq!(
+            broadcast.permission(),
+            Some("android.permission.RECEIVE_SMS")
+        );
+        assert_eq!(
+            broadcast.sender_permission(),
+            Some("android.permission.BROADCAST_SMS")
+        );
+        assert_eq!(broadcast.criticality(), Criticality::Medium);
+        assert_eq!(broadcast.label(), "SMS receiver");
     }
 
     /// Test to check the default reports to be generated
@@ -807,5 +1797,266 @@ mod tests {
 
         assert!(final_config.has_to_generate_html());
         assert!(!final_config.has_to_generate_json());
+        assert!(!final_config.has_to_generate_markdown());
+        assert!(!final_config.has_to_generate_junit());
+        assert!(!final_config.has_to_generate_sbom());
+
+        final_config.markdown = true;
+        assert!(!final_config.has_to_generate_html());
+        assert!(final_config.has_to_generate_markdown());
+
+        final_config.markdown = false;
+        final_config.junit = true;
+        assert!(!final_config.has_to_generate_html());
+        assert!(final_config.has_to_generate_junit());
+
+        final_config.junit = false;
+        final_config.sbom = true;
+        assert!(!final_config.has_to_generate_html());
+        assert!(final_config.has_to_generate_sbom());
+    }
+
+    /// Checks the folders where the reports are written.
+    #[test]
+    fn it_report_folders() {
+        let mut config = Config::default();
+        config.results_folder = PathBuf::from("results");
+        assert_eq!(
+            config.report_folder("com.example"),
+            Path::new("results").join("com.example")
+        );
+        assert_eq!(config.report_format_folder("out", "json"), Path::new("out"));
+        assert_eq!(config.report_file_name(), "results");
+
+        config.output_folder = Some(PathBuf::from("artifacts"));
+        config.per_format_folders = true;
+        assert_eq!(config.report_folder("com.example"), Path::new("artifacts"));
+        assert_eq!(
+            config.report_format_folder("artifacts", "json"),
+            Path::new("artifacts").join("json")
+        );
+    }
+
+    /// Checks the loading of one or several rules files.
+    #[test]
+    fn it_rules_files() {
+        let config: Config = toml::from_str(r#"rules_json = "rules.yaml""#).unwrap();
+        assert_eq!(config.rules_json(), &[PathBuf::from("rules.yaml")]);
+
+        let config: Config =
+            toml::from_str(r#"rules_json = ["rules.json", "project.yaml"]"#).unwrap();
+        assert_eq!(
+            config.rules_json(),
+            &[PathBuf::from("rules.json"), PathBuf::from("project.yaml")]
+        );
+
+        assert!(toml::from_str::<Config>("rules_json = 3").is_err());
+    }
+
+    /// Checks that the `SUPER_*` environment variables override the configuration file, and that
+    /// the CLI overrides them.
+    #[test]
+    fn it_env_overrides() {
+        let environment = vec![
+            ("SUPER_THREADS", "3"),
+            ("SUPER_DIST_FOLDER", "/tmp/dist"),
+            ("SUPER_TEMPLATE", "custom"),
+            ("SUPER_RULES_JSON", r#"["rules.json", "extra.json"]"#),
+            ("SUPER_", "ignored"),
+            ("DIST_FOLDER", "ignored"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect::<Vec<_>>();
+
+        let mut config =
+            Config::from_sources(Some("config.toml.sample"), None, environment.clone()).unwrap();
+        assert_eq!(config.threads(), 3);
+        assert_eq!(config.dist_folder(), Path::new("/tmp/dist"));
+        assert_eq!(config.results_folder(), Path::new("results"));
+        assert_eq!(config.template_name(), "custom");
+        assert_eq!(
+            config.rules_json(),
+            &[PathBuf::from("rules.json"), PathBuf::from("extra.json")]
+        );
+        assert_eq!(
+            config.loaded_config_files().next(),
+            Some(&PathBuf::from("config.toml.sample"))
+        );
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--threads",
+            "5",
+            "com.example",
+        ]);
+        config.set_options(&cli).unwrap();
+        assert_eq!(config.threads(), 5);
+        assert_eq!(config.dist_folder(), Path::new("/tmp/dist"));
+
+        let config = Config::from_sources(None::<&Path>, None, environment).unwrap();
+        assert_eq!(config.threads(), 3);
+        assert_eq!(config.loaded_config_files().count(), 0);
+
+        let environment = vec![("SUPER_THREADS".to_owned(), "0".to_owned())];
+        assert!(Config::from_sources(None::<&Path>, None, environment).is_err());
+    }
+
+    /// Checks that the selected named profile overrides the settings of the file, and that the
+    /// environment and the CLI override it.
+    #[test]
+    fn it_named_profiles() {
+        let path = env::temp_dir().join(format!("super-analyzer-profiles-{}.toml", process::id()));
+        fs::write(
+            &path,
+            r#"
+            threads = 2
+            results_folder = "results"
+
+            [profile.quick]
+            threads = 8
+            min_criticality = "high"
+
+            [profiles.deep]
+            profile = "masvs-l2"
+            rules_json = ["rules.json", "rules.d"]
+            json = true
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::from_sources(Some(&path), None, iter::empty()).unwrap();
+        assert_eq!(config.threads(), 2);
+        assert_eq!(config.min_criticality(), Criticality::Warning);
+
+        let environment = vec![("SUPER_THREADS".to_owned(), "4".to_owned())];
+        let config = Config::from_sources(Some(&path), Some("quick"), environment).unwrap();
+        assert_eq!(config.threads(), 4);
+        assert_eq!(config.min_criticality(), Criticality::High);
+
+        let mut config = Config::from_sources(Some(&path), Some("deep"), iter::empty()).unwrap();
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--profile",
+            "deep",
+            "--markdown",
+            "com.example",
+        ]);
+        config.decorate_with_cli(&cli).unwrap();
+        assert_eq!(config.profile(), Some(Profile::MasvsL2));
+        assert_eq!(config.threads(), 2);
+        assert_eq!(
+            config.rules_json(),
+            &[PathBuf::from("rules.json"), PathBuf::from("rules.d")]
+        );
+        assert!(config.has_to_generate_json());
+        assert!(config.has_to_generate_markdown());
+        assert!(!config.has_to_generate_html());
+
+        let config = Config::from_sources(Some(&path), Some("masvs-l1"), iter::empty()).unwrap();
+        assert_eq!(config.threads(), 2);
+
+        // A mistyped profile must not run the analysis without the policy of the profile.
+        let mut config =
+            Config::from_sources(Some(&path), Some("masvs-l2x"), iter::empty()).unwrap();
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--profile",
+            "masvs-l2x",
+            "com.example",
+        ]);
+        assert!(config.decorate_with_cli(&cli).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Checks the enabled and disabled checks, in the configuration and in the CLI.
+    #[test]
+    fn it_checks() {
+        let config = Config::default();
+        assert!(Check::all()
+            .iter()
+            .all(|check| config.is_check_enabled(*check)));
+
+        let mut config: Config = toml::from_str(
+            r#"
+            enabled_checks = ["secrets", "hardening", "code"]
+            disabled_checks = ["code"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.is_check_enabled(Check::Secrets));
+        assert!(config.is_check_enabled(Check::Hardening));
+        assert!(config.is_check_enabled(Check::Manifest));
+        assert!(!config.is_check_enabled(Check::Code));
+        assert!(!config.is_check_enabled(Check::Crypto));
+        assert!(config.is_code_needed());
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--enable",
+            "crypto,tls",
+            "--disable",
+            "tls",
+            "com.example",
+        ]);
+        config.set_options(&cli).unwrap();
+        assert!(config.is_check_enabled(Check::Crypto));
+        assert!(!config.is_check_enabled(Check::Tls));
+        assert!(!config.is_check_enabled(Check::Secrets));
+        assert!(!config.is_check_enabled(Check::Manifest));
+        assert!(!config.is_check_enabled(Check::Code));
+        assert!(config.is_code_needed());
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--enable",
+            "hardening,payloads",
+            "com.example",
+        ]);
+        config.set_options(&cli).unwrap();
+        assert!(config.is_check_enabled(Check::Manifest));
+        assert!(!config.is_code_needed());
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--enable",
+            "crypto",
+            "--disable",
+            "unknown",
+            "com.example",
+        ]);
+        assert!(config.set_options(&cli).is_err());
+        assert!(toml::from_str::<Config>(r#"enabled_checks = ["unknown"]"#).is_err());
+    }
+
+    /// Checks that the profile sets the minimum criticality and adds its policy.
+    #[test]
+    fn it_profile() {
+        let mut config: Config = toml::from_str(r#"profile = "privacy""#).unwrap();
+        assert_eq!(config.profile(), Some(Profile::Privacy));
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--profile",
+            "pci-mobile",
+            "com.example",
+        ]);
+        config.set_options(&cli).unwrap();
+        assert_eq!(config.profile(), Some(Profile::PciMobile));
+        assert_eq!(config.min_criticality(), Criticality::Medium);
+        assert_eq!(config.policies().len(), 1);
+        assert_eq!(config.policies()[0].name(), "pci-mobile");
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--profile",
+            "pci-mobile",
+            "--min-criticality",
+            "high",
+            "com.example",
+        ]);
+        config.set_options(&cli).unwrap();
+        assert_eq!(config.min_criticality(), Criticality::High);
+        assert_eq!(config.policies().len(), 1);
     }
 }
@@ -24,6 +24,46 @@ pub enum Criticality {
     Critical,
 }
 
+impl Criticality {
+    /// Gets the criticality one level below this one.
+    ///
+    /// Warnings stay as warnings, since it's the lowest level.
+    pub fn lower(self) -> Self {
+        match self {
+            Criticality::Critical => Criticality::High,
+            Criticality::High => Criticality::Medium,
+            Criticality::Medium => Criticality::Low,
+            Criticality::Low | Criticality::Warning => Criticality::Warning,
+        }
+    }
+
+    /// Gets the criticality one level above this one.
+    ///
+    /// Critical vulnerabilities stay as critical, since it's the highest level.
+    pub fn higher(self) -> Self {
+        match self {
+            Criticality::Warning => Criticality::Low,
+            Criticality::Low => Criticality::Medium,
+            Criticality::Medium => Criticality::High,
+            Criticality::High | Criticality::Critical => Criticality::Critical,
+        }
+    }
+
+    /// Gets the criticality the given number of levels above this one, or below it if the number
+    /// of levels is negative.
+    pub fn adjusted(self, levels: i8) -> Self {
+        let mut criticality = self;
+        for _ in 0..i32::from(levels).abs() {
+            criticality = if levels > 0 {
+                criticality.higher()
+            } else {
+                criticality.lower()
+            };
+        }
+        criticality
+    }
+}
+
 impl Display for Criticality {
     #[allow(clippy::use_debug)]
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
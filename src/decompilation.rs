@@ -2,22 +2,108 @@
 //!
 //! Handles the extraction, decompression and  decompilation of `_.apks_`
 
-use std::{fs, path::Path, process::Command};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
 use abxml::apk::Apk;
 use colored::Colorize;
 use failure::{bail, format_err, Error, ResultExt};
+use lazy_static::lazy_static;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
 
-use crate::{get_package_name, print_warning, Config};
+use crate::{
+    audit, cancel, error, get_package_name, print_warning,
+    static_analysis::payloads::{find_payloads, PayloadKind},
+    utils::PackageKind,
+    Config,
+};
 
-/// Decompresses the application using `_Apktool_`.
+lazy_static! {
+    /// Number of external processes currently running, with the condition to wait for a free
+    /// slot.
+    static ref RUNNING_PROCESSES: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
+}
+
+/// File of the decompression folder with the SHA-256 digest of the decompressed file.
+const SOURCE_DIGEST_FILE: &str = ".super-source";
+
+/// Environment variables with the temporary folder of the external tools.
+const TMP_VARIABLES: [&str; 3] = ["TMPDIR", "TMP", "TEMP"];
+
+/// Interval between the checks of the state of a running external tool.
+const TOOL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Permit to run an external process, released when dropped.
+#[derive(Debug)]
+struct ProcessPermit;
+
+impl ProcessPermit {
+    /// Waits until there is a free slot to run an external process.
+    fn acquire(config: &Config) -> Self {
+        let (running, free_slot) = &*RUNNING_PROCESSES;
+        let mut running = running.lock().unwrap();
+        while *running >= config.max_external_processes() {
+            running = free_slot.wait(running).unwrap();
+        }
+        *running += 1;
+        ProcessPermit
+    }
+}
+
+impl Drop for ProcessPermit {
+    fn drop(&mut self) {
+        let (running, free_slot) = &*RUNNING_PROCESSES;
+        *running.lock().unwrap() -= 1;
+        free_slot.notify_one();
+    }
+}
+
+/// Computes the SHA-256 digest of the given file, reading it in chunks so that big
+/// applications are not loaded in memory.
+fn file_digest(path: &Path) -> Result<String, Error> {
+    let mut hasher = Sha256::new();
+    let _ = io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.result()))
+}
+
+/// Decompresses the application.
+///
+/// The application is decoded with `_Apktool_` if it's configured, or with the built-in decoder
+/// otherwise. If the resources can't be decoded, the raw files are extracted. Bare DEX and JAR
+/// files are copied to the decompression folder as they are.
+///
+/// The decompression folder is created again if it was created from a different file with the
+/// same name, so that the analysis never mixes the files of two applications.
 pub fn decompress<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(), Error> {
     let path = config
         .dist_folder()
         .join(package.as_ref().file_stem().unwrap());
-    if !path.exists() || config.is_force() {
+    let digest = file_digest(package.as_ref())?;
+    let stale = fs::read_to_string(path.join(SOURCE_DIGEST_FILE))
+        .map(|previous| previous.trim() != digest)
+        .unwrap_or(false);
+    if !path.exists() || config.is_force() || stale {
         if path.exists() {
-            if config.is_verbose() {
+            if stale {
+                print_warning(format!(
+                    "the decompression folder at {} was created from a different file with the \
+                     same name, so it will be created again",
+                    path.display()
+                ));
+            } else if config.is_verbose() {
                 println!("The application decompression folder exists. But no more…");
             }
 
@@ -35,11 +121,15 @@ pub fn decompress<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(),
             println!("Decompressing the application…");
         }
 
-        let mut apk = Apk::from_path(package.as_ref()).context("error loading apk file")?;
-        apk.export(&path, true).context(format_err!(
-            "could not decompress the apk file. Tried to decompile at: {}",
-            path.display()
-        ))?;
+        let method = match PackageKind::from_path(package.as_ref()) {
+            PackageKind::Apk => decode(config, package.as_ref(), &path)?,
+            kind => {
+                copy_bare(package.as_ref(), &path, kind)?;
+                "bare copy"
+            }
+        };
+        audit::record_folder("file_extracted", &path, &json!({ "method": method }));
+        fs::write(path.join(SOURCE_DIGEST_FILE), &digest)?;
 
         if config.is_verbose() {
             println!(
@@ -58,61 +148,174 @@ pub fn decompress<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(),
             "Seems that the application has already been decompressed. There is no need to do it \
              again."
         );
-    } else {
+    } else if !config.is_quiet() {
         println!("Skipping decompression.");
     }
 
     Ok(())
 }
 
+/// Decodes the application to the destination folder, and returns the method used.
+fn decode(config: &Config, package: &Path, destination: &Path) -> Result<&'static str, Error> {
+    let apktool_decoded = if config.apktool_file().is_some() {
+        match run_apktool(config, package, destination) {
+            Ok(()) => true,
+            Err(e) => {
+                print_warning(format!(
+                    "the application could not be decoded with Apktool, the built-in decoder \
+                     will be used instead. Error: {}",
+                    e
+                ));
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    let decoded = if apktool_decoded {
+        Ok(())
+    } else {
+        Apk::from_path(package).and_then(|mut apk| apk.export(destination, true))
+    };
+    let mut method = if apktool_decoded {
+        "apktool"
+    } else {
+        "built-in decoder"
+    };
+    if let Err(e) = decoded {
+        // Malformed resources are a common anti-analysis technique, so the raw files get
+        // extracted, and the analysis will try to recover as much information as possible.
+        print_warning(format!(
+            "the application resources could not be decoded, the raw files will be extracted \
+             instead. Error: {}",
+            e
+        ));
+        extract_raw(package, destination).context(format_err!(
+            "could not decompress the apk file. Tried to decompress at: {}",
+            destination.display()
+        ))?;
+        method = "raw extraction";
+    }
+
+    Ok(method)
+}
+
+/// Copies a bare DEX or JAR file to the destination folder, as the `classes.dex` or the
+/// `classes.jar` file of an application, so that it gets decompiled in the same way.
+fn copy_bare(package: &Path, destination: &Path, kind: PackageKind) -> Result<(), Error> {
+    fs::create_dir_all(destination)?;
+    let file_name = if kind == PackageKind::Jar {
+        "classes.jar"
+    } else {
+        "classes.dex"
+    };
+    let _ = fs::copy(package, destination.join(file_name)).context(format_err!(
+        "could not copy the {} file to {}",
+        package.display(),
+        destination.display()
+    ))?;
+    Ok(())
+}
+
+/// Decodes the application to the destination folder using `_Apktool_`.
+fn run_apktool<P: AsRef<Path>, D: AsRef<Path>>(
+    config: &Config,
+    package: P,
+    destination: D,
+) -> Result<(), Error> {
+    let apktool_file = match config.apktool_file() {
+        Some(apktool_file) => absolute(apktool_file)?,
+        None => bail!("the Apktool JAR file is not configured"),
+    };
+    let package = absolute(package)?;
+    let destination = absolute(destination)?;
+    // Without a configured framework folder, the framework files are installed in the workspace,
+    // instead of in the folder shared by all the Apktool runs of the user.
+    let framework_folder = match config.apktool_framework_folder() {
+        Some(framework_folder) => absolute(framework_folder)?,
+        None => absolute(config.workspace_folder().join("apktool-framework"))?,
+    };
+
+    let _permit = ProcessPermit::acquire(config);
+
+    // "d" to decode, "-f" to overwrite the destination and "-o path" to specify it.
+    let mut command = java_command(config)?;
+    let _ = command
+        .arg("-jar")
+        .arg(apktool_file)
+        .arg("d")
+        .arg("-f")
+        .arg("-o")
+        .arg(&destination)
+        .arg("--frame-path")
+        .arg(framework_folder);
+    if config.is_apktool_no_res() {
+        let _ = command.arg("--no-res");
+    }
+    if config.is_apktool_force_manifest() {
+        let _ = command.arg("--force-manifest");
+    }
+    if let Some(api_level) = config.apktool_api_level() {
+        let _ = command.arg("--api-level").arg(api_level.to_string());
+    }
+    let _ = command.arg(package);
+    let output = tool_output(&mut command)
+        .context("there was an unknown error decoding the application with Apktool")?;
+
+    if !output.status.success() {
+        bail!(
+            "the Apktool command returned an error. More info:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Extracts the files of the application without decoding them.
+fn extract_raw<P: AsRef<Path>, D: AsRef<Path>>(package: P, destination: D) -> Result<(), Error> {
+    // Remove any partially decoded files.
+    if destination.as_ref().exists() {
+        fs::remove_dir_all(destination.as_ref())?;
+    }
+
+    let mut archive = ZipArchive::new(File::open(package)?)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let out_path = destination.as_ref().join(file.sanitized_name());
+        if file.name().ends_with('/') {
+            fs::create_dir_all(&out_path)?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = io::copy(&mut file, &mut File::create(&out_path)?)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Converts `_.dex_` files to `_.jar_` using `_Dex2jar_`.
 pub fn dex_to_jar<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(), Error> {
+    // Bare JAR files are copied as the `classes.jar` file when decompressing them.
+    if PackageKind::from_path(package.as_ref()) == PackageKind::Jar {
+        return Ok(());
+    }
+
     let package_name = get_package_name(package.as_ref());
     let classes = config.dist_folder().join(&package_name).join("classes.jar");
     if config.is_force() || !classes.exists() {
         config.set_force();
 
-        // Command to convert .dex to .jar. using dex2jar.
-        // "-o path" to specify an output file
-        let output = Command::new(config.dex2jar_folder().join(
-            if cfg!(target_family = "windows") {
-                "d2j-dex2jar.bat"
-            } else {
-                "d2j-dex2jar.sh"
-            },
-        ))
-        .arg(config.dist_folder().join(&package_name).join("classes.dex"))
-        .arg("-f")
-        .arg("-o")
-        .arg(&classes)
-        .output()
-        .context(format_err!(
-            "there was an error when executing the {} to {} conversion command",
-            ".dex".italic(),
-            ".jar".italic()
-        ))?;
+        let package_dir = absolute(config.dist_folder().join(&package_name))?;
 
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        // Here a small hack: seems that dex2jar outputs in stderr even if everything went well,
-        // and the status is always success. So the only difference is if we detect the actual
-        // exception that was produced. But in some cases it does not return an exception, so we
-        // have to check if errors such as "use certain option" occur.
-        let mut call_ok = output.status.success() || !stderr.contains("use");
-        if stderr.find('\n') != Some(stderr.len() - 1) {
-            if stderr.starts_with("Picked up _JAVA_OPTIONS:") {
-                call_ok = stderr.lines().count() == 2;
-            } else {
-                call_ok = false;
-            }
-        }
-        if !call_ok {
-            bail!(
-                "the {} to {} conversion command returned an error. More info: {}",
-                ".dex".italic(),
-                ".jar".italic(),
-                stderr
-            );
-        }
+        run_dex2jar(
+            config,
+            package_dir.join("classes.dex"),
+            package_dir.join("classes.jar"),
+        )?;
 
         if config.is_verbose() {
             println!(
@@ -134,13 +337,245 @@ pub fn dex_to_jar<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(),
              create it again.",
             ".jar".italic()
         );
-    } else {
+    } else if !config.is_quiet() {
         println!("Skipping {} file generation.", ".jar".italic());
     }
 
     Ok(())
 }
 
+/// Minimum percentage of the classes that have to be decompiled for the analysis to be considered
+/// complete.
+const MIN_DECOMPILED_CLASSES: f64 = 90.0;
+
+/// Completeness of the translation of the application code from `_.dex_` to Java sources.
+///
+/// `_Dex2jar_` skips the methods it can't translate, and `_jd\_cmd_` the classes it can't
+/// decompile, so the code that gets analyzed can be only a part of the application code.
+/// Protected applications usually have many of these untranslatable classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Translation {
+    /// Number of classes in the `_.dex_` file.
+    dex_classes: u32,
+    /// Number of classes in the translated `_.jar_` file.
+    translated_classes: u32,
+    /// Number of errors reported by `_Dex2jar_`.
+    errors: u32,
+    /// Number of top-level classes in the `_.dex_` file, that should have a source file.
+    #[serde(default)]
+    top_level_classes: u32,
+    /// Number of top-level classes with a decompiled source file.
+    #[serde(default)]
+    decompiled_classes: u32,
+}
+
+impl Translation {
+    /// Computes the translation completeness of the given package from its distribution folder.
+    pub fn from_package<P: AsRef<Path>>(config: &Config, package: P) -> Result<Self, Error> {
+        let working_dir = config
+            .dist_folder()
+            .join(get_package_name(package.as_ref()));
+
+        let mut dex = Vec::new();
+        let _ = File::open(working_dir.join("classes.dex"))
+            .context("could not open the classes.dex file")?
+            .read_to_end(&mut dex)?;
+        let class_names = dex_class_names(&dex).context("the classes.dex file is not valid")?;
+
+        let jar = working_dir.join("classes.jar");
+        let translated_classes = count_entries(&jar, |name| name.ends_with(".class"))
+            .context("could not read the classes.jar file")?;
+
+        // Dex2jar stores a summary and a file for each error found.
+        let error_file = error_file(&jar);
+        let errors = if error_file.exists() {
+            count_entries(&error_file, |name| name != "summary.txt")
+                .context("could not read the Dex2jar error file")?
+        } else {
+            0
+        };
+
+        // Nested classes are decompiled in the source file of their top-level class.
+        let classes_dir = working_dir.join("classes");
+        let mut top_level_classes = 0;
+        let mut decompiled_classes = 0;
+        for source_file in class_names.iter().filter_map(|name| source_file(name)) {
+            top_level_classes += 1;
+            if classes_dir.join(source_file).exists() {
+                decompiled_classes += 1;
+            }
+        }
+
+        Ok(Self {
+            dex_classes: class_names.len() as u32,
+            translated_classes,
+            errors,
+            top_level_classes,
+            decompiled_classes,
+        })
+    }
+
+    /// Gets the number of classes in the `_.dex_` file.
+    pub fn dex_classes(&self) -> u32 {
+        self.dex_classes
+    }
+
+    /// Gets the number of classes in the translated `_.jar_` file.
+    pub fn translated_classes(&self) -> u32 {
+        self.translated_classes
+    }
+
+    /// Gets the number of errors reported by `_Dex2jar_`.
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    /// Gets the number of top-level classes in the `_.dex_` file.
+    pub fn top_level_classes(&self) -> u32 {
+        self.top_level_classes
+    }
+
+    /// Gets the number of top-level classes with a decompiled source file.
+    pub fn decompiled_classes(&self) -> u32 {
+        self.decompiled_classes
+    }
+
+    /// Gets the percentage of the classes that were translated.
+    pub fn completeness(&self) -> f64 {
+        percentage(self.translated_classes, self.dex_classes)
+    }
+
+    /// Gets the percentage of the top-level classes that were decompiled.
+    pub fn decompilation_completeness(&self) -> f64 {
+        percentage(self.decompiled_classes, self.top_level_classes)
+    }
+
+    /// Checks if a significant part of the classes could not be decompiled, so that the analysis
+    /// might have missed vulnerabilities.
+    pub fn is_incomplete(&self) -> bool {
+        self.decompilation_completeness() < MIN_DECOMPILED_CLASSES
+    }
+}
+
+impl Serialize for Translation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut ser_struct = serializer.serialize_struct("Translation", 8)?;
+        ser_struct.serialize_field("dex_classes", &self.dex_classes)?;
+        ser_struct.serialize_field("translated_classes", &self.translated_classes)?;
+        ser_struct.serialize_field("errors", &self.errors)?;
+        ser_struct.serialize_field("top_level_classes", &self.top_level_classes)?;
+        ser_struct.serialize_field("decompiled_classes", &self.decompiled_classes)?;
+        // Rounded to one decimal, since that's the precision shown in the reports.
+        ser_struct.serialize_field(
+            "completeness",
+            &((self.completeness() * 10.0).round() / 10.0),
+        )?;
+        ser_struct.serialize_field(
+            "decompilation_completeness",
+            &((self.decompilation_completeness() * 10.0).round() / 10.0),
+        )?;
+        ser_struct.serialize_field("incomplete", &self.is_incomplete())?;
+        ser_struct.end()
+    }
+}
+
+/// Gets the percentage that the part represents of the total, or 100 if the total is 0.
+fn percentage(part: u32, total: u32) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        (f64::from(part) * 100.0 / f64::from(total)).min(100.0)
+    }
+}
+
+/// Gets the names of the classes defined in the given `_.dex_` file, as type descriptors.
+fn dex_class_names(dex: &[u8]) -> Result<Vec<String>, Error> {
+    if dex.len() < 0x70 || !dex.starts_with(b"dex\n") {
+        bail!("invalid DEX header");
+    }
+    let string_ids_off = read_u32(dex, 0x3C)? as usize;
+    let type_ids_off = read_u32(dex, 0x44)? as usize;
+    let class_defs_size = read_u32(dex, 0x60)? as usize;
+    let class_defs_off = read_u32(dex, 0x64)? as usize;
+
+    let mut names = Vec::new();
+    for i in 0..class_defs_size {
+        // Each class definition takes 32 bytes, starting with the index of its type.
+        let type_idx = read_u32(dex, class_defs_off + i * 32)? as usize;
+        let string_idx = read_u32(dex, type_ids_off + type_idx * 4)? as usize;
+        let string_data_off = read_u32(dex, string_ids_off + string_idx * 4)? as usize;
+
+        // The string data starts with its length as an ULEB128, and it's null terminated.
+        let mut start = string_data_off;
+        while dex.get(start).map_or(false, |byte| byte & 0x80 != 0) {
+            start += 1;
+        }
+        start += 1;
+        let len = dex
+            .get(start..)
+            .and_then(|data| data.iter().position(|byte| *byte == 0))
+            .ok_or_else(|| format_err!("invalid class name at offset {}", string_data_off))?;
+        names.push(String::from_utf8_lossy(&dex[start..start + len]).into_owned());
+    }
+
+    Ok(names)
+}
+
+/// Reads a little endian `u32` at the given offset of the `_.dex_` file.
+fn read_u32(dex: &[u8], offset: usize) -> Result<u32, Error> {
+    match dex.get(offset..offset + 4) {
+        Some(bytes) => Ok(u32::from(bytes[0])
+            | u32::from(bytes[1]) << 8
+            | u32::from(bytes[2]) << 16
+            | u32::from(bytes[3]) << 24),
+        None => bail!("unexpected end of file at offset {}", offset),
+    }
+}
+
+/// Gets the path of the source file of the given class descriptor, relative to the source folder.
+///
+/// Returns `None` for nested classes, since they are decompiled in the source file of their
+/// top-level class.
+fn source_file(descriptor: &str) -> Option<PathBuf> {
+    if !descriptor.starts_with('L') || !descriptor.ends_with(';') {
+        return None;
+    }
+    let name = &descriptor[1..descriptor.len() - 1];
+    if name.contains('$') {
+        return None;
+    }
+    Some(PathBuf::from(format!("{}.java", name)))
+}
+
+/// Counts the entries of the given ZIP file whose name matches the given predicate.
+fn count_entries<P, F>(path: P, predicate: F) -> Result<u32, Error>
+where
+    P: AsRef<Path>,
+    F: Fn(&str) -> bool,
+{
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut count = 0;
+    for i in 0..archive.len() {
+        if predicate(archive.by_index(i)?.name()) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Gets the path of the file where `_Dex2jar_` stores the errors of the given `_.jar_` file.
+fn error_file<P: AsRef<Path>>(jar: P) -> PathBuf {
+    let mut file_name = jar
+        .as_ref()
+        .file_stem()
+        .map_or_else(Default::default, OsStr::to_os_string);
+    file_name.push("-error.zip");
+    jar.as_ref().with_file_name(file_name)
+}
+
 /// Decompiles the application using `_jd\_cmd_`.
 pub fn decompile<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(), Error> {
     let package_name = get_package_name(package.as_ref());
@@ -148,22 +583,20 @@ pub fn decompile<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(),
     if config.is_force() || !out_path.exists() {
         config.set_force();
 
-        // Command to decompile the application using `jd_cmd`.
-        // "-od path" to specify an output directory
-        let output = Command::new("java")
-            .arg("-jar")
-            .arg(config.jd_cmd_file())
-            .arg(config.dist_folder().join(&package_name).join("classes.jar"))
-            .arg("-od")
-            .arg(&out_path)
-            .output()
-            .context("there was an unknown error decompiling the application")?;
-
-        if !output.status.success() {
-            bail!(
-                "the decompilation command returned an error. More info:\n{}",
-                String::from_utf8_lossy(&output.stdout)
-            );
+        let package_dir = absolute(config.dist_folder().join(&package_name))?;
+
+        if config.decompilation_shards() > 1 {
+            decompile_shards(
+                config,
+                package_dir.join("classes.jar"),
+                package_dir.join("classes"),
+            )?;
+        } else {
+            run_jd_cmd(
+                config,
+                package_dir.join("classes.jar"),
+                package_dir.join("classes"),
+            )?;
         }
 
         if config.is_verbose() {
@@ -179,9 +612,705 @@ pub fn decompile<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(),
             "Seems that there is already a source folder for the application. There is no need to \
              decompile it again."
         );
-    } else {
+    } else if !config.is_quiet() {
         println!("Skipping decompilation.");
     }
 
     Ok(())
 }
+
+/// Decompiles the given `_.jar_` file to the output folder, splitting its classes in shards that
+/// are decompiled in parallel, each one by its own `_jd\_cmd_` process.
+///
+/// The shards are written in the workspace of the analysis, and only the merged sources are
+/// written to the output folder.
+fn decompile_shards<I: AsRef<Path>, D: AsRef<Path>>(
+    config: &Config,
+    input: I,
+    destination: D,
+) -> Result<(), Error> {
+    let shards_dir = absolute(config.workspace_folder().join("shards"))?;
+    if shards_dir.exists() {
+        fs::remove_dir_all(&shards_dir)?;
+    }
+    fs::create_dir_all(&shards_dir)?;
+    let jars = split_jar(input, &shards_dir, config.decompilation_shards())?;
+    if config.is_verbose() {
+        println!("Decompiling the classes in {} shards.", jars.len());
+    }
+
+    let thread_config = Arc::new(config.clone());
+    let handles = jars
+        .into_iter()
+        .map(|jar| {
+            let thread_config = Arc::clone(&thread_config);
+            thread::spawn(move || -> Result<PathBuf, Error> {
+                let out_path = jar.with_extension("");
+                run_jd_cmd(&thread_config, &jar, &out_path)?;
+                Ok(out_path)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // All the shards are waited for, so that no process writes in the folder once it's removed.
+    let mut out_paths = Vec::with_capacity(handles.len());
+    let mut error = None;
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(out_path)) => out_paths.push(out_path),
+            Ok(Err(e)) => {
+                if error.is_none() {
+                    error = Some(e);
+                }
+            }
+            Err(_) => {
+                if error.is_none() {
+                    error = Some(format_err!("a decompilation thread panicked"));
+                }
+            }
+        }
+    }
+
+    let result = match error {
+        Some(e) => Err(e),
+        None => out_paths
+            .iter()
+            .map(|out_path| merge_folder(out_path, destination.as_ref()))
+            .collect(),
+    };
+    fs::remove_dir_all(&shards_dir)?;
+    result
+}
+
+/// Splits the classes of the given `_.jar_` file in, at most, the given number of shards, and
+/// returns the paths of the `_.jar_` files of the shards, created in the destination folder.
+fn split_jar<I: AsRef<Path>, D: AsRef<Path>>(
+    input: I,
+    destination: D,
+    shards: usize,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut archive = ZipArchive::new(File::open(input.as_ref())?)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        entries.push((file.name().to_owned(), file.size()));
+    }
+
+    let assignments = assign_shards(&entries, shards);
+    let shard_count = assignments
+        .iter()
+        .filter_map(|shard| *shard)
+        .max()
+        .map_or(0, |shard| shard + 1);
+    let mut jars = Vec::with_capacity(shard_count);
+    let mut writers = Vec::with_capacity(shard_count);
+    for shard in 0..shard_count {
+        let jar = destination.as_ref().join(format!("shard-{}.jar", shard));
+        writers.push(ZipWriter::new(File::create(&jar)?));
+        jars.push(jar);
+    }
+
+    for (i, shard) in assignments.into_iter().enumerate() {
+        if let Some(shard) = shard {
+            let mut file = archive.by_index(i)?;
+            let writer = &mut writers[shard];
+            writer.start_file(file.name(), FileOptions::default())?;
+            let _ = io::copy(&mut file, writer)?;
+        }
+    }
+    for mut writer in writers {
+        let _ = writer.finish()?;
+    }
+
+    Ok(jars)
+}
+
+/// Assigns the entries of a `_.jar_` file, given by their name and size, to the given number of
+/// shards.
+///
+/// Nested classes go to the shard of their top-level class, since they are decompiled in the
+/// same source file, and the biggest classes are assigned first to the least loaded shard, so
+/// that the shards take a similar time. Entries that are not classes don't go to any shard.
+fn assign_shards(entries: &[(String, u64)], shards: usize) -> Vec<Option<usize>> {
+    let mut sizes: BTreeMap<&str, u64> = BTreeMap::new();
+    for (name, size) in entries {
+        if let Some(class) = top_level_class(name) {
+            *sizes.entry(class).or_insert(0) += size;
+        }
+    }
+    let mut classes = sizes.into_iter().collect::<Vec<_>>();
+    // The sort is stable, so classes of the same size keep their alphabetical order.
+    classes.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    let mut loads = vec![0_u64; shards.max(1)];
+    let mut assigned = HashMap::new();
+    for (class, size) in classes {
+        if let Some((shard, load)) = loads.iter_mut().enumerate().min_by_key(|(_, load)| **load) {
+            *load += size;
+            let _ = assigned.insert(class, shard);
+        }
+    }
+
+    entries
+        .iter()
+        .map(|(name, _)| top_level_class(name).and_then(|class| assigned.get(class).cloned()))
+        .collect()
+}
+
+/// Gets the path of the top-level class of the given `_.jar_` entry, if it's a class.
+fn top_level_class(name: &str) -> Option<&str> {
+    if !name.ends_with(".class") {
+        return None;
+    }
+    let class = &name[..name.len() - ".class".len()];
+    let start = class.rfind('/').map_or(0, |slash| slash + 1);
+    Some(
+        class[start..]
+            .find('$')
+            .map_or(class, |dollar| &class[..start + dollar]),
+    )
+}
+
+/// Moves the files of the source folder to the destination folder, recursively, replacing the
+/// existing ones.
+fn merge_folder<S: AsRef<Path>, D: AsRef<Path>>(source: S, destination: D) -> Result<(), Error> {
+    fs::create_dir_all(destination.as_ref())?;
+    for entry in fs::read_dir(source.as_ref())? {
+        let entry = entry?;
+        let target = destination.as_ref().join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            merge_folder(entry.path(), &target)?;
+        } else {
+            fs::rename(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decompiles the executable payloads hidden in the resources of the application.
+///
+/// Each payload is decompiled to the `payloads` folder of the application, so that its code gets
+/// analyzed with the rest of the application. The APK files of bundles, such as Instant App
+/// bundles, are extracted and decompiled to a folder each. Payloads that can't be decompiled are
+/// skipped.
+pub fn decompile_payloads<P: AsRef<Path>>(config: &mut Config, package: P) -> Result<(), Error> {
+    let package_name = get_package_name(package.as_ref());
+    let package_dir = absolute(config.dist_folder().join(&package_name))?;
+    let payloads_dir = package_dir.join("payloads");
+
+    for payload in find_payloads(&package_dir) {
+        if !payload.kind().is_decompilable() {
+            continue;
+        }
+        let name = payload
+            .path()
+            .to_string_lossy()
+            .replace(|c: char| c == '/' || c == '\\', "_");
+        let out_path = payloads_dir.join(&name);
+        if !config.is_force() && out_path.exists() {
+            continue;
+        }
+        config.set_force();
+        fs::create_dir_all(&payloads_dir)?;
+
+        let inputs = if payload.kind() == PayloadKind::ApkBundle {
+            // The APK files of the bundle are only needed to decompile them.
+            let apks_dir = absolute(config.workspace_folder().join(format!("{}.apks", name)))?;
+            match extract_apks(package_dir.join(payload.path()), &apks_dir) {
+                Ok(apks) => apks
+                    .into_iter()
+                    .map(|apk| {
+                        let apk_out_path = out_path.join(apk.file_stem().unwrap_or_default());
+                        (apk, apk_out_path)
+                    })
+                    .collect(),
+                Err(e) => {
+                    print_warning(format!(
+                        "the APK files of the bundle {} could not be extracted: {}",
+                        payload.path().display(),
+                        e
+                    ));
+                    continue;
+                }
+            }
+        } else {
+            vec![(package_dir.join(payload.path()), out_path)]
+        };
+
+        for (input, out_path) in inputs {
+            let jar = if payload.kind().is_dalvik() {
+                let jar = out_path.with_file_name(format!(
+                    "{}.jar",
+                    out_path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                if let Some(parent) = jar.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if let Err(e) = run_dex2jar(config, &input, &jar) {
+                    print_warning(format!(
+                        "the payload {} could not be converted to {}: {}",
+                        input.strip_prefix(&package_dir).unwrap_or(&input).display(),
+                        ".jar".italic(),
+                        e
+                    ));
+                    continue;
+                }
+                jar
+            } else {
+                input.clone()
+            };
+
+            if let Err(e) = run_jd_cmd(config, jar, &out_path) {
+                print_warning(format!(
+                    "the payload {} could not be decompiled: {}",
+                    input.strip_prefix(&package_dir).unwrap_or(&input).display(),
+                    e
+                ));
+            } else if config.is_verbose() {
+                println!(
+                    "The payload {} has been decompiled.",
+                    input.strip_prefix(&package_dir).unwrap_or(&input).display()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the APK files of the given bundle to the given folder, and returns their paths.
+fn extract_apks<B: AsRef<Path>, D: AsRef<Path>>(
+    bundle: B,
+    destination: D,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut archive = ZipArchive::new(File::open(bundle.as_ref())?)?;
+    let mut apks = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.name().ends_with(".apk") {
+            continue;
+        }
+        // Flatten the APK files, so that every one of them gets its own output folder.
+        let file_name = file
+            .sanitized_name()
+            .to_string_lossy()
+            .replace(|c: char| c == '/' || c == '\\', "_");
+        let out_path = destination.as_ref().join(file_name);
+        fs::create_dir_all(destination.as_ref())?;
+        let _ = io::copy(&mut file, &mut File::create(&out_path)?)?;
+        audit::record_file(
+            "file_extracted",
+            &out_path,
+            json!({ "source": bundle.as_ref().to_string_lossy() }),
+        );
+        apks.push(out_path);
+    }
+    Ok(apks)
+}
+
+/// Converts the given `_.dex_` file, or archive with them, to a `_.jar_` file using `_Dex2jar_`.
+fn run_dex2jar<I: AsRef<Path>, D: AsRef<Path>>(
+    config: &Config,
+    input: I,
+    destination: D,
+) -> Result<(), Error> {
+    // Errors from previous runs would be mistaken for errors of this run.
+    let error_file = error_file(destination.as_ref());
+    if error_file.exists() {
+        fs::remove_file(&error_file)?;
+    }
+
+    let _permit = ProcessPermit::acquire(config);
+
+    // Command to convert .dex to .jar. using dex2jar.
+    // "-o path" to specify an output file and "-e path" to specify the error file.
+    let mut command = tool_command(
+        config,
+        absolute(
+            config
+                .dex2jar_folder()
+                .join(if cfg!(target_family = "windows") {
+                    "d2j-dex2jar.bat"
+                } else {
+                    "d2j-dex2jar.sh"
+                }),
+        )?,
+        absolute(config.workspace_folder())?,
+    );
+    let _ = command
+        .arg(input.as_ref())
+        .arg("-o")
+        .arg(destination.as_ref())
+        .arg("-e")
+        .arg(&error_file);
+    if config.is_dex2jar_force() {
+        let _ = command.arg("--force");
+    }
+    if config.is_dex2jar_skip_exceptions() {
+        let _ = command.arg("--skip-exceptions");
+    }
+    let output = tool_output(&mut command).context(format_err!(
+        "there was an error when executing the {} to {} conversion command",
+        ".dex".italic(),
+        ".jar".italic()
+    ))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Here a small hack: seems that dex2jar outputs in stderr even if everything went well,
+    // and the status is always success. So the only difference is if we detect the actual
+    // exception that was produced. But in some cases it does not return an exception, so we
+    // have to check if errors such as "use certain option" occur.
+    let mut call_ok = output.status.success() || !stderr.contains("use");
+    if stderr.find('\n') != Some(stderr.len() - 1) {
+        if stderr.starts_with("Picked up _JAVA_OPTIONS:") {
+            call_ok = stderr.lines().count() == 2;
+        } else {
+            call_ok = false;
+        }
+    }
+    if !call_ok {
+        bail!(
+            "the {} to {} conversion command returned an error. More info: {}",
+            ".dex".italic(),
+            ".jar".italic(),
+            stderr
+        );
+    }
+
+    Ok(())
+}
+
+/// Decompiles the given `_.jar_` file to the output folder using `_jd\_cmd_`.
+fn run_jd_cmd<I: AsRef<Path>, D: AsRef<Path>>(
+    config: &Config,
+    input: I,
+    destination: D,
+) -> Result<(), Error> {
+    let _permit = ProcessPermit::acquire(config);
+
+    // Command to decompile the application using `jd_cmd`.
+    // "-od path" to specify an output directory
+    let mut command = java_command(config)?;
+    let _ = command
+        .arg("-jar")
+        .arg(absolute(config.jd_cmd_file())?)
+        .arg(input.as_ref())
+        .arg("-od")
+        .arg(destination.as_ref());
+    let output = tool_output(&mut command)
+        .context("there was an unknown error decompiling the application")?;
+
+    if !output.status.success() {
+        bail!(
+            "the decompilation command returned an error. More info:\n{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    Ok(())
+}
+
+/// Creates the command to run an external tool with a restricted environment.
+///
+/// These tools parse attacker-controlled input, so the command only inherits the environment
+/// variables allowed in the configuration, and its temporary files are written in the workspace
+/// of the analysis. If a tool wrapper is configured (such as _Firejail_), the tool is executed
+/// through it.
+pub(crate) fn tool_command<S, P>(config: &Config, program: S, working_dir: P) -> Command
+where
+    S: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let mut command = if let Some((wrapper, args)) = config.tool_wrapper().split_first() {
+        let mut command = Command::new(wrapper);
+        let _ = command.args(args).arg(program);
+        command
+    } else {
+        Command::new(program)
+    };
+
+    let _ = command.env_clear().current_dir(working_dir);
+    for var in config.tool_environment() {
+        if let Some(value) = env::var_os(var) {
+            let _ = command.env(var, value);
+        }
+    }
+    for var in &TMP_VARIABLES {
+        let _ = command.env(var, config.workspace_folder());
+    }
+
+    command
+}
+
+/// Creates the command to run a Java tool in the workspace of the analysis, with its temporary
+/// files in it too, since the Java runtime ignores the temporary folder variables.
+fn java_command(config: &Config) -> Result<Command, Error> {
+    let workspace = absolute(config.workspace_folder())?;
+    let mut command = tool_command(config, "java", &workspace);
+    let _ = command.arg(format!("-Djava.io.tmpdir={}", workspace.display()));
+    Ok(command)
+}
+
+/// Runs the command of an external tool, waiting for its output.
+///
+/// If the program of the command can't be found, a `ToolMissing` error is returned, so that it
+/// can be told apart from the errors of the tool itself. If the analysis is cancelled while the
+/// tool is running, the tool is killed and a `Cancelled` error is returned.
+pub(crate) fn tool_output(command: &mut Command) -> Result<Output, Error> {
+    cancel::check()?;
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(error::Kind::ToolMissing {
+                message: format!(
+                    "the program of the {:?} command could not be found",
+                    command
+                ),
+            }
+            .into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // The output is read while the tool runs, so that it does not block on a full pipe.
+    let start = Instant::now();
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancel::is_cancelled() {
+            kill_tool(&mut child);
+            audit_tool("tool_killed", command, None, start);
+            return Err(error::Kind::Cancelled.into());
+        }
+        thread::sleep(TOOL_POLL_INTERVAL);
+    };
+    audit_tool("tool_invoked", command, status.code(), start);
+
+    Ok(Output {
+        status,
+        stdout: join_pipe(stdout)?,
+        stderr: join_pipe(stderr)?,
+    })
+}
+
+/// Records the invocation of an external tool in the audit log, with its arguments, its exit
+/// code and its duration.
+#[allow(clippy::use_debug)]
+fn audit_tool(action: &str, command: &Command, status: Option<i32>, start: Instant) {
+    if !audit::is_enabled() {
+        return;
+    }
+    let duration = start.elapsed();
+    audit::record(
+        action,
+        json!({
+            "command": format!("{:?}", command),
+            "status": status,
+            "duration_ms": duration.as_secs() * 1_000 + u64::from(duration.subsec_millis()),
+        }),
+    );
+}
+
+/// Reads an output pipe of an external tool in a new thread.
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer)?;
+        }
+        Ok(buffer)
+    })
+}
+
+/// Waits until an output pipe of an external tool has been read, and gets its contents.
+fn join_pipe(reader: JoinHandle<io::Result<Vec<u8>>>) -> Result<Vec<u8>, Error> {
+    match reader.join() {
+        Ok(output) => Ok(output?),
+        Err(_) => bail!("the output of the tool could not be read"),
+    }
+}
+
+/// Kills an external tool.
+///
+/// Tools such as _Dex2jar_ are shell scripts that start a Java process, so on UNIX the direct
+/// children of the tool are killed first, to avoid leaving orphan Java processes behind.
+fn kill_tool(child: &mut Child) {
+    if cfg!(target_family = "unix") {
+        let _ = Command::new("pkill")
+            .arg("-TERM")
+            .arg("-P")
+            .arg(child.id().to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Gets the absolute version of the given path, since tools run in a different working directory.
+fn absolute<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+    if path.as_ref().is_absolute() {
+        Ok(path.as_ref().to_path_buf())
+    } else {
+        Ok(env::current_dir()
+            .context("could not get the current working directory")?
+            .join(path))
+    }
+}
+
+/// Decompilation testing module.
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::Path, process};
+
+    use super::{
+        assign_shards, dex_class_names, error_file, file_digest, source_file, top_level_class,
+        Translation,
+    };
+
+    /// Checks the digest of a file read in chunks.
+    #[test]
+    fn it_file_digest() {
+        let path = env::temp_dir().join(format!("super-analyzer-digest-{}.dex", process::id()));
+        fs::write(&path, b"dex\n035\0").unwrap();
+
+        assert_eq!(
+            file_digest(&path).unwrap(),
+            "2c54a4c51192601674623d247e827ff8fcee7212eda7fed6bab5a4e959ace58e"
+        );
+        assert!(file_digest(&path.with_extension("missing")).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Checks the class names of a minimal DEX file.
+    #[test]
+    fn it_dex_class_names() {
+        let mut dex = b"dex\n035\0".to_vec();
+        dex.resize(0x70, 0);
+        // One string, one type and one class definition, after the header.
+        dex[0x38] = 1;
+        dex[0x3C] = 0x70;
+        dex[0x40] = 1;
+        dex[0x44] = 0x74;
+        dex[0x60] = 1;
+        dex[0x64] = 0x78;
+        dex.resize(0x98, 0);
+        dex[0x70] = 0x98;
+        dex.extend_from_slice(b"\x0fLcom/example/A;\0");
+
+        assert_eq!(dex_class_names(&dex).unwrap(), vec!["Lcom/example/A;"]);
+
+        dex[0x60] = 2;
+        assert!(dex_class_names(&dex).is_err());
+        assert!(dex_class_names(b"PK\x03\x04").is_err());
+    }
+
+    /// Checks the source file of the classes.
+    #[test]
+    fn it_source_file() {
+        assert_eq!(
+            source_file("Lcom/example/Main;"),
+            Some(Path::new("com/example/Main.java").to_path_buf())
+        );
+        assert_eq!(
+            source_file("LMain;"),
+            Some(Path::new("Main.java").to_path_buf())
+        );
+        assert_eq!(source_file("Lcom/example/Main$1;"), None);
+        assert_eq!(source_file("[I"), None);
+    }
+
+    /// Checks the path of the Dex2jar error file.
+    #[test]
+    fn it_error_file() {
+        assert_eq!(
+            error_file(Path::new("dist/app/classes.jar")),
+            Path::new("dist/app/classes-error.zip")
+        );
+    }
+
+    /// Checks the translation completeness.
+    #[test]
+    fn it_completeness() {
+        let translation = Translation {
+            dex_classes: 200,
+            translated_classes: 150,
+            errors: 12,
+            top_level_classes: 100,
+            decompiled_classes: 95,
+        };
+        assert!((translation.completeness() - 75.0).abs() < f64::EPSILON);
+        assert!((translation.decompilation_completeness() - 95.0).abs() < f64::EPSILON);
+        assert!(!translation.is_incomplete());
+
+        let incomplete = Translation {
+            decompiled_classes: 40,
+            ..translation
+        };
+        assert!(incomplete.is_incomplete());
+
+        let empty = Translation {
+            dex_classes: 0,
+            translated_classes: 0,
+            errors: 0,
+            top_level_classes: 0,
+            decompiled_classes: 0,
+        };
+        assert!((empty.completeness() - 100.0).abs() < f64::EPSILON);
+        assert!(!empty.is_incomplete());
+    }
+
+    /// Checks the top-level classes of the entries of a `_.jar_` file.
+    #[test]
+    fn it_top_level_class() {
+        assert_eq!(
+            top_level_class("com/example/Main.class"),
+            Some("com/example/Main")
+        );
+        assert_eq!(
+            top_level_class("com/example/Main$1$Inner.class"),
+            Some("com/example/Main")
+        );
+        assert_eq!(top_level_class("Main$Inner.class"), Some("Main"));
+        assert_eq!(
+            top_level_class("com/ex$ample/Main.class"),
+            Some("com/ex$ample/Main")
+        );
+        assert_eq!(top_level_class("META-INF/MANIFEST.MF"), None);
+    }
+
+    /// Checks that the classes are balanced between the shards, with their nested classes.
+    #[test]
+    fn it_assign_shards() {
+        let entries = vec![
+            ("com/example/Big.class".to_owned(), 100),
+            ("com/example/Medium.class".to_owned(), 60),
+            ("com/example/Small.class".to_owned(), 30),
+            ("com/example/Small$1.class".to_owned(), 20),
+            ("com/".to_owned(), 0),
+            ("META-INF/MANIFEST.MF".to_owned(), 10),
+        ];
+
+        assert_eq!(
+            assign_shards(&entries, 2),
+            vec![Some(0), Some(1), Some(1), Some(1), None, None]
+        );
+        assert_eq!(
+            assign_shards(&entries, 1),
+            vec![Some(0), Some(0), Some(0), Some(0), None, None]
+        );
+        assert_eq!(
+            assign_shards(&entries, 5),
+            vec![Some(0), Some(1), Some(2), Some(2), None, None]
+        );
+    }
+}
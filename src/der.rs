@@ -0,0 +1,99 @@
+//! DER decoding module.
+//!
+//! Minimal reader for the DER encoded structures found in signatures and keystores. It only
+//! supports definite lengths, which is what DER requires.
+
+use failure::{bail, Error};
+
+/// Tag of integers.
+pub(crate) const INTEGER: u8 = 0x02;
+/// Tag of octet strings.
+pub(crate) const OCTET_STRING: u8 = 0x04;
+/// Tag of object identifiers.
+pub(crate) const OBJECT_IDENTIFIER: u8 = 0x06;
+/// Tag of sequences.
+pub(crate) const SEQUENCE: u8 = 0x30;
+/// Tag of the first context specific constructed element.
+pub(crate) const CONTEXT_0: u8 = 0xA0;
+
+/// Reads an element with the given tag, returning its contents and the remaining data.
+pub(crate) fn element(data: &[u8], tag: u8) -> Result<(&[u8], &[u8]), Error> {
+    match data.first() {
+        Some(t) if *t == tag => {}
+        Some(t) => bail!("expected DER tag {:#04x}, found {:#04x}", tag, t),
+        None => bail!("unexpected end of DER data"),
+    }
+    let (header_len, content_len) = length(data)?;
+    let content = &data[header_len..header_len + content_len];
+    Ok((content, &data[header_len + content_len..]))
+}
+
+/// Reads the next element, returning the complete element and the remaining data.
+pub(crate) fn next(data: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let (header_len, content_len) = length(data)?;
+    Ok(data.split_at(header_len + content_len))
+}
+
+/// Reads an unsigned integer of up to 64 bits, returning it and the remaining data.
+pub(crate) fn integer(data: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let (content, rest) = element(data, INTEGER)?;
+    // A leading zero byte can be needed to keep the number positive.
+    let content = match content.split_first() {
+        Some((0, rest)) => rest,
+        _ => content,
+    };
+    if content.len() > 8 {
+        bail!("DER integer too big");
+    }
+    let value = content
+        .iter()
+        .fold(0, |value, b| value << 8 | u64::from(*b));
+    Ok((value, rest))
+}
+
+/// Gets the header and content lengths of the element at the start of the data.
+fn length(data: &[u8]) -> Result<(usize, usize), Error> {
+    let first = match data.get(1) {
+        Some(b) => *b,
+        None => bail!("unexpected end of DER data"),
+    };
+    let (header_len, content_len) = if first < 0x80 {
+        (2, first as usize)
+    } else {
+        let bytes = (first & 0x7F) as usize;
+        if bytes == 0 || bytes > 4 || data.len() < 2 + bytes {
+            bail!("unsupported DER length");
+        }
+        let content_len = data[2..2 + bytes]
+            .iter()
+            .fold(0, |len, b| len << 8 | *b as usize);
+        (2 + bytes, content_len)
+    };
+    if data.len() < header_len + content_len {
+        bail!("unexpected end of DER data");
+    }
+    Ok((header_len, content_len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{element, integer, next, SEQUENCE};
+
+    /// Checks the reading of DER elements, with short and long lengths.
+    #[test]
+    fn it_element() {
+        let data = [0x30, 0x81, 0x03, 0x02, 0x01, 0x2A, 0x04, 0x00];
+        let (content, rest) = element(&data, SEQUENCE).unwrap();
+        assert_eq!(content, &[0x02, 0x01, 0x2A]);
+        assert_eq!(rest, &[0x04, 0x00]);
+        assert_eq!(integer(content).unwrap(), (42, &[][..]));
+
+        let (first, rest) = next(&data[3..]).unwrap();
+        assert_eq!(first, &[0x02, 0x01, 0x2A]);
+        assert_eq!(rest, &[0x04, 0x00]);
+
+        assert!(element(&data, 0x31).is_err());
+        assert!(element(&data[..5], SEQUENCE).is_err());
+        assert!(next(&[]).is_err());
+    }
+}
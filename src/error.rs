@@ -1,4 +1,6 @@
-//! Module containing the definition of error types.
+//! Module containing the definition of error types and exit codes.
+
+use failure::Error;
 
 /// Enumeration of the different error kinds.
 #[derive(Debug, Fail)]
@@ -21,4 +23,130 @@ pub enum Kind {
     /// Code not found.
     #[fail(display = "no code was found in the file")]
     CodeNotFound,
+    /// A required external tool could not be found.
+    #[fail(display = "a required tool is missing: {}", message)]
+    ToolMissing {
+        /// Error message.
+        message: String,
+    },
+    /// The analysis of some applications crashed.
+    #[fail(
+        display = "the analysis of {} of {} application(s) crashed",
+        count, total
+    )]
+    AnalysisCrashed {
+        /// Number of crashed analyses.
+        count: usize,
+        /// Number of analyzed applications.
+        total: usize,
+    },
+    /// Some rules failed to match their own code examples.
+    #[fail(display = "{} rule(s) failed their code examples", count)]
+    RuleTestFailed {
+        /// Number of rules that failed some example.
+        count: usize,
+    },
+    /// Some applications failed the configured policies.
+    #[fail(display = "{} application(s) failed the configured policies", count)]
+    PolicyFailed {
+        /// Number of applications that failed some policy.
+        count: usize,
+    },
+    /// The analysis was cancelled with a termination signal.
+    #[fail(display = "the analysis was cancelled")]
+    Cancelled,
+}
+
+/// Exit codes of the analyzer.
+///
+/// These codes are stable, so that scripts and CI systems can tell why an execution failed
+/// without parsing its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Every application was analyzed and passed the configured policies.
+    Success = 0,
+    /// The analysis failed, or an unexpected error happened.
+    AnalysisFailure = 1,
+    /// There was an error in the configuration or in the command line arguments.
+    Config = 2,
+    /// A required external tool, such as Java or Dex2Jar, could not be found.
+    ToolMissing = 3,
+    /// Some applications failed the configured policies.
+    PolicyFailure = 4,
+    /// Some applications were analyzed, but the analysis of others crashed.
+    PartialSuccess = 5,
+    /// The analysis was cancelled with `SIGINT` or `SIGTERM`.
+    Cancelled = 130,
+}
+
+impl ExitCode {
+    /// Gets the numeric exit code, to pass it to the operating system.
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+impl<'e> From<&'e Error> for ExitCode {
+    /// Gets the exit code of an error, from the first error kind found in its chain of causes.
+    fn from(error: &Error) -> Self {
+        let kind = error
+            .iter_chain()
+            .filter_map(|cause| cause.downcast_ref::<Kind>())
+            .next();
+        match kind {
+            Some(Kind::Config { .. }) | Some(Kind::TemplateName { .. }) => ExitCode::Config,
+            Some(Kind::ToolMissing { .. }) => ExitCode::ToolMissing,
+            Some(Kind::PolicyFailed { .. }) => ExitCode::PolicyFailure,
+            Some(Kind::Cancelled) => ExitCode::Cancelled,
+            Some(Kind::AnalysisCrashed { count, total }) if count < total => {
+                ExitCode::PartialSuccess
+            }
+            _ => ExitCode::AnalysisFailure,
+        }
+    }
+}
+
+/// Error testing module.
+#[cfg(test)]
+mod test {
+    use failure::{Error, ResultExt};
+
+    use super::{ExitCode, Kind};
+
+    /// Checks the exit codes of the errors, even when they are wrapped in a context.
+    #[test]
+    fn it_exit_code() {
+        let error: Error = Kind::PolicyFailed { count: 2 }.into();
+        assert_eq!(ExitCode::from(&error), ExitCode::PolicyFailure);
+        assert_eq!(ExitCode::from(&error).code(), 4);
+
+        let result: Result<(), Error> = Err(Kind::ToolMissing {
+            message: String::from("java"),
+        }
+        .into());
+        let error: Error = result
+            .context("application analysis failed")
+            .unwrap_err()
+            .into();
+        assert_eq!(ExitCode::from(&error), ExitCode::ToolMissing);
+
+        let error: Error = Kind::AnalysisCrashed { count: 1, total: 3 }.into();
+        assert_eq!(ExitCode::from(&error), ExitCode::PartialSuccess);
+        let error: Error = Kind::AnalysisCrashed { count: 3, total: 3 }.into();
+        assert_eq!(ExitCode::from(&error), ExitCode::AnalysisFailure);
+
+        let result: Result<(), Error> = Err(Kind::Cancelled.into());
+        let error: Error = result
+            .context("conversion from DEX to JAR failed")
+            .unwrap_err()
+            .into();
+        assert_eq!(ExitCode::from(&error), ExitCode::Cancelled);
+        assert_eq!(ExitCode::from(&error).code(), 130);
+
+        let error: Error = Kind::RuleTestFailed { count: 1 }.into();
+        assert_eq!(ExitCode::from(&error), ExitCode::AnalysisFailure);
+
+        let error = failure::err_msg("unexpected error");
+        assert_eq!(ExitCode::from(&error), ExitCode::AnalysisFailure);
+    }
 }
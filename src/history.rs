@@ -0,0 +1,510 @@
+//! Analysis history module.
+//!
+//! The history database keeps a record of every analyzed version of each application, so that
+//! changes between versions can be detected. It's stored as a JSON file in the results folder.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    path::Path,
+};
+
+use chrono::{DateTime, Local};
+use failure::{Error, ResultExt};
+use serde_json::{self, json, Value};
+
+use crate::{criticality::Criticality, results::Score};
+
+/// Name of the history database file in the results folder.
+pub const HISTORY_FILE: &str = "history.json";
+/// Name of the security score badge file in the report folder of each application.
+pub const SCORE_BADGE_FILE: &str = "badge.json";
+/// Name of the vulnerabilities badge file in the report folder of each application.
+pub const VULNERABILITIES_BADGE_FILE: &str = "badge-vulnerabilities.json";
+
+/// Record of an analyzed version of an application.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Version string of the application.
+    version: String,
+    /// Version number of the application.
+    version_number: u32,
+    /// SHA-256 fingerprints of the certificates that signed the application.
+    signers: Vec<String>,
+    /// Date of the analysis.
+    analyzed: DateTime<Local>,
+    /// Security score of the version, once the analysis is complete.
+    #[serde(default)]
+    score: Option<Score>,
+    /// Number of vulnerabilities of each criticality found in the version, once the analysis is
+    /// complete.
+    #[serde(default)]
+    vulnerabilities: BTreeMap<Criticality, usize>,
+    /// Attack surface of the version, if it was recorded.
+    #[serde(default)]
+    surface: Option<Surface>,
+}
+
+impl Entry {
+    /// Creates a new history entry, analyzed now.
+    pub fn new<S: Into<String>>(version: S, version_number: u32, signers: Vec<String>) -> Self {
+        Self {
+            version: version.into(),
+            version_number,
+            signers,
+            analyzed: Local::now(),
+            score: None,
+            vulnerabilities: BTreeMap::new(),
+            surface: None,
+        }
+    }
+
+    /// Gets the version string of the application.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Gets the version number of the application.
+    pub fn version_number(&self) -> u32 {
+        self.version_number
+    }
+
+    /// Gets the fingerprints of the certificates that signed the application.
+    pub fn signers(&self) -> &[String] {
+        &self.signers
+    }
+
+    /// Gets the security score of the version, if the analysis was completed.
+    pub fn score(&self) -> Option<Score> {
+        self.score
+    }
+
+    /// Gets the number of vulnerabilities of the given criticality found in the version.
+    pub fn vulnerability_count(&self, criticality: Criticality) -> usize {
+        self.vulnerabilities.get(&criticality).cloned().unwrap_or(0)
+    }
+
+    /// Gets the attack surface of the version, if it was recorded.
+    pub fn surface(&self) -> Option<&Surface> {
+        self.surface.as_ref()
+    }
+
+    /// Creates the security score badge of the version, in the
+    /// [shields.io endpoint](https://shields.io/endpoint) format.
+    pub fn score_badge(&self) -> Value {
+        let (message, color) = match self.score() {
+            Some(score) => (
+                format!("{}/100 ({})", score.value(), score.grade()),
+                match score.grade() {
+                    "A" => "brightgreen",
+                    "B" => "green",
+                    "C" => "yellow",
+                    "D" => "orange",
+                    _ => "red",
+                },
+            ),
+            None => ("unknown".to_owned(), "lightgrey"),
+        };
+
+        json!({
+            "schemaVersion": 1,
+            "label": "security score",
+            "message": message,
+            "color": color,
+        })
+    }
+
+    /// Creates the badge with the number of vulnerabilities by criticality of the version, in the
+    /// shields.io endpoint format.
+    ///
+    /// Warnings are not counted, and the color is the one of the highest criticality found.
+    pub fn vulnerabilities_badge(&self) -> Value {
+        let mut counts = Vec::new();
+        let mut color = "brightgreen";
+        for (criticality, criticality_color) in &[
+            (Criticality::Critical, "red"),
+            (Criticality::High, "orange"),
+            (Criticality::Medium, "yellow"),
+            (Criticality::Low, "yellowgreen"),
+        ] {
+            let count = self.vulnerability_count(*criticality);
+            if count > 0 {
+                if counts.is_empty() {
+                    color = *criticality_color;
+                }
+                counts.push(format!("{} {}", count, criticality));
+            }
+        }
+        let message = if counts.is_empty() {
+            "none".to_owned()
+        } else {
+            counts.join(", ")
+        };
+
+        json!({
+            "schemaVersion": 1,
+            "label": "vulnerabilities",
+            "message": message,
+            "color": color,
+        })
+    }
+}
+
+/// Attack surface of an analyzed version: the permissions it requests and the components it
+/// exports to other applications.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Surface {
+    /// Permissions requested by the application.
+    permissions: BTreeSet<String>,
+    /// Components exported by the application, as `<kind> <class name>`.
+    exported_components: BTreeSet<String>,
+}
+
+impl Surface {
+    /// Creates a new attack surface.
+    pub fn new(permissions: BTreeSet<String>, exported_components: BTreeSet<String>) -> Self {
+        Self {
+            permissions,
+            exported_components,
+        }
+    }
+
+    /// Gets the permissions requested by the application.
+    pub fn permissions(&self) -> &BTreeSet<String> {
+        &self.permissions
+    }
+
+    /// Gets the components exported by the application.
+    pub fn exported_components(&self) -> &BTreeSet<String> {
+        &self.exported_components
+    }
+
+    /// Returns true if the application requests no permissions and exports no components.
+    pub fn is_empty(&self) -> bool {
+        self.permissions.is_empty() && self.exported_components.is_empty()
+    }
+
+    /// Gets the permissions and exported components that are not in the given surface.
+    pub fn added(&self, previous: &Self) -> Self {
+        Self {
+            permissions: self
+                .permissions
+                .difference(&previous.permissions)
+                .cloned()
+                .collect(),
+            exported_components: self
+                .exported_components
+                .difference(&previous.exported_components)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Adds the permissions and exported components of the given surface to this one.
+    fn extend(&mut self, other: &Self) {
+        self.permissions.extend(other.permissions.iter().cloned());
+        self.exported_components
+            .extend(other.exported_components.iter().cloned());
+    }
+}
+
+/// History database, with the analyzed versions of each application package.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    /// Analyzed versions, ordered by version number, by application package.
+    packages: BTreeMap<String, Vec<Entry>>,
+}
+
+impl History {
+    /// Loads the history database from the given file.
+    ///
+    /// If the file does not exist, an empty history is returned.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+
+        let file = File::open(path.as_ref())?;
+        Ok(serde_json::from_reader(file).context(format!(
+            "the history database at {} is corrupted",
+            path.as_ref().display()
+        ))?)
+    }
+
+    /// Saves the history database in the given file.
+    ///
+    /// The database is written to a temporary file first, so that it does not get corrupted if
+    /// the process is interrupted.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let temp_path = path.as_ref().with_extension("json.tmp");
+        serde_json::to_writer_pretty(File::create(&temp_path)?, self)?;
+        fs::rename(&temp_path, path.as_ref())?;
+        Ok(())
+    }
+
+    /// Records a new analyzed version of the given package.
+    ///
+    /// It returns the most recent previous version of the package, if any. If the same version
+    /// had already been analyzed, the entry gets replaced, and the version before it is returned.
+    pub fn record<S: Into<String>>(&mut self, package: S, entry: Entry) -> Option<Entry> {
+        let entries = self.packages.entry(package.into()).or_insert_with(Vec::new);
+        entries.retain(|e| e.version_number != entry.version_number || e.version != entry.version);
+
+        let position = entries
+            .iter()
+            .position(|e| e.version_number > entry.version_number)
+            .unwrap_or_else(|| entries.len());
+        let previous = if position > 0 {
+            Some(entries[position - 1].clone())
+        } else {
+            None
+        };
+        entries.insert(position, entry);
+
+        previous
+    }
+
+    /// Sets the security score and the number of vulnerabilities of each criticality of an
+    /// analyzed version of the given package.
+    ///
+    /// Returns `false` if the version had not been recorded.
+    pub fn set_results<S: AsRef<str>>(
+        &mut self,
+        package: S,
+        version: &str,
+        version_number: u32,
+        score: Score,
+        vulnerabilities: BTreeMap<Criticality, usize>,
+    ) -> bool {
+        let entry = self.packages.get_mut(package.as_ref()).and_then(|entries| {
+            entries
+                .iter_mut()
+                .find(|e| e.version_number == version_number && e.version == version)
+        });
+        match entry {
+            Some(entry) => {
+                entry.score = Some(score);
+                entry.vulnerabilities = vulnerabilities;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the attack surface of an analyzed version of the given package.
+    ///
+    /// The version is recorded without signers if it had not been recorded, for example because
+    /// the application has no JAR signature.
+    pub fn set_surface<S: AsRef<str>>(
+        &mut self,
+        package: S,
+        version: &str,
+        version_number: u32,
+        surface: Surface,
+    ) {
+        let is_recorded = self
+            .entries(package.as_ref())
+            .iter()
+            .any(|e| e.version_number == version_number && e.version == version);
+        if !is_recorded {
+            let _ = self.record(
+                package.as_ref(),
+                Entry::new(version, version_number, Vec::new()),
+            );
+        }
+
+        if let Some(entry) = self.packages.get_mut(package.as_ref()).and_then(|entries| {
+            entries
+                .iter_mut()
+                .find(|e| e.version_number == version_number && e.version == version)
+        }) {
+            entry.surface = Some(surface);
+        }
+    }
+
+    /// Gets the attack surface of every version of the given package before the given version
+    /// number, combined.
+    ///
+    /// Returns `None` if no previous version has a recorded attack surface.
+    pub fn previous_surface<S: AsRef<str>>(
+        &self,
+        package: S,
+        version_number: u32,
+    ) -> Option<Surface> {
+        let mut previous: Option<Surface> = None;
+        for surface in self
+            .entries(package)
+            .iter()
+            .filter(|e| e.version_number < version_number)
+            .filter_map(Entry::surface)
+        {
+            previous
+                .get_or_insert_with(Surface::default)
+                .extend(surface);
+        }
+        previous
+    }
+
+    /// Gets the most recent version of the given package with a complete analysis.
+    pub fn latest_results<S: AsRef<str>>(&self, package: S) -> Option<&Entry> {
+        self.entries(package)
+            .iter()
+            .rev()
+            .find(|entry| entry.score.is_some())
+    }
+
+    /// Gets the recorded versions of the given package, ordered by version number.
+    pub fn entries<S: AsRef<str>>(&self, package: S) -> &[Entry] {
+        self.packages
+            .get(package.as_ref())
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::{Entry, History, Surface};
+    use crate::{config::Config, criticality::Criticality, results::Score};
+
+    /// Checks that versions are recorded in order and that the previous version is returned.
+    #[test]
+    fn it_record() {
+        let mut history = History::default();
+        let signer = vec!["aa".to_owned()];
+
+        assert!(history
+            .record("com.example", Entry::new("1.0", 1, signer.clone()))
+            .is_none());
+        let previous = history
+            .record("com.example", Entry::new("3.0", 3, vec!["bb".to_owned()]))
+            .unwrap();
+        assert_eq!(previous.version(), "1.0");
+
+        // Older versions analyzed later are compared with the version right before them.
+        let previous = history
+            .record("com.example", Entry::new("2.0", 2, signer.clone()))
+            .unwrap();
+        assert_eq!(previous.version_number(), 1);
+
+        // Analyzing the same version again replaces it.
+        let previous = history
+            .record("com.example", Entry::new("3.0", 3, signer))
+            .unwrap();
+        assert_eq!(previous.version_number(), 2);
+
+        let versions: Vec<_> = history
+            .entries("com.example")
+            .iter()
+            .map(Entry::version_number)
+            .collect();
+        assert_eq!(versions, vec![1, 2, 3]);
+        assert_eq!(history.entries("com.example")[2].signers(), &["aa"]);
+        assert!(history.entries("com.other").is_empty());
+    }
+
+    /// Checks that the analysis results are only set for recorded versions.
+    #[test]
+    fn it_set_results() {
+        let mut history = History::default();
+        let score = Score::new(&Config::default(), |_| 0);
+        let mut vulnerabilities = BTreeMap::new();
+        let _ = vulnerabilities.insert(Criticality::High, 2);
+        let _ = history.record("com.example", Entry::new("1.0", 1, Vec::new()));
+        let _ = history.record("com.example", Entry::new("2.0", 2, Vec::new()));
+
+        assert!(!history.set_results("com.example", "3.0", 3, score, BTreeMap::new()));
+        assert!(!history.set_results("com.other", "1.0", 1, score, BTreeMap::new()));
+        assert!(history.latest_results("com.example").is_none());
+
+        assert!(history.set_results("com.example", "1.0", 1, score, vulnerabilities));
+        let latest = history.latest_results("com.example").unwrap();
+        assert_eq!(latest.version(), "1.0");
+        assert_eq!(latest.score(), Some(score));
+        assert_eq!(latest.vulnerability_count(Criticality::High), 2);
+        assert_eq!(latest.vulnerability_count(Criticality::Low), 0);
+    }
+
+    /// Checks that the attack surface of the previous versions is combined.
+    #[test]
+    fn it_previous_surface() {
+        let surface = |permissions: &[&str], components: &[&str]| {
+            Surface::new(
+                permissions.iter().map(|p| (*p).to_owned()).collect(),
+                components.iter().map(|c| (*c).to_owned()).collect(),
+            )
+        };
+        let mut history = History::default();
+        let _ = history.record("com.example", Entry::new("1.0", 1, vec!["aa".to_owned()]));
+        assert!(history.previous_surface("com.example", 2).is_none());
+
+        history.set_surface("com.example", "1.0", 1, surface(&["CAMERA"], &[]));
+        history.set_surface(
+            "com.example",
+            "2.0",
+            2,
+            surface(&["INTERNET"], &["activity .Main"]),
+        );
+        history.set_surface(
+            "com.example",
+            "3.0",
+            3,
+            surface(&["CAMERA", "READ_SMS"], &[]),
+        );
+        assert_eq!(history.entries("com.example").len(), 3);
+        assert_eq!(history.entries("com.example")[0].signers(), &["aa"]);
+        assert!(history.entries("com.example")[1].signers().is_empty());
+
+        let previous = history.previous_surface("com.example", 3).unwrap();
+        assert_eq!(
+            previous,
+            surface(&["CAMERA", "INTERNET"], &["activity .Main"])
+        );
+        let added = history.entries("com.example")[2]
+            .surface()
+            .unwrap()
+            .added(&previous);
+        assert_eq!(added, surface(&["READ_SMS"], &[]));
+        assert!(surface(&["CAMERA"], &[]).added(&previous).is_empty());
+    }
+
+    /// Checks the shields.io endpoint badges of an analyzed version.
+    #[test]
+    fn it_badges() {
+        let mut history = History::default();
+        let _ = history.record("com.example", Entry::new("1.0", 1, Vec::new()));
+        let mut vulnerabilities = BTreeMap::new();
+        let _ = vulnerabilities.insert(Criticality::High, 1);
+        let _ = vulnerabilities.insert(Criticality::Low, 3);
+        let _ = vulnerabilities.insert(Criticality::Warning, 5);
+        let score = Score::new(&Config::default(), |criticality| match criticality {
+            Criticality::High => 1,
+            Criticality::Low => 3,
+            _ => 0,
+        });
+        assert!(history.set_results("com.example", "1.0", 1, score, vulnerabilities));
+        let entry = history.latest_results("com.example").unwrap();
+
+        assert_eq!(
+            entry.score_badge(),
+            json!({
+                "schemaVersion": 1,
+                "label": "security score",
+                "message": "87/100 (B)",
+                "color": "green",
+            })
+        );
+        assert_eq!(
+            entry.vulnerabilities_badge(),
+            json!({
+                "schemaVersion": 1,
+                "label": "vulnerabilities",
+                "message": "1 high, 3 low",
+                "color": "orange",
+            })
+        );
+    }
+}
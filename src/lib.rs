@@ -37,18 +37,28 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
+mod arsc;
+pub mod audit;
+mod axml;
+pub mod cancel;
 pub mod cli;
 mod config;
 mod criticality;
 mod decompilation;
+mod der;
 pub mod error;
+mod history;
+mod profile;
 mod results;
+pub mod rpc;
 mod static_analysis;
 mod utils;
+mod workspace;
 
 use std::{
     collections::BTreeMap,
-    env, fs,
+    env,
+    fs::{self, File},
     path::Path,
     thread::sleep,
     time::{Duration, Instant},
@@ -60,15 +70,30 @@ use failure::{bail, format_err, Error, ResultExt};
 
 pub use crate::{
     config::Config,
+    criticality::Criticality,
+    decompilation::Translation,
+    profile::Profile,
+    results::{
+        Annotations, Confidence, Diff, Engagement, Index, PermissionNotes, PrintSelector, Results,
+        RuleStatistics, Score, Section, Summary, Vulnerability, VulnerabilityBuilder,
+    },
+    static_analysis::{
+        check::Check,
+        libraries::Origin,
+        manifest::{Component, ComponentKind, IntentFilter, Manifest},
+    },
     utils::{
         get_code, get_package_name, get_string, print_vulnerability, print_warning, Benchmark,
         PARSER_CONFIG,
     },
 };
 use crate::{
-    decompilation::{decompile, decompress, dex_to_jar},
-    results::Results,
-    static_analysis::static_analysis,
+    decompilation::{decompile, decompile_payloads, decompress, dex_to_jar},
+    history::{History, HISTORY_FILE, SCORE_BADGE_FILE, VULNERABILITIES_BADGE_FILE},
+    results::{document_template_vars, sample_results},
+    static_analysis::{code::read_rules, static_analysis},
+    utils::PackageKind,
+    workspace::Workspace,
 };
 
 /// Logo ASCII art, used in verbose mode.
@@ -76,29 +101,44 @@ pub static BANNER: &str = include_str!("banner.txt");
 
 /// Initialize the config with the config files and command line options.
 ///
-/// On UNIX, if local file, `config.toml`, does not exist, but the global one does
+/// If the `--config` option is given, its file is used. Otherwise, on UNIX, if local file,
+/// `config.toml`, does not exist, but the global one does
 /// `/etc/super-analyzer/config.toml`, the latter is used. Otherwise, the local file
 /// is used. Finally, if non of the files could be loaded, the default configuration
 /// is used. This default configuration contains the minimal setup for running the
 /// analysis.
 ///
-/// It will then add the configuration selected with the command line interface options.
+/// The settings are then overridden by the named profile selected with `--profile`, if it's
+/// defined in the file, by the `SUPER_*` environment variables, and finally by the configuration
+/// selected with the command line interface options.
 pub fn initialize_config(cli: &ArgMatches<'static>) -> Result<Config, Error> {
     let config_path = Path::new("config.toml");
     let global_config_path = Path::new("/etc/super-analyzer/config.toml");
 
-    let mut config =
-        if cfg!(target_family = "unix") && !config_path.exists() && global_config_path.exists() {
-            Config::from_file(&global_config_path).context(
-                "there was an error when reading the /etc/super-analyzer/config.toml file",
-            )?
-        } else if config_path.exists() {
-            Config::from_file(&config_path)
-                .context("there was an error when reading the config.toml file")?
-        } else {
-            print_warning("config file not found. Using default configuration");
-            Config::default()
-        };
+    let config_path = if let Some(config_path) = cli.value_of("config") {
+        // An explicit configuration file must exist, so it's never replaced by the defaults.
+        Some(Path::new(config_path))
+    } else if cfg!(target_family = "unix") && !config_path.exists() && global_config_path.exists() {
+        Some(global_config_path)
+    } else if config_path.exists() {
+        Some(config_path)
+    } else {
+        print_warning("config file not found. Using default configuration");
+        None
+    };
+
+    // The selected named profile and the `SUPER_*` environment variables override the file, and
+    // the CLI options override them. Variables that are not valid Unicode can't be settings.
+    let environment = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    let mut config = Config::from_sources(config_path, cli.value_of("profile"), environment)
+        .context(match config_path {
+            Some(path) => format!(
+                "there was an error when reading the {} file",
+                path.display()
+            ),
+            None => "there was an error when reading the configuration".to_owned(),
+        })?;
 
     config
         .decorate_with_cli(cli)
@@ -108,12 +148,16 @@ pub fn initialize_config(cli: &ArgMatches<'static>) -> Result<Config, Error> {
 }
 
 /// Analyzes the given package with the given configuration.
+///
+/// The external tools write their scratch files in a temporary workspace of the analysis, in the
+/// configured temporary folder, that is removed once the analysis ends. It returns the summary of
+/// the results, with the configured policies that the application failed.
 #[allow(clippy::print_stdout)]
 pub fn analyze_package<P: AsRef<Path>>(
     package: P,
     config: &mut Config,
     benchmarks: &mut BTreeMap<String, Vec<Benchmark>>,
-) -> Result<(), Error> {
+) -> Result<Summary, Error> {
     let package_name = get_package_name(&package);
     if config.is_bench() {
         let _ = benchmarks.insert(package_name.clone(), Vec::with_capacity(4));
@@ -123,59 +167,88 @@ pub fn analyze_package<P: AsRef<Path>>(
         println!("Starting analysis of {}.", package_name.italic());
     }
 
-    // Apk decompression.
-    let start_time = Instant::now();
-    decompress(config, &package).context("apk decompression failed")?;
-
-    if config.is_bench() {
-        benchmarks
-            .get_mut(&package_name)
-            .unwrap()
-            .push(Benchmark::new("Apk decompression", start_time.elapsed()));
-    }
-
-    // Converting the .dex to .jar.
-    let dex_jar_time = Instant::now();
-    dex_to_jar(config, &package).context("conversion from DEX to JAR failed")?;
-
-    if config.is_bench() {
-        benchmarks
-            .get_mut(&package_name)
-            .unwrap()
-            .push(Benchmark::new(
-                "Dex to Jar decompilation (dex2jar Java dependency)",
-                dex_jar_time.elapsed(),
-            ));
-    }
-
-    if config.is_verbose() {
-        println!();
-        println!(
-            "Now it's time for the actual decompilation of the source code. We'll translate
-             Android JVM bytecode to Java, so that we can check the code afterwards."
-        );
-    }
+    // The scratch files of the external tools are isolated from the ones of other analyses.
+    let workspace = Workspace::create(config, &package_name)?;
+    config.set_workspace(workspace.path());
+    let result = analyze_in_workspace(package.as_ref(), config, benchmarks);
+    config.reset_workspace();
+    result
+}
 
-    // Decompiling the app
-    let decompile_start = Instant::now();
-    decompile(config, &package).context("JAR decompression failed")?;
+/// Analyzes the given package in the temporary workspace set in the configuration.
+#[allow(clippy::print_stdout)]
+fn analyze_in_workspace(
+    package: &Path,
+    config: &mut Config,
+    benchmarks: &mut BTreeMap<String, Vec<Benchmark>>,
+) -> Result<Summary, Error> {
+    let start_time = Instant::now();
+    let package_name = get_package_name(package);
+    let kind = PackageKind::from_path(package);
 
-    if config.is_bench() {
-        benchmarks
-            .get_mut(&package_name)
-            .unwrap()
-            .push(Benchmark::new(
-                "Decompilation (jd-cli Java dependency)",
-                decompile_start.elapsed(),
-            ));
+    // The decompiled files are removed if the analysis is cancelled before they are complete,
+    // but only if this run wrote them, so that the complete files of a previous run are kept.
+    let dist_folder = cancel::PartialFolder::new(config.dist_folder().join(&package_name));
+    let decompiled = decompile_package(package, config, benchmarks);
+    // Every stage that writes in the decompression folder sets the force flag.
+    if decompiled.is_ok() || !config.is_force() {
+        dist_folder.complete();
     }
+    decompiled?;
 
     // Initialize results structure
     let mut results = Results::init(config, &package)?;
 
+    // Bare JAR files have no DEX file to compare their translation with, and the translation
+    // can't be checked if the code was not decompiled.
+    if kind != PackageKind::Jar && config.is_code_needed() {
+        match Translation::from_package(config, &package) {
+            Ok(translation) => {
+                if translation.is_incomplete() {
+                    print_warning(format!(
+                        "only {:.1}% of the classes of the application could be decompiled, so \
+                         the analysis can miss vulnerabilities in the rest of the code",
+                        translation.decompilation_completeness()
+                    ));
+                    let criticality = Criticality::Medium;
+                    if criticality >= config.min_criticality() {
+                        results.add_vulnerability(
+                            VulnerabilityBuilder::new(
+                                criticality,
+                                "Incomplete decompilation",
+                                format!(
+                                    "Only {} of the {} classes of the application ({:.1}%) \
+                                     could be decompiled. The rest of the code was not analyzed, \
+                                     so vulnerabilities in it are not reported. This is common \
+                                     in obfuscated or protected applications.",
+                                    translation.decompiled_classes(),
+                                    translation.top_level_classes(),
+                                    translation.decompilation_completeness()
+                                ),
+                            )
+                            .confidence(Confidence::High)
+                            .build(),
+                        );
+                    }
+                } else if translation.completeness() < 100.0 {
+                    print_warning(format!(
+                        "only {:.1}% of the classes of the application could be translated, so \
+                         part of the code won't be analyzed",
+                        translation.completeness()
+                    ));
+                }
+                results.set_translation(translation);
+            }
+            Err(e) => print_warning(format!(
+                "could not compute the translation completeness of the application: {}",
+                e
+            )),
+        }
+    }
+
     // Static application analysis
     let static_start = Instant::now();
-    static_analysis(config, &package_name, &mut results);
+    static_analysis(config, &package_name, kind, &mut results);
 
     if config.is_bench() {
         benchmarks
@@ -191,14 +264,51 @@ pub fn analyze_package<P: AsRef<Path>>(
         println!();
     }
 
+    // The partial results of a cancelled analysis are still reported, but they are not recorded
+    // in the history database, since they would be mistaken for fixed vulnerabilities.
+    let cancelled = cancel::is_cancelled();
+    if cancelled {
+        results.set_cancelled();
+    }
+    results.apply_suppressions(config);
+    results.compute_score(config);
+    results.evaluate_policies(config);
+    print_selected(config, &results);
+    // The notes of the analysts about previous reports are kept in the new ones.
+    let annotations_path = Annotations::path_for(
+        config
+            .report_format_folder(config.report_folder(results.app_package()), "json")
+            .join(format!("{}.json", config.report_file_name())),
+    );
+    match Annotations::load(&annotations_path) {
+        Ok(Some(annotations)) => results.set_annotations(annotations),
+        Ok(None) => {}
+        Err(e) => print_warning(format!(
+            "the annotations of the previous report could not be loaded: {}",
+            e
+        )),
+    }
+    if !cancelled {
+        if let Err(e) = record_results(config, &results) {
+            print_warning(format!(
+                "could not update the history database with the results of the application: {}",
+                e
+            ));
+        }
+    }
+
     // Generate results report.
     let report_start = Instant::now();
     results
         .generate_report(config, &package_name)
         .context(format_err!(
             "there was an error generating the results report at: {}",
-            config.results_folder().join(&package_name).display()
+            config.report_folder(results.app_package()).display()
         ))?;
+    write_badges(config, results.app_package());
+    if cancelled {
+        return Err(error::Kind::Cancelled.into());
+    }
 
     if config.is_verbose() {
         println!("Everything went smoothly, you can now check all the results.");
@@ -226,16 +336,15 @@ pub fn analyze_package<P: AsRef<Path>>(
     }
 
     if config.is_open() {
+        let report_folder = config.report_folder(results.app_package());
         let open_path = if config.has_to_generate_html() {
             config
-                .results_folder()
-                .join(results.app_package())
+                .report_format_folder(&report_folder, "html")
                 .join("index.html")
         } else {
             config
-                .results_folder()
-                .join(results.app_package())
-                .join("results.json")
+                .report_format_folder(&report_folder, "json")
+                .join(format!("{}.json", config.report_file_name()))
         };
 
         let status =
@@ -246,9 +355,438 @@ pub fn analyze_package<P: AsRef<Path>>(
         }
     }
 
+    Ok(Summary::new(config, &results))
+}
+
+/// Decompresses and decompiles the given package in its decompression folder, along with the
+/// executable payloads hidden in its resources.
+#[allow(clippy::print_stdout)]
+fn decompile_package(
+    package: &Path,
+    config: &mut Config,
+    benchmarks: &mut BTreeMap<String, Vec<Benchmark>>,
+) -> Result<(), Error> {
+    let package_name = get_package_name(package);
+
+    // Apk decompression.
+    let start_time = Instant::now();
+    decompress(config, &package).context("apk decompression failed")?;
+    cancel::check()?;
+
+    if config.is_bench() {
+        benchmarks
+            .get_mut(&package_name)
+            .unwrap()
+            .push(Benchmark::new("Apk decompression", start_time.elapsed()));
+    }
+
+    // The code is only decompiled if an enabled check reads it.
+    if config.is_code_needed() {
+        // Converting the .dex to .jar.
+        let dex_jar_time = Instant::now();
+        dex_to_jar(config, &package).context("conversion from DEX to JAR failed")?;
+        cancel::check()?;
+
+        if config.is_bench() {
+            benchmarks
+                .get_mut(&package_name)
+                .unwrap()
+                .push(Benchmark::new(
+                    "Dex to Jar decompilation (dex2jar Java dependency)",
+                    dex_jar_time.elapsed(),
+                ));
+        }
+
+        if config.is_verbose() {
+            println!();
+            println!(
+                "Now it's time for the actual decompilation of the source code. We'll translate
+                 Android JVM bytecode to Java, so that we can check the code afterwards."
+            );
+        }
+
+        // Decompiling the app
+        let decompile_start = Instant::now();
+        decompile(config, &package).context("JAR decompression failed")?;
+        cancel::check()?;
+
+        if config.is_bench() {
+            benchmarks
+                .get_mut(&package_name)
+                .unwrap()
+                .push(Benchmark::new(
+                    "Decompilation (jd-cli Java dependency)",
+                    decompile_start.elapsed(),
+                ));
+        }
+    }
+
+    // Decompiling the executable payloads hidden in the resources.
+    if config.is_check_enabled(Check::Payloads) {
+        let payloads_start = Instant::now();
+        decompile_payloads(config, &package).context("payload decompilation failed")?;
+        cancel::check()?;
+
+        if config.is_bench() {
+            benchmarks
+                .get_mut(&package_name)
+                .unwrap()
+                .push(Benchmark::new(
+                    "Payload decompilation",
+                    payloads_start.elapsed(),
+                ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the reports of a previous analysis from its JSON results.
+///
+/// The application is not analyzed again, so the reports can be generated in other formats
+/// from archived results. If more than one results file is given, they are merged into a single
+/// report. Existing reports of the application are replaced. It returns the summary of the
+/// results, with the configured policies that the application failed.
+#[allow(clippy::print_stdout)]
+pub fn regenerate_report<P: AsRef<Path>>(
+    results_files: &[P],
+    config: &mut Config,
+) -> Result<Summary, Error> {
+    let mut results: Option<Results> = None;
+    for results_file in results_files {
+        let mut file_results = load_results(results_file)?;
+        // The annotations file is more recent than the annotations stored in the results.
+        if let Some(annotations) = Annotations::load(Annotations::path_for(results_file))? {
+            file_results.set_annotations(annotations);
+        }
+        match results {
+            Some(ref mut results) => results.merge(file_results).context(format_err!(
+                "could not merge the results file at {}",
+                results_file.as_ref().display()
+            ))?,
+            None => results = Some(file_results),
+        }
+    }
+    let mut results = match results {
+        Some(results) => results,
+        None => bail!("no results files were given"),
+    };
+    results.set_engagement(config);
+    results.apply_suppressions(config);
+    results.compute_score(config);
+    results.evaluate_policies(config);
+    print_selected(config, &results);
+
+    if !config.is_quiet() {
+        println!(
+            "Generating the reports of {}.",
+            results.app_package().italic()
+        );
+    }
+
+    config.set_force();
+    results
+        .generate_report(config, results.app_package())
+        .context(format_err!(
+            "there was an error generating the results report at: {}",
+            config.report_folder(results.app_package()).display()
+        ))?;
+    write_badges(config, results.app_package());
+
+    Ok(Summary::new(config, &results))
+}
+
+/// Annotates the results of a previous analysis with the notes of an analyst.
+///
+/// The notes are stored in the annotations file next to the results file, replacing the current
+/// notes, or appended to them if `append` is set. They are shown in the reports generated from
+/// the results from then on.
+#[allow(clippy::print_stdout)]
+pub fn annotate_report<P: AsRef<Path>, S: AsRef<str>>(
+    results_file: P,
+    notes: S,
+    append: bool,
+) -> Result<(), Error> {
+    // The results are loaded to check that the annotations are for valid results.
+    let results = load_results(results_file.as_ref())?;
+    let path = Annotations::path_for(results_file.as_ref());
+    let mut annotations = Annotations::load(&path)?
+        .or_else(|| results.annotations().cloned())
+        .unwrap_or_default();
+    annotations.annotate(notes, append);
+    annotations.save(&path)?;
+
+    println!(
+        "The notes about {} were saved to {}. Generate the reports again to include them.",
+        results.app_package().italic(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Prints the variables available to the handlebars templates, as documented JSON.
+///
+/// The variables are generated from the given results file, or from a sample analysis if none is
+/// given, with the same serialization used to render the HTML report, so that every field that
+/// the results produce is listed with its type and an example value.
+#[allow(clippy::print_stdout)]
+pub fn template_vars<P: AsRef<Path>>(
+    results_file: Option<P>,
+    config: &Config,
+) -> Result<(), Error> {
+    let mut results = match results_file {
+        Some(results_file) => load_results(results_file)?,
+        None => sample_results()?,
+    };
+    results.apply_suppressions(config);
+    results.compute_score(config);
+    results.evaluate_policies(config);
+
+    let context = serde_json::to_value(&results)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&document_template_vars(&context))?
+    );
+    Ok(())
+}
+
+/// Compares the JSON results of two analyses of the same application.
+///
+/// It prints the vulnerabilities that are only found in the new analysis, the ones that were
+/// fixed and the number of unchanged ones. If `json` is set, the comparison is printed in JSON
+/// format instead, so that it can be processed by other tools.
+#[allow(clippy::print_stdout)]
+pub fn compare_results<P: AsRef<Path>, Q: AsRef<Path>>(
+    old_results_file: P,
+    new_results_file: Q,
+    json: bool,
+) -> Result<(), Error> {
+    let old = load_results(old_results_file)?;
+    let new = load_results(new_results_file)?;
+    let diff = Diff::new(&old, &new).context("the results could not be compared")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!(
+        "Comparing version {} of {} with version {}.",
+        diff.old_version().italic(),
+        diff.app_package().italic(),
+        diff.new_version().italic()
+    );
+    println!();
+    if !diff.new_permissions().is_empty() {
+        println!(
+            "{}",
+            format!("{} new permissions:", diff.new_permissions().len())
+                .bold()
+                .red()
+        );
+        for permission in diff.new_permissions() {
+            println!("{}", format!("+ {}", permission).red());
+        }
+        println!();
+    }
+    println!(
+        "{}",
+        format!("{} new vulnerabilities:", diff.new_vulnerabilities().len()).bold()
+    );
+    for vulnerability in diff.new_vulnerabilities() {
+        println!("{}", format_diff_line("+", vulnerability).red());
+    }
+    println!();
+    println!(
+        "{}",
+        format!(
+            "{} fixed vulnerabilities:",
+            diff.fixed_vulnerabilities().len()
+        )
+        .bold()
+    );
+    for vulnerability in diff.fixed_vulnerabilities() {
+        println!("{}", format_diff_line("-", vulnerability).green());
+    }
+    println!();
+    println!(
+        "{}",
+        format!(
+            "{} unchanged vulnerabilities.",
+            diff.unchanged_vulnerabilities().len()
+        )
+        .bold()
+    );
+
     Ok(())
 }
 
+/// Tests the configured rules against their code examples.
+///
+/// It prints the result of each rule with examples, and the reasons of the failed examples. It
+/// returns the number of rules that failed some example.
+#[allow(clippy::print_stdout)]
+pub fn test_rules(config: &Config) -> Result<usize, Error> {
+    let rules = read_rules(config).context("the rules could not be loaded")?;
+
+    let mut tested = 0;
+    let mut failed = 0;
+    for rule in rules.iter().filter(|rule| rule.has_examples()) {
+        tested += 1;
+        let failures = rule.test_examples();
+        if failures.is_empty() {
+            println!("{} {}", "ok".green(), rule.label());
+        } else {
+            failed += 1;
+            println!("{} {}", "FAILED".red(), rule.label());
+            for failure in failures {
+                println!("    - {}", failure);
+            }
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        format!(
+            "{} rules tested, {} failed, {} without examples.",
+            tested,
+            failed,
+            rules.len() - tested
+        )
+        .bold()
+    );
+
+    Ok(failed)
+}
+
+/// Lists the analysis checks, with their description and whether they run with the given
+/// configuration.
+#[allow(clippy::print_stdout)]
+pub fn list_checks(config: &Config) {
+    for check in Check::all().iter() {
+        let name = format!("{:20}", check.name());
+        if config.is_check_enabled(*check) {
+            println!("{} {}", name.green(), check.description());
+        } else {
+            println!(
+                "{} {} {}",
+                name.dimmed(),
+                check.description(),
+                "(disabled)".dimmed()
+            );
+        }
+    }
+}
+
+/// Formats a vulnerability as a line of the results comparison, with the given marker.
+fn format_diff_line(marker: &str, vulnerability: &Vulnerability) -> String {
+    let mut line = format!(
+        "{} [{}] {}",
+        marker,
+        vulnerability.get_criticality(),
+        vulnerability.get_name()
+    );
+    if let Some(file) = vulnerability.get_file() {
+        line.push_str(&format!(" ({}", file.display()));
+        if let Some(start_line) = vulnerability.get_start_line() {
+            line.push_str(&format!(":{}", start_line + 1));
+        }
+        line.push(')');
+    }
+    line
+}
+
+/// Records the security score and the vulnerability counts of the analyzed version of the
+/// application in the history database, so that they are available with the rest of the analyzed
+/// applications.
+fn record_results(config: &Config, results: &Results) -> Result<(), Error> {
+    let score = match results.score() {
+        Some(score) => score,
+        None => return Ok(()),
+    };
+    let history_path = config.results_folder().join(HISTORY_FILE);
+    let mut history = History::load(&history_path)?;
+    let vulnerabilities = [
+        Criticality::Critical,
+        Criticality::High,
+        Criticality::Medium,
+        Criticality::Low,
+        Criticality::Warning,
+    ]
+    .iter()
+    .map(|criticality| (*criticality, results.vulnerability_count(*criticality)))
+    .collect();
+    if history.set_results(
+        results.app_package(),
+        results.app_version(),
+        results.app_version_num(),
+        score,
+        vulnerabilities,
+    ) {
+        history.save(&history_path)?;
+    }
+    Ok(())
+}
+
+/// Writes the [shields.io endpoint](https://shields.io/endpoint) badges of the latest complete
+/// analysis of the application in its report folder, so that any static server can host them.
+///
+/// They are written after the reports, since the report folder is replaced when the reports are
+/// generated.
+fn write_badges(config: &Config, package: &str) {
+    let history = match History::load(config.results_folder().join(HISTORY_FILE)) {
+        Ok(history) => history,
+        Err(e) => {
+            print_warning(format!(
+                "the badges of the application could not be written: {}",
+                e
+            ));
+            return;
+        }
+    };
+    let entry = match history.latest_results(package) {
+        Some(entry) => entry,
+        None => return,
+    };
+    let folder = config.report_folder(package);
+    for (file_name, badge) in &[
+        (SCORE_BADGE_FILE, entry.score_badge()),
+        (VULNERABILITIES_BADGE_FILE, entry.vulnerabilities_badge()),
+    ] {
+        if let Err(e) = fs::create_dir_all(&folder)
+            .and_then(|_| fs::write(folder.join(file_name), badge.to_string()))
+        {
+            print_warning(format!(
+                "the `{}` badge of the application could not be written: {}",
+                file_name, e
+            ));
+        }
+    }
+}
+
+/// Prints the facts of the results selected in the configuration, one per line.
+#[allow(clippy::print_stdout)]
+fn print_selected(config: &Config, results: &Results) {
+    for selector in config.print_selectors() {
+        for line in selector.lines(results) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Loads the results of a previous analysis from its JSON results file.
+fn load_results<P: AsRef<Path>>(results_file: P) -> Result<Results, Error> {
+    let file = File::open(results_file.as_ref()).context(format_err!(
+        "could not open the results file at {}",
+        results_file.as_ref().display()
+    ))?;
+    let results = Results::from_json(file).context(format_err!(
+        "the results file at {} is not valid",
+        results_file.as_ref().display()
+    ))?;
+    Ok(results)
+}
+
 /// Copies the contents of `from` to `to`
 ///
 /// If the destination folder doesn't exist is created. Note that the parent folder must exist. If
@@ -341,8 +879,33 @@ pub fn initialize_logger(is_verbose: bool) -> Result<(), log::SetLoggerError> {
 mod tests {
     use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
 
-    use super::analyze_package;
-    use crate::{config::Config, criticality::Criticality};
+    use super::{analyze_package, initialize_config};
+    use crate::{cli, config::Config, criticality::Criticality};
+
+    /// Checks that the configuration file given with `--config` is loaded and recorded.
+    #[test]
+    fn it_initialize_config_with_file() {
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--config",
+            "config.toml.sample",
+            "com.example",
+        ]);
+        let config = initialize_config(&cli).unwrap();
+        assert_eq!(config.threads(), 2);
+        assert_eq!(
+            config.loaded_config_files().collect::<Vec<_>>(),
+            vec![Path::new("config.toml.sample")]
+        );
+
+        let cli = cli::generate().get_matches_from(vec![
+            "super-analyzer",
+            "--config",
+            "missing.toml",
+            "com.example",
+        ]);
+        assert!(initialize_config(&cli).is_err());
+    }
 
     /// This tests checks that the `Criticality` enumeration works as expected.
     ///
@@ -461,7 +1024,9 @@ mod tests {
         config.add_app_package("downloads/test_app");
 
         // Run the analysis
-        analyze_package("downloads/test_app.apk", &mut config, &mut benchmarks).unwrap();
+        let summary =
+            analyze_package("downloads/test_app.apk", &mut config, &mut benchmarks).unwrap();
+        assert!(summary.failed_policies().is_empty());
 
         // TODO: check results.
 
@@ -23,8 +23,12 @@
 extern crate log;
 
 use std::{
+    any::Any,
     collections::BTreeMap,
+    fs,
     io::{self, Write},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
     thread::sleep,
     time::{Duration, Instant},
 };
@@ -34,13 +38,16 @@ use failure::{Error, ResultExt};
 use log::Level;
 
 use super_analyzer_core::{
-    analyze_package, cli, error, initialize_config, initialize_logger, Benchmark, BANNER,
+    analyze_package, annotate_report, audit, cancel, cli, compare_results,
+    error::{self, ExitCode},
+    initialize_config, initialize_logger, list_checks, regenerate_report, rpc, template_vars,
+    test_rules, Benchmark, Index, RuleStatistics, BANNER,
 };
 
 /// Program entry point.
 ///
 /// This function will just call the `run()` function and report any fatal error that comes out
-/// of it. It will also exit with a non-zero exit code if things go wrong.
+/// of it. It will also exit with the non-zero exit code of the error if things go wrong.
 fn main() {
     // Call the `run()` function and check for errors.
     if let Err(e) = run() {
@@ -60,8 +67,8 @@ fn main() {
             );
         }
 
-        // Exit with a non-zero exit code.
-        ::std::process::exit(1);
+        // Exit with the exit code of the error, so that scripts can tell what went wrong.
+        ::std::process::exit(ExitCode::from(&e).code());
     }
 }
 
@@ -72,13 +79,108 @@ fn main() {
 /// results.
 fn run() -> Result<(), Error> {
     // Check the CLI arguments.
-    let cli = cli::generate().get_matches();
-    let verbose = cli.is_present("verbose");
+    let cli = cli::generate().get_matches_safe().unwrap_or_else(|e| {
+        // The help and version messages are not errors.
+        if !e.use_stderr() {
+            e.exit();
+        }
+        eprintln!("{}", e.message);
+        ::std::process::exit(ExitCode::Config.code());
+    });
+    let rpc_mode = cli.subcommand_matches("rpc").is_some();
+    // The standard output is the communication channel in RPC mode, so no verbose output can be
+    // printed there.
+    let verbose = cli.is_present("verbose") && !rpc_mode;
     // Initialize all logger, specifying if the user wanted verbose mode.
     initialize_logger(verbose).context("could not initialize the logger")?;
 
     // Load the configuration.
-    let mut config = initialize_config(&cli)?;
+    let mut config = initialize_config(&cli).map_err(|e| error::Kind::Config {
+        message: e
+            .iter_chain()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(": "),
+    })?;
+
+    if rpc_mode {
+        rpc::serve(&config).context("the JSON-RPC server failed")?;
+        return Ok(());
+    }
+
+    if let Some(annotate) = cli
+        .subcommand_matches("report")
+        .and_then(|report| report.subcommand_matches("annotate"))
+    {
+        let notes = match annotate.value_of("notes-file") {
+            Some(notes_file) => fs::read_to_string(notes_file)
+                .context(format!("could not read the notes file at {}", notes_file))?,
+            None => annotate
+                .value_of("note")
+                .expect("the notes are required")
+                .to_owned(),
+        };
+        annotate_report(
+            annotate
+                .value_of("results-file")
+                .expect("the results file is required"),
+            notes,
+            annotate.is_present("append"),
+        )
+        .context("report annotation failed")?;
+        return Ok(());
+    }
+
+    if let Some(report) = cli.subcommand_matches("report") {
+        let results_files: Vec<_> = report
+            .values_of("results-file")
+            .expect("the results file is required")
+            .collect();
+        let summary =
+            regenerate_report(&results_files, &mut config).context("report generation failed")?;
+        if !summary.failed_policies().is_empty() {
+            print_failed_policies(&[(
+                PathBuf::from(results_files[0]),
+                summary.failed_policies().to_vec(),
+            )]);
+            return Err(error::Kind::PolicyFailed { count: 1 }.into());
+        }
+        return Ok(());
+    }
+
+    if let Some(diff) = cli.subcommand_matches("diff") {
+        compare_results(
+            diff.value_of("old-results-file")
+                .expect("the old results file is required"),
+            diff.value_of("new-results-file")
+                .expect("the new results file is required"),
+            diff.is_present("json"),
+        )
+        .context("results comparison failed")?;
+        return Ok(());
+    }
+
+    if let Some(vars) = cli
+        .subcommand_matches("template")
+        .and_then(|template| template.subcommand_matches("vars"))
+    {
+        template_vars(vars.value_of("results-file"), &config)
+            .context("template variable generation failed")?;
+        return Ok(());
+    }
+
+    if cli.subcommand_matches("list-checks").is_some() {
+        list_checks(&config);
+        return Ok(());
+    }
+
+    if cli.subcommand_matches("test-rules").is_some() {
+        let failed = test_rules(&config).context("rule testing failed")?;
+        if failed > 0 {
+            return Err(error::Kind::RuleTestFailed { count: failed }.into());
+        }
+        return Ok(());
+    }
 
     // Check the configuration and return an error with the loaded files.
     if !config.check() {
@@ -95,8 +197,14 @@ fn run() -> Result<(), Error> {
             error_string.push_str(&format!("\t- {}\n", file.display()));
         }
 
-        return Err(error::Kind::Config {
-            message: error_string,
+        return Err(if config.has_tools() {
+            error::Kind::Config {
+                message: error_string,
+            }
+        } else {
+            error::Kind::ToolMissing {
+                message: error_string,
+            }
         }
         .into());
     }
@@ -119,15 +227,73 @@ fn run() -> Result<(), Error> {
         sleep(Duration::from_millis(1250));
     }
 
+    // Termination signals cancel the analysis, so that the partial results are written and no
+    // external tool is left running.
+    cancel::install_handler()?;
+    audit::open(&config)?;
+
     // Start benchmarks.
     let mut benchmarks = BTreeMap::new();
 
     let total_start = Instant::now();
+    let mut crashed: Vec<(PathBuf, String)> = Vec::new();
+    let mut failed: Vec<(PathBuf, Vec<String>)> = Vec::new();
+    let mut summaries = Vec::new();
+    let packages = config.app_packages();
+    let total = packages.len();
     // Analyze each apk one by one.
-    for package in config.app_packages() {
+    for package in packages {
+        if cancel::is_cancelled() {
+            return Err(error::Kind::Cancelled.into());
+        }
         config.reset_force();
-        analyze_package(package, &mut config, &mut benchmarks)
-            .context("application analysis failed")?;
+        audit::start_analysis(&package);
+        // A panic while parsing a hostile application must not abort the rest of the batch, so
+        // it gets recorded and the analysis continues with the next application.
+        let analysis = panic::catch_unwind(AssertUnwindSafe(|| {
+            analyze_package(&package, &mut config, &mut benchmarks)
+        }));
+        match analysis {
+            Ok(Ok(summary)) => {
+                audit::finish_analysis("completed", None);
+                if !summary.failed_policies().is_empty() {
+                    failed.push((package, summary.failed_policies().to_vec()));
+                }
+                summaries.push(summary);
+            }
+            Ok(Err(e)) => {
+                let outcome = if cancel::is_cancelled() {
+                    "cancelled"
+                } else {
+                    "failed"
+                };
+                audit::finish_analysis(outcome, Some(e.to_string()));
+                return Err(e.context("application analysis failed").into());
+            }
+            Err(cause) => {
+                let message = panic_message(&*cause);
+                audit::finish_analysis("crashed", Some(message.clone()));
+                crashed.push((package, message));
+            }
+        }
+    }
+
+    // Summarize all the analyzed applications in a single report.
+    if cli.is_present("test-all") && !summaries.is_empty() {
+        // The statistics of the rules in the batch help tuning the rules.
+        RuleStatistics::new(&config, &summaries)
+            .and_then(|statistics| statistics.generate(&config))
+            .context("rule statistics generation failed")?;
+        Index::new(summaries)
+            .generate(&config)
+            .context("aggregate report generation failed")?;
+        if !config.is_quiet() {
+            println!(
+                "The aggregate report of all the applications and the rule statistics have been \
+                 generated in {}.",
+                config.results_folder().display()
+            );
+        }
     }
 
     // Print benchmarks if in benchmark mode.
@@ -145,5 +311,53 @@ fn run() -> Result<(), Error> {
         println!("{}", total_time);
     }
 
+    if !crashed.is_empty() {
+        println!();
+        println!(
+            "{}",
+            "The analysis crashed for the following applications:".bold()
+        );
+        for (package, message) in &crashed {
+            println!("\t- {}: {}", package.display(), message);
+        }
+
+        return Err(error::Kind::AnalysisCrashed {
+            count: crashed.len(),
+            total,
+        }
+        .into());
+    }
+
+    if !failed.is_empty() {
+        print_failed_policies(&failed);
+        return Err(error::Kind::PolicyFailed {
+            count: failed.len(),
+        }
+        .into());
+    }
+
     Ok(())
 }
+
+/// Prints the policies that each application failed.
+fn print_failed_policies(failed: &[(PathBuf, Vec<String>)]) {
+    println!();
+    println!(
+        "{}",
+        "The following applications failed the configured policies:".bold()
+    );
+    for (package, policies) in failed {
+        println!("\t- {}: {}", package.display(), policies.join(", "));
+    }
+}
+
+/// Gets the message of a caught panic, if it has one.
+fn panic_message(cause: &(dyn Any + Send)) -> String {
+    if let Some(message) = cause.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = cause.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
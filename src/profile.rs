@@ -0,0 +1,159 @@
+//! Analysis profiles module.
+//!
+//! Profiles are built-in selections of rules and thresholds for a compliance framework or a kind
+//! of engagement, so that users don't need to curate the rules for each of them. A profile only
+//! checks the rules with the tags that matter for its framework, sets the minimum criticality of
+//! the findings and adds a policy that fails the applications that don't meet its threshold.
+
+use crate::{criticality::Criticality, results::Policy};
+
+/// Analysis profile for a compliance framework.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// OWASP MASVS level 1, the standard security of any mobile application.
+    MasvsL1,
+    /// OWASP MASVS level 2, defense in depth for applications that handle sensitive data.
+    MasvsL2,
+    /// PCI Mobile Payment Acceptance guidelines, for applications that handle cardholder data.
+    PciMobile,
+    /// Privacy review, focused on the personal data that the application accesses or leaks.
+    Privacy,
+}
+
+impl Profile {
+    /// Gets all the profiles.
+    pub fn all() -> &'static [Self] {
+        &[
+            Profile::MasvsL1,
+            Profile::MasvsL2,
+            Profile::PciMobile,
+            Profile::Privacy,
+        ]
+    }
+
+    /// Gets the name of the profile, as used in the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::MasvsL1 => "masvs-l1",
+            Profile::MasvsL2 => "masvs-l2",
+            Profile::PciMobile => "pci-mobile",
+            Profile::Privacy => "privacy",
+        }
+    }
+
+    /// Gets the profile with the given name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .cloned()
+            .find(|profile| profile.name() == name)
+    }
+
+    /// Gets the tags of the rules checked by the profile. Empty if every rule is checked, except
+    /// the disabled ones.
+    fn enabled_tags(self) -> &'static [&'static str] {
+        match self {
+            Profile::MasvsL1 | Profile::MasvsL2 => &[],
+            Profile::PciMobile => &["crypto", "tls", "storage", "logging", "injection"],
+            Profile::Privacy => &["privacy", "logging", "storage"],
+        }
+    }
+
+    /// Gets the tags of the rules that are not checked by the profile.
+    fn disabled_tags(self) -> &'static [&'static str] {
+        match self {
+            Profile::MasvsL1 => &["code-quality", "resilience"],
+            Profile::MasvsL2 | Profile::PciMobile | Profile::Privacy => &["code-quality"],
+        }
+    }
+
+    /// Returns true if the profile checks the rules with the given tags.
+    pub fn checks_rule<I, S>(self, tags: I) -> bool
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let tags: Vec<S> = tags.into_iter().collect();
+        let enabled = self.enabled_tags().is_empty()
+            || tags
+                .iter()
+                .any(|tag| self.enabled_tags().contains(&tag.as_ref()));
+        enabled
+            && !tags
+                .iter()
+                .any(|tag| self.disabled_tags().contains(&tag.as_ref()))
+    }
+
+    /// Gets the minimum criticality of the findings reported with the profile.
+    pub fn min_criticality(self) -> Criticality {
+        match self {
+            Profile::MasvsL1 => Criticality::Low,
+            Profile::MasvsL2 | Profile::Privacy => Criticality::Warning,
+            Profile::PciMobile => Criticality::Medium,
+        }
+    }
+
+    /// Gets the condition that makes an application fail the profile, in the policy syntax.
+    fn fail_if(self) -> &'static str {
+        match self {
+            Profile::MasvsL1 => "critical > 0",
+            Profile::MasvsL2 | Profile::PciMobile => "critical > 0 or high > 0",
+            Profile::Privacy => "critical > 0 or high > 0 or medium > 3",
+        }
+    }
+
+    /// Gets the policy that fails the applications that don't meet the profile threshold.
+    pub fn policy(self) -> Policy {
+        Policy::new(self.name(), self.fail_if()).expect("invalid profile policy condition")
+    }
+}
+
+/// Analysis profiles testing module.
+#[cfg(test)]
+mod test {
+    use super::Profile;
+    use crate::criticality::Criticality;
+
+    /// Checks the names of the profiles.
+    #[test]
+    fn it_from_name() {
+        for profile in Profile::all() {
+            assert_eq!(Profile::from_name(profile.name()), Some(*profile));
+        }
+        assert_eq!(Profile::from_name("masvs-l3"), None);
+    }
+
+    /// Checks the rules checked by each profile, depending on their tags.
+    #[test]
+    fn it_checks_rule() {
+        let untagged: Vec<String> = Vec::new();
+        let crypto = vec!["crypto".to_owned()];
+        let privacy = vec!["privacy".to_owned()];
+        let resilience = vec!["resilience".to_owned()];
+        let quality = vec!["code-quality".to_owned(), "crypto".to_owned()];
+
+        assert!(Profile::MasvsL1.checks_rule(&untagged));
+        assert!(Profile::MasvsL1.checks_rule(&crypto));
+        assert!(!Profile::MasvsL1.checks_rule(&resilience));
+        assert!(Profile::MasvsL2.checks_rule(&resilience));
+        assert!(!Profile::MasvsL2.checks_rule(&quality));
+
+        assert!(!Profile::PciMobile.checks_rule(&untagged));
+        assert!(Profile::PciMobile.checks_rule(&crypto));
+        assert!(!Profile::PciMobile.checks_rule(&privacy));
+        assert!(!Profile::PciMobile.checks_rule(&quality));
+
+        assert!(Profile::Privacy.checks_rule(&privacy));
+        assert!(!Profile::Privacy.checks_rule(&crypto));
+    }
+
+    /// Checks the thresholds of the profiles.
+    #[test]
+    fn it_thresholds() {
+        assert_eq!(Profile::PciMobile.min_criticality(), Criticality::Medium);
+        for profile in Profile::all() {
+            assert_eq!(profile.policy().name(), profile.name());
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Report annotation module.
+//!
+//! Analysts usually add their own observations to the reports: the findings that they confirmed
+//! manually, the context of the application or the next steps of the review. The annotations are
+//! stored in a small JSON file next to the results file, written by the `report annotate`
+//! command, so that they survive the regeneration of the reports, and the HTML report shows them
+//! in an editable notes widget:
+//!
+//! ```json
+//! {
+//!   "notes": "The hardcoded key is only used in the debug flavor.",
+//!   "updated": "2018-11-20T10:32:00+01:00"
+//! }
+//! ```
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use failure::{Error, ResultExt};
+
+/// Name of the annotations file, next to the results file that it annotates.
+pub const ANNOTATIONS_FILE: &str = "annotations.json";
+
+/// Notes of the analysts about the results of an analysis.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Free text notes.
+    #[serde(default)]
+    notes: String,
+    /// Date and time of the last change of the notes.
+    #[serde(default)]
+    updated: Option<DateTime<Local>>,
+}
+
+impl Annotations {
+    /// Gets the path of the annotations file of the given results file.
+    pub fn path_for<P: AsRef<Path>>(results_file: P) -> PathBuf {
+        results_file
+            .as_ref()
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(ANNOTATIONS_FILE)
+    }
+
+    /// Loads the annotations from the given file, if it exists.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, Error> {
+        if !path.as_ref().is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path.as_ref())?;
+        let annotations = serde_json::from_str(&content).context(format!(
+            "the annotations file at {} is not valid",
+            path.as_ref().display()
+        ))?;
+        Ok(Some(annotations))
+    }
+
+    /// Saves the annotations to the given file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path.as_ref()).context(format!(
+            "could not create the annotations file at {}",
+            path.as_ref().display()
+        ))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Gets the notes.
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    /// Gets the date and time of the last change of the notes, if known.
+    pub fn updated(&self) -> Option<DateTime<Local>> {
+        self.updated
+    }
+
+    /// Checks if there are no notes.
+    pub fn is_empty(&self) -> bool {
+        self.notes.trim().is_empty()
+    }
+
+    /// Sets the notes, or appends them to the current ones in a new paragraph if `append` is set.
+    pub fn annotate<S: AsRef<str>>(&mut self, notes: S, append: bool) {
+        let notes = notes.as_ref().trim();
+        if append && !self.is_empty() {
+            self.notes = format!("{}\n\n{}", self.notes.trim_end(), notes);
+        } else {
+            self.notes = notes.to_owned();
+        }
+        self.updated = Some(Local::now());
+    }
+
+    /// Merges the notes of other annotations, for the merged results of partial analyses.
+    pub fn merge(&mut self, other: Self) {
+        if other.is_empty() || self.notes.contains(other.notes.trim()) {
+            return;
+        }
+        if self.is_empty() {
+            *self = other;
+            return;
+        }
+        self.notes = format!("{}\n\n{}", self.notes.trim_end(), other.notes.trim());
+        self.updated = self.updated.max(other.updated);
+    }
+}
+
+/// Report annotation testing module.
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::Annotations;
+
+    /// Checks that the annotations file is next to the results file.
+    #[test]
+    fn it_path_for() {
+        assert_eq!(
+            Annotations::path_for("results/com.example/results.json"),
+            Path::new("results/com.example/annotations.json")
+        );
+        assert_eq!(
+            Annotations::path_for("results.json"),
+            Path::new("annotations.json")
+        );
+    }
+
+    /// Checks that the notes are set and appended.
+    #[test]
+    fn it_annotate() {
+        let mut annotations = Annotations::default();
+        assert!(annotations.is_empty());
+        assert!(annotations.updated().is_none());
+
+        annotations.annotate("  The key is only used in debug builds. ", true);
+        assert_eq!(annotations.notes(), "The key is only used in debug builds.");
+        assert!(annotations.updated().is_some());
+
+        annotations.annotate("Report sent to the vendor.", true);
+        assert_eq!(
+            annotations.notes(),
+            "The key is only used in debug builds.\n\nReport sent to the vendor."
+        );
+
+        annotations.annotate("Fixed in 2.5.0.", false);
+        assert_eq!(annotations.notes(), "Fixed in 2.5.0.");
+    }
+
+    /// Checks that the notes of partial analyses are merged once.
+    #[test]
+    fn it_merge() {
+        let mut first = Annotations::default();
+        first.annotate("Manifest reviewed.", false);
+        let mut second = Annotations::default();
+        second.annotate("Code reviewed.", false);
+
+        let mut merged = first.clone();
+        merged.merge(second.clone());
+        merged.merge(second);
+        merged.merge(Annotations::default());
+        assert_eq!(merged.notes(), "Manifest reviewed.\n\nCode reviewed.");
+
+        let mut empty = Annotations::default();
+        empty.merge(first.clone());
+        assert_eq!(empty, first);
+    }
+
+    /// Checks that the annotations are read from their JSON representation.
+    #[test]
+    fn it_deserialize() {
+        let annotations: Annotations = serde_json::from_str(r#"{ "notes": "Reviewed." }"#).unwrap();
+        assert_eq!(annotations.notes(), "Reviewed.");
+        assert!(annotations.updated().is_none());
+    }
+}
@@ -0,0 +1,77 @@
+//! Capability summary module.
+//!
+//! Besides the vulnerabilities, the report summarizes the sensitive capabilities found in the
+//! application, such as monitoring the clipboard or capturing the screen. They are not
+//! vulnerabilities by themselves, but some of them are rarely seen outside of spyware, so they
+//! are marked as spyware indicators, to be reviewed first.
+
+use std::path::{Path, PathBuf};
+
+/// Sensitive capability of the application.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Capability {
+    /// Name of the capability.
+    name: String,
+    /// Description of the capability, and of the evidence found.
+    description: String,
+    /// Whether the capability is a high-signal spyware indicator.
+    #[serde(default)]
+    spyware_indicator: bool,
+    /// File where the capability was found, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    /// Line of the file where the capability was found, starting at 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+impl Capability {
+    /// Creates a new capability.
+    pub fn new<N: Into<String>, D: Into<String>>(name: N, description: D) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            spyware_indicator: false,
+            file: None,
+            line: None,
+        }
+    }
+
+    /// Marks the capability as a spyware indicator.
+    pub fn spyware_indicator(mut self) -> Self {
+        self.spyware_indicator = true;
+        self
+    }
+
+    /// Sets the file and the 0-based line where the capability was found.
+    pub fn location<P: AsRef<Path>>(mut self, file: P, line: usize) -> Self {
+        self.file = Some(file.as_ref().to_path_buf());
+        self.line = Some(line + 1);
+        self
+    }
+
+    /// Gets the name of the capability.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the description of the capability.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns true if the capability is a spyware indicator.
+    pub fn is_spyware_indicator(&self) -> bool {
+        self.spyware_indicator
+    }
+
+    /// Gets the file where the capability was found, if any.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Gets the line of the file where the capability was found, starting at 1, if any.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
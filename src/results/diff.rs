@@ -0,0 +1,234 @@
+//! Results comparison module.
+//!
+//! Compares the results of two analyses of the same application, usually of two different
+//! versions, to find the vulnerabilities that were introduced, fixed or left unchanged.
+
+use std::collections::BTreeMap;
+
+use failure::{bail, Error};
+
+use super::{Results, Vulnerability};
+
+/// Gets the key of the vulnerability, to match it in other analyses.
+///
+/// It's the fingerprint of the vulnerability, that does not include its line numbers, since they
+/// change every time code is added or removed above the vulnerability.
+fn key(vulnerability: &Vulnerability) -> String {
+    vulnerability.get_fingerprint()
+}
+
+/// Sorts the vulnerabilities from the most critical to the least, and then by file and line.
+fn most_critical_first(mut vulnerabilities: Vec<Vulnerability>) -> Vec<Vulnerability> {
+    vulnerabilities.sort_by(|a, b| b.get_criticality().cmp(&a.get_criticality()).then(a.cmp(b)));
+    vulnerabilities
+}
+
+/// Differences between the results of two analyses of the same application.
+#[derive(Debug, Serialize)]
+pub struct Diff {
+    /// Application package name.
+    app_package: String,
+    /// Version of the application in the old analysis.
+    old_version: String,
+    /// Version of the application in the new analysis.
+    new_version: String,
+    /// Permissions only requested in the new analysis.
+    new_permissions: Vec<String>,
+    /// Vulnerabilities only found in the new analysis.
+    new: Vec<Vulnerability>,
+    /// Vulnerabilities only found in the old analysis.
+    fixed: Vec<Vulnerability>,
+    /// Vulnerabilities found in both analyses, as found in the new one.
+    unchanged: Vec<Vulnerability>,
+}
+
+impl Diff {
+    /// Compares the results of two analyses of the same application.
+    ///
+    /// It fails if the results belong to different applications.
+    pub fn new(old: &Results, new: &Results) -> Result<Self, Error> {
+        if old.app_package() != new.app_package() {
+            bail!(
+                "the results of {} can't be compared with the results of {}",
+                new.app_package(),
+                old.app_package()
+            );
+        }
+
+        // The same vulnerability can be found more than once with the same key, for example in
+        // different lines of the same file, so each occurrence is matched only once.
+        let mut old_vulnerabilities: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for vulnerability in old.vulnerabilities.to_vec()? {
+            old_vulnerabilities
+                .entry(key(&vulnerability))
+                .or_insert_with(Vec::new)
+                .push(vulnerability);
+        }
+
+        let mut new_vulnerabilities = Vec::new();
+        let mut unchanged = Vec::new();
+        for vulnerability in new.vulnerabilities.to_vec()? {
+            let matched = old_vulnerabilities
+                .get_mut(&key(&vulnerability))
+                .and_then(Vec::pop)
+                .is_some();
+            if matched {
+                unchanged.push(vulnerability);
+            } else {
+                new_vulnerabilities.push(vulnerability);
+            }
+        }
+        let fixed = old_vulnerabilities
+            .into_iter()
+            .flat_map(|(_, vulnerabilities)| vulnerabilities)
+            .collect();
+
+        let new_permissions = new
+            .app_permissions()
+            .iter()
+            .filter(|permission| !old.app_permissions().contains(permission))
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            app_package: new.app_package().to_owned(),
+            old_version: old.app_version().to_owned(),
+            new_version: new.app_version().to_owned(),
+            new_permissions,
+            new: most_critical_first(new_vulnerabilities),
+            fixed: most_critical_first(fixed),
+            unchanged: most_critical_first(unchanged),
+        })
+    }
+
+    /// Gets the application package name.
+    pub fn app_package(&self) -> &str {
+        &self.app_package
+    }
+
+    /// Gets the version of the application in the old analysis.
+    pub fn old_version(&self) -> &str {
+        &self.old_version
+    }
+
+    /// Gets the version of the application in the new analysis.
+    pub fn new_version(&self) -> &str {
+        &self.new_version
+    }
+
+    /// Gets the permissions only requested in the new analysis.
+    pub fn new_permissions(&self) -> &[String] {
+        &self.new_permissions
+    }
+
+    /// Gets the vulnerabilities only found in the new analysis, from the most critical.
+    pub fn new_vulnerabilities(&self) -> &[Vulnerability] {
+        &self.new
+    }
+
+    /// Gets the vulnerabilities only found in the old analysis, from the most critical.
+    pub fn fixed_vulnerabilities(&self) -> &[Vulnerability] {
+        &self.fixed
+    }
+
+    /// Gets the vulnerabilities found in both analyses, from the most critical.
+    pub fn unchanged_vulnerabilities(&self) -> &[Vulnerability] {
+        &self.unchanged
+    }
+}
+
+/// Results comparison testing module.
+#[cfg(test)]
+mod test {
+    use super::Diff;
+    use crate::results::Results;
+
+    /// Creates the results of an analysis with the given version, permissions and high
+    /// vulnerabilities.
+    fn results(version: &str, permissions: &str, highs: &str) -> Results {
+        Results::from_json(
+            format!(
+                r#"{{
+                "app_package": "com.example",
+                "app_version": "{}",
+                "app_version_number": 1,
+                "app_fingerprint": {{"md5": "aa"}},
+                "app_min_sdk_number": 21,
+                "app_permissions": [{}],
+                "criticals": [],
+                "highs": [{}],
+                "mediums": [],
+                "lows": [],
+                "warnings": []
+            }}"#,
+                version, permissions, highs
+            )
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    /// Checks the comparison of two analyses.
+    #[test]
+    fn it_diff() {
+        let old = results(
+            "1.0",
+            r#""android.permission.INTERNET""#,
+            r#"{
+                "criticality": "high",
+                "name": "Weak Algorithms",
+                "description": "Description",
+                "file": "classes/Main.java",
+                "language": "java",
+                "line": 3,
+                "code": "MessageDigest.getInstance(\"MD5\");"
+            }, {
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 10,
+                "code": "<activity>"
+            }"#,
+        );
+        let new = results(
+            "2.0",
+            r#""android.permission.INTERNET", "android.permission.READ_SMS""#,
+            r#"{
+                "criticality": "high",
+                "name": "Weak Algorithms",
+                "description": "Description",
+                "file": "classes/Main.java",
+                "language": "java",
+                "line": 7,
+                "code": "  MessageDigest.getInstance(\"MD5\");"
+            }, {
+                "criticality": "high",
+                "name": "SQL injection",
+                "description": "Description",
+                "file": "classes/Db.java",
+                "language": "java",
+                "line": 2,
+                "code": "rawQuery(query)"
+            }"#,
+        );
+
+        let diff = Diff::new(&old, &new).unwrap();
+        assert_eq!(diff.old_version(), "1.0");
+        assert_eq!(diff.new_version(), "2.0");
+        assert_eq!(diff.new_permissions(), &["android.permission.READ_SMS"]);
+        assert_eq!(diff.new_vulnerabilities().len(), 1);
+        assert_eq!(diff.new_vulnerabilities()[0].get_name(), "SQL injection");
+        assert_eq!(diff.fixed_vulnerabilities().len(), 1);
+        assert_eq!(
+            diff.fixed_vulnerabilities()[0].get_name(),
+            "Exported activity"
+        );
+        assert_eq!(diff.unchanged_vulnerabilities().len(), 1);
+        assert_eq!(
+            diff.unchanged_vulnerabilities()[0].get_start_line(),
+            Some(6)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Engagement metadata module.
+//!
+//! Reports delivered as part of a security assessment need the context of the engagement: the
+//! title of the document, the client that requested it and the identifier of the engagement.
+//! They are set in the configuration or with the `--title`, `--client` and `--engagement-id`
+//! options, and they are stored in the results, so that the reports generated again from them
+//! keep the same context unless it's set again.
+
+use crate::Config;
+
+/// Metadata of the engagement that the analysis is part of.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Engagement {
+    /// Title of the reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Client of the engagement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client: Option<String>,
+    /// Identifier of the engagement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+}
+
+impl Engagement {
+    /// Creates the engagement metadata of the given configuration.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            title: config.report_title().map(str::to_owned),
+            client: config.client().map(str::to_owned),
+            id: config.engagement_id().map(str::to_owned),
+        }
+    }
+
+    /// Gets the title of the reports, if set.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_ref().map(String::as_str)
+    }
+
+    /// Gets the client of the engagement, if set.
+    pub fn client(&self) -> Option<&str> {
+        self.client.as_ref().map(String::as_str)
+    }
+
+    /// Gets the identifier of the engagement, if set.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(String::as_str)
+    }
+
+    /// Checks if no metadata is set.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.client.is_none() && self.id.is_none()
+    }
+
+    /// Fills the metadata that is not set with the metadata of other engagement.
+    pub fn fill(&mut self, other: Self) {
+        if self.title.is_none() {
+            self.title = other.title;
+        }
+        if self.client.is_none() {
+            self.client = other.client;
+        }
+        if self.id.is_none() {
+            self.id = other.id;
+        }
+    }
+}
+
+/// Engagement metadata testing module.
+#[cfg(test)]
+mod test {
+    use super::Engagement;
+
+    /// Checks that only the metadata that is not set is filled.
+    #[test]
+    fn it_fill() {
+        let mut engagement: Engagement =
+            serde_json::from_str(r#"{ "title": "Mobile assessment" }"#).unwrap();
+        assert!(!engagement.is_empty());
+
+        engagement.fill(
+            serde_json::from_str(r#"{ "title": "Old title", "client": "Example Bank" }"#).unwrap(),
+        );
+        assert_eq!(engagement.title(), Some("Mobile assessment"));
+        assert_eq!(engagement.client(), Some("Example Bank"));
+        assert_eq!(engagement.id(), None);
+        assert_eq!(
+            serde_json::to_string(&engagement).unwrap(),
+            r#"{"title":"Mobile assessment","client":"Example Bank"}"#
+        );
+
+        assert!(Engagement::default().is_empty());
+    }
+}
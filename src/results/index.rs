@@ -0,0 +1,304 @@
+//! Aggregate report module.
+//!
+//! When several applications are analyzed at once, each one gets its own report. The aggregate
+//! report summarizes all of them in the results folder, with the number of vulnerabilities of
+//! each criticality, the worst finding and a link to the report of each application, so that
+//! they can be compared without opening every report.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use failure::{Error, ResultExt};
+use serde_json::ser;
+
+use crate::{
+    config::Config,
+    criticality::Criticality,
+    results::{
+        report::{write_markdown_index, HandlebarsReport},
+        Results, Score, Vulnerability, CRITICALITIES,
+    },
+};
+
+/// Summary of the results of an application.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    /// Application package name.
+    app_package: String,
+    /// Application version.
+    app_version: String,
+    /// Number of vulnerabilities of each criticality.
+    vulnerabilities: BTreeMap<Criticality, usize>,
+    /// Total number of vulnerabilities.
+    total_vulnerabilities: usize,
+    /// Most critical vulnerability of the application.
+    worst_finding: Option<Vulnerability>,
+    /// Security score of the application, if it has been computed.
+    score: Option<Score>,
+    /// Names of the configured policies that the application failed.
+    failed_policies: Vec<String>,
+    /// Path to the main report of the application, relative to the results folder.
+    report: PathBuf,
+    /// Number of findings of each code analysis rule, by rule ID.
+    #[serde(skip)]
+    rule_matches: BTreeMap<String, usize>,
+}
+
+impl Summary {
+    /// Summarizes the results of an application, once its score and policies are evaluated.
+    pub fn new(config: &Config, results: &Results) -> Self {
+        let vulnerabilities: BTreeMap<_, _> = CRITICALITIES
+            .iter()
+            .map(|criticality| (*criticality, results.vulnerability_count(*criticality)))
+            .collect();
+        let report_folder = PathBuf::from(results.app_package());
+        let report = if config.has_to_generate_html() {
+            config
+                .report_format_folder(&report_folder, "html")
+                .join("index.html")
+        } else {
+            config
+                .report_format_folder(&report_folder, "json")
+                .join(format!("{}.json", config.report_file_name()))
+        };
+
+        let mut rule_matches = BTreeMap::new();
+        for criticality in &CRITICALITIES {
+            // Spilled findings that can't be read are not counted, as in the worst finding.
+            if let Ok(vulnerabilities) = results.vulnerabilities.iter(*criticality) {
+                for vulnerability in vulnerabilities.filter_map(Result::ok) {
+                    if let Some(rule_id) = vulnerability.get_rule_id() {
+                        *rule_matches.entry(rule_id.to_owned()).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        Self {
+            app_package: results.app_package().to_owned(),
+            app_version: results.app_version().to_owned(),
+            total_vulnerabilities: vulnerabilities.values().sum(),
+            vulnerabilities,
+            worst_finding: results.vulnerabilities.worst().unwrap_or(None),
+            score: results.score(),
+            failed_policies: results
+                .failed_policies()
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            report,
+            rule_matches,
+        }
+    }
+
+    /// Gets the application package name.
+    pub fn app_package(&self) -> &str {
+        &self.app_package
+    }
+
+    /// Gets the application version.
+    pub fn app_version(&self) -> &str {
+        &self.app_version
+    }
+
+    /// Gets the number of vulnerabilities of the given criticality.
+    pub fn vulnerability_count(&self, criticality: Criticality) -> usize {
+        self.vulnerabilities.get(&criticality).cloned().unwrap_or(0)
+    }
+
+    /// Gets the most critical vulnerability of the application.
+    pub fn worst_finding(&self) -> Option<&Vulnerability> {
+        self.worst_finding.as_ref()
+    }
+
+    /// Gets the security score of the application, if it has been computed.
+    pub fn score(&self) -> Option<Score> {
+        self.score
+    }
+
+    /// Gets the names of the configured policies that the application failed.
+    pub fn failed_policies(&self) -> &[String] {
+        &self.failed_policies
+    }
+
+    /// Gets the path to the main report of the application, relative to the results folder.
+    pub fn report(&self) -> &Path {
+        &self.report
+    }
+
+    /// Gets the number of findings of each code analysis rule, by rule ID.
+    pub fn rule_matches(&self) -> &BTreeMap<String, usize> {
+        &self.rule_matches
+    }
+}
+
+/// Aggregate report of the analyses of several applications.
+#[derive(Debug, Serialize)]
+pub struct Index {
+    /// Summaries of the analyzed applications.
+    applications: Vec<Summary>,
+    /// Number of vulnerabilities of each criticality, in all the applications.
+    vulnerabilities: BTreeMap<Criticality, usize>,
+    /// Total number of vulnerabilities, in all the applications.
+    total_vulnerabilities: usize,
+}
+
+impl Index {
+    /// Creates the aggregate report of the given applications.
+    pub fn new(applications: Vec<Summary>) -> Self {
+        let vulnerabilities: BTreeMap<_, _> = CRITICALITIES
+            .iter()
+            .map(|criticality| {
+                let count = applications
+                    .iter()
+                    .map(|summary| summary.vulnerability_count(*criticality))
+                    .sum();
+                (*criticality, count)
+            })
+            .collect();
+
+        Self {
+            total_vulnerabilities: vulnerabilities.values().sum(),
+            vulnerabilities,
+            applications,
+        }
+    }
+
+    /// Gets the summaries of the analyzed applications.
+    pub fn applications(&self) -> &[Summary] {
+        &self.applications
+    }
+
+    /// Gets the number of vulnerabilities of the given criticality, in all the applications.
+    pub fn vulnerability_count(&self, criticality: Criticality) -> usize {
+        self.vulnerabilities.get(&criticality).cloned().unwrap_or(0)
+    }
+
+    /// Generates the aggregate report in the results folder, in the selected formats.
+    ///
+    /// The HTML report is written to `index.html`, with the template assets inlined, the JSON
+    /// report to `index.json` and the Markdown report to `index.md`. The JUnit XML format is not
+    /// supported, since each application already has its own report.
+    #[allow(clippy::print_stdout)]
+    pub fn generate(&self, config: &Config) -> Result<(), Error> {
+        let results_folder = config.results_folder();
+
+        if config.has_to_generate_json() {
+            let mut f = BufWriter::new(File::create(results_folder.join("index.json"))?);
+            ser::to_writer(&mut f, self)?;
+            f.flush()?;
+        }
+        if config.has_to_generate_markdown() {
+            let mut f = BufWriter::new(File::create(results_folder.join("index.md"))?);
+            write_markdown_index(self, &mut f)?;
+            f.flush()?;
+        }
+        if config.has_to_generate_html() {
+            let index = HandlebarsReport::render_index(&config.template_path(), self)
+                .context("could not render the HTML aggregate report")?;
+            match index {
+                Some(index) => {
+                    File::create(results_folder.join("index.html"))?.write_all(index.as_bytes())?
+                }
+                None => {
+                    if config.is_verbose() {
+                        println!(
+                            "The template has no index template, so the HTML aggregate report \
+                             won't be generated."
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate report testing module.
+#[cfg(test)]
+mod test {
+    use super::{Index, Summary};
+    use crate::{config::Config, criticality::Criticality, results::Results};
+
+    /// Creates the results of an analysis of the given package with the given vulnerabilities.
+    fn results(package: &str, highs: &str, lows: &str) -> Results {
+        Results::from_json(
+            format!(
+                r#"{{
+                "app_package": "{}",
+                "app_version": "1.0",
+                "app_version_number": 1,
+                "app_fingerprint": {{"md5": "aa"}},
+                "app_min_sdk_number": 21,
+                "criticals": [],
+                "highs": [{}],
+                "mediums": [],
+                "lows": [{}],
+                "warnings": []
+            }}"#,
+                package, highs, lows
+            )
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    /// Checks the summaries of the applications and their aggregation.
+    #[test]
+    fn it_index() {
+        let config = Config::default();
+        let first = results(
+            "com.example.first",
+            r#"{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>"
+            }"#,
+            r#"{
+                "criticality": "low",
+                "name": "Logging",
+                "description": "Description",
+                "file": "classes/Main.java",
+                "language": "java",
+                "line": 8,
+                "code": "Log.d(TAG, message);",
+                "rule_id": "sensitive-logs"
+            }"#,
+        );
+        let second = results("com.example.second", "", "");
+
+        let summary = Summary::new(&config, &first);
+        assert_eq!(summary.app_package(), "com.example.first");
+        assert_eq!(summary.vulnerability_count(Criticality::High), 1);
+        assert_eq!(summary.vulnerability_count(Criticality::Low), 1);
+        assert_eq!(summary.rule_matches().len(), 1);
+        assert_eq!(summary.rule_matches()["sensitive-logs"], 1);
+        assert_eq!(
+            summary.worst_finding().unwrap().get_name(),
+            "Exported activity"
+        );
+        assert_eq!(
+            summary.report().to_str().unwrap(),
+            "com.example.first/index.html"
+        );
+
+        let empty = Summary::new(&config, &second);
+        assert!(empty.worst_finding().is_none());
+        assert!(empty.rule_matches().is_empty());
+
+        let index = Index::new(vec![summary, empty]);
+        assert_eq!(index.applications().len(), 2);
+        assert_eq!(index.vulnerability_count(Criticality::High), 1);
+        assert_eq!(index.vulnerability_count(Criticality::Critical), 0);
+        assert_eq!(index.total_vulnerabilities, 2);
+    }
+}
@@ -0,0 +1,122 @@
+//! Results migration module.
+//!
+//! The JSON results store the version of their format, so that the results of previous releases
+//! can still be loaded to regenerate their reports or to compare them with newer results. Every
+//! change of the format increases the version and adds a migration from the previous one, and the
+//! stored results go through all the migrations from their version before being deserialized.
+
+use failure::{bail, format_err, Error, ResultExt};
+use serde_json::{Map, Value};
+
+/// Current version of the format of the JSON results.
+pub const RESULTS_VERSION: u64 = 2;
+
+/// Version of the results stored before their format was versioned.
+const UNVERSIONED_RESULTS: u64 = 1;
+
+/// Migrations of the results format, in order. The first one migrates the unversioned results.
+const MIGRATIONS: [fn(&mut Map<String, Value>) -> Result<(), Error>; 1] = [add_results_version];
+
+/// Gets the version of the format of the given JSON results.
+pub fn results_version(results: &Value) -> Result<u64, Error> {
+    match results.get("results_version") {
+        Some(version) => match version.as_u64() {
+            Some(version) if version >= UNVERSIONED_RESULTS => Ok(version),
+            _ => bail!("the results version `{}` is not valid", version),
+        },
+        None => Ok(UNVERSIONED_RESULTS),
+    }
+}
+
+/// Migrates the given JSON results to the current version of the format.
+///
+/// It fails if the results were stored by a newer release, with a format that this release
+/// doesn't know.
+pub fn migrate(mut results: Value) -> Result<Value, Error> {
+    let version = results_version(&results)?;
+    if version > RESULTS_VERSION {
+        bail!(
+            "the results have version {} of the format, but this release only supports up to \
+             version {}, upgrade SUPER to load them",
+            version,
+            RESULTS_VERSION
+        );
+    }
+
+    let fields = results
+        .as_object_mut()
+        .ok_or_else(|| format_err!("the results must be a JSON object"))?;
+    for (from, migration) in (version..RESULTS_VERSION).zip(
+        MIGRATIONS
+            .iter()
+            .skip((version - UNVERSIONED_RESULTS) as usize),
+    ) {
+        migration(fields).context(format!(
+            "could not migrate the results from version {} to version {}",
+            from,
+            from + 1
+        ))?;
+        let _ = fields.insert("results_version".to_owned(), Value::from(from + 1));
+    }
+    Ok(results)
+}
+
+/// Migrates the results from version 1 to version 2.
+///
+/// Version 2 only adds the `results_version` field, which is set after every migration, so the
+/// rest of the results are kept as they are.
+fn add_results_version(_results: &mut Map<String, Value>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Results migration testing module.
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{migrate, results_version, MIGRATIONS, RESULTS_VERSION, UNVERSIONED_RESULTS};
+
+    /// Checks that there is a migration to every version of the format.
+    #[test]
+    fn it_migrations() {
+        assert_eq!(
+            MIGRATIONS.len() as u64,
+            RESULTS_VERSION - UNVERSIONED_RESULTS
+        );
+    }
+
+    /// Checks the version of the stored results.
+    #[test]
+    fn it_results_version() {
+        assert_eq!(
+            results_version(&json!({ "app_package": "com.example" })).unwrap(),
+            1
+        );
+        assert_eq!(
+            results_version(&json!({ "results_version": 2 })).unwrap(),
+            2
+        );
+        assert!(results_version(&json!({ "results_version": 0 })).is_err());
+        assert!(results_version(&json!({ "results_version": "2" })).is_err());
+    }
+
+    /// Checks the migration of the results of previous releases.
+    #[test]
+    fn it_migrate() {
+        let unversioned = json!({ "app_package": "com.example", "highs": [] });
+        assert_eq!(
+            migrate(unversioned).unwrap(),
+            json!({
+                "app_package": "com.example",
+                "highs": [],
+                "results_version": RESULTS_VERSION,
+            })
+        );
+
+        let current = json!({ "app_package": "com.example", "results_version": RESULTS_VERSION });
+        assert_eq!(migrate(current.clone()).unwrap(), current);
+
+        assert!(migrate(json!({ "results_version": RESULTS_VERSION + 1 })).is_err());
+        assert!(migrate(json!([])).is_err());
+    }
+}
@@ -1,29 +1,100 @@
 //! Results generation module.
 
-use std::{collections::BTreeSet, fs, path::Path};
+use std::{
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    process,
+};
 
 use chrono::Local;
 use clap::crate_version;
-use failure::{Error, ResultExt};
-use serde::ser::{Serialize, SerializeStruct, Serializer};
+use failure::{bail, Error, ResultExt};
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    ser::{Serialize, SerializeStruct, Serializer},
+};
+use serde_json::{json, Map, Value};
 
+mod annotations;
+mod capability;
+mod diff;
+mod engagement;
 mod handlebars_helpers;
+mod index;
+mod migration;
+mod permission_notes;
+mod policy;
 mod report;
+mod rule_stats;
+mod score;
 mod sdk_number;
+mod section;
+mod selector;
+mod sink;
+mod suppression;
+mod template_vars;
 mod utils;
 
-pub use self::utils::{html_escape, split_indent, Vulnerability};
+pub use self::annotations::{Annotations, ANNOTATIONS_FILE};
+pub use self::capability::Capability;
+pub use self::diff::Diff;
+pub use self::engagement::Engagement;
+pub use self::index::{Index, Summary};
+pub use self::migration::RESULTS_VERSION;
+pub use self::permission_notes::PermissionNotes;
+pub use self::policy::{Policy, PolicyResult};
+pub use self::rule_stats::{RuleStatistics, RuleStats, RULE_STATS_FILE};
+pub use self::score::Score;
+pub use self::section::Section;
+pub use self::selector::PrintSelector;
+pub use self::sink::{Vulnerabilities, VulnerabilityList, VulnerabilitySink};
+pub use self::suppression::{SuppressedVulnerability, Suppressions, DEFAULT_SUPPRESSIONS_FILE};
+pub use self::template_vars::{document_template_vars, sample_results};
+pub use self::utils::{
+    html_escape, split_indent, Confidence, HashAlgorithm, Vulnerability, VulnerabilityBuilder,
+};
 use self::{
     sdk_number::{prettify_android_version, SdkNumber},
     utils::FingerPrint,
 };
 use crate::{
+    audit, copy_folder,
     criticality::Criticality,
-    print_warning,
-    results::report::{Generator, HandlebarsReport, Json},
+    decompilation::Translation,
+    get_package_name, print_warning,
+    results::report::{CycloneDx, Generator, HandlebarsReport, JUnitXml, Json, Markdown, VsCode},
+    static_analysis::category::Category,
     Config,
 };
 
+/// Criticalities of the vulnerabilities, from the most critical to the least.
+const CRITICALITIES: [Criticality; 5] = [
+    Criticality::Critical,
+    Criticality::High,
+    Criticality::Medium,
+    Criticality::Low,
+    Criticality::Warning,
+];
+
+/// Gets the base URL of the decompiled source tree of the given package.
+///
+/// If a source URL has been configured, it will be used as the base for the package folder.
+/// Otherwise, the absolute path to the package in the local distribution folder will be used.
+fn source_url<S: AsRef<str>>(config: &Config, package: S) -> String {
+    if let Some(url) = config.source_url() {
+        format!("{}/{}", url.trim_end_matches('/'), package.as_ref())
+    } else {
+        let dist_folder = config
+            .dist_folder()
+            .canonicalize()
+            .unwrap_or_else(|_| config.dist_folder().to_path_buf());
+        format!("file://{}", dist_folder.join(package.as_ref()).display())
+    }
+}
+
 /// Results representation structure.
 pub struct Results {
     /// Application package name.
@@ -36,32 +107,50 @@ pub struct Results {
     app_version: String,
     /// Application version number.
     app_version_num: u32,
+    /// Inferred category of the application.
+    app_category: Option<Category>,
     /// Application minimum SDK.
     app_min_sdk: SdkNumber,
     /// Target SDK for the application.
     app_target_sdk: Option<SdkNumber>,
     /// Fingerprint of the application,
     app_fingerprint: FingerPrint,
+    /// SHA-256 fingerprints of the certificates that signed the application.
+    app_signers: Vec<String>,
+    /// Permissions requested by the application.
+    app_permissions: Vec<String>,
+    /// Sensitive capabilities of the application, for the capability summary.
+    capabilities: Vec<Capability>,
+    /// Base URL of the decompiled source tree of the application.
+    source_url: String,
+    /// Whether the manifest was recovered from a malformed binary manifest.
+    manifest_recovered: bool,
+    /// Whether the analysis was cancelled before it was complete.
+    cancelled: bool,
+    /// Completeness of the translation of the application code.
+    translation: Option<Translation>,
+    /// Security score of the application, once computed.
+    score: Option<Score>,
+    /// Results of the evaluation of the configured policies.
+    policies: Vec<PolicyResult>,
     /// Certificate of the application.
     #[cfg(feature = "certificate")]
     certificate: String,
-    /// List of warnings found in the application.
-    warnings: BTreeSet<Vulnerability>,
-    /// List of the potential low criticality vulnerabilities in the application.
-    low: BTreeSet<Vulnerability>,
-    /// List of the potential medium criticality vulnerabilities in the application.
-    medium: BTreeSet<Vulnerability>,
-    /// List of the potential high criticality vulnerabilities in the application.
-    high: BTreeSet<Vulnerability>,
-    /// List of the potential critical vulnerabilities in the application.
-    critical: BTreeSet<Vulnerability>,
+    /// Warnings and potential vulnerabilities found in the application.
+    vulnerabilities: VulnerabilitySink,
+    /// Vulnerabilities suppressed with the suppressions file.
+    suppressed: Vec<SuppressedVulnerability>,
+    /// Notes of the analysts about the results.
+    annotations: Option<Annotations>,
+    /// Metadata of the engagement that the analysis is part of.
+    engagement: Engagement,
 }
 
 impl Results {
     /// Initializes the results structure.
     #[allow(clippy::print_stdout)]
     pub fn init<P: AsRef<Path>>(config: &Config, package: P) -> Result<Self, Error> {
-        let fingerprint = match FingerPrint::from_package(package) {
+        let fingerprint = match FingerPrint::from_package(package.as_ref(), config.fingerprints()) {
             Ok(f) => f,
             Err(e) => {
                 print_warning(format!(
@@ -72,6 +161,25 @@ impl Results {
                 return Err(e)?;
             }
         };
+        if audit::is_enabled() {
+            let hashes: Map<String, Value> = config
+                .fingerprints()
+                .iter()
+                .filter_map(|algorithm| {
+                    fingerprint
+                        .hash(*algorithm)
+                        .map(|hash| (algorithm.name().to_owned(), hash.into()))
+                })
+                .collect();
+            audit::record(
+                "hash_computed",
+                json!({
+                    "path": package.as_ref().to_string_lossy(),
+                    "hashes": hashes,
+                }),
+            );
+        }
+        let source_url = source_url(config, get_package_name(package.as_ref()));
         if config.is_verbose() {
             println!(
                 "The results struct has been created. All the vulnerabilities will now \
@@ -90,15 +198,26 @@ impl Results {
                 app_description: String::new(),
                 app_version: String::new(),
                 app_version_num: 0,
+                app_category: None,
                 app_min_sdk: SdkNumber::Unknown(0),
                 app_target_sdk: None,
                 app_fingerprint: fingerprint,
+                app_signers: Vec::new(),
+                app_permissions: Vec::new(),
+                capabilities: Vec::new(),
+                source_url,
+                manifest_recovered: false,
+                cancelled: false,
+                translation: None,
+                score: None,
+                policies: Vec::new(),
                 certificate: String::new(),
-                warnings: BTreeSet::new(),
-                low: BTreeSet::new(),
-                medium: BTreeSet::new(),
-                high: BTreeSet::new(),
-                critical: BTreeSet::new(),
+                vulnerabilities: VulnerabilitySink::with_memory_limit(
+                    config.max_findings_in_memory(),
+                ),
+                suppressed: Vec::new(),
+                annotations: None,
+                engagement: Engagement::from_config(config),
             })
         }
 
@@ -110,18 +229,131 @@ impl Results {
                 app_description: String::new(),
                 app_version: String::new(),
                 app_version_num: 0,
+                app_category: None,
                 app_min_sdk: SdkNumber::Unknown(0),
                 app_target_sdk: None,
                 app_fingerprint: fingerprint,
-                warnings: BTreeSet::new(),
-                low: BTreeSet::new(),
-                medium: BTreeSet::new(),
-                high: BTreeSet::new(),
-                critical: BTreeSet::new(),
+                app_signers: Vec::new(),
+                app_permissions: Vec::new(),
+                capabilities: Vec::new(),
+                source_url,
+                manifest_recovered: false,
+                cancelled: false,
+                translation: None,
+                score: None,
+                policies: Vec::new(),
+                vulnerabilities: VulnerabilitySink::with_memory_limit(
+                    config.max_findings_in_memory(),
+                ),
+                suppressed: Vec::new(),
+                annotations: None,
+                engagement: Engagement::from_config(config),
             })
         }
     }
 
+    /// Loads the results of a previous analysis from its JSON report.
+    ///
+    /// The results stored by previous releases are migrated to the current format first.
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, Error> {
+        let results: Value =
+            serde_json::from_reader(reader).context("the JSON results are not valid")?;
+        let results = migration::migrate(results)?;
+        Ok(serde_json::from_value(results).context("the JSON results are not valid")?)
+    }
+
+    /// Merges the results of another analysis of the same application into these results.
+    ///
+    /// The vulnerabilities of both results are combined, and the application information missing
+    /// in these results is taken from the other results. This allows combining partial analyses
+    /// or findings produced by external tools into a single report. It fails if the results
+    /// belong to different applications.
+    pub fn merge(&mut self, other: Self) -> Result<(), Error> {
+        if self.app_package.is_empty() {
+            self.app_package = other.app_package;
+        } else if !other.app_package.is_empty() && self.app_package != other.app_package {
+            bail!(
+                "the results of {} can't be merged with the results of {}",
+                other.app_package,
+                self.app_package
+            );
+        }
+        self.app_fingerprint
+            .merge(other.app_fingerprint)
+            .context(format!(
+                "the results of {} belong to different builds of the application",
+                self.app_package
+            ))?;
+
+        if self.app_label.is_empty() {
+            self.app_label = other.app_label;
+        }
+        if self.app_description.is_empty() {
+            self.app_description = other.app_description;
+        }
+        if self.app_version.is_empty() {
+            self.app_version = other.app_version;
+        }
+        if self.app_version_num == 0 {
+            self.app_version_num = other.app_version_num;
+        }
+        if self.app_category.is_none() {
+            self.app_category = other.app_category;
+        }
+        if self.app_min_sdk.number() == 0 {
+            self.app_min_sdk = other.app_min_sdk;
+        }
+        if self.app_target_sdk.is_none() {
+            self.app_target_sdk = other.app_target_sdk;
+        }
+        if self.app_signers.is_empty() {
+            self.app_signers = other.app_signers;
+        }
+        if self.app_permissions.is_empty() {
+            self.app_permissions = other.app_permissions;
+        }
+        for capability in other.capabilities {
+            self.add_capability(capability);
+        }
+        if self.source_url.is_empty() {
+            self.source_url = other.source_url;
+        }
+        self.manifest_recovered |= other.manifest_recovered;
+        self.cancelled |= other.cancelled;
+        if self.translation.is_none() {
+            self.translation = other.translation;
+        }
+        #[cfg(feature = "certificate")]
+        {
+            if self.certificate.is_empty() {
+                self.certificate = other.certificate;
+            }
+        }
+
+        for criticality in &CRITICALITIES {
+            for vulnerability in other.vulnerabilities.iter(*criticality)? {
+                self.add_vulnerability(vulnerability?);
+            }
+        }
+        for suppressed in other.suppressed {
+            if !self.suppressed.contains(&suppressed) {
+                self.suppressed.push(suppressed);
+            }
+        }
+        self.engagement.fill(other.engagement);
+        if let Some(other_annotations) = other.annotations {
+            match self.annotations {
+                Some(ref mut annotations) => annotations.merge(other_annotations),
+                None => self.annotations = Some(other_annotations),
+            }
+        }
+        // The score and the policies of the combined vulnerabilities have to be evaluated again.
+        self.score = None;
+        self.policies.clear();
+
+        Ok(())
+    }
+
     /// Sets the application's package.
     pub fn set_app_package<S: Into<String>>(&mut self, package: S) {
         self.app_package = package.into();
@@ -153,11 +385,68 @@ impl Results {
         self.app_version = version.into();
     }
 
+    /// Gets the application version string.
+    pub fn app_version(&self) -> &str {
+        &self.app_version
+    }
+
     /// Sets the application version number.
     pub fn set_app_version_num(&mut self, version: u32) {
         self.app_version_num = version;
     }
 
+    /// Gets the application version number.
+    pub fn app_version_num(&self) -> u32 {
+        self.app_version_num
+    }
+
+    /// Sets the inferred category of the application.
+    pub fn set_app_category(&mut self, category: Category) {
+        self.app_category = Some(category);
+    }
+
+    /// Gets the inferred category of the application, if any.
+    pub fn app_category(&self) -> Option<Category> {
+        self.app_category
+    }
+
+    /// Sets the fingerprints of the certificates that signed the application.
+    pub fn set_app_signers(&mut self, signers: Vec<String>) {
+        self.app_signers = signers;
+    }
+
+    /// Sets the permissions requested by the application.
+    pub fn set_app_permissions(&mut self, permissions: Vec<String>) {
+        self.app_permissions = permissions;
+    }
+
+    /// Gets the permissions requested by the application.
+    pub fn app_permissions(&self) -> &[String] {
+        &self.app_permissions
+    }
+
+    /// Gets the behavior notes of the dangerous permissions requested by the application.
+    ///
+    /// The notes depend on the target SDK of the application, or on its minimum SDK if it has no
+    /// target SDK, as Android does.
+    pub fn permission_notes(&self) -> Vec<PermissionNotes> {
+        let target_sdk = self.app_target_sdk.unwrap_or(self.app_min_sdk);
+        PermissionNotes::from_permissions(&self.app_permissions, target_sdk.number())
+    }
+
+    /// Adds a sensitive capability of the application to the capability summary, if it was not
+    /// already added.
+    pub fn add_capability(&mut self, capability: Capability) {
+        if !self.capabilities.contains(&capability) {
+            self.capabilities.push(capability);
+        }
+    }
+
+    /// Gets the sensitive capabilities of the application, for the capability summary.
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
     /// Sets the application's minimum SDK number.
     pub fn set_app_min_sdk(&mut self, sdk: u32) {
         self.app_min_sdk = SdkNumber::from(sdk);
@@ -168,76 +457,260 @@ impl Results {
         self.app_target_sdk = Some(SdkNumber::from(sdk));
     }
 
+    /// Marks the manifest information as recovered from a malformed manifest.
+    pub fn set_manifest_recovered(&mut self) {
+        self.manifest_recovered = true;
+    }
+
+    /// Marks the results as partial, since the analysis was cancelled before it was complete.
+    pub fn set_cancelled(&mut self) {
+        self.cancelled = true;
+    }
+
+    /// Returns true if the analysis was cancelled before it was complete.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Sets the completeness of the translation of the application code.
+    pub fn set_translation(&mut self, translation: Translation) {
+        self.translation = Some(translation);
+    }
+
+    /// Computes the security score of the application from the vulnerabilities found, with the
+    /// weights in the configuration.
+    ///
+    /// The score is included in the reports, so it should be computed again if vulnerabilities
+    /// are added afterwards.
+    pub fn compute_score(&mut self, config: &Config) {
+        let score = Score::new(config, |criticality| self.vulnerability_count(criticality));
+        self.score = Some(score);
+    }
+
+    /// Gets the security score of the application, if it has been computed.
+    pub fn score(&self) -> Option<Score> {
+        self.score
+    }
+
+    /// Evaluates the configured policies over the results.
+    ///
+    /// The security score should be computed before, since policies can depend on it. The
+    /// results of the evaluation are included in the reports.
+    pub fn evaluate_policies(&mut self, config: &Config) {
+        let policies = config
+            .policies()
+            .iter()
+            .map(|policy| policy.evaluate(self))
+            .collect();
+        self.policies = policies;
+    }
+
+    /// Gets the results of the evaluation of the configured policies.
+    pub fn policy_results(&self) -> &[PolicyResult] {
+        &self.policies
+    }
+
+    /// Gets the names of the policies that the application failed.
+    pub fn failed_policies(&self) -> Vec<&str> {
+        self.policies
+            .iter()
+            .filter(|policy| policy.is_failed())
+            .map(PolicyResult::name)
+            .collect()
+    }
+
     /// Adds a vulnerability to the results.
-    #[allow(unused_variables)] // Until we remove the debug assertions
-    pub fn add_vulnerability(&mut self, vulnerability: Vulnerability) {
-        match vulnerability.get_criticality() {
-            Criticality::Warning => {
-                let new = self.warnings.insert(vulnerability);
-                // FIXME should we maintain it?
-                //debug_assert!(new, "trying to insert the same warning twice");
-            }
-            Criticality::Low => {
-                let new = self.low.insert(vulnerability);
-                // FIXME should we maintain it?
-                // debug_assert!(
-                //     new,
-                //     "trying to insert the same low criticality vulnerability twice"
-                // );
-            }
-            Criticality::Medium => {
-                let new = self.medium.insert(vulnerability);
-                // FIXME should we maintain it?
-                // debug_assert!(
-                //     new,
-                //     "trying to insert the same medium criticality vulnerability twice"
-                // );
-            }
-            Criticality::High => {
-                let new = self.high.insert(vulnerability);
-                // FIXME should we maintain it?
-                // debug_assert!(
-                //     new,
-                //     "trying to insert the same high criticality vulnerability twice"
-                // );
-            }
-            Criticality::Critical => {
-                let new = self.critical.insert(vulnerability);
-                // FIXME should we maintain it?
-                // debug_assert!(
-                //     new,
-                //     "trying to insert the same critical vulnerability twice"
-                // );
-            }
-        }
-    }
-
-    /// Generates the report.
-    #[allow(clippy::print_stdout)]
+    ///
+    /// Vulnerabilities can be added while the results are shared between threads. Workers that
+    /// can't borrow the results can use a [`VulnerabilitySink`](struct.VulnerabilitySink.html)
+    /// obtained with [`sink()`](#method.sink).
+    pub fn add_vulnerability(&self, vulnerability: Vulnerability) {
+        let _ = self.vulnerabilities.add(vulnerability);
+    }
+
+    /// Gets a sink that adds vulnerabilities to these results.
+    pub fn sink(&self) -> VulnerabilitySink {
+        self.vulnerabilities.clone()
+    }
+
+    /// Gets the vulnerabilities grouped in the report sections declared by the rules.
+    ///
+    /// It's empty if no vulnerability declares a report section.
+    pub fn sections(&self) -> Vec<Section> {
+        Section::group(CRITICALITIES.iter().flat_map(|criticality| {
+            self.vulnerabilities
+                .iter(*criticality)
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+        }))
+    }
+
+    /// Moves the vulnerabilities matched by the configured suppressions file to the suppressed
+    /// vulnerabilities, so that they are not counted in the score or the policies.
+    ///
+    /// If the suppressions file can't be loaded, no vulnerability is suppressed.
+    pub fn apply_suppressions(&mut self, config: &Config) {
+        let path = match config.suppressions_file() {
+            Some(path) => path,
+            None => return,
+        };
+        let suppressions = match Suppressions::load(path) {
+            Ok(suppressions) => suppressions,
+            Err(e) => {
+                print_warning(format!(
+                    "the suppressions file could not be loaded, no finding will be suppressed: {}",
+                    e
+                ));
+                return;
+            }
+        };
+        if let Err(e) = self.suppress(&suppressions) {
+            print_warning(format!(
+                "the suppressed findings could not be read back from disk: {}",
+                e
+            ));
+        }
+    }
+
+    /// Moves the vulnerabilities matched by the given suppressions to the suppressed
+    /// vulnerabilities.
+    ///
+    /// It fails if the vulnerabilities spilled to disk can't be read.
+    pub fn suppress(&mut self, suppressions: &Suppressions) -> Result<(), Error> {
+        let extracted = self
+            .vulnerabilities
+            .extract(|vulnerability| suppressions.find(vulnerability).is_some())?;
+        for vulnerability in extracted {
+            let reason = suppressions
+                .find(&vulnerability)
+                .and_then(|suppression| suppression.reason());
+            self.suppressed
+                .push(SuppressedVulnerability::new(vulnerability, reason));
+        }
+        Ok(())
+    }
+
+    /// Sets the engagement metadata of the configuration, keeping the metadata of the results
+    /// that the configuration doesn't set.
+    pub fn set_engagement(&mut self, config: &Config) {
+        let mut engagement = Engagement::from_config(config);
+        engagement.fill(mem::replace(&mut self.engagement, Engagement::default()));
+        self.engagement = engagement;
+    }
+
+    /// Gets the metadata of the engagement that the analysis is part of.
+    pub fn engagement(&self) -> &Engagement {
+        &self.engagement
+    }
+
+    /// Sets the notes of the analysts about the results.
+    ///
+    /// Empty notes remove the annotations of the results.
+    pub fn set_annotations(&mut self, annotations: Annotations) {
+        self.annotations = if annotations.is_empty() {
+            None
+        } else {
+            Some(annotations)
+        };
+    }
+
+    /// Gets the notes of the analysts about the results, if any.
+    pub fn annotations(&self) -> Option<&Annotations> {
+        self.annotations.as_ref()
+    }
+
+    /// Gets the vulnerabilities suppressed with the suppressions file.
+    pub fn suppressed(&self) -> &[SuppressedVulnerability] {
+        &self.suppressed
+    }
+
+    /// Gets the number of vulnerabilities of the given criticality.
+    pub fn vulnerability_count(&self, criticality: Criticality) -> usize {
+        self.vulnerabilities.len(criticality)
+    }
+
+    /// Generates the reports of the analysis.
+    ///
+    /// Reports are written to a staging folder, that replaces the report folder of the
+    /// application once every report is complete, so that consumers watching the report folder
+    /// never read half-written reports. While the reports are being generated, a lock file marks
+    /// the run as in progress.
     pub fn generate_report<S: AsRef<str>>(&self, config: &Config, package: S) -> Result<(), Error> {
-        let path = config.results_folder().join(&self.app_package);
+        let path = config.report_folder(&self.app_package);
+        let json_file = format!("{}.json", config.report_file_name());
+        let markdown_file = format!("{}.md", config.report_file_name());
+        let json_folder = config.report_format_folder(&path, "json");
+        let html_folder = config.report_format_folder(&path, "html");
+        let markdown_folder = config.report_format_folder(&path, "markdown");
+        let junit_folder = config.report_format_folder(&path, "junit");
+        let sbom_folder = config.report_format_folder(&path, "sbom");
         if config.is_verbose() {
             println!("Starting report generation.");
         }
-        if !path.exists() {
+        if !config.results_folder().exists() {
             if config.is_verbose() {
                 println!("First we'll create the results folder.");
             }
-            fs::create_dir_all(&path)?;
+            fs::create_dir_all(config.results_folder())?;
             if config.is_verbose() {
                 println!("Results folder created. Time to create the reports.");
             }
         }
-        if config.has_to_generate_json() {
-            let path = path.join("results.json");
 
-            if config.is_force() || !path.exists() {
-                if path.exists() {
+        let _lock = ResultsLock::acquire(
+            config.results_folder(),
+            &self.app_package,
+            config.is_force(),
+        )?;
+
+        let generate_json = config.has_to_generate_json()
+            && (config.is_force() || !json_folder.join(&json_file).exists());
+        // The HTML report needs all the findings in memory, so it's not generated once some of
+        // them have been spilled to disk.
+        let spilled = self.vulnerabilities.spilled();
+        let generate_html = config.has_to_generate_html()
+            && spilled == 0
+            && (config.is_force() || !html_folder.join("index.html").exists());
+        let generate_markdown = config.has_to_generate_markdown()
+            && (config.is_force() || !markdown_folder.join(&markdown_file).exists());
+        let generate_junit = config.has_to_generate_junit()
+            && (config.is_force() || !junit_folder.join("junit.xml").exists());
+        let generate_sbom = config.has_to_generate_sbom()
+            && (config.is_force() || !sbom_folder.join("bom.json").exists());
+
+        // The staging folder starts with the current results, so that the reports that are not
+        // regenerated are kept. It's created next to the report folder, so that it can replace
+        // it once the reports are complete.
+        let staging = match path.file_name() {
+            Some(name) => path.with_file_name(format!(".{}.partial", name.to_string_lossy())),
+            None => bail!("the report folder {} must have a name", path.display()),
+        };
+        if generate_json || generate_html || generate_markdown || generate_junit || generate_sbom {
+            if staging.exists() {
+                fs::remove_dir_all(&staging)?;
+            }
+            if let Some(parent) = staging.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::create_dir(&staging)?;
+            if path.exists() {
+                copy_folder(&path, &staging)
+                    .context("there was an error preparing the staging results folder")?;
+            }
+        }
+
+        if config.has_to_generate_json() {
+            if generate_json {
+                let json_staging = config.report_format_folder(&staging, "json");
+                fs::create_dir_all(&json_staging)?;
+                let json_path = json_staging.join(&json_file);
+                if json_path.exists() {
                     if config.is_verbose() {
                         println!("The application JSON results file exists. But no more…");
                     }
 
-                    if let Err(e) = fs::remove_file(&path) {
+                    if let Err(e) = fs::remove_file(&json_path) {
                         print_warning(format!(
                             "there was an error when removing the JSON results file: {}",
                             e
@@ -246,7 +719,7 @@ impl Results {
                 }
                 let mut json_reporter = Json::new();
 
-                if let Err(e) = json_reporter.generate(config, self) {
+                if let Err(e) = json_reporter.generate(config, self, &json_staging) {
                     print_warning(format!("there was en error generating JSON report: {}", e));
                 }
 
@@ -258,32 +731,128 @@ impl Results {
                     "Seems that the JSON report has already been generated. There is no \
                      need to do it again."
                 );
-            } else {
+            } else if !config.is_quiet() {
                 println!("Skipping JSON report generation.");
             }
         }
 
+        if config.has_to_generate_markdown() {
+            if generate_markdown {
+                let markdown_staging = config.report_format_folder(&staging, "markdown");
+                fs::create_dir_all(&markdown_staging)?;
+                let mut markdown_reporter = Markdown::new();
+
+                if let Err(e) = markdown_reporter.generate(config, self, &markdown_staging) {
+                    print_warning(format!(
+                        "there was en error generating the Markdown report: {}",
+                        e
+                    ));
+                }
+
+                if !config.is_quiet() {
+                    println!("Markdown report generated.");
+                }
+            } else if config.is_verbose() {
+                println!(
+                    "Seems that the Markdown report has already been generated. There is no \
+                     need to do it again."
+                );
+            } else if !config.is_quiet() {
+                println!("Skipping Markdown report generation.");
+            }
+        }
+
+        if config.has_to_generate_junit() {
+            if generate_junit {
+                let junit_staging = config.report_format_folder(&staging, "junit");
+                fs::create_dir_all(&junit_staging)?;
+                let mut junit_reporter = JUnitXml::new();
+
+                if let Err(e) = junit_reporter.generate(config, self, &junit_staging) {
+                    print_warning(format!(
+                        "there was en error generating the JUnit XML report: {}",
+                        e
+                    ));
+                }
+
+                if !config.is_quiet() {
+                    println!("JUnit XML report generated.");
+                }
+            } else if config.is_verbose() {
+                println!(
+                    "Seems that the JUnit XML report has already been generated. There is no \
+                     need to do it again."
+                );
+            } else if !config.is_quiet() {
+                println!("Skipping JUnit XML report generation.");
+            }
+        }
+
+        if config.has_to_generate_sbom() {
+            if generate_sbom {
+                let sbom_staging = config.report_format_folder(&staging, "sbom");
+                fs::create_dir_all(&sbom_staging)?;
+                let mut sbom_generator = CycloneDx::new(package.as_ref());
+
+                if let Err(e) = sbom_generator.generate(config, self, &sbom_staging) {
+                    print_warning(format!(
+                        "there was en error generating the CycloneDX SBOM: {}",
+                        e
+                    ));
+                }
+
+                if !config.is_quiet() {
+                    println!("CycloneDX SBOM generated.");
+                }
+            } else if config.is_verbose() {
+                println!(
+                    "Seems that the CycloneDX SBOM has already been generated. There is no need \
+                     to do it again."
+                );
+            } else if !config.is_quiet() {
+                println!("Skipping CycloneDX SBOM generation.");
+            }
+        }
+
+        if config.has_to_generate_vscode() {
+            let mut vscode_reporter = VsCode::new(package.as_ref());
+
+            if let Err(e) = vscode_reporter.generate(config, self, &path) {
+                print_warning(format!(
+                    "there was en error generating the VS Code problem matcher output: {}",
+                    e
+                ));
+            }
+        }
+
         if config.has_to_generate_html() {
-            let index_path = path.join("index.html");
+            if generate_html {
+                if path.exists() && config.is_verbose() {
+                    println!("The application HTML results exist. But no more…");
+                }
 
-            if config.is_force() || !index_path.exists() {
-                if path.exists() {
-                    if config.is_verbose() {
-                        println!("The application HTML results exist. But no more…");
-                    }
+                let html_staging = config.report_format_folder(&staging, "html");
+                fs::create_dir_all(&html_staging)?;
+                for f in fs::read_dir(&html_staging)
+                    .context("there was an error when removing the HTML results")?
+                {
+                    let f = f?;
 
-                    for f in fs::read_dir(path)
-                        .context("there was an error when removing the HTML results")?
+                    if f.file_type()?.is_dir() {
+                        fs::remove_dir_all(f.path())
+                            .context("there was an error when removing the HTML results")?;
+                    } else if ![
+                        json_file.as_str(),
+                        markdown_file.as_str(),
+                        "junit.xml",
+                        "bom.json",
+                        ANNOTATIONS_FILE,
+                    ]
+                    .iter()
+                    .any(|report| f.file_name() == *report)
                     {
-                        let f = f?;
-
-                        if f.file_type()?.is_dir() {
-                            fs::remove_dir_all(f.path())
-                                .context("there was an error when removing the HTML results")?;
-                        } else if &f.file_name() != "results.json" {
-                            fs::remove_file(f.path())
-                                .context("there was an error when removing the HTML results")?;
-                        }
+                        fs::remove_file(f.path())
+                            .context("there was an error when removing the HTML results")?;
                     }
                 }
 
@@ -293,7 +862,7 @@ impl Results {
                 );
 
                 if let Ok(mut handlebars_reporter) = handlebars_report_result {
-                    if let Err(e) = handlebars_reporter.generate(config, self) {
+                    if let Err(e) = handlebars_reporter.generate(config, self, &html_staging) {
                         print_warning(format!("There was en error generating HTML report: {}", e));
                     }
 
@@ -301,34 +870,192 @@ impl Results {
                         println!("HTML report generated.");
                     }
                 }
+            } else if spilled > 0 {
+                print_warning(format!(
+                    "the HTML report was not generated, since {} findings did not fit in memory. \
+                     The JSON, Markdown and JUnit reports have all of them, or the \
+                     `--max-findings-in-memory` limit can be raised",
+                    spilled
+                ));
             } else if config.is_verbose() {
                 println!(
                     "Seems that the HTML report has already been generated. There is no
                           need to do it again."
                 );
-            } else {
+            } else if !config.is_quiet() {
                 println!("Skipping HTML report generation.");
             }
         }
 
+        if generate_json || generate_html || generate_markdown || generate_junit || generate_sbom {
+            replace_folder(&staging, &path)
+                .context("there was an error moving the reports to the results folder")?;
+            audit::record_folder("report_written", &path, &Value::Null);
+        }
+
         Ok(())
     }
 }
 
+/// Lock marking that the reports of an application are being generated.
+///
+/// The lock file is removed when the lock is dropped, even if the analysis panics, unless another
+/// run took it in the meantime.
+#[derive(Debug)]
+struct ResultsLock {
+    /// Path to the lock file.
+    path: PathBuf,
+}
+
+impl ResultsLock {
+    /// Acquires the lock for the results of the given package, in the given results folder.
+    ///
+    /// If another run holds the lock, it will fail. If the analysis is forced, the lock is taken
+    /// when the run that created it is no longer running.
+    fn acquire(results_folder: &Path, package: &str, force: bool) -> Result<Self, Error> {
+        let path = results_folder.join(format!("{}.lock", package));
+        let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(f) => f,
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && force => {
+                if let Some(pid) = Self::owner(&path) {
+                    if is_process_running(pid) {
+                        bail!(
+                            "the results of {} are being generated by another run, with process \
+                             ID {}. If that's not the case, remove the {} file",
+                            package,
+                            pid,
+                            path.display()
+                        );
+                    }
+                }
+                print_warning(format!(
+                    "the results lock file {} was left by a run that is no longer running, so it \
+                     will be taken",
+                    path.display()
+                ));
+                fs::remove_file(&path)?;
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => bail!(
+                "the results of {} are being generated by another run. If that's not the case, \
+                 remove the {} file or use the --force flag",
+                package,
+                path.display()
+            ),
+            Err(e) => return Err(e.into()),
+        };
+        writeln!(&file, "{}", process::id())?;
+
+        Ok(Self { path })
+    }
+
+    /// Gets the ID of the process that holds the lock at the given path, if it can be read.
+    fn owner(path: &Path) -> Option<u32> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+}
+
+impl Drop for ResultsLock {
+    fn drop(&mut self) {
+        // The lock is only removed if it still belongs to this run.
+        if Self::owner(&self.path) != Some(process::id()) {
+            return;
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            print_warning(format!(
+                "could not remove the results lock file {}: {}",
+                self.path.display(),
+                e
+            ));
+        }
+    }
+}
+
+/// Checks if the process with the given ID is running.
+///
+/// If it can't be checked, the process is considered to be running, so that a lock is never
+/// taken from a live run.
+fn is_process_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else if cfg!(unix) {
+        process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .stderr(process::Stdio::null())
+            .status()
+            .map_or(true, |status| status.success())
+    } else {
+        true
+    }
+}
+
+/// Replaces the `to` folder with the `from` folder.
+///
+/// Both folders are renamed, so that the destination folder is never half-written.
+fn replace_folder(from: &Path, to: &Path) -> Result<(), Error> {
+    let old = from.with_extension("old");
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    if to.exists() {
+        fs::rename(to, &old)?;
+    }
+    fs::rename(from, to)?;
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    Ok(())
+}
+
 impl Serialize for Results {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         let now = Local::now();
+        let sections = self.sections();
         let len = {
-            let mut len = 21;
+            let mut len = 26;
             if cfg!(feature = "certificate") {
                 len += 1;
             }
             if self.app_min_sdk.version().is_some() {
                 len += 1;
             }
+            if self.app_category.is_some() {
+                len += 1;
+            }
+            if self.translation.is_some() {
+                len += 1;
+            }
+            if self.score.is_some() {
+                len += 1;
+            }
+            if !self.policies.is_empty() {
+                len += 1;
+            }
+            if !self.app_permissions.is_empty() {
+                len += 2;
+            }
+            if !self.capabilities.is_empty() {
+                len += 1;
+            }
+            if !sections.is_empty() {
+                len += 1;
+            }
+            if !self.suppressed.is_empty() {
+                len += 2;
+            }
+            if self.annotations.is_some() {
+                len += 1;
+            }
+            if !self.engagement.is_empty() {
+                len += 1;
+            }
             if let Some(target) = self.app_target_sdk {
                 if target.version().is_some() {
                     len += 3;
@@ -341,14 +1068,41 @@ impl Serialize for Results {
         let mut ser_struct = serializer.serialize_struct("Results", len)?;
 
         ser_struct.serialize_field("super_version", crate_version!())?;
+        ser_struct.serialize_field("results_version", &RESULTS_VERSION)?;
         ser_struct.serialize_field("now", &now)?;
         ser_struct.serialize_field("now_rfc2822", &now.to_rfc2822())?;
         ser_struct.serialize_field("now_rfc3339", &now.to_rfc3339())?;
 
+        if !self.engagement.is_empty() {
+            ser_struct.serialize_field("engagement", &self.engagement)?;
+        }
         ser_struct.serialize_field("app_package", &self.app_package)?;
         ser_struct.serialize_field("app_version", &self.app_version)?;
         ser_struct.serialize_field("app_version_number", &self.app_version_num)?;
+        if let Some(category) = self.app_category {
+            ser_struct.serialize_field("app_category", category.name())?;
+        }
         ser_struct.serialize_field("app_fingerprint", &self.app_fingerprint)?;
+        ser_struct.serialize_field("app_signers", &self.app_signers)?;
+        if !self.app_permissions.is_empty() {
+            ser_struct.serialize_field("app_permissions", &self.app_permissions)?;
+            ser_struct.serialize_field("permission_notes", &self.permission_notes())?;
+        }
+        if !self.capabilities.is_empty() {
+            ser_struct.serialize_field("capabilities", &self.capabilities)?;
+        }
+        ser_struct.serialize_field("source_url", &self.source_url)?;
+        ser_struct.serialize_field("manifest_recovered", &self.manifest_recovered)?;
+        ser_struct.serialize_field("cancelled", &self.cancelled)?;
+        if let Some(translation) = self.translation {
+            ser_struct.serialize_field("translation", &translation)?;
+        }
+        if let Some(score) = self.score {
+            ser_struct.serialize_field("score", &score)?;
+        }
+        if !self.policies.is_empty() {
+            ser_struct.serialize_field("policies", &self.policies)?;
+        }
 
         #[cfg(feature = "certificate")]
         {
@@ -377,21 +1131,319 @@ impl Serialize for Results {
             }
         }
 
+        let critical = self.vulnerabilities.len(Criticality::Critical);
+        let high = self.vulnerabilities.len(Criticality::High);
+        let medium = self.vulnerabilities.len(Criticality::Medium);
+        let low = self.vulnerabilities.len(Criticality::Low);
+        let warnings = self.vulnerabilities.len(Criticality::Warning);
+
+        // Vulnerabilities spilled to disk are streamed from their files.
+        ser_struct.serialize_field("total_vulnerabilities", &(low + medium + high + critical))?;
         ser_struct.serialize_field(
-            "total_vulnerabilities",
-            &(self.low.len() + self.medium.len() + self.high.len() + self.critical.len()),
+            "criticals",
+            &self.vulnerabilities.list(Criticality::Critical),
         )?;
-        ser_struct.serialize_field("criticals", &self.critical)?;
-        ser_struct.serialize_field("criticals_len", &self.critical.len())?;
-        ser_struct.serialize_field("highs", &self.high)?;
-        ser_struct.serialize_field("highs_len", &self.high.len())?;
-        ser_struct.serialize_field("mediums", &self.medium)?;
-        ser_struct.serialize_field("mediums_len", &self.medium.len())?;
-        ser_struct.serialize_field("lows", &self.low)?;
-        ser_struct.serialize_field("lows_len", &self.low.len())?;
-        ser_struct.serialize_field("warnings", &self.warnings)?;
-        ser_struct.serialize_field("warnings_len", &self.warnings.len())?;
+        ser_struct.serialize_field("criticals_len", &critical)?;
+        ser_struct.serialize_field("highs", &self.vulnerabilities.list(Criticality::High))?;
+        ser_struct.serialize_field("highs_len", &high)?;
+        ser_struct.serialize_field("mediums", &self.vulnerabilities.list(Criticality::Medium))?;
+        ser_struct.serialize_field("mediums_len", &medium)?;
+        ser_struct.serialize_field("lows", &self.vulnerabilities.list(Criticality::Low))?;
+        ser_struct.serialize_field("lows_len", &low)?;
+        ser_struct.serialize_field("warnings", &self.vulnerabilities.list(Criticality::Warning))?;
+        ser_struct.serialize_field("warnings_len", &warnings)?;
+        if !sections.is_empty() {
+            ser_struct.serialize_field("sections", &sections)?;
+        }
+        if !self.suppressed.is_empty() {
+            ser_struct.serialize_field("suppressed", &self.suppressed)?;
+            ser_struct.serialize_field("suppressed_len", &self.suppressed.len())?;
+        }
+        if let Some(ref annotations) = self.annotations {
+            ser_struct.serialize_field("annotations", annotations)?;
+        }
 
         ser_struct.end()
     }
 }
+
+/// Results as stored in the JSON report.
+///
+/// Only the fields needed to rebuild the results are read, the rest are computed again when
+/// serializing them.
+#[derive(Deserialize)]
+struct ResultsData {
+    app_package: String,
+    app_version: String,
+    app_version_number: u32,
+    #[serde(default)]
+    app_category: Option<String>,
+    app_fingerprint: FingerPrint,
+    #[serde(default)]
+    app_signers: Vec<String>,
+    #[serde(default)]
+    app_permissions: Vec<String>,
+    #[serde(default)]
+    capabilities: Vec<Capability>,
+    #[serde(default)]
+    source_url: String,
+    #[serde(default)]
+    manifest_recovered: bool,
+    #[serde(default)]
+    cancelled: bool,
+    #[serde(default)]
+    translation: Option<Translation>,
+    #[cfg(feature = "certificate")]
+    #[serde(default)]
+    certificate: String,
+    app_min_sdk_number: u32,
+    app_target_sdk_number: Option<u32>,
+    criticals: BTreeSet<Vulnerability>,
+    highs: BTreeSet<Vulnerability>,
+    mediums: BTreeSet<Vulnerability>,
+    lows: BTreeSet<Vulnerability>,
+    warnings: BTreeSet<Vulnerability>,
+    #[serde(default)]
+    suppressed: Vec<SuppressedVulnerability>,
+    #[serde(default)]
+    annotations: Option<Annotations>,
+    #[serde(default)]
+    engagement: Engagement,
+}
+
+impl<'de> Deserialize<'de> for Results {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = ResultsData::deserialize(deserializer)?;
+
+        let app_category = match data.app_category {
+            Some(name) => Some(Category::from_name(&name).ok_or_else(|| {
+                de::Error::custom(format!("unknown application category `{}`", name))
+            })?),
+            None => None,
+        };
+
+        let vulnerabilities = VulnerabilitySink::new();
+        for vulnerability in data
+            .criticals
+            .into_iter()
+            .chain(data.highs)
+            .chain(data.mediums)
+            .chain(data.lows)
+            .chain(data.warnings)
+        {
+            let _ = vulnerabilities.add(vulnerability);
+        }
+
+        Ok(Self {
+            app_package: data.app_package,
+            app_label: String::new(),
+            app_description: String::new(),
+            app_version: data.app_version,
+            app_version_num: data.app_version_number,
+            app_category,
+            app_min_sdk: SdkNumber::from(data.app_min_sdk_number),
+            app_target_sdk: data.app_target_sdk_number.map(SdkNumber::from),
+            app_fingerprint: data.app_fingerprint,
+            app_signers: data.app_signers,
+            app_permissions: data.app_permissions,
+            capabilities: data.capabilities,
+            source_url: data.source_url,
+            manifest_recovered: data.manifest_recovered,
+            cancelled: data.cancelled,
+            translation: data.translation,
+            score: None,
+            policies: Vec::new(),
+            #[cfg(feature = "certificate")]
+            certificate: data.certificate,
+            vulnerabilities,
+            suppressed: data.suppressed,
+            annotations: data.annotations,
+            engagement: data.engagement,
+        })
+    }
+}
+
+/// Results testing module.
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use super::{Results, ResultsLock, Suppressions};
+    use crate::criticality::Criticality;
+
+    /// Checks that a forced run only takes the results lock of runs that are no longer running,
+    /// and that it only removes its own lock.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn it_results_lock() {
+        let folder = env::temp_dir().join(format!("super-analyzer-lock-{}", process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("com.example.lock");
+
+        let lock = ResultsLock::acquire(&folder, "com.example", false).unwrap();
+        assert_eq!(ResultsLock::owner(&path), Some(process::id()));
+        assert!(ResultsLock::acquire(&folder, "com.example", false).is_err());
+        assert!(ResultsLock::acquire(&folder, "com.example", true).is_err());
+        drop(lock);
+        assert!(!path.exists());
+
+        // The init process is always running.
+        fs::write(&path, "1\n").unwrap();
+        assert!(ResultsLock::acquire(&folder, "com.example", true).is_err());
+        assert!(path.exists());
+
+        fs::write(&path, format!("{}\n", u32::max_value())).unwrap();
+        let lock = ResultsLock::acquire(&folder, "com.example", true).unwrap();
+        assert_eq!(ResultsLock::owner(&path), Some(process::id()));
+
+        // Another run took the lock, so it must not be removed.
+        fs::write(&path, "1\n").unwrap();
+        drop(lock);
+        assert!(path.exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    /// Checks that the results of partial analyses of the same application are merged.
+    #[test]
+    fn it_merge() {
+        let manifest = r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "app_target_sdk_number": 28,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>"
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#;
+        let code = r#"{
+            "app_package": "com.example",
+            "app_version": "",
+            "app_version_number": 0,
+            "app_fingerprint": {"md5": "aa", "sha1": "bb"},
+            "app_min_sdk_number": 0,
+            "criticals": [{
+                "criticality": "critical",
+                "name": "Hardcoded key",
+                "description": "Description",
+                "file": "classes/Main.java",
+                "language": "java",
+                "start_line": 2,
+                "end_line": 4,
+                "code": "key"
+            }],
+            "highs": [],
+            "mediums": [],
+            "lows": [],
+            "warnings": [],
+            "capabilities": [{
+                "name": "Screen capture",
+                "description": "Description",
+                "spyware_indicator": true,
+                "file": "classes/Main.java",
+                "line": 7
+            }]
+        }"#;
+
+        let mut results = Results::from_json(manifest.as_bytes()).unwrap();
+        results
+            .merge(Results::from_json(code.as_bytes()).unwrap())
+            .unwrap();
+
+        assert_eq!(results.app_package(), "com.example");
+        assert_eq!(results.app_version(), "1.0");
+        assert_eq!(results.app_version_num(), 1);
+        assert_eq!(results.app_min_sdk.number(), 21);
+        assert_eq!(results.app_target_sdk.map(|sdk| sdk.number()), Some(28));
+        assert_eq!(results.vulnerability_count(Criticality::High), 1);
+        assert_eq!(results.vulnerability_count(Criticality::Critical), 1);
+
+        {
+            let criticals = results.vulnerabilities.get(Criticality::Critical);
+            let critical = criticals.iter().next().unwrap();
+            assert_eq!(critical.get_criticality(), Criticality::Critical);
+            assert_eq!(critical.get_start_line(), Some(1));
+            assert_eq!(critical.get_end_line(), Some(3));
+        }
+        assert_eq!(results.capabilities().len(), 1);
+        assert!(results.capabilities()[0].is_spyware_indicator());
+        assert_eq!(results.capabilities()[0].line(), Some(7));
+
+        let other = manifest.replace("com.example", "com.other");
+        assert!(results
+            .merge(Results::from_json(other.as_bytes()).unwrap())
+            .is_err());
+    }
+
+    /// Checks that suppressed vulnerabilities are kept apart from the rest.
+    #[test]
+    fn it_suppress() {
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [],
+            "mediums": [],
+            "lows": [{
+                "criticality": "low",
+                "name": "Log usage",
+                "description": "Description",
+                "file": "classes/com/example/legacy/Main.java",
+                "language": "java",
+                "line": 3,
+                "code": "Log.d(TAG, message);",
+                "rule_id": "log-usage"
+            }, {
+                "criticality": "low",
+                "name": "Log usage",
+                "description": "Description",
+                "file": "classes/com/example/Main.java",
+                "language": "java",
+                "line": 8,
+                "code": "Log.d(TAG, message);",
+                "rule_id": "log-usage"
+            }],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let suppressions = Suppressions::from_toml(
+            r#"
+[[suppress]]
+file = "classes/com/example/legacy"
+reason = "Legacy code"
+"#,
+        )
+        .unwrap();
+        results.suppress(&suppressions).unwrap();
+
+        assert_eq!(results.vulnerability_count(Criticality::Low), 1);
+        assert_eq!(results.suppressed().len(), 1);
+        assert_eq!(results.suppressed()[0].reason(), Some("Legacy code"));
+
+        let json = serde_json::to_string(&results).unwrap();
+        let read = Results::from_json(json.as_bytes()).unwrap();
+        assert_eq!(read.suppressed(), results.suppressed());
+        assert_eq!(read.vulnerability_count(Criticality::Low), 1);
+    }
+}
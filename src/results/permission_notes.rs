@@ -0,0 +1,310 @@
+//! Permission behavior notes module.
+//!
+//! The behavior of dangerous permissions changes with the target SDK of the application: they
+//! are granted at install time or at runtime, they can be reset automatically, and some of them,
+//! such as the storage permissions, grant less access in newer versions. This module has a
+//! built-in table of those changes, so that the report explains how each requested permission
+//! behaves for the target SDK of the application.
+
+/// Prefix of the Android platform permissions.
+const PERMISSION_PREFIX: &str = "android.permission.";
+
+/// Dangerous permissions, that protect private data or restricted actions, with the SDK where
+/// they were added.
+const DANGEROUS_PERMISSIONS: [(&str, u32); 40] = [
+    ("ACCEPT_HANDOVER", 28),
+    ("ACCESS_BACKGROUND_LOCATION", 29),
+    ("ACCESS_COARSE_LOCATION", 1),
+    ("ACCESS_FINE_LOCATION", 1),
+    ("ACCESS_MEDIA_LOCATION", 29),
+    ("ACTIVITY_RECOGNITION", 29),
+    ("ADD_VOICEMAIL", 14),
+    ("ANSWER_PHONE_CALLS", 26),
+    ("BLUETOOTH_ADVERTISE", 31),
+    ("BLUETOOTH_CONNECT", 31),
+    ("BLUETOOTH_SCAN", 31),
+    ("BODY_SENSORS", 20),
+    ("BODY_SENSORS_BACKGROUND", 33),
+    ("CALL_PHONE", 1),
+    ("CAMERA", 1),
+    ("GET_ACCOUNTS", 1),
+    ("NEARBY_WIFI_DEVICES", 33),
+    ("POST_NOTIFICATIONS", 33),
+    ("PROCESS_OUTGOING_CALLS", 1),
+    ("READ_CALENDAR", 1),
+    ("READ_CALL_LOG", 16),
+    ("READ_CONTACTS", 1),
+    ("READ_EXTERNAL_STORAGE", 16),
+    ("READ_MEDIA_AUDIO", 33),
+    ("READ_MEDIA_IMAGES", 33),
+    ("READ_MEDIA_VIDEO", 33),
+    ("READ_PHONE_NUMBERS", 26),
+    ("READ_PHONE_STATE", 1),
+    ("READ_SMS", 1),
+    ("RECEIVE_MMS", 1),
+    ("RECEIVE_SMS", 1),
+    ("RECEIVE_WAP_PUSH", 1),
+    ("RECORD_AUDIO", 1),
+    ("SEND_SMS", 1),
+    ("USE_SIP", 9),
+    ("UWB_RANGING", 31),
+    ("WRITE_CALENDAR", 1),
+    ("WRITE_CALL_LOG", 16),
+    ("WRITE_CONTACTS", 1),
+    ("WRITE_EXTERNAL_STORAGE", 4),
+];
+
+/// Change in the behavior of some permissions, for a range of target SDKs.
+struct Behavior {
+    /// Permissions affected by the change, without prefix. Empty for every dangerous permission
+    /// that exists in the target SDK.
+    permissions: &'static [&'static str],
+    /// Minimum target SDK of the applications affected by the change.
+    min_target_sdk: u32,
+    /// Maximum target SDK of the applications affected by the change, if any.
+    max_target_sdk: Option<u32>,
+    /// Note explaining the behavior.
+    note: &'static str,
+}
+
+impl Behavior {
+    /// Returns true if the behavior applies to the given permission, for the given target SDK.
+    fn applies(&self, permission: &str, target_sdk: u32) -> bool {
+        (self.permissions.is_empty() || self.permissions.contains(&permission))
+            && target_sdk >= self.min_target_sdk
+            && self.max_target_sdk.map_or(true, |max| target_sdk <= max)
+    }
+}
+
+/// Built-in table of the changes in the behavior of dangerous permissions.
+const BEHAVIORS: [Behavior; 16] = [
+    Behavior {
+        permissions: &[],
+        min_target_sdk: 0,
+        max_target_sdk: Some(22),
+        note: "Granted at install time. Users can revoke it in the settings since Android 6.0, \
+               and the application might not expect it to be denied.",
+    },
+    Behavior {
+        permissions: &[],
+        min_target_sdk: 23,
+        max_target_sdk: None,
+        note: "Requested at runtime. The application must handle the user denying it.",
+    },
+    Behavior {
+        permissions: &[],
+        min_target_sdk: 23,
+        max_target_sdk: Some(25),
+        note: "Granting it also grants the rest of the requested permissions of its group, \
+               without asking the user.",
+    },
+    Behavior {
+        permissions: &[],
+        min_target_sdk: 23,
+        max_target_sdk: Some(29),
+        note: "It can be reset automatically if the application is not used for a few months, \
+               in devices with Android 12 or with Google Play services.",
+    },
+    Behavior {
+        permissions: &[],
+        min_target_sdk: 30,
+        max_target_sdk: None,
+        note: "It is reset automatically if the application is not used for a few months. \
+               One-time grants are also available, so it can be denied again after every use.",
+    },
+    Behavior {
+        permissions: &["READ_EXTERNAL_STORAGE", "WRITE_EXTERNAL_STORAGE"],
+        min_target_sdk: 0,
+        max_target_sdk: Some(28),
+        note: "Grants access to every file in the shared storage, including the files of other \
+               applications.",
+    },
+    Behavior {
+        permissions: &["READ_EXTERNAL_STORAGE", "WRITE_EXTERNAL_STORAGE"],
+        min_target_sdk: 29,
+        max_target_sdk: Some(29),
+        note: "Scoped storage is enabled, unless the application opts out with the \
+               `requestLegacyExternalStorage` attribute.",
+    },
+    Behavior {
+        permissions: &["READ_EXTERNAL_STORAGE", "WRITE_EXTERNAL_STORAGE"],
+        min_target_sdk: 30,
+        max_target_sdk: None,
+        note: "Scoped storage is enforced: the permission only grants access to the media files \
+               of the shared storage, and `WRITE_EXTERNAL_STORAGE` grants no additional access.",
+    },
+    Behavior {
+        permissions: &["READ_EXTERNAL_STORAGE"],
+        min_target_sdk: 33,
+        max_target_sdk: None,
+        note: "It has no effect. The granular media permissions, such as `READ_MEDIA_IMAGES`, \
+               have to be requested instead.",
+    },
+    Behavior {
+        permissions: &["ACCESS_COARSE_LOCATION", "ACCESS_FINE_LOCATION"],
+        min_target_sdk: 0,
+        max_target_sdk: Some(28),
+        note: "It also grants access to the location in the background.",
+    },
+    Behavior {
+        permissions: &["ACCESS_BACKGROUND_LOCATION"],
+        min_target_sdk: 30,
+        max_target_sdk: None,
+        note: "It must be requested after the foreground location, and users can only grant it \
+               from the settings.",
+    },
+    Behavior {
+        permissions: &["ACCESS_FINE_LOCATION"],
+        min_target_sdk: 31,
+        max_target_sdk: None,
+        note: "Users can grant the approximate location only, so `ACCESS_COARSE_LOCATION` has \
+               to be requested with it.",
+    },
+    Behavior {
+        permissions: &["GET_ACCOUNTS"],
+        min_target_sdk: 26,
+        max_target_sdk: None,
+        note: "It no longer grants access to the accounts of the device, that have to be chosen \
+               by the user.",
+    },
+    Behavior {
+        permissions: &["BLUETOOTH_ADVERTISE", "BLUETOOTH_CONNECT", "BLUETOOTH_SCAN"],
+        min_target_sdk: 31,
+        max_target_sdk: None,
+        note: "Requested at runtime instead of the install time `BLUETOOTH` and \
+               `BLUETOOTH_ADMIN` permissions.",
+    },
+    Behavior {
+        permissions: &["POST_NOTIFICATIONS"],
+        min_target_sdk: 0,
+        max_target_sdk: Some(32),
+        note: "Not requested by the application: the system asks the user when the first \
+               notification channel is created.",
+    },
+    Behavior {
+        permissions: &["BODY_SENSORS"],
+        min_target_sdk: 33,
+        max_target_sdk: None,
+        note: "It only grants access to the sensors in the foreground. The \
+               `BODY_SENSORS_BACKGROUND` permission is needed in the background.",
+    },
+];
+
+/// Note for permissions that don't exist in the target SDK, and have no specific notes.
+const NEWER_PERMISSION_NOTE: &str = "The permission does not exist in the target SDK of the \
+                                     application, so it only has effect in newer versions of \
+                                     Android.";
+
+/// Behavior notes of a dangerous permission requested by the application.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PermissionNotes {
+    /// Name of the permission.
+    permission: String,
+    /// SDK where the permission was added.
+    added_in_sdk: u32,
+    /// Notes on the behavior of the permission, for the target SDK of the application.
+    notes: Vec<&'static str>,
+}
+
+impl PermissionNotes {
+    /// Gets the behavior notes of the given permissions, for the given target SDK.
+    ///
+    /// Permissions that are not dangerous don't get notes.
+    pub fn from_permissions<S: AsRef<str>>(permissions: &[S], target_sdk: u32) -> Vec<Self> {
+        permissions
+            .iter()
+            .filter_map(|permission| {
+                if !permission.as_ref().starts_with(PERMISSION_PREFIX) {
+                    return None;
+                }
+                let name = &permission.as_ref()[PERMISSION_PREFIX.len()..];
+                let added_in_sdk = DANGEROUS_PERMISSIONS
+                    .iter()
+                    .find(|(dangerous, _)| *dangerous == name)
+                    .map(|(_, added_in_sdk)| *added_in_sdk)?;
+
+                let exists = target_sdk >= added_in_sdk;
+                let mut notes: Vec<_> = BEHAVIORS
+                    .iter()
+                    .filter(|behavior| {
+                        (exists || !behavior.permissions.is_empty())
+                            && behavior.applies(name, target_sdk)
+                    })
+                    .map(|behavior| behavior.note)
+                    .collect();
+                if notes.is_empty() && !exists {
+                    notes.push(NEWER_PERMISSION_NOTE);
+                }
+
+                Some(Self {
+                    permission: permission.as_ref().to_owned(),
+                    added_in_sdk,
+                    notes,
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the name of the permission.
+    pub fn permission(&self) -> &str {
+        &self.permission
+    }
+
+    /// Gets the SDK where the permission was added.
+    pub fn added_in_sdk(&self) -> u32 {
+        self.added_in_sdk
+    }
+
+    /// Gets the notes on the behavior of the permission.
+    pub fn notes(&self) -> &[&'static str] {
+        &self.notes
+    }
+}
+
+/// Permission behavior notes testing module.
+#[cfg(test)]
+mod test {
+    use super::{PermissionNotes, BEHAVIORS, NEWER_PERMISSION_NOTE};
+
+    /// Checks the notes of the permissions depending on the target SDK.
+    #[test]
+    fn it_from_permissions() {
+        let permissions = [
+            "android.permission.INTERNET",
+            "android.permission.READ_EXTERNAL_STORAGE",
+            "android.permission.CAMERA",
+            "com.example.permission.CAMERA",
+            "android.permission.BLUETOOTH_SCAN",
+            "android.permission.POST_NOTIFICATIONS",
+        ];
+
+        let notes = PermissionNotes::from_permissions(&permissions, 22);
+        assert_eq!(notes.len(), 4);
+        assert_eq!(
+            notes[0].permission(),
+            "android.permission.READ_EXTERNAL_STORAGE"
+        );
+        assert_eq!(notes[0].notes(), &[BEHAVIORS[0].note, BEHAVIORS[5].note]);
+        assert_eq!(notes[1].permission(), "android.permission.CAMERA");
+        assert_eq!(notes[1].notes(), &[BEHAVIORS[0].note]);
+        assert_eq!(notes[2].added_in_sdk(), 31);
+        assert_eq!(notes[2].notes(), &[NEWER_PERMISSION_NOTE]);
+        assert_eq!(notes[3].notes(), &[BEHAVIORS[14].note]);
+
+        let notes = PermissionNotes::from_permissions(&permissions, 33);
+        assert_eq!(
+            notes[0].notes(),
+            &[
+                BEHAVIORS[1].note,
+                BEHAVIORS[4].note,
+                BEHAVIORS[7].note,
+                BEHAVIORS[8].note
+            ]
+        );
+        assert_eq!(notes[1].notes(), &[BEHAVIORS[1].note, BEHAVIORS[4].note]);
+        assert_eq!(
+            notes[2].notes(),
+            &[BEHAVIORS[1].note, BEHAVIORS[4].note, BEHAVIORS[13].note]
+        );
+    }
+}
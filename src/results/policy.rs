@@ -0,0 +1,510 @@
+//! Policy evaluation module.
+//!
+//! Policies are conditions over the finished results of an analysis, configured in the
+//! `config.toml` file, such as "fail if there is any critical vulnerability or more than 5 high
+//! criticality vulnerabilities in first-party code". An application fails a policy if its
+//! condition is true, and failed policies make the analysis exit with an error.
+//!
+//! Conditions compare a variable with a number, using `>`, `>=`, `<`, `<=`, `==` or `!=`, and can
+//! be combined with `and` (or `&&`), `or` (or `||`), `not` and parentheses. Variables are the
+//! number of vulnerabilities of a criticality (`critical`, `high`, `medium`, `low` and `warning`),
+//! optionally only in `first_party`, `sdk` or `third_party` code (`high.first_party`), and the
+//! security `score` of the application.
+
+use std::{fmt, iter::Peekable, str::Chars, vec::IntoIter};
+
+use failure::{bail, format_err, Error};
+use serde::de::{self, Deserialize, Deserializer};
+
+use crate::{
+    criticality::Criticality,
+    results::{score::MAX_SCORE, Results},
+    static_analysis::libraries::Origin,
+};
+
+/// Policy over the results of the analyses.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    /// Name of the policy.
+    name: String,
+    /// Condition that makes an application fail the policy, as written in the configuration.
+    fail_if: String,
+    /// Parsed condition.
+    condition: Condition,
+}
+
+impl Policy {
+    /// Creates a new policy, parsing its condition.
+    pub fn new<N: Into<String>, C: Into<String>>(name: N, fail_if: C) -> Result<Self, Error> {
+        let fail_if = fail_if.into();
+        let condition = Condition::parse(&fail_if)?;
+        Ok(Self {
+            name: name.into(),
+            fail_if,
+            condition,
+        })
+    }
+
+    /// Gets the name of the policy.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Evaluates the policy over the given results.
+    ///
+    /// The security score of the results should be computed before, or a perfect score is used.
+    pub fn evaluate(&self, results: &Results) -> PolicyResult {
+        PolicyResult {
+            name: self.name.clone(),
+            fail_if: self.fail_if.clone(),
+            failed: self.condition.evaluate(results),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Policy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        /// Policy as written in the configuration.
+        #[derive(Deserialize)]
+        struct PolicyData {
+            name: String,
+            fail_if: String,
+        }
+
+        let data = PolicyData::deserialize(deserializer)?;
+        let name = data.name.clone();
+        Self::new(data.name, data.fail_if).map_err(|e| {
+            de::Error::custom(format!("invalid condition in the `{}` policy: {}", name, e))
+        })
+    }
+}
+
+/// Result of the evaluation of a policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyResult {
+    /// Name of the policy.
+    name: String,
+    /// Condition that makes an application fail the policy.
+    fail_if: String,
+    /// Whether the application failed the policy.
+    failed: bool,
+}
+
+impl PolicyResult {
+    /// Gets the name of the policy.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the condition that makes an application fail the policy.
+    pub fn fail_if(&self) -> &str {
+        &self.fail_if
+    }
+
+    /// Returns true if the application failed the policy.
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+}
+
+/// Variable of a policy condition.
+#[derive(Debug, Clone, PartialEq)]
+enum Variable {
+    /// Number of vulnerabilities of a criticality, optionally only in code of the given origin.
+    Vulnerabilities(Criticality, Option<Origin>),
+    /// Security score of the application.
+    Score,
+}
+
+impl Variable {
+    /// Parses a variable name.
+    fn parse(name: &str) -> Result<Self, Error> {
+        if name == "score" {
+            return Ok(Variable::Score);
+        }
+
+        let mut parts = name.splitn(2, '.');
+        let criticality = parts
+            .next()
+            .unwrap_or_default()
+            .parse::<Criticality>()
+            .map_err(|_| format_err!("unknown variable `{}`", name))?;
+        let origin = match parts.next() {
+            None => None,
+            Some("first_party") => Some(Origin::FirstParty),
+            // The name of the SDK is not compared, any known SDK matches.
+            Some("sdk") => Some(Origin::Sdk(String::new())),
+            Some("third_party") => Some(Origin::ThirdParty),
+            Some(origin) => bail!("unknown code origin `{}` in `{}`", origin, name),
+        };
+        Ok(Variable::Vulnerabilities(criticality, origin))
+    }
+
+    /// Gets the value of the variable in the given results.
+    fn value(&self, results: &Results) -> u32 {
+        match self {
+            Variable::Vulnerabilities(criticality, None) => {
+                results.vulnerability_count(*criticality) as u32
+            }
+            Variable::Vulnerabilities(criticality, Some(origin)) => results
+                .vulnerabilities
+                .iter(*criticality)
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .filter(|vulnerability| {
+                    vulnerability
+                        .get_origin()
+                        .map_or(false, |o| o.name() == origin.name())
+                })
+                .count() as u32,
+            Variable::Score => results.score().map_or(MAX_SCORE, |score| score.value()),
+        }
+    }
+}
+
+/// Comparison operator of a policy condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+}
+
+impl Operator {
+    /// Compares the two values with the operator.
+    fn compare(self, left: u32, right: u32) -> bool {
+        match self {
+            Operator::Greater => left > right,
+            Operator::GreaterOrEqual => left >= right,
+            Operator::Less => left < right,
+            Operator::LessOrEqual => left <= right,
+            Operator::Equal => left == right,
+            Operator::NotEqual => left != right,
+        }
+    }
+}
+
+/// Parsed policy condition.
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    /// Comparison of a variable with a number.
+    Compare(Variable, Operator, u32),
+    /// Negation of a condition.
+    Not(Box<Condition>),
+    /// Both conditions are true.
+    And(Box<Condition>, Box<Condition>),
+    /// Any of the conditions is true.
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    /// Parses a condition.
+    fn parse(condition: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(condition)?.into_iter().peekable(),
+        };
+        let parsed = parser.or()?;
+        if let Some(token) = parser.tokens.next() {
+            bail!("unexpected {} in the condition", token);
+        }
+        Ok(parsed)
+    }
+
+    /// Evaluates the condition over the given results.
+    fn evaluate(&self, results: &Results) -> bool {
+        match self {
+            Condition::Compare(variable, operator, number) => {
+                operator.compare(variable.value(results), *number)
+            }
+            Condition::Not(condition) => !condition.evaluate(results),
+            Condition::And(left, right) => left.evaluate(results) && right.evaluate(results),
+            Condition::Or(left, right) => left.evaluate(results) || right.evaluate(results),
+        }
+    }
+}
+
+/// Token of a policy condition.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Variable name, or `and`, `or` and `not` keywords.
+    Word(String),
+    /// Number.
+    Number(u32),
+    /// Comparison operator.
+    Operator(Operator),
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `(`
+    OpenParenthesis,
+    /// `)`
+    CloseParenthesis,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Number(number) => write!(f, "`{}`", number),
+            Token::Operator(_) => write!(f, "comparison"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::OpenParenthesis => write!(f, "`(`"),
+            Token::CloseParenthesis => write!(f, "`)`"),
+        }
+    }
+}
+
+/// Splits a policy condition in tokens.
+fn tokenize(condition: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = condition.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpenParenthesis,
+            ')' => Token::CloseParenthesis,
+            '&' if next_is(&mut chars, '&') => Token::And,
+            '|' if next_is(&mut chars, '|') => Token::Or,
+            '>' if next_is(&mut chars, '=') => Token::Operator(Operator::GreaterOrEqual),
+            '>' => Token::Operator(Operator::Greater),
+            '<' if next_is(&mut chars, '=') => Token::Operator(Operator::LessOrEqual),
+            '<' => Token::Operator(Operator::Less),
+            '=' if next_is(&mut chars, '=') => Token::Operator(Operator::Equal),
+            '!' if next_is(&mut chars, '=') => Token::Operator(Operator::NotEqual),
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.peek().cloned().filter(char::is_ascii_digit) {
+                    number.push(c);
+                    let _ = chars.next();
+                }
+                Token::Number(number.parse()?)
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some(c) = chars
+                    .peek()
+                    .cloned()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    word.push(c);
+                    let _ = chars.next();
+                }
+                Token::Word(word)
+            }
+            c => bail!("unexpected character `{}` in the condition", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Consumes the next character if it's the expected one.
+fn next_is(chars: &mut Peekable<Chars<'_>>, expected: char) -> bool {
+    if chars.peek() == Some(&expected) {
+        let _ = chars.next();
+        true
+    } else {
+        false
+    }
+}
+
+/// Recursive descent parser of policy conditions.
+#[derive(Debug)]
+struct Parser {
+    /// Remaining tokens of the condition.
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    /// Parses conditions joined by `or`, the operator with the lowest precedence.
+    fn or(&mut self) -> Result<Condition, Error> {
+        let mut condition = self.and()?;
+        while self.next_is_keyword("or", &Token::Or) {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    /// Parses conditions joined by `and`.
+    fn and(&mut self) -> Result<Condition, Error> {
+        let mut condition = self.unary()?;
+        while self.next_is_keyword("and", &Token::And) {
+            condition = Condition::And(Box::new(condition), Box::new(self.unary()?));
+        }
+        Ok(condition)
+    }
+
+    /// Parses negated conditions, conditions between parentheses and comparisons.
+    fn unary(&mut self) -> Result<Condition, Error> {
+        match self.tokens.next() {
+            Some(Token::Word(ref word)) if word == "not" => {
+                Ok(Condition::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::OpenParenthesis) => {
+                let condition = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::CloseParenthesis) => Ok(condition),
+                    Some(token) => bail!("expected `)`, found {}", token),
+                    None => bail!("expected `)` at the end of the condition"),
+                }
+            }
+            Some(Token::Word(name)) => {
+                let variable = Variable::parse(&name)?;
+                let operator = match self.tokens.next() {
+                    Some(Token::Operator(operator)) => operator,
+                    Some(token) => bail!("expected a comparison after `{}`, found {}", name, token),
+                    None => bail!("expected a comparison after `{}`", name),
+                };
+                match self.tokens.next() {
+                    Some(Token::Number(number)) => {
+                        Ok(Condition::Compare(variable, operator, number))
+                    }
+                    Some(token) => bail!("expected a number, found {}", token),
+                    None => bail!("expected a number at the end of the condition"),
+                }
+            }
+            Some(token) => bail!("expected a variable, found {}", token),
+            None => bail!("the condition is incomplete"),
+        }
+    }
+
+    /// Consumes the next token if it's the given keyword or its symbol.
+    fn next_is_keyword(&mut self, keyword: &str, symbol: &Token) -> bool {
+        let found = match self.tokens.peek() {
+            Some(Token::Word(word)) => word == keyword,
+            Some(token) => token == symbol,
+            None => false,
+        };
+        if found {
+            let _ = self.tokens.next();
+        }
+        found
+    }
+}
+
+/// Policy evaluation testing module.
+#[cfg(test)]
+mod test {
+    use super::{Condition, Operator, Policy, Variable};
+    use crate::{
+        config::Config, criticality::Criticality, results::Results,
+        static_analysis::libraries::Origin,
+    };
+
+    /// Checks the parsing of policy conditions.
+    #[test]
+    fn it_parse() {
+        assert_eq!(
+            Condition::parse("critical > 0").unwrap(),
+            Condition::Compare(
+                Variable::Vulnerabilities(Criticality::Critical, None),
+                Operator::Greater,
+                0
+            )
+        );
+        assert_eq!(
+            Condition::parse("not (score>=80 || high.first_party != 2) and low <= 10").unwrap(),
+            Condition::And(
+                Box::new(Condition::Not(Box::new(Condition::Or(
+                    Box::new(Condition::Compare(
+                        Variable::Score,
+                        Operator::GreaterOrEqual,
+                        80
+                    )),
+                    Box::new(Condition::Compare(
+                        Variable::Vulnerabilities(Criticality::High, Some(Origin::FirstParty)),
+                        Operator::NotEqual,
+                        2
+                    )),
+                )))),
+                Box::new(Condition::Compare(
+                    Variable::Vulnerabilities(Criticality::Low, None),
+                    Operator::LessOrEqual,
+                    10
+                )),
+            )
+        );
+
+        assert!(Condition::parse("").is_err());
+        assert!(Condition::parse("critical").is_err());
+        assert!(Condition::parse("critical > ").is_err());
+        assert!(Condition::parse("severe > 0").is_err());
+        assert!(Condition::parse("high.vendor > 0").is_err());
+        assert!(Condition::parse("(critical > 0").is_err());
+        assert!(Condition::parse("critical > 0 high > 1").is_err());
+        assert!(Condition::parse("critical = 0").is_err());
+    }
+
+    /// Checks the evaluation of policies over the results of an analysis.
+    #[test]
+    fn it_evaluate() {
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Weak Algorithms",
+                "description": "Description",
+                "file": "classes/com/example/Main.java",
+                "language": "java",
+                "line": 3,
+                "code": "MessageDigest.getInstance(\"MD5\");",
+                "origin": "first_party"
+            }, {
+                "criticality": "high",
+                "name": "Weak Algorithms",
+                "description": "Description",
+                "file": "classes/okhttp3/Client.java",
+                "language": "java",
+                "line": 3,
+                "code": "MessageDigest.getInstance(\"MD5\");",
+                "origin": "sdk",
+                "sdk": "OkHttp"
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        results.compute_score(&Config::default());
+
+        let policy = Policy::new("Release gate", "critical > 0 or high.first_party > 0").unwrap();
+        let result = policy.evaluate(&results);
+        assert_eq!(result.name(), "Release gate");
+        assert_eq!(result.fail_if(), "critical > 0 or high.first_party > 0");
+        assert!(result.is_failed());
+
+        assert!(
+            Policy::new("SDKs", "high.sdk == 1 && high.third_party == 0")
+                .unwrap()
+                .evaluate(&results)
+                .is_failed()
+        );
+        assert!(!Policy::new("Score", "score < 80")
+            .unwrap()
+            .evaluate(&results)
+            .is_failed());
+    }
+}
@@ -0,0 +1,349 @@
+//! CycloneDX software bill of materials generation module.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::Local;
+use clap::crate_version;
+use failure::Error;
+use serde_json::ser;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::Config,
+    print_warning,
+    results::{report::Generator, HashAlgorithm, Results},
+    static_analysis::{
+        libraries::find_sdks,
+        payloads::{find_payloads, PayloadKind},
+    },
+};
+
+/// Version of the CycloneDX specification of the generated bills of materials.
+const SPEC_VERSION: &str = "1.4";
+
+/// Folders of the application where the `META-INF` version files of its libraries can be.
+const META_INF_FOLDERS: [&str; 2] = ["META-INF", "original/META-INF"];
+
+/// CycloneDX software bill of materials generator.
+///
+/// It generates a `bom.json` file with the components found in the decompiled application: the
+/// Maven libraries that left a version file in `META-INF`, the well known SDKs found in the
+/// decompiled code and the native libraries, so that the results can be fed to dependency
+/// vulnerability tools.
+pub struct CycloneDx {
+    /// Package name.
+    package: String,
+}
+
+impl CycloneDx {
+    /// Creates a new CycloneDX software bill of materials generator.
+    pub fn new<S: Into<String>>(package: S) -> Self {
+        Self {
+            package: package.into(),
+        }
+    }
+}
+
+impl Generator for CycloneDx {
+    #[allow(clippy::print_stdout)]
+    fn generate(&mut self, config: &Config, results: &Results, output: &Path) -> Result<(), Error> {
+        if config.is_verbose() {
+            println!("Starting CycloneDX SBOM generation. First we search for the components.")
+        }
+        let dist_folder = config.dist_folder().join(&self.package);
+        let mut components = Vec::new();
+        for folder in &META_INF_FOLDERS {
+            components.extend(maven_components(&dist_folder.join(folder))?);
+        }
+        components.extend(
+            find_sdks(dist_folder.join("classes"))
+                .into_iter()
+                .map(Component::library),
+        );
+        components.extend(native_components(&dist_folder)?);
+        components.sort();
+        components.dedup();
+
+        let bom = Bom {
+            bom_format: "CycloneDX",
+            spec_version: SPEC_VERSION,
+            version: 1,
+            metadata: Metadata {
+                timestamp: Local::now().to_rfc3339(),
+                tools: vec![Tool {
+                    vendor: "SUPER Android Analyzer",
+                    name: "super-analyzer",
+                    version: crate_version!(),
+                }],
+                component: Component {
+                    kind: "application",
+                    version: Some(results.app_version.clone()),
+                    hashes: results
+                        .app_fingerprint
+                        .hash(HashAlgorithm::Sha256)
+                        .map(|hash| ComponentHash::sha256(hash.to_owned()))
+                        .into_iter()
+                        .collect(),
+                    ..Component::library(results.app_package.clone())
+                },
+            },
+            components,
+        };
+
+        if config.is_verbose() {
+            println!("The components have been found. Now it's time to write the file.")
+        }
+        let mut f = BufWriter::new(File::create(output.join("bom.json"))?);
+        ser::to_writer_pretty(&mut f, &bom)?;
+        f.flush()?;
+
+        Ok(())
+    }
+}
+
+/// CycloneDX bill of materials.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bom {
+    /// Format of the bill of materials, always `CycloneDX`.
+    bom_format: &'static str,
+    /// Version of the CycloneDX specification.
+    spec_version: &'static str,
+    /// Version of the bill of materials.
+    version: u32,
+    /// Metadata of the bill of materials.
+    metadata: Metadata,
+    /// Components of the application.
+    components: Vec<Component>,
+}
+
+/// Metadata of a bill of materials.
+#[derive(Debug, Serialize)]
+struct Metadata {
+    /// Time of the generation of the bill of materials.
+    timestamp: String,
+    /// Tools that generated the bill of materials.
+    tools: Vec<Tool>,
+    /// Component described by the bill of materials, the application.
+    component: Component,
+}
+
+/// Tool that generated a bill of materials.
+#[derive(Debug, Serialize)]
+struct Tool {
+    /// Vendor of the tool.
+    vendor: &'static str,
+    /// Name of the tool.
+    name: &'static str,
+    /// Version of the tool.
+    version: &'static str,
+}
+
+/// Component of a bill of materials.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Component {
+    /// Type of the component.
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Group of the component, such as the Maven group ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    /// Name of the component.
+    name: String,
+    /// Version of the component, if it's known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// Package URL of the component, if it's known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    /// Hashes of the component.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<ComponentHash>,
+    /// Properties of the component, such as its path in the application.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Property>,
+}
+
+impl Component {
+    /// Creates a library component with the given name.
+    fn library<S: Into<String>>(name: S) -> Self {
+        Self {
+            kind: "library",
+            group: None,
+            name: name.into(),
+            version: None,
+            purl: None,
+            hashes: Vec::new(),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Creates the component of a Maven library from its `META-INF` version file.
+    ///
+    /// Version files are named after the group and the artifact of the library, separated by an
+    /// underscore, such as `androidx.core_core.version`, and contain its version.
+    fn from_version_file(file_name: &str, contents: &str) -> Option<Self> {
+        let artifact = file_name.trim_end_matches(".version");
+        let mut parts = artifact.splitn(2, '_');
+        let (group, name) = match (parts.next(), parts.next()) {
+            (Some(group), Some(name)) if !group.is_empty() && !name.is_empty() => (group, name),
+            _ => return None,
+        };
+        let version = contents.trim();
+        if version.is_empty() || version.contains(char::is_whitespace) {
+            return None;
+        }
+
+        Some(Self {
+            group: Some(group.to_owned()),
+            version: Some(version.to_owned()),
+            purl: Some(format!("pkg:maven/{}/{}@{}", group, name, version)),
+            ..Self::library(name)
+        })
+    }
+}
+
+/// Hash of a component.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct ComponentHash {
+    /// Hash algorithm.
+    alg: &'static str,
+    /// Hexadecimal hash.
+    content: String,
+}
+
+impl ComponentHash {
+    /// Creates a SHA-256 hash.
+    fn sha256(content: String) -> Self {
+        Self {
+            alg: "SHA-256",
+            content,
+        }
+    }
+}
+
+/// Property of a component.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Property {
+    /// Name of the property.
+    name: &'static str,
+    /// Value of the property.
+    value: String,
+}
+
+/// Gets the components of the Maven libraries with a version file in the given folder.
+fn maven_components(meta_inf: &Path) -> Result<Vec<Component>, Error> {
+    let mut components = Vec::new();
+    if !meta_inf.is_dir() {
+        return Ok(components);
+    }
+
+    for entry in fs::read_dir(meta_inf)? {
+        let path = entry?.path();
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) if file_name.ends_with(".version") => file_name.to_owned(),
+            _ => continue,
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => components.extend(Component::from_version_file(&file_name, &contents)),
+            Err(e) => print_warning(format!(
+                "could not read the {} library version file: {}",
+                file_name, e
+            )),
+        }
+    }
+    Ok(components)
+}
+
+/// Gets the components of the native libraries of the application, in the `lib` folder and
+/// hidden in its resources.
+fn native_components(dist_folder: &Path) -> Result<Vec<Component>, Error> {
+    let mut libraries = Vec::new();
+    let lib_folder = dist_folder.join("lib");
+    if lib_folder.is_dir() {
+        for abi_entry in fs::read_dir(&lib_folder)? {
+            let abi_path = abi_entry?.path();
+            if !abi_path.is_dir() {
+                continue;
+            }
+            let abi = abi_path
+                .file_name()
+                .map(|abi| abi.to_string_lossy().into_owned());
+            for entry in fs::read_dir(&abi_path)? {
+                let path = entry?.path();
+                if path.extension().map_or(false, |e| e == "so") {
+                    libraries.push((path.strip_prefix(dist_folder)?.to_path_buf(), abi.clone()));
+                }
+            }
+        }
+    }
+    for payload in find_payloads(dist_folder) {
+        if payload.kind() == PayloadKind::Elf {
+            libraries.push((
+                payload.path().to_path_buf(),
+                payload.architecture().map(str::to_owned),
+            ));
+        }
+    }
+
+    let mut components = Vec::with_capacity(libraries.len());
+    for (path, architecture) in libraries {
+        let mut component = Component::library(
+            path.file_name()
+                .expect("native libraries must have a file name")
+                .to_string_lossy(),
+        );
+        component
+            .hashes
+            .push(ComponentHash::sha256(hex::encode(Sha256::digest(
+                &fs::read(dist_folder.join(&path))?,
+            ))));
+        component.properties.push(Property {
+            name: "super:path",
+            value: path.display().to_string(),
+        });
+        if let Some(architecture) = architecture {
+            component.properties.push(Property {
+                name: "super:architecture",
+                value: architecture,
+            });
+        }
+        components.push(component);
+    }
+    Ok(components)
+}
+
+/// CycloneDX software bill of materials testing module.
+#[cfg(test)]
+mod test {
+    use super::Component;
+
+    /// Checks the components created from `META-INF` version files.
+    #[test]
+    fn it_from_version_file() {
+        let component =
+            Component::from_version_file("androidx.core_core.version", "1.0.1\n").unwrap();
+        assert_eq!(component.group.as_ref().unwrap(), "androidx.core");
+        assert_eq!(component.name, "core");
+        assert_eq!(component.version.as_ref().unwrap(), "1.0.1");
+        assert_eq!(
+            component.purl.as_ref().unwrap(),
+            "pkg:maven/androidx.core/core@1.0.1"
+        );
+
+        let component =
+            Component::from_version_file("com.android.support_appcompat-v7.version", "28.0.0")
+                .unwrap();
+        assert_eq!(component.name, "appcompat-v7");
+
+        assert!(Component::from_version_file("kotlin.version", "1.3.11").is_none());
+        assert!(Component::from_version_file("androidx.core_core.version", "").is_none());
+        assert!(
+            Component::from_version_file("androidx.core_core.version", "task ':core'").is_none()
+        );
+    }
+}
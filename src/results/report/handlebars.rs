@@ -10,11 +10,14 @@ use std::{
 use colored::Colorize;
 use failure::{Error, ResultExt};
 use handlebars::Handlebars;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
 use serde_json::{value::Value, Map};
 
 use crate::{
     config::Config,
-    copy_folder, error,
+    copy_folder, error, print_warning,
     results::{
         handlebars_helpers::{
             all_code, all_lines, generate_menu, html_code, line_numbers, report_index,
@@ -25,6 +28,19 @@ use crate::{
     },
 };
 
+#[cfg(feature = "screenshots")]
+use crate::results::report::screenshots;
+
+lazy_static! {
+    /// Stylesheet links of the rendered report, with the path of the stylesheet.
+    static ref STYLESHEET_LINK: Regex =
+        Regex::new(r#"<link rel="stylesheet" href="([^":]+)">"#).unwrap();
+    /// External scripts of the rendered report, with the path of the script.
+    static ref SCRIPT_SOURCE: Regex = Regex::new(r#"<script src="([^":]+)"></script>"#).unwrap();
+    /// SVG images of the rendered report, with the path of the image.
+    static ref SVG_SOURCE: Regex = Regex::new(r#"<img src="([^":]+\.svg)""#).unwrap();
+}
+
 /// Handlebars report generator.
 pub struct Report {
     /// Handlebars template structure.
@@ -48,6 +64,24 @@ impl Report {
         })
     }
 
+    /// Renders the aggregate report of several analyses with the `index` template of the given
+    /// template path, with the template assets inlined, since it's not written next to them.
+    ///
+    /// It returns `None` if there is no `index` template.
+    pub fn render_index<T: Serialize>(
+        template_path: &Path,
+        index: &T,
+    ) -> Result<Option<String>, Error> {
+        let handlebars =
+            Self::load_templates(template_path).context("Could not load handlebars templates")?;
+        if handlebars.get_template("index").is_none() {
+            return Ok(None);
+        }
+
+        let index = handlebars.render("index", index)?;
+        Ok(Some(inline_assets(&index, template_path)))
+    }
+
     /// Loads templates from the given path.
     fn load_templates<P: AsRef<Path>>(template_path: P) -> Result<Handlebars, Error> {
         let mut handlebars = Handlebars::new();
@@ -99,16 +133,10 @@ impl Report {
     }
 
     /// Generates the HTML files for the code.
-    fn generate_code_html_files(&self, config: &Config, results: &Results) -> Result<(), Error> {
-        let menu = Value::Array(self.generate_code_html_folder("", config, results)?);
+    fn generate_code_html_files(&self, config: &Config, output: &Path) -> Result<(), Error> {
+        let menu = Value::Array(self.generate_code_html_folder("", config, output)?);
 
-        let mut f = File::create(
-            config
-                .results_folder()
-                .join(&results.app_package())
-                .join("src")
-                .join("index.html"),
-        )?;
+        let mut f = File::create(output.join("src").join("index.html"))?;
 
         let mut data = BTreeMap::new();
         let _ = data.insert("menu", menu);
@@ -122,7 +150,7 @@ impl Report {
         &self,
         path: P,
         config: &Config,
-        results: &Results,
+        output: &Path,
     ) -> Result<Vec<Value>, Error> {
         if path.as_ref() == Path::new("classes/android")
             || path.as_ref() == Path::new("classes/com/google/android/gms")
@@ -132,13 +160,7 @@ impl Report {
         }
         let dir_iter = fs::read_dir(config.dist_folder().join(&self.package).join(path.as_ref()))?;
 
-        fs::create_dir_all(
-            config
-                .results_folder()
-                .join(&results.app_package())
-                .join("src")
-                .join(path.as_ref()),
-        )?;
+        fs::create_dir_all(output.join("src").join(path.as_ref()))?;
 
         let mut menu = Vec::new();
         for entry in dir_iter {
@@ -152,13 +174,9 @@ impl Report {
 
             if path.is_dir() {
                 if stripped != Path::new("original") {
-                    let inner_menu = self.generate_code_html_folder(stripped, config, results)?;
+                    let inner_menu = self.generate_code_html_folder(stripped, config, output)?;
                     if inner_menu.is_empty() {
-                        let path = config
-                            .results_folder()
-                            .join(&results.app_package())
-                            .join("src")
-                            .join(stripped);
+                        let path = output.join("src").join(stripped);
                         if path.exists() {
                             fs::remove_dir_all(path)?;
                         }
@@ -174,7 +192,7 @@ impl Report {
             } else {
                 match path.extension() {
                     Some(e) if e == "xml" || e == "java" => {
-                        self.generate_code_html_for(&stripped, config, results, &self.package)?;
+                        self.generate_code_html_for(&stripped, config, output, &self.package)?;
                         let name = path.file_name().unwrap().to_string_lossy().into_owned();
                         let mut data = Map::with_capacity(3);
                         let _ = data.insert("name".to_owned(), Value::String(name));
@@ -201,7 +219,7 @@ impl Report {
         &self,
         path: P,
         config: &Config,
-        results: &Results,
+        output: &Path,
         cli_package_name: S,
     ) -> Result<(), Error> {
         let code = fs::read_to_string(
@@ -212,12 +230,7 @@ impl Report {
         )?;
         let mut f_out = File::create(format!(
             "{}.html",
-            config
-                .results_folder()
-                .join(&results.app_package())
-                .join("src")
-                .join(path.as_ref())
-                .display()
+            output.join("src").join(path.as_ref()).display()
         ))?;
 
         let mut back_path = String::new();
@@ -241,57 +254,137 @@ impl Report {
 
 impl Generator for Report {
     #[allow(clippy::print_stdout)]
-    fn generate(&mut self, config: &Config, results: &Results) -> Result<(), Error> {
+    fn generate(&mut self, config: &Config, results: &Results, output: &Path) -> Result<(), Error> {
         if config.is_verbose() {
             println!("Starting HTML report generation. First we create the file.")
         }
-        let mut f = File::create(
-            config
-                .results_folder()
-                .join(&results.app_package)
-                .join("index.html"),
-        )?;
+        let mut f = File::create(output.join("index.html"))?;
         if config.is_verbose() {
             println!("The report file has been created. Now it's time to fill it.")
         }
 
-        f.write_all(self.handler.render("report", results)?.as_bytes())?;
+        let mut data = serde_json::to_value(results)?;
+        // Screenshots are separate images, so they are not included in single file reports.
+        if config.has_to_take_screenshots() && !config.is_single_file_html() {
+            #[cfg(feature = "screenshots")]
+            {
+                screenshots::embed(config, &self.package, &mut data, output);
+            }
+            #[cfg(not(feature = "screenshots"))]
+            {
+                print_warning(
+                    "evidence screenshots need SUPER to be built with the `screenshots` feature",
+                );
+            }
+        }
+
+        if config.is_single_file_html() {
+            // The source code tree is not generated, so the template must not link to it.
+            if let Value::Object(ref mut data) = data {
+                let _ = data.insert("single_file".to_owned(), Value::Bool(true));
+            }
+            let report = self.handler.render("report", &data)?;
+            f.write_all(inline_assets(&report, &config.template_path()).as_bytes())?;
+
+            return Ok(());
+        }
+
+        f.write_all(self.handler.render("report", &data)?.as_bytes())?;
 
         for entry in fs::read_dir(config.template_path())? {
             let entry = entry?;
             let entry_path = entry.path();
             if entry.file_type()?.is_dir() {
-                copy_folder(
-                    &entry_path,
-                    &config
-                        .results_folder()
-                        .join(&results.app_package())
-                        .join(entry_path.file_name().unwrap()),
-                )?;
+                copy_folder(&entry_path, &output.join(entry_path.file_name().unwrap()))?;
             } else {
                 match entry_path.as_path().extension() {
                     Some(e) if e == "hbs" => {}
                     None => {}
                     _ => {
-                        let _ = fs::copy(
-                            &entry_path,
-                            &config.results_folder().join(&results.app_package()),
-                        )?;
+                        let _ = fs::copy(&entry_path, &output)?;
                     }
                 }
             }
         }
 
-        self.generate_code_html_files(config, results)?;
+        // Reports generated from stored results might not have the decompiled sources anymore.
+        if config.dist_folder().join(&self.package).exists() {
+            self.generate_code_html_files(config, output)?;
+        } else if config.is_verbose() {
+            println!("The decompiled sources are not available, so they won't be included.");
+        }
 
         Ok(())
     }
 }
 
+/// Inlines the stylesheets, scripts and SVG images of the template in the rendered report, so
+/// that it can be shared as a single self-contained file.
+///
+/// Assets that can't be read are left as they are, with a warning.
+fn inline_assets(report: &str, template_path: &Path) -> String {
+    let read_asset = |captures: &Captures<'_>| {
+        fs::read_to_string(template_path.join(&captures[1]))
+            .map_err(|e| {
+                print_warning(format!(
+                    "could not inline the {} template asset in the report: {}",
+                    &captures[1], e
+                ))
+            })
+            .ok()
+    };
+
+    let report = STYLESHEET_LINK.replace_all(report, |captures: &Captures<'_>| {
+        read_asset(captures).map_or_else(
+            || captures[0].to_owned(),
+            |css| format!("<style>\n{}\n</style>", css),
+        )
+    });
+    let report = SCRIPT_SOURCE.replace_all(&report, |captures: &Captures<'_>| {
+        read_asset(captures).map_or_else(
+            || captures[0].to_owned(),
+            // A closing tag inside the script would end it early.
+            |js| {
+                format!(
+                    "<script>\n{}\n</script>",
+                    js.replace("</script", "<\\/script")
+                )
+            },
+        )
+    });
+    let report = SVG_SOURCE.replace_all(&report, |captures: &Captures<'_>| {
+        read_asset(captures).map_or_else(
+            || captures[0].to_owned(),
+            |svg| format!(r#"<img src="{}""#, svg_data_uri(&svg)),
+        )
+    });
+
+    report.into_owned()
+}
+
+/// Encodes an SVG image as a `data:` URI.
+///
+/// SVG images are text, so only the characters that are not allowed in URIs are percent-encoded,
+/// which keeps the URI much smaller than encoding the whole image.
+fn svg_data_uri(svg: &str) -> String {
+    let mut uri = String::from("data:image/svg+xml,");
+    for byte in svg.trim().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => uri.push(byte as char),
+            b' ' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' | b'=' | b',' | b';' | b'(' | b')'
+            | b'!' | b'*' | b'+' | b'@' | b'\'' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
 /// Handlebars templates testing module.
 #[cfg(test)]
 mod test {
-    use super::Report;
+    use std::fs;
+
+    use super::{inline_assets, svg_data_uri, Report};
     use crate::config::Config;
 
     /// Test the creation of a new report.
@@ -311,4 +404,32 @@ mod test {
     fn it_load_templates() {
         let _ = Report::load_templates(&Config::default().template_path()).unwrap();
     }
+
+    /// Checks the encoding of SVG images as `data:` URIs.
+    #[test]
+    fn it_svg_data_uri() {
+        assert_eq!(
+            svg_data_uri("<svg width=\"1\"><path d=\"M0 0\"/></svg>\n"),
+            "data:image/svg+xml,%3Csvg width=%221%22%3E%3Cpath d=%22M0 0%22/%3E%3C/svg%3E"
+        );
+    }
+
+    /// Checks that the template assets are inlined in the report.
+    #[test]
+    fn it_inline_assets() {
+        let template_path = Config::default().template_path();
+        let report = inline_assets(
+            r#"<link rel="stylesheet" href="css/style.css">
+<link rel="stylesheet" href="css/missing.css">
+<script src="js/src_nav.js"></script>
+<img src="img/logo.svg" alt="Logo">"#,
+            &template_path,
+        );
+
+        let css = fs::read_to_string(template_path.join("css").join("style.css")).unwrap();
+        assert!(report.contains(&format!("<style>\n{}\n</style>", css)));
+        assert!(report.contains(r#"<link rel="stylesheet" href="css/missing.css">"#));
+        assert!(!report.contains("js/src_nav.js"));
+        assert!(report.contains(r#"<img src="data:image/svg+xml,%3C"#));
+    }
 }
@@ -1,6 +1,6 @@
 //! JSON report generation module.
 
-use std::{fs::File, io::BufWriter};
+use std::{fs::File, io::BufWriter, path::Path};
 
 use failure::Error;
 use serde_json::ser;
@@ -22,15 +22,12 @@ impl Json {
 
 impl Generator for Json {
     #[allow(clippy::print_stdout)]
-    fn generate(&mut self, config: &Config, results: &Results) -> Result<(), Error> {
+    fn generate(&mut self, config: &Config, results: &Results, output: &Path) -> Result<(), Error> {
         if config.is_verbose() {
             println!("Starting JSON report generation. First we create the file.")
         }
         let mut f = BufWriter::new(File::create(
-            config
-                .results_folder()
-                .join(&results.app_package())
-                .join("results.json"),
+            output.join(format!("{}.json", config.report_file_name())),
         )?);
         if config.is_verbose() {
             println!("The report file has been created. Now it's time to fill it.")
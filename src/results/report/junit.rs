@@ -0,0 +1,290 @@
+//! JUnit XML report generation module.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use failure::Error;
+
+use crate::{
+    config::Config,
+    results::{report::Generator, Results, Vulnerability, CRITICALITIES},
+};
+
+/// JUnit XML report generator.
+///
+/// It generates a `junit.xml` file with a test suite for each criticality, where each
+/// vulnerability is a failing test case, so that CI systems can show the findings natively. Test
+/// suites without vulnerabilities get a passing test case, so that they are still shown. The
+/// configured policies get their own test suite, with a test case for each policy.
+pub struct JUnitXml;
+
+impl JUnitXml {
+    /// Creates a new JUnit XML report generator.
+    pub fn new() -> Self {
+        JUnitXml
+    }
+}
+
+impl Generator for JUnitXml {
+    #[allow(clippy::print_stdout)]
+    fn generate(&mut self, config: &Config, results: &Results, output: &Path) -> Result<(), Error> {
+        if config.is_verbose() {
+            println!("Starting JUnit XML report generation. First we create the file.")
+        }
+        let mut f = BufWriter::new(File::create(output.join("junit.xml"))?);
+        if config.is_verbose() {
+            println!("The report file has been created. Now it's time to fill it.")
+        }
+        write_report(results, &mut f)?;
+        f.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Writes the JUnit XML report of the given results.
+fn write_report<W: Write>(results: &Results, out: &mut W) -> io::Result<()> {
+    let counts: Vec<_> = CRITICALITIES
+        .iter()
+        .map(|criticality| results.vulnerability_count(*criticality))
+        .collect();
+    let failed_policies = results.failed_policies().len();
+    let failures: usize = counts.iter().sum::<usize>() + failed_policies;
+    // Test suites without vulnerabilities have a passing test case.
+    let tests: usize =
+        counts.iter().map(|count| (*count).max(1)).sum::<usize>() + results.policies.len();
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="{}" tests="{}" failures="{}">"#,
+        xml_escape(&results.app_package),
+        tests,
+        failures
+    )?;
+    for criticality in &CRITICALITIES {
+        let count = results.vulnerability_count(*criticality);
+        let classname = format!("{}.{}", results.app_package, criticality);
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+            criticality,
+            count.max(1),
+            count
+        )?;
+        if let Some(score) = results.score {
+            writeln!(out, "    <properties>")?;
+            writeln!(
+                out,
+                r#"      <property name="security_score" value="{}"/>"#,
+                score.value()
+            )?;
+            writeln!(
+                out,
+                r#"      <property name="security_grade" value="{}"/>"#,
+                score.grade()
+            )?;
+            writeln!(out, "    </properties>")?;
+        }
+        if count == 0 {
+            writeln!(
+                out,
+                r#"    <testcase name="No {} vulnerabilities" classname="{}"/>"#,
+                criticality,
+                xml_escape(&classname)
+            )?;
+        }
+        for vulnerability in results.vulnerabilities.iter(*criticality)? {
+            write_test_case(&vulnerability?, &classname, out)?;
+        }
+        writeln!(out, "  </testsuite>")?;
+    }
+    if !results.policies.is_empty() {
+        let classname = format!("{}.policies", results.app_package);
+        writeln!(
+            out,
+            r#"  <testsuite name="policies" tests="{}" failures="{}">"#,
+            results.policies.len(),
+            failed_policies
+        )?;
+        for policy in &results.policies {
+            write!(
+                out,
+                r#"    <testcase name="{}" classname="{}""#,
+                xml_escape(policy.name()),
+                xml_escape(&classname)
+            )?;
+            if policy.is_failed() {
+                writeln!(out, ">")?;
+                writeln!(
+                    out,
+                    r#"      <failure message="{}" type="policy">"#,
+                    xml_escape(&format!("Fails if {}", policy.fail_if()))
+                )?;
+                writeln!(out, "      </failure>")?;
+                writeln!(out, "    </testcase>")?;
+            } else {
+                writeln!(out, "/>")?;
+            }
+        }
+        writeln!(out, "  </testsuite>")?;
+    }
+    writeln!(out, "</testsuites>")?;
+
+    Ok(())
+}
+
+/// Writes a vulnerability as a failing test case.
+fn write_test_case<W: Write>(
+    vulnerability: &Vulnerability,
+    classname: &str,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut location = String::new();
+    if let Some(file) = vulnerability.get_file() {
+        location = file.display().to_string();
+        if let Some(line) = vulnerability.get_start_line() {
+            location.push_str(&format!(":{}", line + 1));
+        }
+    }
+
+    write!(
+        out,
+        r#"    <testcase name="{}" classname="{}""#,
+        xml_escape(&if location.is_empty() {
+            vulnerability.get_name().to_owned()
+        } else {
+            format!("{} ({})", vulnerability.get_name(), location)
+        }),
+        xml_escape(classname)
+    )?;
+    if let Some(file) = vulnerability.get_file() {
+        write!(
+            out,
+            r#" file="{}""#,
+            xml_escape(&file.display().to_string())
+        )?;
+    }
+    if let Some(line) = vulnerability.get_start_line() {
+        write!(out, r#" line="{}""#, line + 1)?;
+    }
+    writeln!(out, ">")?;
+
+    writeln!(
+        out,
+        r#"      <failure message="{}" type="{}">"#,
+        xml_escape(vulnerability.get_name()),
+        vulnerability.get_criticality()
+    )?;
+    writeln!(out, "{}", xml_escape(vulnerability.get_description()))?;
+    if let Some(rule_id) = vulnerability.get_rule_id() {
+        writeln!(out, "Rule: {}", xml_escape(rule_id))?;
+    }
+    if let Some(cwe) = vulnerability.get_cwe() {
+        writeln!(out, "CWE-{}", cwe)?;
+    }
+    if let Some(owasp_masvs) = vulnerability.get_owasp_masvs() {
+        writeln!(out, "OWASP MASVS: {}", xml_escape(owasp_masvs))?;
+    }
+    if !location.is_empty() {
+        writeln!(out, "{}", xml_escape(&location))?;
+    }
+    if let Some(code) = vulnerability.get_code() {
+        writeln!(out, "{}", xml_escape(code.trim_end()))?;
+    }
+    writeln!(out, "      </failure>")?;
+    writeln!(out, "    </testcase>")?;
+
+    Ok(())
+}
+
+/// Escapes the text so that it can be used in XML text and attributes.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0.
+            c if c.is_control() && c != '\n' && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// JUnit XML report testing module.
+#[cfg(test)]
+mod test {
+    use super::{write_report, xml_escape};
+    use crate::{
+        config::Config,
+        results::{Policy, Results},
+    };
+
+    /// Checks the escaping of XML text.
+    #[test]
+    fn it_xml_escape() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">&'\u{1}</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+
+    /// Checks the generated report.
+    #[test]
+    fn it_write_report() {
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>",
+                "rule_id": "exported-component",
+                "cwe": 926
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        results.compute_score(&Config::default());
+        let policy = Policy::new("No high vulnerabilities", "high > 0").unwrap();
+        results.policies = vec![policy.evaluate(&results)];
+
+        let mut report = Vec::new();
+        write_report(&results, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+
+        assert!(report.contains(r#"<testsuites name="com.example" tests="6" failures="2">"#));
+        assert!(report.contains(r#"<testsuite name="high" tests="1" failures="1">"#));
+        assert!(report.contains(r#"<property name="security_score" value="90"/>"#));
+        assert!(report.contains(r#"<testsuite name="critical" tests="1" failures="0">"#));
+        assert!(report.contains(
+            r#"<testcase name="Exported activity (AndroidManifest.xml:3)" classname="com.example.high" file="AndroidManifest.xml" line="3">"#
+        ));
+        assert!(report.contains(r#"<failure message="Exported activity" type="high">"#));
+        assert!(report.contains("Description\nRule: exported-component\nCWE-926\n"));
+        assert!(report.contains("&lt;activity&gt;"));
+        assert!(report.contains(r#"<testsuite name="policies" tests="1" failures="1">"#));
+        assert!(report.contains(r#"<failure message="Fails if high &gt; 0" type="policy">"#));
+    }
+}
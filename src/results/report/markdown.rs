@@ -0,0 +1,605 @@
+//! Markdown report generation module.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use chrono::Local;
+use clap::crate_version;
+use failure::Error;
+
+use crate::{
+    config::Config,
+    criticality::Criticality,
+    results::{report::Generator, HashAlgorithm, Index, Results, Vulnerability, CRITICALITIES},
+};
+
+/// Markdown report generator.
+///
+/// It generates a single Markdown file, `results.md` by default, with the application data and
+/// the vulnerabilities grouped by criticality, that can be pasted into issues or wiki pages.
+pub struct Markdown;
+
+impl Markdown {
+    /// Creates a new Markdown report generator.
+    pub fn new() -> Self {
+        Markdown
+    }
+}
+
+impl Generator for Markdown {
+    #[allow(clippy::print_stdout)]
+    fn generate(&mut self, config: &Config, results: &Results, output: &Path) -> Result<(), Error> {
+        if config.is_verbose() {
+            println!("Starting Markdown report generation. First we create the file.")
+        }
+        let mut f = BufWriter::new(File::create(
+            output.join(format!("{}.md", config.report_file_name())),
+        )?);
+        if config.is_verbose() {
+            println!("The report file has been created. Now it's time to fill it.")
+        }
+        write_report(results, &mut f)?;
+        f.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Writes the Markdown report of the given results.
+fn write_report<W: Write>(results: &Results, out: &mut W) -> io::Result<()> {
+    match results.engagement.title() {
+        Some(title) => {
+            writeln!(out, "# {}", title)?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Security report of `{}`, generated on {} with SUPER Android Analyzer {}.",
+                results.app_package,
+                Local::now().to_rfc2822(),
+                crate_version!()
+            )?;
+        }
+        None => {
+            writeln!(out, "# Security report of `{}`", results.app_package)?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Report generated on {} with SUPER Android Analyzer {}.",
+                Local::now().to_rfc2822(),
+                crate_version!()
+            )?;
+        }
+    }
+    writeln!(out)?;
+    if let Some(client) = results.engagement.client() {
+        writeln!(out, "- **Client:** {}", client)?;
+    }
+    if let Some(id) = results.engagement.id() {
+        writeln!(out, "- **Engagement:** {}", id)?;
+    }
+    if results.engagement.client().is_some() || results.engagement.id().is_some() {
+        writeln!(out)?;
+    }
+
+    writeln!(out, "## Application data")?;
+    writeln!(out)?;
+    if results.cancelled {
+        writeln!(
+            out,
+            "> **Warning:** the analysis was cancelled before it was complete. These results are \
+             partial, and they can miss vulnerabilities."
+        )?;
+        writeln!(out)?;
+    }
+    if results.manifest_recovered {
+        writeln!(
+            out,
+            "> **Warning:** the manifest of the application was malformed and it was only \
+             partially recovered. The application data and the manifest findings are low \
+             confidence."
+        )?;
+        writeln!(out)?;
+    }
+    if let Some(translation) = results.translation.filter(|t| t.is_incomplete()) {
+        writeln!(
+            out,
+            "> **Warning:** only {:.1}% of the classes of the application could be decompiled. \
+             The rest of the code was not analyzed, so it can contain vulnerabilities that are \
+             not reported.",
+            translation.decompilation_completeness()
+        )?;
+        writeln!(out)?;
+    }
+    writeln!(out, "| Property | Value |")?;
+    writeln!(out, "| --- | --- |")?;
+    writeln!(out, "| Package | `{}` |", results.app_package)?;
+    if !results.app_label.is_empty() {
+        writeln!(out, "| Label | {} |", table_escape(&results.app_label))?;
+    }
+    writeln!(
+        out,
+        "| Version | {} ({}) |",
+        table_escape(&results.app_version),
+        results.app_version_num
+    )?;
+    if let Some(category) = results.app_category {
+        writeln!(out, "| Category | {} |", category.name())?;
+    }
+    if let Some(score) = results.score {
+        writeln!(
+            out,
+            "| Security score | {}/100 (grade {}) |",
+            score.value(),
+            score.grade()
+        )?;
+    }
+    writeln!(
+        out,
+        "| Minimum SDK | {} ({}) |",
+        results.app_min_sdk.number(),
+        results.app_min_sdk.name()
+    )?;
+    if let Some(sdk) = results.app_target_sdk {
+        writeln!(out, "| Target SDK | {} ({}) |", sdk.number(), sdk.name())?;
+    }
+    for algorithm in &[
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::DexSha256,
+    ] {
+        if let Some(hash) = results.app_fingerprint.hash(*algorithm) {
+            writeln!(out, "| {} | `{}` |", algorithm.name(), hash)?;
+        }
+    }
+    for signer in &results.app_signers {
+        writeln!(out, "| Signing certificate (SHA-256) | `{}` |", signer)?;
+    }
+    if let Some(translation) = results.translation {
+        writeln!(
+            out,
+            "| Translated code | {:.1}% of the classes ({} of {}), with {} errors |",
+            translation.completeness(),
+            translation.translated_classes(),
+            translation.dex_classes(),
+            translation.errors()
+        )?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "## Vulnerabilities")?;
+    writeln!(out)?;
+    writeln!(out, "| Criticality | Count |")?;
+    writeln!(out, "| --- | --- |")?;
+    for criticality in &CRITICALITIES {
+        writeln!(
+            out,
+            "| {} | {} |",
+            title(*criticality),
+            results.vulnerability_count(*criticality)
+        )?;
+    }
+
+    if !results.policies.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Policies")?;
+        writeln!(out)?;
+        writeln!(out, "| Policy | Fails if | Result |")?;
+        writeln!(out, "| --- | --- | --- |")?;
+        for policy in &results.policies {
+            writeln!(
+                out,
+                "| {} | `{}` | {} |",
+                table_escape(policy.name()),
+                table_escape(policy.fail_if()),
+                if policy.is_failed() {
+                    "**Failed**"
+                } else {
+                    "Passed"
+                }
+            )?;
+        }
+    }
+
+    if !results.capabilities().is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Capabilities")?;
+        writeln!(out)?;
+        for capability in results.capabilities() {
+            write!(out, "- **{}**", capability.name())?;
+            if capability.is_spyware_indicator() {
+                write!(out, " (spyware indicator)")?;
+            }
+            write!(out, ": {}", capability.description())?;
+            if let Some(file) = capability.file() {
+                match capability.line() {
+                    Some(line) => write!(out, " (`{}:{}`)", file.display(), line)?,
+                    None => write!(out, " (`{}`)", file.display())?,
+                }
+            }
+            writeln!(out)?;
+        }
+    }
+
+    let permission_notes = results.permission_notes();
+    if !permission_notes.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Dangerous permissions")?;
+        writeln!(out)?;
+        for permission in &permission_notes {
+            writeln!(
+                out,
+                "- `{}` (added in SDK {})",
+                permission.permission(),
+                permission.added_in_sdk()
+            )?;
+            for note in permission.notes() {
+                writeln!(out, "  - {}", note)?;
+            }
+        }
+    }
+
+    for criticality in &CRITICALITIES {
+        let count = results.vulnerability_count(*criticality);
+        if count == 0 {
+            continue;
+        }
+        writeln!(out)?;
+        writeln!(out, "### {} ({})", title(*criticality), count)?;
+        for vulnerability in results.vulnerabilities.iter(*criticality)? {
+            writeln!(out)?;
+            write_vulnerability(&vulnerability?, out)?;
+        }
+    }
+
+    if !results.suppressed().is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "## Suppressed findings ({})",
+            results.suppressed().len()
+        )?;
+        writeln!(out)?;
+        writeln!(out, "| Finding | Criticality | File | Reason |")?;
+        writeln!(out, "| --- | --- | --- | --- |")?;
+        for suppressed in results.suppressed() {
+            let vulnerability = suppressed.vulnerability();
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                table_escape(vulnerability.get_name()),
+                vulnerability.get_criticality(),
+                vulnerability
+                    .get_file()
+                    .map_or_else(|| "-".to_owned(), |file| format!("`{}`", file.display())),
+                suppressed
+                    .reason()
+                    .map_or_else(|| "-".to_owned(), table_escape)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the Markdown aggregate report of the analyses of several applications.
+pub fn write_index<W: Write>(index: &Index, out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "# Security report of {} applications",
+        index.applications().len()
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Report generated on {} with SUPER Android Analyzer {}.",
+        Local::now().to_rfc2822(),
+        crate_version!()
+    )?;
+    writeln!(out)?;
+
+    writeln!(out, "## Vulnerabilities")?;
+    writeln!(out)?;
+    writeln!(out, "| Criticality | Count |")?;
+    writeln!(out, "| --- | --- |")?;
+    for criticality in &CRITICALITIES {
+        writeln!(
+            out,
+            "| {} | {} |",
+            title(*criticality),
+            index.vulnerability_count(*criticality)
+        )?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "## Applications")?;
+    writeln!(out)?;
+    write!(out, "| Application | Version |")?;
+    for criticality in &CRITICALITIES {
+        write!(out, " {} |", title(*criticality))?;
+    }
+    writeln!(out, " Score | Worst finding | Report |")?;
+    writeln!(
+        out,
+        "| --- | --- | --- | --- | --- | --- | --- | --- | --- | --- |"
+    )?;
+    for summary in index.applications() {
+        write!(
+            out,
+            "| `{}` | {} |",
+            summary.app_package(),
+            table_escape(summary.app_version())
+        )?;
+        for criticality in &CRITICALITIES {
+            write!(out, " {} |", summary.vulnerability_count(*criticality))?;
+        }
+        match summary.score() {
+            Some(score) => write!(out, " {} ({}) |", score.value(), score.grade())?,
+            None => write!(out, " - |")?,
+        }
+        match summary.worst_finding() {
+            Some(vulnerability) => write!(
+                out,
+                " {} ({}) |",
+                table_escape(vulnerability.get_name()),
+                vulnerability.get_criticality()
+            )?,
+            None => write!(out, " - |")?,
+        }
+        writeln!(out, " [Report]({}) |", summary.report().display())?;
+    }
+
+    Ok(())
+}
+
+/// Writes a vulnerability as a Markdown section.
+fn write_vulnerability<W: Write>(vulnerability: &Vulnerability, out: &mut W) -> io::Result<()> {
+    writeln!(out, "#### {}", vulnerability.get_name())?;
+    writeln!(out)?;
+    if let Some(file) = vulnerability.get_file() {
+        match (vulnerability.get_start_line(), vulnerability.get_end_line()) {
+            (Some(start), Some(end)) if start != end => writeln!(
+                out,
+                "- **File:** `{}`, lines {} to {}",
+                file.display(),
+                start + 1,
+                end + 1
+            )?,
+            (Some(line), _) => {
+                writeln!(out, "- **File:** `{}`, line {}", file.display(), line + 1)?
+            }
+            _ => writeln!(out, "- **File:** `{}`", file.display())?,
+        }
+    }
+    if let Some(rule_id) = vulnerability.get_rule_id() {
+        writeln!(out, "- **Rule:** `{}`", rule_id)?;
+    }
+    if let Some(cwe) = vulnerability.get_cwe() {
+        writeln!(out, "- **CWE:** CWE-{}", cwe)?;
+    }
+    if let Some(owasp_masvs) = vulnerability.get_owasp_masvs() {
+        writeln!(out, "- **OWASP MASVS:** {}", owasp_masvs)?;
+    }
+    if let Some(origin) = vulnerability.get_origin() {
+        writeln!(out, "- **Origin:** {}", origin.name())?;
+    }
+    writeln!(
+        out,
+        "- **Fingerprint:** `{}`",
+        vulnerability.get_fingerprint()
+    )?;
+    writeln!(out)?;
+    writeln!(out, "{}", vulnerability.get_description())?;
+
+    if let Some(code) = vulnerability.get_code() {
+        let language = vulnerability
+            .get_file()
+            .and_then(Path::extension)
+            .map_or_else(Default::default, |extension| extension.to_string_lossy());
+        let fence = fence(code);
+        writeln!(out)?;
+        writeln!(out, "{}{}", fence, language)?;
+        writeln!(out, "{}", code.trim_end())?;
+        writeln!(out, "{}", fence)?;
+    }
+    for reference in vulnerability.get_references() {
+        writeln!(out)?;
+        writeln!(out, "- <{}>", reference)?;
+    }
+
+    Ok(())
+}
+
+/// Gets the title of the given criticality.
+fn title(criticality: Criticality) -> &'static str {
+    match criticality {
+        Criticality::Critical => "Critical",
+        Criticality::High => "High",
+        Criticality::Medium => "Medium",
+        Criticality::Low => "Low",
+        Criticality::Warning => "Warning",
+    }
+}
+
+/// Escapes the text so that it can be used in a table cell.
+fn table_escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Gets a code fence longer than any backtick run in the given code.
+fn fence(code: &str) -> String {
+    let longest = code
+        .split(|c: char| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Markdown report testing module.
+#[cfg(test)]
+mod test {
+    use super::{fence, table_escape, write_index, write_report};
+    use crate::{
+        config::Config,
+        results::{Index, Policy, Results, Summary},
+    };
+
+    /// Checks the escaping of table cells.
+    #[test]
+    fn it_table_escape() {
+        assert_eq!(table_escape("a|b\nc"), "a\\|b c");
+    }
+
+    /// Checks that code fences are longer than the backtick runs in the code.
+    #[test]
+    fn it_fence() {
+        assert_eq!(fence("let a = 1;"), "```");
+        assert_eq!(fence("```\ncode\n```"), "````");
+    }
+
+    /// Checks the generated report.
+    #[test]
+    fn it_write_report() {
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_permissions": ["android.permission.CAMERA"],
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>",
+                "rule_id": "exported-component",
+                "cwe": 926,
+                "owasp_masvs": "MASVS-PLATFORM-1"
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": [],
+            "suppressed": [{
+                "vulnerability": {
+                    "criticality": "low",
+                    "name": "Log usage",
+                    "description": "Description",
+                    "file": "classes/com/example/Main.java"
+                },
+                "reason": "Stripped in release builds"
+            }]
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        results.compute_score(&Config::default());
+        let policy = Policy::new("No high vulnerabilities", "high > 0").unwrap();
+        results.policies = vec![policy.evaluate(&results)];
+
+        let mut report = Vec::new();
+        write_report(&results, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+
+        assert!(report.starts_with("# Security report of `com.example`"));
+        assert!(report.contains("| md5 | `aa` |"));
+        assert!(report.contains("| Security score | 90/100 (grade A) |"));
+        assert!(report.contains("| High | 1 |"));
+        assert!(report.contains("| Critical | 0 |"));
+        assert!(report.contains("| No high vulnerabilities | `high > 0` | **Failed** |"));
+        assert!(report.contains("- `android.permission.CAMERA` (added in SDK 1)\n  - Granted"));
+        assert!(report.contains("### High (1)"));
+        assert!(report.contains("- **File:** `AndroidManifest.xml`, line 3"));
+        assert!(report.contains("- **Rule:** `exported-component`\n- **CWE:** CWE-926\n"));
+        assert!(report.contains("- **OWASP MASVS:** MASVS-PLATFORM-1"));
+        assert!(report.contains("```xml\n<activity>\n```"));
+        assert!(!report.contains("### Critical"));
+        assert!(report.contains("## Suppressed findings (1)"));
+        assert!(report.contains(
+            "| Log usage | low | `classes/com/example/Main.java` | Stripped in release builds |"
+        ));
+    }
+
+    /// Checks that the engagement metadata is included in the report.
+    #[test]
+    fn it_write_report_engagement() {
+        let results = Results::from_json(
+            r#"{
+            "engagement": {
+                "title": "Mobile assessment",
+                "client": "Example Bank",
+                "id": "ENG-042"
+            },
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut report = Vec::new();
+        write_report(&results, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+
+        assert!(report.starts_with("# Mobile assessment\n\nSecurity report of `com.example`"));
+        assert!(report.contains("- **Client:** Example Bank\n- **Engagement:** ENG-042\n"));
+    }
+
+    /// Checks the generated aggregate report.
+    #[test]
+    fn it_write_index() {
+        let results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>"
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let index = Index::new(vec![Summary::new(&Config::default(), &results)]);
+
+        let mut report = Vec::new();
+        write_index(&index, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+
+        assert!(report.starts_with("# Security report of 1 applications"));
+        assert!(report.contains("| High | 1 |"));
+        assert!(report.contains(
+            "| `com.example` | 1.0 | 0 | 1 | 0 | 0 | 0 | - | Exported activity (high) | \
+             [Report](com.example/index.html) |"
+        ));
+    }
+}
@@ -1,15 +1,30 @@
 //! Report generation module.
 
+mod cyclonedx;
 mod handlebars;
 mod json;
+mod junit;
+mod markdown;
+#[cfg(feature = "screenshots")]
+mod screenshots;
+mod vscode;
+
+use std::path::Path;
 
 use failure::Error;
 
-pub use self::{handlebars::Report as HandlebarsReport, json::Json};
+pub use self::{
+    cyclonedx::CycloneDx,
+    handlebars::Report as HandlebarsReport,
+    json::Json,
+    junit::JUnitXml,
+    markdown::{write_index as write_markdown_index, Markdown},
+    vscode::VsCode,
+};
 use crate::{config::Config, results::Results};
 
 /// Trait that represents a type that can generate a report.
 pub trait Generator {
-    /// Generates an actual report.
-    fn generate(&mut self, config: &Config, result: &Results) -> Result<(), Error>;
+    /// Generates an actual report in the given output folder.
+    fn generate(&mut self, config: &Config, result: &Results, output: &Path) -> Result<(), Error>;
 }
@@ -0,0 +1,234 @@
+//! Evidence screenshots module.
+//!
+//! Findings in the HTML assets of the application, or in the code that loads them in a WebView,
+//! are easier to understand for non-developers if the report shows how the asset looks. This
+//! module renders those assets to images with a headless browser, and links the thumbnails from
+//! the findings of the HTML report.
+//!
+//! The assets come from the analyzed application, so they can't be trusted: a page opened from
+//! its `file://` path could load other local files, such as the decompiled code or the files of the
+//! analyst, and show them in the screenshot. Each asset is copied alone to the workspace of the
+//! analysis and rendered there with a content security policy that blocks every resource except
+//! inline images.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use failure::{bail, Error, ResultExt};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::value::Value;
+
+use crate::{
+    config::Config,
+    decompilation::{tool_command, tool_output},
+    print_warning,
+};
+
+lazy_static! {
+    /// URLs of the HTML assets loaded in WebViews, with the path of the asset.
+    static ref ASSET_URL: Regex =
+        Regex::new(r#"file:///android_asset/([^"'\s]+\.html?)"#).unwrap();
+}
+
+/// Folder of the report where the screenshots are written.
+const SCREENSHOTS_FOLDER: &str = "screenshots";
+
+/// Content security policy of the rendered assets, prepended to them, so that they can only load
+/// inline images.
+const CONTENT_SECURITY_POLICY: &str =
+    r#"<meta http-equiv="Content-Security-Policy" content="default-src 'none'; img-src data:">"#;
+
+/// Lists of vulnerabilities of the results, by criticality.
+const VULNERABILITY_LISTS: [&str; 5] = ["criticals", "highs", "mediums", "lows", "warnings"];
+
+/// Renders the HTML assets of the findings and adds the path of their screenshots to the report
+/// data, in the `screenshot` field of each finding.
+///
+/// Assets are rendered once, even if many findings refer to them. Assets that can't be rendered
+/// are skipped with a warning.
+pub fn embed<S: AsRef<str>>(config: &Config, package: S, data: &mut Value, output: &Path) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let mut screenshots: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+
+    let mut add_screenshot = |vulnerability: &mut Value| {
+        let asset = match referenced_asset(vulnerability) {
+            Some(asset) => asset,
+            None => return,
+        };
+        let screenshot = screenshots
+            .entry(asset.clone())
+            .or_insert_with(|| match render(config, &dist_folder, &asset, output) {
+                Ok(screenshot) => Some(screenshot),
+                Err(e) => {
+                    print_warning(format!(
+                        "could not render the {} asset: {}",
+                        asset.display(),
+                        e
+                    ));
+                    None
+                }
+            })
+            .clone();
+        if let (Some(screenshot), Value::Object(vulnerability)) = (screenshot, vulnerability) {
+            let _ = vulnerability.insert("screenshot".to_owned(), Value::String(screenshot));
+        }
+    };
+
+    for list in &VULNERABILITY_LISTS {
+        if let Some(Value::Array(vulnerabilities)) = data.get_mut(*list) {
+            vulnerabilities.iter_mut().for_each(&mut add_screenshot);
+        }
+    }
+    if let Some(Value::Array(sections)) = data.get_mut("sections") {
+        for section in sections {
+            if let Some(Value::Array(vulnerabilities)) = section.get_mut("vulnerabilities") {
+                vulnerabilities.iter_mut().for_each(&mut add_screenshot);
+            }
+        }
+    }
+}
+
+/// Gets the HTML asset of a finding, relative to the decompiled application.
+///
+/// It's the file of the finding if it's an HTML file, or the asset loaded in its code.
+fn referenced_asset(vulnerability: &Value) -> Option<PathBuf> {
+    let file = vulnerability.get("file").and_then(Value::as_str);
+    if let Some(file) = file {
+        let path = Path::new(file);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("html") | Some("htm") => return Some(path.to_path_buf()),
+            _ => {}
+        }
+    }
+
+    let code = vulnerability.get("code").and_then(Value::as_str)?;
+    ASSET_URL
+        .captures(code)
+        .map(|caps| Path::new("assets").join(&caps[1]))
+        .filter(|asset| !asset.components().any(|c| c.as_os_str() == ".."))
+}
+
+/// Renders the given asset with the configured headless browser.
+///
+/// The asset is rendered from a copy in its own folder of the workspace, with the content
+/// security policy, so that it can't load other local files. JavaScript is disabled and every
+/// network request is sent to a closed proxy too. It returns the path of the screenshot, relative
+/// to the report folder.
+fn render(
+    config: &Config,
+    dist_folder: &Path,
+    asset: &Path,
+    output: &Path,
+) -> Result<String, Error> {
+    let input = fs::canonicalize(dist_folder.join(asset))
+        .context("the asset could not be found in the application")?;
+    if !input.starts_with(fs::canonicalize(dist_folder)?) {
+        bail!("the asset is outside of the application folder");
+    }
+
+    let screenshots_folder = output.join(SCREENSHOTS_FOLDER);
+    fs::create_dir_all(&screenshots_folder)?;
+    let name = format!(
+        "{}.png",
+        asset
+            .to_string_lossy()
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "_")
+    );
+    let screenshot = fs::canonicalize(&screenshots_folder)?.join(&name);
+
+    // The policy is the first element of the page, so it applies to every resource of the asset.
+    let render_folder = fs::canonicalize(config.workspace_folder())?.join(format!(
+        "super-screenshot-{}-{}",
+        process::id(),
+        name
+    ));
+    fs::create_dir_all(&render_folder)?;
+    let page = render_folder.join("index.html");
+    let mut contents = CONTENT_SECURITY_POLICY.as_bytes().to_vec();
+    contents.extend(fs::read(&input)?);
+    fs::write(&page, contents)?;
+
+    let result = take_screenshot(config, &render_folder, &page, &screenshot);
+    if let Err(e) = fs::remove_dir_all(&render_folder) {
+        print_warning(format!(
+            "the rendering folder at {} could not be removed: {}",
+            render_folder.display(),
+            e
+        ));
+    }
+    result?;
+
+    Ok(format!("{}/{}", SCREENSHOTS_FOLDER, name))
+}
+
+/// Takes the screenshot of the given page with the configured headless browser.
+fn take_screenshot(
+    config: &Config,
+    render_folder: &Path,
+    page: &Path,
+    screenshot: &Path,
+) -> Result<(), Error> {
+    let mut command = tool_command(config, config.screenshot_renderer(), render_folder);
+    let _ = command
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--hide-scrollbars")
+        .arg("--blink-settings=scriptEnabled=false")
+        .arg("--proxy-server=127.0.0.1:9")
+        .arg("--window-size=480,800")
+        .arg(format!("--screenshot={}", screenshot.display()))
+        .arg(format!("file://{}", page.display()));
+    let output = tool_output(&mut command).context("the headless browser could not be run")?;
+    if !output.status.success() || !screenshot.exists() {
+        bail!(
+            "the headless browser returned an error:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Evidence screenshots testing module.
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::referenced_asset;
+
+    /// Checks the assets referenced by the findings.
+    #[test]
+    fn it_referenced_asset() {
+        let html = json!({ "file": "assets/www/index.html", "code": "<script>" });
+        assert_eq!(
+            referenced_asset(&html),
+            Some(Path::new("assets/www/index.html").to_path_buf())
+        );
+
+        let java = json!({
+            "file": "classes/com/example/Main.java",
+            "code": "webView.loadUrl(\"file:///android_asset/help/terms.html\");"
+        });
+        assert_eq!(
+            referenced_asset(&java),
+            Some(Path::new("assets").join("help/terms.html"))
+        );
+
+        let traversal = json!({
+            "file": "classes/com/example/Main.java",
+            "code": "webView.loadUrl(\"file:///android_asset/../../secret.html\");"
+        });
+        assert_eq!(referenced_asset(&traversal), None);
+
+        let layout = json!({ "file": "res/layout/main.xml", "code": "<WebView />" });
+        assert_eq!(referenced_asset(&layout), None);
+        assert_eq!(referenced_asset(&json!({ "file": null })), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! VS Code problem matcher report generation module.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use failure::Error;
+
+use crate::{
+    config::Config,
+    criticality::Criticality,
+    results::{report::Generator, Results, CRITICALITIES},
+};
+
+/// VS Code problem matcher report generator.
+///
+/// It prints each vulnerability in a `file:line:column: severity: message` format to `stdout`, so
+/// that editors can show them inline in the decompiled source tree. Vulnerabilities without an
+/// associated file are not printed, since problem matchers need a location.
+pub struct VsCode {
+    /// Package name.
+    package: String,
+}
+
+impl VsCode {
+    /// Creates a new VS Code problem matcher report generator.
+    pub fn new<S: Into<String>>(package: S) -> Self {
+        Self {
+            package: package.into(),
+        }
+    }
+}
+
+/// Gets the problem matcher severity for the given criticality.
+fn severity(criticality: Criticality) -> &'static str {
+    match criticality {
+        Criticality::Critical | Criticality::High => "error",
+        Criticality::Medium | Criticality::Low => "warning",
+        Criticality::Warning => "info",
+    }
+}
+
+impl Generator for VsCode {
+    fn generate(&mut self, config: &Config, results: &Results, _: &Path) -> Result<(), Error> {
+        let dist_folder = config.dist_folder().join(&self.package);
+        let dist_folder = dist_folder
+            .canonicalize()
+            .unwrap_or_else(|_| dist_folder.clone());
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        for criticality in &CRITICALITIES {
+            for vulnerability in results.vulnerabilities.iter(*criticality)? {
+                let vulnerability = vulnerability?;
+                if let Some(file) = vulnerability.get_file() {
+                    writeln!(
+                        out,
+                        "{}:{}:1: {}: {}: {}",
+                        dist_folder.join(file).display(),
+                        vulnerability.get_start_line().map_or(1, |l| l + 1),
+                        severity(vulnerability.get_criticality()),
+                        vulnerability.get_name(),
+                        vulnerability.get_description()
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// VS Code problem matcher testing module.
+#[cfg(test)]
+mod test {
+    use super::severity;
+    use crate::criticality::Criticality;
+
+    /// Checks the mapping between criticality levels and problem matcher severities.
+    #[test]
+    fn it_severity() {
+        assert_eq!(severity(Criticality::Critical), "error");
+        assert_eq!(severity(Criticality::High), "error");
+        assert_eq!(severity(Criticality::Medium), "warning");
+        assert_eq!(severity(Criticality::Low), "warning");
+        assert_eq!(severity(Criticality::Warning), "info");
+    }
+}
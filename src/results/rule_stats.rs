@@ -0,0 +1,236 @@
+//! Rule statistics module.
+//!
+//! When several applications are analyzed at once, the findings of each code analysis rule in
+//! the whole batch tell the rule maintainers which rules need tuning: rules that never match any
+//! application could be broken or obsolete, and rules that match many times in every application
+//! are usually too generic. The statistics are written to the `rule_stats.json` file of the
+//! results folder.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use failure::{Error, ResultExt};
+use serde_json::ser;
+
+use crate::{config::Config, results::Summary, static_analysis::code::load_rules};
+
+/// Name of the rule statistics file, in the results folder.
+pub const RULE_STATS_FILE: &str = "rule_stats.json";
+
+/// Statistics of a code analysis rule in a batch of analyses.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleStats {
+    /// ID of the rule.
+    id: String,
+    /// Label of the rule.
+    label: String,
+    /// Number of findings of the rule, in all the applications.
+    matches: usize,
+    /// Number of applications with findings of the rule.
+    applications: usize,
+    /// Average number of findings of the rule in the applications with findings of it.
+    average_matches: f64,
+}
+
+impl RuleStats {
+    /// Gets the ID of the rule.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the label of the rule.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Gets the number of findings of the rule, in all the applications.
+    pub fn matches(&self) -> usize {
+        self.matches
+    }
+
+    /// Gets the number of applications with findings of the rule.
+    pub fn applications(&self) -> usize {
+        self.applications
+    }
+
+    /// Gets the average number of findings of the rule in the applications with findings of it.
+    pub fn average_matches(&self) -> f64 {
+        self.average_matches
+    }
+}
+
+/// Statistics of the code analysis rules in a batch of analyses.
+#[derive(Debug, Serialize)]
+pub struct RuleStatistics {
+    /// Number of analyzed applications.
+    analyzed_applications: usize,
+    /// Statistics of each rule, from the rules with the most findings to the rules without them.
+    rules: Vec<RuleStats>,
+}
+
+impl RuleStatistics {
+    /// Aggregates the findings of the configured rules in the given applications.
+    ///
+    /// Rules without an ID are not included, since their findings can't be told apart.
+    pub fn new(config: &Config, applications: &[Summary]) -> Result<Self, Error> {
+        let rules = load_rules(config).context("could not load the code analysis rules")?;
+        Ok(Self::from_rules(
+            rules
+                .iter()
+                .filter_map(|rule| rule.id().map(|id| (id, rule.label()))),
+            applications,
+        ))
+    }
+
+    /// Aggregates the findings of the given rules, as ID and label pairs, in the given
+    /// applications.
+    fn from_rules<'r, I>(rules: I, applications: &[Summary]) -> Self
+    where
+        I: IntoIterator<Item = (&'r str, &'r str)>,
+    {
+        let mut rules = rules
+            .into_iter()
+            .map(|(id, label)| {
+                let counts = applications
+                    .iter()
+                    .filter_map(|summary| summary.rule_matches().get(id).cloned())
+                    .collect::<Vec<_>>();
+                let matches = counts.iter().sum();
+                let average_matches = if counts.is_empty() {
+                    0.0
+                } else {
+                    matches as f64 / counts.len() as f64
+                };
+                RuleStats {
+                    id: id.to_owned(),
+                    label: label.to_owned(),
+                    matches,
+                    applications: counts.len(),
+                    average_matches,
+                }
+            })
+            .collect::<Vec<_>>();
+        rules.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.id.cmp(&b.id)));
+
+        Self {
+            analyzed_applications: applications.len(),
+            rules,
+        }
+    }
+
+    /// Gets the statistics of each rule, from the rules with the most findings to the rules
+    /// without them.
+    pub fn rules(&self) -> &[RuleStats] {
+        &self.rules
+    }
+
+    /// Gets the rules without findings in any application.
+    pub fn unmatched(&self) -> impl Iterator<Item = &RuleStats> {
+        self.rules.iter().filter(|rule| rule.matches == 0)
+    }
+
+    /// Writes the statistics to the rule statistics file of the results folder.
+    pub fn generate(&self, config: &Config) -> Result<(), Error> {
+        let path = config.results_folder().join(RULE_STATS_FILE);
+        let mut f = BufWriter::new(File::create(&path).context(format!(
+            "could not create the rule statistics file at {}",
+            path.display()
+        ))?);
+        ser::to_writer_pretty(&mut f, self)?;
+        f.flush()?;
+        Ok(())
+    }
+}
+
+/// Rule statistics testing module.
+#[cfg(test)]
+mod test {
+    use super::RuleStatistics;
+    use crate::{
+        config::Config,
+        results::{Results, Summary},
+    };
+
+    /// Creates the summary of an analysis with findings of the given rules.
+    fn summary(package: &str, rule_ids: &[&str]) -> Summary {
+        let lows = rule_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                format!(
+                    r#"{{
+                    "criticality": "low",
+                    "name": "Finding",
+                    "description": "Description",
+                    "file": "classes/Main.java",
+                    "language": "java",
+                    "line": {},
+                    "code": "code();",
+                    "rule_id": "{}"
+                }}"#,
+                    i + 1,
+                    id
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let results = Results::from_json(
+            format!(
+                r#"{{
+                "app_package": "{}",
+                "app_version": "1.0",
+                "app_version_number": 1,
+                "app_fingerprint": {{"md5": "aa"}},
+                "app_min_sdk_number": 21,
+                "criticals": [],
+                "highs": [],
+                "mediums": [],
+                "lows": [{}],
+                "warnings": []
+            }}"#,
+                package, lows
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        Summary::new(&Config::default(), &results)
+    }
+
+    /// Checks the aggregation of the findings of each rule.
+    #[test]
+    fn it_rule_statistics() {
+        let applications = vec![
+            summary("com.example.first", &["logs", "logs", "logs", "random"]),
+            summary("com.example.second", &["logs"]),
+            summary("com.example.third", &[]),
+        ];
+        let statistics = RuleStatistics::from_rules(
+            vec![
+                ("random", "Math random"),
+                ("sleep", "Sleep method"),
+                ("logs", "Sensitive logs"),
+            ],
+            &applications,
+        );
+        assert_eq!(statistics.analyzed_applications, 3);
+
+        let rules = statistics.rules();
+        let ids = rules.iter().map(|rule| rule.id()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["logs", "random", "sleep"]);
+        assert_eq!(rules[0].label(), "Sensitive logs");
+        assert_eq!(rules[0].matches(), 4);
+        assert_eq!(rules[0].applications(), 2);
+        assert!((rules[0].average_matches() - 2.0).abs() < std::f64::EPSILON);
+        assert_eq!(rules[1].matches(), 1);
+        assert_eq!(rules[1].applications(), 1);
+
+        let unmatched = statistics
+            .unmatched()
+            .map(|rule| rule.id())
+            .collect::<Vec<_>>();
+        assert_eq!(unmatched, vec!["sleep"]);
+        assert!(rules[2].average_matches().abs() < std::f64::EPSILON);
+    }
+}
@@ -0,0 +1,103 @@
+//! Security score module.
+//!
+//! Summarizes the findings of an analysis in a single number from 0 to 100, and a letter grade,
+//! so that applications can be compared and tracked over time. Each vulnerability subtracts the
+//! weight of its criticality, as configured in the `score_weights` table, from a perfect score.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{config::Config, criticality::Criticality, results::CRITICALITIES};
+
+/// Score of an application without vulnerabilities.
+pub(crate) const MAX_SCORE: u32 = 100;
+
+/// Minimum scores of each grade, from the best grade to the worst.
+const GRADES: [(u32, &str); 4] = [(90, "A"), (80, "B"), (70, "C"), (60, "D")];
+
+/// Grade of the applications that score below every grade in `GRADES`.
+const FAILING_GRADE: &str = "F";
+
+/// Security score of an application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct Score {
+    /// Score, from 0 to 100.
+    value: u32,
+}
+
+impl Score {
+    /// Computes the score from the number of vulnerabilities of each criticality.
+    pub fn new<F>(config: &Config, vulnerability_count: F) -> Self
+    where
+        F: Fn(Criticality) -> usize,
+    {
+        let penalty = CRITICALITIES
+            .iter()
+            .map(|criticality| {
+                (vulnerability_count(*criticality) as u32)
+                    .saturating_mul(config.score_weight(*criticality))
+            })
+            .fold(0_u32, u32::saturating_add);
+
+        Self {
+            value: MAX_SCORE.saturating_sub(penalty),
+        }
+    }
+
+    /// Gets the score, from 0 to 100.
+    pub fn value(self) -> u32 {
+        self.value
+    }
+
+    /// Gets the letter grade of the score, from `A` to `F`.
+    pub fn grade(self) -> &'static str {
+        GRADES
+            .iter()
+            .find(|(min_score, _)| self.value >= *min_score)
+            .map_or(FAILING_GRADE, |(_, grade)| *grade)
+    }
+}
+
+impl Serialize for Score {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut ser_struct = serializer.serialize_struct("Score", 2)?;
+        ser_struct.serialize_field("value", &self.value)?;
+        ser_struct.serialize_field("grade", self.grade())?;
+        ser_struct.end()
+    }
+}
+
+/// Security score testing module.
+#[cfg(test)]
+mod test {
+    use super::Score;
+    use crate::{config::Config, criticality::Criticality};
+
+    /// Checks the score and grade computed from the vulnerability counts.
+    #[test]
+    fn it_score() {
+        let config = Config::default();
+
+        let clean = Score::new(&config, |_| 0);
+        assert_eq!(clean.value(), 100);
+        assert_eq!(clean.grade(), "A");
+
+        let score = Score::new(&config, |criticality| match criticality {
+            Criticality::High => 1,
+            Criticality::Medium => 2,
+            Criticality::Low => 3,
+            _ => 0,
+        });
+        assert_eq!(score.value(), 79);
+        assert_eq!(score.grade(), "C");
+
+        let score = Score::new(&config, |criticality| match criticality {
+            Criticality::Critical => 10,
+            _ => 100,
+        });
+        assert_eq!(score.value(), 0);
+        assert_eq!(score.grade(), "F");
+    }
+}
@@ -0,0 +1,116 @@
+//! Report sections module.
+//!
+//! Rules can declare the report section of their findings, such as "Network security" or "Data
+//! storage", so that reports can follow the structure of a testing methodology instead of only
+//! grouping the findings by criticality.
+
+use super::Vulnerability;
+
+/// Name of the section with the findings that don't declare one.
+pub const OTHER_SECTION: &str = "Other findings";
+
+/// Named section of the report, with its findings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Section {
+    /// Name of the section.
+    name: String,
+    /// Findings of the section, from the most critical to the least.
+    vulnerabilities: Vec<Vulnerability>,
+    /// Number of findings of the section.
+    vulnerabilities_len: usize,
+}
+
+impl Section {
+    /// Groups the given vulnerabilities in sections, sorted by name, keeping their order.
+    ///
+    /// Vulnerabilities without a section are grouped in a last section, but only if any other
+    /// vulnerability declares a section, so that reports without sections keep their structure.
+    pub fn group<I>(vulnerabilities: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = Vulnerability>,
+    {
+        let mut sections: Vec<Self> = Vec::new();
+        let mut others = Vec::new();
+        for vulnerability in vulnerabilities {
+            let name = match vulnerability.get_section() {
+                Some(name) => name.to_owned(),
+                None => {
+                    others.push(vulnerability);
+                    continue;
+                }
+            };
+            match sections.binary_search_by(|section| section.name.cmp(&name)) {
+                Ok(i) => sections[i].vulnerabilities.push(vulnerability),
+                Err(i) => sections.insert(
+                    i,
+                    Self {
+                        name,
+                        vulnerabilities: vec![vulnerability],
+                        vulnerabilities_len: 0,
+                    },
+                ),
+            }
+        }
+
+        if !sections.is_empty() && !others.is_empty() {
+            sections.push(Self {
+                name: OTHER_SECTION.to_owned(),
+                vulnerabilities: others,
+                vulnerabilities_len: 0,
+            });
+        }
+        for section in &mut sections {
+            section.vulnerabilities_len = section.vulnerabilities.len();
+        }
+        sections
+    }
+
+    /// Gets the name of the section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the findings of the section.
+    pub fn vulnerabilities(&self) -> &[Vulnerability] {
+        &self.vulnerabilities
+    }
+}
+
+/// Report sections testing module.
+#[cfg(test)]
+mod test {
+    use super::{Section, OTHER_SECTION};
+    use crate::{criticality::Criticality, results::VulnerabilityBuilder};
+
+    /// Checks the grouping of the vulnerabilities in sections.
+    #[test]
+    fn it_group() {
+        let storage = VulnerabilityBuilder::new(Criticality::High, "Storage", "Description")
+            .section("Data storage")
+            .build();
+        let network = VulnerabilityBuilder::new(Criticality::Medium, "Network", "Description")
+            .section("Network security")
+            .build();
+        let other = VulnerabilityBuilder::new(Criticality::Low, "Other", "Description").build();
+        let storage_warning =
+            VulnerabilityBuilder::new(Criticality::Warning, "Warning", "Description")
+                .section("Data storage")
+                .build();
+
+        assert!(Section::group(vec![other.clone()]).is_empty());
+
+        let sections = Section::group(vec![
+            storage.clone(),
+            network.clone(),
+            other.clone(),
+            storage_warning.clone(),
+        ]);
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].name(), "Data storage");
+        assert_eq!(sections[0].vulnerabilities(), &[storage, storage_warning]);
+        assert_eq!(sections[1].name(), "Network security");
+        assert_eq!(sections[1].vulnerabilities(), &[network]);
+        assert_eq!(sections[2].name(), OTHER_SECTION);
+        assert_eq!(sections[2].vulnerabilities(), &[other]);
+    }
+}
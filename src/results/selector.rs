@@ -0,0 +1,139 @@
+//! Print selectors module.
+//!
+//! Selectors choose facts of the results that are printed to the standard output after the
+//! analysis, one per line, so that shell pipelines can use them without parsing the JSON report.
+
+use crate::results::{HashAlgorithm, Results, CRITICALITIES};
+
+/// Fact of the results that can be printed after the analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintSelector {
+    /// Fingerprints of the application, as `algorithm=hash` lines.
+    Fingerprint,
+    /// Package name of the application.
+    Package,
+    /// Security score of the application, from 0 to 100.
+    Score,
+    /// Number of vulnerabilities of each criticality, as `criticality=count` lines.
+    Counts,
+}
+
+impl PrintSelector {
+    /// Gets the name of the selector, as used in the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            PrintSelector::Fingerprint => "fingerprint",
+            PrintSelector::Package => "package",
+            PrintSelector::Score => "score",
+            PrintSelector::Counts => "counts",
+        }
+    }
+
+    /// Gets the selector with the given name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            PrintSelector::Fingerprint,
+            PrintSelector::Package,
+            PrintSelector::Score,
+            PrintSelector::Counts,
+        ]
+        .iter()
+        .cloned()
+        .find(|selector| selector.name() == name)
+    }
+
+    /// Gets the lines to print for the selected fact of the given results.
+    ///
+    /// Facts that are not available, such as the score before computing it, have no lines.
+    pub fn lines(self, results: &Results) -> Vec<String> {
+        match self {
+            PrintSelector::Fingerprint => [
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Sha512,
+                HashAlgorithm::DexSha256,
+            ]
+            .iter()
+            .filter_map(|algorithm| {
+                results
+                    .app_fingerprint
+                    .hash(*algorithm)
+                    .map(|hash| format!("{}={}", algorithm.name(), hash))
+            })
+            .collect(),
+            PrintSelector::Package => vec![results.app_package.clone()],
+            PrintSelector::Score => results
+                .score
+                .map(|score| score.value().to_string())
+                .into_iter()
+                .collect(),
+            PrintSelector::Counts => CRITICALITIES
+                .iter()
+                .map(|criticality| {
+                    format!(
+                        "{}={}",
+                        criticality,
+                        results.vulnerability_count(*criticality)
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Print selectors testing module.
+#[cfg(test)]
+mod test {
+    use super::PrintSelector;
+    use crate::{config::Config, results::Results};
+
+    /// Checks the lines printed for each selector.
+    #[test]
+    fn it_lines() {
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa", "sha256": "bb"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [{
+                "criticality": "high",
+                "name": "Exported activity",
+                "description": "Description",
+                "file": "AndroidManifest.xml",
+                "language": "xml",
+                "line": 3,
+                "code": "<activity>"
+            }],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            PrintSelector::from_name("counts"),
+            Some(PrintSelector::Counts)
+        );
+        assert_eq!(PrintSelector::from_name("version"), None);
+
+        assert_eq!(PrintSelector::Package.lines(&results), vec!["com.example"]);
+        assert_eq!(
+            PrintSelector::Fingerprint.lines(&results),
+            vec!["md5=aa", "sha256=bb"]
+        );
+        assert!(PrintSelector::Score.lines(&results).is_empty());
+        results.compute_score(&Config::default());
+        assert_eq!(PrintSelector::Score.lines(&results), vec!["90"]);
+        assert_eq!(
+            PrintSelector::Counts.lines(&results),
+            vec!["critical=0", "high=1", "medium=0", "low=0", "warning=0"]
+        );
+    }
+}
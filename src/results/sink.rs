@@ -0,0 +1,545 @@
+//! Thread-safe vulnerability collection.
+//!
+//! The vulnerabilities of an application can be found by many parallel workers at the same time,
+//! so they are collected in a sink that can be shared between threads.
+//!
+//! Some applications, such as the ones that bundle huge generated sources, can have hundreds of
+//! thousands of findings. The sink can be limited to a number of vulnerabilities in memory, and
+//! the ones above the limit are spilled to a temporary folder on disk, and read back one by one
+//! when the reports are generated.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Lines, Write},
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    vec,
+};
+
+use serde::ser::{Error as SerError, Serialize, SerializeSeq, Serializer};
+use sha2::{Digest, Sha256};
+
+use super::Vulnerability;
+use crate::criticality::Criticality;
+
+/// Criticalities, in the order of the shards of the sink.
+const SHARD_CRITICALITIES: [Criticality; 5] = [
+    Criticality::Warning,
+    Criticality::Low,
+    Criticality::Medium,
+    Criticality::High,
+    Criticality::Critical,
+];
+
+/// Counter used to give each spill folder of the process a different name.
+static SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Thread-safe collection of the vulnerabilities found in an application.
+///
+/// Vulnerabilities are stored in a different shard for each criticality, so that workers adding
+/// vulnerabilities of different criticalities don't block each other. Cloning the sink is cheap,
+/// and all the clones add the vulnerabilities to the same results.
+///
+/// If the sink has a memory limit, the vulnerabilities added once the limit is reached are
+/// spilled to disk. Spilled vulnerabilities are only returned by [`iter()`](#method.iter),
+/// [`list()`](#method.list) and [`to_vec()`](#method.to_vec), after the ones in memory.
+#[derive(Debug, Clone, Default)]
+pub struct VulnerabilitySink {
+    /// Vulnerabilities, by criticality.
+    shards: Arc<[Mutex<BTreeSet<Vulnerability>>; 5]>,
+    /// Maximum number of vulnerabilities kept in memory, if any.
+    memory_limit: Option<usize>,
+    /// Number of vulnerabilities kept in memory.
+    in_memory: Arc<AtomicUsize>,
+    /// Vulnerabilities spilled to disk, once the memory limit is reached.
+    spill: Arc<Mutex<Option<Spill>>>,
+}
+
+impl VulnerabilitySink {
+    /// Creates a new empty vulnerability sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new empty vulnerability sink that keeps at most the given number of
+    /// vulnerabilities in memory, and spills the rest to disk.
+    pub fn with_memory_limit(memory_limit: Option<usize>) -> Self {
+        Self {
+            memory_limit,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a vulnerability to the sink.
+    ///
+    /// Returns `false` if the vulnerability had already been added. If the vulnerability can't
+    /// be spilled to disk, it's kept in memory.
+    pub fn add(&self, vulnerability: Vulnerability) -> bool {
+        let criticality = vulnerability.get_criticality();
+        let mut shard = self.get(criticality);
+        if shard.contains(&vulnerability) {
+            return false;
+        }
+
+        let mut spill = self.spill();
+        if let Some(ref spill) = *spill {
+            if spill.contains(&vulnerability) {
+                return false;
+            }
+        }
+        let full = self.memory_limit.map_or(false, |limit| {
+            self.in_memory.load(Ordering::SeqCst) >= limit
+        });
+        if full {
+            if spill.is_none() {
+                *spill = Spill::new().ok();
+            }
+            if let Some(ref mut spill) = *spill {
+                if spill.add(&vulnerability).is_ok() {
+                    return true;
+                }
+            }
+        }
+
+        let _ = self.in_memory.fetch_add(1, Ordering::SeqCst);
+        shard.insert(vulnerability)
+    }
+
+    /// Gets the vulnerabilities of the given criticality that are kept in memory.
+    ///
+    /// The shard of the criticality stays locked while the returned guard is alive, so it should
+    /// not be kept for long. Vulnerabilities removed from the returned set are still counted in
+    /// the memory limit.
+    pub fn get(&self, criticality: Criticality) -> MutexGuard<'_, BTreeSet<Vulnerability>> {
+        // A worker panicking while holding the lock can't leave the set in an invalid state.
+        self.shards[criticality as usize]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets the spilled vulnerabilities, if the memory limit has been reached.
+    fn spill(&self) -> MutexGuard<'_, Option<Spill>> {
+        self.spill.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets the number of vulnerabilities of the given criticality, including the spilled ones.
+    pub fn len(&self, criticality: Criticality) -> usize {
+        let in_memory = self.get(criticality).len();
+        in_memory
+            + self
+                .spill()
+                .as_ref()
+                .map_or(0, |spill| spill.lens[criticality as usize])
+    }
+
+    /// Gets the number of vulnerabilities spilled to disk.
+    pub fn spilled(&self) -> usize {
+        self.spill()
+            .as_ref()
+            .map_or(0, |spill| spill.lens.iter().sum())
+    }
+
+    /// Iterates over the vulnerabilities of the given criticality.
+    ///
+    /// The vulnerabilities in memory are returned sorted, followed by the spilled ones in the
+    /// order in which they were found. Spilled vulnerabilities are read from disk one by one, so
+    /// the iteration fails if they can't be read.
+    pub fn iter(&self, criticality: Criticality) -> io::Result<Vulnerabilities> {
+        let in_memory: Vec<_> = self.get(criticality).iter().cloned().collect();
+        let mut spill = self.spill();
+        let spilled = match *spill {
+            Some(ref mut spill) => spill.lines(criticality)?,
+            None => None,
+        };
+        Ok(Vulnerabilities {
+            in_memory: in_memory.into_iter(),
+            spilled,
+        })
+    }
+
+    /// Gets a serializable list of the vulnerabilities of the given criticality, that streams
+    /// the spilled vulnerabilities from disk.
+    pub fn list(&self, criticality: Criticality) -> VulnerabilityList<'_> {
+        VulnerabilityList {
+            sink: self,
+            criticality,
+        }
+    }
+
+    /// Removes the vulnerabilities that match the given predicate from the sink, including the
+    /// spilled ones, and returns them.
+    pub fn extract<F>(&self, mut predicate: F) -> io::Result<Vec<Vulnerability>>
+    where
+        F: FnMut(&Vulnerability) -> bool,
+    {
+        let mut extracted = Vec::new();
+        for criticality in &SHARD_CRITICALITIES {
+            let mut shard = self.get(*criticality);
+            let (matched, kept): (Vec<_>, Vec<_>) =
+                shard.iter().cloned().partition(|v| predicate(v));
+            *shard = kept.into_iter().collect();
+            let _ = self.in_memory.fetch_sub(matched.len(), Ordering::SeqCst);
+            extracted.extend(matched);
+        }
+        let mut spill = self.spill();
+        if let Some(ref mut spill) = *spill {
+            for criticality in &SHARD_CRITICALITIES {
+                extracted.extend(spill.extract(*criticality, &mut predicate)?);
+            }
+        }
+        Ok(extracted)
+    }
+
+    /// Gets a copy of all the vulnerabilities in the sink, sorted from the least critical to the
+    /// most critical.
+    ///
+    /// Spilled vulnerabilities are loaded in memory too, after the ones of their criticality.
+    pub fn to_vec(&self) -> io::Result<Vec<Vulnerability>> {
+        let mut vulnerabilities = Vec::new();
+        for criticality in &SHARD_CRITICALITIES {
+            for vulnerability in self.iter(*criticality)? {
+                vulnerabilities.push(vulnerability?);
+            }
+        }
+        Ok(vulnerabilities)
+    }
+
+    /// Gets the most critical vulnerability of the sink, if any.
+    pub fn worst(&self) -> io::Result<Option<Vulnerability>> {
+        for criticality in SHARD_CRITICALITIES.iter().rev() {
+            let mut worst = None;
+            for vulnerability in self.iter(*criticality)? {
+                worst = worst.max(Some(vulnerability?));
+            }
+            if worst.is_some() {
+                return Ok(worst);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over the vulnerabilities of a criticality in a sink.
+#[derive(Debug)]
+pub struct Vulnerabilities {
+    /// Vulnerabilities kept in memory.
+    in_memory: vec::IntoIter<Vulnerability>,
+    /// Lines of the spill file of the criticality, if any.
+    spilled: Option<Lines<BufReader<File>>>,
+}
+
+impl Iterator for Vulnerabilities {
+    type Item = io::Result<Vulnerability>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(vulnerability) = self.in_memory.next() {
+            return Some(Ok(vulnerability));
+        }
+        let line = self.spilled.as_mut()?.next()?;
+        Some(line.and_then(|line| SpillRecord::parse(&line)))
+    }
+}
+
+/// Serializable list of the vulnerabilities of a criticality in a sink.
+#[derive(Debug, Clone, Copy)]
+pub struct VulnerabilityList<'s> {
+    /// Sink with the vulnerabilities.
+    sink: &'s VulnerabilitySink,
+    /// Criticality of the listed vulnerabilities.
+    criticality: Criticality,
+}
+
+impl<'s> Serialize for VulnerabilityList<'s> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let vulnerabilities = self.sink.iter(self.criticality).map_err(S::Error::custom)?;
+        let mut seq = serializer.serialize_seq(Some(self.sink.len(self.criticality)))?;
+        for vulnerability in vulnerabilities {
+            seq.serialize_element(&vulnerability.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+/// Spilled vulnerability, as a line of a spill file.
+///
+/// The JSON representation of a vulnerability omits its lines if it has no code, so the record
+/// keeps them apart, along with the code, to read back exactly the same vulnerability.
+#[derive(Debug, Serialize, Deserialize)]
+struct SpillRecord {
+    /// Spilled vulnerability.
+    vulnerability: Vulnerability,
+    /// Optional starting line of the vulnerability.
+    start_line: Option<usize>,
+    /// Optional ending line of the vulnerability.
+    end_line: Option<usize>,
+    /// Optional vulnerable code snippet.
+    code: Option<String>,
+}
+
+impl SpillRecord {
+    /// Gets the line of the spill file for the given vulnerability.
+    fn line(vulnerability: &Vulnerability) -> io::Result<String> {
+        let record = Self {
+            vulnerability: vulnerability.clone(),
+            start_line: vulnerability.get_start_line(),
+            end_line: vulnerability.get_end_line(),
+            code: vulnerability.get_code().map(str::to_owned),
+        };
+        Ok(serde_json::to_string(&record)?)
+    }
+
+    /// Parses a line of a spill file into the spilled vulnerability.
+    fn parse(line: &str) -> io::Result<Vulnerability> {
+        let record: Self = serde_json::from_str(line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut vulnerability = record.vulnerability;
+        vulnerability.set_location(record.start_line, record.end_line, record.code);
+        Ok(vulnerability)
+    }
+}
+
+/// Vulnerabilities spilled to a temporary folder, with a JSON lines file for each criticality.
+///
+/// The folder is removed when the last clone of the sink is dropped.
+#[derive(Debug)]
+struct Spill {
+    /// Temporary folder of the spill files.
+    folder: PathBuf,
+    /// Writers of the spill files, by criticality, once they have been created.
+    writers: [Option<BufWriter<File>>; 5],
+    /// Number of spilled vulnerabilities, by criticality.
+    lens: [usize; 5],
+    /// Hashes of the spilled vulnerabilities, to detect duplicates without reading them back.
+    hashes: HashSet<Vec<u8>>,
+}
+
+impl Spill {
+    /// Creates a new empty spill folder in the temporary folder of the system.
+    fn new() -> io::Result<Self> {
+        let folder = env::temp_dir().join(format!(
+            "super-analyzer-{}-{}",
+            process::id(),
+            SPILL_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&folder)?;
+        Ok(Self {
+            folder,
+            writers: [None, None, None, None, None],
+            lens: [0; 5],
+            hashes: HashSet::new(),
+        })
+    }
+
+    /// Computes the hash of a vulnerability, from its line in the spill file.
+    fn hash(line: &str) -> Vec<u8> {
+        Sha256::digest(line.as_bytes()).to_vec()
+    }
+
+    /// Gets the path of the spill file of the given criticality.
+    fn path(&self, criticality: Criticality) -> PathBuf {
+        self.folder.join(format!("{}.jsonl", criticality))
+    }
+
+    /// Returns true if the vulnerability has already been spilled.
+    fn contains(&self, vulnerability: &Vulnerability) -> bool {
+        !self.hashes.is_empty()
+            && SpillRecord::line(vulnerability)
+                .map(|line| self.hashes.contains(&Self::hash(&line)))
+                .unwrap_or(false)
+    }
+
+    /// Appends a vulnerability to the spill file of its criticality.
+    fn add(&mut self, vulnerability: &Vulnerability) -> io::Result<()> {
+        let criticality = vulnerability.get_criticality();
+        let line = SpillRecord::line(vulnerability)?;
+        if self.writers[criticality as usize].is_none() {
+            let file = File::create(self.path(criticality))?;
+            self.writers[criticality as usize] = Some(BufWriter::new(file));
+        }
+        if let Some(ref mut writer) = self.writers[criticality as usize] {
+            writeln!(writer, "{}", line)?;
+        }
+        let _ = self.hashes.insert(Self::hash(&line));
+        self.lens[criticality as usize] += 1;
+        Ok(())
+    }
+
+    /// Gets the lines of the spill file of the given criticality, if it has been created.
+    fn lines(&mut self, criticality: Criticality) -> io::Result<Option<Lines<BufReader<File>>>> {
+        match self.writers[criticality as usize] {
+            Some(ref mut writer) => writer.flush()?,
+            None => return Ok(None),
+        }
+        Ok(Some(
+            BufReader::new(File::open(self.path(criticality))?).lines(),
+        ))
+    }
+
+    /// Removes the spilled vulnerabilities of the given criticality that match the predicate,
+    /// and returns them.
+    ///
+    /// The spill file is rewritten with the rest of the vulnerabilities.
+    fn extract<F>(
+        &mut self,
+        criticality: Criticality,
+        predicate: &mut F,
+    ) -> io::Result<Vec<Vulnerability>>
+    where
+        F: FnMut(&Vulnerability) -> bool,
+    {
+        let lines = match self.lines(criticality)? {
+            Some(lines) => lines,
+            None => return Ok(Vec::new()),
+        };
+
+        let path = self.path(criticality);
+        let temp_path = path.with_extension("jsonl.tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        let mut extracted = Vec::new();
+        for line in lines {
+            let line = line?;
+            let vulnerability = SpillRecord::parse(&line)?;
+            if predicate(&vulnerability) {
+                let _ = self.hashes.remove(&Self::hash(&line));
+                extracted.push(vulnerability);
+            } else {
+                writeln!(writer, "{}", line)?;
+            }
+        }
+        writer.flush()?;
+        fs::rename(&temp_path, &path)?;
+
+        self.lens[criticality as usize] -= extracted.len();
+        let file = fs::OpenOptions::new().append(true).open(&path)?;
+        self.writers[criticality as usize] = Some(BufWriter::new(file));
+        Ok(extracted)
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        for writer in &mut self.writers {
+            *writer = None;
+        }
+        let _ = fs::remove_dir_all(&self.folder);
+    }
+}
+
+/// Vulnerability sink testing module.
+#[cfg(test)]
+mod test {
+    use std::{path::Path, thread};
+
+    use super::VulnerabilitySink;
+    use crate::{criticality::Criticality, results::Vulnerability};
+
+    /// Creates a vulnerability of the given criticality in the given line.
+    fn vulnerability(criticality: Criticality, name: &str, line: usize) -> Vulnerability {
+        Vulnerability::new(
+            criticality,
+            name,
+            "Description",
+            Some(Path::new("Main.java")),
+            Some(line),
+            Some(line),
+            None::<String>,
+        )
+    }
+
+    /// Checks that vulnerabilities can be added from different threads.
+    #[test]
+    fn it_add_concurrently() {
+        let sink = VulnerabilitySink::new();
+        let handles: Vec<_> = (0..4_usize)
+            .map(|i| {
+                let thread_sink = sink.clone();
+                thread::spawn(move || {
+                    for line in 0..10 {
+                        let criticality = if line % 2 == 0 {
+                            Criticality::High
+                        } else {
+                            Criticality::Low
+                        };
+                        let _ = thread_sink.add(vulnerability(
+                            criticality,
+                            &format!("Vulnerability {}", i),
+                            line,
+                        ));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(sink.len(Criticality::High), 20);
+        assert_eq!(sink.len(Criticality::Low), 20);
+        assert_eq!(sink.len(Criticality::Critical), 0);
+
+        let duplicate = sink.get(Criticality::High).iter().next().cloned().unwrap();
+        assert!(!sink.add(duplicate));
+
+        let vulnerabilities = sink.to_vec().unwrap();
+        assert_eq!(vulnerabilities.len(), 40);
+        assert_eq!(vulnerabilities[0].get_criticality(), Criticality::Low);
+        assert_eq!(vulnerabilities[39].get_criticality(), Criticality::High);
+        assert_eq!(sink.spilled(), 0);
+    }
+
+    /// Checks that the vulnerabilities above the memory limit are spilled to disk and read back.
+    #[test]
+    fn it_spill() {
+        let sink = VulnerabilitySink::with_memory_limit(Some(3));
+        for line in 0..5 {
+            assert!(sink.add(vulnerability(Criticality::Medium, "Medium", line)));
+            assert!(sink.add(vulnerability(Criticality::Low, "Low", line)));
+        }
+        assert!(!sink.add(vulnerability(Criticality::Low, "Low", 4)));
+        assert!(!sink.add(vulnerability(Criticality::Medium, "Medium", 0)));
+
+        assert_eq!(sink.spilled(), 7);
+        assert_eq!(sink.len(Criticality::Medium), 5);
+        assert_eq!(sink.len(Criticality::Low), 5);
+        assert_eq!(sink.get(Criticality::Medium).len(), 2);
+
+        let mediums: Vec<_> = sink
+            .iter(Criticality::Medium)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(mediums.len(), 5);
+        assert_eq!(mediums[4].get_start_line(), Some(4));
+        assert_eq!(
+            serde_json::to_value(sink.list(Criticality::Low))
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(
+            sink.worst().unwrap().unwrap().get_criticality(),
+            Criticality::Medium
+        );
+
+        let extracted = sink
+            .extract(|vulnerability| vulnerability.get_start_line() == Some(3))
+            .unwrap();
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(sink.len(Criticality::Medium), 4);
+        assert_eq!(sink.len(Criticality::Low), 4);
+        assert_eq!(sink.to_vec().unwrap().len(), 8);
+        assert!(sink.add(vulnerability(Criticality::Low, "Low", 3)));
+        assert_eq!(sink.len(Criticality::Low), 5);
+    }
+}
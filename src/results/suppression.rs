@@ -0,0 +1,196 @@
+//! Finding suppression module.
+//!
+//! Legacy codebases usually have many findings that have been reviewed and accepted, and that
+//! would hide the new ones. A suppressions file lists the findings to suppress by rule ID, by file
+//! or folder, or by fingerprint. Suppressed findings don't count for the score or the policies,
+//! but they are kept apart in the results, so that reports can still list them.
+//!
+//! The suppressions file is a TOML file with a `suppress` entry for each suppression:
+//!
+//! ```toml
+//! [[suppress]]
+//! rule = "math-random"
+//! file = "classes/com/example/animation"
+//! reason = "Only used for animations."
+//!
+//! [[suppress]]
+//! fingerprint = "2f6a0b1c..."
+//! ```
+//!
+//! An entry suppresses the findings that match all of its criteria.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::{bail, Error, ResultExt};
+
+use super::Vulnerability;
+
+/// Name of the suppressions file that is used if none is configured, in the working directory.
+pub const DEFAULT_SUPPRESSIONS_FILE: &str = ".superignore";
+
+/// Findings suppressed in the analyses.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppressions {
+    /// Suppression entries.
+    #[serde(default)]
+    suppress: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// Loads the suppressions from the given TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = fs::read_to_string(path.as_ref())?;
+        Ok(Self::from_toml(&content).context(format!(
+            "the suppressions file at {} is not valid",
+            path.as_ref().display()
+        ))?)
+    }
+
+    /// Parses the suppressions from their TOML representation.
+    ///
+    /// It fails if an entry has no criteria, since it would suppress every finding.
+    pub fn from_toml(content: &str) -> Result<Self, Error> {
+        let suppressions: Self = toml::from_str(content)?;
+        for suppression in &suppressions.suppress {
+            if suppression.rule.is_none()
+                && suppression.file.is_none()
+                && suppression.fingerprint.is_none()
+            {
+                bail!("every suppression needs a `rule`, a `file` or a `fingerprint`");
+            }
+        }
+        Ok(suppressions)
+    }
+
+    /// Finds the suppression entry that suppresses the given vulnerability, if any.
+    pub fn find(&self, vulnerability: &Vulnerability) -> Option<&Suppression> {
+        self.suppress
+            .iter()
+            .find(|suppression| suppression.matches(vulnerability))
+    }
+}
+
+/// Suppression entry, with the criteria of the suppressed findings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    /// ID of the rule of the suppressed findings.
+    rule: Option<String>,
+    /// File or folder of the suppressed findings, relative to the decompiled application.
+    file: Option<PathBuf>,
+    /// Fingerprint of the suppressed finding.
+    fingerprint: Option<String>,
+    /// Reason of the suppression.
+    reason: Option<String>,
+}
+
+impl Suppression {
+    /// Gets the reason of the suppression, if any.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the vulnerability matches all the criteria of the entry.
+    fn matches(&self, vulnerability: &Vulnerability) -> bool {
+        self.rule.as_ref().map_or(true, |rule| {
+            vulnerability.get_rule_id() == Some(rule.as_str())
+        }) && self.file.as_ref().map_or(true, |file| {
+            vulnerability
+                .get_file()
+                .map_or(false, |path| path.starts_with(file))
+        }) && self.fingerprint.as_ref().map_or(true, |fingerprint| {
+            vulnerability
+                .get_fingerprint()
+                .eq_ignore_ascii_case(fingerprint)
+        })
+    }
+}
+
+/// Vulnerability suppressed in the results, with the reason of the suppression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuppressedVulnerability {
+    /// Suppressed vulnerability.
+    vulnerability: Vulnerability,
+    /// Reason of the suppression, if any.
+    reason: Option<String>,
+}
+
+impl SuppressedVulnerability {
+    /// Creates a new suppressed vulnerability.
+    pub fn new(vulnerability: Vulnerability, reason: Option<&str>) -> Self {
+        Self {
+            vulnerability,
+            reason: reason.map(str::to_owned),
+        }
+    }
+
+    /// Gets the suppressed vulnerability.
+    pub fn vulnerability(&self) -> &Vulnerability {
+        &self.vulnerability
+    }
+
+    /// Gets the reason of the suppression, if any.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_ref().map(String::as_str)
+    }
+}
+
+/// Finding suppression testing module.
+#[cfg(test)]
+mod test {
+    use super::Suppressions;
+    use crate::{criticality::Criticality, results::VulnerabilityBuilder};
+
+    /// Checks the findings suppressed by each kind of entry.
+    #[test]
+    fn it_find() {
+        let random = VulnerabilityBuilder::new(Criticality::Warning, "Math random", "Description")
+            .file("classes/com/example/animation/Fade.java")
+            .lines(10, 10)
+            .code("Math.random();")
+            .rule_id("math-random")
+            .build();
+        let other_random =
+            VulnerabilityBuilder::new(Criticality::Warning, "Math random", "Description")
+                .file("classes/com/example/Token.java")
+                .lines(3, 3)
+                .code("Math.random();")
+                .rule_id("math-random")
+                .build();
+        let log = VulnerabilityBuilder::new(Criticality::Low, "Log", "Description")
+            .file("classes/com/example/Token.java")
+            .lines(5, 5)
+            .code("Log.d(TAG, token);")
+            .rule_id("log")
+            .build();
+
+        let suppressions = Suppressions::from_toml(&format!(
+            r#"
+[[suppress]]
+rule = "math-random"
+file = "classes/com/example/animation"
+reason = "Only used for animations."
+
+[[suppress]]
+fingerprint = "{}"
+"#,
+            log.get_fingerprint().to_uppercase()
+        ))
+        .unwrap();
+
+        assert_eq!(
+            suppressions.find(&random).unwrap().reason(),
+            Some("Only used for animations.")
+        );
+        assert!(suppressions.find(&other_random).is_none());
+        assert!(suppressions.find(&log).unwrap().reason().is_none());
+
+        assert!(Suppressions::from_toml("").unwrap().find(&log).is_none());
+        assert!(Suppressions::from_toml("[[suppress]]\nreason = \"All\"").is_err());
+        assert!(Suppressions::from_toml("[[suppress]]\nline = 3").is_err());
+    }
+}
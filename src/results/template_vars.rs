@@ -0,0 +1,461 @@
+//! Template variable documentation module.
+//!
+//! The HTML report is rendered from the JSON serialization of the results, so the variables
+//! available to the handlebars templates are the fields of that serialization. This module walks
+//! the serialized results of an analysis and documents every field that they contain, with its
+//! type, an example value and its description, so that template authors don't need to read the
+//! serialization code to know what they can use.
+
+use failure::{Error, ResultExt};
+use serde_json::{json, Map, Value};
+
+use crate::results::Results;
+
+/// Results of a sample analysis, with every optional field of the results filled.
+const SAMPLE_RESULTS: &str = include_str!("sample_results.json");
+
+/// Maximum length of the example strings, longer examples are truncated.
+const MAX_EXAMPLE_LEN: usize = 80;
+
+/// Lists of vulnerabilities, documented as a single `vulnerability` structure.
+const VULNERABILITY_LISTS: [&str; 7] = [
+    "criticals[]",
+    "highs[]",
+    "mediums[]",
+    "lows[]",
+    "warnings[]",
+    "sections[].vulnerabilities[]",
+    "suppressed[].vulnerability",
+];
+
+/// Descriptions of the template variables, by path. Array items are marked with `[]`, and the
+/// fields of the vulnerabilities are under `vulnerability`, wherever they are listed.
+const DESCRIPTIONS: [(&str, &str); 78] = [
+    (
+        "super_version",
+        "Version of SUPER that analyzed the application.",
+    ),
+    ("results_version", "Version of the format of the results."),
+    ("now", "Date and time of the report generation."),
+    (
+        "now_rfc2822",
+        "Date and time of the report generation, in RFC 2822 format.",
+    ),
+    (
+        "now_rfc3339",
+        "Date and time of the report generation, in RFC 3339 format.",
+    ),
+    (
+        "engagement",
+        "Metadata of the engagement that the analysis is part of, if set.",
+    ),
+    ("engagement.title", "Title of the reports."),
+    ("engagement.client", "Client of the engagement."),
+    ("engagement.id", "Identifier of the engagement."),
+    ("app_package", "Package of the application."),
+    (
+        "app_version",
+        "Version string of the application (`versionName`).",
+    ),
+    (
+        "app_version_number",
+        "Version number of the application (`versionCode`).",
+    ),
+    (
+        "app_category",
+        "Category of the application, if it could be inferred.",
+    ),
+    (
+        "app_fingerprint",
+        "Hashes of the application, by algorithm.",
+    ),
+    (
+        "app_signers",
+        "Distinguished names of the signers of the application.",
+    ),
+    (
+        "app_permissions",
+        "Permissions requested by the application.",
+    ),
+    (
+        "permission_notes",
+        "Notes about the behavior of the permissions in each Android version.",
+    ),
+    ("permission_notes[].permission", "Name of the permission."),
+    (
+        "permission_notes[].added_in_sdk",
+        "SDK version that added the permission.",
+    ),
+    ("permission_notes[].notes", "Notes about the permission."),
+    (
+        "capabilities",
+        "Sensitive capabilities found in the application.",
+    ),
+    ("capabilities[].name", "Name of the capability."),
+    (
+        "capabilities[].description",
+        "Description of the capability and of its evidence.",
+    ),
+    (
+        "capabilities[].spyware_indicator",
+        "Whether the capability is a spyware indicator.",
+    ),
+    (
+        "capabilities[].file",
+        "File where the capability was found.",
+    ),
+    (
+        "capabilities[].line",
+        "Line of the file where the capability was found.",
+    ),
+    (
+        "source_url",
+        "Base URL of the published decompiled sources, if configured.",
+    ),
+    (
+        "manifest_recovered",
+        "Whether the manifest was recovered from a malformed binary file.",
+    ),
+    (
+        "cancelled",
+        "Whether the analysis was cancelled, so the results are partial.",
+    ),
+    (
+        "translation",
+        "Completeness of the translation and decompilation of the code.",
+    ),
+    ("score", "Security score of the application."),
+    ("policies", "Results of the configured policies."),
+    ("certificate", "Information about the signing certificate."),
+    (
+        "app_min_sdk_number",
+        "Minimum SDK version of the application.",
+    ),
+    (
+        "app_min_sdk_name",
+        "Name of the minimum Android version of the application.",
+    ),
+    (
+        "app_min_sdk_version",
+        "Minimum Android version of the application.",
+    ),
+    (
+        "app_target_sdk_number",
+        "Target SDK version of the application.",
+    ),
+    (
+        "app_target_sdk_name",
+        "Name of the target Android version of the application.",
+    ),
+    (
+        "app_target_sdk_version",
+        "Target Android version of the application.",
+    ),
+    (
+        "total_vulnerabilities",
+        "Number of vulnerabilities, without the warnings.",
+    ),
+    ("criticals", "Critical vulnerabilities."),
+    ("criticals_len", "Number of critical vulnerabilities."),
+    ("highs", "High criticality vulnerabilities."),
+    ("highs_len", "Number of high criticality vulnerabilities."),
+    ("mediums", "Medium criticality vulnerabilities."),
+    (
+        "mediums_len",
+        "Number of medium criticality vulnerabilities.",
+    ),
+    ("lows", "Low criticality vulnerabilities."),
+    ("lows_len", "Number of low criticality vulnerabilities."),
+    ("warnings", "Warnings."),
+    ("warnings_len", "Number of warnings."),
+    (
+        "sections",
+        "Vulnerabilities grouped in the report sections declared by the rules.",
+    ),
+    (
+        "suppressed",
+        "Vulnerabilities suppressed by the suppressions file.",
+    ),
+    ("suppressed[].reason", "Reason of the suppression."),
+    ("suppressed_len", "Number of suppressed vulnerabilities."),
+    (
+        "annotations",
+        "Notes of the analysts, from the annotations file of the results.",
+    ),
+    ("annotations.notes", "Text of the notes."),
+    (
+        "annotations.updated",
+        "Date and time of the last change of the notes, if known.",
+    ),
+    (
+        "single_file",
+        "Set in single file HTML reports, that don't include the source code tree.",
+    ),
+    (
+        "vulnerability.criticality",
+        "Criticality of the vulnerability.",
+    ),
+    ("vulnerability.name", "Name of the vulnerability."),
+    (
+        "vulnerability.description",
+        "Description of the vulnerability.",
+    ),
+    (
+        "vulnerability.file",
+        "File where the vulnerability was found.",
+    ),
+    (
+        "vulnerability.fingerprint",
+        "Stable identifier of the vulnerability.",
+    ),
+    (
+        "vulnerability.language",
+        "Language of the vulnerable code, from the file extension.",
+    ),
+    (
+        "vulnerability.line",
+        "Line of the vulnerable code, if it's a single line.",
+    ),
+    (
+        "vulnerability.start_line",
+        "First line of the vulnerable code.",
+    ),
+    (
+        "vulnerability.end_line",
+        "Last line of the vulnerable code.",
+    ),
+    ("vulnerability.code", "Vulnerable code snippet."),
+    ("vulnerability.cwe", "CWE identifier of the weakness."),
+    (
+        "vulnerability.confidence",
+        "Confidence in the finding: `low`, `medium` or `high`.",
+    ),
+    (
+        "vulnerability.references",
+        "References with further information.",
+    ),
+    (
+        "vulnerability.metadata",
+        "Additional information about the vulnerability, by key.",
+    ),
+    (
+        "vulnerability.section",
+        "Report section of the vulnerability.",
+    ),
+    (
+        "vulnerability.rule_id",
+        "Stable ID of the rule that found the vulnerability.",
+    ),
+    (
+        "vulnerability.owasp_masvs",
+        "OWASP MASVS control affected by the vulnerability.",
+    ),
+    (
+        "vulnerability.origin",
+        "Origin of the code: `first_party`, `sdk` or `third_party`.",
+    ),
+    (
+        "vulnerability.sdk",
+        "Name of the SDK of the code, if the origin is `sdk`.",
+    ),
+    (
+        "vulnerability.screenshot",
+        "Path of the screenshot of the HTML asset, if taken.",
+    ),
+];
+
+/// Loads the results of the sample analysis.
+pub fn sample_results() -> Result<Results, Error> {
+    Ok(
+        Results::from_json(SAMPLE_RESULTS.as_bytes())
+            .context("the sample results are not valid")?,
+    )
+}
+
+/// Documents the template variables of the given serialized results.
+///
+/// Every field gets its `type`, an `example` value for scalars, its `description` if it's known,
+/// and the documentation of its `fields` for objects and of its `items` for arrays. The
+/// variables that are only added when rendering the report are documented too.
+pub fn document_template_vars(context: &Value) -> Value {
+    let mut documentation = describe(context, "");
+    if let Some(Value::Object(fields)) = documentation.get_mut("fields") {
+        if !fields.contains_key("single_file") {
+            let _ = fields.insert(
+                "single_file".to_owned(),
+                variable("boolean", None, "single_file"),
+            );
+        }
+    }
+    documentation
+}
+
+/// Describes the given value, found at the given path.
+fn describe(value: &Value, path: &str) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut fields = Map::new();
+            for (key, field) in object {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let _ = fields.insert(key.clone(), describe(field, &field_path));
+            }
+            let mut documentation = variable("object", None, path);
+            if let Some(documentation) = documentation.as_object_mut() {
+                let _ = documentation.insert("fields".to_owned(), Value::Object(fields));
+            }
+            documentation
+        }
+        Value::Array(items) => {
+            let item_path = format!("{}[]", path);
+            let items =
+                items
+                    .iter()
+                    .map(|item| describe(item, &item_path))
+                    .fold(None, |merged, item| match merged {
+                        Some(merged) => Some(merge(merged, item)),
+                        None => Some(item),
+                    });
+            let mut documentation = variable("array", None, path);
+            if let (Some(documentation), Some(items)) = (documentation.as_object_mut(), items) {
+                let _ = documentation.insert("items".to_owned(), items);
+            }
+            documentation
+        }
+        Value::String(string) => {
+            let example = if string.chars().count() > MAX_EXAMPLE_LEN {
+                let truncated = string.chars().take(MAX_EXAMPLE_LEN).collect::<String>();
+                Value::String(format!("{}…", truncated))
+            } else {
+                value.clone()
+            };
+            variable("string", Some(example), path)
+        }
+        Value::Number(_) => variable("number", Some(value.clone()), path),
+        Value::Bool(_) => variable("boolean", Some(value.clone()), path),
+        Value::Null => variable("null", None, path),
+    }
+}
+
+/// Creates the documentation of a variable of the given type and path.
+fn variable(kind: &str, example: Option<Value>, path: &str) -> Value {
+    let mut documentation = json!({ "type": kind });
+    if let Some(object) = documentation.as_object_mut() {
+        if let Some(description) = description(path) {
+            let _ = object.insert(
+                "description".to_owned(),
+                Value::String(description.to_owned()),
+            );
+        }
+        if let Some(example) = example {
+            let _ = object.insert("example".to_owned(), example);
+        }
+    }
+    documentation
+}
+
+/// Gets the description of the variable at the given path, if it's known.
+fn description(path: &str) -> Option<&'static str> {
+    let path = VULNERABILITY_LISTS
+        .iter()
+        .find(|list| path.starts_with(*list))
+        .map_or_else(
+            || path.to_owned(),
+            |list| format!("vulnerability{}", &path[list.len()..]),
+        );
+    DESCRIPTIONS
+        .iter()
+        .find(|(variable, _)| *variable == path)
+        .map(|(_, description)| *description)
+}
+
+/// Merges the documentation of two items of the same array, so that the fields that only some
+/// items have are documented too.
+fn merge(mut first: Value, second: Value) -> Value {
+    match (first.get_mut("fields"), second.get("fields")) {
+        (Some(Value::Object(first_fields)), Some(Value::Object(second_fields))) => {
+            for (key, field) in second_fields {
+                let merged = match first_fields.remove(key) {
+                    Some(existing) => merge(existing, field.clone()),
+                    None => field.clone(),
+                };
+                let _ = first_fields.insert(key.clone(), merged);
+            }
+        }
+        _ => {
+            if first.get("items").is_none() {
+                if let (Some(object), Some(items)) = (first.as_object_mut(), second.get("items")) {
+                    let _ = object.insert("items".to_owned(), items.clone());
+                }
+            }
+        }
+    }
+    first
+}
+
+/// Template variable documentation testing module.
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{description, document_template_vars, sample_results};
+
+    /// Checks that the sample results are valid.
+    #[test]
+    fn it_sample() {
+        let results = sample_results().unwrap();
+        assert_eq!(results.app_package(), "com.example.bank");
+    }
+
+    /// Checks the documentation of objects, arrays and scalars.
+    #[test]
+    fn it_document() {
+        let documentation = document_template_vars(&json!({
+            "app_package": "com.example",
+            "highs_len": 1,
+            "highs": [
+                { "name": "Weak hash algorithm", "cwe": 328 },
+                { "name": "Exported activity", "line": 3 }
+            ]
+        }));
+
+        let fields = &documentation["fields"];
+        assert_eq!(fields["app_package"]["type"], "string");
+        assert_eq!(fields["app_package"]["example"], "com.example");
+        assert_eq!(
+            fields["app_package"]["description"],
+            "Package of the application."
+        );
+        assert_eq!(fields["highs_len"]["example"], 1);
+        assert_eq!(fields["single_file"]["type"], "boolean");
+
+        let item = &fields["highs"]["items"]["fields"];
+        assert_eq!(item["name"]["example"], "Weak hash algorithm");
+        assert_eq!(item["cwe"]["type"], "number");
+        assert_eq!(
+            item["line"]["description"],
+            "Line of the vulnerable code, if it's a single line."
+        );
+    }
+
+    /// Checks that the fields of every vulnerability list share their descriptions.
+    #[test]
+    fn it_description() {
+        assert_eq!(
+            description("sections[].vulnerabilities[].name"),
+            Some("Name of the vulnerability.")
+        );
+        assert_eq!(
+            description("suppressed[].vulnerability.code"),
+            Some("Vulnerable code snippet.")
+        );
+        assert_eq!(
+            description("suppressed[].reason"),
+            Some("Reason of the suppression.")
+        );
+        assert_eq!(description("unknown"), None);
+    }
+}
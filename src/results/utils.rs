@@ -6,19 +6,23 @@
 use std::{
     borrow::Cow,
     cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
     fs::File,
-    io::Read,
+    io::{Cursor, Read},
     path::{Path, PathBuf},
 };
 
-use failure::Error;
-use hex::ToHex;
+use failure::{bail, Error};
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::ser::{Serialize, SerializeStruct, Serializer};
-use {md5, sha1, sha2};
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    ser::{Serialize, SerializeStruct, Serializer},
+};
+use zip::ZipArchive;
+use {hex, md5, sha1, sha2};
 
-use crate::criticality::Criticality;
+use crate::{criticality::Criticality, static_analysis::libraries::Origin};
 
 /// Structure to store information about a vulnerability.
 #[derive(Debug, Clone, PartialEq, Eq, Ord)]
@@ -37,6 +41,22 @@ pub struct Vulnerability {
     end_line: Option<usize>,
     /// The vulnerable code snippet.
     code: Option<String>,
+    /// Optional CWE identifier of the weakness.
+    cwe: Option<u32>,
+    /// Optional confidence in the finding.
+    confidence: Option<Confidence>,
+    /// References with further information about the vulnerability.
+    references: Vec<String>,
+    /// Additional information about the vulnerability, by key.
+    metadata: BTreeMap<String, String>,
+    /// Origin of the code where the vulnerability was found, if known.
+    origin: Option<Origin>,
+    /// Report section of the vulnerability, if any.
+    section: Option<String>,
+    /// Stable ID of the rule that found the vulnerability, if any.
+    rule_id: Option<String>,
+    /// OWASP MASVS control affected by the vulnerability, if any.
+    owasp_masvs: Option<String>,
 }
 
 impl Vulnerability {
@@ -64,13 +84,283 @@ impl Vulnerability {
                 Some(c) => Some(c.into()),
                 None => None,
             },
+            cwe: None,
+            confidence: None,
+            references: Vec::new(),
+            metadata: BTreeMap::new(),
+            origin: None,
+            section: None,
+            rule_id: None,
+            owasp_masvs: None,
         }
     }
 
+    /// Sets the lines and the code of the vulnerability.
+    ///
+    /// The JSON representation of a vulnerability without code omits its lines, so they have to
+    /// be restored when the exact vulnerability is read back.
+    pub(crate) fn set_location(
+        &mut self,
+        start_line: Option<usize>,
+        end_line: Option<usize>,
+        code: Option<String>,
+    ) {
+        self.start_line = start_line;
+        self.end_line = end_line;
+        self.code = code;
+    }
+
     /// Gets the criticality of the vulnerability.
     pub fn get_criticality(&self) -> Criticality {
         self.criticality
     }
+
+    /// Gets the fingerprint of the vulnerability, used to suppress it and to match it in other
+    /// analyses.
+    ///
+    /// It's a hash of its name, its file and its matched code, ignoring the whitespace, so it
+    /// does not change when code is added or removed around the vulnerability.
+    pub fn get_fingerprint(&self) -> String {
+        use sha2::Digest;
+
+        let file = self.file.as_ref().map_or_else(String::new, |file| {
+            file.to_string_lossy().replace('\\', "/")
+        });
+        let code = match self.matched_code() {
+            Some(code) => code.split_whitespace().collect::<Vec<_>>().join(" "),
+            None => self.description.clone(),
+        };
+        hex::encode(sha2::Sha256::digest(
+            format!("{}\0{}\0{}", self.name, file, code).as_bytes(),
+        ))
+    }
+
+    /// Gets the lines of the code snippet between the start and the end lines of the
+    /// vulnerability, without the context lines that `get_code()` adds around them.
+    ///
+    /// Snippets without context lines are returned whole.
+    fn matched_code(&self) -> Option<String> {
+        let code = self.code.as_ref()?;
+        let matched = match (self.start_line, self.end_line) {
+            (Some(start_line), Some(end_line)) => {
+                // The snippet starts 4 lines above the start line, or at the top of the file.
+                let first_line = start_line.saturating_sub(4);
+                code.lines()
+                    .skip(start_line - first_line)
+                    .take(end_line.saturating_sub(start_line) + 1)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => String::new(),
+        };
+        if matched.trim().is_empty() {
+            Some(code.clone())
+        } else {
+            Some(matched)
+        }
+    }
+
+    /// Gets the name of the vulnerability.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the description of the vulnerability.
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    /// Gets the file where the vulnerability was found, if any.
+    pub fn get_file(&self) -> Option<&Path> {
+        self.file.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Gets the starting line of the vulnerability in its file, if any.
+    ///
+    /// Note that the first line of the file is line `0`.
+    pub fn get_start_line(&self) -> Option<usize> {
+        self.start_line
+    }
+
+    /// Gets the ending line of the vulnerability in its file, if any.
+    ///
+    /// Note that the first line of the file is line `0`.
+    pub fn get_end_line(&self) -> Option<usize> {
+        self.end_line
+    }
+
+    /// Gets the vulnerable code snippet, if any.
+    pub fn get_code(&self) -> Option<&str> {
+        self.code.as_ref().map(String::as_str)
+    }
+
+    /// Gets the CWE identifier of the weakness, if any.
+    pub fn get_cwe(&self) -> Option<u32> {
+        self.cwe
+    }
+
+    /// Gets the confidence in the finding, if any.
+    pub fn get_confidence(&self) -> Option<Confidence> {
+        self.confidence
+    }
+
+    /// Gets the references with further information about the vulnerability.
+    pub fn get_references(&self) -> &[String] {
+        &self.references
+    }
+
+    /// Gets the additional information about the vulnerability.
+    pub fn get_metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Gets the origin of the code where the vulnerability was found, if known.
+    pub fn get_origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    /// Gets the report section of the vulnerability, if any.
+    pub fn get_section(&self) -> Option<&str> {
+        self.section.as_ref().map(String::as_str)
+    }
+
+    /// Gets the stable ID of the rule that found the vulnerability, if any.
+    pub fn get_rule_id(&self) -> Option<&str> {
+        self.rule_id.as_ref().map(String::as_str)
+    }
+
+    /// Gets the OWASP MASVS control affected by the vulnerability, if any.
+    pub fn get_owasp_masvs(&self) -> Option<&str> {
+        self.owasp_masvs.as_ref().map(String::as_str)
+    }
+}
+
+/// Confidence in a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// The finding might be a false positive.
+    Low,
+    /// The finding is probably right.
+    Medium,
+    /// The finding is certain.
+    High,
+}
+
+/// Builder for vulnerabilities.
+///
+/// It allows creating vulnerabilities with only the information that is available, and it's the
+/// recommended way of creating findings from outside of the analyzer.
+#[derive(Debug, Clone)]
+pub struct VulnerabilityBuilder {
+    /// Vulnerability being built.
+    vulnerability: Vulnerability,
+}
+
+impl VulnerabilityBuilder {
+    /// Starts building a vulnerability with the given criticality, name and description.
+    pub fn new<N: Into<String>, D: Into<String>>(
+        criticality: Criticality,
+        name: N,
+        description: D,
+    ) -> Self {
+        Self {
+            vulnerability: Vulnerability::new(
+                criticality,
+                name,
+                description,
+                None::<&Path>,
+                None,
+                None,
+                None::<String>,
+            ),
+        }
+    }
+
+    /// Sets the file where the vulnerability was found, relative to the decompiled application.
+    pub fn file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.vulnerability.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the starting and ending lines of the vulnerability in its file.
+    ///
+    /// Note that the first line of the file is line `0`.
+    pub fn lines(mut self, start_line: usize, end_line: usize) -> Self {
+        self.vulnerability.start_line = Some(start_line);
+        self.vulnerability.end_line = Some(end_line);
+        self
+    }
+
+    /// Sets the vulnerable code snippet.
+    ///
+    /// The code is only kept if the file and the lines of the vulnerability are also set.
+    pub fn code<C: Into<String>>(mut self, code: C) -> Self {
+        self.vulnerability.code = Some(code.into());
+        self
+    }
+
+    /// Sets the CWE identifier of the weakness.
+    pub fn cwe(mut self, cwe: u32) -> Self {
+        self.vulnerability.cwe = Some(cwe);
+        self
+    }
+
+    /// Sets the confidence in the finding.
+    pub fn confidence(mut self, confidence: Confidence) -> Self {
+        self.vulnerability.confidence = Some(confidence);
+        self
+    }
+
+    /// Adds a reference with further information about the vulnerability.
+    pub fn reference<S: Into<String>>(mut self, reference: S) -> Self {
+        self.vulnerability.references.push(reference.into());
+        self
+    }
+
+    /// Adds additional information about the vulnerability.
+    pub fn metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        let _ = self.vulnerability.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the origin of the code where the vulnerability was found.
+    pub fn origin(mut self, origin: Origin) -> Self {
+        self.vulnerability.origin = Some(origin);
+        self
+    }
+
+    /// Sets the report section where the vulnerability is grouped, such as "Network security".
+    pub fn section<S: Into<String>>(mut self, section: S) -> Self {
+        self.vulnerability.section = Some(section.into());
+        self
+    }
+
+    /// Sets the stable ID of the rule that found the vulnerability.
+    pub fn rule_id<S: Into<String>>(mut self, rule_id: S) -> Self {
+        self.vulnerability.rule_id = Some(rule_id.into());
+        self
+    }
+
+    /// Sets the OWASP MASVS control affected by the vulnerability, such as `MASVS-CRYPTO-1`.
+    pub fn owasp_masvs<S: Into<String>>(mut self, owasp_masvs: S) -> Self {
+        self.vulnerability.owasp_masvs = Some(owasp_masvs.into());
+        self
+    }
+
+    /// Builds the vulnerability.
+    pub fn build(mut self) -> Vulnerability {
+        let has_location = self
+            .vulnerability
+            .file
+            .as_ref()
+            .map_or(false, |file| file.extension().is_some())
+            && self.vulnerability.start_line.is_some();
+        if !has_location {
+            self.vulnerability.code = None;
+        }
+        self.vulnerability
+    }
 }
 
 impl Serialize for Vulnerability {
@@ -78,22 +368,50 @@ impl Serialize for Vulnerability {
     where
         S: Serializer,
     {
-        let mut ser_struct = serializer.serialize_struct(
-            "Vulnerability",
-            if self.code.is_some() {
+        let len = {
+            let mut len = if self.code.is_some() {
                 if self.start_line == self.end_line {
-                    7
-                } else {
                     8
+                } else {
+                    9
                 }
             } else {
-                4
-            },
-        )?;
+                5
+            };
+            if self.cwe.is_some() {
+                len += 1;
+            }
+            if self.confidence.is_some() {
+                len += 1;
+            }
+            if !self.references.is_empty() {
+                len += 1;
+            }
+            if !self.metadata.is_empty() {
+                len += 1;
+            }
+            match self.origin {
+                Some(Origin::Sdk(_)) => len += 2,
+                Some(_) => len += 1,
+                None => {}
+            }
+            if self.section.is_some() {
+                len += 1;
+            }
+            if self.rule_id.is_some() {
+                len += 1;
+            }
+            if self.owasp_masvs.is_some() {
+                len += 1;
+            }
+            len
+        };
+        let mut ser_struct = serializer.serialize_struct("Vulnerability", len)?;
         ser_struct.serialize_field("criticality", &self.criticality)?;
         ser_struct.serialize_field("name", self.name.as_str())?;
         ser_struct.serialize_field("description", self.description.as_str())?;
         ser_struct.serialize_field("file", &self.file)?;
+        ser_struct.serialize_field("fingerprint", &self.get_fingerprint())?;
         if self.code.is_some() {
             ser_struct.serialize_field(
                 "language",
@@ -113,10 +431,102 @@ impl Serialize for Vulnerability {
             }
             ser_struct.serialize_field("code", &self.code)?;
         }
+        if let Some(cwe) = self.cwe {
+            ser_struct.serialize_field("cwe", &cwe)?;
+        }
+        if let Some(confidence) = self.confidence {
+            ser_struct.serialize_field("confidence", &confidence)?;
+        }
+        if !self.references.is_empty() {
+            ser_struct.serialize_field("references", &self.references)?;
+        }
+        if !self.metadata.is_empty() {
+            ser_struct.serialize_field("metadata", &self.metadata)?;
+        }
+        if let Some(ref origin) = self.origin {
+            ser_struct.serialize_field("origin", origin.name())?;
+            if let Origin::Sdk(ref sdk) = origin {
+                ser_struct.serialize_field("sdk", sdk)?;
+            }
+        }
+        if let Some(ref section) = self.section {
+            ser_struct.serialize_field("section", section)?;
+        }
+        if let Some(ref rule_id) = self.rule_id {
+            ser_struct.serialize_field("rule_id", rule_id)?;
+        }
+        if let Some(ref owasp_masvs) = self.owasp_masvs {
+            ser_struct.serialize_field("owasp_masvs", owasp_masvs)?;
+        }
         ser_struct.end()
     }
 }
 
+/// Vulnerability as stored in the JSON results, with lines starting at line `1`.
+#[derive(Deserialize)]
+struct VulnerabilityData {
+    criticality: Criticality,
+    name: String,
+    description: String,
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    code: Option<String>,
+    cwe: Option<u32>,
+    confidence: Option<Confidence>,
+    #[serde(default)]
+    references: Vec<String>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+    origin: Option<String>,
+    sdk: Option<String>,
+    section: Option<String>,
+    rule_id: Option<String>,
+    owasp_masvs: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Vulnerability {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = VulnerabilityData::deserialize(deserializer)?;
+        let start_line = data.line.or(data.start_line);
+        let end_line = data.line.or(data.end_line);
+        let origin = match data.origin.as_ref().map(String::as_str) {
+            Some("first_party") => Some(Origin::FirstParty),
+            Some("sdk") => Some(Origin::Sdk(data.sdk.unwrap_or_default())),
+            Some("third_party") => Some(Origin::ThirdParty),
+            Some(origin) => {
+                return Err(de::Error::custom(format!(
+                    "unknown code origin `{}`",
+                    origin
+                )));
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            criticality: data.criticality,
+            name: data.name,
+            description: data.description,
+            file: data.file,
+            start_line: start_line.map(|l| l.saturating_sub(1)),
+            end_line: end_line.map(|l| l.saturating_sub(1)),
+            code: data.code,
+            cwe: data.cwe,
+            confidence: data.confidence,
+            references: data.references,
+            metadata: data.metadata,
+            origin,
+            section: data.section,
+            rule_id: data.rule_id,
+            owasp_masvs: data.owasp_masvs,
+        })
+    }
+}
+
 impl PartialOrd for Vulnerability {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(
@@ -138,41 +548,161 @@ impl PartialOrd for Vulnerability {
     }
 }
 
+/// Hash algorithm used to fingerprint applications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// MD5 hash of the package.
+    Md5,
+    /// SHA-1 hash of the package.
+    Sha1,
+    /// SHA-256 hash of the package.
+    Sha256,
+    /// SHA-512 hash of the package.
+    Sha512,
+    /// SHA-256 hash of the DEX files of the package, that does not change if the package is
+    /// re-signed or its resources are modified.
+    DexSha256,
+}
+
+impl HashAlgorithm {
+    /// Gets the name of the algorithm, used as the key of the hash in the results.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::DexSha256 => "dex_sha256",
+        }
+    }
+
+    /// Gets the algorithm with the given name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            HashAlgorithm::Md5,
+            HashAlgorithm::Sha1,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha512,
+            HashAlgorithm::DexSha256,
+        ]
+        .iter()
+        .cloned()
+        .find(|algorithm| algorithm.name() == name)
+    }
+}
+
 /// Structure to store the application fingerprint.
+#[derive(Debug)]
 pub struct FingerPrint {
-    /// MD5 hash.
-    md5: md5::Digest,
-    /// SHA-1 hash.
-    sha1: sha1::Digest,
-    /// SHA-256 hash.
-    sha256: [u8; 32],
+    /// Hexadecimal hashes of the application, by algorithm.
+    hashes: BTreeMap<HashAlgorithm, String>,
 }
 
 impl FingerPrint {
     /// Creates a new fingerprint.
     ///
-    /// This function will read the complete file and generate its MD5, SHA-1 and SHA-256 hashes.
-    pub fn from_package<P: AsRef<Path>>(package: P) -> Result<Self, Error> {
+    /// This function will read the complete file and generate the hashes of the given
+    /// algorithms. The DEX hash is omitted if the package does not contain DEX files.
+    pub fn from_package<P: AsRef<Path>>(
+        package: P,
+        algorithms: &BTreeSet<HashAlgorithm>,
+    ) -> Result<Self, Error> {
         use sha2::Digest;
 
         let mut f = File::open(package)?;
         let mut buffer = Vec::with_capacity(f.metadata()?.len() as usize);
         let _ = f.read_to_end(&mut buffer)?;
 
-        let mut sha1 = sha1::Sha1::new();
-        sha1.update(&buffer);
+        let mut hashes = BTreeMap::new();
+        for algorithm in algorithms {
+            let hash = match algorithm {
+                HashAlgorithm::Md5 => format!("{:x}", md5::compute(&buffer)),
+                HashAlgorithm::Sha1 => {
+                    let mut sha1 = sha1::Sha1::new();
+                    sha1.update(&buffer);
+                    sha1.digest().to_string()
+                }
+                HashAlgorithm::Sha256 => hex::encode(sha2::Sha256::digest(&buffer)),
+                HashAlgorithm::Sha512 => hex::encode(sha2::Sha512::digest(&buffer)),
+                HashAlgorithm::DexSha256 => match dex_sha256(&buffer)? {
+                    Some(hash) => hash,
+                    None => continue,
+                },
+            };
+            let _ = hashes.insert(*algorithm, hash);
+        }
 
-        let mut sha256 = sha2::Sha256::default();
-        sha256.input(&buffer);
+        Ok(Self { hashes })
+    }
 
-        let mut sha256_res = [0_u8; 32];
-        sha256_res.clone_from_slice(&sha256.result()[..]);
-        Ok(Self {
-            md5: md5::compute(&buffer),
-            sha1: sha1.digest(),
-            sha256: sha256_res,
-        })
+    /// Gets the hash of the given algorithm, if it was computed.
+    pub fn hash(&self, algorithm: HashAlgorithm) -> Option<&str> {
+        self.hashes.get(&algorithm).map(String::as_str)
+    }
+
+    /// Adds the hashes of another fingerprint of the same package to this fingerprint.
+    ///
+    /// It fails if both fingerprints have a different hash for the same algorithm.
+    pub fn merge(&mut self, other: Self) -> Result<(), Error> {
+        for (algorithm, hash) in other.hashes {
+            if let Some(current) = self.hashes.get(&algorithm) {
+                if *current != hash {
+                    bail!(
+                        "the {} hashes of the packages don't match",
+                        algorithm.name()
+                    );
+                }
+            }
+            let _ = self.hashes.insert(algorithm, hash);
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes the SHA-256 hash of the DEX files of the package, in order, or of the package itself
+/// if it's a bare DEX file.
+///
+/// Returns `None` if the package has no DEX files.
+fn dex_sha256(package: &[u8]) -> Result<Option<String>, Error> {
+    use sha2::Digest;
+
+    // Bare DEX files are hashed as they are.
+    if package.starts_with(b"dex\n") {
+        return Ok(Some(hex::encode(sha2::Sha256::digest(package))));
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(package))?;
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        if is_dex_file_name(file.name()) {
+            names.push(file.name().to_owned());
+        }
+    }
+    if names.is_empty() {
+        return Ok(None);
+    }
+    // `classes.dex` goes first, then `classes2.dex`, `classes3.dex` and so on.
+    names.sort_by_key(|name| (name.len(), name.clone()));
+
+    let mut sha256 = sha2::Sha256::default();
+    for name in names {
+        let mut dex = Vec::new();
+        let _ = archive.by_name(&name)?.read_to_end(&mut dex)?;
+        sha256.input(&dex);
     }
+    Ok(Some(hex::encode(sha256.result())))
+}
+
+/// Checks if the given archive entry is one of the DEX files of the application.
+fn is_dex_file_name(name: &str) -> bool {
+    name.starts_with("classes")
+        && name.ends_with(".dex")
+        && name["classes".len()..name.len() - ".dex".len()]
+            .chars()
+            .all(|c| c.is_ascii_digit())
 }
 
 impl Serialize for FingerPrint {
@@ -180,17 +710,28 @@ impl Serialize for FingerPrint {
     where
         S: Serializer,
     {
-        let mut ser_struct = serializer.serialize_struct("fingerprint", 3)?;
-        ser_struct.serialize_field("md5", &format!("{:x}", self.md5))?;
-        ser_struct.serialize_field("sha1", &self.sha1.to_string())?;
-        let mut sha256_hex = String::new();
-        // It should never fail, we are writing directly to memory, without I/O access
-        // That's why the `expect()` should never panic.
-        self.sha256
-            .write_hex(&mut sha256_hex)
-            .expect("the SHA-256 fingerprinting of the application failed");
-        ser_struct.serialize_field("sha256", &sha256_hex)?;
-        ser_struct.end()
+        serializer.collect_map(
+            self.hashes
+                .iter()
+                .map(|(algorithm, hash)| (algorithm.name(), hash)),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for FingerPrint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut hashes = BTreeMap::new();
+        for (name, hash) in BTreeMap::<String, String>::deserialize(deserializer)? {
+            let algorithm = HashAlgorithm::from_name(&name).ok_or_else(|| {
+                de::Error::custom(format!("unknown fingerprint hash algorithm `{}`", name))
+            })?;
+            let _ = hashes.insert(algorithm, hash);
+        }
+
+        Ok(Self { hashes })
     }
 }
 
@@ -234,3 +775,172 @@ pub fn html_escape<'a, S: Into<Cow<'a, str>>>(input: S) -> Cow<'a, str> {
         input
     }
 }
+
+/// Results utilities testing module.
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, path::Path};
+
+    use serde_json;
+
+    use super::{
+        dex_sha256, Confidence, FingerPrint, HashAlgorithm, Vulnerability, VulnerabilityBuilder,
+    };
+    use crate::{criticality::Criticality, get_code, static_analysis::libraries::Origin};
+
+    /// Checks that vulnerabilities are read back from their JSON representation.
+    #[test]
+    fn it_vulnerability_round_trip() {
+        let single_line = Vulnerability::new(
+            Criticality::High,
+            "Name",
+            "Description",
+            Some("classes/Main.java"),
+            Some(4),
+            Some(4),
+            Some("code();"),
+        );
+        let multi_line = Vulnerability::new(
+            Criticality::Low,
+            "Name",
+            "Description",
+            Some("AndroidManifest.xml"),
+            Some(0),
+            Some(2),
+            Some("<manifest>"),
+        );
+        let no_code = Vulnerability::new(
+            Criticality::Warning,
+            "Name",
+            "Description",
+            None::<&Path>,
+            None,
+            None,
+            None::<String>,
+        );
+
+        let built = VulnerabilityBuilder::new(Criticality::Medium, "Name", "Description")
+            .file("classes/Main.java")
+            .lines(1, 1)
+            .code("code();")
+            .cwe(327)
+            .confidence(Confidence::High)
+            .reference("https://cwe.mitre.org/data/definitions/327.html")
+            .metadata("rule", "weak_crypto")
+            .origin(Origin::Sdk("OkHttp".to_owned()))
+            .section("Cryptography")
+            .rule_id("weak-algorithms")
+            .owasp_masvs("MASVS-CRYPTO-1")
+            .build();
+
+        for vulnerability in &[single_line, multi_line, no_code, built] {
+            let json = serde_json::to_string(vulnerability).unwrap();
+            let read: Vulnerability = serde_json::from_str(&json).unwrap();
+            assert_eq!(&read, vulnerability);
+        }
+    }
+
+    /// Checks that the builder only keeps the code of vulnerabilities with a location.
+    #[test]
+    fn it_vulnerability_builder() {
+        let vulnerability = VulnerabilityBuilder::new(Criticality::Low, "Name", "Description")
+            .code("code();")
+            .cwe(20)
+            .build();
+        assert_eq!(vulnerability.get_criticality(), Criticality::Low);
+        assert_eq!(vulnerability.get_cwe(), Some(20));
+        assert!(vulnerability.get_confidence().is_none());
+        assert!(vulnerability.code.is_none());
+
+        let vulnerability = VulnerabilityBuilder::new(Criticality::Low, "Name", "Description")
+            .file("res/values/strings.xml")
+            .lines(3, 5)
+            .code("<string>")
+            .reference("https://example.com")
+            .build();
+        assert_eq!(
+            vulnerability.get_file(),
+            Some(Path::new("res/values/strings.xml"))
+        );
+        assert_eq!(vulnerability.get_start_line(), Some(3));
+        assert_eq!(vulnerability.get_end_line(), Some(5));
+        assert_eq!(
+            vulnerability.code.as_ref().map(String::as_str),
+            Some("<string>")
+        );
+        assert_eq!(vulnerability.get_references(), &["https://example.com"]);
+    }
+
+    /// Checks that the fingerprint of a vulnerability only depends on its matched lines, and not
+    /// on the code around them.
+    #[test]
+    fn it_vulnerability_fingerprint() {
+        let vulnerability = |code: &str, line: usize| {
+            VulnerabilityBuilder::new(Criticality::High, "Weak Algorithms", "Description")
+                .file("classes/Main.java")
+                .lines(line, line)
+                .code(get_code(code, line, line))
+                .build()
+        };
+
+        let code = "class Main {\nint a;\nint b;\nint c;\nint d;\nint e;\n\
+                    MessageDigest.getInstance(\"MD5\");\nint f;\n}";
+        let edited = "class Main {\nint a;\nint b;\nint z;\nint d;\nint e;\n\
+                      MessageDigest.getInstance(\"MD5\");\nint g;\n}";
+        let moved = "class Main {\nint a;\n\n\nint b;\nint c;\nint d;\nint e;\n\
+                     MessageDigest.getInstance(\"MD5\");\nint f;\n}";
+        let fingerprint = vulnerability(code, 6).get_fingerprint();
+        assert_eq!(vulnerability(edited, 6).get_fingerprint(), fingerprint);
+        assert_eq!(vulnerability(moved, 8).get_fingerprint(), fingerprint);
+        assert_ne!(vulnerability(code, 5).get_fingerprint(), fingerprint);
+
+        let other = VulnerabilityBuilder::new(Criticality::High, "Weak Algorithms", "Description")
+            .file("classes/Main.java")
+            .lines(6, 6)
+            .code("MessageDigest.getInstance(\"MD5\");")
+            .build();
+        assert_eq!(other.get_fingerprint(), fingerprint);
+    }
+
+    /// Checks that fingerprints are read back from their JSON representation.
+    #[test]
+    fn it_fingerprint_round_trip() {
+        let mut hashes = BTreeMap::new();
+        let _ = hashes.insert(HashAlgorithm::Md5, "aa".to_owned());
+        let _ = hashes.insert(HashAlgorithm::DexSha256, "bb".to_owned());
+        let fingerprint = FingerPrint { hashes };
+
+        let json = serde_json::to_string(&fingerprint).unwrap();
+        assert_eq!(json, r#"{"md5":"aa","dex_sha256":"bb"}"#);
+        let read: FingerPrint = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.hash(HashAlgorithm::Md5), Some("aa"));
+        assert_eq!(read.hash(HashAlgorithm::DexSha256), Some("bb"));
+        assert!(read.hash(HashAlgorithm::Sha1).is_none());
+
+        assert!(serde_json::from_str::<FingerPrint>(r#"{"crc32":"cc"}"#).is_err());
+    }
+
+    /// Checks that the hashes of fingerprints of the same package are merged.
+    #[test]
+    fn it_fingerprint_merge() {
+        let mut fingerprint: FingerPrint = serde_json::from_str(r#"{"md5":"aa"}"#).unwrap();
+        let other = serde_json::from_str(r#"{"md5":"aa","sha1":"bb"}"#).unwrap();
+        fingerprint.merge(other).unwrap();
+        assert_eq!(fingerprint.hash(HashAlgorithm::Md5), Some("aa"));
+        assert_eq!(fingerprint.hash(HashAlgorithm::Sha1), Some("bb"));
+
+        let other = serde_json::from_str(r#"{"sha1":"cc"}"#).unwrap();
+        assert!(fingerprint.merge(other).is_err());
+    }
+
+    /// Checks that bare DEX files are hashed as they are.
+    #[test]
+    fn it_dex_sha256() {
+        let dex = b"dex\n035\0";
+        assert_eq!(
+            dex_sha256(dex).unwrap(),
+            Some("2c54a4c51192601674623d247e827ff8fcee7212eda7fed6bab5a4e959ace58e".to_owned())
+        );
+        assert!(dex_sha256(b"not a package").is_err());
+    }
+}
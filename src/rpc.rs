@@ -0,0 +1,496 @@
+//! JSON-RPC analysis mode.
+//!
+//! This experimental mode keeps the analyzer running and accepts JSON-RPC 2.0 requests, one per
+//! line, in the standard input. Responses and notifications are written to the standard output,
+//! also one per line, so that editor plugins can analyze the decompiled code of an application
+//! without paying for a cold start on every change.
+//!
+//! The supported methods are:
+//!
+//! - `analyze`: analyzes the file or directory in the `path` parameter. A `diagnostics`
+//!   notification is sent for every analyzed file as soon as it has been checked, and the
+//!   response contains the number of analyzed files and found vulnerabilities.
+//! - `badge`: answers with a [shields.io endpoint](https://shields.io/endpoint) badge for the
+//!   application in the `package` parameter, from the latest complete analysis in the history
+//!   database. The optional `style` parameter selects between the security `score` badge, the
+//!   default, and the `vulnerabilities` badge, with the number of vulnerabilities by criticality.
+//!   The same badges are written in the report folder of the application after each analysis.
+//! - `shutdown`: answers the request and stops the server.
+//!
+//! The rules files are watched while the server runs. When they change, the new rules are loaded
+//! and validated, and they replace the previous ones at once, so that rule authors can iterate
+//! against a corpus without restarting the server. A `rulesReloaded` notification is sent with the
+//! number of loaded rules, or a `rulesError` notification with the reason if the new rules are not
+//! valid, in which case the previous rules are kept.
+
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use failure::{bail, Error, ResultExt};
+use serde_json::{self, json, Value};
+
+use crate::{
+    history::{History, HISTORY_FILE},
+    results::VulnerabilitySink,
+    static_analysis::code::{analyze_file, load_rules, rules_modified, Rule},
+    utils::add_files_with_extensions,
+    Config,
+};
+
+/// Invalid JSON was received.
+const PARSE_ERROR: i64 = -32_700;
+/// The JSON sent is not a valid request object.
+const INVALID_REQUEST: i64 = -32_600;
+/// The method does not exist.
+const METHOD_NOT_FOUND: i64 = -32_601;
+/// Invalid method parameters.
+const INVALID_PARAMS: i64 = -32_602;
+/// Internal error while processing the request.
+const INTERNAL_ERROR: i64 = -32_603;
+
+/// Interval between the checks of the rules files for changes, in milliseconds.
+const RULES_WATCH_INTERVAL: u64 = 500;
+
+/// Code analysis rules of the server, reloaded when the rules files change.
+#[derive(Debug)]
+struct WatchedRules {
+    /// Current rules, with the last modification time of the rules files when they were checked.
+    state: Mutex<(Arc<Vec<Rule>>, Option<SystemTime>)>,
+    /// Whether the server is still running.
+    running: AtomicBool,
+}
+
+impl WatchedRules {
+    /// Loads the configured rules.
+    fn load(config: &Config) -> Result<Self, Error> {
+        let modified = rules_modified(config);
+        let rules = load_rules(config)?;
+        Ok(Self {
+            state: Mutex::new((Arc::new(rules), modified)),
+            running: AtomicBool::new(true),
+        })
+    }
+
+    /// Gets the current rules.
+    ///
+    /// Analyses keep the rules they started with, even if the rules are reloaded meanwhile.
+    fn current(&self) -> Arc<Vec<Rule>> {
+        Arc::clone(&self.state.lock().unwrap_or_else(PoisonError::into_inner).0)
+    }
+
+    /// Reloads the rules if any rules file changed since they were last checked.
+    ///
+    /// The new rules are validated first, and they only replace the current ones if they are
+    /// valid. Otherwise, the current rules are kept until the rules files change again. It returns
+    /// the number of loaded rules, or `None` if the rules files did not change.
+    fn reload(&self, config: &Config) -> Option<Result<usize, Error>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let modified = rules_modified(config);
+        if modified == state.1 {
+            return None;
+        }
+        state.1 = modified;
+
+        Some(load_valid_rules(config).map(|rules| {
+            let len = rules.len();
+            state.0 = Arc::new(rules);
+            len
+        }))
+    }
+
+    /// Checks if the server is still running.
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Stops watching the rules files.
+    fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Starts serving JSON-RPC requests from the standard input.
+///
+/// Rules are loaded when the server starts, and they are reloaded as soon as any rules file
+/// changes, and before every analysis. The server stops when a `shutdown` request is received or
+/// when the standard input is closed.
+pub fn serve(config: &Config) -> Result<(), Error> {
+    let rules =
+        Arc::new(WatchedRules::load(config).context("could not load the code analysis rules")?);
+    let watcher = {
+        let rules = Arc::clone(&rules);
+        let config = config.clone();
+        thread::spawn(move || watch_rules(&config, &rules))
+    };
+
+    let result = handle_requests(config, &rules);
+    rules.stop();
+    let _ = watcher.join();
+    result
+}
+
+/// Handles the requests of the standard input until the server stops.
+fn handle_requests(config: &Config, rules: &WatchedRules) -> Result<(), Error> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line.context("could not read from the standard input")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // The output is only locked while handling a request, so that the rules watcher can send
+        // its notifications between requests.
+        let mut out = stdout.lock();
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(e) => {
+                respond(
+                    &mut out,
+                    Some(Value::Null),
+                    Err((PARSE_ERROR, e.to_string())),
+                )?;
+                continue;
+            }
+        };
+        // Requests without an `id` are notifications, and must not be answered.
+        let id = request.get("id").cloned();
+
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(m) => m,
+            None => {
+                respond(
+                    &mut out,
+                    Some(id.unwrap_or(Value::Null)),
+                    Err((INVALID_REQUEST, "the request has no method".to_owned())),
+                )?;
+                continue;
+            }
+        };
+
+        match method {
+            "analyze" => {
+                let path = request
+                    .get("params")
+                    .and_then(|p| p.get("path"))
+                    .and_then(Value::as_str);
+                // The watcher could have not seen the last changes of the rules files yet.
+                reload_rules(&mut out, config, rules)?;
+                let result = match path {
+                    Some(path) => analyze(&mut out, Path::new(path), &rules.current(), config)?,
+                    None => Err((
+                        INVALID_PARAMS,
+                        "the `path` parameter is required".to_owned(),
+                    )),
+                };
+                respond(&mut out, id, result)?;
+            }
+            "badge" => {
+                let params = request.get("params");
+                let package = params
+                    .and_then(|p| p.get("package"))
+                    .and_then(Value::as_str);
+                let style = params
+                    .and_then(|p| p.get("style"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("score");
+                let result = match package {
+                    Some(package) => badge(package, style, config),
+                    None => Err((
+                        INVALID_PARAMS,
+                        "the `package` parameter is required".to_owned(),
+                    )),
+                };
+                respond(&mut out, id, result)?;
+            }
+            "shutdown" => {
+                respond(&mut out, id, Ok(Value::Null))?;
+                break;
+            }
+            _ => respond(
+                &mut out,
+                id,
+                Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Watches the rules files, reloading the rules when they change, until the server stops.
+fn watch_rules(config: &Config, rules: &WatchedRules) {
+    while rules.is_running() {
+        thread::sleep(Duration::from_millis(RULES_WATCH_INTERVAL));
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        if reload_rules(&mut out, config, rules).is_err() {
+            break;
+        }
+    }
+}
+
+/// Reloads the rules if any rules file changed, and notifies the result.
+///
+/// The error is only returned if the output channel fails.
+fn reload_rules<W: Write>(out: &mut W, config: &Config, rules: &WatchedRules) -> Result<(), Error> {
+    let notification = match rules.reload(config) {
+        None => return Ok(()),
+        Some(Ok(len)) => json!({
+            "jsonrpc": "2.0",
+            "method": "rulesReloaded",
+            "params": {
+                "rules": len,
+            },
+        }),
+        Some(Err(e)) => json!({
+            "jsonrpc": "2.0",
+            "method": "rulesError",
+            "params": {
+                "message": e
+                    .iter_chain()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(": "),
+            },
+        }),
+    };
+    send(out, &notification)
+}
+
+/// Loads the code analysis rules, checking that the rules with code examples pass them.
+fn load_valid_rules(config: &Config) -> Result<Vec<Rule>, Error> {
+    let rules = load_rules(config)?;
+    for rule in &rules {
+        if let Some(failure) = rule.test_examples().into_iter().next() {
+            bail!(
+                "the `{}` rule fails its examples: {}",
+                rule.label(),
+                failure
+            );
+        }
+    }
+    Ok(rules)
+}
+
+/// Analyzes the given file or directory, sending a `diagnostics` notification per file.
+///
+/// Since there is no manifest available, rules that depend on permissions are skipped. The
+/// outer error is only returned if the output channel fails.
+fn analyze<W: Write>(
+    out: &mut W,
+    path: &Path,
+    rules: &[Rule],
+    config: &Config,
+) -> Result<Result<Value, (i64, String)>, Error> {
+    let (root, files) = if path.is_dir() {
+        let mut files = Vec::new();
+        if let Err(e) = add_files_with_extensions(path, &["java", "xml"], &mut files) {
+            return Ok(Err((
+                INTERNAL_ERROR,
+                format!("could not read `{}`: {}", path.display(), e),
+            )));
+        }
+        // The manifest and the generated resource classes have no code to analyze.
+        files.retain(|file| {
+            let file_name = file.file_name().unwrap().to_string_lossy();
+            file_name != "AndroidManifest.xml"
+                && file_name != "R.java"
+                && !file_name.starts_with("R$")
+        });
+        (path, files)
+    } else if path.is_file() {
+        (
+            path.parent().unwrap_or_else(|| Path::new("")),
+            vec![path.to_path_buf()],
+        )
+    } else {
+        return Ok(Err((
+            INVALID_PARAMS,
+            format!("`{}` is not a file or a directory", path.display()),
+        )));
+    };
+
+    let mut total_vulnerabilities = 0;
+    for file in &files {
+        let sink = VulnerabilitySink::new();
+        if let Err(e) = analyze_file(file, root, rules, &None, config, &sink) {
+            return Ok(Err((
+                INTERNAL_ERROR,
+                format!("could not analyze `{}`: {}", file.display(), e),
+            )));
+        }
+        let vulnerabilities = match sink.to_vec() {
+            Ok(vulnerabilities) => vulnerabilities,
+            Err(e) => {
+                return Ok(Err((
+                    INTERNAL_ERROR,
+                    format!("could not read the findings of `{}`: {}", file.display(), e),
+                )));
+            }
+        };
+        total_vulnerabilities += vulnerabilities.len();
+
+        send(
+            out,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "diagnostics",
+                "params": {
+                    "file": file,
+                    "vulnerabilities": vulnerabilities,
+                },
+            }),
+        )?;
+    }
+
+    Ok(Ok(json!({
+        "files": files.len(),
+        "vulnerabilities": total_vulnerabilities,
+    })))
+}
+
+/// Gets the badge of the given application package, from its latest complete analysis.
+///
+/// The history database is loaded on every request, so that the badge reflects the analyses
+/// finished while the server is running.
+fn badge(package: &str, style: &str, config: &Config) -> Result<Value, (i64, String)> {
+    let history = History::load(config.results_folder().join(HISTORY_FILE))
+        .map_err(|e| (INTERNAL_ERROR, format!("could not load the history: {}", e)))?;
+    let entry = history.latest_results(package).ok_or_else(|| {
+        (
+            INVALID_PARAMS,
+            format!("`{}` has no complete analysis", package),
+        )
+    })?;
+
+    match style {
+        "score" => Ok(entry.score_badge()),
+        "vulnerabilities" => Ok(entry.vulnerabilities_badge()),
+        _ => Err((INVALID_PARAMS, format!("unknown badge style `{}`", style))),
+    }
+}
+
+/// Sends the response to a request, if it was not a notification.
+fn respond<W: Write>(
+    out: &mut W,
+    id: Option<Value>,
+    result: Result<Value, (i64, String)>,
+) -> Result<(), Error> {
+    if let Some(id) = id {
+        let message = match result {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+            }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": code,
+                    "message": message,
+                },
+            }),
+        };
+        send(out, &message)?;
+    }
+    Ok(())
+}
+
+/// Writes a message as a single line in the output and flushes it.
+fn send<W: Write>(out: &mut W, message: &Value) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, message)?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// JSON-RPC analysis mode testing module.
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, sync::Arc};
+
+    use serde_json::{json, Value};
+
+    use super::{respond, WatchedRules, INVALID_PARAMS};
+    use crate::Config;
+
+    /// Checks that only the requests are answered, with their result or their error.
+    #[test]
+    fn it_respond() {
+        let mut out = Vec::new();
+        respond(&mut out, Some(json!(1)), Ok(Value::Null)).unwrap();
+        respond(&mut out, None, Ok(Value::Null)).unwrap();
+        respond(
+            &mut out,
+            Some(json!(2)),
+            Err((INVALID_PARAMS, "missing".to_owned())),
+        )
+        .unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["id"], 1);
+        assert!(lines[0]["result"].is_null());
+        assert_eq!(lines[1]["error"]["code"], INVALID_PARAMS);
+    }
+
+    /// Checks that the rules are only replaced by valid rules.
+    #[test]
+    fn it_reload_rules() {
+        let folder = Path::new("target/rpc_rules_test");
+        fs::create_dir_all(folder).unwrap();
+        let file = folder.join("rules.yaml");
+        let rule = "- id: hash\n  label: Weak hash\n  description: Test\n  criticality: low\n  \
+                    regex: MD5\n";
+        fs::write(&file, rule).unwrap();
+        let config: Config =
+            toml::from_str(&format!(r#"rules_json = ["{}"]"#, file.display())).unwrap();
+
+        let rules = WatchedRules::load(&config).unwrap();
+        let loaded = rules.current();
+        assert_eq!(loaded.len(), 1);
+        assert!(rules.reload(&config).is_none());
+
+        // The modification time is reset, since it could not change within the same second.
+        fs::write(
+            &file,
+            format!("{}  examples:\n    positive: [SHA256]\n", rule),
+        )
+        .unwrap();
+        rules.state.lock().unwrap().1 = None;
+        assert!(rules.reload(&config).unwrap().is_err());
+        assert!(Arc::ptr_eq(&loaded, &rules.current()));
+        assert!(rules.reload(&config).is_none());
+
+        fs::write(
+            &file,
+            format!(
+                "{}- id: cipher\n  label: Weak cipher\n  description: Test\n  criticality: \
+                 low\n  regex: RC4\n",
+                rule
+            ),
+        )
+        .unwrap();
+        rules.state.lock().unwrap().1 = None;
+        assert_eq!(rules.reload(&config).unwrap().unwrap(), 2);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(rules.current().len(), 2);
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+//! Debug artifact analysis module.
+//!
+//! Release builds sometimes ship with leftovers from development: debug build configurations,
+//! relaxed `StrictMode` policies, debugging libraries or the root certificates of intercepting
+//! proxies. Each kind of artifact can be enabled or disabled in the configuration.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::Error;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{code::get_line_for, resources::Resources};
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    utils::add_java_files,
+    Config,
+};
+
+/// Debugging libraries that should not be included in release builds, with the folder of their
+/// classes and their name.
+const DEBUG_LIBRARIES: [(&str, &str); 3] = [
+    ("com/squareup/leakcanary", "LeakCanary"),
+    ("com/facebook/flipper", "Flipper"),
+    ("com/facebook/stetho", "Stetho"),
+];
+/// Names in the root certificates of intercepting proxies.
+const PROXY_CERTIFICATE_NAMES: [&str; 5] = [
+    "Charles Proxy",
+    "mitmproxy",
+    "PortSwigger",
+    "DO_NOT_TRUST_FiddlerRoot",
+    "Proxyman",
+];
+/// Folders of the application where bundled certificates are searched.
+const CERTIFICATE_FOLDERS: [&str; 2] = ["assets", "res/raw"];
+/// Maximum size of the files that are checked for certificates, since certificates are small.
+const MAX_CERTIFICATE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Kind of debug artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugArtifact {
+    /// `BuildConfig.DEBUG` enabled, or used to bypass security checks.
+    BuildConfigDebug,
+    /// `StrictMode` policies that permit everything.
+    StrictMode,
+    /// Debugging libraries, such as _LeakCanary_, _Flipper_ or _Stetho_.
+    DebugLibraries,
+    /// Root certificates of intercepting proxies, such as _Charles_ or _mitmproxy_.
+    ProxyCertificates,
+}
+
+impl DebugArtifact {
+    /// Gets all the kinds of debug artifacts.
+    pub fn all() -> &'static [Self] {
+        &[
+            DebugArtifact::BuildConfigDebug,
+            DebugArtifact::StrictMode,
+            DebugArtifact::DebugLibraries,
+            DebugArtifact::ProxyCertificates,
+        ]
+    }
+}
+
+/// Searches the application for the debug artifacts enabled in the configuration.
+pub fn analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut Results) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let artifacts = config.debug_artifacts();
+
+    if artifacts.contains(&DebugArtifact::BuildConfigDebug)
+        || artifacts.contains(&DebugArtifact::StrictMode)
+    {
+        let mut files = Vec::new();
+        if let Err(e) = add_java_files(&dist_folder.join("classes"), &mut files) {
+            print_warning(format!(
+                "there was an error searching the code for debug artifacts: {}",
+                e
+            ));
+        }
+        for file in files {
+            match fs::read_to_string(&file) {
+                Ok(code) => {
+                    let path = file.strip_prefix(&dist_folder).unwrap_or(&file);
+                    check_code(config, path, &code, results);
+                }
+                Err(e) => print_warning(format!(
+                    "could not read {} searching for debug artifacts: {}",
+                    file.display(),
+                    e
+                )),
+            }
+        }
+    }
+
+    if artifacts.contains(&DebugArtifact::DebugLibraries) {
+        for (folder, library) in &DEBUG_LIBRARIES {
+            if dist_folder.join("classes").join(folder).is_dir() {
+                report(
+                    config,
+                    results,
+                    Criticality::Medium,
+                    "Debug library in release build",
+                    format!(
+                        "The {} debugging library is included in the application. Debugging \
+                         libraries expose internal data, such as databases, preferences, network \
+                         traffic or heap dumps, and should only be included in debug builds.",
+                        library
+                    ),
+                    Some(Path::new("classes").join(folder)),
+                    None,
+                );
+            }
+        }
+    }
+
+    if artifacts.contains(&DebugArtifact::ProxyCertificates) {
+        for folder in &CERTIFICATE_FOLDERS {
+            let path = dist_folder.join(folder);
+            if path.is_dir() {
+                if let Err(e) = check_certificates(config, &dist_folder, &path, results) {
+                    print_warning(format!(
+                        "there was an error searching for proxy certificates in {}: {}",
+                        path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+        // Resource obfuscation moves the raw resources out of the `res/raw` folder.
+        for path in Resources::load(&dist_folder).obfuscated_raw_files() {
+            let path = dist_folder.join(path);
+            if let Err(e) = check_certificate(config, &dist_folder, &path, results) {
+                print_warning(format!(
+                    "there was an error checking the raw resource {} for proxy certificates: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+    }
+}
+
+/// Checks the code of a Java file for debug artifacts.
+fn check_code(config: &Config, path: &Path, code: &str, results: &mut Results) {
+    lazy_static! {
+        static ref DEBUG_ENABLED: Regex = Regex::new(
+            r"boolean\s+DEBUG\s*=\s*(?:true|Boolean\s*\.\s*parseBoolean\s*\(\s*\x22true\x22\s*\))\s*;"
+        )
+        .unwrap();
+        static ref DEBUG_BYPASS: Regex = Regex::new(
+            r"if\s*\(\s*BuildConfig\s*\.\s*DEBUG\s*\)\s*\{?[^}]*(?:ALLOW_ALL_HOSTNAME_VERIFIER|setHostnameVerifier|checkServerTrusted|TrustManager|setWebContentsDebuggingEnabled\s*\(\s*true|proceed\s*\(\s*\))"
+        )
+        .unwrap();
+        static ref STRICT_MODE_PERMIT_ALL: Regex = Regex::new(
+            r"StrictMode\s*\.\s*set(?:Thread|Vm)Policy\s*\([^;]*\.\s*permitAll\s*\(\s*\)"
+        )
+        .unwrap();
+    }
+
+    let artifacts = config.debug_artifacts();
+    let is_build_config = path.file_name().map_or(false, |n| n == "BuildConfig.java");
+
+    if artifacts.contains(&DebugArtifact::BuildConfigDebug) {
+        if is_build_config {
+            if let Some(m) = DEBUG_ENABLED.find(code) {
+                report(
+                    config,
+                    results,
+                    Criticality::Medium,
+                    "Debug build configuration",
+                    "The application was built with the debug configuration: BuildConfig.DEBUG \
+                     is true. Code meant only for development, such as verbose logging or \
+                     relaxed checks, is enabled in this build.",
+                    Some(path.to_path_buf()),
+                    Some((code, m.start(), m.end())),
+                );
+            }
+        }
+        for m in DEBUG_BYPASS.find_iter(code) {
+            report(
+                config,
+                results,
+                Criticality::Low,
+                "Security check bypassed in debug builds",
+                "A security check, such as certificate or host name validation, is relaxed when \
+                 BuildConfig.DEBUG is true. If a debug build is distributed, or the flag is \
+                 modified, the application will be vulnerable. Use a separate build type or \
+                 network security configuration for debugging.",
+                Some(path.to_path_buf()),
+                Some((code, m.start(), m.end())),
+            );
+        }
+    }
+
+    if artifacts.contains(&DebugArtifact::StrictMode) {
+        for m in STRICT_MODE_PERMIT_ALL.find_iter(code) {
+            report(
+                config,
+                results,
+                Criticality::Low,
+                "StrictMode penalties disabled",
+                "A StrictMode policy that permits everything is set. It's usually a workaround to \
+                 perform network or disk operations in the main thread, that hides performance \
+                 problems and can make the application unresponsive.",
+                Some(path.to_path_buf()),
+                Some((code, m.start(), m.end())),
+            );
+        }
+    }
+}
+
+/// Checks the certificates in the given folder, recursively, reporting the root certificates of
+/// intercepting proxies.
+///
+/// The files that can't be read are skipped with a warning, so that they don't stop the search in
+/// the rest of the folder.
+fn check_certificates(
+    config: &Config,
+    dist_folder: &Path,
+    path: &Path,
+    results: &mut Results,
+) -> Result<(), Error> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                print_warning(format!(
+                    "there was an error searching for proxy certificates in {}: {}",
+                    path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        let checked = if entry_path.is_dir() {
+            check_certificates(config, dist_folder, &entry_path, results)
+        } else {
+            check_certificate(config, dist_folder, &entry_path, results)
+        };
+        if let Err(e) = checked {
+            print_warning(format!(
+                "there was an error checking {} for proxy certificates: {}",
+                entry_path.display(),
+                e
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the given file, reporting it if it's the root certificate of an intercepting proxy.
+///
+/// Files bigger than `MAX_CERTIFICATE_FILE_SIZE` are skipped without reading them.
+fn check_certificate(
+    config: &Config,
+    dist_folder: &Path,
+    path: &Path,
+    results: &mut Results,
+) -> Result<(), Error> {
+    if path.metadata()?.len() > MAX_CERTIFICATE_FILE_SIZE {
+        return Ok(());
+    }
+    let data = fs::read(path)?;
+    if let Some(name) = proxy_certificate_name(&data) {
+        report(
+            config,
+            results,
+            Criticality::High,
+            "Intercepting proxy certificate",
+            format!(
+                "The application contains the root certificate of the {} intercepting proxy. If \
+                 the application trusts it, anyone with that proxy can intercept its encrypted \
+                 traffic. It's usually a leftover of testing.",
+                name
+            ),
+            Some(path.strip_prefix(dist_folder)?.to_path_buf()),
+            None,
+        );
+    }
+    Ok(())
+}
+
+/// Gets the name of the intercepting proxy that issued the certificate in the given data, if any.
+///
+/// Both DER and PEM encoded certificates are supported.
+fn proxy_certificate_name(data: &[u8]) -> Option<&'static str> {
+    let pem_begin = b"-----BEGIN CERTIFICATE-----";
+    let der = if let Some(start) = find(data, pem_begin) {
+        let body = &data[start + pem_begin.len()..];
+        let end = find(body, b"-----END").unwrap_or_else(|| body.len());
+        base64_decode(&body[..end])?
+    } else if data.first() == Some(&0x30) {
+        data.to_vec()
+    } else {
+        return None;
+    };
+
+    PROXY_CERTIFICATE_NAMES
+        .iter()
+        .find(|name| find(&der, name.as_bytes()).is_some())
+        .cloned()
+}
+
+/// Finds the position of the needle in the data.
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Decodes base64 data, ignoring whitespace.
+fn base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+    for byte in data {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\r' | b'\n' | b' ' | b'\t' => continue,
+            _ => return None,
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// Reports a debug artifact, if its criticality is over the minimum.
+fn report<D: Into<String>>(
+    config: &Config,
+    results: &mut Results,
+    criticality: Criticality,
+    name: &str,
+    description: D,
+    path: Option<PathBuf>,
+    code: Option<(&str, usize, usize)>,
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let description = description.into();
+    let (start_line, end_line, code) = match code {
+        Some((code, start, end)) => {
+            let start_line = get_line_for(start, code);
+            let end_line = get_line_for(end, code);
+            (
+                Some(start_line),
+                Some(end_line),
+                Some(get_code(code, start_line, end_line)),
+            )
+        }
+        None => (None, None, None),
+    };
+    results.add_vulnerability(Vulnerability::new(
+        criticality,
+        name,
+        description.as_str(),
+        path,
+        start_line,
+        end_line,
+        code,
+    ));
+    print_vulnerability(description, criticality);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base64_decode, proxy_certificate_name};
+
+    /// Checks the base64 decoding of PEM bodies.
+    #[test]
+    fn it_base64_decode() {
+        assert_eq!(base64_decode(b"SGVs\nbG8=").unwrap(), b"Hello");
+        assert_eq!(base64_decode(b"").unwrap(), b"");
+        assert!(base64_decode(b"SGVs*").is_none());
+    }
+
+    /// Checks the detection of proxy root certificates, in DER and PEM formats.
+    #[test]
+    fn it_proxy_certificate_name() {
+        let der = b"0\x82\x01\x0a...Charles Proxy CA (1 Jan 2019)...";
+        assert_eq!(proxy_certificate_name(der), Some("Charles Proxy"));
+
+        // "mitmproxy" encoded in base64.
+        let pem = b"-----BEGIN CERTIFICATE-----\nbWl0bXByb3h5\n-----END CERTIFICATE-----\n";
+        assert_eq!(proxy_certificate_name(pem), Some("mitmproxy"));
+
+        assert_eq!(proxy_certificate_name(b"0\x82\x01\x0aExample CA"), None);
+        assert_eq!(proxy_certificate_name(b"\x89PNG"), None);
+    }
+}
@@ -0,0 +1,422 @@
+//! Backup rules analysis module.
+//!
+//! The `android:fullBackupContent` attribute of the manifest, and the
+//! `android:dataExtractionRules` attribute since Android 12, point to XML resources with the
+//! rules that select the files of the application that get copied to backups and to new devices.
+//! This module parses them and reports the rules that include the shared preferences and the
+//! databases of the application, where most applications keep their tokens and their private
+//! data.
+
+use std::{fs, path::Path};
+
+use xml::{
+    common::Position,
+    reader::{EventReader, XmlEvent},
+};
+
+use crate::{
+    axml::is_binary_xml,
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::{manifest::Manifest, resources::Resources},
+    Config, PARSER_CONFIG,
+};
+
+/// CWE of the backup findings: exposure of backup file to an unauthorized control sphere.
+const BACKUP_CWE: u32 = 530;
+
+/// Folders of the application data that usually contain sensitive information.
+const SENSITIVE_FOLDERS: [&str; 2] = ["shared_prefs", "databases"];
+
+/// Backup that a set of rules applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backup {
+    /// Backups configured by the `<full-backup-content>` element.
+    Full,
+    /// Cloud backups, configured by the `<cloud-backup>` element.
+    Cloud,
+    /// Device to device transfers, configured by the `<device-transfer>` element.
+    DeviceTransfer,
+}
+
+impl Backup {
+    /// Gets the description of the backup.
+    fn description(self) -> &'static str {
+        match self {
+            Backup::Full => "backups",
+            Backup::Cloud => "cloud backups",
+            Backup::DeviceTransfer => "device to device transfers",
+        }
+    }
+
+    /// Gets the criticality of including sensitive data in the backup, explicitly or by
+    /// default.
+    ///
+    /// Device to device transfers copy the data to another device of the same user, so they are
+    /// less exposed than backups.
+    fn criticality(self, explicit: bool) -> Criticality {
+        match (self, explicit) {
+            (Backup::DeviceTransfer, true) | (_, false) => Criticality::Low,
+            (_, true) => Criticality::Medium,
+        }
+    }
+}
+
+/// `<include>` or `<exclude>` rule.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Line of the rule in the rules file.
+    line: usize,
+    /// Domain of the rule, such as `sharedpref` or `database`.
+    domain: String,
+    /// Path of the rule, relative to its domain.
+    path: String,
+}
+
+impl Rule {
+    /// Gets the sensitive folders that the rule matches, and whether it matches all of their
+    /// files.
+    fn sensitive_folders(&self) -> Vec<(&'static str, bool)> {
+        let path = self.path.trim_start_matches("./").trim_matches('/');
+        let whole = path.is_empty() || path == ".";
+        match self.domain.as_str() {
+            "sharedpref" | "device_sharedpref" => vec![("shared_prefs", whole)],
+            "database" | "device_database" => vec![("databases", whole)],
+            "root" | "device_root" if whole => SENSITIVE_FOLDERS
+                .iter()
+                .map(|folder| (*folder, true))
+                .collect(),
+            "root" | "device_root" => SENSITIVE_FOLDERS
+                .iter()
+                .filter_map(|folder| {
+                    if path == *folder {
+                        Some((*folder, true))
+                    } else if path.starts_with(&format!("{}/", folder)) {
+                        Some((*folder, false))
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Rules of a backup.
+#[derive(Debug, Clone, PartialEq)]
+struct BackupRules {
+    /// Backup that the rules apply to.
+    backup: Backup,
+    /// Line of the element in the rules file.
+    line: usize,
+    /// Rules that select the files to back up. If there are none, all the files are included.
+    includes: Vec<Rule>,
+    /// Rules that remove files from the backup.
+    excludes: Vec<Rule>,
+}
+
+impl BackupRules {
+    /// Parses the XML code of a backup rules file, either a `<full-backup-content>` or a
+    /// `<data-extraction-rules>` file.
+    fn parse(code: &str) -> Vec<Self> {
+        let mut rules: Vec<Self> = Vec::new();
+        let mut in_backup = false;
+        let mut reader = EventReader::new_with_config(code.as_bytes(), PARSER_CONFIG.clone());
+        loop {
+            match reader.next() {
+                Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                }) => {
+                    let line = reader.position().row as usize;
+                    let attribute = |key: &str| {
+                        attributes
+                            .iter()
+                            .find(|attr| attr.name.local_name == key)
+                            .map_or_else(String::new, |attr| attr.value.trim().to_owned())
+                    };
+                    let backup = match name.local_name.as_str() {
+                        "full-backup-content" => Some(Backup::Full),
+                        "cloud-backup" => Some(Backup::Cloud),
+                        "device-transfer" => Some(Backup::DeviceTransfer),
+                        _ => None,
+                    };
+                    if let Some(backup) = backup {
+                        rules.push(Self {
+                            backup,
+                            line,
+                            includes: Vec::new(),
+                            excludes: Vec::new(),
+                        });
+                        in_backup = true;
+                        continue;
+                    }
+
+                    let rule = Rule {
+                        line,
+                        domain: attribute("domain"),
+                        path: attribute("path"),
+                    };
+                    match (name.local_name.as_str(), rules.last_mut()) {
+                        ("include", Some(current)) if in_backup => current.includes.push(rule),
+                        ("exclude", Some(current)) if in_backup => current.excludes.push(rule),
+                        _ => {}
+                    }
+                }
+                Ok(XmlEvent::EndElement { name }) => match name.local_name.as_str() {
+                    "full-backup-content" | "cloud-backup" | "device-transfer" => in_backup = false,
+                    _ => {}
+                },
+                Ok(XmlEvent::EndDocument) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        rules
+    }
+
+    /// Gets the sensitive folders that are backed up because there are no `<include>` rules and
+    /// no `<exclude>` rule removes them.
+    fn implicit_folders(&self) -> Vec<&'static str> {
+        if !self.includes.is_empty() {
+            return Vec::new();
+        }
+        SENSITIVE_FOLDERS
+            .iter()
+            .cloned()
+            .filter(|folder| {
+                !self
+                    .excludes
+                    .iter()
+                    .any(|rule| rule.sensitive_folders().contains(&(*folder, true)))
+            })
+            .collect()
+    }
+}
+
+/// Reports the backup rules that include the shared preferences or the databases of the
+/// application.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: &Manifest,
+    results: &mut Results,
+) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let resources = Resources::load(&dist_folder);
+    // The `fullBackupContent` attribute can also be a boolean, without rules.
+    let references = manifest
+        .full_backup_content()
+        .map(|resource| (resource, "full-backup-content"))
+        .into_iter()
+        .chain(
+            manifest
+                .data_extraction_rules()
+                .map(|resource| (resource, "data-extraction-rules")),
+        )
+        .filter(|(resource, _)| resource.starts_with('@'));
+
+    for (resource, root_element) in references {
+        let rules_path = match resources.find_xml(resource, &[root_element]) {
+            Some(path) => path,
+            None => {
+                print_warning(format!(
+                    "the backup rules {} could not be found, so they will not be analyzed",
+                    resource
+                ));
+                continue;
+            }
+        };
+        let code = match fs::read(dist_folder.join(&rules_path)) {
+            Ok(ref code) if !is_binary_xml(code) => String::from_utf8_lossy(code).into_owned(),
+            Ok(_) => {
+                print_warning(format!(
+                    "the backup rules at {} could not be decoded, so they will not be analyzed",
+                    rules_path.display()
+                ));
+                continue;
+            }
+            Err(e) => {
+                print_warning(format!(
+                    "the backup rules at {} could not be read: {}",
+                    rules_path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+
+        for rules in BackupRules::parse(&code) {
+            for rule in &rules.includes {
+                let folders = rule.sensitive_folders();
+                if folders.is_empty() {
+                    continue;
+                }
+                let description = format!(
+                    "The backup rules include {}{} of the application in its {}. \
+                     Applications usually store their session tokens and private data there, so \
+                     anyone with access to the backups can read them. Exclude the sensitive files \
+                     from the {}.",
+                    describe_folders(folders.iter().map(|(folder, _)| *folder)),
+                    if rule.path.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (`{}`)", rule.path)
+                    },
+                    rules.backup.description(),
+                    rules.backup.description()
+                );
+                report(
+                    config,
+                    results,
+                    rules.backup.criticality(true),
+                    description,
+                    (&rules_path, &code, rule.line),
+                );
+            }
+
+            let folders = rules.implicit_folders();
+            if !folders.is_empty() {
+                let description = format!(
+                    "The backup rules don't have any `<include>` rule, so all the files of the \
+                     application are included in its {}, without excluding {}. \
+                     Applications usually store their session tokens and private data there, so \
+                     anyone with access to the backups can read them. Exclude the sensitive files \
+                     with `<exclude>` rules.",
+                    rules.backup.description(),
+                    describe_folders(folders.into_iter()),
+                );
+                report(
+                    config,
+                    results,
+                    rules.backup.criticality(false),
+                    description,
+                    (&rules_path, &code, rules.line),
+                );
+            }
+        }
+    }
+}
+
+/// Describes the given folders, such as "the `databases` folder".
+fn describe_folders<'a, I: Iterator<Item = &'a str>>(folders: I) -> String {
+    let folders = folders.collect::<Vec<_>>();
+    format!(
+        "the `{}` folder{}",
+        folders.join("` and `"),
+        if folders.len() > 1 { "s" } else { "" }
+    )
+}
+
+/// Reports a backup finding in the given line of the rules file.
+fn report(
+    config: &Config,
+    results: &mut Results,
+    criticality: Criticality,
+    description: String,
+    (file, code, line): (&Path, &str, usize),
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let vulnerability = VulnerabilityBuilder::new(
+        criticality,
+        "Sensitive data in backups",
+        description.as_str(),
+    )
+    .file(file)
+    .lines(line, line)
+    .code(get_code(code, line, line))
+    .cwe(BACKUP_CWE)
+    .build();
+    results.add_vulnerability(vulnerability);
+    print_vulnerability(description, criticality);
+}
+
+/// Backup rules analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{Backup, BackupRules, Rule};
+    use crate::criticality::Criticality;
+
+    /// Creates a rule of the given domain and path.
+    fn rule(domain: &str, path: &str) -> Rule {
+        Rule {
+            line: 0,
+            domain: domain.to_owned(),
+            path: path.to_owned(),
+        }
+    }
+
+    /// Checks the sensitive folders matched by the rules.
+    #[test]
+    fn it_sensitive_folders() {
+        assert_eq!(
+            rule("sharedpref", ".").sensitive_folders(),
+            vec![("shared_prefs", true)]
+        );
+        assert_eq!(
+            rule("database", "app.db").sensitive_folders(),
+            vec![("databases", false)]
+        );
+        assert_eq!(
+            rule("root", "").sensitive_folders(),
+            vec![("shared_prefs", true), ("databases", true)]
+        );
+        assert_eq!(
+            rule("root", "shared_prefs/auth.xml").sensitive_folders(),
+            vec![("shared_prefs", false)]
+        );
+        assert!(rule("file", "images/").sensitive_folders().is_empty());
+        assert!(rule("root", "cache").sensitive_folders().is_empty());
+    }
+
+    /// Checks the parsing of full backup content and data extraction rules.
+    #[test]
+    fn it_parse() {
+        let full = BackupRules::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<full-backup-content>
+    <exclude domain="sharedpref" path="." />
+</full-backup-content>"#,
+        );
+        assert_eq!(full.len(), 1);
+        assert_eq!(full[0].backup, Backup::Full);
+        assert_eq!(full[0].line, 1);
+        assert_eq!(full[0].excludes.len(), 1);
+        assert_eq!(full[0].excludes[0].line, 2);
+        assert_eq!(full[0].excludes[0].domain, "sharedpref");
+        assert_eq!(full[0].implicit_folders(), vec!["databases"]);
+
+        let extraction = BackupRules::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<data-extraction-rules>
+    <cloud-backup disableIfNoEncryptionCapabilities="true">
+        <include domain="database" path="notes.db" />
+        <include domain="file" path="notes/" />
+    </cloud-backup>
+    <device-transfer>
+        <exclude domain="root" path="shared_prefs" />
+        <exclude domain="database" path="." />
+    </device-transfer>
+</data-extraction-rules>"#,
+        );
+        assert_eq!(extraction.len(), 2);
+        assert_eq!(extraction[0].backup, Backup::Cloud);
+        assert_eq!(extraction[0].includes.len(), 2);
+        assert_eq!(extraction[0].includes[0].line, 3);
+        assert!(extraction[0].implicit_folders().is_empty());
+        assert_eq!(extraction[1].backup, Backup::DeviceTransfer);
+        assert_eq!(extraction[1].excludes.len(), 2);
+        assert!(extraction[1].implicit_folders().is_empty());
+    }
+
+    /// Checks the criticality of the sensitive data in each backup.
+    #[test]
+    fn it_criticality() {
+        assert_eq!(Backup::Cloud.criticality(true), Criticality::Medium);
+        assert_eq!(Backup::Full.criticality(false), Criticality::Low);
+        assert_eq!(Backup::DeviceTransfer.criticality(true), Criticality::Low);
+    }
+}
@@ -0,0 +1,271 @@
+//! Biometric authentication analysis module.
+//!
+//! Biometric authentication only protects the data of the application if the system enforces it:
+//! the deprecated `FingerprintManager` API lacks the checks of the current biometric prompt, a
+//! `BiometricPrompt` without allowed authenticators accepts the weak biometrics of the device, and
+//! a key that does not require user authentication can be used without any biometric check at
+//! all. This module searches the decompiled code for these misuses, and groups the findings in
+//! the *Authentication* section of the report.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{arguments, enclosing_method, mask, methods},
+    logging::sensitive_identifier,
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+/// Report section of the biometric authentication findings.
+const AUTHENTICATION_SECTION: &str = "Authentication";
+
+lazy_static! {
+    /// Uses of the deprecated fingerprint API.
+    static ref FINGERPRINT_MANAGER: Regex =
+        Regex::new(r"\bFingerprintManager(?:Compat)?\b").unwrap();
+    /// Builders of the biometric prompt, of the platform or of the support library.
+    static ref PROMPT_BUILDER: Regex =
+        Regex::new(r"\bnew\s+BiometricPrompt\s*\.\s*(?:PromptInfo\s*\.\s*)?Builder\s*\(").unwrap();
+    /// Restrictions of the authenticators accepted by the biometric prompt.
+    static ref ALLOWED_AUTHENTICATORS: Regex =
+        Regex::new(r"\.\s*setAllowedAuthenticators\s*\(").unwrap();
+    /// Builders of the parameters of the keys of the Android key store.
+    static ref KEY_SPEC_BUILDER: Regex =
+        Regex::new(r"\bnew\s+KeyGenParameterSpec\s*\.\s*Builder\s*\(").unwrap();
+    /// Requirement of user authentication to use a key.
+    static ref USER_AUTHENTICATION: Regex =
+        Regex::new(r"\.\s*setUserAuthenticationRequired\s*\(\s*true\s*\)").unwrap();
+    /// Biometric authentication bound to a cryptographic operation.
+    static ref CRYPTO_OBJECT: Regex = Regex::new(r"\bCryptoObject\b").unwrap();
+}
+
+/// Biometric authentication issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// Use of the deprecated `FingerprintManager` API.
+    DeprecatedFingerprint,
+    /// Biometric prompt that does not restrict the allowed authenticators.
+    UnrestrictedPrompt,
+    /// Key guarding sensitive data that can be used without user authentication.
+    UnauthenticatedKey,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::DeprecatedFingerprint => "biometrics-deprecated-fingerprint",
+            Issue::UnrestrictedPrompt => "biometrics-unrestricted-prompt",
+            Issue::UnauthenticatedKey => "biometrics-unauthenticated-key",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::DeprecatedFingerprint => "Deprecated fingerprint API",
+            Issue::UnrestrictedPrompt => "Unrestricted biometric prompt",
+            Issue::UnauthenticatedKey => "Key without user authentication",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Use of obsolete function.
+            Issue::DeprecatedFingerprint => 477,
+            // Improper authentication.
+            Issue::UnrestrictedPrompt => 287,
+            // Missing authentication for critical function.
+            Issue::UnauthenticatedKey => 306,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        "MASVS-AUTH-2"
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            Issue::DeprecatedFingerprint => Confidence::High,
+            // The builder could be configured in another method.
+            Issue::UnrestrictedPrompt | Issue::UnauthenticatedKey => Confidence::Medium,
+        }
+    }
+}
+
+impl Issue {
+    /// Gets the criticality of the findings of the issue.
+    fn criticality(self) -> Criticality {
+        match self {
+            Issue::DeprecatedFingerprint => Criticality::Low,
+            Issue::UnrestrictedPrompt | Issue::UnauthenticatedKey => Criticality::Medium,
+        }
+    }
+
+    /// Gets the description of the findings of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            Issue::DeprecatedFingerprint => {
+                "The application uses the `FingerprintManager` API, deprecated since Android 9. \
+                 It only supports fingerprints, does not enforce the security class of the \
+                 sensor and lets the application draw its own authentication dialog. Use \
+                 `BiometricPrompt` instead."
+            }
+            Issue::UnrestrictedPrompt => {
+                "The application shows a biometric prompt without calling \
+                 `setAllowedAuthenticators()`, so it relies on the default authenticators, that \
+                 can change between Android versions and devices. Restrict the prompt to \
+                 `BIOMETRIC_STRONG` authenticators, optionally with `DEVICE_CREDENTIAL`, when it \
+                 protects sensitive data or operations."
+            }
+            Issue::UnauthenticatedKey => {
+                "The application creates a key of the Android key store that seems to guard \
+                 sensitive data without calling `setUserAuthenticationRequired(true)`, so the key \
+                 can be used without any biometric or device credential check, even if the \
+                 application shows a biometric prompt. Require user authentication for the key, \
+                 and use it in the `CryptoObject` of the prompt."
+            }
+        }
+    }
+}
+
+/// Scanner of the biometric authentication misuses.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BiometricsScanner;
+
+impl Scanner for BiometricsScanner {
+    fn check(&self) -> Check {
+        Check::Biometrics
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn scan(&self, config: &Config, code: &str) -> Vec<Finding> {
+        find_issues(code, config.sensitive_identifiers())
+            .into_iter()
+            .map(|(issue, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(&issue, issue.criticality(), issue.description(), line, line)
+                    .section(AUTHENTICATION_SECTION)
+            })
+            .collect()
+    }
+}
+
+/// Finds the biometric authentication misuses in the given code, with their index in it.
+///
+/// The deprecated fingerprint API is only reported once per file, at its first use outside of
+/// the imports.
+fn find_issues<S: AsRef<str>>(code: &str, sensitive_identifiers: &[S]) -> Vec<(Issue, usize)> {
+    let masked = mask(code);
+    let methods = methods(&masked);
+    let mut issues = Vec::new();
+
+    if let Some(usage) = FINGERPRINT_MANAGER.find_iter(&masked).find(|usage| {
+        let line_start = masked[..usage.start()].rfind('\n').map_or(0, |i| i + 1);
+        !masked[line_start..].trim_start().starts_with("import ")
+    }) {
+        issues.push((Issue::DeprecatedFingerprint, usage.start()));
+    }
+
+    // The builders are usually configured in the same method where they are created.
+    let configured_in_method = |regex: &Regex, start: usize| {
+        let end =
+            enclosing_method(&methods, start).map_or(masked.len(), |method| method.body().end);
+        regex.is_match(&masked[start..end])
+    };
+
+    issues.extend(
+        PROMPT_BUILDER
+            .find_iter(&masked)
+            .filter(|builder| !configured_in_method(&ALLOWED_AUTHENTICATORS, builder.start()))
+            .map(|builder| (Issue::UnrestrictedPrompt, builder.start())),
+    );
+
+    let biometric_keys = CRYPTO_OBJECT.is_match(&masked);
+    for builder in KEY_SPEC_BUILDER.find_iter(&masked) {
+        if configured_in_method(&USER_AUTHENTICATION, builder.start()) {
+            continue;
+        }
+        // The alias is usually a literal, so it's checked in the original code.
+        let sensitive_alias = arguments(&masked, builder.end())
+            .into_iter()
+            .next()
+            .and_then(|alias| sensitive_identifier(&code[alias], sensitive_identifiers))
+            .is_some();
+        let sensitive_method = enclosing_method(&methods, builder.start())
+            .and_then(|method| sensitive_identifier(method.name(), sensitive_identifiers))
+            .is_some();
+        if biometric_keys || sensitive_alias || sensitive_method {
+            issues.push((Issue::UnauthenticatedKey, builder.start()));
+        }
+    }
+
+    issues.sort_by_key(|&(_, index)| index);
+    issues
+}
+
+/// Biometric authentication analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_issues, get_line_for, Issue};
+
+    /// Sensitive identifiers used in the tests.
+    const SENSITIVE_IDENTIFIERS: [&str; 2] = ["password", "token"];
+
+    /// Checks the issues found in the code, in order.
+    #[test]
+    fn it_find_issues() {
+        let code = "import android.hardware.fingerprint.FingerprintManager;\n\
+                    public void authenticate() {\n\
+                    FingerprintManager manager = getSystemService(FingerprintManager.class);\n\
+                    BiometricPrompt.PromptInfo info = new BiometricPrompt.PromptInfo.Builder()\n\
+                    .setTitle(\"Log in\").build();\n\
+                    }\n\
+                    private void createTokenKey() {\n\
+                    generator.init(new KeyGenParameterSpec.Builder(ALIAS, 3).build());\n\
+                    }\n\
+                    private void createKey() {\n\
+                    generator.init(new KeyGenParameterSpec.Builder(\"password_key\", 3).build());\n\
+                    }";
+
+        let issues = find_issues(code, &SENSITIVE_IDENTIFIERS)
+            .into_iter()
+            .map(|(issue, index)| (issue, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Issue::DeprecatedFingerprint, 2),
+                (Issue::UnrestrictedPrompt, 3),
+                (Issue::UnauthenticatedKey, 7),
+                (Issue::UnauthenticatedKey, 10),
+            ]
+        );
+    }
+
+    /// Checks that the restricted prompts and the authenticated or non-sensitive keys are not
+    /// reported.
+    #[test]
+    fn it_secure_authentication() {
+        let code = "public void authenticate() {\n\
+                    BiometricPrompt.PromptInfo info = new BiometricPrompt.PromptInfo.Builder()\n\
+                    .setTitle(\"Log in\")\n\
+                    .setAllowedAuthenticators(BiometricManager.Authenticators.BIOMETRIC_STRONG)\n\
+                    .build();\n\
+                    }\n\
+                    private void createTokenKey() {\n\
+                    generator.init(new KeyGenParameterSpec.Builder(ALIAS, 3)\n\
+                    .setUserAuthenticationRequired(true).build());\n\
+                    }\n\
+                    private void createCacheKey() {\n\
+                    generator.init(new KeyGenParameterSpec.Builder(\"cache\", 3).build());\n\
+                    }\n\
+                    // FingerprintManager is deprecated.";
+
+        assert!(find_issues(code, &SENSITIVE_IDENTIFIERS).is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+//! Sensitive broadcast receiver analysis module.
+//!
+//! Receivers registered for broadcasts such as incoming SMS messages, outgoing calls or the end of
+//! the boot process get sensitive data, or run every time the device starts, so they are only
+//! justified for some applications. The broadcasts are configured in the `[[broadcasts]]` tables
+//! of the `config.toml` file, with the permission that the application needs to receive them,
+//! that is checked against the permissions that it requests, and the permission that the
+//! receivers should require from the sender, so that other applications can't spoof them.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, Component, ComponentKind, Manifest},
+    Config,
+};
+
+/// CWE of the receivers of sensitive broadcasts: execution with unnecessary privileges.
+const SENSITIVE_RECEIVER_CWE: u32 = 250;
+
+/// CWE of the receivers that don't require the permission of the sender: improper verification
+/// of intent by broadcast receiver.
+const SPOOFABLE_RECEIVER_CWE: u32 = 925;
+
+/// Sensitive broadcast configuration information.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SensitiveBroadcast {
+    /// Action of the broadcast.
+    action: String,
+    /// Permission that the application needs to receive the broadcast, if any.
+    #[serde(default)]
+    permission: Option<String>,
+    /// Permission that the receivers should require from the sender of the broadcast, if any.
+    #[serde(default)]
+    sender_permission: Option<String>,
+    /// Criticality of the receivers of the broadcast.
+    criticality: Criticality,
+    /// Label of the receivers of the broadcast.
+    label: String,
+    /// Description of the receivers of the broadcast.
+    description: String,
+}
+
+impl SensitiveBroadcast {
+    /// Returns the `action` of the broadcast.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Returns the `permission` needed to receive the broadcast, if any.
+    pub fn permission(&self) -> Option<&str> {
+        self.permission.as_ref().map(String::as_str)
+    }
+
+    /// Returns the `sender_permission` that the receivers should require, if any.
+    pub fn sender_permission(&self) -> Option<&str> {
+        self.sender_permission.as_ref().map(String::as_str)
+    }
+
+    /// Returns the broadcast's `criticality`.
+    pub fn criticality(&self) -> Criticality {
+        self.criticality
+    }
+
+    /// Returns the broadcast's `label`.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the broadcast's `description`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Checks if an application that requests the given permissions gets the broadcast.
+    fn is_received(&self, requested_permissions: &[String]) -> bool {
+        self.permission().map_or(true, |permission| {
+            requested_permissions
+                .iter()
+                .any(|requested| requested == permission)
+        })
+    }
+
+    /// Checks if other applications can send the broadcast to the given receiver.
+    fn is_spoofable(&self, receiver: &Component, min_sdk: u32) -> bool {
+        receiver.is_exported(min_sdk)
+            && self.sender_permission().map_or(false, |permission| {
+                receiver.permission() != Some(permission)
+            })
+    }
+}
+
+/// Reports the receivers registered for the configured sensitive broadcasts.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    for receiver in manifest.components_of(ComponentKind::Receiver) {
+        let actions = receiver
+            .intent_filters()
+            .iter()
+            .flat_map(|filter| filter.actions())
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+
+        for broadcast in config
+            .broadcasts()
+            .iter()
+            .filter(|broadcast| actions.contains(broadcast.action()))
+        {
+            // Receivers of broadcasts that need a permission that the application doesn't
+            // request are never called.
+            if !broadcast.is_received(manifest.requested_permissions()) {
+                continue;
+            }
+
+            let spoofable = broadcast.is_spoofable(receiver, manifest.min_sdk());
+            let criticality = if spoofable {
+                broadcast.criticality().higher()
+            } else {
+                broadcast.criticality()
+            };
+            if criticality < config.min_criticality() {
+                continue;
+            }
+
+            let mut description = format!(
+                "The `{}` receiver listens to the `{}` broadcast",
+                receiver.name(),
+                broadcast.action()
+            );
+            if let Some(permission) = broadcast.permission() {
+                description.push_str(&format!(
+                    ", and the application requests the `{}` permission to receive it",
+                    permission
+                ));
+            }
+            description.push_str(&format!(". {}", broadcast.description()));
+            if spoofable {
+                let sender_permission = broadcast.sender_permission().unwrap_or_default();
+                description.push_str(&format!(
+                    " The receiver is exported and doesn't require the `{}` permission from the \
+                     sender, so other applications can send it fake broadcasts. Set \
+                     `android:permission=\"{}\"` in the receiver.",
+                    sender_permission, sender_permission
+                ));
+            }
+
+            let cwe = if spoofable {
+                SPOOFABLE_RECEIVER_CWE
+            } else {
+                SENSITIVE_RECEIVER_CWE
+            };
+            let mut vulnerability =
+                VulnerabilityBuilder::new(criticality, broadcast.label(), description.as_str())
+                    .file("AndroidManifest.xml")
+                    .cwe(cwe);
+            if let Ok(line) = get_line(
+                manifest.code(),
+                &format!("android:name=\"{}\"", receiver.name()),
+            ) {
+                let code = get_code(manifest.code(), line, line);
+                vulnerability = vulnerability.lines(line, line).code(code);
+            }
+            results.add_vulnerability(vulnerability.build());
+            print_vulnerability(description, criticality);
+        }
+    }
+}
+
+/// Sensitive broadcast receiver analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::SensitiveBroadcast;
+    use crate::criticality::Criticality;
+
+    /// Checks that the broadcasts are only received with their permission.
+    #[test]
+    fn it_is_received() {
+        let sms = SensitiveBroadcast {
+            action: "android.provider.Telephony.SMS_RECEIVED".to_owned(),
+            permission: Some("android.permission.RECEIVE_SMS".to_owned()),
+            sender_permission: Some("android.permission.BROADCAST_SMS".to_owned()),
+            criticality: Criticality::Medium,
+            label: "SMS receiver".to_owned(),
+            description: "Description".to_owned(),
+        };
+        let device_admin = SensitiveBroadcast {
+            action: "android.app.action.DEVICE_ADMIN_ENABLED".to_owned(),
+            permission: None,
+            sender_permission: Some("android.permission.BIND_DEVICE_ADMIN".to_owned()),
+            criticality: Criticality::Medium,
+            label: "Device administrator receiver".to_owned(),
+            description: "Description".to_owned(),
+        };
+
+        let requested = vec![
+            "android.permission.INTERNET".to_owned(),
+            "android.permission.RECEIVE_SMS".to_owned(),
+        ];
+        assert!(sms.is_received(&requested));
+        assert!(!sms.is_received(&requested[..1]));
+        assert!(device_admin.is_received(&[]));
+    }
+}
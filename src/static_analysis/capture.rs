@@ -0,0 +1,385 @@
+//! Camera and microphone capture analysis module.
+//!
+//! Spyware records the camera and the microphone from components that the user never sees:
+//! broadcast receivers woken up by the system, and services that run without the ongoing
+//! notification of a foreground service. This module searches the code of the services and the
+//! receivers declared in the manifest for the capture APIs, and checks that the application
+//! explains why it needs the camera and microphone permissions before requesting them. All the
+//! findings are grouped in the *Privacy* section of the report.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::code::get_line_for;
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{ComponentKind, Manifest},
+    utils::add_java_files,
+    Config,
+};
+
+/// CWE of the capture findings: exposure of private personal information to an unauthorized
+/// actor.
+const CAPTURE_CWE: u32 = 359;
+/// Report section of the capture findings.
+pub(crate) const PRIVACY_SECTION: &str = "Privacy";
+
+/// Capture device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Device {
+    /// Camera.
+    Camera,
+    /// Microphone.
+    Microphone,
+}
+
+impl Device {
+    /// Gets the name of the device, that is also its foreground service type.
+    fn name(self) -> &'static str {
+        match self {
+            Device::Camera => "camera",
+            Device::Microphone => "microphone",
+        }
+    }
+
+    /// Gets the permission needed to use the device.
+    fn permission(self) -> &'static str {
+        match self {
+            Device::Camera => "android.permission.CAMERA",
+            Device::Microphone => "android.permission.RECORD_AUDIO",
+        }
+    }
+}
+
+lazy_static! {
+    /// Calls that capture from the camera or the microphone, with their device.
+    static ref CAPTURE_CALLS: [(Device, Regex); 6] = [
+        (Device::Camera, Regex::new(r"\bCamera\s*\.\s*open\s*\(").unwrap()),
+        (Device::Camera, Regex::new(r"\.\s*openCamera\s*\(").unwrap()),
+        (Device::Camera, Regex::new(r"\.\s*setVideoSource\s*\(").unwrap()),
+        (Device::Microphone, Regex::new(r"\.\s*setAudioSource\s*\(").unwrap()),
+        (Device::Microphone, Regex::new(r"\bnew\s+AudioRecord\s*\(").unwrap()),
+        (Device::Microphone, Regex::new(r"\bAudioRecord\s*\.\s*Builder\b").unwrap()),
+    ];
+    /// Calls that request permissions at runtime.
+    static ref PERMISSION_REQUEST: Regex =
+        Regex::new(r"\brequestPermissions\s*\(|\bRequestPermission\b").unwrap();
+}
+
+/// Searches the services and the receivers of the application for background camera and
+/// microphone capture, and the code of the application for permission requests without a
+/// rationale.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: &Manifest,
+    results: &mut Results,
+) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+
+    for component in manifest.components().iter().filter(|component| {
+        component.kind() == ComponentKind::Service || component.kind() == ComponentKind::Receiver
+    }) {
+        let path = Path::new("classes").join(format!(
+            "{}.java",
+            component.class_name(manifest.package()).replace('.', "/")
+        ));
+        let code = match fs::read_to_string(dist_folder.join(&path)) {
+            Ok(code) => code,
+            // The class can be missing if it could not be decompiled, or if it's obfuscated.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                print_warning(format!(
+                    "could not read {} searching for background capture: {}",
+                    path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+
+        let starts_foreground = code.contains("startForeground(");
+        for (device, index) in capture_calls(&code) {
+            if let Some((criticality, name, description)) = check_component(
+                component.kind(),
+                component.foreground_service_types(),
+                starts_foreground,
+                device,
+            ) {
+                let description = format!("{} Component: `{}`.", description, component.name());
+                let line = get_line_for(index, &code);
+                report(
+                    config,
+                    results,
+                    (criticality, name, description),
+                    &path,
+                    Some((&code, line)),
+                );
+            }
+        }
+    }
+
+    check_rationale(config, &dist_folder, manifest, results);
+}
+
+/// Gets the capture devices used in the given code, with the index of their first call.
+fn capture_calls(code: &str) -> BTreeMap<Device, usize> {
+    let mut calls = BTreeMap::new();
+    for (device, regex) in CAPTURE_CALLS.iter() {
+        if let Some(call) = regex.find(code) {
+            let index = calls.entry(*device).or_insert_with(|| call.start());
+            if call.start() < *index {
+                *index = call.start();
+            }
+        }
+    }
+    calls
+}
+
+/// Checks the use of a capture device from a component, and returns the criticality, the name
+/// and the description of the finding, if any.
+fn check_component(
+    kind: ComponentKind,
+    foreground_service_types: &[String],
+    starts_foreground: bool,
+    device: Device,
+) -> Option<(Criticality, &'static str, String)> {
+    match kind {
+        ComponentKind::Receiver => Some((
+            Criticality::High,
+            "Capture from a broadcast receiver",
+            format!(
+                "A broadcast receiver uses the {0}. Receivers run in the background, without any \
+                 visible interface, when the system or other applications send them a broadcast, \
+                 so the user can't know that the {0} is being recorded. Capture should only be \
+                 started from a visible activity, or from a foreground service with the `{0}` \
+                 type.",
+                device.name()
+            ),
+        )),
+        ComponentKind::Service if !starts_foreground => Some((
+            Criticality::High,
+            "Background capture",
+            format!(
+                "A service uses the {0}, but it never calls `startForeground()`, so it records \
+                 without the ongoing notification of a foreground service, and the user can't \
+                 know that the {0} is being recorded. Run the capture in a foreground service \
+                 with the `{0}` type, or from a visible activity.",
+                device.name()
+            ),
+        )),
+        ComponentKind::Service
+            if !foreground_service_types
+                .iter()
+                .any(|service_type| service_type == device.name()) =>
+        {
+            Some((
+                Criticality::Medium,
+                "Missing foreground service type",
+                format!(
+                    "A foreground service uses the {0}, but it does not declare the `{0}` \
+                     foreground service type in `android:foregroundServiceType`. Since Android \
+                     11 the service can't access the {0} while the application is in the \
+                     background without the type, and since Android 14 the type must be \
+                     declared, so the capture is either broken or done while the user is using \
+                     the application without being told.",
+                    device.name()
+                ),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Reports the camera and microphone permissions that the application requests at runtime
+/// without ever showing a rationale for them.
+fn check_rationale(
+    config: &Config,
+    dist_folder: &Path,
+    manifest: &Manifest,
+    results: &mut Results,
+) {
+    // Libraries, such as the support library, implement the rationale checks themselves, so only
+    // the code of the application is searched.
+    let app_folder = Path::new("classes").join(manifest.package().replace('.', "/"));
+    let mut files = Vec::new();
+    if let Err(e) = add_java_files(&dist_folder.join(&app_folder), &mut files) {
+        print_warning(format!(
+            "there was an error searching the code for permission requests: {}",
+            e
+        ));
+    }
+
+    let mut requests: BTreeMap<Device, (PathBuf, String, usize)> = BTreeMap::new();
+    for file in files {
+        let code = match fs::read_to_string(&file) {
+            Ok(code) => code,
+            Err(e) => {
+                print_warning(format!(
+                    "could not read {} searching for permission requests: {}",
+                    file.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        if code.contains("shouldShowRequestPermissionRationale") {
+            return;
+        }
+
+        let path = file
+            .strip_prefix(dist_folder)
+            .unwrap_or(&file)
+            .to_path_buf();
+        for (device, index) in requested_devices(&code) {
+            if !requests.contains_key(&device) {
+                let line = get_line_for(index, &code);
+                let _ = requests.insert(device, (path.clone(), code.clone(), line));
+            }
+        }
+    }
+
+    for (device, (path, code, line)) in requests {
+        if !manifest
+            .requested_permissions()
+            .iter()
+            .any(|permission| permission == device.permission())
+        {
+            continue;
+        }
+        let description = format!(
+            "The application requests the `{}` permission at runtime, but it never calls \
+             `shouldShowRequestPermissionRationale()`, so it does not explain why it needs the \
+             {} before the system asks the user. Users should know why an application wants to \
+             record them, and should be able to keep using it if they deny the permission.",
+            device.permission(),
+            device.name()
+        );
+        report(
+            config,
+            results,
+            (
+                Criticality::Low,
+                "Missing permission rationale",
+                description,
+            ),
+            &path,
+            Some((&code, line)),
+        );
+    }
+}
+
+/// Gets the capture devices whose permission is requested in the given code, with the index of
+/// the request.
+fn requested_devices(code: &str) -> Vec<(Device, usize)> {
+    let request = match PERMISSION_REQUEST.find(code) {
+        Some(request) => request,
+        None => return Vec::new(),
+    };
+    [Device::Camera, Device::Microphone]
+        .iter()
+        .filter(|device| code.contains(&format!("\"{}\"", device.permission())))
+        .map(|device| (*device, request.start()))
+        .collect()
+}
+
+/// Reports a capture finding in the privacy section, pointing to the given line of the code.
+fn report(
+    config: &Config,
+    results: &mut Results,
+    (criticality, name, description): (Criticality, &str, String),
+    path: &Path,
+    code: Option<(&str, usize)>,
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let mut vulnerability = VulnerabilityBuilder::new(criticality, name, description.as_str())
+        .file(path)
+        .cwe(CAPTURE_CWE)
+        .section(PRIVACY_SECTION);
+    if let Some((code, line)) = code {
+        let code = get_code(code, line, line);
+        vulnerability = vulnerability.lines(line, line).code(code);
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Camera and microphone capture analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{capture_calls, check_component, requested_devices, Device};
+    use crate::{criticality::Criticality, static_analysis::manifest::ComponentKind};
+
+    /// Checks the detection of the camera and microphone capture calls.
+    #[test]
+    fn it_capture_calls() {
+        let code = "MediaRecorder recorder = new MediaRecorder();\n\
+                    recorder.setAudioSource(1);\n\
+                    recorder.setVideoSource(2);\n\
+                    this.camera = Camera.open(0);\n";
+        let calls = capture_calls(code);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[&Device::Microphone],
+            code.find(".setAudioSource").unwrap()
+        );
+        assert_eq!(
+            calls[&Device::Camera],
+            code.find(".setVideoSource").unwrap()
+        );
+
+        assert!(capture_calls("new AudioRecord(1, 44100, 16, 2, size);")
+            .contains_key(&Device::Microphone));
+        assert!(capture_calls("manager.openCamera(id, callback, handler);")
+            .contains_key(&Device::Camera));
+        assert!(capture_calls("MediaPlayer player = new MediaPlayer();").is_empty());
+    }
+
+    /// Checks the findings of the capture devices used from services and receivers.
+    #[test]
+    fn it_check_component() {
+        let types = vec!["microphone".to_owned()];
+        let name = |kind, starts_foreground, device| {
+            check_component(kind, &types, starts_foreground, device).map(|(c, name, _)| (c, name))
+        };
+
+        assert_eq!(
+            name(ComponentKind::Receiver, false, Device::Camera),
+            Some((Criticality::High, "Capture from a broadcast receiver"))
+        );
+        assert_eq!(
+            name(ComponentKind::Service, false, Device::Microphone),
+            Some((Criticality::High, "Background capture"))
+        );
+        assert_eq!(
+            name(ComponentKind::Service, true, Device::Camera),
+            Some((Criticality::Medium, "Missing foreground service type"))
+        );
+        assert_eq!(name(ComponentKind::Service, true, Device::Microphone), None);
+        assert_eq!(name(ComponentKind::Activity, false, Device::Camera), None);
+    }
+
+    /// Checks the detection of the runtime requests of the capture permissions.
+    #[test]
+    fn it_requested_devices() {
+        let code = "String[] permissions = {\"android.permission.RECORD_AUDIO\"};\n\
+                    ActivityCompat.requestPermissions(this, permissions, 1);\n";
+        assert_eq!(
+            requested_devices(code),
+            vec![(Device::Microphone, code.find("requestPermissions").unwrap())]
+        );
+        assert!(requested_devices("String p = \"android.permission.CAMERA\";").is_empty());
+        assert!(requested_devices("requestPermissions(new String[] {p}, 1);").is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+//! Application category module.
+//!
+//! Infers the category of the application from its permissions, the SDKs it bundles and its
+//! native libraries, so that the rules that matter the most for that kind of applications can be
+//! weighted accordingly. For example, weak cryptography is much more serious in a banking
+//! application than in a game.
+
+use std::{fs, path::Path};
+
+use crate::static_analysis::manifest::Manifest;
+
+/// Minimum score for a category to be assigned to an application.
+const MIN_SCORE: u32 = 3;
+
+/// Evidence of an application category, with its score.
+#[derive(Debug, Clone, Copy)]
+enum Evidence {
+    /// A requested permission.
+    Permission(&'static str),
+    /// A word in the package name or the label of the application.
+    Keyword(&'static str),
+    /// A folder in the decompiled code, from a bundled SDK.
+    CodeFolder(&'static str),
+    /// A native library, by the start of its file name.
+    NativeLibrary(&'static str),
+}
+
+/// Evidence of banking and payment applications.
+const BANKING_EVIDENCE: [(Evidence, u32); 14] = [
+    (Evidence::Keyword("bank"), 3),
+    (Evidence::Keyword("banking"), 3),
+    (Evidence::Keyword("wallet"), 2),
+    (Evidence::Keyword("pay"), 1),
+    (Evidence::Keyword("finance"), 2),
+    (
+        Evidence::Permission("android.permission.USE_FINGERPRINT"),
+        1,
+    ),
+    (Evidence::Permission("android.permission.USE_BIOMETRIC"), 1),
+    (Evidence::Permission("android.permission.NFC"), 1),
+    (Evidence::CodeFolder("io/card"), 2),
+    (Evidence::CodeFolder("com/google/android/gms/wallet"), 2),
+    (Evidence::CodeFolder("com/stripe"), 2),
+    (Evidence::CodeFolder("com/paypal"), 2),
+    (Evidence::CodeFolder("com/braintreepayments"), 2),
+    (Evidence::CodeFolder("com/adyen"), 2),
+];
+
+/// Evidence of games.
+const GAME_EVIDENCE: [(Evidence, u32); 9] = [
+    (Evidence::Keyword("game"), 2),
+    (Evidence::Keyword("games"), 2),
+    (Evidence::CodeFolder("com/unity3d/player"), 3),
+    (Evidence::CodeFolder("org/cocos2dx"), 3),
+    (Evidence::CodeFolder("com/badlogic/gdx"), 3),
+    (Evidence::CodeFolder("com/google/android/gms/games"), 2),
+    (Evidence::NativeLibrary("libunity"), 3),
+    (Evidence::NativeLibrary("libcocos2d"), 3),
+    (Evidence::NativeLibrary("libUE4"), 3),
+];
+
+/// Evidence of companion applications of IoT devices.
+const IOT_COMPANION_EVIDENCE: [(Evidence, u32); 11] = [
+    (Evidence::Keyword("smarthome"), 2),
+    (Evidence::Keyword("iot"), 2),
+    (Evidence::Permission("android.permission.BLUETOOTH"), 1),
+    (
+        Evidence::Permission("android.permission.BLUETOOTH_ADMIN"),
+        1,
+    ),
+    (
+        Evidence::Permission("android.permission.CHANGE_WIFI_STATE"),
+        1,
+    ),
+    (
+        Evidence::Permission("android.permission.CHANGE_WIFI_MULTICAST_STATE"),
+        1,
+    ),
+    (Evidence::CodeFolder("com/espressif"), 3),
+    (Evidence::CodeFolder("com/tuya"), 3),
+    (Evidence::CodeFolder("org/eclipse/paho"), 2),
+    (
+        Evidence::CodeFolder("com/amazonaws/mobileconnectors/iot"),
+        3,
+    ),
+    (Evidence::CodeFolder("com/polidea/rxandroidble"), 2),
+];
+
+/// Category of an application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    /// Banking and payment applications.
+    Banking,
+    /// Games.
+    Game,
+    /// Companion applications of IoT devices.
+    IotCompanion,
+}
+
+impl Category {
+    /// Gets all the categories.
+    pub fn all() -> [Self; 3] {
+        [Category::Banking, Category::Game, Category::IotCompanion]
+    }
+
+    /// Gets the name of the category, as used in the configuration and the reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Banking => "banking",
+            Category::Game => "game",
+            Category::IotCompanion => "iot_companion",
+        }
+    }
+
+    /// Gets the category with the given name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .cloned()
+            .find(|category| category.name() == name)
+    }
+
+    /// Gets the evidence of the category, with its score.
+    fn evidence(self) -> &'static [(Evidence, u32)] {
+        match self {
+            Category::Banking => &BANKING_EVIDENCE,
+            Category::Game => &GAME_EVIDENCE,
+            Category::IotCompanion => &IOT_COMPANION_EVIDENCE,
+        }
+    }
+
+    /// Infers the category of the application decompressed in the given folder.
+    ///
+    /// Returns `None` if there is not enough evidence of any category.
+    pub fn infer<P: AsRef<Path>>(manifest: &Manifest, dist_folder: P) -> Option<Self> {
+        let dist_folder = dist_folder.as_ref();
+        let mut native_libraries = Vec::new();
+        if let Ok(abis) = fs::read_dir(dist_folder.join("lib")) {
+            for abi in abis.filter_map(Result::ok) {
+                if let Ok(libraries) = fs::read_dir(abi.path()) {
+                    native_libraries.extend(
+                        libraries
+                            .filter_map(Result::ok)
+                            .map(|library| library.file_name().to_string_lossy().into_owned()),
+                    );
+                }
+            }
+        }
+
+        let words = words(&format!("{} {}", manifest.package(), manifest.label()));
+        Self::from_evidence(|evidence| match evidence {
+            Evidence::Permission(permission) => manifest
+                .requested_permissions()
+                .iter()
+                .any(|requested| requested == permission),
+            Evidence::Keyword(keyword) => words.iter().any(|word| word == keyword),
+            Evidence::CodeFolder(folder) => dist_folder.join("classes").join(folder).is_dir(),
+            Evidence::NativeLibrary(prefix) => native_libraries
+                .iter()
+                .any(|library| library.starts_with(prefix)),
+        })
+    }
+
+    /// Gets the category with the highest score, from the evidence found in the application.
+    fn from_evidence<F>(found: F) -> Option<Self>
+    where
+        F: Fn(Evidence) -> bool,
+    {
+        Self::all()
+            .iter()
+            .map(|category| {
+                let score: u32 = category
+                    .evidence()
+                    .iter()
+                    .filter(|(evidence, _)| found(*evidence))
+                    .map(|(_, score)| score)
+                    .sum();
+                (score, *category)
+            })
+            .filter(|(score, _)| *score >= MIN_SCORE)
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, category)| category)
+    }
+}
+
+/// Splits the given text in lowercase words, so that keywords are not matched inside other words.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Application category testing module.
+#[cfg(test)]
+mod test {
+    use super::{words, Category, Evidence};
+
+    /// Checks the conversion between categories and their names.
+    #[test]
+    fn it_name() {
+        for category in &Category::all() {
+            assert_eq!(Category::from_name(category.name()), Some(*category));
+        }
+        assert_eq!(Category::from_name("unknown"), None);
+    }
+
+    /// Checks the splitting of package names and labels in words.
+    #[test]
+    fn it_words() {
+        assert_eq!(
+            words("com.example.bank Example Bank"),
+            vec!["com", "example", "bank", "example", "bank"]
+        );
+    }
+
+    /// Checks the inference of the category from the evidence found.
+    #[test]
+    fn it_from_evidence() {
+        let banking = Category::from_evidence(|evidence| match evidence {
+            Evidence::Keyword("bank") | Evidence::CodeFolder("io/card") => true,
+            _ => false,
+        });
+        assert_eq!(banking, Some(Category::Banking));
+
+        let game = Category::from_evidence(|evidence| match evidence {
+            Evidence::NativeLibrary("libunity") | Evidence::Keyword("pay") => true,
+            _ => false,
+        });
+        assert_eq!(game, Some(Category::Game));
+
+        let weak = Category::from_evidence(|evidence| match evidence {
+            Evidence::Permission("android.permission.BLUETOOTH") => true,
+            _ => false,
+        });
+        assert_eq!(weak, None);
+    }
+}
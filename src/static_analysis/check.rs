@@ -0,0 +1,280 @@
+//! Analysis checks module.
+//!
+//! Each stage of the static analysis is a check with its own name, so that the checks that are
+//! not needed can be skipped, with the `--enable` and `--disable` options or the
+//! `enabled_checks` and `disabled_checks` keys of the configuration. This saves time when
+//! scanning thousands of applications for a few kinds of issues, since the code is only
+//! decompiled if a check that reads it is enabled.
+
+/// Stage of the static analysis, that can be enabled or disabled by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// Manifest analysis, needed by every check that reads the manifest.
+    Manifest,
+    /// Manifest hardening settings.
+    Hardening,
+    /// Task hijacking of the activities.
+    TaskHijacking,
+    /// Sensitive broadcasts.
+    Broadcasts,
+    /// Minimum and target SDK versions allowed by the organization.
+    SdkPolicy,
+    /// Signing certificate.
+    Certificate,
+    /// Signers tracked across the analyzed versions.
+    Signers,
+    /// Debug artifacts left in the application.
+    Artifacts,
+    /// Keystores and private keys bundled in the application.
+    Keystores,
+    /// Trust relationships with other applications.
+    Trust,
+    /// Custom permissions.
+    CustomPermissions,
+    /// Network security configuration.
+    NetworkConfig,
+    /// Backup rules.
+    BackupRules,
+    /// Deep links.
+    DeepLinks,
+    /// Background camera and microphone capture.
+    Capture,
+    /// Screenshot, tapjacking and clipboard protections.
+    UiProtection,
+    /// Data safety declaration.
+    DataSafety,
+    /// Permissions and components new in a version.
+    Escalation,
+    /// Embedded Wear OS and Instant Apps.
+    Embedded,
+    /// Spyware indicators of the capability summary.
+    Spyware,
+    /// Homograph domains.
+    Homographs,
+    /// Hardcoded secrets.
+    Secrets,
+    /// Cryptography misuses.
+    Crypto,
+    /// TLS validation bypasses.
+    Tls,
+    /// WebView settings.
+    Webview,
+    /// SQL injections.
+    Sql,
+    /// Data storage.
+    Storage,
+    /// Dynamic code loading and execution.
+    DynamicCode,
+    /// Sensitive logging.
+    Logging,
+    /// Biometric authentication.
+    Biometrics,
+    /// Executable payloads hidden in the resources.
+    Payloads,
+    /// Code analysis rules.
+    Code,
+}
+
+impl Check {
+    /// Gets all the checks, in the order they run.
+    pub fn all() -> [Self; 32] {
+        [
+            Check::Manifest,
+            Check::Hardening,
+            Check::TaskHijacking,
+            Check::Broadcasts,
+            Check::SdkPolicy,
+            Check::Certificate,
+            Check::Signers,
+            Check::Artifacts,
+            Check::Keystores,
+            Check::Trust,
+            Check::CustomPermissions,
+            Check::NetworkConfig,
+            Check::BackupRules,
+            Check::DeepLinks,
+            Check::Capture,
+            Check::UiProtection,
+            Check::DataSafety,
+            Check::Escalation,
+            Check::Embedded,
+            Check::Spyware,
+            Check::Homographs,
+            Check::Secrets,
+            Check::Crypto,
+            Check::Tls,
+            Check::Webview,
+            Check::Sql,
+            Check::Storage,
+            Check::DynamicCode,
+            Check::Logging,
+            Check::Biometrics,
+            Check::Payloads,
+            Check::Code,
+        ]
+    }
+
+    /// Gets the name of the check, as used in the command line and the configuration.
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::Manifest => "manifest",
+            Check::Hardening => "hardening",
+            Check::TaskHijacking => "task-hijacking",
+            Check::Broadcasts => "broadcasts",
+            Check::SdkPolicy => "sdk-policy",
+            Check::Certificate => "certificate",
+            Check::Signers => "signers",
+            Check::Artifacts => "artifacts",
+            Check::Keystores => "keystores",
+            Check::Trust => "trust",
+            Check::CustomPermissions => "custom-permissions",
+            Check::NetworkConfig => "network-config",
+            Check::BackupRules => "backup-rules",
+            Check::DeepLinks => "deep-links",
+            Check::Capture => "capture",
+            Check::UiProtection => "ui-protection",
+            Check::DataSafety => "data-safety",
+            Check::Escalation => "escalation",
+            Check::Embedded => "embedded",
+            Check::Spyware => "spyware",
+            Check::Homographs => "homographs",
+            Check::Secrets => "secrets",
+            Check::Crypto => "crypto",
+            Check::Tls => "tls",
+            Check::Webview => "webview",
+            Check::Sql => "sql",
+            Check::Storage => "storage",
+            Check::DynamicCode => "dynamic-code",
+            Check::Logging => "logging",
+            Check::Biometrics => "biometrics",
+            Check::Payloads => "payloads",
+            Check::Code => "code",
+        }
+    }
+
+    /// Gets the check with the given name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .cloned()
+            .find(|check| check.name() == name)
+    }
+
+    /// Returns true if the check needs the manifest of the application to run.
+    pub fn needs_manifest(self) -> bool {
+        match self {
+            Check::Hardening
+            | Check::TaskHijacking
+            | Check::Broadcasts
+            | Check::SdkPolicy
+            | Check::Trust
+            | Check::CustomPermissions
+            | Check::NetworkConfig
+            | Check::BackupRules
+            | Check::DeepLinks
+            | Check::Capture
+            | Check::UiProtection
+            | Check::DataSafety
+            | Check::Escalation => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the check reads the decompiled code of the application, so the code has to
+    /// be decompiled for it to run.
+    pub fn needs_code(self) -> bool {
+        match self {
+            Check::Artifacts
+            | Check::Capture
+            | Check::UiProtection
+            | Check::Spyware
+            | Check::Homographs
+            | Check::Secrets
+            | Check::Crypto
+            | Check::Tls
+            | Check::Webview
+            | Check::Sql
+            | Check::Storage
+            | Check::DynamicCode
+            | Check::Logging
+            | Check::Biometrics
+            | Check::Code => true,
+            _ => false,
+        }
+    }
+
+    /// Gets the description of what the check searches for.
+    pub fn description(self) -> &'static str {
+        match self {
+            Check::Manifest => {
+                "Permissions and exported components of the manifest. Disabling it disables \
+                 every check that reads the manifest"
+            }
+            Check::Hardening => "Application attributes of the manifest that weaken its security",
+            Check::TaskHijacking => "Activities vulnerable to task hijacking",
+            Check::Broadcasts => "Sensitive system broadcasts received by the application",
+            Check::SdkPolicy => "Target and minimum SDK versions below the allowed ones",
+            Check::Certificate => "Signing certificate of the application",
+            Check::Signers => "Signers that changed since the previous analyzed versions",
+            Check::Artifacts => "Debug artifacts left in the application",
+            Check::Keystores => "Keystores and private keys bundled in the application",
+            Check::Trust => "Trust relationships with other applications",
+            Check::CustomPermissions => "Custom permissions that don't protect their components",
+            Check::NetworkConfig => "Insecure network security configuration settings",
+            Check::BackupRules => "Backup rules that include sensitive data",
+            Check::DeepLinks => "Unverified and overly broad deep links",
+            Check::Capture => "Camera and microphone capture that the user can't notice",
+            Check::UiProtection => "Screenshot, tapjacking and clipboard protections",
+            Check::DataSafety => "Data safety declaration cross-checked with the permissions",
+            Check::Escalation => "Permissions and components that previous versions didn't have",
+            Check::Embedded => "Embedded Wear OS and Instant Apps, and companion devices",
+            Check::Spyware => "Spyware indicators of the capability summary",
+            Check::Homographs => "Domains that imitate the domains of well-known brands",
+            Check::Secrets => "Secrets hardcoded in the code, the resources and the assets",
+            Check::Crypto => "Misuses of the cryptography APIs",
+            Check::Tls => "Bypasses of the TLS certificate validation",
+            Check::Webview => "Insecure settings of the WebViews",
+            Check::Sql => "SQL injections in raw queries and content providers",
+            Check::Storage => "Sensitive files in the external storage and world accessible files",
+            Check::DynamicCode => "Code loaded and executed from untrusted locations",
+            Check::Logging => "Sensitive data and stack traces written to the system log",
+            Check::Biometrics => "Deprecated and weakly enforced biometric authentication",
+            Check::Payloads => "Executable payloads hidden in the resources",
+            Check::Code => "Code analysis rules of the rules files",
+        }
+    }
+}
+
+/// Analysis checks testing module.
+#[cfg(test)]
+mod test {
+    use super::Check;
+
+    /// Checks the names of the checks.
+    #[test]
+    fn it_check_names() {
+        for check in Check::all().iter() {
+            assert_eq!(Check::from_name(check.name()), Some(*check));
+            assert!(!check.description().is_empty());
+        }
+        assert_eq!(
+            Check::from_name("task-hijacking"),
+            Some(Check::TaskHijacking)
+        );
+        assert_eq!(Check::from_name("task_hijacking"), None);
+        assert!(Check::Code.needs_code());
+        assert!(!Check::Code.needs_manifest());
+        assert!(Check::UiProtection.needs_code());
+        assert!(Check::UiProtection.needs_manifest());
+        assert!(!Check::Payloads.needs_code());
+
+        let checks: Vec<Check> = toml::Value::Array(vec![
+            toml::Value::String("dynamic-code".to_owned()),
+            toml::Value::String("code".to_owned()),
+        ])
+        .try_into()
+        .unwrap();
+        assert_eq!(checks, vec![Check::DynamicCode, Check::Code]);
+    }
+}
@@ -2,25 +2,36 @@
 
 use std::{
     borrow::Borrow,
+    collections::{BTreeMap, BTreeSet},
     fmt,
     fs::{self, DirEntry, File},
-    path::Path,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
     slice::Iter,
     sync::{Arc, Mutex},
     thread,
+    time::{Duration, SystemTime},
 };
 
 use colored::Colorize;
-use failure::{Error, Fail, ResultExt};
-use regex::Regex;
-use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use failure::{bail, format_err, Error, Fail, ResultExt};
+use regex::{Captures, Regex};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde_json;
-
-use super::manifest::{Manifest, Permission};
+use serde_yaml;
+use sha2::{Digest, Sha256};
+
+use super::{
+    category::Category,
+    libraries::{package_matches, sdk_folders, Origin},
+    library_cache::{class_set_hash, CachedLibrary, CachedMatch, LibraryCache, LIBRARY_CACHE_FILE},
+    manifest::{Manifest, Permission},
+};
 use crate::{
+    cancel,
     criticality::Criticality,
     error, get_code, print_vulnerability, print_warning,
-    results::{Results, Vulnerability},
+    results::{Results, VulnerabilityBuilder, VulnerabilitySink},
     Config,
 };
 
@@ -29,9 +40,9 @@ pub fn analysis<S: AsRef<str>>(
     manifest: Option<Manifest>,
     config: &Config,
     package: S,
-    results: &mut Results,
+    results: &Results,
 ) {
-    let rules = match load_rules(config) {
+    let rules = match load_weighted_rules(config, results.app_category()) {
         Ok(r) => r,
         Err(e) => {
             print_warning(format!(
@@ -50,13 +61,45 @@ pub fn analysis<S: AsRef<str>>(
             e
         ));
     }
+
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let cache_path = config.results_folder().join(LIBRARY_CACHE_FILE);
+    let rule_keys: Vec<String> = rules.iter().map(Rule::cache_key).collect();
+    let mut cache = None;
+    let files = if config.library_cache() {
+        match LibraryCache::load(&cache_path) {
+            Ok(library_cache) => {
+                let (files, misses) =
+                    plan_with_cache(files, &dist_folder, &library_cache, &rule_keys, config);
+                cache = Some((library_cache, misses));
+                files
+            }
+            Err(e) => {
+                print_warning(format!(
+                    "the library cache could not be loaded, the code of the SDKs will be \
+                     analyzed again: {}",
+                    e
+                ));
+                files
+                    .into_iter()
+                    .map(|f| (f, FileRules::Applicable))
+                    .collect()
+            }
+        }
+    } else {
+        files
+            .into_iter()
+            .map(|f| (f, FileRules::Applicable))
+            .collect()
+    };
     let total_files = files.len();
 
     let rules = Arc::new(rules);
     let manifest = Arc::new(manifest);
-    let found_vulnerabilities: Arc<Mutex<Vec<Vulnerability>>> = Arc::new(Mutex::new(Vec::new()));
     let files = Arc::new(Mutex::new(files));
-    let dist_folder = Arc::new(config.dist_folder().join(package.as_ref()));
+    let found = Arc::new(Mutex::new(Vec::new()));
+    let dist_folder = Arc::new(dist_folder);
+    let thread_config = Arc::new(config.clone());
 
     if config.is_verbose() {
         println!(
@@ -70,24 +113,32 @@ pub fn analysis<S: AsRef<str>>(
         .map(|_| {
             let thread_manifest = Arc::clone(&manifest);
             let thread_files = Arc::clone(&files);
+            let thread_found = Arc::clone(&found);
             let thread_rules = Arc::clone(&rules);
-            let thread_vulnerabilities = Arc::clone(&found_vulnerabilities);
+            let thread_sink = results.sink();
             let thread_dist_folder = Arc::clone(&dist_folder);
+            let thread_config = Arc::clone(&thread_config);
 
             thread::spawn(move || loop {
-                let f = {
+                // The rest of the files are skipped if the analysis is cancelled.
+                let f = if cancel::is_cancelled() {
+                    None
+                } else {
                     let mut files = thread_files.lock().unwrap();
                     files.pop()
                 };
                 match f {
-                    Some(f) => {
-                        if let Err(e) = analyze_file(
+                    Some((f, file_rules)) => {
+                        let matches = analyze_file_with(
                             f.path(),
                             &*thread_dist_folder,
                             &thread_rules,
                             &thread_manifest,
-                            &thread_vulnerabilities,
-                        ) {
+                            &thread_config,
+                            &thread_sink,
+                            &file_rules,
+                        );
+                        if let Err(ref e) = matches {
                             print_warning(format!(
                                 "could not analyze `{}`. The analysis will continue, though. \
                                  Error: {}",
@@ -95,6 +146,9 @@ pub fn analysis<S: AsRef<str>>(
                                 e
                             ))
                         }
+                        if let FileRules::All = file_rules {
+                            thread_found.lock().unwrap().push((f.path(), matches.ok()));
+                        }
                     }
                     None => break,
                 }
@@ -109,6 +163,7 @@ pub fn analysis<S: AsRef<str>>(
             Ok(f) => f.len(),
             Err(_) => 1,
         } > 0
+            && !cancel::is_cancelled()
         {
             let left = match files.lock() {
                 Ok(f) => f.len(),
@@ -120,6 +175,16 @@ pub fn analysis<S: AsRef<str>>(
                 println!("{} files already analyzed.", last_print);
             }
         }
+    } else if config.is_summary_live() {
+        let sink = results.sink();
+        loop {
+            let left = files.lock().map(|f| f.len()).unwrap_or_default();
+            print_live_summary(total_files - left, total_files, &sink);
+            if left == 0 || cancel::is_cancelled() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(LIVE_SUMMARY_INTERVAL));
+        }
     }
 
     for t in handles {
@@ -132,15 +197,30 @@ pub fn analysis<S: AsRef<str>>(
         }
     }
 
-    for vulnerability in Arc::try_unwrap(found_vulnerabilities)
-        .unwrap()
-        .into_inner()
-        .unwrap()
-    {
-        results.add_vulnerability(vulnerability);
+    // The last files were still being analyzed when the status line was last printed.
+    if config.is_summary_live() {
+        let left = files.lock().map(|f| f.len()).unwrap_or_default();
+        print_live_summary(total_files - left, total_files, &results.sink());
+        eprintln!();
     }
 
-    if config.is_verbose() {
+    // The SDKs of a cancelled analysis were not completely analyzed, so they are not cached.
+    if let Some((mut library_cache, misses)) = cache.filter(|_| !cancel::is_cancelled()) {
+        if !misses.is_empty() {
+            let found = found.lock().unwrap();
+            cache_libraries(&mut library_cache, misses, &found, &dist_folder, &rule_keys);
+            let saved = fs::create_dir_all(config.results_folder())
+                .map_err(Error::from)
+                .and_then(|_| library_cache.save(&cache_path));
+            if let Err(e) = saved {
+                print_warning(format!("the library cache could not be saved: {}", e));
+            }
+        }
+    }
+
+    if cancel::is_cancelled() {
+        print_warning("the analysis of the source code was cancelled");
+    } else if config.is_verbose() {
         println!();
         println!("{}", "The source code was analyzed correctly!".green());
     } else if !config.is_quiet() {
@@ -148,120 +228,327 @@ pub fn analysis<S: AsRef<str>>(
     }
 }
 
+/// Milliseconds between the updates of the status line of the `--summary-live` mode.
+const LIVE_SUMMARY_INTERVAL: u64 = 250;
+
+/// Criticalities of the findings counted in the status line, from the most critical.
+const LIVE_SUMMARY_CRITICALITIES: [Criticality; 5] = [
+    Criticality::Critical,
+    Criticality::High,
+    Criticality::Medium,
+    Criticality::Low,
+    Criticality::Warning,
+];
+
+/// Prints the status line of the `--summary-live` mode, replacing the previous one.
+///
+/// It's printed to the standard error, so that it works in `--quiet` mode without mixing with
+/// the facts selected with `--print`.
+fn print_live_summary(done: usize, total: usize, sink: &VulnerabilitySink) {
+    let findings = LIVE_SUMMARY_CRITICALITIES
+        .iter()
+        .map(|criticality| format!("{} {}", sink.len(*criticality), criticality))
+        .collect::<Vec<_>>()
+        .join(", ");
+    eprint!("\r{}/{} files scanned, findings: {}", done, total, findings);
+    let _ = io::stderr().flush();
+}
+
+/// Matches of the rules in a file, with the index of the rule and the first and last lines of
+/// each match.
+type FileMatches = Vec<(usize, usize, usize)>;
+
+/// SDK whose analysis was not found in the library cache, with the hash of its classes and its
+/// folder.
+type CacheMiss = (&'static str, String, PathBuf);
+
+/// How the rules are checked in a file.
+enum FileRules {
+    /// The rules that apply to the application are checked.
+    Applicable,
+    /// All the rules that apply to the file are checked, even if they don't apply to the
+    /// application, so that their matches can be cached for other applications.
+    All,
+    /// The matches of the rules are taken from the library cache.
+    Cached(FileMatches),
+}
+
+/// Decides how the rules are checked in each file, using the library cache for the code of the
+/// well known SDKs.
+///
+/// The files of the SDKs found in the cache only get their cached matches, and the ones without
+/// matches are not analyzed at all. All the rules are checked in the files of the SDKs that are
+/// not in the cache, which are returned so that their analysis can be cached.
+fn plan_with_cache(
+    files: Vec<DirEntry>,
+    dist_folder: &Path,
+    cache: &LibraryCache,
+    rule_keys: &[String],
+    config: &Config,
+) -> (Vec<(DirEntry, FileRules)>, Vec<CacheMiss>) {
+    let keys: BTreeSet<String> = rule_keys.iter().cloned().collect();
+    let mut remaining = files;
+    let mut planned = Vec::new();
+    let mut misses = Vec::new();
+
+    for (name, folder) in sdk_folders(dist_folder.join("classes")) {
+        let (sdk_files, others): (Vec<_>, Vec<_>) = remaining
+            .into_iter()
+            .partition(|f| f.path().starts_with(&folder));
+        remaining = others;
+        if sdk_files.is_empty() {
+            continue;
+        }
+
+        let paths: Vec<PathBuf> = sdk_files.iter().map(DirEntry::path).collect();
+        let hash = match class_set_hash(dist_folder, &paths) {
+            Ok(hash) => hash,
+            Err(e) => {
+                print_warning(format!(
+                    "the classes of {} could not be hashed, so the library cache will not be \
+                     used for them: {}",
+                    name, e
+                ));
+                planned.extend(sdk_files.into_iter().map(|f| (f, FileRules::Applicable)));
+                continue;
+            }
+        };
+
+        match cache.get(&hash, &keys) {
+            Some(library) => {
+                if config.is_verbose() {
+                    println!(
+                        "The code of {} was found in the library cache, it will not be analyzed.",
+                        name.bold()
+                    );
+                }
+                let mut file_matches: BTreeMap<&Path, FileMatches> = BTreeMap::new();
+                for m in library.matches() {
+                    let indexes = rule_keys
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, key)| key.as_str() == m.rule())
+                        .map(|(index, _)| (index, m.start_line(), m.end_line()));
+                    file_matches
+                        .entry(m.file())
+                        .or_insert_with(Vec::new)
+                        .extend(indexes);
+                }
+                for f in sdk_files {
+                    let path = f.path();
+                    let relative = path.strip_prefix(dist_folder).unwrap_or(path.as_path());
+                    if let Some(matches) = file_matches.remove(relative) {
+                        if !matches.is_empty() {
+                            planned.push((f, FileRules::Cached(matches)));
+                        }
+                    }
+                }
+            }
+            None => {
+                planned.extend(sdk_files.into_iter().map(|f| (f, FileRules::All)));
+                misses.push((name, hash, folder));
+            }
+        }
+    }
+
+    planned.extend(remaining.into_iter().map(|f| (f, FileRules::Applicable)));
+    (planned, misses)
+}
+
+/// Adds the analysis of the SDKs that were not in the library cache to the cache, from the
+/// matches found in their files.
+///
+/// SDKs with files that could not be analyzed are not cached.
+fn cache_libraries(
+    cache: &mut LibraryCache,
+    misses: Vec<CacheMiss>,
+    found: &[(PathBuf, Option<FileMatches>)],
+    dist_folder: &Path,
+    rule_keys: &[String],
+) {
+    let keys: BTreeSet<String> = rule_keys.iter().cloned().collect();
+    'library: for (name, hash, folder) in misses {
+        let mut matches = Vec::new();
+        for (path, file_matches) in found.iter().filter(|(path, _)| path.starts_with(&folder)) {
+            let file_matches = match file_matches {
+                Some(file_matches) => file_matches,
+                None => continue 'library,
+            };
+            let relative = path.strip_prefix(dist_folder).unwrap_or(path.as_path());
+            matches.extend(file_matches.iter().map(|&(index, start_line, end_line)| {
+                CachedMatch::new(relative, rule_keys[index].as_str(), start_line, end_line)
+            }));
+        }
+        matches.sort();
+        cache.insert(hash, CachedLibrary::new(name, keys.clone(), matches));
+    }
+}
+
 /// Analyzes the given file.
-fn analyze_file<P: AsRef<Path>, T: AsRef<Path>>(
+pub(crate) fn analyze_file<P: AsRef<Path>, T: AsRef<Path>>(
     path: P,
     dist_folder: T,
     rules: &[Rule],
     manifest: &Option<Manifest>,
-    results: &Mutex<Vec<Vulnerability>>,
+    config: &Config,
+    results: &VulnerabilitySink,
 ) -> Result<(), Error> {
+    let _ = analyze_file_with(
+        path,
+        dist_folder,
+        rules,
+        manifest,
+        config,
+        results,
+        &FileRules::Applicable,
+    )?;
+    Ok(())
+}
+
+/// Analyzes the given file, checking the rules in the given way.
+///
+/// It returns the matches of the checked rules, including the ones that were not reported because
+/// the rule does not apply to the application.
+fn analyze_file_with<P: AsRef<Path>, T: AsRef<Path>>(
+    path: P,
+    dist_folder: T,
+    rules: &[Rule],
+    manifest: &Option<Manifest>,
+    config: &Config,
+    results: &VulnerabilitySink,
+    file_rules: &FileRules,
+) -> Result<FileMatches, Error> {
     let code = fs::read_to_string(&path)?;
+    let package = path
+        .as_ref()
+        .strip_prefix(&dist_folder)
+        .ok()
+        .and_then(code_package);
+    let origin = package
+        .as_ref()
+        .and_then(|package| Origin::of_package(package, manifest.as_ref().map(Manifest::package)));
+    let add_finding = |rule: &Rule, start_line: usize, end_line: usize| {
+        let criticality = match origin {
+            Some(Origin::Sdk(_)) if config.downrank_sdk_findings() => rule.criticality().lower(),
+            _ => rule.criticality(),
+        };
+        if criticality < config.min_criticality() {
+            return;
+        }
 
-    'check: for rule in rules {
-        if manifest.is_some()
-            && rule.max_sdk().is_some()
-            && rule.max_sdk().unwrap() < manifest.as_ref().unwrap().min_sdk()
-        {
-            continue 'check;
+        let mut builder = VulnerabilityBuilder::new(criticality, rule.label(), rule.description())
+            .file(path.as_ref().strip_prefix(&dist_folder).unwrap())
+            .lines(start_line, end_line)
+            .code(get_code(code.as_str(), start_line, end_line));
+        if let Some(ref origin) = origin {
+            builder = builder.origin(origin.clone());
+        }
+        if let Some(section) = rule.section() {
+            builder = builder.section(section);
+        }
+        if let Some(id) = rule.id() {
+            builder = builder.rule_id(id);
+        }
+        if let Some(cwe) = rule.cwe() {
+            builder = builder.cwe(cwe);
         }
+        if let Some(owasp_masvs) = rule.owasp_masvs() {
+            builder = builder.owasp_masvs(owasp_masvs);
+        }
+        let _ = results.add(builder.build());
 
-        let filename = path.as_ref().file_name().and_then(|f| f.to_str());
+        print_vulnerability(rule.description(), criticality);
+    };
 
-        if let Some(f) = filename {
-            if !rule.has_to_check(f) {
-                continue 'check;
-            }
+    // File regular expressions match the path relative to the distribution folder, so that
+    // rules can skip whole folders, such as the code of third party libraries.
+    let file_path = path
+        .as_ref()
+        .strip_prefix(dist_folder.as_ref())
+        .unwrap_or_else(|_| path.as_ref())
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    let mut found = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        if !rule.has_to_check(&file_path)
+            || !rule.has_to_check_package(package.as_ref().map(String::as_str))
+        {
+            continue;
         }
-
-        for permission in rule.permissions() {
-            if manifest.is_none()
-                || !manifest
-                    .as_ref()
-                    .unwrap()
-                    .permission_checklist()
-                    .needs_permission(*permission)
-            {
-                continue 'check;
+        let applies = applies_to_app(rule, manifest);
+        if !applies {
+            if let FileRules::Applicable = file_rules {
+                continue;
             }
         }
 
-        'rule: for m in rule.regex().find_iter(code.as_str()) {
-            for white in rule.whitelist() {
-                if white.is_match(&code[m.start()..m.end()]) {
-                    continue 'rule;
-                }
-            }
-            match rule.forward_check() {
-                None => {
-                    let start_line = get_line_for(m.start(), code.as_str());
-                    let end_line = get_line_for(m.end(), code.as_str());
-                    let mut results = results.lock().unwrap();
-                    results.push(Vulnerability::new(
-                        rule.criticality(),
+        let lines: Vec<(usize, usize)> = match file_rules {
+            FileRules::Cached(cached) => cached
+                .iter()
+                .filter(|(i, _, _)| *i == index)
+                .map(|&(_, start_line, end_line)| (start_line, end_line))
+                .collect(),
+            FileRules::Applicable | FileRules::All => match rule.find_matches(code.as_str()) {
+                Ok(matches) => matches
+                    .into_iter()
+                    .map(|(start, end)| {
+                        (
+                            get_line_for(start, code.as_str()),
+                            get_line_for(end, code.as_str()),
+                        )
+                    })
+                    .collect(),
+                Err(e) => {
+                    print_warning(format!(
+                        "there was an error creating the forward check of the `{}` rule. The \
+                         rule will be skipped. {}",
                         rule.label(),
-                        rule.description(),
-                        Some(path.as_ref().strip_prefix(&dist_folder).unwrap()),
-                        Some(start_line),
-                        Some(end_line),
-                        Some(get_code(code.as_str(), start_line, end_line)),
+                        e
                     ));
-
-                    print_vulnerability(rule.description(), rule.criticality());
+                    continue;
                 }
-                Some(check) => {
-                    let caps = rule.regex().captures(&code[m.start()..m.end()]).unwrap();
-
-                    let forward_check1 = caps.name("fc1");
-                    let forward_check2 = caps.name("fc2");
-                    let mut r = check.clone();
-
-                    if let Some(fc1) = forward_check1 {
-                        r = r.replace("{fc1}", fc1.as_str());
-                    }
-
-                    if let Some(fc2) = forward_check2 {
-                        r = r.replace("{fc2}", fc2.as_str());
-                    }
+            },
+        };
 
-                    let regex = match Regex::new(r.as_str()) {
-                        Ok(r) => r,
-                        Err(e) => {
-                            print_warning(format!(
-                                "there was an error creating the forward_check '{}'. The rule will \
-                                 be skipped. {}",
-                                r,
-                                e
-                            ));
-                            break 'rule;
-                        }
-                    };
-
-                    for m in regex.find_iter(code.as_str()) {
-                        let start_line = get_line_for(m.start(), code.as_str());
-                        let end_line = get_line_for(m.end(), code.as_str());
-                        let mut results = results.lock().unwrap();
-                        results.push(Vulnerability::new(
-                            rule.criticality(),
-                            rule.label(),
-                            rule.description(),
-                            Some(path.as_ref().strip_prefix(&dist_folder).unwrap()),
-                            Some(start_line),
-                            Some(end_line),
-                            Some(get_code(code.as_str(), start_line, end_line)),
-                        ));
-
-                        print_vulnerability(rule.description(), rule.criticality());
-                    }
-                }
+        found.extend(
+            lines
+                .iter()
+                .map(|&(start_line, end_line)| (index, start_line, end_line)),
+        );
+        if applies {
+            for (start_line, end_line) in lines {
+                add_finding(rule, start_line, end_line);
             }
         }
     }
 
-    Ok(())
+    Ok(found)
+}
+
+/// Returns true if the rule applies to the application, because its minimum SDK is not greater
+/// than the maximum SDK of the rule and it has the permissions required by the rule.
+///
+/// Rules that require permissions don't apply if the manifest is not available.
+fn applies_to_app(rule: &Rule, manifest: &Option<Manifest>) -> bool {
+    if let Some(manifest) = manifest {
+        if rule
+            .max_sdk()
+            .map_or(false, |max_sdk| max_sdk < manifest.min_sdk())
+        {
+            return false;
+        }
+    }
+    rule.permissions().all(|permission| {
+        manifest.as_ref().map_or(false, |manifest| {
+            manifest
+                .permission_checklist()
+                .needs_permission(*permission)
+        })
+    })
 }
 
-fn get_line_for<S: AsRef<str>>(index: usize, text: S) -> usize {
+pub(crate) fn get_line_for<S: AsRef<str>>(index: usize, text: S) -> usize {
     let mut line = 0;
     for (i, c) in text.as_ref().char_indices() {
         if i == index {
@@ -329,12 +616,26 @@ fn add_files_to_vec<P: AsRef<Path>, S: AsRef<str>>(
 
 /// Vulnerability searching rule.
 #[derive(Debug, Deserialize)]
-struct Rule {
+#[serde(deny_unknown_fields)]
+pub(crate) struct Rule {
+    #[serde(default)]
+    id: Option<String>,
     #[serde(deserialize_with = "deserialize_main_regex")]
     regex: Regex,
     #[serde(default)]
     permissions: Box<[Permission]>,
     forward_check: Option<String>,
+    backward_check: Option<String>,
+    forward_absent: Option<String>,
+    backward_absent: Option<String>,
+    within_lines: Option<usize>,
+    #[serde(default)]
+    multiline: bool,
+    #[serde(deserialize_with = "deserialize_file_regex")]
+    #[serde(default)]
+    source: Option<Regex>,
+    #[serde(default)]
+    scope: Scope,
     max_sdk: Option<u32>,
     #[serde(deserialize_with = "deserialize_whitelist_regex")]
     #[serde(default)]
@@ -348,9 +649,85 @@ struct Rule {
     #[serde(deserialize_with = "deserialize_file_regex")]
     #[serde(default)]
     exclude_file_regex: Option<Regex>,
+    #[serde(default)]
+    include_packages: Box<[String]>,
+    #[serde(default)]
+    exclude_packages: Box<[String]>,
+    #[serde(default)]
+    tags: Box<[String]>,
+    #[serde(default)]
+    section: Option<String>,
+    cwe: Option<u32>,
+    owasp_masvs: Option<String>,
+    #[serde(default)]
+    examples: Examples,
+}
+
+/// Flags of the regular expressions of multiline rules.
+const MULTILINE_FLAGS: &str = "(?ms)";
+
+/// Code block where the source of a rule must be found, before its match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Scope {
+    /// The method of the match.
+    Method,
+    /// The class of the match.
+    Class,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Scope::Method
+    }
+}
+
+impl Scope {
+    /// Gets the nesting level of the blocks of the scope: classes are at the first level of a
+    /// file, and their methods at the second one.
+    fn depth(self) -> usize {
+        match self {
+            Scope::Class => 1,
+            Scope::Method => 2,
+        }
+    }
+
+    /// Gets the position of the opening brace of the scope block that contains the given index.
+    ///
+    /// Braces in strings and comments are not ignored, so the blocks are an approximation.
+    fn block_start(self, code: &str, index: usize) -> Option<usize> {
+        let mut open = Vec::new();
+        for (i, c) in code[..index].char_indices() {
+            match c {
+                '{' => open.push(i),
+                '}' => {
+                    let _ = open.pop();
+                }
+                _ => {}
+            }
+        }
+        open.get(self.depth() - 1).cloned()
+    }
+}
+
+/// Code examples of a rule, used to test its regular expressions.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Examples {
+    /// Code that the rule must report.
+    #[serde(default)]
+    positive: Vec<String>,
+    /// Code that the rule must not report.
+    #[serde(default)]
+    negative: Vec<String>,
 }
 
 impl Rule {
+    /// Gets the ID of the rule, used by later rules files to override or disable it.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(String::as_str)
+    }
+
     /// Gets the regex of the rule.
     pub fn regex(&self) -> &Regex {
         &self.regex
@@ -386,28 +763,278 @@ impl Rule {
         self.criticality
     }
 
-    /// Gets the whitelist regex list.
-    pub fn whitelist(&self) -> Iter<Regex> {
-        self.whitelist.iter()
+    /// Gets the tags of the rule, such as `crypto` or `storage`.
+    pub fn tags(&self) -> Iter<String> {
+        self.tags.iter()
+    }
+
+    /// Gets the report section where the findings of the rule are grouped, if any.
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_ref().map(String::as_str)
+    }
+
+    /// Gets the CWE identifier of the weakness reported by the rule, if any.
+    pub fn cwe(&self) -> Option<u32> {
+        self.cwe
+    }
+
+    /// Gets the OWASP MASVS control that the rule checks, if any.
+    pub fn owasp_masvs(&self) -> Option<&str> {
+        self.owasp_masvs.as_ref().map(String::as_str)
     }
 
-    /// Returns if this rule has to be applied to the given filename
-    pub fn has_to_check(&self, filename: &str) -> bool {
-        if self.include_file_regex.is_none() && self.exclude_file_regex.is_none() {
-            return true;
+    /// Gets the key of the rule in the library cache.
+    ///
+    /// It's a hash of the fields that decide where the rule matches, so that the cached matches of
+    /// a rule are not used once it changes. The fields that only change how the findings are
+    /// reported, such as the criticality, are not part of the key, and neither are the permissions
+    /// and the maximum SDK, that are checked for each application.
+    pub fn cache_key(&self) -> String {
+        let optional = |field: &Option<String>| field.clone().unwrap_or_default();
+        let optional_regex = |field: &Option<Regex>| {
+            field
+                .as_ref()
+                .map_or_else(String::new, |r| r.as_str().to_owned())
+        };
+        let fields = [
+            self.regex.as_str().to_owned(),
+            self.whitelist
+                .iter()
+                .map(Regex::as_str)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            optional(&self.forward_check),
+            optional(&self.backward_check),
+            optional(&self.forward_absent),
+            optional(&self.backward_absent),
+            self.within_lines
+                .map_or_else(String::new, |lines| lines.to_string()),
+            self.multiline.to_string(),
+            optional_regex(&self.source),
+            self.scope.depth().to_string(),
+            optional_regex(&self.include_file_regex),
+            optional_regex(&self.exclude_file_regex),
+            self.include_packages.join("\n"),
+            self.exclude_packages.join("\n"),
+        ];
+        hex::encode(Sha256::digest(fields.join("\0").as_bytes()))
+    }
+
+    /// Applies the weights of the given application category to the criticality of the rule.
+    ///
+    /// The criticality is adjusted with the weight of the rule tag with the largest weight, or
+    /// with the lowest one if all the weights are negative.
+    fn apply_category_weights(&mut self, config: &Config, category: Category) {
+        let weights: Vec<_> = self
+            .tags()
+            .map(|tag| config.category_weight(category, tag))
+            .collect();
+        let max = weights.iter().cloned().max().unwrap_or(0);
+        let weight = if max > 0 {
+            max
+        } else {
+            weights.iter().cloned().min().unwrap_or(0)
+        };
+        self.criticality = self.criticality.adjusted(weight);
+    }
+
+    /// Finds the code that the rule reports in the given code, as byte ranges.
+    ///
+    /// The matches of the rule regular expression that match a whitelist expression are
+    /// discarded, and so are the matches without the backward check, or with the forward or
+    /// backward absent checks, in their context. The context is limited to the given number of
+    /// lines before or after the match, if any. If the rule has a forward check, the matches of
+    /// the forward check are returned instead, which are searched in the whole code unless the
+    /// context is limited. It's an error if a check is not a valid regular expression.
+    ///
+    /// If the rule has a source, only the matches with a match of the source before them, in the
+    /// same method or class, are reported, from the closest source to the end of the match.
+    pub fn find_matches(&self, code: &str) -> Result<Vec<(usize, usize)>, regex::Error> {
+        let mut matches = Vec::new();
+        'rule: for m in self.regex().find_iter(code) {
+            for white in self.whitelist() {
+                if white.is_match(m.as_str()) {
+                    continue 'rule;
+                }
+            }
+
+            let caps = self.regex().captures(m.as_str()).unwrap();
+            let before = lines_before(code, m.start(), self.within_lines);
+            let after = lines_after(code, m.end(), self.within_lines);
+            if let Some(ref check) = self.backward_check {
+                if !self.check_regex(check, &caps)?.is_match(before) {
+                    continue 'rule;
+                }
+            }
+            if let Some(ref check) = self.backward_absent {
+                if self.check_regex(check, &caps)?.is_match(before) {
+                    continue 'rule;
+                }
+            }
+            if let Some(ref check) = self.forward_absent {
+                if self.check_regex(check, &caps)?.is_match(after) {
+                    continue 'rule;
+                }
+            }
+            let start = match self.source {
+                Some(ref source) => match self.find_source(source, code, m.start()) {
+                    Some(start) => start,
+                    None => continue 'rule,
+                },
+                None => m.start(),
+            };
+
+            match self.forward_check() {
+                None => matches.push((start, m.end())),
+                Some(check) => {
+                    let regex = self.check_regex(check, &caps)?;
+                    if self.within_lines.is_some() {
+                        let offset = m.end();
+                        matches.extend(
+                            regex
+                                .find_iter(after)
+                                .map(|m| (offset + m.start(), offset + m.end())),
+                        );
+                    } else {
+                        matches.extend(regex.find_iter(code).map(|m| (m.start(), m.end())));
+                    }
+                }
+            }
         }
+        Ok(matches)
+    }
+
+    /// Finds the start of the closest match of the source before the given index, in the same
+    /// scope block.
+    fn find_source(&self, source: &Regex, code: &str, index: usize) -> Option<usize> {
+        let block = self.scope.block_start(code, index)?;
+        source
+            .find_iter(&code[block..index])
+            .last()
+            .map(|m| block + m.start())
+    }
 
-        let mut has_to_check = false;
+    /// Creates the regular expression of a check, replacing the `{fc1}` and `{fc2}` placeholders
+    /// with the captures of the match.
+    fn check_regex(&self, check: &str, caps: &Captures) -> Result<Regex, regex::Error> {
+        let mut r = check.to_owned();
+        if let Some(fc1) = caps.name("fc1") {
+            r = r.replace("{fc1}", fc1.as_str());
+        }
+        if let Some(fc2) = caps.name("fc2") {
+            r = r.replace("{fc2}", fc2.as_str());
+        }
+        if self.multiline {
+            r.insert_str(0, MULTILINE_FLAGS);
+        }
+        Regex::new(r.as_str())
+    }
 
-        if let Some(ref r) = self.include_file_regex {
-            has_to_check = r.is_match(filename)
+    /// Prepares the regular expression of a multiline rule, so that `^` and `$` match at the
+    /// start and the end of each line, and `.` matches line breaks.
+    fn prepare(&mut self) -> Result<(), regex::Error> {
+        if self.multiline && !self.regex.as_str().starts_with(MULTILINE_FLAGS) {
+            self.regex = Regex::new(&format!("{}{}", MULTILINE_FLAGS, self.regex.as_str()))?;
+            if let Some(ref mut source) = self.source {
+                *source = Regex::new(&format!("{}{}", MULTILINE_FLAGS, source.as_str()))?;
+            }
         }
+        Ok(())
+    }
+
+    /// Returns true if the rule has code examples to test it.
+    pub fn has_examples(&self) -> bool {
+        !self.examples.positive.is_empty() || !self.examples.negative.is_empty()
+    }
+
+    /// Tests the rule against its code examples, returning the reasons of the failed ones.
+    ///
+    /// Positive examples must be reported by the rule, and negative examples must not.
+    pub fn test_examples(&self) -> Vec<String> {
+        let positive = self.examples.positive.iter().map(|code| (code, true));
+        let negative = self.examples.negative.iter().map(|code| (code, false));
+        positive
+            .chain(negative)
+            .filter_map(|(code, positive)| match self.find_matches(code) {
+                Ok(ref matches) if matches.is_empty() == positive => Some(format!(
+                    "the {} example `{}` {}",
+                    if positive { "positive" } else { "negative" },
+                    code.trim(),
+                    if positive {
+                        "is not reported"
+                    } else {
+                        "is reported"
+                    }
+                )),
+                Ok(_) => None,
+                Err(e) => Some(format!(
+                    "the forward check is not valid for the example `{}`: {}",
+                    code.trim(),
+                    e
+                )),
+            })
+            .collect()
+    }
+
+    /// Gets the whitelist regex list.
+    pub fn whitelist(&self) -> Iter<Regex> {
+        self.whitelist.iter()
+    }
 
-        if let Some(ref r) = self.exclude_file_regex {
-            has_to_check = !r.is_match(filename)
+    /// Returns if this rule has to be applied to the file in the given path.
+    ///
+    /// The path is relative to the distribution folder of the application, with `/` separators,
+    /// such as `classes/com/example/Main.java`. The file has to match the include regular
+    /// expression, if any, and must not match the exclude regular expression, if any.
+    pub fn has_to_check(&self, path: &str) -> bool {
+        self.include_file_regex
+            .as_ref()
+            .map_or(true, |r| r.is_match(path))
+            && !self
+                .exclude_file_regex
+                .as_ref()
+                .map_or(false, |r| r.is_match(path))
+    }
+
+    /// Returns if this rule has to be applied to the code of the given Java package.
+    ///
+    /// Files that are not part of a Java package, such as resources, are only checked by rules
+    /// that are not restricted to some packages.
+    pub fn has_to_check_package(&self, package: Option<&str>) -> bool {
+        match package {
+            Some(package) => {
+                (self.include_packages.is_empty()
+                    || self
+                        .include_packages
+                        .iter()
+                        .any(|pattern| package_matches(pattern, package)))
+                    && !self
+                        .exclude_packages
+                        .iter()
+                        .any(|pattern| package_matches(pattern, package))
+            }
+            None => self.include_packages.is_empty(),
         }
+    }
+}
 
-        has_to_check
+/// Gets the Java package of the given decompiled source file, relative to the distribution
+/// folder of the application.
+pub(crate) fn code_package<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mut components = path.as_ref().components();
+    if components.next()?.as_os_str() != "classes" {
+        return None;
+    }
+    let folders: Vec<_> = components
+        .as_path()
+        .parent()?
+        .iter()
+        .map(|folder| folder.to_string_lossy())
+        .collect();
+    if folders.is_empty() {
+        None
+    } else {
+        Some(folders.join("."))
     }
 }
 
@@ -521,8 +1148,177 @@ where
     deserializer.deserialize_option(RegexOptionVisitor)
 }
 
-fn load_rules(config: &Config) -> Result<Vec<Rule>, Error> {
-    let f = File::open(config.rules_json())?;
+/// Format of a rules file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RulesFormat {
+    /// JSON rules, such as the default `rules.json` file.
+    Json,
+    /// YAML rules, better suited for long descriptions and regular expressions.
+    Yaml,
+}
+
+impl RulesFormat {
+    /// Gets the format of the given rules file, from its extension.
+    fn from_path(path: &Path) -> Self {
+        Self::from_extension(path).unwrap_or(RulesFormat::Json)
+    }
+
+    /// Gets the format of the given file from its extension, if it's a JSON or YAML file.
+    fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension)
+                if extension.eq_ignore_ascii_case("yaml")
+                    || extension.eq_ignore_ascii_case("yml") =>
+            {
+                Some(RulesFormat::Yaml)
+            }
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Some(RulesFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Rules of a rules file.
+///
+/// A rules file is either a list of rules, or a map with the list of rules in its `rules` key
+/// and the IDs of the rules of previous files to disable in its `disable` key.
+#[derive(Debug, Default)]
+struct RuleSet {
+    /// IDs of the rules of previous rules files to disable.
+    disable: Vec<String>,
+    /// Rules of the file.
+    rules: Vec<Rule>,
+}
+
+impl<'de> Deserialize<'de> for RuleSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        /// Rule set serde visitor.
+        struct RuleSetVisitor;
+
+        impl<'de> Visitor<'de> for RuleSetVisitor {
+            type Value = RuleSet;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of rules, or a map with `disable` and `rules` keys")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let mut rules = Vec::new();
+                while let Some(rule) = seq.next_element()? {
+                    rules.push(rule);
+                }
+                Ok(RuleSet {
+                    disable: Vec::new(),
+                    rules,
+                })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut set = RuleSet::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "disable" => set.disable = map.next_value()?,
+                        "rules" => set.rules = map.next_value()?,
+                        _ => return Err(de::Error::unknown_field(&key, &["disable", "rules"])),
+                    }
+                }
+                Ok(set)
+            }
+        }
+
+        deserializer.deserialize_any(RuleSetVisitor)
+    }
+}
+
+impl RuleSet {
+    /// Applies the rule set on top of the rules of the previous rules files.
+    ///
+    /// The disabled rules are removed, the rules with the ID of a previous rule replace it, and
+    /// the rest of the rules are added at the end.
+    fn apply(self, rules: &mut Vec<Rule>, path: &Path) {
+        for id in &self.disable {
+            let len = rules.len();
+            rules.retain(|rule| rule.id() != Some(id.as_str()));
+            if rules.len() == len {
+                print_warning(format!(
+                    "the `{}` rule disabled in {} is not defined in previous rules files",
+                    id,
+                    path.display()
+                ));
+            }
+        }
+
+        for rule in self.rules {
+            let position = rule
+                .id()
+                .and_then(|id| rules.iter().position(|previous| previous.id() == Some(id)));
+            match position {
+                Some(i) => rules[i] = rule,
+                None => rules.push(rule),
+            }
+        }
+    }
+}
+
+/// Parses the rules in the given format.
+///
+/// Unknown keys are rejected, and the errors include the line and column of the problem.
+fn parse_rules<R: Read>(reader: R, format: RulesFormat) -> Result<RuleSet, Error> {
+    let mut rule_set: RuleSet = match format {
+        RulesFormat::Json => serde_json::from_reader(reader)?,
+        RulesFormat::Yaml => serde_yaml::from_reader(reader)?,
+    };
+    for rule in &mut rule_set.rules {
+        rule.prepare()
+            .context(format!("the `{}` rule is not valid", rule.label()))?;
+    }
+    Ok(rule_set)
+}
+
+/// Gets the code in the given number of lines before the given index, and in the line of the
+/// index. All the code before the index is returned if no number of lines is given.
+fn lines_before(code: &str, index: usize, lines: Option<usize>) -> &str {
+    let start = lines.map_or(0, |lines| {
+        code[..index]
+            .rmatch_indices('\n')
+            .nth(lines)
+            .map_or(0, |(i, _)| i + 1)
+    });
+    &code[start..index]
+}
+
+/// Gets the code in the line of the given index, and in the given number of lines after it. All
+/// the code after the index is returned if no number of lines is given.
+fn lines_after(code: &str, index: usize, lines: Option<usize>) -> &str {
+    let end = lines.map_or(code.len(), |lines| {
+        code[index..]
+            .match_indices('\n')
+            .nth(lines)
+            .map_or(code.len(), |(i, _)| index + i)
+    });
+    &code[index..end]
+}
+
+/// Loads the code analysis rules from the configured rules files.
+pub(crate) fn load_rules(config: &Config) -> Result<Vec<Rule>, Error> {
+    load_weighted_rules(config, None)
+}
+
+/// Reads every rule of the configured rules files, without filtering them.
+///
+/// Later rules files override or disable the rules of the previous ones. The files of a rules
+/// directory are loaded in path order, and they can't define the same rule ID more than once,
+/// since their order should not change the rules.
+pub(crate) fn read_rules(config: &Config) -> Result<Vec<Rule>, Error> {
     let format_error = format!(
         "rules must be objects with the following structure:\n{}\nAn optional {} attribute can be \
          added: an array of regular expressions that if matched, the found match will be \
@@ -545,12 +1341,106 @@ fn load_rules(config: &Config) -> Result<Vec<Rule>, Error> {
         "{fc2}".italic()
     );
 
-    let rules: Vec<Rule> = serde_json::from_reader(f).context(format_error.clone())?;
-    let rules =
-        rules
-            .into_iter()
-            .filter_map(|rule| {
-                if rule.criticality >= config.min_criticality() {
+    let mut rules = Vec::new();
+    for path in config.rules_json() {
+        let mut ids: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for file in rules_files(path)? {
+            let f = File::open(&file)
+                .context(format!("could not open the rules file {}", file.display()))?;
+            let rule_set = parse_rules(f, RulesFormat::from_path(&file)).map_err(|e| {
+                format_err!(
+                    "the rules file {} is not valid: {}\n{}",
+                    file.display(),
+                    e,
+                    format_error
+                )
+            })?;
+
+            if path.is_dir() {
+                for id in rule_set.rules.iter().filter_map(Rule::id) {
+                    if let Some(previous) = ids.insert(id.to_owned(), file.clone()) {
+                        if previous == file {
+                            bail!(
+                                "the `{}` rule is defined more than once in {}",
+                                id,
+                                file.display()
+                            );
+                        }
+                        bail!(
+                            "the `{}` rule is defined in both {} and {}",
+                            id,
+                            previous.display(),
+                            file.display()
+                        );
+                    }
+                }
+            }
+            rule_set.apply(&mut rules, &file);
+        }
+    }
+    Ok(rules)
+}
+
+/// Gets the rules files of the given path, in load order.
+///
+/// A directory is expanded to the JSON and YAML files in it and in its subdirectories, sorted by
+/// path, so that the load order does not depend on the file system. Hidden files are skipped.
+fn rules_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).context(format!(
+        "could not read the rules directory {}",
+        path.display()
+    ))? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let entry_path = entry.path();
+        if entry_path.is_dir() {
+            files.extend(rules_files(&entry_path)?);
+        } else if RulesFormat::from_extension(&entry_path).is_some() {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Gets the last modification time of the configured rules files and directories, so that the
+/// rules can be reloaded when they change.
+pub(crate) fn rules_modified(config: &Config) -> Option<SystemTime> {
+    config
+        .rules_json()
+        .iter()
+        .flat_map(|path| {
+            let mut paths = rules_files(path).unwrap_or_default();
+            paths.push(path.clone());
+            paths
+        })
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Loads the code analysis rules, weighted for the given application category.
+fn load_weighted_rules(config: &Config, category: Option<Category>) -> Result<Vec<Rule>, Error> {
+    let mut rules = read_rules(config)?;
+    if let Some(profile) = config.profile() {
+        rules.retain(|rule| profile.checks_rule(rule.tags()));
+    }
+    if let Some(category) = category {
+        for rule in &mut rules {
+            rule.apply_category_weights(config, category);
+        }
+    }
+    let rules =
+        rules
+            .into_iter()
+            .filter_map(|rule| {
+                if rule.criticality >= config.min_criticality() {
                     let fc1_in_regex = rule.regex().capture_names().any(|c| c == Some("fc1"));
                     let fc2_in_regex = rule.regex().capture_names().any(|c| c == Some("fc2"));
 
@@ -605,8 +1495,7 @@ fn load_rules(config: &Config) -> Result<Vec<Rule>, Error> {
                     None
                 }
             })
-            .collect::<Result<Vec<Rule>, Error>>()
-            .context(format_error)?;
+            .collect::<Result<Vec<Rule>, Error>>()?;
 
     Ok(rules)
 }
@@ -617,8 +1506,13 @@ mod tests {
     use failure::Error;
     use regex::Regex;
 
-    use super::{load_rules, Rule};
-    use crate::{config::Config, criticality::Criticality};
+    use std::{fs, path::Path};
+
+    use super::{
+        code_package, lines_after, lines_before, load_rules, parse_rules, read_rules, Examples,
+        Rule, RulesFormat, Scope,
+    };
+    use crate::{config::Config, criticality::Criticality, static_analysis::category::Category};
 
     /// Prints information about the given error.
     fn print_error(e: &Error) {
@@ -699,6 +1593,31 @@ mod tests {
         }
     }
 
+    /// Checks that every default rule has a unique ID, and valid CWE and OWASP MASVS metadata.
+    #[test]
+    fn it_rule_metadata() {
+        let rules = load_rules(&Config::default()).unwrap();
+        let mut ids = Vec::new();
+        for rule in &rules {
+            let id = rule.id().expect("every default rule must have an ID");
+            assert!(!ids.contains(&id), "duplicated rule ID `{}`", id);
+            ids.push(id);
+
+            let cwe = rule
+                .cwe()
+                .unwrap_or_else(|| panic!("the rule `{}` has no CWE", id));
+            assert!(cwe > 0, "the rule `{}` has an invalid CWE", id);
+            let owasp_masvs = rule
+                .owasp_masvs()
+                .unwrap_or_else(|| panic!("the rule `{}` has no OWASP MASVS control", id));
+            assert!(
+                owasp_masvs.starts_with("MASVS-"),
+                "the rule `{}` has an invalid OWASP MASVS control",
+                id
+            );
+        }
+    }
+
     #[test]
     fn it_url_regex() {
         let config = Config::default();
@@ -1106,6 +2025,7 @@ mod tests {
 
         let should_match = &[
             "MODE_WORLD_WRITABLE",
+            "Context.MODE_WORLD_WRITEABLE",
             "openFileOutput(\"file.txt  \", 2) ",
             "openFileOutput(\"filename\", 2) ",
             "openFileOutput(filepath, 2) ",
@@ -1477,7 +2397,273 @@ mod tests {
 
         let should_match = &["telephony.TelephonyManager      getSimSerialNumber()"];
 
-        let should_not_match = &["getSimSerialNumber()", "telephony.TelephonyManager"];
+        let should_not_match = &["getSimSerialNumber()", "telephony.TelephonyManager"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_gps_location() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[24];
+
+        let should_match = &[
+            "android.location   getLastKnownLocation()",
+            "android.location   requestLocationUpdates()",
+            "android.location   getLatitude()",
+            "android.location   getLongitude()",
+        ];
+
+        let should_not_match = &[
+            "getLastKnownLocation()",
+            "requestLocationUpdates()",
+            "getLatitude()",
+            "getLongitude()",
+            "android.location",
+        ];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_base64_encode() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[25];
+
+        let should_match = &[
+            "android.util.Base64 .encodeToString()",
+            "android.util.Base64    .encode()",
+        ];
+
+        let should_not_match = &[".encodeToString()", ".encode()", "android.util.Base64"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_base64_decoding() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[26];
+
+        let should_match = &["android.util.Base64   .decode()"];
+
+        let should_not_match = &["android.util.Base64", ".decode()"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_infinite_loop() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[27];
+
+        let should_match = &["while(true)"];
+
+        let should_not_match = &["while(i<10)"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_email_disclosure() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[28];
+
+        let should_match = &[
+            "super@super.es",
+            "android_analizer@dem.co.uk",
+            "foo@mywebpage.com",
+            "android-rust69@tux.rox",
+        ];
+
+        let should_not_match = &["@", "@strings/", "@id/user.id", "android:id=\"@id/userid\""];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_hardcoded_certificate() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[29];
+
+        let should_match = &[
+            "\"key.key              ",
+            "\"cert.cert\"",
+            "\"    key.pub    ",
+            "\"    cert.pub   ",
+            "     throw new IllegalArgumentException(\"translateAPI.key is not \
+             specified\");",
+        ];
+
+        let should_not_match = &[
+            "Iterator localIterator = paramBundle.keySet().iterator();",
+            "import java.security.cert.X509Certificate;",
+            "",
+            "",
+        ];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_get_sim_operator() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[30];
+
+        let should_match = &["telephony.TelephonyManager      getSimOperator()"];
+
+        let should_not_match = &["getSimOperator()", "telephony.TelephonyManager"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_get_sim_operator_name() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[31];
+
+        let should_match = &["telephony.TelephonyManager      getSimOperatorName()"];
+
+        let should_not_match = &["getSimOperatorName()", "telephony.TelephonyManager"];
+
+        for m in should_match {
+            assert!(check_match(m, rule));
+        }
+
+        for m in should_not_match {
+            assert!(!check_match(m, rule));
+        }
+    }
+
+    #[test]
+    fn it_obfuscation() {
+        let config = Config::default();
+        let rules = match load_rules(&config) {
+            Ok(r) => r,
+            Err(e) => {
+                print_error(&e);
+                panic!()
+            }
+        };
+        let rule = &rules[32];
+
+        let should_match = &[
+            "android.utils.AESObfuscator getObfuscator();",
+            "android.utils.AESObfuscator   obfuscation.getObfuscator();",
+            "utils.AESObfuscator getObfuscator();",
+            "utils.AESObfuscator   obfuscation.getObfuscator();",
+        ];
+
+        let should_not_match = &[
+            "AESObfuscator  getObfuscator();",
+            "android.utils.AESObfuscator   obfuscation",
+            "getObfuscator();",
+            "android.utils.AESObfuscator",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1489,7 +2675,7 @@ mod tests {
     }
 
     #[test]
-    fn it_gps_location() {
+    fn it_command_exec() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1498,21 +2684,20 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[24];
+        let rule = &rules[33];
 
         let should_match = &[
-            "android.location   getLastKnownLocation()",
-            "android.location   requestLocationUpdates()",
-            "android.location   getLatitude()",
-            "android.location   getLongitude()",
+            "Runtime.getRuntime().exec(\"command\", options);",
+            "getRuntime().exec(\"ls -la\", options);",
+            "Runtime.getRuntime().exec(\"ls -la\", options);",
+            "getRuntime().exec(\"ps -l\", options);",
         ];
 
         let should_not_match = &[
-            "getLastKnownLocation()",
-            "requestLocationUpdates()",
-            "getLatitude()",
-            "getLongitude()",
-            "android.location",
+            "Runtime.getRuntime()(\"\", options);",
+            "getRuntime()(\"\", options);",
+            "Runtime.getRuntime()(\"\", options);",
+            "getRuntime()(\"\", options);",
         ];
 
         for m in should_match {
@@ -1525,7 +2710,7 @@ mod tests {
     }
 
     #[test]
-    fn it_base64_encode() {
+    fn it_ssl_get_insecure_method() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1534,14 +2719,17 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[25];
+        let rule = &rules[34];
 
-        let should_match = &[
-            "android.util.Base64 .encodeToString()",
-            "android.util.Base64    .encode()",
-        ];
+        let should_match = &[" javax.net.ssl.SSLSocketFactory                 \
+                              SSLSocketFactory.getInsecure()"];
 
-        let should_not_match = &[".encodeToString()", ".encode()", "android.util.Base64"];
+        let should_not_match = &[
+            "getInsecure()",
+            "javax.net.ssl.SSL  getInsecure();",
+            "javax.net.ssl.SSLSocketFactory",
+            "net.ssl.SSL getSecure();",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1553,7 +2741,7 @@ mod tests {
     }
 
     #[test]
-    fn it_base64_decoding() {
+    fn it_finally_with_return() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1562,11 +2750,18 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[26];
+        let rule = &rules[35];
 
-        let should_match = &["android.util.Base64   .decode()"];
+        let should_match = &[
+            "finally {                      return;",
+            "finally {                      return;}",
+        ];
 
-        let should_not_match = &["android.util.Base64", ".decode()"];
+        let should_not_match = &[
+            "finally{}",
+            "finally{ var;}",
+            "finally { Printf (“Hello”); return true; }",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1578,7 +2773,7 @@ mod tests {
     }
 
     #[test]
-    fn it_infinite_loop() {
+    fn it_sleep_method_not_validated() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1587,11 +2782,19 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[27];
+        let rule = &rules[36];
 
-        let should_match = &["while(true)"];
+        let should_match = &[
+            "int var = EditText.getText  Thread.sleep(100 + var);",
+            "var = .getText  Thread.sleep(100 + var);",
+        ];
 
-        let should_not_match = &["while(i<10)"];
+        let should_not_match = &[
+            "int var4 = EditText.getText  Thread.sleep(100 + var);",
+            "var = .getText  Thread.sleep(100 + hola);",
+            "",
+            "",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1603,7 +2806,7 @@ mod tests {
     }
 
     #[test]
-    fn it_email_disclosure() {
+    fn it_weak_random_token() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1612,16 +2815,19 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[28];
+        let rule = &rules[37];
 
         let should_match = &[
-            "super@super.es",
-            "android_analizer@dem.co.uk",
-            "foo@mywebpage.com",
-            "android-rust69@tux.rox",
+            "String token = Integer.toString(new Random().nextInt(999999));",
+            "sessionId = String.valueOf(Math.random());",
+            "int otpCode = (int) (Math.random() * 9000) + 1000;",
         ];
 
-        let should_not_match = &["@", "@strings/", "@id/user.id", "android:id=\"@id/userid\""];
+        let should_not_match = &[
+            "int delay = (int) (Math.random() * 1000);",
+            "String token = new BigInteger(130, new SecureRandom()).toString(32);",
+            "int color = new Random().nextInt(255);",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1633,7 +2839,7 @@ mod tests {
     }
 
     #[test]
-    fn it_hardcoded_certificate() {
+    fn it_weak_random_cryptographic_material() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1642,22 +2848,17 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[29];
+        let rule = &rules[38];
 
         let should_match = &[
-            "\"key.key              ",
-            "\"cert.cert\"",
-            "\"    key.pub    ",
-            "\"    cert.pub   ",
-            "     throw new IllegalArgumentException(\"translateAPI.key is not \
-             specified\");",
+            "Random random = new Random(); String sessionToken = Long.toHexString(random.nextLong());",
+            "Random rnd = new java.util.Random(); rnd.nextBytes(ivBytes);",
         ];
 
         let should_not_match = &[
-            "Iterator localIterator = paramBundle.keySet().iterator();",
-            "import java.security.cert.X509Certificate;",
-            "",
-            "",
+            "Random random = new Random(); int index = random.nextInt(items.size());",
+            "Random rnd = new Random(); rnd.nextBytes(noise);",
+            "SecureRandom random = new SecureRandom(); String token = Long.toHexString(random.nextLong());",
         ];
 
         for m in should_match {
@@ -1670,7 +2871,7 @@ mod tests {
     }
 
     #[test]
-    fn it_get_sim_operator() {
+    fn it_insecure_deserialization() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1679,11 +2880,19 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[30];
+        let rule = &rules[39];
 
-        let should_match = &["telephony.TelephonyManager      getSimOperator()"];
+        let should_match = &[
+            "ObjectInputStream in = new ObjectInputStream(socket.getInputStream()); Object o = in.readObject();",
+            "ObjectInputStream ois = new ObjectInputStream(new FileInputStream(file));\nUser user = (User) ois.readObject();",
+            "ois = new ObjectInputStream(new ByteArrayInputStream(intent.getByteArrayExtra(\"data\"))); ois.readObject();",
+        ];
 
-        let should_not_match = &["getSimOperator()", "telephony.TelephonyManager"];
+        let should_not_match = &[
+            "ObjectInputStream in = new ObjectInputStream(socket.getInputStream()); in.close();",
+            "ObjectOutputStream out = new ObjectOutputStream(socket.getOutputStream()); out.writeObject(o);",
+            "ObjectInputStream in = new ObjectInputStream(getResources().openRawResource(R.raw.data)); in.readObject();",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1695,7 +2904,7 @@ mod tests {
     }
 
     #[test]
-    fn it_get_sim_operator_name() {
+    fn it_unsafe_parcel_deserialization() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1704,11 +2913,19 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[31];
+        let rule = &rules[40];
 
-        let should_match = &["telephony.TelephonyManager      getSimOperatorName()"];
+        let should_match = &[
+            "User user = (User) getIntent().getSerializableExtra(\"user\");",
+            "parcel.unmarshall(bytes, 0, bytes.length);",
+            "Object value = in.readSerializable();",
+        ];
 
-        let should_not_match = &["getSimOperatorName()", "telephony.TelephonyManager"];
+        let should_not_match = &[
+            "String name = getIntent().getStringExtra(\"name\");",
+            "User user = in.readParcelable(User.class.getClassLoader());",
+            "dest.writeSerializable(value);",
+        ];
 
         for m in should_match {
             assert!(check_match(m, rule));
@@ -1720,7 +2937,7 @@ mod tests {
     }
 
     #[test]
-    fn it_obfuscation() {
+    fn it_polymorphic_json_deserialization() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1729,20 +2946,19 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[32];
+        let rule = &rules[41];
 
         let should_match = &[
-            "android.utils.AESObfuscator getObfuscator();",
-            "android.utils.AESObfuscator   obfuscation.getObfuscator();",
-            "utils.AESObfuscator getObfuscator();",
-            "utils.AESObfuscator   obfuscation.getObfuscator();",
+            "mapper.enableDefaultTyping();",
+            "objectMapper.activateDefaultTyping(ptv, ObjectMapper.DefaultTyping.NON_FINAL);",
+            "@JsonTypeInfo(use = JsonTypeInfo.Id.CLASS, include = JsonTypeInfo.As.PROPERTY)",
+            "Class<?> clazz = Class.forName(json.getAsJsonObject().get(\"type\").getAsString());",
         ];
 
         let should_not_match = &[
-            "AESObfuscator  getObfuscator();",
-            "android.utils.AESObfuscator   obfuscation",
-            "getObfuscator();",
-            "android.utils.AESObfuscator",
+            "@JsonTypeInfo(use = JsonTypeInfo.Id.NAME, include = JsonTypeInfo.As.PROPERTY)",
+            "Class<?> clazz = Class.forName(\"com.example.Model\");",
+            "User user = gson.fromJson(json, User.class);",
         ];
 
         for m in should_match {
@@ -1755,7 +2971,7 @@ mod tests {
     }
 
     #[test]
-    fn it_command_exec() {
+    fn it_xxe_dom_parser() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1764,20 +2980,17 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[33];
+        let rule = &rules[42];
 
         let should_match = &[
-            "Runtime.getRuntime().exec(\"command\", options);",
-            "getRuntime().exec(\"ls -la\", options);",
-            "Runtime.getRuntime().exec(\"ls -la\", options);",
-            "getRuntime().exec(\"ps -l\", options);",
+            "DocumentBuilderFactory factory = DocumentBuilderFactory.newInstance();\nDocumentBuilder builder = factory.newDocumentBuilder();",
+            "DocumentBuilder builder = DocumentBuilderFactory.newInstance().newDocumentBuilder();",
         ];
 
         let should_not_match = &[
-            "Runtime.getRuntime()(\"\", options);",
-            "getRuntime()(\"\", options);",
-            "Runtime.getRuntime()(\"\", options);",
-            "getRuntime()(\"\", options);",
+            "DocumentBuilderFactory factory = DocumentBuilderFactory.newInstance();\nfactory.setFeature(\"http://apache.org/xml/features/disallow-doctype-decl\", true);\nDocumentBuilder builder = factory.newDocumentBuilder();",
+            "DocumentBuilderFactory f = DocumentBuilderFactory.newInstance();\nf.setFeature(XMLConstants.FEATURE_SECURE_PROCESSING, true);\nf.newDocumentBuilder();",
+            "DocumentBuilderFactory factory = DocumentBuilderFactory.newInstance();",
         ];
 
         for m in should_match {
@@ -1790,7 +3003,7 @@ mod tests {
     }
 
     #[test]
-    fn it_ssl_get_insecure_method() {
+    fn it_xxe_sax_parser() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1799,16 +3012,17 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[34];
+        let rule = &rules[43];
 
-        let should_match = &[" javax.net.ssl.SSLSocketFactory                 \
-                              SSLSocketFactory.getInsecure()"];
+        let should_match = &[
+            "SAXParserFactory spf = SAXParserFactory.newInstance();\nSAXParser parser = spf.newSAXParser();",
+            "XMLReader reader = XMLReaderFactory.createXMLReader();\nreader.setContentHandler(handler);\nreader.parse(new InputSource(in));",
+        ];
 
         let should_not_match = &[
-            "getInsecure()",
-            "javax.net.ssl.SSL  getInsecure();",
-            "javax.net.ssl.SSLSocketFactory",
-            "net.ssl.SSL getSecure();",
+            "SAXParserFactory spf = SAXParserFactory.newInstance();\nspf.setFeature(\"http://xml.org/sax/features/external-general-entities\", false);\nSAXParser parser = spf.newSAXParser();",
+            "XMLReader reader = XMLReaderFactory.createXMLReader();\nreader.setFeature(\"http://apache.org/xml/features/disallow-doctype-decl\", true);\nreader.parse(source);",
+            "XmlPullParser parser = Xml.newPullParser();",
         ];
 
         for m in should_match {
@@ -1821,7 +3035,7 @@ mod tests {
     }
 
     #[test]
-    fn it_finally_with_return() {
+    fn it_xxe_stax_parser() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1830,17 +3044,15 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[35];
+        let rule = &rules[44];
 
         let should_match = &[
-            "finally {                      return;",
-            "finally {                      return;}",
+            "XMLInputFactory factory = XMLInputFactory.newInstance();\nXMLStreamReader reader = factory.createXMLStreamReader(in);",
         ];
 
         let should_not_match = &[
-            "finally{}",
-            "finally{ var;}",
-            "finally { Printf (“Hello”); return true; }",
+            "XMLInputFactory factory = XMLInputFactory.newInstance();\nfactory.setProperty(XMLInputFactory.SUPPORT_DTD, false);\nXMLStreamReader reader = factory.createXMLStreamReader(in);",
+            "XMLOutputFactory factory = XMLOutputFactory.newInstance();\nfactory.createXMLStreamWriter(out);",
         ];
 
         for m in should_match {
@@ -1853,7 +3065,7 @@ mod tests {
     }
 
     #[test]
-    fn it_sleep_method_not_validated() {
+    fn it_zip_path_traversal() {
         let config = Config::default();
         let rules = match load_rules(&config) {
             Ok(r) => r,
@@ -1862,18 +3074,17 @@ mod tests {
                 panic!()
             }
         };
-        let rule = &rules[36];
+        let rule = &rules[45];
 
         let should_match = &[
-            "int var = EditText.getText  Thread.sleep(100 + var);",
-            "var = .getText  Thread.sleep(100 + var);",
+            "ZipEntry entry;\nwhile ((entry = zis.getNextEntry()) != null) {\n    File file = new File(destination, entry.getName());\n    FileOutputStream out = new FileOutputStream(file);\n}",
+            "ZipEntry ze = zin.getNextEntry();\nString path = dir + File.separator + ze.getName();\nFileOutputStream fout = new FileOutputStream(path);",
         ];
 
         let should_not_match = &[
-            "int var4 = EditText.getText  Thread.sleep(100 + var);",
-            "var = .getText  Thread.sleep(100 + hola);",
-            "",
-            "",
+            "ZipEntry entry;\nwhile ((entry = zis.getNextEntry()) != null) {\n    File file = new File(destination, entry.getName());\n    if (!file.getCanonicalPath().startsWith(destination.getCanonicalPath())) {\n        throw new SecurityException();\n    }\n}",
+            "ZipEntry ze = zin.getNextEntry();\nString name = ze.getName();\nif (name.contains(\"..\")) { continue; }",
+            "File file = new File(directory, child.getName());",
         ];
 
         for m in should_match {
@@ -1888,9 +3099,17 @@ mod tests {
     #[test]
     fn it_has_to_check_rule_if_exclude_and_include_regexp_are_not_provided() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1898,6 +3117,13 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: None,
             exclude_file_regex: None,
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(rule.has_to_check("filename.xml"));
@@ -1906,9 +3132,17 @@ mod tests {
     #[test]
     fn it_has_to_check_rule_if_include_regexp_is_match_and_exclude_not_provided() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1916,6 +3150,13 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
             exclude_file_regex: None,
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(rule.has_to_check("filename.xml"));
@@ -1924,9 +3165,17 @@ mod tests {
     #[test]
     fn it_does_not_have_to_check_rule_if_include_regexp_is_non_match_and_exclude_not_provided() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1934,6 +3183,13 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
             exclude_file_regex: None,
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(!rule.has_to_check("filename.yml"));
@@ -1942,9 +3198,17 @@ mod tests {
     #[test]
     fn it_has_to_check_rule_if_include_regexp_is_match_and_exclude_not() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1952,6 +3216,13 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
             exclude_file_regex: Some(Regex::new(r"non_matching").unwrap()),
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(rule.has_to_check("filename.xml"));
@@ -1960,9 +3231,17 @@ mod tests {
     #[test]
     fn it_does_not_have_to_check_rule_if_exclude_is_match() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1970,6 +3249,13 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: Some(Regex::new(r"non_matching").unwrap()),
             exclude_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(!rule.has_to_check("filename.xml"));
@@ -1978,9 +3264,17 @@ mod tests {
     #[test]
     fn it_does_not_have_to_check_if_both_regexps_matches() {
         let rule = Rule {
+            id: None,
             regex: Regex::new("").unwrap(),
             permissions: Box::new([]),
             forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
             max_sdk: None,
             whitelist: Box::new([]),
             label: String::new(),
@@ -1988,8 +3282,508 @@ mod tests {
             criticality: Criticality::Warning,
             include_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
             exclude_file_regex: Some(Regex::new(r".*\.xml").unwrap()),
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
         };
 
         assert!(!rule.has_to_check("filename.xml"));
     }
+
+    #[test]
+    fn it_has_to_check_rule_by_path() {
+        let rule = Rule {
+            id: None,
+            regex: Regex::new("").unwrap(),
+            permissions: Box::new([]),
+            forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
+            max_sdk: None,
+            whitelist: Box::new([]),
+            label: String::new(),
+            description: String::new(),
+            criticality: Criticality::Warning,
+            include_file_regex: Some(Regex::new(r"^classes/.*\.java$").unwrap()),
+            exclude_file_regex: Some(Regex::new(r"^classes/(android|com/google)/").unwrap()),
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
+        };
+
+        assert!(rule.has_to_check("classes/com/example/Main.java"));
+        assert!(!rule.has_to_check("classes/com/google/gson/Gson.java"));
+        assert!(!rule.has_to_check("classes/android/support/v4/app/Fragment.java"));
+        assert!(!rule.has_to_check("res/layout/main.xml"));
+        assert!(!rule.has_to_check("payloads/classes/com/example/Main.java"));
+    }
+
+    #[test]
+    fn it_rules_format() {
+        assert_eq!(
+            RulesFormat::from_path(Path::new("rules.json")),
+            RulesFormat::Json
+        );
+        assert_eq!(
+            RulesFormat::from_path(Path::new("rules.yaml")),
+            RulesFormat::Yaml
+        );
+        assert_eq!(
+            RulesFormat::from_path(Path::new("rules.YML")),
+            RulesFormat::Yaml
+        );
+        assert_eq!(RulesFormat::from_extension(Path::new("README.md")), None);
+    }
+
+    /// Checks the loading of a directory of rules files, in path order.
+    #[test]
+    fn it_rules_dir() {
+        let folder = Path::new("target/rules_dir_test");
+        fs::create_dir_all(folder.join("network")).unwrap();
+        let rule = |id: &str| {
+            format!(
+                "- id: {}\n  label: {}\n  description: Test\n  criticality: low\n  regex: {}\n",
+                id, id, id
+            )
+        };
+        fs::write(folder.join("20-storage.yaml"), rule("storage")).unwrap();
+        fs::write(
+            folder.join("10-crypto.yml"),
+            format!("{}{}", rule("cipher"), rule("hash")),
+        )
+        .unwrap();
+        fs::write(folder.join("network/tls.yaml"), rule("tls")).unwrap();
+        fs::write(folder.join(".draft.yaml"), "not: [valid").unwrap();
+        fs::write(folder.join("README.md"), "# Rules").unwrap();
+
+        let config: Config =
+            toml::from_str(&format!(r#"rules_json = ["{}"]"#, folder.display())).unwrap();
+        let ids: Vec<_> = read_rules(&config)
+            .unwrap()
+            .iter()
+            .filter_map(Rule::id)
+            .map(str::to_owned)
+            .collect();
+        assert_eq!(ids, vec!["cipher", "hash", "storage", "tls"]);
+
+        fs::write(folder.join("30-legacy.yaml"), rule("hash")).unwrap();
+        let error = read_rules(&config).unwrap_err().to_string();
+        assert!(error.contains("the `hash` rule is defined in both"));
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn it_parse_yaml_rules() {
+        let rules = parse_rules(
+            r#"
+- label: Weak hash
+  description: >
+    MD5 is not a secure hash algorithm, and it should not be used to protect
+    sensitive data.
+  criticality: medium
+  regex: 'MessageDigest\.getInstance\("MD5"\)'
+  include_file_regex: \.java$
+  tags: [crypto]
+  section: Cryptography
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .rules;
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].label(), "Weak hash");
+        assert!(rules[0]
+            .description()
+            .starts_with("MD5 is not a secure hash algorithm, and"));
+        assert!(rules[0]
+            .regex()
+            .is_match(r#"MessageDigest.getInstance("MD5")"#));
+        assert!(rules[0].has_to_check("classes/com/example/Hash.java"));
+        assert!(!rules[0].has_to_check("res/values/strings.xml"));
+        assert_eq!(rules[0].section(), Some("Cryptography"));
+    }
+
+    /// Checks the testing of the rules against their code examples.
+    #[test]
+    fn it_test_examples() {
+        let rules = parse_rules(
+            r#"
+- label: Weak hash
+  description: MD5 is not a secure hash algorithm.
+  criticality: medium
+  regex: 'MessageDigest\.getInstance\("MD5"\)'
+  examples:
+    positive:
+      - 'MessageDigest.getInstance("MD5")'
+    negative:
+      - 'MessageDigest.getInstance("SHA-256")'
+- label: Broken rule
+  description: The examples don't match the regular expression.
+  criticality: low
+  regex: 'Log\.d\([^,]+,'
+  whitelist: ['Log\.d\(TAG']
+  examples:
+    positive:
+      - 'Log.v(TAG, password);'
+    negative:
+      - 'Log.d(TAG, "started");'
+      - 'Log.d("password", password);'
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .rules;
+
+        assert!(rules[0].has_examples());
+        assert!(rules[0].test_examples().is_empty());
+
+        let failures = rules[1].test_examples();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0],
+            "the positive example `Log.v(TAG, password);` is not reported"
+        );
+        assert_eq!(
+            failures[1],
+            "the negative example `Log.d(\"password\", password);` is reported"
+        );
+
+        assert!(parse_rules(
+            "- label: Test\n  description: Test\n  criticality: low\n  regex: test\n  \
+             examples:\n    matches: [test]\n"
+                .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .is_err());
+    }
+
+    /// Checks the context checks of the rules, within a number of lines, and multiline rules.
+    #[test]
+    fn it_context_checks() {
+        let rules = parse_rules(
+            r#"
+- label: CBC without IV
+  description: The cipher is initialized without an IV.
+  criticality: medium
+  regex: 'Cipher\.getInstance\("AES/CBC'
+  forward_absent: 'IvParameterSpec'
+  within_lines: 2
+- label: Unchecked ECB
+  description: ECB mode is used after a key check.
+  criticality: high
+  regex: 'Cipher\.getInstance\("AES/ECB'
+  backward_check: 'SecretKeySpec'
+  within_lines: 1
+- label: Empty catch
+  description: An exception is ignored.
+  criticality: low
+  regex: 'catch \(\w+ e\) \{\s*\}'
+  multiline: true
+- label: Empty block
+  description: A block is empty.
+  criticality: low
+  regex: '^\{$.^\}$'
+  multiline: true
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .rules;
+
+        let without_iv = "Cipher c = Cipher.getInstance(\"AES/CBC/PKCS5Padding\");\n\
+                          c.init(Cipher.ENCRYPT_MODE, key);\n";
+        assert_eq!(rules[0].find_matches(without_iv).unwrap().len(), 1);
+        let with_iv = "Cipher c = Cipher.getInstance(\"AES/CBC/PKCS5Padding\");\n\
+                       IvParameterSpec iv = new IvParameterSpec(bytes);\n";
+        assert!(rules[0].find_matches(with_iv).unwrap().is_empty());
+        let far_iv = "Cipher c = Cipher.getInstance(\"AES/CBC/PKCS5Padding\");\n\n\n\
+                      IvParameterSpec iv = new IvParameterSpec(bytes);\n";
+        assert_eq!(rules[0].find_matches(far_iv).unwrap().len(), 1);
+
+        let with_key = "SecretKeySpec key = new SecretKeySpec(bytes, \"AES\");\n\
+                        Cipher c = Cipher.getInstance(\"AES/ECB/PKCS5Padding\");\n";
+        assert_eq!(rules[1].find_matches(with_key).unwrap().len(), 1);
+        let far_key = "SecretKeySpec key = new SecretKeySpec(bytes, \"AES\");\n\n\
+                       Cipher c = Cipher.getInstance(\"AES/ECB/PKCS5Padding\");\n";
+        assert!(rules[1].find_matches(far_key).unwrap().is_empty());
+
+        let empty_catch = "try {\n    run();\n} catch (Exception e) {\n}\n";
+        assert_eq!(rules[2].find_matches(empty_catch).unwrap().len(), 1);
+        assert_eq!(rules[3].find_matches("if (a)\n{\n}\n").unwrap().len(), 1);
+
+        let code = "one\ntwo\nthree\nfour\nfive";
+        let index = code.find("three").unwrap();
+        assert_eq!(lines_before(code, index, Some(1)), "two\n");
+        assert_eq!(lines_before(code, index, Some(0)), "");
+        assert_eq!(lines_before(code, index, None), "one\ntwo\n");
+        assert_eq!(lines_after(code, index + 5, Some(1)), "\nfour");
+        assert_eq!(lines_after(code, index + 5, Some(0)), "");
+        assert_eq!(lines_after(code, index + 5, None), "\nfour\nfive");
+    }
+
+    /// Checks the rules with a source, that must be found before the match in the same scope.
+    #[test]
+    fn it_source_sink() {
+        let rules = parse_rules(
+            r#"
+- label: Intent to WebView
+  description: An Intent extra is loaded in a WebView.
+  criticality: high
+  source: 'getStringExtra\('
+  regex: '\.loadUrl\('
+- label: Intent field to WebView
+  description: An Intent extra is loaded in a WebView.
+  criticality: high
+  source: 'getStringExtra\('
+  regex: '\.loadUrl\('
+  scope: class
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .rules;
+
+        let same_method = "class Main {\n    void onCreate() {\n        \
+                           String url = getIntent().getStringExtra(\"url\");\n        \
+                           webView.loadUrl(url);\n    }\n}\n";
+        let matches = rules[0].find_matches(same_method).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            &same_method[matches[0].0..matches[0].1],
+            "getStringExtra(\"url\");\n        webView.loadUrl("
+        );
+
+        let other_method = "class Main {\n    void onCreate() {\n        \
+                            url = getIntent().getStringExtra(\"url\");\n    }\n    \
+                            void onResume() {\n        webView.loadUrl(url);\n    }\n}\n";
+        assert!(rules[0].find_matches(other_method).unwrap().is_empty());
+        assert_eq!(rules[1].find_matches(other_method).unwrap().len(), 1);
+
+        let sink_first = "class Main {\n    void onCreate() {\n        \
+                          webView.loadUrl(url);\n        \
+                          url = getIntent().getStringExtra(\"url\");\n    }\n}\n";
+        assert!(rules[0].find_matches(sink_first).unwrap().is_empty());
+
+        assert_eq!(Scope::Method.block_start(same_method, 0), None);
+        assert_eq!(Scope::Class.block_start(same_method, 20), Some(11));
+        assert_eq!(Scope::Method.block_start(same_method, 40), Some(33));
+    }
+
+    /// Checks that the library cache key of a rule only depends on where it matches.
+    #[test]
+    fn it_cache_key() {
+        let rules = parse_rules(
+            r#"
+- label: Logging
+  description: The application logs information.
+  criticality: low
+  regex: 'Log\.[dv]\('
+- label: Debug logging
+  description: The application logs debug information.
+  criticality: warning
+  regex: 'Log\.[dv]\('
+- label: Logging
+  description: The application logs information.
+  criticality: low
+  regex: 'Log\.[dv]\('
+  exclude_packages:
+    - android.support.**
+- label: Logging
+  description: The application logs information.
+  criticality: low
+  regex: 'Log\.[div]\('
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .rules;
+
+        assert_eq!(rules[0].cache_key().len(), 64);
+        assert_eq!(rules[0].cache_key(), rules[1].cache_key());
+        assert_ne!(rules[0].cache_key(), rules[2].cache_key());
+        assert_ne!(rules[0].cache_key(), rules[3].cache_key());
+    }
+
+    #[test]
+    fn it_rejects_unknown_rule_keys() {
+        let yaml_error = parse_rules(
+            "- label: Test\n  descripton: Test\n  criticality: low\n  regex: test\n".as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(yaml_error.contains("unknown field `descripton`"));
+        assert!(yaml_error.contains("line"));
+
+        let json_error = parse_rules(
+            r#"[{
+                "label": "Test",
+                "description": "Test",
+                "criticallity": "low",
+                "regex": "test"
+            }]"#
+            .as_bytes(),
+            RulesFormat::Json,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(json_error.contains("unknown field `criticallity`"));
+        assert!(json_error.contains("line 4"));
+    }
+
+    #[test]
+    fn it_rule_sets() {
+        let mut rules = Vec::new();
+        parse_rules(
+            r#"[
+                {"id": "weak-hash", "label": "Weak hash", "description": "Base",
+                 "criticality": "medium", "regex": "MD5"},
+                {"id": "logging", "label": "Logging", "description": "Base",
+                 "criticality": "low", "regex": "Log\\.d"},
+                {"label": "Unnamed", "description": "Base", "criticality": "low", "regex": "a"}
+            ]"#
+            .as_bytes(),
+            RulesFormat::Json,
+        )
+        .unwrap()
+        .apply(&mut rules, Path::new("base.json"));
+        parse_rules(
+            r#"
+disable: [logging]
+rules:
+  - id: weak-hash
+    label: Weak hash
+    description: Project
+    criticality: high
+    regex: MD5|SHA1
+  - id: project
+    label: Project rule
+    description: Project
+    criticality: warning
+    regex: TODO
+"#
+            .as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap()
+        .apply(&mut rules, Path::new("project.yaml"));
+
+        let labels: Vec<_> = rules.iter().map(Rule::label).collect();
+        assert_eq!(labels, vec!["Weak hash", "Unnamed", "Project rule"]);
+        assert_eq!(rules[0].criticality(), Criticality::High);
+        assert_eq!(rules[0].description(), "Project");
+        assert_eq!(rules[2].id(), Some("project"));
+
+        let error = parse_rules(
+            "rules: []\nenable: [logging]\n".as_bytes(),
+            RulesFormat::Yaml,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("unknown field `enable`"));
+    }
+
+    #[test]
+    fn it_code_package() {
+        assert_eq!(
+            code_package(Path::new("classes/com/example/app/Main.java")),
+            Some("com.example.app".to_owned())
+        );
+        assert_eq!(code_package(Path::new("classes/Main.java")), None);
+        assert_eq!(code_package(Path::new("res/layout/main.xml")), None);
+    }
+
+    #[test]
+    fn it_has_to_check_package() {
+        let rule = Rule {
+            id: None,
+            regex: Regex::new("").unwrap(),
+            permissions: Box::new([]),
+            forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
+            max_sdk: None,
+            whitelist: Box::new([]),
+            label: String::new(),
+            description: String::new(),
+            criticality: Criticality::Warning,
+            include_file_regex: None,
+            exclude_file_regex: None,
+            include_packages: Box::new(["com.example.**".to_owned()]),
+            exclude_packages: Box::new(["com.example.vendor.**".to_owned()]),
+            tags: Box::new([]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
+        };
+
+        assert!(rule.has_to_check_package(Some("com.example.app")));
+        assert!(!rule.has_to_check_package(Some("com.example.vendor.sdk")));
+        assert!(!rule.has_to_check_package(Some("androidx.core")));
+        assert!(!rule.has_to_check_package(None));
+    }
+
+    #[test]
+    fn it_applies_category_weights() {
+        let config = Config::default();
+        let mut rule = Rule {
+            id: None,
+            regex: Regex::new("").unwrap(),
+            permissions: Box::new([]),
+            forward_check: None,
+            backward_check: None,
+            forward_absent: None,
+            backward_absent: None,
+            within_lines: None,
+            multiline: false,
+            source: None,
+            scope: Scope::Method,
+            max_sdk: None,
+            whitelist: Box::new([]),
+            label: String::new(),
+            description: String::new(),
+            criticality: Criticality::Medium,
+            include_file_regex: None,
+            exclude_file_regex: None,
+            include_packages: Box::new([]),
+            exclude_packages: Box::new([]),
+            tags: Box::new(["crypto".to_owned(), "tls".to_owned()]),
+            section: None,
+            cwe: None,
+            owasp_masvs: None,
+            examples: Examples::default(),
+        };
+
+        rule.apply_category_weights(&config, Category::Game);
+        assert_eq!(rule.criticality(), Criticality::Medium);
+
+        rule.apply_category_weights(&config, Category::Banking);
+        assert_eq!(rule.criticality(), Criticality::High);
+    }
 }
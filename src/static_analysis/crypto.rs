@@ -0,0 +1,320 @@
+//! Cryptography misuse analysis module.
+//!
+//! The Java cryptography APIs make it easy to encrypt data insecurely: the default mode of block
+//! ciphers is ECB, the initialization vectors and the keys can be any byte array, and obsolete
+//! algorithms are still available. This module searches the decompiled code for the most common
+//! misuses: ECB mode, static initialization vectors, hardcoded symmetric keys, DES and RC4
+//! ciphers, short RSA keys and `SecureRandom` instances seeded with constants.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+/// Minimum size, in bits, of the RSA keys considered secure.
+const MIN_RSA_KEY_SIZE: u32 = 2048;
+
+/// Block ciphers that use the ECB mode when no mode is given in the transformation.
+const ECB_DEFAULT_CIPHERS: [&str; 4] = ["AES", "DES", "DESEDE", "BLOWFISH"];
+
+/// Obsolete ciphers, that can be broken in practice.
+const WEAK_CIPHERS: [&str; 4] = ["DES", "RC4", "ARCFOUR", "RC2"];
+
+lazy_static! {
+    /// Transformations and algorithms given to the factories of the cryptography APIs.
+    static ref ALGORITHM: Regex = Regex::new(
+        r#"\b(Cipher|KeyGenerator|SecretKeyFactory)\s*\.\s*getInstance\(\s*"([^"]*)""#
+    )
+    .unwrap();
+    /// Initialization vectors built from constants.
+    static ref STATIC_IV: Regex = Regex::new(
+        r#"\bnew\s+(?:IvParameterSpec|GCMParameterSpec)\(\s*(?:\d+\s*,\s*)?(?:"(?:[^"\\\n]|\\.)*"\s*\.\s*getBytes|new\s+byte\s*\[)"#
+    )
+    .unwrap();
+    /// Secret keys built from constants.
+    static ref HARDCODED_KEY: Regex = Regex::new(
+        r#"\bnew\s+SecretKeySpec\(\s*(?:"(?:[^"\\\n]|\\.)*"\s*\.\s*getBytes|new\s+byte\s*\[)"#
+    )
+    .unwrap();
+    /// Sizes given to the RSA key generators.
+    static ref RSA_KEY_SIZE: Regex = Regex::new(
+        r"(?:\.\s*initialize|\bnew\s+RSAKeyGenParameterSpec)\(\s*(\d+)\s*[,)]"
+    )
+    .unwrap();
+    /// `SecureRandom` instances seeded with constants.
+    static ref CONSTANT_SEED: Regex = Regex::new(
+        r#"(?:\bnew\s+SecureRandom|\.\s*setSeed)\(\s*(?:-?\d+L?\s*\)|"(?:[^"\\\n]|\\.)*"\s*\.\s*getBytes|new\s+byte\s*\[)"#
+    )
+    .unwrap();
+}
+
+/// Misuse of the cryptography APIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Misuse {
+    /// Block cipher in ECB mode, with its transformation.
+    EcbMode(String),
+    /// Initialization vector built from a constant.
+    StaticIv,
+    /// Symmetric key built from a constant.
+    HardcodedKey,
+    /// Obsolete cipher, with its name.
+    WeakCipher(String),
+    /// RSA key shorter than the minimum secure size, with its size.
+    ShortRsaKey(u32),
+    /// `SecureRandom` instance seeded with a constant.
+    ConstantSeed,
+}
+
+impl CodeIssue for Misuse {
+    fn id(&self) -> &'static str {
+        match *self {
+            Misuse::EcbMode(_) => "crypto-ecb-mode",
+            Misuse::StaticIv => "crypto-static-iv",
+            Misuse::HardcodedKey => "crypto-hardcoded-key",
+            Misuse::WeakCipher(_) => "crypto-weak-cipher",
+            Misuse::ShortRsaKey(_) => "crypto-short-rsa-key",
+            Misuse::ConstantSeed => "crypto-constant-seed",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Misuse::EcbMode(_) => "ECB encryption mode",
+            Misuse::StaticIv => "Static initialization vector",
+            Misuse::HardcodedKey => "Hardcoded encryption key",
+            Misuse::WeakCipher(_) => "Weak cipher",
+            Misuse::ShortRsaKey(_) => "Short RSA key",
+            Misuse::ConstantSeed => "SecureRandom with constant seed",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Use of a broken or risky cryptographic algorithm.
+            Misuse::EcbMode(_) | Misuse::WeakCipher(_) => 327,
+            // Generation of weak initialization vector.
+            Misuse::StaticIv => 1204,
+            // Use of hard-coded cryptographic key.
+            Misuse::HardcodedKey => 321,
+            // Inadequate encryption strength.
+            Misuse::ShortRsaKey(_) => 326,
+            // Predictable seed in pseudo-random number generator.
+            Misuse::ConstantSeed => 337,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        match *self {
+            // The keys are not stored securely.
+            Misuse::HardcodedKey => "MASVS-CRYPTO-2",
+            _ => "MASVS-CRYPTO-1",
+        }
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            // The size of the key could be for any other key pair generator.
+            Misuse::ShortRsaKey(_) => Confidence::Medium,
+            _ => Confidence::High,
+        }
+    }
+}
+
+impl Misuse {
+    /// Gets the criticality of the findings of the misuse.
+    fn criticality(&self) -> Criticality {
+        match *self {
+            Misuse::HardcodedKey | Misuse::ConstantSeed => Criticality::High,
+            Misuse::EcbMode(_)
+            | Misuse::StaticIv
+            | Misuse::WeakCipher(_)
+            | Misuse::ShortRsaKey(_) => Criticality::Medium,
+        }
+    }
+
+    /// Gets the description of the findings of the misuse.
+    fn description(&self) -> String {
+        match *self {
+            Misuse::EcbMode(ref transformation) => format!(
+                "The application encrypts data with the `{}` transformation, that uses the ECB \
+                 mode. ECB encrypts equal blocks of plaintext into equal blocks of ciphertext, so \
+                 the encrypted data reveals the patterns of the original data. Use an \
+                 authenticated mode, such as `AES/GCM/NoPadding`.",
+                transformation
+            ),
+            Misuse::StaticIv => String::from(
+                "The application uses an initialization vector built from a constant. Encrypting \
+                 with the same key and initialization vector reveals which messages are equal, \
+                 and breaks the security of modes such as GCM. Generate a random initialization \
+                 vector for every encryption, and store it with the ciphertext.",
+            ),
+            Misuse::HardcodedKey => String::from(
+                "The application builds an encryption key from a constant. Anyone who \
+                 decompiles the application can get the key and decrypt the data. Generate the \
+                 keys at runtime, and store them in the Android Keystore.",
+            ),
+            Misuse::WeakCipher(ref cipher) => format!(
+                "The application uses the {} cipher, that is obsolete and can be broken in \
+                 practice. Use AES in an authenticated mode, such as `AES/GCM/NoPadding`.",
+                cipher
+            ),
+            Misuse::ShortRsaKey(size) => format!(
+                "The application generates RSA keys of {} bits, but keys shorter than {} bits \
+                 are no longer considered secure. Generate keys of at least {} bits.",
+                size, MIN_RSA_KEY_SIZE, MIN_RSA_KEY_SIZE
+            ),
+            Misuse::ConstantSeed => String::from(
+                "The application seeds a `SecureRandom` instance with a constant. In some \
+                 Android versions, seeding the generator before using it replaces its entropy, \
+                 so it always generates the same numbers. Use `SecureRandom` without a seed.",
+            ),
+        }
+    }
+}
+
+/// Scanner of the misuses of the cryptography APIs.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CryptoScanner;
+
+impl Scanner for CryptoScanner {
+    fn check(&self) -> Check {
+        Check::Crypto
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["crypto"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_misuses(code)
+            .into_iter()
+            .map(|(misuse, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(
+                    &misuse,
+                    misuse.criticality(),
+                    misuse.description(),
+                    line,
+                    line,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Finds the misuses of the cryptography APIs in the given code, with their index in it.
+fn find_misuses(code: &str) -> Vec<(Misuse, usize)> {
+    let mut misuses = Vec::new();
+
+    for captures in ALGORITHM.captures_iter(code) {
+        let transformation = &captures[2];
+        let mut parts = transformation.split('/').map(str::trim);
+        let algorithm = parts.next().unwrap_or_default().to_uppercase();
+        let mode = parts.next().map(str::to_uppercase);
+        let index = captures.get(0).map_or(0, |m| m.start());
+
+        if WEAK_CIPHERS.contains(&algorithm.as_str()) {
+            misuses.push((Misuse::WeakCipher(algorithm.clone()), index));
+        }
+        // Only ciphers have a mode, and RSA ignores it even if it's ECB.
+        if &captures[1] == "Cipher"
+            && ECB_DEFAULT_CIPHERS.contains(&algorithm.as_str())
+            && mode.as_ref().map_or(true, |mode| mode == "ECB")
+        {
+            misuses.push((Misuse::EcbMode(transformation.to_owned()), index));
+        }
+    }
+
+    misuses.extend(
+        STATIC_IV
+            .find_iter(code)
+            .map(|m| (Misuse::StaticIv, m.start())),
+    );
+    misuses.extend(
+        HARDCODED_KEY
+            .find_iter(code)
+            .map(|m| (Misuse::HardcodedKey, m.start())),
+    );
+    // Seeding other random number generators is not a cryptography misuse.
+    if code.contains("SecureRandom") {
+        misuses.extend(
+            CONSTANT_SEED
+                .find_iter(code)
+                .map(|m| (Misuse::ConstantSeed, m.start())),
+        );
+    }
+
+    // The sizes are only checked in the files that generate RSA keys.
+    if code.contains("\"RSA\"") {
+        for captures in RSA_KEY_SIZE.captures_iter(code) {
+            if let Ok(size) = captures[1].parse::<u32>() {
+                if size < MIN_RSA_KEY_SIZE {
+                    let index = captures.get(0).map_or(0, |m| m.start());
+                    misuses.push((Misuse::ShortRsaKey(size), index));
+                }
+            }
+        }
+    }
+
+    misuses.sort_by_key(|&(_, index)| index);
+    misuses
+}
+
+/// Cryptography misuse analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_misuses, Misuse};
+
+    /// Checks the misuses found in the code, in order.
+    #[test]
+    fn it_find_misuses() {
+        let code = "Cipher a = Cipher.getInstance(\"AES\");\n\
+                    Cipher b = Cipher.getInstance(\"AES/ECB/PKCS5Padding\");\n\
+                    Cipher c = Cipher.getInstance(\"DES/CBC/PKCS5Padding\");\n\
+                    Cipher d = Cipher.getInstance(\"RC4\");\n\
+                    IvParameterSpec iv = new IvParameterSpec(\"0123456789abcdef\".getBytes());\n\
+                    SecretKeySpec key = new SecretKeySpec(\"secret\".getBytes(), \"AES\");\n\
+                    SecureRandom random = new SecureRandom();\n\
+                    random.setSeed(42L);\n\
+                    KeyPairGenerator generator = KeyPairGenerator.getInstance(\"RSA\");\n\
+                    generator.initialize(1024);";
+
+        let misuses = find_misuses(code)
+            .into_iter()
+            .map(|(misuse, _)| misuse)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            misuses,
+            vec![
+                Misuse::EcbMode("AES".to_owned()),
+                Misuse::EcbMode("AES/ECB/PKCS5Padding".to_owned()),
+                Misuse::WeakCipher("DES".to_owned()),
+                Misuse::WeakCipher("RC4".to_owned()),
+                Misuse::StaticIv,
+                Misuse::HardcodedKey,
+                Misuse::ConstantSeed,
+                Misuse::ShortRsaKey(1024),
+            ]
+        );
+    }
+
+    /// Checks that the secure uses of the cryptography APIs are not reported.
+    #[test]
+    fn it_secure_uses() {
+        let code = "Cipher a = Cipher.getInstance(\"AES/GCM/NoPadding\");\n\
+                    Cipher b = Cipher.getInstance(\"RSA/ECB/OAEPWithSHA-256AndMGF1Padding\");\n\
+                    byte[] ivBytes = new byte[12];\n\
+                    new SecureRandom().nextBytes(ivBytes);\n\
+                    GCMParameterSpec spec = new GCMParameterSpec(128, ivBytes);\n\
+                    SecretKeySpec key = new SecretKeySpec(keyBytes, \"AES\");\n\
+                    KeyPairGenerator generator = KeyPairGenerator.getInstance(\"RSA\");\n\
+                    generator.initialize(4096);";
+
+        assert!(find_misuses(code).is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Custom permission analysis module.
+//!
+//! Applications protect their exported components with custom permissions, but the protection
+//! is only as strong as the protection level of the permission: `normal` permissions, the default
+//! level, are granted to any application that requests them. Permissions are also identified only
+//! by their name, and the first application that declares a permission defines it, so an
+//! application installed before this one can declare its permissions with a weaker level and get
+//! access to its components. This is known as permission squatting.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// CWE of the custom permission findings: incorrect permission assignment for critical
+/// resource.
+const PERMISSION_CWE: u32 = 732;
+
+/// Prefixes of the permissions of the platform and of the system applications.
+const PLATFORM_PREFIXES: [&str; 3] = ["android.", "com.android.", "com.google.android."];
+
+/// Reports the custom permissions that don't protect the exported components that they guard,
+/// and the permissions exposed to squatting.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    // Permissions that guard exported components, with the components that they guard.
+    let mut guards: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for component in manifest
+        .components()
+        .iter()
+        .filter(|component| component.is_exported(manifest.min_sdk()))
+    {
+        let permissions = component
+            .permission()
+            .into_iter()
+            .chain(component.read_permission())
+            .chain(component.write_permission());
+        for permission in permissions {
+            let components = guards.entry(permission).or_insert_with(Vec::new);
+            if !components.contains(&component.name()) {
+                components.push(component.name());
+            }
+        }
+    }
+
+    for (permission, components) in guards {
+        let declared = manifest
+            .declared_permissions()
+            .iter()
+            .find(|declared| declared.name() == permission);
+        let (criticality, name) =
+            match check_guard(permission, declared.map(|declared| declared.is_normal())) {
+                Some(finding) => finding,
+                None => continue,
+            };
+        let components = components
+            .iter()
+            .map(|component| format!("`{}`", component))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let description = if declared.is_some() {
+            format!(
+                "The `{}` permission guards the exported components {}, but it has the `normal` \
+                 protection level, so any application that requests it is granted it without \
+                 asking the user. Use the `signature` protection level to restrict the components \
+                 to the applications of the same developer.",
+                permission, components
+            )
+        } else {
+            format!(
+                "The `{}` permission guards the exported components {}, but the application does \
+                 not declare it. If an application that declares it with the `normal` \
+                 protection level is installed first, any application can be granted it and \
+                 access the components. Declare the permission with the `signature` protection \
+                 level.",
+                permission, components
+            )
+        };
+        let needle = if declared.is_some() {
+            format!("android:name=\"{}\"", permission)
+        } else {
+            format!("=\"{}\"", permission)
+        };
+        report(
+            config,
+            manifest,
+            results,
+            criticality,
+            name,
+            description,
+            &needle,
+        );
+    }
+
+    for permission in manifest.declared_permissions() {
+        let criticality = match check_prefix(manifest.package(), permission.name()) {
+            Some(criticality) => criticality,
+            None => continue,
+        };
+        let description = format!(
+            "The application declares the `{}` permission outside of its package namespace \
+             (`{}`). Other applications can declare the same permission, and the first one \
+             installed defines its protection level, so another application can squat it with a \
+             weaker level, or this application can fail to install. Prefix the custom \
+             permissions with the package of the application.",
+            permission.name(),
+            manifest.package()
+        );
+        let needle = format!("android:name=\"{}\"", permission.name());
+        report(
+            config,
+            manifest,
+            results,
+            criticality,
+            "Permission squatting risk",
+            description,
+            &needle,
+        );
+    }
+}
+
+/// Checks a permission that guards exported components. `declared_normal` tells if the permission
+/// has the `normal` protection level, if it's declared by the application.
+///
+/// It returns the criticality and the name of the finding, if any.
+fn check_guard(
+    permission: &str,
+    declared_normal: Option<bool>,
+) -> Option<(Criticality, &'static str)> {
+    match declared_normal {
+        Some(true) => Some((Criticality::Medium, "Normal custom permission")),
+        Some(false) => None,
+        // The permissions of the platform can't be squatted.
+        None if PLATFORM_PREFIXES
+            .iter()
+            .any(|prefix| permission.starts_with(prefix)) =>
+        {
+            None
+        }
+        None => Some((Criticality::Medium, "Undeclared custom permission")),
+    }
+}
+
+/// Checks the name of a custom permission declared by the application.
+///
+/// Permissions in the namespace of the platform are the most likely to collide, and permissions
+/// outside of the package of the application can collide with the permissions of other
+/// applications.
+fn check_prefix(package: &str, permission: &str) -> Option<Criticality> {
+    if PLATFORM_PREFIXES
+        .iter()
+        .any(|prefix| permission.starts_with(prefix))
+    {
+        Some(Criticality::Medium)
+    } else if !permission.starts_with(&format!("{}.", package)) {
+        Some(Criticality::Low)
+    } else {
+        None
+    }
+}
+
+/// Reports a custom permission finding, pointing to the first line of the manifest with the
+/// given text.
+fn report(
+    config: &Config,
+    manifest: &Manifest,
+    results: &mut Results,
+    criticality: Criticality,
+    name: &str,
+    description: String,
+    needle: &str,
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let mut vulnerability = VulnerabilityBuilder::new(criticality, name, description.as_str())
+        .file("AndroidManifest.xml")
+        .cwe(PERMISSION_CWE);
+    if let Ok(line) = get_line(manifest.code(), needle) {
+        let code = get_code(manifest.code(), line, line);
+        vulnerability = vulnerability.lines(line, line).code(code);
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Custom permission analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{check_guard, check_prefix};
+    use crate::criticality::Criticality;
+
+    /// Checks the findings of the permissions that guard exported components.
+    #[test]
+    fn it_check_guard() {
+        assert_eq!(
+            check_guard("com.example.permission.SYNC", Some(true)),
+            Some((Criticality::Medium, "Normal custom permission"))
+        );
+        assert_eq!(
+            check_guard("com.example.permission.SYNC", Some(false)),
+            None
+        );
+        assert_eq!(
+            check_guard("com.example.permission.SYNC", None),
+            Some((Criticality::Medium, "Undeclared custom permission"))
+        );
+        assert_eq!(
+            check_guard("android.permission.BIND_JOB_SERVICE", None),
+            None
+        );
+    }
+
+    /// Checks the squatting risk of the names of custom permissions.
+    #[test]
+    fn it_check_prefix() {
+        assert_eq!(
+            check_prefix("com.example", "com.example.permission.SYNC"),
+            None
+        );
+        assert_eq!(
+            check_prefix("com.example", "com.examples.permission.SYNC"),
+            Some(Criticality::Low)
+        );
+        assert_eq!(
+            check_prefix("com.example", "android.permission.SYNC"),
+            Some(Criticality::Medium)
+        );
+    }
+}
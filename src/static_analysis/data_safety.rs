@@ -0,0 +1,394 @@
+//! Data safety declaration module.
+//!
+//! Google Play requires applications to declare the user data that they collect in the data
+//! safety section of their listing. This module cross-checks the declaration, as exported from
+//! the Play Console, with the permissions requested by the application, and reports the data
+//! that the application can collect without declaring it, and the declared data that it can't
+//! collect.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use failure::{format_err, Error};
+use serde_json;
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// Kind of user data of the data safety section, that can be collected with a permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DataType {
+    /// Approximate location of the device.
+    ApproximateLocation,
+    /// Precise location of the device.
+    PreciseLocation,
+    /// Contacts of the user.
+    Contacts,
+    /// Calendar events of the user.
+    Calendar,
+    /// SMS and MMS messages.
+    Messages,
+    /// Photos taken or stored in the device.
+    Photos,
+    /// Voice or sound recordings.
+    Audio,
+    /// Health information, such as the data of body sensors.
+    Health,
+    /// Fitness information, such as the physical activity of the user.
+    Fitness,
+    /// Applications installed in the device.
+    InstalledApps,
+    /// Identifiers of the device, such as the IMEI.
+    DeviceId,
+}
+
+impl DataType {
+    /// Gets all the data types.
+    pub fn all() -> &'static [Self] {
+        &[
+            DataType::ApproximateLocation,
+            DataType::PreciseLocation,
+            DataType::Contacts,
+            DataType::Calendar,
+            DataType::Messages,
+            DataType::Photos,
+            DataType::Audio,
+            DataType::Health,
+            DataType::Fitness,
+            DataType::InstalledApps,
+            DataType::DeviceId,
+        ]
+    }
+
+    /// Gets the name of the data type, as used in JSON declarations.
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::ApproximateLocation => "approximate-location",
+            DataType::PreciseLocation => "precise-location",
+            DataType::Contacts => "contacts",
+            DataType::Calendar => "calendar",
+            DataType::Messages => "messages",
+            DataType::Photos => "photos",
+            DataType::Audio => "audio",
+            DataType::Health => "health",
+            DataType::Fitness => "fitness",
+            DataType::InstalledApps => "installed-apps",
+            DataType::DeviceId => "device-id",
+        }
+    }
+
+    /// Gets the response ID of the data type in the Play Console export.
+    pub fn play_id(self) -> &'static str {
+        match self {
+            DataType::ApproximateLocation => "PSL_APPROX_LOCATION",
+            DataType::PreciseLocation => "PSL_PRECISE_LOCATION",
+            DataType::Contacts => "PSL_CONTACTS",
+            DataType::Calendar => "PSL_CALENDAR",
+            DataType::Messages => "PSL_SMS",
+            DataType::Photos => "PSL_PHOTOS",
+            DataType::Audio => "PSL_AUDIO",
+            DataType::Health => "PSL_HEALTH",
+            DataType::Fitness => "PSL_FITNESS",
+            DataType::InstalledApps => "PSL_INSTALLED_APPS",
+            DataType::DeviceId => "PSL_DEVICE_ID",
+        }
+    }
+
+    /// Gets the data type with the given name or Play Console response ID, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .cloned()
+            .find(|data_type| data_type.name() == name || data_type.play_id() == name)
+    }
+
+    /// Gets the label of the data type, as shown in the data safety section.
+    pub fn label(self) -> &'static str {
+        match self {
+            DataType::ApproximateLocation => "Approximate location",
+            DataType::PreciseLocation => "Precise location",
+            DataType::Contacts => "Contacts",
+            DataType::Calendar => "Calendar events",
+            DataType::Messages => "SMS or MMS",
+            DataType::Photos => "Photos",
+            DataType::Audio => "Voice or sound recordings",
+            DataType::Health => "Health info",
+            DataType::Fitness => "Fitness info",
+            DataType::InstalledApps => "Installed apps",
+            DataType::DeviceId => "Device or other IDs",
+        }
+    }
+
+    /// Gets the permissions that give access to the data type.
+    fn permissions(self) -> &'static [&'static str] {
+        match self {
+            DataType::ApproximateLocation => &["android.permission.ACCESS_COARSE_LOCATION"],
+            DataType::PreciseLocation => &["android.permission.ACCESS_FINE_LOCATION"],
+            DataType::Contacts => &["android.permission.READ_CONTACTS"],
+            DataType::Calendar => &["android.permission.READ_CALENDAR"],
+            DataType::Messages => &[
+                "android.permission.READ_SMS",
+                "android.permission.RECEIVE_SMS",
+                "android.permission.RECEIVE_MMS",
+            ],
+            DataType::Photos => &[
+                "android.permission.CAMERA",
+                "android.permission.READ_MEDIA_IMAGES",
+            ],
+            DataType::Audio => &["android.permission.RECORD_AUDIO"],
+            DataType::Health => &["android.permission.BODY_SENSORS"],
+            DataType::Fitness => &[
+                "android.permission.ACTIVITY_RECOGNITION",
+                "com.google.android.gms.permission.ACTIVITY_RECOGNITION",
+            ],
+            DataType::InstalledApps => &["android.permission.QUERY_ALL_PACKAGES"],
+            DataType::DeviceId => &["android.permission.READ_PHONE_STATE"],
+        }
+    }
+}
+
+/// Data types declared in the data safety section of the application.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Declaration {
+    /// Declared data types.
+    data_types: BTreeSet<DataType>,
+}
+
+impl Declaration {
+    /// Loads the declaration from the given file.
+    ///
+    /// JSON files contain a `data_types` list with the names or the Play Console response IDs
+    /// of the declared data types. Any other file is read as the CSV export of the Play Console.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Ok(Self::from_csv(&contents)),
+        }
+    }
+
+    /// Reads the declaration from the CSV export of the Play Console.
+    ///
+    /// A data type is declared if a row has its response ID, or a question ID that contains it,
+    /// with the `true` value. Other rows are ignored.
+    fn from_csv(contents: &str) -> Self {
+        let mut data_types = BTreeSet::new();
+        for line in contents.lines() {
+            let fields = csv_fields(line);
+            if fields.len() < 3 || fields[2] != "true" {
+                continue;
+            }
+            let data_type = DataType::from_name(&fields[1])
+                .or_else(|| fields[0].split(':').filter_map(DataType::from_name).next());
+            if let Some(data_type) = data_type {
+                let _ = data_types.insert(data_type);
+            }
+        }
+        Self { data_types }
+    }
+
+    /// Reads the declaration from a JSON file with the list of declared data types.
+    fn from_json(contents: &str) -> Result<Self, Error> {
+        /// JSON data safety declaration.
+        #[derive(Deserialize)]
+        struct JsonDeclaration {
+            /// Names of the declared data types.
+            data_types: Vec<String>,
+        }
+
+        let declaration: JsonDeclaration = serde_json::from_str(contents)?;
+        let data_types = declaration
+            .data_types
+            .iter()
+            .map(|name| {
+                DataType::from_name(name).ok_or_else(|| format_err!("unknown data type `{}`", name))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { data_types })
+    }
+
+    /// Returns true if the given data type is declared.
+    pub fn declares(&self, data_type: DataType) -> bool {
+        self.data_types.contains(&data_type)
+    }
+
+    /// Gets the mismatches between the declaration and the given requested permissions.
+    ///
+    /// Each mismatch has the data type and, if it's not declared, the first requested permission
+    /// that gives access to it. Declared data types without a permission are not collected.
+    pub fn mismatches<'p>(&self, permissions: &'p [String]) -> Vec<(DataType, Option<&'p str>)> {
+        DataType::all()
+            .iter()
+            .filter_map(|&data_type| {
+                let permission = permissions
+                    .iter()
+                    .map(String::as_str)
+                    .find(|permission| data_type.permissions().contains(permission));
+                match (permission, self.declares(data_type)) {
+                    (Some(permission), false) => Some((data_type, Some(permission))),
+                    (None, true) => Some((data_type, None)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Splits a CSV line in its fields, removing the quotes.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                let _ = chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(field.split_off(0)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Reports the mismatches between the data safety declaration and the permissions of the
+/// application, if a declaration is configured.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    let path = match config.data_safety() {
+        Some(path) => path,
+        None => return,
+    };
+    let declaration = match Declaration::load(path) {
+        Ok(declaration) => declaration,
+        Err(e) => {
+            print_warning(format!(
+                "the data safety declaration {} could not be loaded: {}",
+                path.display(),
+                e
+            ));
+            return;
+        }
+    };
+
+    for (data_type, permission) in declaration.mismatches(manifest.requested_permissions()) {
+        let (criticality, name, description) = match permission {
+            Some(permission) => (
+                Criticality::Medium,
+                "Undeclared data collection",
+                format!(
+                    "The application requests the `{}` permission, that gives access to the \
+                     \"{}\" data, but it's not declared in the data safety section. Google Play \
+                     requires every collected or shared data type to be declared, so check if \
+                     the data leaves the device.",
+                    permission,
+                    data_type.label()
+                ),
+            ),
+            None => (
+                Criticality::Warning,
+                "Declared data not accessed",
+                format!(
+                    "The data safety section declares the \"{}\" data, but the application \
+                     doesn't request any permission that gives access to it. The declaration \
+                     could be outdated, or the data could be entered by the user.",
+                    data_type.label()
+                ),
+            ),
+        };
+        if criticality < config.min_criticality() {
+            continue;
+        }
+
+        let line = permission.and_then(|permission| get_line(manifest.code(), permission).ok());
+        let code = line.map(|l| get_code(manifest.code(), l, l));
+        results.add_vulnerability(Vulnerability::new(
+            criticality,
+            name,
+            description.as_str(),
+            permission.map(|_| "AndroidManifest.xml"),
+            line,
+            line,
+            code,
+        ));
+        print_vulnerability(description, criticality);
+    }
+}
+
+/// Data safety declaration testing module.
+#[cfg(test)]
+mod test {
+    use super::{csv_fields, DataType, Declaration};
+
+    /// Checks the parsing of the CSV export of the Play Console.
+    #[test]
+    fn it_from_csv() {
+        assert_eq!(
+            csv_fields(
+                r#"PSL_DATA_TYPES_LOCATION,PSL_APPROX_LOCATION,true,,"Location, ""approx""""#
+            ),
+            vec![
+                "PSL_DATA_TYPES_LOCATION",
+                "PSL_APPROX_LOCATION",
+                "true",
+                "",
+                "Location, \"approx\"",
+            ]
+        );
+
+        let declaration = Declaration::from_csv(
+            "Question ID (machine readable),Response ID (machine readable),Response value\n\
+             PSL_DATA_TYPES_LOCATION,PSL_APPROX_LOCATION,true\n\
+             PSL_DATA_TYPES_LOCATION,PSL_PRECISE_LOCATION,\n\
+             PSL_DATA_USAGE_RESPONSES:PSL_CONTACTS:PSL_DATA_USAGE_COLLECTED,,true\n",
+        );
+        assert!(declaration.declares(DataType::ApproximateLocation));
+        assert!(!declaration.declares(DataType::PreciseLocation));
+        assert!(declaration.declares(DataType::Contacts));
+    }
+
+    /// Checks the parsing of JSON declarations.
+    #[test]
+    fn it_from_json() {
+        let declaration =
+            Declaration::from_json(r#"{ "data_types": ["precise-location", "PSL_DEVICE_ID"] }"#)
+                .unwrap();
+        assert!(declaration.declares(DataType::PreciseLocation));
+        assert!(declaration.declares(DataType::DeviceId));
+        assert!(!declaration.declares(DataType::Contacts));
+
+        assert!(Declaration::from_json(r#"{ "data_types": ["location"] }"#).is_err());
+    }
+
+    /// Checks the mismatches between the declaration and the requested permissions.
+    #[test]
+    fn it_mismatches() {
+        let declaration =
+            Declaration::from_json(r#"{ "data_types": ["approximate-location", "contacts"] }"#)
+                .unwrap();
+        let permissions = vec![
+            "android.permission.INTERNET".to_owned(),
+            "android.permission.ACCESS_COARSE_LOCATION".to_owned(),
+            "android.permission.ACCESS_FINE_LOCATION".to_owned(),
+        ];
+
+        assert_eq!(
+            declaration.mismatches(&permissions),
+            vec![
+                (
+                    DataType::PreciseLocation,
+                    Some("android.permission.ACCESS_FINE_LOCATION")
+                ),
+                (DataType::Contacts, None),
+            ]
+        );
+    }
+}
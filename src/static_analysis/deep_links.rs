@@ -0,0 +1,218 @@
+//! Deep link analysis module.
+//!
+//! Intent filters with the `VIEW` action and the `BROWSABLE` category let web pages and other
+//! applications open the components of the application with a URL. This module reports the web
+//! links that the system does not verify, since any other application can declare the same
+//! links and intercept them, the filters that accept links from any host, and the custom schemes,
+//! that no application can own.
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, Component, IntentFilter, Manifest},
+    Config,
+};
+
+/// CWE of the deep link findings: improper authorization in handler for custom URL scheme.
+const DEEP_LINK_CWE: u32 = 939;
+
+/// Reports the unverified and overly broad deep links of the exported components.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    for component in manifest
+        .components()
+        .iter()
+        .filter(|component| component.is_exported(manifest.min_sdk()))
+    {
+        for filter in component
+            .intent_filters()
+            .iter()
+            .filter(|filter| filter.is_deep_link())
+        {
+            let findings = check_filter(filter.schemes(), filter.hosts(), filter.is_auto_verify());
+            for (criticality, name, description) in findings {
+                let description = format!("{} {}", description, describe_filter(component, filter));
+                report(
+                    config,
+                    manifest,
+                    results,
+                    component,
+                    criticality,
+                    name,
+                    description,
+                );
+            }
+        }
+    }
+}
+
+/// Checks the schemes, the hosts and the `autoVerify` attribute of a deep link intent filter,
+/// and returns the criticality, the name and the description of each finding.
+fn check_filter(
+    schemes: &[String],
+    hosts: &[String],
+    auto_verify: bool,
+) -> Vec<(Criticality, &'static str, &'static str)> {
+    let mut findings = Vec::new();
+    let web = schemes
+        .iter()
+        .any(|scheme| scheme == "http" || scheme == "https");
+
+    if web && !auto_verify {
+        findings.push((
+            Criticality::Medium,
+            "Unverified deep link",
+            "The component handles web links without `android:autoVerify=\"true\"`, so the \
+             system does not verify that the application owns their hosts. Any other application \
+             can declare the same links, and the user can be tricked into opening them with it, \
+             leaking the data in the URLs, such as tokens or password reset codes.",
+        ));
+    }
+    if web && schemes.iter().any(|scheme| scheme == "http") {
+        findings.push((
+            Criticality::Low,
+            "Cleartext deep link",
+            "The component handles `http` links. The URLs can be read and modified by anyone in \
+             the same network before they reach the application, so they should not carry \
+             sensitive data or trigger sensitive actions.",
+        ));
+    }
+    if web && (hosts.is_empty() || hosts.iter().any(|host| host == "*")) {
+        findings.push((
+            Criticality::Medium,
+            "Overly broad deep link",
+            "The component handles web links of any host, so any web page or application can send it \
+             arbitrary URLs. Restrict the filter to the hosts of the application, and validate \
+             every parameter of the received URLs.",
+        ));
+    }
+    if schemes
+        .iter()
+        .any(|scheme| scheme != "http" && scheme != "https")
+    {
+        findings.push((
+            Criticality::Low,
+            "Custom scheme deep link",
+            "The component handles links with a custom scheme. Custom schemes can't be verified, \
+             so any other application can declare the same scheme and intercept the links. Use \
+             verified web links (App Links) for sensitive flows, such as OAuth redirects.",
+        ));
+    }
+
+    findings
+}
+
+/// Describes a component and its intent filter, with all the data of the filter.
+fn describe_filter(component: &Component, filter: &IntentFilter) -> String {
+    let mut fields = vec![
+        format!("actions: {}", list(filter.actions())),
+        format!("categories: {}", list(filter.categories())),
+        format!("schemes: {}", list(filter.schemes())),
+        format!("hosts: {}", list(filter.hosts())),
+    ];
+    if !filter.ports().is_empty() {
+        fields.push(format!("ports: {}", list(filter.ports())));
+    }
+    if !filter.paths().is_empty() {
+        fields.push(format!("paths: {}", list(filter.paths())));
+    }
+    if !filter.mime_types().is_empty() {
+        fields.push(format!("MIME types: {}", list(filter.mime_types())));
+    }
+    fields.push(format!("autoVerify: {}", filter.is_auto_verify()));
+
+    format!(
+        "Component: {} `{}`. Intent filter: {}.",
+        component.kind().tag(),
+        component.name(),
+        fields.join("; ")
+    )
+}
+
+/// Formats a list of values of an intent filter.
+fn list(values: &[String]) -> String {
+    if values.is_empty() {
+        String::from("none")
+    } else {
+        values
+            .iter()
+            .map(|value| format!("`{}`", value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Reports a deep link finding, pointing to the declaration of the component in the manifest.
+fn report(
+    config: &Config,
+    manifest: &Manifest,
+    results: &mut Results,
+    component: &Component,
+    criticality: Criticality,
+    name: &str,
+    description: String,
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let mut vulnerability = VulnerabilityBuilder::new(criticality, name, description.as_str())
+        .file("AndroidManifest.xml")
+        .cwe(DEEP_LINK_CWE);
+    let needle = format!("android:name=\"{}\"", component.name());
+    if let Ok(line) = get_line(manifest.code(), needle.as_str()) {
+        vulnerability = vulnerability
+            .lines(line, line)
+            .code(get_code(manifest.code(), line, line));
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Deep link analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::check_filter;
+    use crate::criticality::Criticality;
+
+    /// Converts a list of string slices to the values of an intent filter.
+    fn values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| (*value).to_owned()).collect()
+    }
+
+    /// Gets the names of the findings of a filter, from its schemes, hosts and `autoVerify`
+    /// attribute.
+    fn findings(schemes: &[&str], hosts: &[&str], auto_verify: bool) -> Vec<&'static str> {
+        check_filter(&values(schemes), &values(hosts), auto_verify)
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect()
+    }
+
+    /// Checks the findings of verified, unverified, broad and custom scheme deep links.
+    #[test]
+    fn it_check_filter() {
+        assert!(findings(&["https"], &["example.com"], true).is_empty());
+        assert_eq!(
+            findings(&["http", "https"], &["example.com"], false),
+            vec!["Unverified deep link", "Cleartext deep link"]
+        );
+        assert_eq!(
+            findings(&["https"], &["*"], true),
+            vec!["Overly broad deep link"]
+        );
+        assert_eq!(
+            findings(&["example"], &["callback"], false),
+            vec!["Custom scheme deep link"]
+        );
+
+        let criticalities: Vec<_> = check_filter(&values(&["https"]), &[], false)
+            .into_iter()
+            .map(|(criticality, _, _)| criticality)
+            .collect();
+        assert_eq!(
+            criticalities,
+            vec![Criticality::Medium, Criticality::Medium]
+        );
+    }
+}
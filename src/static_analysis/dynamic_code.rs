@@ -0,0 +1,269 @@
+//! Dynamic code analysis module.
+//!
+//! Code loaded or executed at runtime is not in the analyzed application, and if it comes from a
+//! location that other applications can write, such as the external storage, anyone can replace
+//! it to run their code with the permissions of the application. This module searches the
+//! decompiled code for system commands run through a `Runtime` instance, DEX files loaded with
+//! `DexClassLoader` or `PathClassLoader` from the external storage, and native libraries loaded
+//! from paths or names built at runtime. Commands run directly with `Runtime.getRuntime().exec()`
+//! are found by the default rules.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{arguments, enclosing_method, mask, methods},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+lazy_static! {
+    /// Variables that store the `Runtime` instance.
+    static ref RUNTIME_VARIABLE: Regex =
+        Regex::new(r"\b([A-Za-z_$][\w$]*)\s*=\s*Runtime\s*\.\s*getRuntime\s*\(\s*\)").unwrap();
+    /// Class loaders that load DEX files from a path.
+    static ref CLASS_LOADER: Regex =
+        Regex::new(r"\bnew\s+(?:dalvik\.system\.)?(?:DexClassLoader|PathClassLoader)\s*\(").unwrap();
+    /// Locations of the external storage.
+    static ref EXTERNAL_STORAGE: Regex = Regex::new(
+        r#"\bgetExternal(?:StorageDirectory|StoragePublicDirectory|FilesDirs?|CacheDirs?)\s*\(|"(?:/sdcard|/storage/|/mnt/sdcard)"#
+    )
+    .unwrap();
+    /// Calls that load native libraries.
+    static ref LOAD_LIBRARY: Regex =
+        Regex::new(r"\b(?:System|getRuntime\s*\(\s*\))\s*\.\s*(?:loadLibrary|load)\s*\(").unwrap();
+    /// Constants, by the Java naming conventions.
+    static ref CONSTANT: Regex = Regex::new(r"^(?:[\w$]+\.)*[A-Z][A-Z0-9_]*$").unwrap();
+    /// Java identifiers.
+    static ref IDENTIFIER: Regex = Regex::new(r"^[A-Za-z_$][\w$]*$").unwrap();
+}
+
+/// Dynamic code loading or execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// System command run through a `Runtime` instance stored in a variable.
+    CommandExecution,
+    /// DEX file loaded from the external storage.
+    ExternalClassLoading,
+    /// Native library loaded from a path or a name built at runtime.
+    DynamicNativeLibrary,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::CommandExecution => "dynamic-command-execution",
+            Issue::ExternalClassLoading => "dynamic-external-class-loading",
+            Issue::DynamicNativeLibrary => "dynamic-native-library",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::CommandExecution => "System command execution",
+            Issue::ExternalClassLoading => "Code loaded from external storage",
+            Issue::DynamicNativeLibrary => "Native library loaded from dynamic path",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Improper neutralization of special elements used in an OS command.
+            Issue::CommandExecution => 78,
+            // Inclusion of functionality from untrusted control sphere.
+            Issue::ExternalClassLoading => 829,
+            // Process control.
+            Issue::DynamicNativeLibrary => 114,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        "MASVS-CODE-4"
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            Issue::CommandExecution | Issue::ExternalClassLoading => Confidence::High,
+            // The path could be built from trusted locations only.
+            Issue::DynamicNativeLibrary => Confidence::Medium,
+        }
+    }
+}
+
+impl Issue {
+    /// Gets the description of the findings of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            Issue::CommandExecution => {
+                "The application runs system commands through a `Runtime` instance. If any part \
+                 of the command can be controlled by an attacker, the attacker can run any \
+                 command with the permissions of the application. Avoid running system commands, \
+                 or only run fixed commands with validated arguments."
+            }
+            Issue::ExternalClassLoading => {
+                "The application loads code with a class loader from the external storage. Any \
+                 application with the storage permissions can replace the loaded file to run its \
+                 own code with the permissions of the application. Load code only from the \
+                 internal storage of the application, and verify its signature before loading \
+                 it."
+            }
+            Issue::DynamicNativeLibrary => {
+                "The application loads a native library from a path or a name built at runtime. \
+                 If an attacker can control the path or write the loaded file, the attacker can \
+                 run native code with the permissions of the application. Only load the native \
+                 libraries packaged with the application, by a fixed name."
+            }
+        }
+    }
+}
+
+/// Scanner of the dynamic code loading and execution.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DynamicCodeScanner;
+
+impl Scanner for DynamicCodeScanner {
+    fn check(&self) -> Check {
+        Check::DynamicCode
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["injection"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_issues(code)
+            .into_iter()
+            .map(|(issue, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(&issue, Criticality::High, issue.description(), line, line)
+            })
+            .collect()
+    }
+}
+
+/// Finds the dynamic code loading and execution in the given code, with their index in it.
+fn find_issues(code: &str) -> Vec<(Issue, usize)> {
+    let masked = mask(code);
+    let methods = methods(&masked);
+    let mut issues = Vec::new();
+
+    for captures in RUNTIME_VARIABLE.captures_iter(&masked) {
+        let exec = Regex::new(&format!(
+            r"\b{}\s*\.\s*exec\s*\(",
+            regex::escape(&captures[1])
+        ))
+        .unwrap();
+        let start = captures.get(0).unwrap().end();
+        let end =
+            enclosing_method(&methods, start).map_or(masked.len(), |method| method.body().end);
+        issues.extend(
+            exec.find_iter(&masked[start..end])
+                .map(|m| (Issue::CommandExecution, start + m.start())),
+        );
+    }
+
+    for loader in CLASS_LOADER.find_iter(&masked) {
+        let path = match arguments(&masked, loader.end()).into_iter().next() {
+            Some(path) => path,
+            None => continue,
+        };
+        let external = if IDENTIFIER.is_match(masked[path.clone()].trim()) {
+            // The path is usually built in a variable of the same method.
+            let assignment = Regex::new(&format!(
+                r"\b{}\s*=\s*([^;]*);",
+                regex::escape(masked[path.clone()].trim())
+            ))
+            .unwrap();
+            let start =
+                enclosing_method(&methods, loader.start()).map_or(0, |method| method.body().start);
+            let external = assignment
+                .captures_iter(&masked[start..loader.start()])
+                .any(|captures| {
+                    let value = captures.get(1).unwrap();
+                    is_external(&code[start + value.start()..start + value.end()])
+                });
+            external
+        } else {
+            is_external(&code[path])
+        };
+        if external {
+            issues.push((Issue::ExternalClassLoading, loader.start()));
+        }
+    }
+
+    for load in LOAD_LIBRARY.find_iter(&masked) {
+        let library = match arguments(&masked, load.end()).into_iter().next() {
+            Some(library) => masked[library].trim(),
+            None => continue,
+        };
+        let fixed = (library.starts_with('"') && library.ends_with('"') && library.len() > 1)
+            || CONSTANT.is_match(library);
+        if !fixed {
+            issues.push((Issue::DynamicNativeLibrary, load.start()));
+        }
+    }
+
+    issues.sort_by_key(|&(_, index)| index);
+    issues
+}
+
+/// Checks if the given expression of the original code refers to the external storage.
+///
+/// The original code is used, since the paths in the string literals are masked.
+fn is_external(expression: &str) -> bool {
+    EXTERNAL_STORAGE.is_match(expression)
+}
+
+/// Dynamic code analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_issues, get_line_for, Issue};
+
+    /// Checks the issues found in the code, in order.
+    #[test]
+    fn it_find_issues() {
+        let code = "public void update(String name) {\n\
+                    Runtime runtime = Runtime.getRuntime();\n\
+                    Process process = runtime.exec(\"pm install \" + name);\n\
+                    String path = Environment.getExternalStorageDirectory() + \"/update.dex\";\n\
+                    DexClassLoader loader = new DexClassLoader(path, getCacheDir().getPath(), \
+                    null, getClassLoader());\n\
+                    new PathClassLoader(\"/sdcard/plugin.apk\", getClassLoader());\n\
+                    System.load(getFilesDir() + \"/lib\" + name + \".so\");\n\
+                    System.loadLibrary(name);\n\
+                    }";
+
+        let issues = find_issues(code)
+            .into_iter()
+            .map(|(issue, index)| (issue, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Issue::CommandExecution, 2),
+                (Issue::ExternalClassLoading, 4),
+                (Issue::ExternalClassLoading, 5),
+                (Issue::DynamicNativeLibrary, 6),
+                (Issue::DynamicNativeLibrary, 7),
+            ]
+        );
+    }
+
+    /// Checks that the code loaded from fixed, internal locations is not reported.
+    #[test]
+    fn it_fixed_code() {
+        let code = "public void init() {\n\
+                    System.loadLibrary(\"native-lib\");\n\
+                    System.loadLibrary(Constants.LIBRARY_NAME);\n\
+                    String path = new File(getFilesDir(), \"plugin.dex\").getPath();\n\
+                    DexClassLoader loader = new DexClassLoader(path, getCacheDir().getPath(), \
+                    null, getClassLoader());\n\
+                    // new PathClassLoader(\"/sdcard/plugin.apk\", getClassLoader());\n\
+                    Runtime.getRuntime().gc();\n\
+                    }";
+
+        assert!(find_issues(code).is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+//! Embedded artifacts analysis module.
+//!
+//! Applications can carry other applications inside them: Wear OS applications embedded as raw
+//! resources for older watches, Instant App bundles with the APK files of each feature, and the
+//! metadata used to pair and run along companion devices. Their code is decompiled with the
+//! payloads, so this module reports their presence, so that they don't go unnoticed inside the
+//! host application.
+
+use std::{fs, path::Path};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    axml::is_binary_xml,
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    static_analysis::{
+        manifest::{get_line, Manifest},
+        payloads::{bundle_entries, find_payloads, PayloadKind, WEAR_APK_NAME},
+        resources::Resources,
+    },
+    Config, PARSER_CONFIG,
+};
+
+/// Meta-data pointing to the description of the embedded Wear OS application.
+const WEAR_APP_META_DATA: &str = "com.google.android.wearable.beta.app";
+/// Meta-data of Wear OS applications that work without a paired phone.
+const WEAR_STANDALONE_META_DATA: &str = "com.google.android.wearable.standalone";
+/// Root elements of the description of the embedded Wear OS application.
+const WEAR_APP_ROOT_ELEMENTS: [&str; 1] = ["wearableApp"];
+/// Shared library of the Wear OS platform.
+const WEAR_LIBRARY: &str = "com.google.android.wearable";
+/// Features declaring the use of companion devices or watches.
+const COMPANION_FEATURES: [&str; 2] = [
+    "android.software.companion_device_setup",
+    "android.hardware.type.watch",
+];
+/// Prefix of the permissions granted to companion device applications.
+const COMPANION_PERMISSION_PREFIX: &str = "android.permission.REQUEST_COMPANION_";
+
+/// Description of the Wear OS application embedded in a phone application.
+#[derive(Debug, Default, PartialEq)]
+struct WearApp {
+    /// Package of the Wear OS application.
+    package: String,
+    /// Version name of the Wear OS application.
+    version: String,
+    /// Name of the raw resource with the Wear OS application.
+    raw_resource: String,
+}
+
+impl WearApp {
+    /// Parses the XML description of the embedded Wear OS application.
+    fn parse(code: &str) -> Self {
+        let mut wear_app = Self::default();
+        let mut element = String::new();
+        for e in EventReader::new_with_config(code.as_bytes(), PARSER_CONFIG.clone()) {
+            match e {
+                Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                }) => {
+                    if name.local_name == "wearableApp" {
+                        if let Some(package) = attributes
+                            .into_iter()
+                            .find(|attr| attr.name.local_name == "package")
+                        {
+                            wear_app.package = package.value;
+                        }
+                    }
+                    element = name.local_name;
+                }
+                Ok(XmlEvent::Characters(text)) => match element.as_str() {
+                    "versionName" => wear_app.version = text.trim().to_owned(),
+                    "rawPathResId" => wear_app.raw_resource = text.trim().to_owned(),
+                    _ => {}
+                },
+                Ok(XmlEvent::EndElement { .. }) => element.clear(),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        wear_app
+    }
+}
+
+/// Reports the Wear OS, Instant App and companion device artifacts of the application.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: Option<&Manifest>,
+    results: &mut Results,
+) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    wear_app_analysis(config, &dist_folder, manifest, results);
+    instant_app_analysis(config, &dist_folder, manifest, results);
+    if let Some(manifest) = manifest {
+        companion_analysis(config, manifest, results);
+    }
+}
+
+/// Reports the Wear OS application embedded as a raw resource.
+fn wear_app_analysis(
+    config: &Config,
+    dist_folder: &Path,
+    manifest: Option<&Manifest>,
+    results: &mut Results,
+) {
+    let criticality = Criticality::Warning;
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    // The meta-data points to the description, that points to the raw resource with the APK.
+    let resources = Resources::load(dist_folder);
+    let description_reference = manifest
+        .and_then(|manifest| manifest.meta_data(WEAR_APP_META_DATA))
+        .unwrap_or(WEAR_APK_NAME);
+    let description_path = resources.find_xml(description_reference, &WEAR_APP_ROOT_ELEMENTS);
+    let wear_app = match description_path
+        .as_ref()
+        .map(|path| fs::read(dist_folder.join(path)))
+    {
+        Some(Ok(ref code)) if !is_binary_xml(code) => {
+            Some(WearApp::parse(&String::from_utf8_lossy(code)))
+        }
+        _ => None,
+    };
+    let raw_resource = wear_app
+        .as_ref()
+        .map(|wear_app| wear_app.raw_resource.as_str())
+        .filter(|raw_resource| !raw_resource.is_empty())
+        .unwrap_or(WEAR_APK_NAME);
+    let apk_path = resources.find(raw_resource, "raw", "apk");
+    let apk_found = apk_path.is_some();
+    if !apk_found && wear_app.is_none() {
+        return;
+    }
+
+    let mut description = String::from("The application embeds a Wear OS application");
+    if let Some(ref wear_app) = wear_app {
+        if !wear_app.package.is_empty() {
+            description.push_str(&format!(" ({}", wear_app.package));
+            if !wear_app.version.is_empty() {
+                description.push_str(&format!(" {}", wear_app.version));
+            }
+            description.push(')');
+        }
+    }
+    if apk_found {
+        description.push_str(
+            ", that gets installed in the paired watches. It runs with its own permissions and \
+             components, so its code has been decompiled and analyzed with the rest of the \
+             application. Check that it's up to date and that it follows the same security \
+             practices as the phone application.",
+        );
+    } else {
+        description.push_str(
+            ", but its APK file could not be found in the resources, so it has not been analyzed.",
+        );
+    }
+
+    let vulnerability = Vulnerability::new(
+        criticality,
+        "Embedded Wear OS application",
+        description.as_str(),
+        apk_path.or(description_path),
+        None,
+        None,
+        None::<String>,
+    );
+    results.add_vulnerability(vulnerability);
+    print_vulnerability(description, criticality);
+}
+
+/// Reports the Instant App bundles in the resources and the support of Instant Apps.
+fn instant_app_analysis(
+    config: &Config,
+    dist_folder: &Path,
+    manifest: Option<&Manifest>,
+    results: &mut Results,
+) {
+    let criticality = Criticality::Warning;
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    for payload in find_payloads(dist_folder) {
+        if payload.kind() != PayloadKind::ApkBundle {
+            continue;
+        }
+        let apks = match bundle_entries(dist_folder.join(payload.path())) {
+            Ok(apks) => apks,
+            Err(e) => {
+                print_warning(format!(
+                    "the bundle {} could not be read: {}",
+                    payload.path().display(),
+                    e
+                ));
+                continue;
+            }
+        };
+
+        let description = format!(
+            "An Instant App bundle was found in the resources of the application, with the \
+             following APK files: {}. Each of them has been decompiled and analyzed with the \
+             rest of the application. Bundled applications are not installed through the store, \
+             so they don't get the same checks and updates.",
+            apks.join(", ")
+        );
+        let vulnerability = Vulnerability::new(
+            criticality,
+            "Instant App bundle",
+            description.as_str(),
+            Some(payload.path()),
+            None,
+            None,
+            None::<String>,
+        );
+        results.add_vulnerability(vulnerability);
+        print_vulnerability(description, criticality);
+    }
+
+    if let Some(manifest) = manifest {
+        if manifest.is_instant() {
+            let description = "The application can run as an Instant App, without being \
+                               installed. Instant Apps run in a more restrictive sandbox, but \
+                               they can be launched from any link that they handle, so their \
+                               entry points must validate every input.";
+            let line = get_line(manifest.code(), "targetSandboxVersion")
+                .or_else(|_| get_line(manifest.code(), "instant="))
+                .ok();
+            let code = line.map(|l| get_code(manifest.code(), l, l));
+
+            let vulnerability = Vulnerability::new(
+                criticality,
+                "Instant App",
+                description,
+                Some("AndroidManifest.xml"),
+                line,
+                line,
+                code,
+            );
+            results.add_vulnerability(vulnerability);
+            print_vulnerability(description, criticality);
+        }
+    }
+}
+
+/// Reports the companion device and Wear OS metadata declared in the manifest.
+fn companion_analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    let criticality = Criticality::Warning;
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let mut evidence: Vec<&str> = manifest
+        .features()
+        .iter()
+        .chain(manifest.libraries())
+        .map(String::as_str)
+        .filter(|name| COMPANION_FEATURES.contains(name) || *name == WEAR_LIBRARY)
+        .chain(
+            manifest
+                .requested_permissions()
+                .iter()
+                .map(String::as_str)
+                .filter(|permission| permission.starts_with(COMPANION_PERMISSION_PREFIX)),
+        )
+        .collect();
+    if manifest.meta_data(WEAR_STANDALONE_META_DATA).is_some() {
+        evidence.push(WEAR_STANDALONE_META_DATA);
+    }
+    if evidence.is_empty() {
+        return;
+    }
+
+    let description = format!(
+        "The application runs on, or along with, companion devices such as watches, as declared \
+         by {}. Data exchanged with companion devices leaves the phone, and companion \
+         applications can be granted to run and use data in the background, so check that the \
+         communication is authenticated and that only the needed data is shared.",
+        evidence.join(", ")
+    );
+    let line = get_line(manifest.code(), evidence[0]).ok();
+    let code = line.map(|l| get_code(manifest.code(), l, l));
+
+    let vulnerability = Vulnerability::new(
+        criticality,
+        "Companion device integration",
+        description.as_str(),
+        Some("AndroidManifest.xml"),
+        line,
+        line,
+        code,
+    );
+    results.add_vulnerability(vulnerability);
+    print_vulnerability(description, criticality);
+}
+
+/// Embedded artifacts testing module.
+#[cfg(test)]
+mod test {
+    use super::WearApp;
+
+    /// Checks the parsing of the description of the embedded Wear OS application.
+    #[test]
+    fn it_parse_wear_app() {
+        let wear_app = WearApp::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<wearableApp package="com.example.wear">
+    <versionCode>12</versionCode>
+    <versionName>1.2</versionName>
+    <rawPathResId>wear_app</rawPathResId>
+</wearableApp>"#,
+        );
+        assert_eq!(wear_app.package, "com.example.wear");
+        assert_eq!(wear_app.version, "1.2");
+        assert_eq!(wear_app.raw_resource, "wear_app");
+
+        assert_eq!(WearApp::parse("<resources/>"), WearApp::default());
+    }
+}
@@ -0,0 +1,191 @@
+//! Attack surface escalation module.
+//!
+//! Records the permissions and the exported components of each analyzed version in the history
+//! database, and reports the ones that no previous version of the application had. New
+//! permissions and entry points are the usual way in which an update starts collecting more data
+//! or exposing more functionality, so they are reported even if they are not vulnerable by
+//! themselves. An external command can be notified of them, for example to alert the team that
+//! tracks the application.
+
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use failure::{bail, Error, ResultExt};
+use serde_json::{self, json};
+
+use crate::{
+    criticality::Criticality,
+    get_code,
+    history::{History, Surface, HISTORY_FILE},
+    print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// Gets the attack surface of the application described by the manifest.
+fn surface(manifest: &Manifest) -> Surface {
+    Surface::new(
+        manifest.requested_permissions().iter().cloned().collect(),
+        manifest
+            .components()
+            .iter()
+            .filter(|component| component.is_exported(manifest.min_sdk()))
+            .map(|component| format!("{} {}", component.kind().tag(), component.name()))
+            .collect(),
+    )
+}
+
+/// Records the attack surface of the application in the history database, and reports the
+/// permissions and exported components that previous versions didn't have.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    let surface = surface(manifest);
+    let added = match record_surface(config, results, surface) {
+        Ok(Some(added)) => added,
+        Ok(None) => return,
+        Err(e) => {
+            print_warning(format!(
+                "could not update the history database with the attack surface of the \
+                 application: {}",
+                e
+            ));
+            return;
+        }
+    };
+    if added.is_empty() {
+        return;
+    }
+
+    for permission in added.permissions() {
+        let description = format!(
+            "This version requests the `{}` permission, that no previously analyzed version of \
+             the application requested. Check that the new access is needed and that it's \
+             disclosed to the users.",
+            permission
+        );
+        report(
+            config,
+            manifest,
+            results,
+            "Permission escalation",
+            description,
+            permission,
+        );
+    }
+    for component in added.exported_components() {
+        let name = component.splitn(2, ' ').nth(1).unwrap_or(component);
+        let description = format!(
+            "This version exports the `{}` component, that no previously analyzed version of \
+             the application exported. Other applications can now interact with it, so check \
+             that it validates its input and that it's protected with a permission if needed.",
+            component
+        );
+        report(
+            config,
+            manifest,
+            results,
+            "New exported component",
+            description,
+            &format!("android:name=\"{}\"", name),
+        );
+    }
+
+    if let Some(hook) = config.escalation_hook() {
+        if let Err(e) = notify(hook, results, &added) {
+            print_warning(format!(
+                "the escalation hook {} could not be notified: {}",
+                hook.display(),
+                e
+            ));
+        }
+    }
+}
+
+/// Records the attack surface in the history database.
+///
+/// It returns the permissions and exported components that are new in this version, or `None`
+/// if no previous version has a recorded attack surface.
+fn record_surface(
+    config: &Config,
+    results: &Results,
+    surface: Surface,
+) -> Result<Option<Surface>, Error> {
+    fs::create_dir_all(config.results_folder())?;
+    let history_path = config.results_folder().join(HISTORY_FILE);
+    let mut history = History::load(&history_path)?;
+
+    let added = history
+        .previous_surface(results.app_package(), results.app_version_num())
+        .map(|previous| surface.added(&previous));
+    history.set_surface(
+        results.app_package(),
+        results.app_version(),
+        results.app_version_num(),
+        surface,
+    );
+    history.save(&history_path)?;
+
+    Ok(added)
+}
+
+/// Reports an escalation, pointing to the first line of the manifest with the given text.
+fn report(
+    config: &Config,
+    manifest: &Manifest,
+    results: &mut Results,
+    name: &str,
+    description: String,
+    needle: &str,
+) {
+    let criticality = Criticality::Medium;
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let line = get_line(manifest.code(), needle).ok();
+    let code = line.map(|l| get_code(manifest.code(), l, l));
+    results.add_vulnerability(Vulnerability::new(
+        criticality,
+        name,
+        description.as_str(),
+        Some("AndroidManifest.xml"),
+        line,
+        line,
+        code,
+    ));
+    print_vulnerability(description, criticality);
+}
+
+/// Notifies the escalation hook of the new permissions and exported components.
+///
+/// The hook is run with the package and the version of the application as arguments, and the
+/// escalations in JSON format in its standard input.
+fn notify(hook: &Path, results: &Results, added: &Surface) -> Result<(), Error> {
+    let notification = json!({
+        "package": results.app_package(),
+        "version": results.app_version(),
+        "version_number": results.app_version_num(),
+        "permissions": added.permissions(),
+        "exported_components": added.exported_components()
+    });
+
+    let mut child = Command::new(hook)
+        .arg(results.app_package())
+        .arg(results.app_version())
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("the hook could not be run")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        serde_json::to_writer_pretty(&mut stdin, &notification)?;
+        stdin.flush()?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("the hook returned {}", status);
+    }
+    Ok(())
+}
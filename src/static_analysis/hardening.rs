@@ -0,0 +1,246 @@
+//! Manifest hardening analysis module.
+//!
+//! Some attributes of the `<application>` element of the manifest weaken the security of the
+//! whole application. Their risk depends on the SDK version that the application targets, since
+//! newer versions of Android change their defaults and their effects, so each finding gets its
+//! criticality and its remediation from the target SDK of the application.
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// First SDK version that blocks cleartext traffic by default (Android 9).
+const CLEARTEXT_BLOCKED_SDK: u32 = 28;
+/// SDK version that introduced scoped storage (Android 10).
+const SCOPED_STORAGE_SDK: u32 = 29;
+/// First SDK version that enforces scoped storage (Android 11).
+const SCOPED_STORAGE_ENFORCED_SDK: u32 = 30;
+/// First SDK version whose applications are excluded from `adb backup` (Android 12).
+const ADB_BACKUP_EXCLUDED_SDK: u32 = 31;
+
+/// Hardening setting of the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    /// The application is debuggable.
+    Debuggable,
+    /// The application allows backups of its data.
+    AllowBackup,
+    /// The application declares that it uses cleartext traffic.
+    UsesCleartextTraffic,
+    /// The application does not declare whether it uses cleartext traffic.
+    DefaultCleartextTraffic,
+    /// The application is marked as a test only application.
+    TestOnly,
+    /// The application requests the legacy external storage.
+    RequestLegacyExternalStorage,
+}
+
+impl Setting {
+    /// Gets the name of the finding of the setting.
+    fn name(self) -> &'static str {
+        match self {
+            Setting::Debuggable => "Manifest Debug",
+            Setting::AllowBackup => "Allows Backup",
+            Setting::UsesCleartextTraffic => "Cleartext traffic permitted",
+            Setting::DefaultCleartextTraffic => "Cleartext traffic permitted by default",
+            Setting::TestOnly => "Test only application",
+            Setting::RequestLegacyExternalStorage => "Legacy external storage",
+        }
+    }
+
+    /// Gets the attribute of the setting in the manifest, if it's declared.
+    fn attribute(self) -> Option<&'static str> {
+        match self {
+            Setting::Debuggable => Some("android:debuggable=\"true\""),
+            Setting::AllowBackup => Some("android:allowBackup=\"true\""),
+            Setting::UsesCleartextTraffic => Some("android:usesCleartextTraffic=\"true\""),
+            Setting::DefaultCleartextTraffic => None,
+            Setting::TestOnly => Some("android:testOnly=\"true\""),
+            Setting::RequestLegacyExternalStorage => {
+                Some("android:requestLegacyExternalStorage=\"true\"")
+            }
+        }
+    }
+
+    /// Gets the CWE of the setting.
+    fn cwe(self) -> u32 {
+        match self {
+            Setting::Debuggable | Setting::TestOnly => 489,
+            Setting::AllowBackup => 530,
+            Setting::UsesCleartextTraffic | Setting::DefaultCleartextTraffic => 319,
+            Setting::RequestLegacyExternalStorage => 276,
+        }
+    }
+
+    /// Gets the criticality and the description of the setting, with its remediation, for an
+    /// application that targets the given SDK version.
+    fn check(self, target_sdk: u32) -> (Criticality, &'static str) {
+        match self {
+            Setting::Debuggable => (
+                Criticality::Critical,
+                "The application is in debug mode. This allows any malicious person to inject \
+                 arbitrary code in the application. This option should only be used while in \
+                 development: remove the `android:debuggable` attribute, since the build tools \
+                 set it in debug builds.",
+            ),
+            Setting::AllowBackup if target_sdk < ADB_BACKUP_EXCLUDED_SDK => (
+                Criticality::Medium,
+                "This option allows backups of the application data via adb. Malicious people \
+                 with physical access could use adb to get private data of your app into their \
+                 PC. Set `android:allowBackup=\"false\"`, or exclude the private files with \
+                 `android:fullBackupContent` rules.",
+            ),
+            Setting::AllowBackup => (
+                Criticality::Low,
+                "This option allows backups of the application data. Since the application \
+                 targets Android 12, its data is excluded from adb backups, but it's still copied \
+                 to cloud backups and in device to device transfers. Exclude the private files \
+                 with `android:dataExtractionRules`, or set `android:allowBackup=\"false\"`.",
+            ),
+            Setting::UsesCleartextTraffic if target_sdk >= CLEARTEXT_BLOCKED_SDK => (
+                Criticality::Medium,
+                "The application sets `android:usesCleartextTraffic=\"true\"`, opting out of the \
+                 default of Android 9, that blocks cleartext traffic. Anyone in the same network \
+                 can read and modify the HTTP connections of the application. Use HTTPS, and a \
+                 network security configuration to permit cleartext traffic only for the domains \
+                 that need it.",
+            ),
+            Setting::UsesCleartextTraffic => (
+                Criticality::Low,
+                "The application sets `android:usesCleartextTraffic=\"true\"`, so it permits \
+                 cleartext traffic for every domain. Anyone in the same network can read and \
+                 modify the HTTP connections of the application. Use HTTPS, and remove the \
+                 attribute when targeting Android 9 or newer, so that cleartext traffic gets \
+                 blocked by default.",
+            ),
+            Setting::DefaultCleartextTraffic => (
+                Criticality::Low,
+                "The application targets an SDK older than Android 9 and does not set \
+                 `android:usesCleartextTraffic`, so cleartext traffic is permitted for every \
+                 domain. Target Android 9 or newer, or set the attribute to `false`.",
+            ),
+            Setting::TestOnly => (
+                Criticality::Medium,
+                "The application is marked with `android:testOnly=\"true\"`, so it's a test build \
+                 that can only be installed with adb. Test builds can include testing code and \
+                 relaxed security settings, and should not be distributed. Remove the attribute, \
+                 that the development tools add when running the application.",
+            ),
+            Setting::RequestLegacyExternalStorage if target_sdk == SCOPED_STORAGE_SDK => (
+                Criticality::Medium,
+                "The application sets `android:requestLegacyExternalStorage=\"true\"`, opting out \
+                 of scoped storage in Android 10. The files that it writes in the external \
+                 storage can be read and modified by any application with the storage \
+                 permissions. Migrate to scoped storage and remove the attribute.",
+            ),
+            Setting::RequestLegacyExternalStorage if target_sdk >= SCOPED_STORAGE_ENFORCED_SDK => (
+                Criticality::Low,
+                "The application sets `android:requestLegacyExternalStorage=\"true\"`. Since it \
+                 targets Android 11, the attribute is ignored in Android 11 and newer, but it \
+                 still opts out of scoped storage in Android 10 devices, where the files that it \
+                 writes in the external storage can be read by other applications. Remove the \
+                 attribute.",
+            ),
+            Setting::RequestLegacyExternalStorage => (
+                Criticality::Warning,
+                "The application sets `android:requestLegacyExternalStorage=\"true\"`, but it \
+                 targets an SDK older than Android 10, so it uses the legacy external storage \
+                 anyway, where its files can be read by other applications. Target Android 11 or \
+                 newer and migrate to scoped storage.",
+            ),
+        }
+    }
+}
+
+/// Reports the settings of the manifest that weaken the security of the application.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    // The target SDK version defaults to the minimum SDK version.
+    let target_sdk = manifest.target_sdk().unwrap_or_else(|| manifest.min_sdk());
+
+    let mut settings = Vec::new();
+    if manifest.is_debug() {
+        settings.push(Setting::Debuggable);
+    }
+    if manifest.allows_backup() {
+        settings.push(Setting::AllowBackup);
+    }
+    // The network security configuration overrides the attribute, and it's checked separately.
+    if manifest.network_security_config().is_none() {
+        match manifest.uses_cleartext_traffic() {
+            Some(true) => settings.push(Setting::UsesCleartextTraffic),
+            None if target_sdk < CLEARTEXT_BLOCKED_SDK => {
+                settings.push(Setting::DefaultCleartextTraffic)
+            }
+            _ => {}
+        }
+    }
+    if manifest.is_test_only() {
+        settings.push(Setting::TestOnly);
+    }
+    if manifest.requests_legacy_external_storage() {
+        settings.push(Setting::RequestLegacyExternalStorage);
+    }
+
+    for setting in settings {
+        let (criticality, description) = setting.check(target_sdk);
+        if criticality < config.min_criticality() {
+            continue;
+        }
+
+        let line = setting
+            .attribute()
+            .and_then(|attribute| get_line(manifest.code(), attribute).ok());
+        let mut vulnerability = VulnerabilityBuilder::new(criticality, setting.name(), description)
+            .file("AndroidManifest.xml")
+            .cwe(setting.cwe());
+        if let Some(line) = line {
+            let code = get_code(manifest.code(), line, line);
+            vulnerability = vulnerability.lines(line, line).code(code);
+        }
+        results.add_vulnerability(vulnerability.build());
+        print_vulnerability(description, criticality);
+    }
+}
+
+/// Manifest hardening analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::Setting;
+    use crate::criticality::Criticality;
+
+    /// Checks that the criticality of the settings scales with the target SDK version.
+    #[test]
+    fn it_check() {
+        let criticality = |setting: Setting, target_sdk| setting.check(target_sdk).0;
+
+        assert_eq!(criticality(Setting::Debuggable, 19), Criticality::Critical);
+        assert_eq!(criticality(Setting::Debuggable, 33), Criticality::Critical);
+        assert_eq!(criticality(Setting::AllowBackup, 30), Criticality::Medium);
+        assert_eq!(criticality(Setting::AllowBackup, 31), Criticality::Low);
+        assert_eq!(
+            criticality(Setting::UsesCleartextTraffic, 27),
+            Criticality::Low
+        );
+        assert_eq!(
+            criticality(Setting::UsesCleartextTraffic, 28),
+            Criticality::Medium
+        );
+        assert_eq!(
+            criticality(Setting::RequestLegacyExternalStorage, 28),
+            Criticality::Warning
+        );
+        assert_eq!(
+            criticality(Setting::RequestLegacyExternalStorage, 29),
+            Criticality::Medium
+        );
+        assert_eq!(
+            criticality(Setting::RequestLegacyExternalStorage, 30),
+            Criticality::Low
+        );
+        assert_eq!(criticality(Setting::TestOnly, 33), Criticality::Medium);
+    }
+}
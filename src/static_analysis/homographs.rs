@@ -0,0 +1,313 @@
+//! Homograph domain analysis module.
+//!
+//! Internationalized domain names can use characters of any script, and many of them look exactly
+//! like Latin letters: the Cyrillic `а` of `pаypal.com` can't be told apart from the Latin `a`.
+//! Phishing applications hide such domains in their resources and code, usually in their punycode
+//! form (`xn--pypal-4ve.com`), so that they don't stand out. This module extracts the hosts of the
+//! URLs in the code, the resources and the assets, and reports the internationalized ones that
+//! look like the domain of a well-known brand.
+
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::code::get_line_for;
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, VulnerabilityBuilder},
+    utils::add_files_with_extensions,
+    Config,
+};
+
+/// CWE of the homograph findings: insufficient visual distinction of homoglyphs.
+const HOMOGRAPH_CWE: u32 = 1007;
+
+/// Extensions of the text files searched for URLs.
+const TEXT_EXTENSIONS: [&str; 9] = [
+    "java",
+    "xml",
+    "json",
+    "js",
+    "html",
+    "htm",
+    "txt",
+    "properties",
+    "smali",
+];
+
+/// Well-known brands that phishing domains imitate, as the label of their domains.
+const BRANDS: [&str; 30] = [
+    "amazon",
+    "apple",
+    "bankofamerica",
+    "binance",
+    "chase",
+    "citibank",
+    "coinbase",
+    "dropbox",
+    "ebay",
+    "facebook",
+    "github",
+    "gmail",
+    "google",
+    "hsbc",
+    "icloud",
+    "instagram",
+    "linkedin",
+    "microsoft",
+    "netflix",
+    "outlook",
+    "paypal",
+    "santander",
+    "steam",
+    "telegram",
+    "tiktok",
+    "twitter",
+    "wellsfargo",
+    "whatsapp",
+    "yahoo",
+    "youtube",
+];
+
+/// Characters of other scripts, and accented Latin letters, that look like an ASCII letter, with
+/// that letter.
+const CONFUSABLES: [(char, char); 69] = [
+    // Cyrillic.
+    ('а', 'a'),
+    ('с', 'c'),
+    ('ԁ', 'd'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ӏ', 'l'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('ѕ', 's'),
+    ('у', 'y'),
+    ('ԝ', 'w'),
+    ('х', 'x'),
+    // Greek.
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+    // Armenian.
+    ('հ', 'h'),
+    ('ո', 'n'),
+    ('ս', 'u'),
+    ('օ', 'o'),
+    // Latin.
+    ('à', 'a'),
+    ('á', 'a'),
+    ('â', 'a'),
+    ('ã', 'a'),
+    ('ä', 'a'),
+    ('å', 'a'),
+    ('ā', 'a'),
+    ('ç', 'c'),
+    ('ć', 'c'),
+    ('è', 'e'),
+    ('é', 'e'),
+    ('ê', 'e'),
+    ('ë', 'e'),
+    ('ē', 'e'),
+    ('ğ', 'g'),
+    ('ɡ', 'g'),
+    ('ì', 'i'),
+    ('í', 'i'),
+    ('î', 'i'),
+    ('ï', 'i'),
+    ('ı', 'i'),
+    ('ł', 'l'),
+    ('ñ', 'n'),
+    ('ń', 'n'),
+    ('ò', 'o'),
+    ('ó', 'o'),
+    ('ô', 'o'),
+    ('õ', 'o'),
+    ('ö', 'o'),
+    ('ø', 'o'),
+    ('ō', 'o'),
+    ('ś', 's'),
+    ('š', 's'),
+    ('ù', 'u'),
+    ('ú', 'u'),
+    ('û', 'u'),
+    ('ü', 'u'),
+    ('ý', 'y'),
+    ('ÿ', 'y'),
+    ('ź', 'z'),
+    ('ż', 'z'),
+    ('ž', 'z'),
+];
+
+/// Searches the files of the application for internationalized domains that look like the
+/// domains of well-known brands.
+pub fn analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut Results) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let mut files = Vec::new();
+    if let Err(e) = add_files_with_extensions(&dist_folder, &TEXT_EXTENSIONS, &mut files) {
+        print_warning(format!(
+            "there was an error searching the application for homograph domains: {}",
+            e
+        ));
+    }
+
+    for file in files {
+        let code = match fs::read_to_string(&file) {
+            Ok(code) => code,
+            // Binary files with a text extension are common in the assets.
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => continue,
+            Err(e) => {
+                print_warning(format!(
+                    "could not read {} searching for homograph domains: {}",
+                    file.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        let path = file.strip_prefix(&dist_folder).unwrap_or(&file);
+        check_code(config, path, &code, results);
+    }
+}
+
+/// Checks the hosts of the URLs in the given file, reporting each homograph domain once.
+fn check_code(config: &Config, path: &Path, code: &str, results: &mut Results) {
+    lazy_static! {
+        static ref URL_HOST: Regex =
+            Regex::new(r#"(?i)(?:\b[a-z][a-z0-9+.-]*://|\bwww\.)([^\s/?#:@"'<>\\;,()\[\]{}]+)"#)
+                .unwrap();
+    }
+
+    let mut reported = BTreeSet::new();
+    for captures in URL_HOST.captures_iter(code) {
+        let host = match captures.get(1) {
+            Some(host) => host,
+            None => continue,
+        };
+        let (domain, brand) = match check_host(host.as_str()) {
+            Some(homograph) => homograph,
+            None => continue,
+        };
+        if !reported.insert(domain.clone()) {
+            continue;
+        }
+
+        let criticality = Criticality::High;
+        if criticality < config.min_criticality() {
+            continue;
+        }
+        let description = format!(
+            "The application contains the `{}` domain, displayed as `{}`. It uses characters of \
+             other scripts that look like Latin letters, so it can't be told apart from the \
+             domains of {}. Phishing applications use such domains to steal credentials, or to \
+             hide their servers from a manual review.",
+            host.as_str(),
+            domain,
+            brand
+        );
+        let start_line = get_line_for(host.start(), code);
+        let end_line = get_line_for(host.end(), code);
+        let vulnerability =
+            VulnerabilityBuilder::new(criticality, "Homograph domain", description.as_str())
+                .file(path)
+                .lines(start_line, end_line)
+                .code(get_code(code, start_line, end_line))
+                .cwe(HOMOGRAPH_CWE)
+                .build();
+        results.add_vulnerability(vulnerability);
+        print_vulnerability(description, criticality);
+    }
+}
+
+/// Checks if a host is an internationalized domain that looks like the domain of a well-known
+/// brand.
+///
+/// If it is, it returns the Unicode form of the domain and the imitated brand.
+fn check_host(host: &str) -> Option<(String, &'static str)> {
+    let host = host.trim_end_matches('.');
+    let (domain, _) = idna::domain_to_unicode(host);
+    if domain.is_ascii() {
+        return None;
+    }
+
+    domain
+        .split('.')
+        .filter(|label| !label.is_ascii())
+        .find_map(|label| {
+            let skeleton = skeleton(label)?;
+            BRANDS
+                .iter()
+                .find(|brand| **brand == skeleton)
+                .map(|brand| (domain.clone(), *brand))
+        })
+}
+
+/// Gets the ASCII letters that a domain label looks like, if all of its characters look like
+/// ASCII characters.
+fn skeleton(label: &str) -> Option<String> {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_ascii() {
+                Some(c.to_ascii_lowercase())
+            } else {
+                CONFUSABLES
+                    .iter()
+                    .find(|(confusable, _)| *confusable == c)
+                    .map(|&(_, ascii)| ascii)
+            }
+        })
+        .collect()
+}
+
+/// Homograph domain analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{check_host, skeleton};
+
+    /// Checks the ASCII skeletons of domain labels.
+    #[test]
+    fn it_skeleton() {
+        assert_eq!(skeleton("pаypal").unwrap(), "paypal");
+        assert_eq!(skeleton("аррӏе").unwrap(), "apple");
+        assert_eq!(skeleton("Gооgle").unwrap(), "google");
+        assert!(skeleton("пример").is_none());
+    }
+
+    /// Checks the detection of homograph domains, in their Unicode and punycode forms.
+    #[test]
+    fn it_check_host() {
+        assert_eq!(
+            check_host("xn--pypal-4ve.com"),
+            Some(("pаypal.com".to_owned(), "paypal"))
+        );
+        assert_eq!(
+            check_host("xn--ggle-55da.com"),
+            Some(("gооgle.com".to_owned(), "google"))
+        );
+        assert_eq!(
+            check_host("login.facebοok.com"),
+            Some(("login.facebοok.com".to_owned(), "facebook"))
+        );
+        assert_eq!(
+            check_host("аррӏе.com."),
+            Some(("аррӏе.com".to_owned(), "apple"))
+        );
+
+        assert!(check_host("paypal.com").is_none());
+        assert!(check_host("xn--e1afmkfd.xn--p1ai").is_none());
+        assert!(check_host("xn--mnchen-3ya.de").is_none());
+        assert!(check_host("xn--pple-43d.example.com").is_some());
+        assert!(check_host("bücher.example").is_none());
+    }
+}
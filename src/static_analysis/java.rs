@@ -0,0 +1,308 @@
+//! Decompiled Java structure module.
+//!
+//! Some insecure implementations can't be found reliably with single-line regular expressions:
+//! they depend on what the body of a method does, and their names can appear in comments or
+//! strings. This module gives the analyses a simple view of the structure of the decompiled code:
+//! the code with its comments and literals masked, and the methods declared in it with their
+//! bodies.
+
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Method declarations, and other blocks with parentheses, such as the anonymous classes.
+    static ref METHOD: Regex =
+        Regex::new(r"\b([A-Za-z_$][\w$]*)\s*\(([^()]*)\)\s*(?:throws\s+[\w$.,\s]+)?\{").unwrap();
+}
+
+/// Keywords of the blocks that are matched as methods, but are statements.
+const STATEMENT_KEYWORDS: [&str; 6] = ["if", "for", "while", "switch", "catch", "synchronized"];
+
+/// Method declared in the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Method {
+    /// Name of the method.
+    name: String,
+    /// Parameters of the method, as declared.
+    parameters: String,
+    /// Index of the declaration in the code.
+    start: usize,
+    /// Range of the body of the method in the code, without its braces.
+    body: Range<usize>,
+}
+
+impl Method {
+    /// Gets the name of the method.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the types of the parameters of the method.
+    pub fn parameter_types(&self) -> Vec<&str> {
+        self.parameters
+            .split(',')
+            .filter_map(|parameter| parameter.split_whitespace().rev().nth(1))
+            .collect()
+    }
+
+    /// Gets the names of the parameters of the method.
+    pub fn parameter_names(&self) -> Vec<&str> {
+        self.parameters
+            .split(',')
+            .filter_map(|parameter| parameter.split_whitespace().last())
+            .collect()
+    }
+
+    /// Gets the index of the declaration of the method in the code.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Gets the range of the body of the method in the code, without its braces.
+    pub fn body(&self) -> Range<usize> {
+        self.body.clone()
+    }
+
+    /// Checks if the given index of the code is in the body of the method.
+    pub fn contains(&self, index: usize) -> bool {
+        self.body.start <= index && index < self.body.end
+    }
+
+    /// Gets the statements of the body of the method in the given masked code, without
+    /// whitespace, such as `returntrue;`.
+    pub fn statements(&self, masked: &str) -> String {
+        masked[self.body.clone()]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    }
+}
+
+/// Masks the comments and the contents of the string and character literals of the given code
+/// with spaces.
+///
+/// Line breaks and the lengths of the masked characters are kept, so the indices and the lines of
+/// the masked code are the same as in the original code.
+pub fn mask(code: &str) -> String {
+    let mut masked = String::with_capacity(code.len());
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                masked.push_str("  ");
+                let _ = chars.next();
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    mask_char(&mut masked, next);
+                    let _ = chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                masked.push_str("  ");
+                let _ = chars.next();
+                let mut previous = ' ';
+                for next in &mut chars {
+                    mask_char(&mut masked, next);
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            '"' | '\'' => {
+                masked.push(c);
+                while let Some(next) = chars.next() {
+                    if next == c || next == '\n' {
+                        masked.push(next);
+                        break;
+                    }
+                    mask_char(&mut masked, next);
+                    if next == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            mask_char(&mut masked, escaped);
+                        }
+                    }
+                }
+            }
+            _ => masked.push(c),
+        }
+    }
+    masked
+}
+
+/// Adds the mask of the given character to the masked code.
+fn mask_char(masked: &mut String, c: char) {
+    if c == '\n' {
+        masked.push('\n');
+    } else {
+        for _ in 0..c.len_utf8() {
+            masked.push(' ');
+        }
+    }
+}
+
+/// Gets the methods declared in the given masked code, in order.
+///
+/// Constructors and the anonymous classes created with `new` are returned too, since they can't
+/// be told apart from the methods without parsing the code.
+pub fn methods(masked: &str) -> Vec<Method> {
+    METHOD
+        .captures_iter(masked)
+        .filter(|captures| !STATEMENT_KEYWORDS.contains(&&captures[1]))
+        .filter_map(|captures| {
+            let declaration = captures.get(0)?;
+            let body_start = declaration.end();
+            Some(Method {
+                name: captures[1].to_owned(),
+                parameters: captures[2].to_owned(),
+                start: declaration.start(),
+                body: body_start..block_end(masked, body_start),
+            })
+        })
+        .collect()
+}
+
+/// Gets the innermost of the given methods whose body contains the given index of the code.
+pub fn enclosing_method(methods: &[Method], index: usize) -> Option<&Method> {
+    methods.iter().rev().find(|method| method.contains(index))
+}
+
+/// Gets the ranges of the arguments of the call whose opening parenthesis is just before the
+/// given index of the masked code.
+///
+/// The arguments are split by the commas outside of parentheses, brackets and braces, so the
+/// commas of generic types are not told apart.
+pub fn arguments(masked: &str, start: usize) -> Vec<Range<usize>> {
+    let mut arguments = Vec::new();
+    let mut depth = 0_usize;
+    let mut argument_start = start;
+    for (i, c) in masked[start..].char_indices() {
+        let index = start + i;
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(argument_start..index);
+                argument_start = index + 1;
+            }
+            ')' => {
+                if !arguments.is_empty() || !masked[argument_start..index].trim().is_empty() {
+                    arguments.push(argument_start..index);
+                }
+                return arguments;
+            }
+            _ => {}
+        }
+    }
+    arguments
+}
+
+/// Gets the index of the brace that closes the block opened just before the given index, or the
+/// end of the code if it's not closed.
+///
+/// Braces in strings and comments are not ignored, so the code should be masked first.
+pub fn block_end(code: &str, start: usize) -> usize {
+    let mut depth = 1_usize;
+    for (i, c) in code[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// Decompiled Java structure testing module.
+#[cfg(test)]
+mod test {
+    use super::{arguments, enclosing_method, mask, methods};
+
+    /// Checks that comments and literals are masked, keeping the indices.
+    #[test]
+    fn it_mask() {
+        let code = "String a = \"{ // \\\" }\"; // return true;\n\
+                    /* checkServerTrusted() {\n} */ char b = '}';";
+        let masked = mask(code);
+        assert_eq!(masked.len(), code.len());
+        assert_eq!(masked.lines().count(), code.lines().count());
+        assert!(masked.starts_with("String a = \""));
+        assert!(masked.ends_with("char b = ' ';"));
+        assert!(!masked.contains('{'));
+        assert!(!masked.contains('}'));
+        assert!(!masked.contains("return"));
+        assert!(!masked.contains("checkServerTrusted"));
+
+        assert_eq!(mask("String ñ = \"ñ\";").len(), "String ñ = \"ñ\";".len());
+    }
+
+    /// Checks that the methods and their bodies are found.
+    #[test]
+    fn it_methods() {
+        let code = mask(
+            "public class Verifier implements HostnameVerifier {\n\
+             public boolean verify(String hostname, SSLSession session) {\n\
+             if (hostname != null) { log(\"}\"); }\n\
+             return true;\n\
+             }\n\
+             public void check(X509Certificate[] chain, String authType) throws \
+             CertificateException {}\n\
+             }",
+        );
+
+        let methods = methods(&code);
+        let names = methods
+            .iter()
+            .map(|method| method.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["verify", "check"]);
+        assert_eq!(methods[0].parameter_types(), vec!["String", "SSLSession"]);
+        assert_eq!(
+            methods[0].statements(&code),
+            "if(hostname!=null){log(\"\");}returntrue;"
+        );
+        assert_eq!(
+            methods[1].parameter_types(),
+            vec!["X509Certificate[]", "String"]
+        );
+        assert_eq!(methods[1].statements(&code), "");
+        assert_eq!(methods[1].parameter_names(), vec!["chain", "authType"]);
+
+        let index = code.find("return true").unwrap();
+        assert_eq!(
+            enclosing_method(&methods, index).map(|method| method.name()),
+            Some("verify")
+        );
+        assert!(enclosing_method(&methods, 0).is_none());
+    }
+
+    /// Checks that the arguments of the calls are split at the top level.
+    #[test]
+    fn it_arguments() {
+        let query = "SELECT a, b FROM t WHERE id = ";
+        let code = mask(&format!("db.rawQuery(\"{}\" + get(id, 1), null);", query));
+        let found = arguments(&code, code.find('(').unwrap() + 1)
+            .into_iter()
+            .map(|range| code[range].trim().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                format!("\"{}\" + get(id, 1)", " ".repeat(query.len())),
+                "null".to_owned()
+            ]
+        );
+
+        let code = "db.close();";
+        assert!(arguments(code, code.find('(').unwrap() + 1).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Library detection module.
+//!
+//! Attributes the decompiled code to the application developers or to the SDKs and libraries
+//! bundled in the application, using a catalog of the packages of well known libraries. The fix
+//! for a finding in an SDK is usually updating or replacing it, instead of changing the code.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+/// Catalog of well known SDKs and libraries, by package pattern.
+const CATALOG: [(&str, &str); 32] = [
+    ("android.support.**", "Android Support Library"),
+    ("androidx.**", "AndroidX"),
+    ("com.google.android.gms.**", "Google Play Services"),
+    ("com.google.firebase.**", "Firebase"),
+    ("com.google.android.exoplayer2.**", "ExoPlayer"),
+    ("com.google.gson.**", "Gson"),
+    ("com.google.protobuf.**", "Protocol Buffers"),
+    ("com.google.common.**", "Guava"),
+    ("com.facebook.**", "Facebook SDK"),
+    ("com.crashlytics.**", "Crashlytics"),
+    ("io.fabric.**", "Fabric"),
+    ("com.squareup.okhttp.**", "OkHttp"),
+    ("okhttp3.**", "OkHttp"),
+    ("okio.**", "Okio"),
+    ("retrofit2.**", "Retrofit"),
+    ("com.squareup.picasso.**", "Picasso"),
+    ("com.bumptech.glide.**", "Glide"),
+    ("com.fasterxml.jackson.**", "Jackson"),
+    ("io.reactivex.**", "RxJava"),
+    ("rx.**", "RxJava"),
+    ("kotlin.**", "Kotlin standard library"),
+    ("kotlinx.**", "Kotlin extensions"),
+    ("dagger.**", "Dagger"),
+    ("butterknife.**", "Butter Knife"),
+    ("org.greenrobot.**", "greenrobot"),
+    ("io.realm.**", "Realm"),
+    ("com.airbnb.lottie.**", "Lottie"),
+    ("com.appsflyer.**", "AppsFlyer"),
+    ("com.adjust.sdk.**", "Adjust"),
+    ("com.onesignal.**", "OneSignal"),
+    ("com.unity3d.**", "Unity"),
+    ("org.apache.**", "Apache libraries"),
+];
+
+/// Origin of the code where a finding was found.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    /// Code of the application developers.
+    FirstParty,
+    /// Code of a well known SDK or library, with its name.
+    Sdk(String),
+    /// Code of an unknown third party.
+    ThirdParty,
+}
+
+impl Origin {
+    /// Attributes the code of the given Java package, of the application with the given package.
+    ///
+    /// If the package of the application is not known, code that is not part of a known SDK can't
+    /// be attributed.
+    pub fn of_package(package: &str, app_package: Option<&str>) -> Option<Self> {
+        let app_package = app_package.filter(|app_package| !app_package.is_empty());
+        if let Some(app_package) = app_package {
+            if package_matches(&format!("{}.**", app_package), package) {
+                return Some(Origin::FirstParty);
+            }
+        }
+
+        match CATALOG
+            .iter()
+            .find(|(pattern, _)| package_matches(pattern, package))
+        {
+            Some((_, name)) => Some(Origin::Sdk((*name).to_owned())),
+            None if app_package.is_some() => Some(Origin::ThirdParty),
+            None => None,
+        }
+    }
+
+    /// Gets the name of the origin, as used in the reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Origin::FirstParty => "first_party",
+            Origin::Sdk(_) => "sdk",
+            Origin::ThirdParty => "third_party",
+        }
+    }
+}
+
+/// Finds the well known SDKs and libraries bundled in the application, from the folder with its
+/// decompiled classes.
+pub fn find_sdks<P: AsRef<Path>>(classes_folder: P) -> BTreeSet<&'static str> {
+    sdk_folders(classes_folder)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Finds the folders with the code of the well known SDKs and libraries bundled in the
+/// application, inside the folder with its decompiled classes, with the name of each SDK.
+pub fn sdk_folders<P: AsRef<Path>>(classes_folder: P) -> Vec<(&'static str, PathBuf)> {
+    CATALOG
+        .iter()
+        .map(|(pattern, name)| {
+            let package = pattern.trim_end_matches(".**").trim_end_matches(".*");
+            (
+                *name,
+                classes_folder.as_ref().join(package.replace('.', "/")),
+            )
+        })
+        .filter(|(_, folder)| folder.is_dir())
+        .collect()
+}
+
+/// Checks if the given Java package matches the given package pattern.
+///
+/// A pattern ending in `.**` matches the package and all its subpackages, and a pattern ending in
+/// `.*` matches only the direct subpackages. Any other pattern must match the package exactly.
+pub(crate) fn package_matches(pattern: &str, package: &str) -> bool {
+    if pattern.ends_with(".**") {
+        let prefix = &pattern[..pattern.len() - 3];
+        package == prefix
+            || (package.starts_with(prefix) && package[prefix.len()..].starts_with('.'))
+    } else if pattern.ends_with(".*") {
+        let prefix = &pattern[..pattern.len() - 1];
+        package.starts_with(prefix) && !package[prefix.len()..].contains('.')
+    } else {
+        package == pattern
+    }
+}
+
+/// Library detection testing module.
+#[cfg(test)]
+mod test {
+    use super::{package_matches, Origin};
+
+    /// Checks the package pattern matching.
+    #[test]
+    fn it_package_matches() {
+        assert!(package_matches("com.example", "com.example"));
+        assert!(!package_matches("com.example", "com.example.app"));
+
+        assert!(package_matches("com.example.*", "com.example.app"));
+        assert!(!package_matches("com.example.*", "com.example"));
+        assert!(!package_matches("com.example.*", "com.example.app.ui"));
+
+        assert!(package_matches("com.example.**", "com.example"));
+        assert!(package_matches("com.example.**", "com.example.app.ui"));
+        assert!(!package_matches("com.example.**", "com.examples"));
+        assert!(!package_matches("androidx.**", "com.example"));
+    }
+
+    /// Checks the attribution of the code to its origin.
+    #[test]
+    fn it_of_package() {
+        assert_eq!(
+            Origin::of_package("com.example.app.ui", Some("com.example.app")),
+            Some(Origin::FirstParty)
+        );
+        assert_eq!(
+            Origin::of_package("okhttp3.internal", Some("com.example.app")),
+            Some(Origin::Sdk("OkHttp".to_owned()))
+        );
+        assert_eq!(
+            Origin::of_package("com.vendor.sdk", Some("com.example.app")),
+            Some(Origin::ThirdParty)
+        );
+        assert_eq!(
+            Origin::of_package("okhttp3", None),
+            Some(Origin::Sdk("OkHttp".to_owned()))
+        );
+        assert_eq!(Origin::of_package("com.example.app", None), None);
+    }
+}
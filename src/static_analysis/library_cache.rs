@@ -0,0 +1,241 @@
+//! Library analysis cache module.
+//!
+//! Big SDKs, such as Firebase or the Facebook SDK, are bundled in many applications, and analyzing
+//! their code again in each of them takes most of the time of a corpus-wide scan. This module keeps
+//! a cache of the rule matches found in the classes of each SDK, in the results folder, keyed by a
+//! hash of the classes, so that an identical version of the SDK is recognized in the next
+//! applications and its code is not analyzed again.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use failure::{Error, ResultExt};
+use sha2::{Digest, Sha256};
+
+/// Name of the library cache file, in the results folder.
+pub const LIBRARY_CACHE_FILE: &str = "libraries.json";
+
+/// Cache of the rule matches in the code of the analyzed SDKs, by class set hash.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LibraryCache {
+    /// Analyzed SDKs, by the hash of their classes.
+    libraries: BTreeMap<String, CachedLibrary>,
+}
+
+impl LibraryCache {
+    /// Loads the cache from the given file, or creates an empty one if it does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        if !path.as_ref().exists() {
+            return Ok(Self::default());
+        }
+
+        let file = File::open(path.as_ref())?;
+        Ok(serde_json::from_reader(file).context(format!(
+            "the library cache at {} is corrupted",
+            path.as_ref().display()
+        ))?)
+    }
+
+    /// Saves the cache in the given file.
+    ///
+    /// The cache is written to a temporary file first, so that it does not get corrupted if the
+    /// process is interrupted.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let temp_path = path.as_ref().with_extension("json.tmp");
+        serde_json::to_writer(File::create(&temp_path)?, self)?;
+        fs::rename(&temp_path, path.as_ref())?;
+        Ok(())
+    }
+
+    /// Gets the cached analysis of the classes with the given hash.
+    ///
+    /// The analysis is only returned if it was done with all the given rules, so that changes in
+    /// the rules don't go unnoticed.
+    pub fn get(&self, hash: &str, rules: &BTreeSet<String>) -> Option<&CachedLibrary> {
+        self.libraries
+            .get(hash)
+            .filter(|library| library.rules.is_superset(rules))
+    }
+
+    /// Adds the analysis of the classes with the given hash, replacing the previous one.
+    pub fn insert<S: Into<String>>(&mut self, hash: S, library: CachedLibrary) {
+        let _ = self.libraries.insert(hash.into(), library);
+    }
+}
+
+/// Cached analysis of the classes of an SDK.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedLibrary {
+    /// Name of the SDK.
+    name: String,
+    /// Keys of the rules checked in the classes.
+    rules: BTreeSet<String>,
+    /// Matches of the rules in the classes.
+    matches: Vec<CachedMatch>,
+}
+
+impl CachedLibrary {
+    /// Creates the analysis of an SDK, with the keys of the checked rules and their matches.
+    pub fn new<S: Into<String>>(
+        name: S,
+        rules: BTreeSet<String>,
+        matches: Vec<CachedMatch>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            rules,
+            matches,
+        }
+    }
+
+    /// Gets the name of the SDK.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the matches of the rules in the classes of the SDK.
+    pub fn matches(&self) -> &[CachedMatch] {
+        &self.matches
+    }
+}
+
+/// Match of a rule in a file of an SDK.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CachedMatch {
+    /// Path of the file, relative to the distribution folder of the application.
+    file: PathBuf,
+    /// Key of the rule.
+    rule: String,
+    /// First line of the match.
+    start_line: usize,
+    /// Last line of the match.
+    end_line: usize,
+}
+
+impl CachedMatch {
+    /// Creates a new match of the rule with the given key.
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(
+        file: P,
+        rule: S,
+        start_line: usize,
+        end_line: usize,
+    ) -> Self {
+        Self {
+            file: file.into(),
+            rule: rule.into(),
+            start_line,
+            end_line,
+        }
+    }
+
+    /// Gets the path of the file, relative to the distribution folder of the application.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Gets the key of the rule.
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Gets the first line of the match.
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    /// Gets the last line of the match.
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+}
+
+/// Computes the hash of a set of classes, from their paths relative to the distribution folder
+/// and their contents.
+///
+/// The hash does not depend on the order of the files, and changes if any class is added, removed,
+/// renamed or modified.
+pub fn class_set_hash<P: AsRef<Path>>(dist_folder: P, files: &[PathBuf]) -> Result<String, Error> {
+    let mut files: Vec<&PathBuf> = files.iter().collect();
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let path = file
+            .strip_prefix(dist_folder.as_ref())
+            .unwrap_or(file.as_path());
+        hasher.input(path.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.input(&[0_u8]);
+        hasher.input(Sha256::digest(&fs::read(file)?));
+    }
+    Ok(hex::encode(hasher.result()))
+}
+
+/// Library analysis cache testing module.
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, fs, path::PathBuf};
+
+    use super::{class_set_hash, CachedLibrary, CachedMatch, LibraryCache};
+
+    /// Checks that the hash of a set of classes depends on their paths and contents only.
+    #[test]
+    fn it_class_set_hash() {
+        let folder = PathBuf::from("target/library_cache_test");
+        let sdk = folder.join("classes/com/example/sdk");
+        fs::create_dir_all(&sdk).unwrap();
+        let first = sdk.join("First.java");
+        let second = sdk.join("Second.java");
+        fs::write(&first, "class First {}").unwrap();
+        fs::write(&second, "class Second {}").unwrap();
+
+        let hash = class_set_hash(&folder, &[first.clone(), second.clone()]).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            class_set_hash(&folder, &[second.clone(), first.clone()]).unwrap(),
+            hash
+        );
+        assert_ne!(class_set_hash(&folder, &[first.clone()]).unwrap(), hash);
+
+        fs::write(&second, "class Second { int i; }").unwrap();
+        assert_ne!(
+            class_set_hash(&folder, &[first.clone(), second.clone()]).unwrap(),
+            hash
+        );
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    /// Checks that cached analyses are only used if they checked all the current rules.
+    #[test]
+    fn it_get() {
+        let rules: BTreeSet<String> = vec!["a".to_owned(), "b".to_owned()].into_iter().collect();
+        let mut cache = LibraryCache::default();
+        cache.insert(
+            "hash",
+            CachedLibrary::new(
+                "Example SDK",
+                rules.clone(),
+                vec![CachedMatch::new(
+                    "classes/com/example/sdk/A.java",
+                    "a",
+                    3,
+                    4,
+                )],
+            ),
+        );
+
+        let library = cache.get("hash", &rules).unwrap();
+        assert_eq!(library.name(), "Example SDK");
+        assert_eq!(library.matches()[0].rule(), "a");
+        assert_eq!(library.matches()[0].start_line(), 3);
+
+        let fewer: BTreeSet<String> = vec!["b".to_owned()].into_iter().collect();
+        assert!(cache.get("hash", &fewer).is_some());
+        let more: BTreeSet<String> = vec!["a".to_owned(), "c".to_owned()].into_iter().collect();
+        assert!(cache.get("hash", &more).is_none());
+        assert!(cache.get("other", &rules).is_none());
+    }
+}
@@ -0,0 +1,250 @@
+//! Sensitive logging analysis module.
+//!
+//! The system log can be read by the tools connected to the device and, in old Android versions,
+//! by any application with the `READ_LOGS` permission, so sensitive data must never be logged.
+//! This module searches the decompiled code for debug, verbose and information log calls that
+//! reference variables named like sensitive data, using the configured list of sensitive
+//! identifiers, and for stack traces printed outside of the debug code paths.
+
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{arguments, block_end, mask},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+lazy_static! {
+    /// Debug, verbose and information log calls.
+    static ref LOG_CALL: Regex = Regex::new(r"\bLog\s*\.\s*[dvi]\s*\(").unwrap();
+    /// Identifiers in the code.
+    static ref IDENTIFIER: Regex = Regex::new(r"[A-Za-z_$][\w$]*").unwrap();
+    /// Stack traces printed to the log.
+    static ref PRINT_STACK_TRACE: Regex = Regex::new(r"\.\s*printStackTrace\s*\(\s*\)").unwrap();
+    /// Blocks only run in debug builds.
+    static ref DEBUG_BLOCK: Regex =
+        Regex::new(r"\bif\s*\(\s*(?:(?:[\w$]+\.)*BuildConfig\s*\.\s*)?DEBUG\s*\)\s*\{").unwrap();
+}
+
+/// Sensitive logging issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Issue {
+    /// Variable with sensitive data logged, with its name.
+    SensitiveData(String),
+    /// Stack trace printed outside of the debug code paths.
+    StackTrace,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::SensitiveData(_) => "logging-sensitive-data",
+            Issue::StackTrace => "logging-stack-trace",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::SensitiveData(_) => "Sensitive data logged",
+            Issue::StackTrace => "Stack trace printed",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Insertion of sensitive information into log file.
+            Issue::SensitiveData(_) => 532,
+            // Generation of error message containing sensitive information.
+            Issue::StackTrace => 209,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        "MASVS-STORAGE-2"
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            // The name of a variable does not prove that its content is sensitive.
+            Issue::SensitiveData(_) => Confidence::Medium,
+            Issue::StackTrace => Confidence::High,
+        }
+    }
+}
+
+impl Issue {
+    /// Gets the criticality of the findings of the issue.
+    fn criticality(&self) -> Criticality {
+        match *self {
+            Issue::SensitiveData(_) => Criticality::Medium,
+            Issue::StackTrace => Criticality::Low,
+        }
+    }
+
+    /// Gets the description of the findings of the issue.
+    fn description(&self) -> String {
+        match *self {
+            Issue::SensitiveData(ref variable) => format!(
+                "The application logs the `{}` variable, that seems to contain sensitive data. \
+                 The system log can be read by the tools connected to the device and, in old \
+                 Android versions, by any application with the `READ_LOGS` permission. Don't log \
+                 sensitive data, or only log it in debug builds.",
+                variable
+            ),
+            Issue::StackTrace => "The application prints a stack trace in a release code path. \
+                                  Stack traces reveal the internal structure of the application \
+                                  and the data of the exceptions in the system log. Report the \
+                                  errors to the user or to a crash reporting service instead."
+                .to_owned(),
+        }
+    }
+}
+
+/// Scanner of the sensitive data and stack traces logged.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoggingScanner;
+
+impl Scanner for LoggingScanner {
+    fn check(&self) -> Check {
+        Check::Logging
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["logging"]
+    }
+
+    fn scan(&self, config: &Config, code: &str) -> Vec<Finding> {
+        find_issues(code, config.sensitive_identifiers())
+            .into_iter()
+            .map(|(issue, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(&issue, issue.criticality(), issue.description(), line, line)
+            })
+            .collect()
+    }
+}
+
+/// Finds the sensitive data and stack traces logged in the given code, with their index in it.
+///
+/// The log calls and stack traces in the blocks only run in debug builds are not reported.
+fn find_issues<S: AsRef<str>>(code: &str, sensitive_identifiers: &[S]) -> Vec<(Issue, usize)> {
+    let masked = mask(code);
+    let debug_blocks = DEBUG_BLOCK
+        .find_iter(&masked)
+        .map(|block| block.end()..block_end(&masked, block.end()))
+        .collect::<Vec<_>>();
+    let in_debug_block = |index: usize| {
+        debug_blocks
+            .iter()
+            .any(|block: &Range<usize>| block.start <= index && index < block.end)
+    };
+    let mut issues = Vec::new();
+
+    for call in LOG_CALL.find_iter(&masked) {
+        if in_debug_block(call.start()) {
+            continue;
+        }
+        let variable = arguments(&masked, call.end())
+            .into_iter()
+            .filter_map(|argument| sensitive_identifier(&masked[argument], sensitive_identifiers))
+            .next();
+        if let Some(variable) = variable {
+            issues.push((Issue::SensitiveData(variable.to_owned()), call.start()));
+        }
+    }
+
+    issues.extend(
+        PRINT_STACK_TRACE
+            .find_iter(&masked)
+            .filter(|trace| !in_debug_block(trace.start()))
+            .map(|trace| (Issue::StackTrace, trace.start())),
+    );
+
+    issues.sort_by_key(|&(_, index)| index);
+    issues
+}
+
+/// Finds the first identifier of the given masked code named like sensitive data, if any.
+///
+/// Identifiers are sensitive if they contain any of the given words, ignoring the case and
+/// underscores. String contents are masked, so only the identifiers of the code are checked.
+pub fn sensitive_identifier<'c, S: AsRef<str>>(
+    masked: &'c str,
+    sensitive_identifiers: &[S],
+) -> Option<&'c str> {
+    let words = sensitive_identifiers
+        .iter()
+        .map(|word| normalize(word.as_ref()))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    IDENTIFIER
+        .find_iter(masked)
+        .map(|identifier| identifier.as_str())
+        .find(|identifier| {
+            let identifier = normalize(identifier);
+            words.iter().any(|word| identifier.contains(word.as_str()))
+        })
+}
+
+/// Normalizes an identifier or a word to compare them, in lowercase and without underscores.
+fn normalize(identifier: &str) -> String {
+    identifier
+        .chars()
+        .filter(|&c| c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Sensitive logging analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_issues, get_line_for, Issue};
+
+    /// Sensitive identifiers used in the tests.
+    const SENSITIVE_IDENTIFIERS: [&str; 3] = ["password", "token", "api_key"];
+
+    /// Checks the issues found in the code, in order.
+    #[test]
+    fn it_find_issues() {
+        let code = "Log.d(TAG, \"Login with \" + user.getPassword());\n\
+                    Log.v(TAG, \"Token: \" + accessToken);\n\
+                    Log.i(TAG, String.format(\"Key %s\", API_KEY));\n\
+                    try { connect(); } catch (IOException e) {\n\
+                    e.printStackTrace();\n\
+                    }";
+
+        let issues = find_issues(code, &SENSITIVE_IDENTIFIERS)
+            .into_iter()
+            .map(|(issue, index)| (issue, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Issue::SensitiveData("getPassword".to_owned()), 0),
+                (Issue::SensitiveData("accessToken".to_owned()), 1),
+                (Issue::SensitiveData("API_KEY".to_owned()), 2),
+                (Issue::StackTrace, 4),
+            ]
+        );
+    }
+
+    /// Checks that the logs without sensitive data and the debug code paths are not reported.
+    #[test]
+    fn it_safe_logs() {
+        let code = "Log.d(TAG, \"password changed for \" + user.getName());\n\
+                    Log.e(TAG, \"Invalid token\", error);\n\
+                    if (BuildConfig.DEBUG) {\n\
+                    Log.d(TAG, \"Token: \" + accessToken);\n\
+                    error.printStackTrace();\n\
+                    }\n\
+                    // Log.d(TAG, password);";
+
+        assert!(find_issues(code, &SENSITIVE_IDENTIFIERS).is_empty());
+        assert!(find_issues("Log.d(TAG, password);", &[] as &[&str]).is_empty());
+    }
+}
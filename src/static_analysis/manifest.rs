@@ -11,9 +11,11 @@ use xml::{
 };
 
 use crate::{
+    axml::{is_binary_xml, recover_elements},
     criticality::Criticality,
     error, get_code, get_string, print_vulnerability, print_warning,
-    results::{Results, Vulnerability},
+    results::{Results, Vulnerability, VulnerabilityBuilder},
+    utils::add_java_files,
     Config, PARSER_CONFIG,
 };
 
@@ -77,6 +79,14 @@ pub fn analysis<S: AsRef<str>>(
         }
     }
 
+    if manifest.is_recovered() {
+        print_warning(
+            "The AndroidManifest.xml file was malformed, and it was partially recovered. The \
+             information from the manifest will be marked as low confidence in the report.",
+        );
+        results.set_manifest_recovered();
+    }
+
     results.set_app_package(manifest.package());
     results.set_app_label(manifest.label());
     results.set_app_description(manifest.description());
@@ -86,35 +96,7 @@ pub fn analysis<S: AsRef<str>>(
     if manifest.target_sdk().is_some() {
         results.set_app_target_sdk(manifest.target_sdk().unwrap());
     }
-
-    if manifest.is_debug() {
-        let criticality = Criticality::Critical;
-
-        if criticality >= config.min_criticality() {
-            let description = "The application is in debug mode. This allows any malicious person \
-                               to inject arbitrary code in the application. This option should \
-                               only be used while in development.";
-
-            let line = get_line(manifest.code(), "android:debuggable=\"true\"").ok();
-            let code = match line {
-                Some(l) => Some(get_code(manifest.code(), l, l)),
-                None => None,
-            };
-
-            let vulnerability = Vulnerability::new(
-                criticality,
-                "Manifest Debug",
-                description,
-                Some("AndroidManifest.xml"),
-                line,
-                line,
-                code,
-            );
-
-            results.add_vulnerability(vulnerability);
-            print_vulnerability(description, criticality);
-        }
-    }
+    results.set_app_permissions(manifest.requested_permissions().to_vec());
 
     if manifest.needs_large_heap() {
         let criticality = Criticality::Warning;
@@ -144,34 +126,6 @@ pub fn analysis<S: AsRef<str>>(
         }
     }
 
-    if manifest.allows_backup() {
-        let criticality = Criticality::Medium;
-
-        if criticality >= config.min_criticality() {
-            let description = "This option allows backups of the application data via adb. \
-                               Malicious people with physical access could use adb to get private \
-                               data of your app into their PC.";
-
-            let line = get_line(manifest.code(), "android:allowBackup=\"true\"").ok();
-            let code = match line {
-                Some(l) => Some(get_code(manifest.code(), l, l)),
-                None => None,
-            };
-
-            let vulnerability = Vulnerability::new(
-                criticality,
-                "Allows Backup",
-                description,
-                Some("AndroidManifest.xml"),
-                line,
-                line,
-                code,
-            );
-            results.add_vulnerability(vulnerability);
-            print_vulnerability(description, criticality);
-        }
-    }
-
     for permission in config.permissions() {
         if manifest
             .permission_checklist()
@@ -198,6 +152,18 @@ pub fn analysis<S: AsRef<str>>(
         }
     }
 
+    // Without the decompiled code, file providers can't be reported as unreferenced.
+    let file_provider_used = !config.is_code_needed()
+        || uses_file_provider(
+            config.dist_folder().join(package.as_ref()),
+            manifest.package(),
+        );
+    for provider in manifest.components_of(ComponentKind::Provider) {
+        for issue in provider.provider_issues(manifest.min_sdk(), file_provider_used) {
+            report_provider_issue(config, &manifest, provider, issue, results);
+        }
+    }
+
     if config.is_verbose() {
         println!();
         println!("{}", "The manifest was analyzed correctly!".green());
@@ -209,10 +175,85 @@ pub fn analysis<S: AsRef<str>>(
     Some(manifest)
 }
 
-/// Manifest analysis representation structure.
+/// Checks if the code of the application shares files with a `FileProvider`.
+///
+/// Only the code in the package of the application is searched, since the `FileProvider` class of
+/// the support library references itself.
+fn uses_file_provider<P: AsRef<Path>>(dist_folder: P, package: &str) -> bool {
+    let mut files = Vec::new();
+    let app_folder = dist_folder
+        .as_ref()
+        .join("classes")
+        .join(package.replace('.', "/"));
+    if let Err(e) = add_java_files(&app_folder, &mut files) {
+        print_warning(format!(
+            "there was an error searching the code for FileProvider references: {}",
+            e
+        ));
+    }
+    files.iter().any(|file| {
+        fs::read_to_string(file)
+            .map(|code| code.contains("getUriForFile("))
+            .unwrap_or(false)
+    })
+}
+
+/// Reports an issue of a content provider, pointing to its declaration in the manifest.
+fn report_provider_issue(
+    config: &Config,
+    manifest: &Manifest,
+    provider: &Component,
+    issue: ProviderIssue,
+    results: &mut Results,
+) {
+    let criticality = issue.criticality();
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let description = format!(
+        "{} Provider: `{}`, authorities: `{}`.",
+        issue.description(),
+        provider.name(),
+        provider.authorities().join(";")
+    );
+    // Wildcard path permissions point to their own element.
+    let path_permission = match issue {
+        ProviderIssue::WildcardPathPermission => provider
+            .path_permissions()
+            .iter()
+            .find(|path_permission| path_permission.is_wildcard()),
+        _ => None,
+    };
+    let needle = match path_permission {
+        Some(path_permission) => format!(
+            "android:{}=\"{}\"",
+            path_permission.attribute(),
+            path_permission.path()
+        ),
+        None => format!("android:name=\"{}\"", provider.name()),
+    };
+
+    let mut vulnerability =
+        VulnerabilityBuilder::new(criticality, issue.name(), description.as_str())
+            .file("AndroidManifest.xml")
+            .cwe(PROVIDER_CWE);
+    if let Ok(line) = get_line(manifest.code(), needle.as_str()) {
+        let code = get_code(manifest.code(), line, line);
+        vulnerability = vulnerability.lines(line, line).code(code);
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Parsed `AndroidManifest.xml` file of an application.
+///
+/// It gives typed access to the information in the manifest, so that custom checks can be built
+/// on top of it without parsing the manifest again.
 #[derive(Debug, Default)]
 pub struct Manifest {
     code: String,
+    recovered: bool,
     package: String,
     label: String,
     description: String,
@@ -222,10 +263,30 @@ pub struct Manifest {
     install_location: InstallLocation,
     permissions: PermissionChecklist,
     debug: bool,
+    uses_cleartext_traffic: Option<bool>,
+    network_security_config: Option<String>,
+    full_backup_content: Option<String>,
+    data_extraction_rules: Option<String>,
+    test_only: bool,
+    request_legacy_external_storage: bool,
+    task_affinity: Option<String>,
     min_sdk: u32,
     target_sdk: Option<u32>,
     version_number: u32,
     version_str: String,
+    requested_permissions: Vec<String>,
+    components: Vec<Component>,
+    instant: bool,
+    features: Vec<String>,
+    libraries: Vec<String>,
+    meta_data: Vec<(String, String)>,
+    shared_user_id: Option<String>,
+    declared_permissions: Vec<DeclaredPermission>,
+    in_queries: bool,
+    queried_packages: Vec<String>,
+    queried_authorities: Vec<String>,
+    queried_actions: Vec<String>,
+    protected_broadcasts: Vec<String>,
 }
 
 impl Manifest {
@@ -236,36 +297,27 @@ impl Manifest {
         package: S,
         results: &mut Results,
     ) -> Result<Self, Error> {
-        let code = fs::read_to_string(path.as_ref().join("AndroidManifest.xml"))?;
+        let bytes = fs::read(path.as_ref().join("AndroidManifest.xml"))?;
+        if is_binary_xml(&bytes) {
+            return Ok(Self::recover(&bytes, config, package.as_ref(), results));
+        }
         let mut manifest = Self::default();
 
-        manifest.set_code(code.as_str());
+        manifest.set_code(String::from_utf8_lossy(&bytes));
 
-        let bytes = code.into_bytes();
         let parser = EventReader::new_with_config(bytes.as_slice(), PARSER_CONFIG.clone());
 
         for e in parser {
             match e {
                 Ok(XmlEvent::StartElement {
                     name, attributes, ..
-                }) => match name.local_name.as_str() {
-                    "manifest" => manifest.parse_manifest_attributes(attributes),
-                    "uses-sdk" => manifest.parse_sdk_attributes(attributes),
-                    "application" => {
-                        manifest.parse_application_attributes(attributes, config, package.as_ref())
-                    }
-                    "uses-permission" => {
-                        manifest.parse_permission_attributes(attributes, config, results)
-                    }
-                    tag @ "provider"
-                    | tag @ "receiver"
-                    | tag @ "activity"
-                    | tag @ "activity-alias"
-                    | tag @ "service" => {
-                        manifest.check_exported_attributes(tag, attributes, config, results)
-                    }
-                    _ => {}
-                },
+                }) => manifest.parse_element(
+                    name.local_name.as_str(),
+                    attributes,
+                    config,
+                    package.as_ref(),
+                    results,
+                ),
                 Ok(_) => {}
                 Err(e) => {
                     print_warning(format!(
@@ -280,6 +332,91 @@ impl Manifest {
         Ok(manifest)
     }
 
+    /// Recovers as much information as possible from a binary manifest that could not be
+    /// decoded.
+    ///
+    /// The manifest gets marked as recovered, since the information in it is not reliable.
+    fn recover(data: &[u8], config: &Config, package: &str, results: &mut Results) -> Self {
+        let elements = recover_elements(data);
+        let mut manifest = Self::default();
+        manifest.recovered = true;
+
+        // Recreate the code of the manifest, so that findings can point to the affected element.
+        manifest.set_code(
+            elements
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+
+        for element in elements {
+            manifest.parse_element(
+                element.name(),
+                element.attributes().iter().cloned(),
+                config,
+                package,
+                results,
+            );
+        }
+
+        manifest
+    }
+
+    /// Parses the attributes of an element of the manifest.
+    fn parse_element<A>(
+        &mut self,
+        tag: &str,
+        attributes: A,
+        config: &Config,
+        package: &str,
+        results: &mut Results,
+    ) where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        // Elements inside `<queries>` declare the visibility of other applications, they are
+        // not components of this application.
+        if self.in_queries {
+            match tag {
+                "package" | "provider" | "intent" | "action" | "category" | "data" => {
+                    self.parse_queries_element(tag, attributes);
+                    return;
+                }
+                _ => self.in_queries = false,
+            }
+        }
+
+        match tag {
+            "manifest" => self.parse_manifest_attributes(attributes),
+            "queries" => self.in_queries = true,
+            "permission" | "protected-broadcast" => self.parse_declaration_element(tag, attributes),
+            "uses-sdk" => self.parse_sdk_attributes(attributes),
+            "application" => self.parse_application_attributes(attributes, config, package),
+            "uses-permission" => self.parse_permission_attributes(attributes, config, results),
+            "activity" => {
+                self.parse_component(ComponentKind::Activity, attributes, config, results)
+            }
+            "activity-alias" => {
+                self.parse_component(ComponentKind::ActivityAlias, attributes, config, results)
+            }
+            "service" => self.parse_component(ComponentKind::Service, attributes, config, results),
+            "receiver" => {
+                self.parse_component(ComponentKind::Receiver, attributes, config, results)
+            }
+            "provider" => {
+                self.parse_component(ComponentKind::Provider, attributes, config, results)
+            }
+            "intent-filter" | "action" | "category" | "data" => {
+                self.parse_intent_filter_element(tag, attributes)
+            }
+            "path-permission" => self.parse_path_permission(attributes),
+            "uses-feature" | "uses-library" | "meta-data" | "module" => {
+                self.parse_metadata_element(tag, attributes)
+            }
+            _ => {}
+        }
+    }
+
     fn parse_manifest_attributes<A>(&mut self, attributes: A)
     where
         A: IntoIterator<Item = OwnedAttribute>,
@@ -302,6 +439,13 @@ impl Manifest {
                     self.set_version_number(version_number);
                 }
                 "versionName" => self.set_version_str(attr.value.as_str()),
+                "sharedUserId" => self.shared_user_id = Some(attr.value),
+                // Instant Apps run in the second version of the sandbox.
+                "targetSandboxVersion" => {
+                    if attr.value == "2" {
+                        self.instant = true;
+                    }
+                }
                 "installLocation" => {
                     let location = match InstallLocation::from_str(attr.value.as_str()) {
                         Ok(l) => l,
@@ -399,6 +543,45 @@ impl Manifest {
                         self.set_allows_backup();
                     }
                 }
+                "usesCleartextTraffic" => match attr.value.as_str().parse() {
+                    Ok(b) => self.uses_cleartext_traffic = Some(b),
+                    Err(e) => {
+                        print_warning(format!(
+                            "An error occurred when parsing the `usesCleartextTraffic` attribute \
+                             in the manifest: {}.\nThe process will continue, though.",
+                            e
+                        ));
+                        break;
+                    }
+                },
+                "networkSecurityConfig" => {
+                    self.network_security_config = Some(attr.value.clone());
+                }
+                "fullBackupContent" => self.full_backup_content = Some(attr.value.clone()),
+                "dataExtractionRules" => self.data_extraction_rules = Some(attr.value.clone()),
+                "taskAffinity" => self.task_affinity = Some(attr.value.clone()),
+                "testOnly" => match attr.value.as_str().parse() {
+                    Ok(b) => self.test_only = b,
+                    Err(e) => {
+                        print_warning(format!(
+                            "An error occurred when parsing the `testOnly` attribute in the \
+                             manifest: {}.\nThe process will continue, though.",
+                            e
+                        ));
+                        break;
+                    }
+                },
+                "requestLegacyExternalStorage" => match attr.value.as_str().parse() {
+                    Ok(b) => self.request_legacy_external_storage = b,
+                    Err(e) => {
+                        print_warning(format!(
+                            "An error occurred when parsing the `requestLegacyExternalStorage` \
+                             attribute in the manifest: {}.\nThe process will continue, though.",
+                            e
+                        ));
+                        break;
+                    }
+                },
                 "description" => self.set_description(attr.value.as_str()),
                 "hasCode" => {
                     let has_code: bool = match attr.value.as_str().parse() {
@@ -464,6 +647,7 @@ impl Manifest {
     {
         for attr in attributes {
             if let "name" = attr.name.local_name.as_str() {
+                self.requested_permissions.push(attr.value.clone());
                 let permission = if let Ok(p) = Permission::from_str(attr.value.as_str()) {
                     p
                 } else {
@@ -498,79 +682,260 @@ impl Manifest {
         }
     }
 
-    fn check_exported_attributes<A>(
+    /// Parses a component of the application and checks if it's exported.
+    fn parse_component<A>(
         &mut self,
-        tag: &str,
+        kind: ComponentKind,
         attributes: A,
         config: &Config,
         results: &mut Results,
     ) where
         A: IntoIterator<Item = OwnedAttribute>,
     {
-        {
-            let mut exported = None;
-            let mut name = String::new();
-            for attr in attributes {
-                match attr.name.local_name.as_str() {
-                    "exported" => {
-                        if let Ok(found_exported) = attr.value.as_str().parse() {
-                            exported = Some(found_exported);
-                        }
+        let mut component = Component::new(kind);
+        for attr in attributes {
+            match attr.name.local_name.as_str() {
+                "exported" => {
+                    if let Ok(found_exported) = attr.value.as_str().parse() {
+                        component.exported = Some(found_exported);
                     }
-                    "name" => name = attr.value,
-                    _ => {}
                 }
+                "name" => component.name = attr.value,
+                "permission" => component.permission = Some(attr.value),
+                "readPermission" => component.read_permission = Some(attr.value),
+                "writePermission" => component.write_permission = Some(attr.value),
+                "grantUriPermissions" => component.grant_uri_permissions = attr.value == "true",
+                "taskAffinity" => component.task_affinity = Some(attr.value),
+                "launchMode" => component.launch_mode = Some(attr.value),
+                "authorities" => {
+                    component.authorities = attr
+                        .value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|authority| !authority.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                "foregroundServiceType" => {
+                    component.foreground_service_types = attr
+                        .value
+                        .split('|')
+                        .map(str::trim)
+                        .filter(|service_type| !service_type.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                _ => {}
             }
-            match exported {
-                Some(true) | None => {
-                    if tag != "provider" || exported.is_some() || self.min_sdk() < 17 {
-                        let line =
-                            get_line(self.code(), &format!("android:name=\"{}\"", name)).ok();
-                        let code = match line {
-                            Some(l) => Some(get_code(self.code(), l, l)),
-                            None => None,
-                        };
-
-                        let criticality = Criticality::Warning;
-
-                        if criticality >= config.min_criticality() {
-                            let vulnerability = Vulnerability::new(
-                                criticality,
-                                format!("Exported {}", tag),
-                                format!(
-                                    "Exported {} was found. It can be used by other applications.",
-                                    tag
-                                ),
-                                Some("AndroidManifest.xml"),
-                                line,
-                                line,
-                                code,
-                            );
-                            results.add_vulnerability(vulnerability);
-
-                            print_vulnerability(
-                                format!(
-                                    "Exported {} was found. It can be used by other applications.",
-                                    tag
-                                ),
-                                Criticality::Warning,
-                            );
-                        }
-                    }
+        }
+
+        self.check_exported_component(&component, config, results);
+        self.components.push(component);
+    }
+
+    /// Parses a path permission of the last parsed content provider.
+    fn parse_path_permission<A>(&mut self, attributes: A)
+    where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        let provider = match self.components.last_mut() {
+            Some(component) if component.kind() == ComponentKind::Provider => component,
+            _ => return,
+        };
+        let mut path_permission = PathPermission::default();
+        for attr in attributes {
+            match attr.name.local_name.as_str() {
+                "path" | "pathPrefix" | "pathPattern" | "pathAdvancedPattern" => {
+                    path_permission.attribute = attr.name.local_name.clone();
+                    path_permission.path = attr.value;
                 }
+                "permission" => path_permission.permission = Some(attr.value),
+                "readPermission" => path_permission.read_permission = Some(attr.value),
+                "writePermission" => path_permission.write_permission = Some(attr.value),
+                _ => {}
+            }
+        }
+        provider.path_permissions.push(path_permission);
+    }
+
+    /// Parses an intent filter, or one of its elements, of the last parsed component.
+    fn parse_intent_filter_element<A>(&mut self, tag: &str, attributes: A)
+    where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        let component = match self.components.last_mut() {
+            Some(component) => component,
+            None => return,
+        };
+        if tag == "intent-filter" {
+            let mut filter = IntentFilter::default();
+            for attr in attributes {
+                if attr.name.local_name == "autoVerify" {
+                    filter.auto_verify = attr.value == "true";
+                }
+            }
+            component.intent_filters.push(filter);
+            return;
+        }
+        let filter = match component.intent_filters.last_mut() {
+            Some(filter) => filter,
+            None => return,
+        };
+
+        for attr in attributes {
+            match (tag, attr.name.local_name.as_str()) {
+                ("action", "name") => filter.actions.push(attr.value),
+                ("category", "name") => filter.categories.push(attr.value),
+                ("data", "scheme") => filter.schemes.push(attr.value),
+                ("data", "host") => filter.hosts.push(attr.value),
+                ("data", "port") => filter.ports.push(attr.value),
+                ("data", "path")
+                | ("data", "pathPrefix")
+                | ("data", "pathSuffix")
+                | ("data", "pathPattern")
+                | ("data", "pathAdvancedPattern") => filter.paths.push(attr.value),
+                ("data", "mimeType") => filter.mime_types.push(attr.value),
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses the features, libraries and meta-data declared in the manifest, and the module
+    /// element of applications built from bundles.
+    fn parse_metadata_element<A>(&mut self, tag: &str, attributes: A)
+    where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        let mut name = None;
+        let mut value = None;
+        for attr in attributes {
+            match (tag, attr.name.local_name.as_str()) {
+                ("module", "instant") => self.instant |= attr.value == "true",
+                (_, "name") => name = Some(attr.value),
+                ("meta-data", "value") | ("meta-data", "resource") => value = Some(attr.value),
+                _ => {}
+            }
+        }
+
+        if let Some(name) = name {
+            match tag {
+                "uses-feature" => self.features.push(name),
+                "uses-library" => self.libraries.push(name),
+                "meta-data" => self.meta_data.push((name, value.unwrap_or_default())),
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses the permissions and protected broadcasts declared by the application.
+    fn parse_declaration_element<A>(&mut self, tag: &str, attributes: A)
+    where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        let mut permission = DeclaredPermission::default();
+        for attr in attributes {
+            match attr.name.local_name.as_str() {
+                "name" => permission.name = attr.value,
+                "protectionLevel" => permission.protection_level = attr.value,
+                "knownCerts" => permission.known_certs = Some(attr.value),
+                _ => {}
+            }
+        }
+        if permission.name.is_empty() {
+            return;
+        }
+
+        if tag == "protected-broadcast" {
+            self.protected_broadcasts.push(permission.name);
+        } else {
+            self.declared_permissions.push(permission);
+        }
+    }
+
+    /// Parses an element of the `<queries>` element, that declares the other applications that
+    /// this application can see.
+    fn parse_queries_element<A>(&mut self, tag: &str, attributes: A)
+    where
+        A: IntoIterator<Item = OwnedAttribute>,
+    {
+        for attr in attributes {
+            match (tag, attr.name.local_name.as_str()) {
+                ("package", "name") => self.queried_packages.push(attr.value),
+                ("action", "name") => self.queried_actions.push(attr.value),
+                ("provider", "authorities") => self.queried_authorities.extend(
+                    attr.value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|authority| !authority.is_empty())
+                        .map(String::from),
+                ),
                 _ => {}
             }
         }
     }
 
+    fn check_exported_component(
+        &self,
+        component: &Component,
+        config: &Config,
+        results: &mut Results,
+    ) {
+        let tag = component.kind().tag();
+        if component.is_exported(self.min_sdk()) {
+            let line = get_line(
+                self.code(),
+                &format!("android:name=\"{}\"", component.name()),
+            )
+            .ok();
+            let code = match line {
+                Some(l) => Some(get_code(self.code(), l, l)),
+                None => None,
+            };
+
+            let criticality = Criticality::Warning;
+
+            if criticality >= config.min_criticality() {
+                let vulnerability = Vulnerability::new(
+                    criticality,
+                    format!("Exported {}", tag),
+                    format!(
+                        "Exported {} was found. It can be used by other applications.",
+                        tag
+                    ),
+                    Some("AndroidManifest.xml"),
+                    line,
+                    line,
+                    code,
+                );
+                results.add_vulnerability(vulnerability);
+
+                print_vulnerability(
+                    format!(
+                        "Exported {} was found. It can be used by other applications.",
+                        tag
+                    ),
+                    Criticality::Warning,
+                );
+            }
+        }
+    }
+
     fn set_code<S: Into<String>>(&mut self, code: S) {
         self.code = code.into();
     }
 
+    /// Gets the XML code of the manifest.
     pub fn code(&self) -> &str {
         &self.code
     }
 
+    /// Returns true if the manifest was recovered from a malformed binary manifest, so its
+    /// information is not reliable.
+    pub fn is_recovered(&self) -> bool {
+        self.recovered
+    }
+
+    /// Gets the package of the application.
     pub fn package(&self) -> &str {
         &self.package
     }
@@ -579,6 +944,7 @@ impl Manifest {
         self.package = package.into();
     }
 
+    /// Gets the version number (`versionCode`) of the application.
     pub fn version_number(&self) -> u32 {
         self.version_number
     }
@@ -587,6 +953,7 @@ impl Manifest {
         self.version_number = version_number;
     }
 
+    /// Gets the version string (`versionName`) of the application.
     pub fn version_str(&self) -> &str {
         &self.version_str
     }
@@ -595,6 +962,7 @@ impl Manifest {
         self.version_str = version_str.into();
     }
 
+    /// Gets the label of the application.
     pub fn label(&self) -> &str {
         &self.label
     }
@@ -603,6 +971,7 @@ impl Manifest {
         self.label = label.into();
     }
 
+    /// Gets the description of the application.
     pub fn description(&self) -> &str {
         &self.description
     }
@@ -611,18 +980,22 @@ impl Manifest {
         self.description = description.into();
     }
 
+    /// Gets the minimum SDK version supported by the application.
     pub fn min_sdk(&self) -> u32 {
         self.min_sdk
     }
 
+    /// Sets the minimum SDK version supported by the application.
     pub fn set_min_sdk(&mut self, min_sdk: u32) {
         self.min_sdk = min_sdk;
     }
 
+    /// Gets the SDK version targeted by the application, if declared.
     pub fn target_sdk(&self) -> Option<u32> {
         self.target_sdk
     }
 
+    /// Sets the SDK version targeted by the application.
     pub fn set_target_sdk(&mut self, target_sdk: u32) {
         self.target_sdk = Some(target_sdk);
     }
@@ -631,6 +1004,7 @@ impl Manifest {
         self.has_code = true;
     }
 
+    /// Returns true if the application allows backups of its data.
     pub fn allows_backup(&self) -> bool {
         self.allows_backup
     }
@@ -639,6 +1013,7 @@ impl Manifest {
         self.allows_backup = true;
     }
 
+    /// Returns true if the application requests a large heap.
     pub fn needs_large_heap(&self) -> bool {
         self.large_heap
     }
@@ -651,6 +1026,7 @@ impl Manifest {
         self.install_location = install_location;
     }
 
+    /// Returns true if the application is debuggable.
     pub fn is_debug(&self) -> bool {
         self.debug
     }
@@ -659,9 +1035,609 @@ impl Manifest {
         self.debug = true;
     }
 
+    /// Gets the `usesCleartextTraffic` attribute of the application, if it's declared.
+    pub fn uses_cleartext_traffic(&self) -> Option<bool> {
+        self.uses_cleartext_traffic
+    }
+
+    /// Gets the resource of the network security configuration of the application, such as
+    /// `@xml/network_security_config`, if it's declared.
+    pub fn network_security_config(&self) -> Option<&str> {
+        self.network_security_config.as_ref().map(String::as_str)
+    }
+
+    /// Gets the `fullBackupContent` attribute of the application, if it's declared. It's either
+    /// a boolean or the resource of the backup rules, such as `@xml/backup_rules`.
+    pub fn full_backup_content(&self) -> Option<&str> {
+        self.full_backup_content.as_ref().map(String::as_str)
+    }
+
+    /// Gets the resource of the data extraction rules of the application, such as
+    /// `@xml/data_extraction_rules`, if it's declared.
+    pub fn data_extraction_rules(&self) -> Option<&str> {
+        self.data_extraction_rules.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the application is marked as a test only application.
+    pub fn is_test_only(&self) -> bool {
+        self.test_only
+    }
+
+    /// Returns true if the application requests the legacy external storage, opting out of
+    /// scoped storage.
+    pub fn requests_legacy_external_storage(&self) -> bool {
+        self.request_legacy_external_storage
+    }
+
+    /// Gets the default task affinity of the activities of the application, if it's declared.
+    pub fn task_affinity(&self) -> Option<&str> {
+        self.task_affinity.as_ref().map(String::as_str)
+    }
+
+    /// Gets the checklist of the known permissions requested by the application.
     pub fn permission_checklist(&self) -> &PermissionChecklist {
         &self.permissions
     }
+
+    /// Gets the names of the permissions requested by the application, including unknown ones.
+    pub fn requested_permissions(&self) -> &[String] {
+        &self.requested_permissions
+    }
+
+    /// Gets the components declared by the application, in declaration order.
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Returns true if the application can run as an Instant App.
+    pub fn is_instant(&self) -> bool {
+        self.instant
+    }
+
+    /// Gets the features used by the application, declared in the manifest.
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Gets the shared libraries used by the application, declared in the manifest.
+    pub fn libraries(&self) -> &[String] {
+        &self.libraries
+    }
+
+    /// Gets the value, or resource, of the given meta-data of the manifest, if it's declared.
+    pub fn meta_data<S: AsRef<str>>(&self, name: S) -> Option<&str> {
+        self.meta_data
+            .iter()
+            .find(|(n, _)| n == name.as_ref())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the user ID shared with other applications signed with the same certificate, if any.
+    pub fn shared_user_id(&self) -> Option<&str> {
+        self.shared_user_id.as_ref().map(String::as_str)
+    }
+
+    /// Gets the permissions declared by the application.
+    pub fn declared_permissions(&self) -> &[DeclaredPermission] {
+        &self.declared_permissions
+    }
+
+    /// Gets the packages of the other applications that this application can see.
+    pub fn queried_packages(&self) -> &[String] {
+        &self.queried_packages
+    }
+
+    /// Gets the content provider authorities of other applications that this application can
+    /// see.
+    pub fn queried_authorities(&self) -> &[String] {
+        &self.queried_authorities
+    }
+
+    /// Gets the intent actions handled by the other applications that this application can see.
+    pub fn queried_actions(&self) -> &[String] {
+        &self.queried_actions
+    }
+
+    /// Gets the broadcasts that the application declares as protected.
+    pub fn protected_broadcasts(&self) -> &[String] {
+        &self.protected_broadcasts
+    }
+
+    /// Gets the declared components of the given kind.
+    pub fn components_of(&self, kind: ComponentKind) -> impl Iterator<Item = &Component> {
+        self.components.iter().filter(move |c| c.kind() == kind)
+    }
+}
+
+/// Kind of application component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ComponentKind {
+    /// Activity (`<activity>`).
+    Activity,
+    /// Activity alias (`<activity-alias>`).
+    ActivityAlias,
+    /// Service (`<service>`).
+    Service,
+    /// Broadcast receiver (`<receiver>`).
+    Receiver,
+    /// Content provider (`<provider>`).
+    Provider,
+}
+
+impl ComponentKind {
+    /// Gets the manifest tag of the component kind.
+    pub fn tag(self) -> &'static str {
+        match self {
+            ComponentKind::Activity => "activity",
+            ComponentKind::ActivityAlias => "activity-alias",
+            ComponentKind::Service => "service",
+            ComponentKind::Receiver => "receiver",
+            ComponentKind::Provider => "provider",
+        }
+    }
+}
+
+/// Component declared in the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    /// Kind of the component.
+    kind: ComponentKind,
+    /// Class name of the component.
+    name: String,
+    /// Explicit `exported` attribute of the component.
+    exported: Option<bool>,
+    /// Permission required to interact with the component.
+    permission: Option<String>,
+    /// Authorities of the content provider.
+    authorities: Vec<String>,
+    /// Intent filters of the component.
+    intent_filters: Vec<IntentFilter>,
+    /// Foreground service types of the service, such as `camera` or `microphone`.
+    foreground_service_types: Vec<String>,
+    /// Permission required to read the data of the content provider.
+    read_permission: Option<String>,
+    /// Permission required to write the data of the content provider.
+    write_permission: Option<String>,
+    /// Whether the content provider can grant temporary access to its data.
+    grant_uri_permissions: bool,
+    /// Path permissions of the content provider.
+    path_permissions: Vec<PathPermission>,
+    /// Task affinity of the activity.
+    task_affinity: Option<String>,
+    /// Launch mode of the activity, such as `singleTask`.
+    launch_mode: Option<String>,
+}
+
+impl Component {
+    /// Creates an empty component of the given kind.
+    fn new(kind: ComponentKind) -> Self {
+        Self {
+            kind,
+            name: String::new(),
+            exported: None,
+            permission: None,
+            authorities: Vec::new(),
+            intent_filters: Vec::new(),
+            foreground_service_types: Vec::new(),
+            read_permission: None,
+            write_permission: None,
+            grant_uri_permissions: false,
+            path_permissions: Vec::new(),
+            task_affinity: None,
+            launch_mode: None,
+        }
+    }
+
+    /// Gets the kind of the component.
+    pub fn kind(&self) -> ComponentKind {
+        self.kind
+    }
+
+    /// Gets the class name of the component, as declared in the manifest.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the explicit value of the `exported` attribute, if it was declared.
+    pub fn exported(&self) -> Option<bool> {
+        self.exported
+    }
+
+    /// Returns true if other applications can use the component, in an application with the
+    /// given minimum SDK. Components are exported by default, except content providers since
+    /// API 17.
+    pub fn is_exported(&self, min_sdk: u32) -> bool {
+        match self.exported {
+            Some(exported) => exported,
+            None => self.kind != ComponentKind::Provider || min_sdk < 17,
+        }
+    }
+
+    /// Gets the permission required to interact with the component, if any.
+    pub fn permission(&self) -> Option<&str> {
+        self.permission.as_ref().map(String::as_str)
+    }
+
+    /// Gets the authorities of the component, only declared by content providers.
+    pub fn authorities(&self) -> &[String] {
+        &self.authorities
+    }
+
+    /// Gets the intent filters of the component.
+    pub fn intent_filters(&self) -> &[IntentFilter] {
+        &self.intent_filters
+    }
+
+    /// Gets the foreground service types of the component, only declared by services.
+    pub fn foreground_service_types(&self) -> &[String] {
+        &self.foreground_service_types
+    }
+
+    /// Gets the fully qualified class name of the component, resolving the names relative to the
+    /// package of the application.
+    pub fn class_name(&self, package: &str) -> String {
+        if self.name.starts_with('.') {
+            format!("{}{}", package, self.name)
+        } else if !self.name.contains('.') {
+            format!("{}.{}", package, self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Gets the permission required to read the data of the content provider, if any.
+    pub fn read_permission(&self) -> Option<&str> {
+        self.read_permission.as_ref().map(String::as_str)
+    }
+
+    /// Gets the permission required to write the data of the content provider, if any.
+    pub fn write_permission(&self) -> Option<&str> {
+        self.write_permission.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the content provider can grant temporary access to its data.
+    pub fn grants_uri_permissions(&self) -> bool {
+        self.grant_uri_permissions
+    }
+
+    /// Gets the path permissions of the content provider.
+    pub fn path_permissions(&self) -> &[PathPermission] {
+        &self.path_permissions
+    }
+
+    /// Gets the task affinity of the activity, if it's declared.
+    pub fn task_affinity(&self) -> Option<&str> {
+        self.task_affinity.as_ref().map(String::as_str)
+    }
+
+    /// Gets the launch mode of the activity, if it's declared.
+    pub fn launch_mode(&self) -> Option<&str> {
+        self.launch_mode.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the activity is launched as the root of its own task, with the
+    /// `singleTask` or `singleInstance` launch modes.
+    pub fn is_task_root(&self) -> bool {
+        match self.launch_mode() {
+            Some("singleTask") | Some("singleInstance") => true,
+            _ => false,
+        }
+    }
+
+    /// Gets the issues of the declaration of a content provider, in an application with the given
+    /// minimum SDK. `file_provider_used` tells if the code of the application shares files with
+    /// a `FileProvider`.
+    fn provider_issues(&self, min_sdk: u32, file_provider_used: bool) -> Vec<ProviderIssue> {
+        let mut issues = Vec::new();
+        if self.kind != ComponentKind::Provider {
+            return issues;
+        }
+
+        if self.is_exported(min_sdk) {
+            if self.permission.is_none() && self.read_permission.is_none() {
+                issues.push(ProviderIssue::MissingReadPermission);
+            }
+            if self.permission.is_none() && self.write_permission.is_none() {
+                issues.push(ProviderIssue::MissingWritePermission);
+            }
+            if self
+                .path_permissions
+                .iter()
+                .any(PathPermission::is_wildcard)
+            {
+                issues.push(ProviderIssue::WildcardPathPermission);
+            }
+        }
+        if self.grant_uri_permissions {
+            // File providers need to grant URI permissions, it's how they share their files.
+            if !self.name.ends_with("FileProvider") {
+                issues.push(ProviderIssue::GrantUriPermissions);
+            } else if !file_provider_used {
+                issues.push(ProviderIssue::UnreferencedFileProvider);
+            }
+        }
+        issues
+    }
+}
+
+/// Path permission of a content provider, declared in a `<path-permission>` element.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathPermission {
+    /// Attribute that declares the path: `path`, `pathPrefix`, `pathPattern` or
+    /// `pathAdvancedPattern`.
+    attribute: String,
+    /// Path, path prefix or path pattern of the permission.
+    path: String,
+    /// Permission required to read and write the path.
+    permission: Option<String>,
+    /// Permission required to read the path.
+    read_permission: Option<String>,
+    /// Permission required to write the path.
+    write_permission: Option<String>,
+}
+
+impl PathPermission {
+    /// Gets the attribute that declares the path, such as `pathPrefix`.
+    pub fn attribute(&self) -> &str {
+        &self.attribute
+    }
+
+    /// Gets the path, path prefix or path pattern of the permission.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Gets the permission required to read and write the path, if any.
+    pub fn permission(&self) -> Option<&str> {
+        self.permission.as_ref().map(String::as_str)
+    }
+
+    /// Gets the permission required to read the path, if any.
+    pub fn read_permission(&self) -> Option<&str> {
+        self.read_permission.as_ref().map(String::as_str)
+    }
+
+    /// Gets the permission required to write the path, if any.
+    pub fn write_permission(&self) -> Option<&str> {
+        self.write_permission.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the path permission matches every path of the provider.
+    pub fn is_wildcard(&self) -> bool {
+        match self.attribute.as_str() {
+            "pathPrefix" => self.path.is_empty() || self.path == "/",
+            "pathPattern" | "pathAdvancedPattern" => {
+                let pattern = self.path.trim_start_matches('/');
+                pattern.starts_with(".*") || pattern.starts_with('*')
+            }
+            _ => false,
+        }
+    }
+}
+
+/// CWE of the content provider issues: improper export of Android application components.
+const PROVIDER_CWE: u32 = 926;
+
+/// Issue of the declaration of a content provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProviderIssue {
+    /// Exported provider without a read permission.
+    MissingReadPermission,
+    /// Exported provider without a write permission.
+    MissingWritePermission,
+    /// Exported provider with a path permission that matches every path.
+    WildcardPathPermission,
+    /// Provider, other than a `FileProvider`, that grants URI permissions.
+    GrantUriPermissions,
+    /// `FileProvider` that the code of the application never uses.
+    UnreferencedFileProvider,
+}
+
+impl ProviderIssue {
+    /// Gets the criticality of the issue.
+    fn criticality(self) -> Criticality {
+        match self {
+            ProviderIssue::MissingWritePermission => Criticality::High,
+            ProviderIssue::MissingReadPermission
+            | ProviderIssue::WildcardPathPermission
+            | ProviderIssue::GrantUriPermissions => Criticality::Medium,
+            ProviderIssue::UnreferencedFileProvider => Criticality::Low,
+        }
+    }
+
+    /// Gets the name of the issue.
+    fn name(self) -> &'static str {
+        match self {
+            ProviderIssue::MissingReadPermission => "Provider without read permission",
+            ProviderIssue::MissingWritePermission => "Provider without write permission",
+            ProviderIssue::WildcardPathPermission => "Wildcard path permission",
+            ProviderIssue::GrantUriPermissions => "Provider grants URI permissions",
+            ProviderIssue::UnreferencedFileProvider => "Unreferenced FileProvider",
+        }
+    }
+
+    /// Gets the description of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            ProviderIssue::MissingReadPermission => {
+                "The content provider is exported without `android:readPermission` or \
+                 `android:permission`, so any application can query its data."
+            }
+            ProviderIssue::MissingWritePermission => {
+                "The content provider is exported without `android:writePermission` or \
+                 `android:permission`, so any application can insert, update and delete its \
+                 data, and exploit any injection in its queries."
+            }
+            ProviderIssue::WildcardPathPermission => {
+                "The content provider declares a `<path-permission>` that matches every path, so \
+                 its permissions replace the permissions of the whole provider. If they are \
+                 weaker, or missing for reads or writes, the provider is less protected than its \
+                 declaration suggests."
+            }
+            ProviderIssue::GrantUriPermissions => {
+                "The content provider sets `android:grantUriPermissions=\"true\"`, so the \
+                 application can grant temporary access to any of its URIs. If the application \
+                 forwards intents received from other applications, they can get access to the \
+                 data of the provider. Restrict the grants with `<grant-uri-permission>` \
+                 elements."
+            }
+            ProviderIssue::UnreferencedFileProvider => {
+                "The application declares a `FileProvider`, but its code never shares a file \
+                 with `FileProvider.getUriForFile()`. The provider can still grant access to the \
+                 files of its paths configuration if the application forwards intents from other \
+                 applications. Remove it if it's not needed."
+            }
+        }
+    }
+}
+
+/// Permission declared by the application.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeclaredPermission {
+    /// Name of the permission.
+    name: String,
+    /// Protection level of the permission, such as `signature` or `dangerous|instant`.
+    protection_level: String,
+    /// Certificates that are granted the permission, for `knownSigner` permissions.
+    known_certs: Option<String>,
+}
+
+impl DeclaredPermission {
+    /// Gets the name of the permission.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the protection level of the permission, as declared in the manifest.
+    pub fn protection_level(&self) -> &str {
+        &self.protection_level
+    }
+
+    /// Gets the certificates that are granted the permission, for `knownSigner` permissions.
+    pub fn known_certs(&self) -> Option<&str> {
+        self.known_certs.as_ref().map(String::as_str)
+    }
+
+    /// Returns true if the permission is granted to applications signed with the same
+    /// certificate, or with one of the known certificates.
+    ///
+    /// Binary manifests that could not be decoded have the protection level as a number.
+    pub fn is_signature(&self) -> bool {
+        match self.level_number() {
+            // The base protection levels are `signature` (2) and `signatureOrSystem` (3).
+            Some(number) => number & 0xf == 2 || number & 0xf == 3,
+            None => self
+                .protection_level
+                .split('|')
+                .any(|flag| flag.trim().starts_with("signature")),
+        }
+    }
+
+    /// Returns true if the permission is granted to any application that requests it, without
+    /// asking the user. `normal` is the default protection level.
+    pub fn is_normal(&self) -> bool {
+        match self.level_number() {
+            Some(number) => number & 0xf == 0,
+            None => !self.protection_level.split('|').any(|flag| {
+                let flag = flag.trim();
+                flag.starts_with("signature") || flag == "dangerous" || flag == "internal"
+            }),
+        }
+    }
+
+    /// Gets the protection level as a number, as in the binary manifests that could not be
+    /// decoded.
+    fn level_number(&self) -> Option<u32> {
+        let level = self.protection_level.trim();
+        if level.starts_with("0x") {
+            u32::from_str_radix(&level[2..], 16).ok()
+        } else {
+            level.parse().ok()
+        }
+    }
+
+    /// Returns true if the permission is granted to applications signed with the known
+    /// certificates.
+    pub fn is_known_signer(&self) -> bool {
+        self.known_certs.is_some() || self.protection_level.contains("knownSigner")
+    }
+}
+
+/// Intent filter of a component.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntentFilter {
+    /// Actions of the filter.
+    actions: Vec<String>,
+    /// Categories of the filter.
+    categories: Vec<String>,
+    /// Data schemes of the filter.
+    schemes: Vec<String>,
+    /// Data hosts of the filter.
+    hosts: Vec<String>,
+    /// Data ports of the filter.
+    ports: Vec<String>,
+    /// Data paths, path prefixes, suffixes and patterns of the filter.
+    paths: Vec<String>,
+    /// Data MIME types of the filter.
+    mime_types: Vec<String>,
+    /// Whether the filter asks the system to verify its web links (`android:autoVerify`).
+    auto_verify: bool,
+}
+
+impl IntentFilter {
+    /// Gets the actions of the intent filter.
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// Gets the categories of the intent filter.
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Gets the data schemes of the intent filter.
+    pub fn schemes(&self) -> &[String] {
+        &self.schemes
+    }
+
+    /// Gets the data hosts of the intent filter.
+    pub fn hosts(&self) -> &[String] {
+        &self.hosts
+    }
+
+    /// Gets the data ports of the intent filter.
+    pub fn ports(&self) -> &[String] {
+        &self.ports
+    }
+
+    /// Gets the data paths, path prefixes, suffixes and patterns of the intent filter.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Gets the data MIME types of the intent filter.
+    pub fn mime_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    /// Returns true if the filter asks the system to verify that the application owns the hosts
+    /// of its web links.
+    pub fn is_auto_verify(&self) -> bool {
+        self.auto_verify
+    }
+
+    /// Returns true if the filter handles links opened from a browser, that is, if it has the
+    /// `VIEW` action, the `BROWSABLE` category and some data scheme.
+    pub fn is_deep_link(&self) -> bool {
+        self.actions
+            .iter()
+            .any(|action| action == "android.intent.action.VIEW")
+            && self
+                .categories
+                .iter()
+                .any(|category| category == "android.intent.category.BROWSABLE")
+            && !self.schemes.is_empty()
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -689,7 +1665,7 @@ impl FromStr for InstallLocation {
     }
 }
 
-fn get_line<S: AsRef<str>>(code: S, haystack: S) -> Result<usize, error::Kind> {
+pub(crate) fn get_line<S: AsRef<str>>(code: S, haystack: S) -> Result<usize, error::Kind> {
     for (i, line) in code.as_ref().lines().enumerate() {
         if line.contains(haystack.as_ref()) {
             return Ok(i);
@@ -703,7 +1679,13 @@ fn get_line<S: AsRef<str>>(code: S, haystack: S) -> Result<usize, error::Kind> {
 mod tests {
     use std::str::FromStr;
 
-    use super::{get_line, InstallLocation, Permission, PermissionChecklist};
+    use xml::{attribute::OwnedAttribute, name::OwnedName};
+
+    use super::{
+        get_line, ComponentKind, DeclaredPermission, InstallLocation, Manifest, Permission,
+        PermissionChecklist, ProviderIssue,
+    };
+    use crate::{config::Config, criticality::Criticality, results::Results};
 
     #[test]
     fn it_get_line() {
@@ -732,6 +1714,309 @@ mod tests {
         assert!(get_line(code3, "non-matching").is_err());
     }
 
+    /// Creates the attributes of an element, from their names and values.
+    fn attributes(attributes: &[(&str, &str)]) -> Vec<OwnedAttribute> {
+        attributes
+            .iter()
+            .map(|(name, value)| OwnedAttribute::new(OwnedName::local(*name), *value))
+            .collect()
+    }
+
+    #[test]
+    fn it_intent_filters() {
+        let mut manifest = Manifest::default();
+        manifest
+            .components
+            .push(super::Component::new(ComponentKind::Activity));
+        manifest
+            .parse_intent_filter_element("intent-filter", attributes(&[("autoVerify", "true")]));
+        manifest.parse_intent_filter_element(
+            "action",
+            attributes(&[("name", "android.intent.action.VIEW")]),
+        );
+        manifest.parse_intent_filter_element(
+            "category",
+            attributes(&[("name", "android.intent.category.BROWSABLE")]),
+        );
+        manifest.parse_intent_filter_element(
+            "data",
+            attributes(&[("scheme", "https"), ("host", "example.com")]),
+        );
+        manifest.parse_intent_filter_element("data", attributes(&[("pathPrefix", "/account")]));
+        manifest
+            .components
+            .push(super::Component::new(ComponentKind::Provider));
+
+        assert_eq!(manifest.components_of(ComponentKind::Activity).count(), 1);
+        assert_eq!(manifest.components_of(ComponentKind::Service).count(), 0);
+
+        let filters = manifest.components()[0].intent_filters();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].actions(), &["android.intent.action.VIEW"]);
+        assert_eq!(
+            filters[0].categories(),
+            &["android.intent.category.BROWSABLE"]
+        );
+        assert_eq!(filters[0].schemes(), &["https"]);
+        assert_eq!(filters[0].hosts(), &["example.com"]);
+        assert_eq!(filters[0].paths(), &["/account"]);
+        assert!(filters[0].ports().is_empty());
+        assert!(filters[0].is_auto_verify());
+        assert!(filters[0].is_deep_link());
+        assert!(manifest.components()[1].intent_filters().is_empty());
+    }
+
+    #[test]
+    fn it_metadata_elements() {
+        let mut manifest = Manifest::default();
+        manifest.parse_metadata_element(
+            "uses-feature",
+            attributes(&[
+                ("name", "android.software.companion_device_setup"),
+                ("required", "false"),
+            ]),
+        );
+        manifest.parse_metadata_element(
+            "uses-library",
+            attributes(&[("name", "com.google.android.wearable")]),
+        );
+        manifest.parse_metadata_element(
+            "meta-data",
+            attributes(&[
+                ("name", "com.google.android.wearable.beta.app"),
+                ("resource", "@xml/android_wear_micro_apk"),
+            ]),
+        );
+        assert!(!manifest.is_instant());
+        manifest.parse_metadata_element("module", attributes(&[("instant", "true")]));
+
+        assert!(manifest.is_instant());
+        assert_eq!(
+            manifest.features(),
+            &["android.software.companion_device_setup"]
+        );
+        assert_eq!(manifest.libraries(), &["com.google.android.wearable"]);
+        assert_eq!(
+            manifest.meta_data("com.google.android.wearable.beta.app"),
+            Some("@xml/android_wear_micro_apk")
+        );
+        assert_eq!(manifest.meta_data("com.google.android.gms.version"), None);
+    }
+
+    #[test]
+    fn it_queries_and_declarations() {
+        let config = Config::default();
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let mut manifest = Manifest::default();
+        let mut parse = |tag, attrs: &[(&str, &str)]| {
+            manifest.parse_element(tag, attributes(attrs), &config, "com.example", &mut results)
+        };
+        parse("manifest", &[("sharedUserId", "com.example.shared")]);
+        parse(
+            "permission",
+            &[
+                ("name", "com.example.permission.SYNC"),
+                ("protectionLevel", "signature|knownSigner"),
+                ("knownCerts", "@array/known_certs"),
+            ],
+        );
+        parse("queries", &[]);
+        parse("package", &[("name", "com.example.other")]);
+        parse("intent", &[]);
+        parse("action", &[("name", "android.intent.action.SEND")]);
+        parse("provider", &[("authorities", "com.example.other.files")]);
+        parse(
+            "application",
+            &[
+                ("usesCleartextTraffic", "false"),
+                ("networkSecurityConfig", "@xml/network_security_config"),
+                ("testOnly", "true"),
+                ("requestLegacyExternalStorage", "true"),
+                ("taskAffinity", ""),
+                ("fullBackupContent", "@xml/backup_rules"),
+                ("dataExtractionRules", "@xml/data_extraction_rules"),
+            ],
+        );
+        parse("provider", &[("name", ".Provider"), ("exported", "false")]);
+        parse(
+            "service",
+            &[
+                ("name", "Recorder"),
+                ("exported", "false"),
+                ("foregroundServiceType", "camera|microphone"),
+            ],
+        );
+        parse(
+            "protected-broadcast",
+            &[("name", "com.example.action.SYNC")],
+        );
+        parse(
+            "activity",
+            &[
+                ("name", ".LoginActivity"),
+                ("launchMode", "singleTask"),
+                ("taskAffinity", "com.example.login"),
+            ],
+        );
+
+        assert_eq!(manifest.shared_user_id(), Some("com.example.shared"));
+        assert_eq!(manifest.uses_cleartext_traffic(), Some(false));
+        assert_eq!(
+            manifest.network_security_config(),
+            Some("@xml/network_security_config")
+        );
+        assert!(manifest.is_test_only());
+        assert!(manifest.requests_legacy_external_storage());
+        assert_eq!(manifest.task_affinity(), Some(""));
+        assert_eq!(manifest.full_backup_content(), Some("@xml/backup_rules"));
+        assert_eq!(
+            manifest.data_extraction_rules(),
+            Some("@xml/data_extraction_rules")
+        );
+        assert_eq!(manifest.declared_permissions().len(), 1);
+        assert!(manifest.declared_permissions()[0].is_signature());
+        assert!(manifest.declared_permissions()[0].is_known_signer());
+        assert_eq!(manifest.queried_packages(), &["com.example.other"]);
+        assert_eq!(manifest.queried_actions(), &["android.intent.action.SEND"]);
+        assert_eq!(manifest.queried_authorities(), &["com.example.other.files"]);
+        assert_eq!(manifest.components().len(), 3);
+        assert_eq!(manifest.components()[0].name(), ".Provider");
+        assert_eq!(
+            manifest.components()[0].class_name("com.example"),
+            "com.example.Provider"
+        );
+        assert_eq!(
+            manifest.components()[1].class_name("com.example"),
+            "com.example.Recorder"
+        );
+        assert_eq!(
+            manifest.components()[1].foreground_service_types(),
+            &["camera", "microphone"]
+        );
+        assert_eq!(
+            manifest.protected_broadcasts(),
+            &["com.example.action.SYNC"]
+        );
+        assert_eq!(
+            manifest.components()[2].task_affinity(),
+            Some("com.example.login")
+        );
+        assert!(manifest.components()[2].is_task_root());
+        assert!(!manifest.components()[1].is_task_root());
+    }
+
+    #[test]
+    fn it_provider_issues() {
+        let mut manifest = Manifest::default();
+        let config = Config::default();
+        let mut results = Results::from_json(
+            r#"{
+            "app_package": "com.example",
+            "app_version": "1.0",
+            "app_version_number": 1,
+            "app_fingerprint": {"md5": "aa"},
+            "app_min_sdk_number": 21,
+            "criticals": [],
+            "highs": [],
+            "mediums": [],
+            "lows": [],
+            "warnings": []
+        }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        manifest.parse_component(
+            ComponentKind::Provider,
+            attributes(&[
+                ("name", ".DataProvider"),
+                ("exported", "true"),
+                ("readPermission", "com.example.permission.READ"),
+                ("grantUriPermissions", "true"),
+            ]),
+            &config,
+            &mut results,
+        );
+        manifest.parse_path_permission(attributes(&[
+            ("pathPattern", "/.*"),
+            ("readPermission", "com.example.permission.READ_PATH"),
+        ]));
+        manifest.parse_path_permission(attributes(&[("path", "/users")]));
+
+        let provider = &manifest.components()[0];
+        assert_eq!(
+            provider.read_permission(),
+            Some("com.example.permission.READ")
+        );
+        assert_eq!(provider.write_permission(), None);
+        assert!(provider.grants_uri_permissions());
+        assert_eq!(provider.path_permissions().len(), 2);
+        assert!(provider.path_permissions()[0].is_wildcard());
+        assert!(!provider.path_permissions()[1].is_wildcard());
+        assert_eq!(
+            provider.provider_issues(21, true),
+            vec![
+                ProviderIssue::MissingWritePermission,
+                ProviderIssue::WildcardPathPermission,
+                ProviderIssue::GrantUriPermissions,
+            ]
+        );
+
+        let mut file_provider = super::Component::new(ComponentKind::Provider);
+        file_provider.name = "androidx.core.content.FileProvider".to_owned();
+        file_provider.exported = Some(false);
+        file_provider.grant_uri_permissions = true;
+        assert!(file_provider.provider_issues(21, true).is_empty());
+        assert_eq!(
+            file_provider.provider_issues(21, false),
+            vec![ProviderIssue::UnreferencedFileProvider]
+        );
+        assert_eq!(
+            ProviderIssue::UnreferencedFileProvider.criticality(),
+            Criticality::Low
+        );
+    }
+
+    #[test]
+    fn it_signature_permission() {
+        let permission = |level: &str| DeclaredPermission {
+            name: "com.example.permission.SYNC".to_owned(),
+            protection_level: level.to_owned(),
+            known_certs: None,
+        };
+
+        assert!(permission("signature").is_signature());
+        assert!(permission("signatureOrSystem").is_signature());
+        assert!(permission("0x2").is_signature());
+        assert!(permission("0x12").is_signature());
+        assert!(!permission("dangerous").is_signature());
+        assert!(!permission("normal").is_signature());
+        assert!(!permission("").is_signature());
+        assert!(!permission("signature").is_known_signer());
+
+        assert!(permission("normal").is_normal());
+        assert!(permission("").is_normal());
+        assert!(permission("0x0").is_normal());
+        assert!(permission("normal|instant").is_normal());
+        assert!(!permission("dangerous").is_normal());
+        assert!(!permission("signature|privileged").is_normal());
+        assert!(!permission("0x1").is_normal());
+    }
+
     #[test]
     fn it_install_loc_from_str() {
         assert_eq!(
@@ -3,21 +3,69 @@
 //! The static analysis of the application's source files is used to search for vulnerable
 //! code, settings and any other form of implementation that might be used as an exploit.
 
+pub mod artifacts;
+pub mod backup_rules;
+pub mod biometrics;
+pub mod broadcasts;
+pub mod capture;
+pub mod category;
 #[cfg(feature = "certificate")]
 pub mod certificate;
+pub mod check;
 pub mod code;
+pub mod crypto;
+pub mod custom_permissions;
+pub mod data_safety;
+pub mod deep_links;
+pub mod dynamic_code;
+pub mod embedded;
+pub mod escalation;
+pub mod hardening;
+pub mod homographs;
+pub mod java;
+pub mod keystores;
+pub mod libraries;
+pub mod library_cache;
+pub mod logging;
 pub mod manifest;
+pub mod network_config;
+pub mod payloads;
+pub mod resources;
+pub mod scanner;
+pub mod sdk_policy;
+pub mod secrets;
+pub mod signers;
+pub mod spyware;
+pub mod sql;
+pub mod storage;
+pub mod task_hijacking;
+pub mod tls;
+pub mod trust;
+pub mod ui_protection;
+pub mod webview;
 
 #[cfg(feature = "certificate")]
 use self::certificate::certificate_analysis;
+use self::{
+    category::Category, check::Check, scanner::Scanner, secrets::SecretsScanner,
+    ui_protection::ClipboardScanner,
+};
 #[cfg(feature = "certificate")]
 use crate::print_warning;
-use crate::{results::Results, Config};
+use crate::{results::Results, utils::PackageKind, Config};
 
 /// Runs the analysis for manifest, certificate and code files.
 ///
+/// Bare DEX and JAR files have no manifest nor resources, so only their code is analyzed. Each
+/// stage only runs if its check is enabled in the configuration.
+///
 /// * Benchmarking support.
-pub fn static_analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut Results) {
+pub fn static_analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    kind: PackageKind,
+    results: &mut Results,
+) {
     if config.is_verbose() {
         println!(
             "It's time to analyze the application. First, a static analysis will be performed, \
@@ -26,20 +74,178 @@ pub fn static_analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut
         );
     }
 
+    if !kind.is_apk() {
+        results.set_app_package(package.as_ref());
+        if scanner::has_to_run(config, Check::Secrets, SecretsScanner.tags()) {
+            secrets::analysis(config, package.as_ref(), results);
+        }
+        scanner::analysis(config, package.as_ref(), None, results);
+        if config.is_check_enabled(Check::Code) {
+            code::analysis(None, config, package.as_ref(), results);
+        }
+        return;
+    }
+
     // Run analysis for manifest file.
-    let manifest = manifest::analysis(config, package.as_ref(), results);
+    let manifest = if config.is_check_enabled(Check::Manifest) {
+        manifest::analysis(config, package.as_ref(), results)
+    } else {
+        results.set_app_package(package.as_ref());
+        None
+    };
+
+    // Report the manifest settings that weaken the security of the application.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::Hardening) {
+            hardening::analysis(config, manifest, results);
+        }
+        if config.is_check_enabled(Check::TaskHijacking) {
+            task_hijacking::analysis(config, manifest, results);
+        }
+        if config.is_check_enabled(Check::Broadcasts) {
+            broadcasts::analysis(config, manifest, results);
+        }
+        if config.is_check_enabled(Check::SdkPolicy) {
+            sdk_policy::analysis(config, manifest, results);
+        }
+    }
+
+    // Infer the category of the application, used to weight the code rules.
+    if let Some(ref manifest) = manifest {
+        let dist_folder = config.dist_folder().join(package.as_ref());
+        if let Some(category) = Category::infer(manifest, dist_folder) {
+            if config.is_verbose() {
+                println!(
+                    "The application seems to be a {} application.",
+                    category.name()
+                );
+            }
+            results.set_app_category(category);
+        }
+    }
 
     #[cfg(feature = "certificate")]
     {
         // Run analysis for certificate file.
-        if let Err(e) = certificate_analysis(config, package.as_ref(), results) {
-            print_warning(format!(
-                "there was an error analyzing the certificate: {}",
-                e
-            ))
+        if config.is_check_enabled(Check::Certificate) {
+            if let Err(e) = certificate_analysis(config, package.as_ref(), results) {
+                print_warning(format!(
+                    "there was an error analyzing the certificate: {}",
+                    e
+                ))
+            }
+        }
+    }
+
+    // Track the signers of the application across analyzed versions.
+    if config.is_check_enabled(Check::Signers) {
+        signers::analysis(config, package.as_ref(), results);
+    }
+
+    // Report debug artifacts left in the application.
+    if config.is_check_enabled(Check::Artifacts) {
+        artifacts::analysis(config, package.as_ref(), results);
+    }
+
+    // Report keystores and private keys bundled in the application.
+    if config.is_check_enabled(Check::Keystores) {
+        keystores::analysis(config, package.as_ref(), results);
+    }
+
+    // Report the trust relationships with other applications.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::Trust) {
+            trust::analysis(config, manifest, results);
         }
     }
 
+    // Report the custom permissions that don't protect the components that they guard.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::CustomPermissions) {
+            custom_permissions::analysis(config, manifest, results);
+        }
+    }
+
+    // Report the insecure settings of the network security configuration.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::NetworkConfig) {
+            network_config::analysis(config, package.as_ref(), manifest, results);
+        }
+    }
+
+    // Report the backup rules that include sensitive data.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::BackupRules) {
+            backup_rules::analysis(config, package.as_ref(), manifest, results);
+        }
+    }
+
+    // Report the unverified and overly broad deep links.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::DeepLinks) {
+            deep_links::analysis(config, manifest, results);
+        }
+    }
+
+    // Report the camera and microphone capture that the user can't notice.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::Capture) {
+            capture::analysis(config, package.as_ref(), manifest, results);
+        }
+    }
+
+    // Report the sensitive screens without screenshot and tapjacking protections.
+    if let Some(ref manifest) = manifest {
+        if scanner::has_to_run(config, Check::UiProtection, ClipboardScanner.tags()) {
+            ui_protection::analysis(config, package.as_ref(), manifest, results);
+        }
+    }
+
+    // Cross-check the data safety declaration with the permissions of the application.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::DataSafety) {
+            data_safety::analysis(config, manifest, results);
+        }
+    }
+
+    // Report the permissions and exported components that previous versions didn't have.
+    if let Some(ref manifest) = manifest {
+        if config.is_check_enabled(Check::Escalation) {
+            escalation::analysis(config, manifest, results);
+        }
+    }
+
+    // Report embedded Wear OS and Instant Apps, and companion device metadata.
+    if config.is_check_enabled(Check::Embedded) {
+        embedded::analysis(config, package.as_ref(), manifest.as_ref(), results);
+    }
+
+    // Add the spyware indicators to the capability summary.
+    if config.is_check_enabled(Check::Spyware) {
+        spyware::analysis(config, package.as_ref(), manifest.as_ref(), results);
+    }
+
+    // Report internationalized domains that imitate the domains of well-known brands.
+    if config.is_check_enabled(Check::Homographs) {
+        homographs::analysis(config, package.as_ref(), results);
+    }
+
+    // Report the secrets hardcoded in the resources and the assets.
+    if scanner::has_to_run(config, Check::Secrets, SecretsScanner.tags()) {
+        secrets::analysis(config, package.as_ref(), results);
+    }
+
+    // Search the decompiled code with the scanners of the enabled checks, such as the hardcoded
+    // secrets or the misuses of the cryptography APIs.
+    scanner::analysis(config, package.as_ref(), manifest.as_ref(), results);
+
+    // Report executable payloads hidden in the resources.
+    if config.is_check_enabled(Check::Payloads) {
+        payloads::analysis(config, package.as_ref(), results);
+    }
+
     // Run analysis for source code files.
-    code::analysis(manifest, config, package.as_ref(), results)
+    if config.is_check_enabled(Check::Code) {
+        code::analysis(manifest, config, package.as_ref(), results)
+    }
 }
@@ -0,0 +1,494 @@
+//! Network security configuration analysis module.
+//!
+//! Since Android 7.0, the `android:networkSecurityConfig` attribute of the manifest points to an
+//! XML resource that configures the network security of the application: the certificate
+//! authorities that it trusts, the certificates that it pins and whether it permits cleartext
+//! traffic, for the whole application and for specific domains. This module parses it and
+//! reports the settings that weaken the security of the connections: cleartext traffic, trust in
+//! the certificates added by the user, missing certificate pinning and debug overrides.
+
+use std::{fs, path::Path};
+
+use xml::{
+    common::Position,
+    reader::{EventReader, XmlEvent},
+};
+
+use crate::{
+    axml::is_binary_xml,
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::{manifest::Manifest, resources::Resources},
+    Config, PARSER_CONFIG,
+};
+
+/// Root element of the network security configuration, used to find it in obfuscated builds.
+const CONFIG_ROOT_ELEMENTS: [&str; 1] = ["network-security-config"];
+/// First SDK version that blocks cleartext traffic by default.
+const CLEARTEXT_BLOCKED_SDK: u32 = 28;
+/// CWE of cleartext traffic findings: cleartext transmission of sensitive information.
+const CLEARTEXT_CWE: u32 = 319;
+/// CWE of certificate trust findings: improper certificate validation.
+const CERTIFICATE_CWE: u32 = 295;
+/// CWE of debug override findings: active debug code.
+const DEBUG_CWE: u32 = 489;
+
+/// Configuration of the application, of a set of domains or of debug builds.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DomainConfig {
+    /// Line of the element in the configuration file.
+    line: usize,
+    /// Domains of the configuration, empty for the base configuration.
+    domains: Vec<String>,
+    /// The `cleartextTrafficPermitted` attribute, if it's declared.
+    cleartext_permitted: Option<bool>,
+    /// Whether the certificates added by the user are trusted.
+    user_certificates: bool,
+    /// Whether a trusted certificate authority overrides the pinned certificates.
+    override_pins: bool,
+    /// Whether the configuration pins certificates.
+    pinned: bool,
+}
+
+/// Element of the configuration file that contains the current element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    /// `<base-config>` element.
+    Base,
+    /// `<domain-config>` element, with its index.
+    Domain(usize),
+    /// `<debug-overrides>` element.
+    Debug,
+}
+
+/// Parsed network security configuration.
+#[derive(Debug, Default, PartialEq)]
+struct NetworkSecurityConfig {
+    /// Configuration of all the connections, if it's declared.
+    base: Option<DomainConfig>,
+    /// Configurations of specific domains, including nested ones.
+    domains: Vec<DomainConfig>,
+    /// Configuration only applied when the application is debuggable, if it's declared.
+    debug_overrides: Option<DomainConfig>,
+}
+
+impl NetworkSecurityConfig {
+    /// Parses the XML code of a network security configuration.
+    fn parse(code: &str) -> Self {
+        let mut config = Self::default();
+        let mut scopes: Vec<Scope> = Vec::new();
+        let mut in_domain = false;
+        let mut reader = EventReader::new_with_config(code.as_bytes(), PARSER_CONFIG.clone());
+        loop {
+            match reader.next() {
+                Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                }) => {
+                    let line = reader.position().row as usize;
+                    let attribute = |key: &str| {
+                        attributes
+                            .iter()
+                            .find(|attr| attr.name.local_name == key)
+                            .map(|attr| attr.value.trim().to_owned())
+                    };
+                    let new_config = || DomainConfig {
+                        line,
+                        cleartext_permitted: attribute("cleartextTrafficPermitted")
+                            .and_then(|value| value.parse().ok()),
+                        ..DomainConfig::default()
+                    };
+                    match name.local_name.as_str() {
+                        "base-config" => {
+                            config.base = Some(new_config());
+                            scopes.push(Scope::Base);
+                        }
+                        "domain-config" => {
+                            config.domains.push(new_config());
+                            scopes.push(Scope::Domain(config.domains.len() - 1));
+                        }
+                        "debug-overrides" => {
+                            config.debug_overrides = Some(new_config());
+                            scopes.push(Scope::Debug);
+                        }
+                        "domain" => in_domain = true,
+                        "certificates" => {
+                            let user = attribute("src").map_or(false, |src| src == "user");
+                            let override_pins =
+                                attribute("overridePins").map_or(false, |value| value == "true");
+                            if let Some(current) = config.current(&scopes) {
+                                current.user_certificates |= user;
+                                current.override_pins |= override_pins;
+                            }
+                        }
+                        "pin-set" => {
+                            if let Some(current) = config.current(&scopes) {
+                                current.pinned = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(XmlEvent::Characters(text)) => {
+                    if in_domain {
+                        if let Some(current) = config.current(&scopes) {
+                            current.domains.push(text.trim().to_owned());
+                        }
+                    }
+                }
+                Ok(XmlEvent::EndElement { name }) => match name.local_name.as_str() {
+                    "base-config" | "domain-config" | "debug-overrides" => {
+                        let _ = scopes.pop();
+                    }
+                    "domain" => in_domain = false,
+                    _ => {}
+                },
+                Ok(XmlEvent::EndDocument) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        config
+    }
+
+    /// Gets the configuration of the innermost element being parsed, if any.
+    fn current(&mut self, scopes: &[Scope]) -> Option<&mut DomainConfig> {
+        match scopes.last() {
+            Some(Scope::Base) => self.base.as_mut(),
+            Some(Scope::Domain(index)) => self.domains.get_mut(*index),
+            Some(Scope::Debug) => self.debug_overrides.as_mut(),
+            None => None,
+        }
+    }
+
+    /// Returns true if any domain configuration pins certificates.
+    fn is_pinned(&self) -> bool {
+        self.domains.iter().any(|domain| domain.pinned)
+    }
+}
+
+/// Reports the insecure network settings of the application.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: &Manifest,
+    results: &mut Results,
+) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    // Cleartext traffic is only blocked by default when targeting Android 9 or newer.
+    let cleartext_by_default =
+        manifest.target_sdk().unwrap_or_else(|| manifest.min_sdk()) < CLEARTEXT_BLOCKED_SDK;
+
+    let resources = Resources::load(&dist_folder);
+    let config_path = match manifest.network_security_config() {
+        Some(resource) => match resources.find_xml(resource, &CONFIG_ROOT_ELEMENTS) {
+            Some(path) => path,
+            None => {
+                print_warning(format!(
+                    "the network security configuration {} could not be found, so it will not be \
+                     analyzed",
+                    resource
+                ));
+                return;
+            }
+        },
+        None => {
+            // The cleartext traffic of the manifest is checked in the manifest hardening.
+            if let Some(path) = resources
+                .find_xml_by_root(&CONFIG_ROOT_ELEMENTS)
+                .into_iter()
+                .next()
+            {
+                report(
+                    config,
+                    results,
+                    Criticality::Warning,
+                    "Unused network security configuration",
+                    None,
+                    format!(
+                        "The application contains the `{}` file, but the manifest does not \
+                         reference it in the `android:networkSecurityConfig` attribute, so its \
+                         settings are not applied.",
+                        path.display()
+                    ),
+                    (path.as_path(), None),
+                );
+            }
+            report_missing_pinning(config, results, Path::new("AndroidManifest.xml"));
+            return;
+        }
+    };
+
+    let code = match fs::read(dist_folder.join(&config_path)) {
+        Ok(ref code) if !is_binary_xml(code) => String::from_utf8_lossy(code).into_owned(),
+        Ok(_) => {
+            print_warning(format!(
+                "the network security configuration at {} could not be decoded, so it will not \
+                 be analyzed",
+                config_path.display()
+            ));
+            return;
+        }
+        Err(e) => {
+            print_warning(format!(
+                "the network security configuration at {} could not be read: {}",
+                config_path.display(),
+                e
+            ));
+            return;
+        }
+    };
+    let network_config = NetworkSecurityConfig::parse(&code);
+    let file = config_path.as_path();
+    let at = |line: usize| (file, Some((code.as_str(), line)));
+
+    match network_config.base {
+        Some(DomainConfig {
+            cleartext_permitted: Some(true),
+            line,
+            ..
+        }) => report(
+            config,
+            results,
+            Criticality::Medium,
+            "Cleartext traffic permitted",
+            Some(CLEARTEXT_CWE),
+            "The base network security configuration permits cleartext traffic for every \
+             domain. Anyone in the same network can read and modify the HTTP connections of the \
+             application. Use HTTPS, and only permit cleartext traffic for the domains that \
+             need it.",
+            at(line),
+        ),
+        Some(DomainConfig {
+            cleartext_permitted: Some(false),
+            ..
+        }) => {}
+        _ if cleartext_by_default => report(
+            config,
+            results,
+            Criticality::Low,
+            "Cleartext traffic permitted by default",
+            Some(CLEARTEXT_CWE),
+            "The application targets an SDK older than Android 9, and its network security \
+             configuration does not set `cleartextTrafficPermitted`, so cleartext traffic is \
+             permitted for every domain. Set it to `false` in the `<base-config>` element.",
+            (file, None),
+        ),
+        _ => {}
+    }
+
+    for domain in network_config
+        .domains
+        .iter()
+        .filter(|domain| domain.cleartext_permitted == Some(true))
+    {
+        report(
+            config,
+            results,
+            Criticality::Low,
+            "Cleartext traffic permitted for domains",
+            Some(CLEARTEXT_CWE),
+            format!(
+                "The network security configuration permits cleartext traffic for the following \
+                 domains: {}. Anyone in the same network can read and modify these connections.",
+                list(&domain.domains)
+            ),
+            at(domain.line),
+        );
+    }
+
+    for trusting in network_config
+        .base
+        .iter()
+        .chain(network_config.domains.iter())
+        .filter(|domain| domain.user_certificates)
+    {
+        let scope = if trusting.domains.is_empty() {
+            String::from("every domain")
+        } else {
+            format!("the following domains: {}", list(&trusting.domains))
+        };
+        let pins = if trusting.override_pins {
+            " These certificates also override the pinned certificates."
+        } else {
+            ""
+        };
+        report(
+            config,
+            results,
+            Criticality::High,
+            "User certificates trusted",
+            Some(CERTIFICATE_CWE),
+            format!(
+                "The network security configuration trusts the certificates added by the user \
+                 for {}. Any certificate that the user is tricked into installing, or that a \
+                 device administrator adds, can be used to intercept the encrypted traffic of \
+                 the application.{}",
+                scope, pins
+            ),
+            at(trusting.line),
+        );
+    }
+
+    if !network_config.is_pinned() {
+        report_missing_pinning(config, results, file);
+    }
+
+    if let Some(ref debug_overrides) = network_config.debug_overrides {
+        let (criticality, state) = if manifest.is_debug() {
+            (
+                Criticality::Medium,
+                "The application is debuggable, so they are applied in this build.",
+            )
+        } else {
+            (
+                Criticality::Low,
+                "They are only applied if the application is debuggable, but they are a \
+                 leftover of development that should not be shipped.",
+            )
+        };
+        let mut settings = Vec::new();
+        if debug_overrides.user_certificates {
+            settings.push("trust the certificates added by the user");
+        }
+        if debug_overrides.override_pins {
+            settings.push("override the pinned certificates");
+        }
+        if debug_overrides.cleartext_permitted == Some(true) {
+            settings.push("permit cleartext traffic");
+        }
+        let settings = if settings.is_empty() {
+            String::new()
+        } else {
+            format!(" They {}.", settings.join(", "))
+        };
+        report(
+            config,
+            results,
+            criticality,
+            "Network security debug overrides",
+            Some(DEBUG_CWE),
+            format!(
+                "The network security configuration declares debug overrides, that relax the \
+                 security of the connections for debugging.{} {}",
+                settings, state
+            ),
+            at(debug_overrides.line),
+        );
+    }
+}
+
+/// Reports that the application does not pin the certificates of its servers.
+fn report_missing_pinning(config: &Config, results: &mut Results, file: &Path) {
+    report(
+        config,
+        results,
+        Criticality::Warning,
+        "No certificate pinning",
+        Some(CERTIFICATE_CWE),
+        "The network security configuration does not pin the certificates of any domain, so \
+         the connections of the application trust any certificate issued by a trusted \
+         authority. If the application pins certificates in its code, this can be ignored. \
+         Otherwise, consider adding a `<pin-set>` for its servers, with a backup pin.",
+        (file, None),
+    );
+}
+
+/// Formats a list of domains.
+fn list(domains: &[String]) -> String {
+    domains
+        .iter()
+        .map(|domain| format!("`{}`", domain))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reports a network security finding in the given file, pointing to a line of its code, if any.
+fn report<D: Into<String>>(
+    config: &Config,
+    results: &mut Results,
+    criticality: Criticality,
+    name: &str,
+    cwe: Option<u32>,
+    description: D,
+    (file, line): (&Path, Option<(&str, usize)>),
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let description = description.into();
+    let mut vulnerability =
+        VulnerabilityBuilder::new(criticality, name, description.as_str()).file(file);
+    if let Some(cwe) = cwe {
+        vulnerability = vulnerability.cwe(cwe);
+    }
+    if let Some((code, line)) = line {
+        vulnerability = vulnerability
+            .lines(line, line)
+            .code(get_code(code, line, line));
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Network security configuration analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::NetworkSecurityConfig;
+
+    /// Checks the parsing of the base, domain and debug configurations.
+    #[test]
+    fn it_parse() {
+        let config = NetworkSecurityConfig::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<network-security-config>
+    <base-config cleartextTrafficPermitted="false">
+        <trust-anchors>
+            <certificates src="system" />
+        </trust-anchors>
+    </base-config>
+    <domain-config cleartextTrafficPermitted="true">
+        <domain includeSubdomains="true">example.com</domain>
+        <domain-config>
+            <domain>secure.example.com</domain>
+            <pin-set expiration="2030-01-01">
+                <pin digest="SHA-256">7HIpactkIAq2Y49orFOOQKurWxmmSFZhBCoQYcRhJ3Y=</pin>
+            </pin-set>
+        </domain-config>
+        <trust-anchors>
+            <certificates src="user" overridePins="true" />
+        </trust-anchors>
+    </domain-config>
+    <debug-overrides>
+        <trust-anchors>
+            <certificates src="user" />
+        </trust-anchors>
+    </debug-overrides>
+</network-security-config>"#,
+        );
+
+        let base = config.base.as_ref().unwrap();
+        assert_eq!(base.line, 2);
+        assert_eq!(base.cleartext_permitted, Some(false));
+        assert!(!base.user_certificates);
+
+        assert_eq!(config.domains.len(), 2);
+        assert_eq!(config.domains[0].line, 7);
+        assert_eq!(config.domains[0].domains, vec!["example.com"]);
+        assert_eq!(config.domains[0].cleartext_permitted, Some(true));
+        assert!(config.domains[0].user_certificates);
+        assert!(config.domains[0].override_pins);
+        assert!(!config.domains[0].pinned);
+        assert_eq!(config.domains[1].domains, vec!["secure.example.com"]);
+        assert_eq!(config.domains[1].cleartext_permitted, None);
+        assert!(config.domains[1].pinned);
+        assert!(!config.domains[1].user_certificates);
+        assert!(config.is_pinned());
+
+        let debug_overrides = config.debug_overrides.as_ref().unwrap();
+        assert!(debug_overrides.user_certificates);
+        assert!(!debug_overrides.override_pins);
+
+        assert!(!NetworkSecurityConfig::parse("<network-security-config />").is_pinned());
+    }
+}
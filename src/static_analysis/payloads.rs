@@ -0,0 +1,361 @@
+//! Hidden payload analysis module.
+//!
+//! Applications can hide executable code under the `assets` and `res/raw` folders, often with a
+//! misleading extension, to load it at runtime. This module detects ELF, DEX and JAR payloads,
+//! and bundles of APK files, in those folders by their magic bytes, so that they can be
+//! decompiled and reported. The raw resources moved out of the `res/raw` folder by resource
+//! obfuscation are found through the resource table.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use failure::Error;
+use zip::ZipArchive;
+
+use crate::{
+    criticality::Criticality,
+    print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    static_analysis::resources::Resources,
+    Config,
+};
+
+/// Folders of the application where payloads are searched.
+const PAYLOAD_FOLDERS: [&str; 2] = ["assets", "res/raw"];
+
+/// Name of the raw resource where the Wear OS application is embedded by default.
+pub const WEAR_APK_NAME: &str = "android_wear_micro_apk";
+
+/// Kind of an executable payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// Native ELF binary.
+    Elf,
+    /// Dalvik executable.
+    Dex,
+    /// Archive containing Dalvik executables, such as an APK.
+    DexArchive,
+    /// Archive containing Java classes.
+    Jar,
+    /// Archive containing APK files, such as an Instant App bundle.
+    ApkBundle,
+}
+
+impl PayloadKind {
+    /// Gets the extensions that the payload would normally have.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            PayloadKind::Elf => &["so"],
+            PayloadKind::Dex => &["dex"],
+            PayloadKind::DexArchive => &["apk", "jar", "zip"],
+            PayloadKind::Jar => &["jar", "zip"],
+            PayloadKind::ApkBundle => &["zip", "aia"],
+        }
+    }
+
+    /// Gets the name of the payload kind.
+    fn name(self) -> &'static str {
+        match self {
+            PayloadKind::Elf => "native library",
+            PayloadKind::Dex => "DEX file",
+            PayloadKind::DexArchive => "archive with DEX files",
+            PayloadKind::Jar => "JAR file",
+            PayloadKind::ApkBundle => "bundle of APK files",
+        }
+    }
+
+    /// Returns true if the payload contains Java or Dalvik code that can be decompiled.
+    pub fn is_decompilable(self) -> bool {
+        self != PayloadKind::Elf
+    }
+
+    /// Returns true if the payload contains Dalvik code, that has to be converted to JAR first.
+    pub fn is_dalvik(self) -> bool {
+        self != PayloadKind::Elf && self != PayloadKind::Jar
+    }
+}
+
+/// Executable payload found in the application resources.
+#[derive(Debug)]
+pub struct Payload {
+    /// Path of the payload, relative to the application distribution folder.
+    path: PathBuf,
+    /// Kind of the payload.
+    kind: PayloadKind,
+    /// Architecture of native payloads.
+    architecture: Option<&'static str>,
+}
+
+impl Payload {
+    /// Gets the path of the payload, relative to the application distribution folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the kind of the payload.
+    pub fn kind(&self) -> PayloadKind {
+        self.kind
+    }
+
+    /// Gets the architecture of native payloads, if it's known.
+    pub fn architecture(&self) -> Option<&'static str> {
+        self.architecture
+    }
+
+    /// Returns true if the extension of the payload does not match its contents.
+    fn is_disguised(&self) -> bool {
+        match self.path.extension().and_then(|e| e.to_str()) {
+            Some(extension) => !self
+                .kind
+                .extensions()
+                .iter()
+                .any(|e| extension.eq_ignore_ascii_case(e)),
+            None => true,
+        }
+    }
+}
+
+/// Finds the executable payloads in the `assets` and `res/raw` folders of the application, and in
+/// its obfuscated raw resources.
+pub fn find_payloads<P: AsRef<Path>>(dist_folder: P) -> Vec<Payload> {
+    let mut payloads = Vec::new();
+    for folder in &PAYLOAD_FOLDERS {
+        let path = dist_folder.as_ref().join(folder);
+        if path.is_dir() {
+            if let Err(e) = add_payloads(dist_folder.as_ref(), &path, &mut payloads) {
+                print_warning(format!(
+                    "there was an error searching for payloads in {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+    }
+    for path in Resources::load(dist_folder.as_ref()).obfuscated_raw_files() {
+        let path = dist_folder.as_ref().join(path);
+        if let Err(e) = add_payload(dist_folder.as_ref(), &path, &mut payloads) {
+            print_warning(format!(
+                "there was an error checking the raw resource {} for payloads: {}",
+                path.display(),
+                e
+            ));
+        }
+    }
+    payloads
+}
+
+/// Adds the payloads in the given folder to the vector, recursively.
+fn add_payloads(dist_folder: &Path, path: &Path, payloads: &mut Vec<Payload>) -> Result<(), Error> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            add_payloads(dist_folder, &entry_path, payloads)?;
+        } else {
+            add_payload(dist_folder, &entry_path, payloads)?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds the given file to the vector if it's a payload.
+fn add_payload(dist_folder: &Path, path: &Path, payloads: &mut Vec<Payload>) -> Result<(), Error> {
+    let mut header = [0; 20];
+    let read = File::open(path)?.read(&mut header)?;
+    let kind = match detect(&header[..read]) {
+        Some(PayloadKind::Jar) => archive_kind(path),
+        kind => kind,
+    };
+    if let Some(kind) = kind {
+        payloads.push(Payload {
+            path: path.strip_prefix(dist_folder)?.to_path_buf(),
+            kind,
+            architecture: if kind == PayloadKind::Elf {
+                elf_architecture(&header[..read])
+            } else {
+                None
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Detects the kind of payload from its first bytes.
+///
+/// Every ZIP file is detected as a JAR file, its contents must be checked to know if it
+/// contains code.
+fn detect(header: &[u8]) -> Option<PayloadKind> {
+    if header.starts_with(b"\x7fELF") {
+        Some(PayloadKind::Elf)
+    } else if header.starts_with(b"dex\n") {
+        Some(PayloadKind::Dex)
+    } else if header.starts_with(b"PK\x03\x04") {
+        Some(PayloadKind::Jar)
+    } else {
+        None
+    }
+}
+
+/// Gets the kind of payload of an archive, if it contains code.
+fn archive_kind(path: &Path) -> Option<PayloadKind> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    let mut kind = None;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).ok()?;
+        if file.name().ends_with(".dex") {
+            return Some(PayloadKind::DexArchive);
+        } else if file.name().ends_with(".apk") {
+            kind = Some(PayloadKind::ApkBundle);
+        } else if file.name().ends_with(".class") && kind.is_none() {
+            kind = Some(PayloadKind::Jar);
+        }
+    }
+    kind
+}
+
+/// Gets the names of the APK files of a bundle.
+pub fn bundle_entries<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        if file.name().ends_with(".apk") {
+            entries.push(file.name().to_owned());
+        }
+    }
+    Ok(entries)
+}
+
+/// Gets the architecture of an ELF binary from its header.
+fn elf_architecture(header: &[u8]) -> Option<&'static str> {
+    // The machine is a 16-bit field at offset 18, in the byte order given at offset 5.
+    let machine = match header.get(5) {
+        Some(1) => u16::from(*header.get(18)?) | u16::from(*header.get(19)?) << 8,
+        Some(2) => u16::from(*header.get(18)?) << 8 | u16::from(*header.get(19)?),
+        _ => return None,
+    };
+    match machine {
+        0x03 => Some("x86"),
+        0x08 => Some("MIPS"),
+        0x28 => Some("ARM"),
+        0x3e => Some("x86-64"),
+        0xb7 => Some("AArch64"),
+        _ => None,
+    }
+}
+
+/// Reports the executable payloads hidden in the application resources.
+///
+/// Embedded applications, such as Wear OS applications and Instant App bundles, are not reported
+/// here, since the embedded artifacts analysis reports them.
+pub fn analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut Results) {
+    for payload in find_payloads(config.dist_folder().join(package.as_ref())) {
+        if payload.kind == PayloadKind::ApkBundle || is_wear_apk(payload.path()) {
+            continue;
+        }
+        let disguised = payload.is_disguised();
+        let criticality = match (payload.kind, disguised) {
+            (_, true) => Criticality::High,
+            (PayloadKind::Elf, false) => Criticality::Low,
+            (_, false) => Criticality::Medium,
+        };
+        if criticality < config.min_criticality() {
+            continue;
+        }
+
+        let mut description = format!(
+            "A {} was found in the resources of the application",
+            payload.kind.name()
+        );
+        if let Some(architecture) = payload.architecture {
+            description.push_str(&format!(" (for {})", architecture));
+        }
+        if disguised {
+            description.push_str(
+                ", with an extension that hides its real content. Hiding executable code is a \
+                 common technique used by malware to load code at runtime that evades analysis.",
+            );
+        } else {
+            description.push_str(
+                ". Code loaded at runtime from the resources is not subject to the same checks as \
+                 the code of the application, and could be replaced. If it's decompilable, its \
+                 code has been analyzed along with the rest of the application.",
+            );
+        }
+
+        let vulnerability = Vulnerability::new(
+            criticality,
+            if disguised {
+                "Disguised executable payload"
+            } else {
+                "Executable payload in resources"
+            },
+            description.as_str(),
+            Some(payload.path()),
+            None,
+            None,
+            None::<String>,
+        );
+        results.add_vulnerability(vulnerability);
+        print_vulnerability(description, criticality);
+    }
+}
+
+/// Returns true if the given path is the one of the embedded Wear OS application.
+pub fn is_wear_apk(path: &Path) -> bool {
+    path.file_stem().map_or(false, |stem| stem == WEAR_APK_NAME)
+        && path.parent() == Some(Path::new("res/raw"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{detect, elf_architecture, is_wear_apk, Payload, PayloadKind};
+
+    /// Checks the detection of payloads by their magic bytes.
+    #[test]
+    fn it_detect() {
+        assert_eq!(detect(b"\x7fELF\x01\x01"), Some(PayloadKind::Elf));
+        assert_eq!(detect(b"dex\n035\0"), Some(PayloadKind::Dex));
+        assert_eq!(detect(b"PK\x03\x04\x14\0"), Some(PayloadKind::Jar));
+        assert_eq!(detect(b"\x89PNG\r\n"), None);
+        assert_eq!(detect(b""), None);
+    }
+
+    /// Checks the detection of the architecture of native payloads.
+    #[test]
+    fn it_elf_architecture() {
+        let mut header = *b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0\x03\0\x28\0";
+        assert_eq!(elf_architecture(&header), Some("ARM"));
+        header[18] = 0xb7;
+        assert_eq!(elf_architecture(&header), Some("AArch64"));
+        assert_eq!(elf_architecture(&header[..10]), None);
+    }
+
+    /// Checks the detection of payloads with misleading extensions.
+    #[test]
+    fn it_is_disguised() {
+        let payload = |path: &str, kind| Payload {
+            path: PathBuf::from(path),
+            kind,
+            architecture: None,
+        };
+
+        assert!(!payload("assets/libfoo.so", PayloadKind::Elf).is_disguised());
+        assert!(payload("assets/logo.png", PayloadKind::Elf).is_disguised());
+        assert!(!payload("assets/plugin.APK", PayloadKind::DexArchive).is_disguised());
+        assert!(payload("res/raw/data", PayloadKind::Dex).is_disguised());
+        assert!(!payload("assets/instant.zip", PayloadKind::ApkBundle).is_disguised());
+    }
+
+    /// Checks the detection of the embedded Wear OS application.
+    #[test]
+    fn it_is_wear_apk() {
+        assert!(is_wear_apk(Path::new("res/raw/android_wear_micro_apk.apk")));
+        assert!(!is_wear_apk(Path::new("assets/android_wear_micro_apk.apk")));
+        assert!(!is_wear_apk(Path::new("res/raw/plugin.apk")));
+    }
+}
@@ -0,0 +1,211 @@
+//! Resource location module.
+//!
+//! Applications built with resource obfuscation, such as AndResGuard or the path shortening of
+//! the Android Gradle plugin, don't keep their resources in the conventional `res/<type>/<name>`
+//! paths: the files are moved to short paths such as `r/a.xml` and their names are replaced, so
+//! that only the `resources.arsc` table knows where every resource is. This module finds the
+//! resources in the conventional paths first, then in the resource table and, for the XML
+//! resources that can't be found in any of them, by the root element of their contents.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::Error;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    arsc::ResourceTable,
+    axml::{is_binary_xml, recover_elements},
+    print_warning, PARSER_CONFIG,
+};
+
+/// Folders of the decompiled application that don't contain resources.
+const NON_RESOURCE_FOLDERS: [&str; 7] = [
+    "assets", "classes", "lib", "META-INF", "original", "payloads", "shards",
+];
+
+/// Resources of an application.
+#[derive(Debug)]
+pub struct Resources {
+    /// Folder with the decompiled application.
+    dist_folder: PathBuf,
+    /// Resource table of the application, if it could be loaded.
+    table: Option<ResourceTable>,
+}
+
+impl Resources {
+    /// Loads the resources of the application decompiled in the given folder.
+    pub fn load<P: AsRef<Path>>(dist_folder: P) -> Self {
+        let dist_folder = dist_folder.as_ref().to_path_buf();
+        let table = ResourceTable::from_file(dist_folder.join("resources.arsc")).ok();
+        Self { dist_folder, table }
+    }
+
+    /// Finds the file of the resource with the given reference, such as `@xml/rules`, of the
+    /// given type and extension.
+    ///
+    /// The returned path is relative to the folder of the decompiled application. References to
+    /// resource IDs, such as `@7F120003`, are only resolved with the resource table.
+    pub fn find<R: AsRef<str>>(
+        &self,
+        reference: R,
+        type_name: &str,
+        extension: &str,
+    ) -> Option<PathBuf> {
+        let reference = reference.as_ref().trim_start_matches('@');
+        if let Some(id) = resource_id(reference) {
+            return self
+                .table
+                .as_ref()
+                .and_then(|table| table.file_by_id(id))
+                .map(PathBuf::from)
+                .filter(|path| self.dist_folder.join(path).is_file());
+        }
+
+        let name = reference.rsplit('/').next().unwrap_or(reference);
+        let conventional = Path::new("res")
+            .join(type_name)
+            .join(format!("{}.{}", name, extension));
+        if self.dist_folder.join(&conventional).is_file() {
+            return Some(conventional);
+        }
+        self.table
+            .as_ref()
+            .and_then(|table| table.file(type_name, name))
+            .map(PathBuf::from)
+            .filter(|path| self.dist_folder.join(path).is_file())
+    }
+
+    /// Finds the XML resource with the given reference, or, if it can't be found, the first XML
+    /// resource with one of the given root elements.
+    pub fn find_xml<R: AsRef<str>>(&self, reference: R, root_elements: &[&str]) -> Option<PathBuf> {
+        self.find(reference, "xml", "xml")
+            .or_else(|| self.find_xml_by_root(root_elements).into_iter().next())
+    }
+
+    /// Finds the XML resources with one of the given root elements, whatever their path.
+    pub fn find_xml_by_root(&self, root_elements: &[&str]) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if let Err(e) = add_xml_files(&self.dist_folder, &self.dist_folder, &mut files) {
+            print_warning(format!(
+                "there was an error searching for XML resources in {}: {}",
+                self.dist_folder.display(),
+                e
+            ));
+        }
+        files.sort();
+        files
+            .into_iter()
+            .filter(|path| {
+                fs::read(self.dist_folder.join(path))
+                    .ok()
+                    .and_then(|data| root_element(&data))
+                    .map_or(false, |root| root_elements.contains(&root.as_str()))
+            })
+            .collect()
+    }
+
+    /// Gets the files of the raw resources that are not in the `res/raw` folder, because their
+    /// paths have been obfuscated.
+    pub fn obfuscated_raw_files(&self) -> Vec<PathBuf> {
+        self.table
+            .as_ref()
+            .map(|table| table.files("raw"))
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .filter(|path| !path.starts_with("res/raw") && self.dist_folder.join(path).is_file())
+            .collect()
+    }
+}
+
+/// Gets the resource ID of a reference recovered from a binary XML file, such as `7F120003`.
+fn resource_id(reference: &str) -> Option<u32> {
+    if reference.len() != 8
+        || !reference
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_lowercase())
+    {
+        return None;
+    }
+    u32::from_str_radix(reference, 16).ok()
+}
+
+/// Adds the XML files in the given folder to the vector, recursively, skipping the folders that
+/// don't contain resources.
+fn add_xml_files(dist_folder: &Path, path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            let skipped = entry_path.file_name().map_or(false, |name| {
+                let name = name.to_string_lossy();
+                path == dist_folder
+                    && (NON_RESOURCE_FOLDERS.contains(&name.as_ref()) || name.starts_with("smali"))
+            });
+            if !skipped {
+                add_xml_files(dist_folder, &entry_path, files)?;
+            }
+        } else if entry_path.extension().map_or(false, |e| e == "xml")
+            && entry_path
+                .file_name()
+                .map_or(false, |name| name != "AndroidManifest.xml")
+        {
+            files.push(entry_path.strip_prefix(dist_folder)?.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Gets the name of the root element of the given XML file, either decoded or binary.
+fn root_element(data: &[u8]) -> Option<String> {
+    if is_binary_xml(data) {
+        return recover_elements(data)
+            .into_iter()
+            .next()
+            .map(|element| element.name().to_owned());
+    }
+
+    for event in EventReader::new_with_config(data, PARSER_CONFIG.clone()) {
+        match event {
+            Ok(XmlEvent::StartElement { name, .. }) => return Some(name.local_name),
+            Err(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resource location testing module.
+#[cfg(test)]
+mod test {
+    use super::{resource_id, root_element};
+
+    /// Checks the parsing of the resource IDs of recovered references.
+    #[test]
+    fn it_resource_id() {
+        assert_eq!(resource_id("7F120003"), Some(0x7f12_0003));
+        assert_eq!(resource_id("xml/rules"), None);
+        assert_eq!(resource_id("cafebabe"), None);
+        assert_eq!(resource_id("7F12"), None);
+    }
+
+    /// Checks that the root element of decoded XML files is found.
+    #[test]
+    fn it_root_element() {
+        assert_eq!(
+            root_element(
+                b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!-- Rules -->\n\
+                  <full-backup-content><include domain=\"file\" path=\".\"/></full-backup-content>"
+            ),
+            Some("full-backup-content".to_owned())
+        );
+        assert_eq!(
+            root_element(b"<network-security-config/>"),
+            Some("network-security-config".to_owned())
+        );
+        assert_eq!(root_element(b"not XML"), None);
+        assert_eq!(root_element(b""), None);
+    }
+}
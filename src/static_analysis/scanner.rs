@@ -0,0 +1,428 @@
+//! Code scanner module.
+//!
+//! The focused checks of the static analysis, such as the cryptography or the TLS checks, search
+//! the decompiled code for their own issues. Instead of walking the decompiled classes once per
+//! check, this module walks them once, in the analysis threads, and gives the code of each file to
+//! the scanners of every enabled check. The scanners follow the same selection as the code rules:
+//! the checks can be scoped to some Java packages in the configuration, and the profiles only run
+//! the scanners with the tags of their framework. Every finding gets the stable ID and the OWASP
+//! MASVS control of its issue, and the origin of the code where it was found, so that findings in
+//! known SDKs can be lowered one criticality level.
+
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use super::{
+    biometrics::BiometricsScanner,
+    check::Check,
+    code::code_package,
+    crypto::CryptoScanner,
+    dynamic_code::DynamicCodeScanner,
+    libraries::{package_matches, Origin},
+    logging::LoggingScanner,
+    manifest::Manifest,
+    secrets::SecretsScanner,
+    sql::SqlScanner,
+    storage::StorageScanner,
+    tls::TlsScanner,
+    ui_protection::ClipboardScanner,
+    webview::WebviewScanner,
+};
+use crate::{
+    cancel,
+    criticality::Criticality,
+    get_code, print_vulnerability, print_warning,
+    results::{Confidence, Results, VulnerabilityBuilder, VulnerabilitySink},
+    utils::add_java_files,
+    Config,
+};
+
+/// Issue searched by a scanner.
+pub(crate) trait CodeIssue {
+    /// Gets the stable ID of the issue, used in the reports and to suppress its findings.
+    fn id(&self) -> &'static str;
+
+    /// Gets the name of the findings of the issue.
+    fn name(&self) -> &'static str;
+
+    /// Gets the CWE of the findings of the issue.
+    fn cwe(&self) -> u32;
+
+    /// Gets the OWASP MASVS control affected by the issue, such as `MASVS-CRYPTO-1`.
+    fn owasp_masvs(&self) -> &'static str;
+
+    /// Gets the confidence of the findings of the issue.
+    fn confidence(&self) -> Confidence;
+}
+
+/// Scanner of the decompiled code, that searches each source file for the issues of a check.
+pub(crate) trait Scanner: Send + Sync {
+    /// Gets the check of the scanner.
+    fn check(&self) -> Check;
+
+    /// Gets the tags of the issues of the scanner, such as `crypto`, that select the scanners run
+    /// by each profile.
+    fn tags(&self) -> &'static [&'static str];
+
+    /// Searches the given code of a source file for issues.
+    fn scan(&self, config: &Config, code: &str) -> Vec<Finding>;
+}
+
+/// Issue found in a file.
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    /// Stable ID of the issue.
+    id: &'static str,
+    /// Name of the finding.
+    name: &'static str,
+    /// Criticality of the finding, before the origin of the code is taken into account.
+    criticality: Criticality,
+    /// Description of the finding.
+    description: String,
+    /// CWE of the issue.
+    cwe: u32,
+    /// OWASP MASVS control affected by the issue.
+    owasp_masvs: &'static str,
+    /// Confidence in the finding.
+    confidence: Confidence,
+    /// Report section where the finding is grouped, if any.
+    section: Option<&'static str>,
+    /// First line of the finding in the file.
+    start_line: usize,
+    /// Last line of the finding in the file.
+    end_line: usize,
+    /// Code snippet of the finding, if the lines around it can't be shown as they are.
+    code: Option<String>,
+}
+
+impl Finding {
+    /// Creates a finding of the given issue, in the given lines of the file.
+    pub(crate) fn new<I, D>(
+        issue: &I,
+        criticality: Criticality,
+        description: D,
+        start_line: usize,
+        end_line: usize,
+    ) -> Self
+    where
+        I: CodeIssue,
+        D: Into<String>,
+    {
+        Self {
+            id: issue.id(),
+            name: issue.name(),
+            criticality,
+            description: description.into(),
+            cwe: issue.cwe(),
+            owasp_masvs: issue.owasp_masvs(),
+            confidence: issue.confidence(),
+            section: None,
+            start_line,
+            end_line,
+            code: None,
+        }
+    }
+
+    /// Sets the report section where the finding is grouped.
+    pub(crate) fn section(mut self, section: &'static str) -> Self {
+        self.section = Some(section);
+        self
+    }
+
+    /// Sets the code snippet of the finding, instead of the lines around it.
+    pub(crate) fn code<C: Into<String>>(mut self, code: C) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+}
+
+/// Java packages where a check searches for issues.
+///
+/// The patterns follow the syntax of the package scoping of the code rules: a pattern ending in
+/// `.**` matches a package and all its subpackages, and a pattern ending in `.*` only its direct
+/// subpackages.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageScope {
+    /// Packages where the check searches for issues, or every package if empty.
+    #[serde(default)]
+    include_packages: Vec<String>,
+    /// Packages where the check never searches for issues.
+    #[serde(default)]
+    exclude_packages: Vec<String>,
+}
+
+impl PackageScope {
+    /// Returns true if the check has to search the code of the given Java package.
+    ///
+    /// Files that are not part of a Java package are only searched if the check is not
+    /// restricted to some packages.
+    pub fn has_to_check_package(&self, package: Option<&str>) -> bool {
+        match package {
+            Some(package) => {
+                (self.include_packages.is_empty()
+                    || self
+                        .include_packages
+                        .iter()
+                        .any(|pattern| package_matches(pattern, package)))
+                    && !self
+                        .exclude_packages
+                        .iter()
+                        .any(|pattern| package_matches(pattern, package))
+            }
+            None => self.include_packages.is_empty(),
+        }
+    }
+}
+
+/// Gets the scanners of every check, in the order the checks run.
+fn scanners() -> Vec<Box<dyn Scanner>> {
+    vec![
+        Box::new(SecretsScanner),
+        Box::new(CryptoScanner),
+        Box::new(TlsScanner),
+        Box::new(WebviewScanner),
+        Box::new(SqlScanner),
+        Box::new(StorageScanner),
+        Box::new(DynamicCodeScanner),
+        Box::new(LoggingScanner),
+        Box::new(BiometricsScanner),
+        Box::new(ClipboardScanner),
+    ]
+}
+
+/// Returns true if the issues of the given check, with the given tags, have to be searched: the
+/// check has to be enabled, and the selected profile has to check the tags.
+pub(crate) fn has_to_run(config: &Config, check: Check, tags: &[&str]) -> bool {
+    config.is_check_enabled(check)
+        && config
+            .profile()
+            .map_or(true, |profile| profile.checks_rule(tags))
+}
+
+/// Searches the decompiled code of the application with the scanners of the enabled checks.
+///
+/// The classes are walked once, and the files are split among the configured analysis threads.
+/// The checks that need the manifest are skipped if it's not available.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: Option<&Manifest>,
+    results: &Results,
+) {
+    let scanners: Vec<_> = scanners()
+        .into_iter()
+        .filter(|scanner| {
+            has_to_run(config, scanner.check(), scanner.tags())
+                && (manifest.is_some() || !scanner.check().needs_manifest())
+        })
+        .collect();
+    if scanners.is_empty() {
+        return;
+    }
+
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let mut files = Vec::new();
+    if let Err(e) = add_java_files(&dist_folder.join("classes"), &mut files) {
+        print_warning(format!(
+            "there was an error searching the decompiled code, the results might be \
+             incomplete: {}",
+            e
+        ));
+    }
+    if config.is_verbose() {
+        println!(
+            "Scanning {} files of the decompiled code with {} checks.",
+            files.len(),
+            scanners.len()
+        );
+    }
+
+    let scanners = Arc::new(scanners);
+    let files = Arc::new(Mutex::new(files));
+    let dist_folder = Arc::new(dist_folder);
+    let app_package = Arc::new(manifest.map(|manifest| manifest.package().to_owned()));
+    let thread_config = Arc::new(config.clone());
+
+    let handles: Vec<_> = (0..config.threads())
+        .map(|_| {
+            let thread_scanners = Arc::clone(&scanners);
+            let thread_files = Arc::clone(&files);
+            let thread_dist_folder = Arc::clone(&dist_folder);
+            let thread_app_package = Arc::clone(&app_package);
+            let thread_config = Arc::clone(&thread_config);
+            let thread_sink = results.sink();
+
+            thread::spawn(move || loop {
+                // The rest of the files are skipped if the analysis is cancelled.
+                let file = if cancel::is_cancelled() {
+                    None
+                } else {
+                    thread_files.lock().unwrap().pop()
+                };
+                match file {
+                    Some(file) => scan_file(
+                        &thread_config,
+                        &thread_scanners,
+                        &thread_dist_folder,
+                        (*thread_app_package).as_ref().map(String::as_str),
+                        &file,
+                        &thread_sink,
+                    ),
+                    None => break,
+                }
+            })
+        })
+        .collect();
+
+    for t in handles {
+        if let Err(e) = t.join() {
+            #[allow(clippy::use_debug)]
+            print_warning(format!(
+                "an error occurred when joining the scanner threads: {:?}",
+                e
+            ));
+        }
+    }
+
+    if cancel::is_cancelled() {
+        print_warning("the scan of the decompiled code was cancelled");
+    }
+}
+
+/// Scans the given file with the scanners whose check is scoped to its Java package.
+fn scan_file(
+    config: &Config,
+    scanners: &[Box<dyn Scanner>],
+    dist_folder: &Path,
+    app_package: Option<&str>,
+    file: &Path,
+    sink: &VulnerabilitySink,
+) {
+    let code = match fs::read_to_string(file) {
+        Ok(code) => code,
+        Err(e) => {
+            print_warning(format!(
+                "could not read {} scanning the decompiled code: {}",
+                file.display(),
+                e
+            ));
+            return;
+        }
+    };
+    let path = file.strip_prefix(dist_folder).unwrap_or(file);
+    let package = code_package(path);
+
+    for scanner in scanners {
+        let in_scope = config.check_scope(scanner.check()).map_or(true, |scope| {
+            scope.has_to_check_package(package.as_ref().map(String::as_str))
+        });
+        if !in_scope {
+            continue;
+        }
+        for finding in scanner.scan(config, &code) {
+            report(config, sink, app_package, path, &code, finding);
+        }
+    }
+}
+
+/// Reports a finding in the given file, relative to the decompiled application.
+///
+/// Findings in the code of known SDKs are lowered one criticality level if the configuration
+/// says so, and the findings below the minimum criticality are not reported.
+pub(crate) fn report(
+    config: &Config,
+    sink: &VulnerabilitySink,
+    app_package: Option<&str>,
+    path: &Path,
+    code: &str,
+    finding: Finding,
+) {
+    let origin = code_package(path).and_then(|package| Origin::of_package(&package, app_package));
+    let criticality = match origin {
+        Some(Origin::Sdk(_)) if config.downrank_sdk_findings() => finding.criticality.lower(),
+        _ => finding.criticality,
+    };
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let snippet = match finding.code {
+        Some(code) => code,
+        None => get_code(code, finding.start_line, finding.end_line),
+    };
+    let mut builder =
+        VulnerabilityBuilder::new(criticality, finding.name, finding.description.as_str())
+            .file(path)
+            .lines(finding.start_line, finding.end_line)
+            .code(snippet)
+            .rule_id(finding.id)
+            .cwe(finding.cwe)
+            .owasp_masvs(finding.owasp_masvs)
+            .confidence(finding.confidence);
+    if let Some(origin) = origin {
+        builder = builder.origin(origin);
+    }
+    if let Some(section) = finding.section {
+        builder = builder.section(section);
+    }
+    let _ = sink.add(builder.build());
+    print_vulnerability(finding.description, criticality);
+}
+
+/// Code scanner testing module.
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::{has_to_run, scanners, PackageScope};
+    use crate::{static_analysis::check::Check, Config};
+
+    /// Checks that each check has one scanner at most.
+    #[test]
+    fn it_scanners() {
+        let checks: BTreeSet<Check> = scanners().iter().map(|scanner| scanner.check()).collect();
+        assert_eq!(checks.len(), scanners().len());
+        assert!(checks.iter().all(|check| check.needs_code()));
+    }
+
+    /// Checks the scanners run by the profiles, depending on their tags.
+    #[test]
+    fn it_has_to_run() {
+        let config = Config::default();
+        assert!(has_to_run(&config, Check::Biometrics, &[]));
+
+        let config: Config = toml::from_str(r#"profile = "pci-mobile""#).unwrap();
+        assert!(has_to_run(&config, Check::Crypto, &["crypto"]));
+        assert!(!has_to_run(&config, Check::Biometrics, &[]));
+        assert!(!has_to_run(&config, Check::UiProtection, &["privacy"]));
+
+        let config: Config = toml::from_str(r#"disabled_checks = ["crypto"]"#).unwrap();
+        assert!(!has_to_run(&config, Check::Crypto, &["crypto"]));
+    }
+
+    /// Checks the Java packages where the checks search for issues.
+    #[test]
+    fn it_package_scope() {
+        let scope = PackageScope::default();
+        assert!(scope.has_to_check_package(Some("androidx.core")));
+        assert!(scope.has_to_check_package(None));
+
+        let scope: PackageScope = toml::from_str(
+            r#"
+            include_packages = ["com.example.**"]
+            exclude_packages = ["com.example.vendor.**"]
+            "#,
+        )
+        .unwrap();
+        assert!(scope.has_to_check_package(Some("com.example")));
+        assert!(scope.has_to_check_package(Some("com.example.login")));
+        assert!(!scope.has_to_check_package(Some("com.example.vendor.ads")));
+        assert!(!scope.has_to_check_package(Some("androidx.core")));
+        assert!(!scope.has_to_check_package(None));
+    }
+}
@@ -0,0 +1,120 @@
+//! SDK policy analysis module.
+//!
+//! Organizations usually require a baseline of SDK versions for the applications that they
+//! deploy: a recent target SDK, so that the applications get the protections of the newer
+//! Android versions, and a minimum SDK that leaves out the devices that no longer receive security
+//! updates. The baseline is declared in the `min_allowed_target_sdk` and `min_allowed_min_sdk`
+//! keys of the configuration, and the applications below it are reported with the criticality of
+//! the `sdk_policy_criticality` key.
+
+use crate::{
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// SDK version of the application below the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Violation {
+    /// The target SDK version is below the allowed one, given.
+    TargetSdk(u32),
+    /// The minimum SDK version is below the allowed one, given.
+    MinSdk(u32),
+}
+
+/// Reports the SDK versions of the application that are below the policy of the configuration.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    let criticality = config.sdk_policy_criticality();
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    // The target SDK version defaults to the minimum SDK version.
+    let target_sdk = manifest.target_sdk().unwrap_or_else(|| manifest.min_sdk());
+    let violations = check(
+        (
+            config.min_allowed_min_sdk(),
+            config.min_allowed_target_sdk(),
+        ),
+        manifest.min_sdk(),
+        target_sdk,
+    );
+
+    for violation in violations {
+        let (name, description, attribute) = match violation {
+            Violation::TargetSdk(allowed) => (
+                "Target SDK below policy",
+                format!(
+                    "The application targets SDK {}, but the policy requires targeting SDK {} or \
+                     newer. Applications that target older SDK versions don't get the security \
+                     and privacy protections of the newer Android versions. Update the \
+                     `targetSdkVersion` of the application.",
+                    target_sdk, allowed
+                ),
+                "android:targetSdkVersion",
+            ),
+            Violation::MinSdk(allowed) => (
+                "Minimum SDK below policy",
+                format!(
+                    "The application supports SDK {}, but the policy requires a minimum SDK of \
+                     {} or newer. Older Android versions don't receive security updates anymore, \
+                     and the application has to keep the insecure behaviors that they need. \
+                     Update the `minSdkVersion` of the application.",
+                    manifest.min_sdk(),
+                    allowed
+                ),
+                "android:minSdkVersion",
+            ),
+        };
+
+        let mut vulnerability = VulnerabilityBuilder::new(criticality, name, description.as_str())
+            .file("AndroidManifest.xml");
+        if let Ok(line) = get_line(manifest.code(), attribute) {
+            let code = get_code(manifest.code(), line, line);
+            vulnerability = vulnerability.lines(line, line).code(code);
+        }
+        results.add_vulnerability(vulnerability.build());
+        print_vulnerability(description, criticality);
+    }
+}
+
+/// Checks the minimum and target SDK versions of the application against the minimum allowed
+/// ones, if any.
+fn check(
+    (min_allowed_min_sdk, min_allowed_target_sdk): (Option<u32>, Option<u32>),
+    min_sdk: u32,
+    target_sdk: u32,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if let Some(allowed) = min_allowed_target_sdk {
+        if target_sdk < allowed {
+            violations.push(Violation::TargetSdk(allowed));
+        }
+    }
+    if let Some(allowed) = min_allowed_min_sdk {
+        if min_sdk < allowed {
+            violations.push(Violation::MinSdk(allowed));
+        }
+    }
+    violations
+}
+
+/// SDK policy analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{check, Violation};
+
+    /// Checks the SDK versions against the policy.
+    #[test]
+    fn it_check() {
+        assert!(check((None, None), 16, 19).is_empty());
+        assert!(check((Some(26), Some(33)), 26, 33).is_empty());
+        assert_eq!(
+            check((Some(26), Some(33)), 21, 30),
+            vec![Violation::TargetSdk(33), Violation::MinSdk(26)]
+        );
+        assert!(check((None, Some(33)), 21, 34).is_empty());
+        assert_eq!(check((Some(23), None), 21, 34), vec![Violation::MinSdk(23)]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Signing certificate tracking module.
+//!
+//! Computes the fingerprints of the certificates that signed the application and compares them
+//! with the ones of the previously analyzed version, in the history database. An application
+//! whose signer changes between versions may have had its supply chain compromised.
+
+use std::{fs, path::Path};
+
+use failure::{format_err, Error, ResultExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    criticality::Criticality,
+    der,
+    history::{Entry, History, HISTORY_FILE},
+    print_vulnerability, print_warning,
+    results::{Results, Vulnerability},
+    Config,
+};
+
+/// Gets the SHA-256 fingerprints of the certificates in the signature files of the given
+/// `META-INF` folder, sorted and without duplicates.
+pub fn signer_fingerprints<P: AsRef<Path>>(meta_inf: P) -> Result<Vec<String>, Error> {
+    let mut fingerprints = Vec::new();
+    for f in fs::read_dir(meta_inf)? {
+        let path = f?.path();
+        let is_signature = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map_or(false, |e| e == "RSA" || e == "DSA" || e == "EC");
+        if is_signature {
+            let data = fs::read(&path)?;
+            for certificate in pkcs7_certificates(&data)
+                .context(format_err!("invalid signature file {}", path.display()))?
+            {
+                fingerprints.push(hex::encode(Sha256::digest(certificate)));
+            }
+        }
+    }
+    fingerprints.sort();
+    fingerprints.dedup();
+    Ok(fingerprints)
+}
+
+/// Gets the DER encoded certificates of a PKCS#7 signed data structure.
+fn pkcs7_certificates(data: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    // ContentInfo ::= SEQUENCE { contentType OID, content [0] EXPLICIT SignedData }
+    let (content_info, _) = der::element(data, der::SEQUENCE)?;
+    let (_, rest) = der::next(content_info)?;
+    let (content, _) = der::element(rest, der::CONTEXT_0)?;
+
+    // SignedData ::= SEQUENCE { version, digestAlgorithms, contentInfo,
+    //                           certificates [0] IMPLICIT SET OF Certificate OPTIONAL, ... }
+    let (signed_data, _) = der::element(content, der::SEQUENCE)?;
+    let mut rest = signed_data;
+    for _ in 0..3 {
+        rest = der::next(rest)?.1;
+    }
+    let (mut certificates_data, _) = der::element(rest, der::CONTEXT_0)?;
+
+    let mut certificates = Vec::new();
+    while !certificates_data.is_empty() {
+        let (certificate, rest) = der::next(certificates_data)?;
+        certificates.push(certificate);
+        certificates_data = rest;
+    }
+    Ok(certificates)
+}
+
+/// Records the signers of the application in the history database, and reports a change of
+/// signers since the previously analyzed version.
+pub fn analysis<S: AsRef<str>>(config: &Config, package: S, results: &mut Results) {
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let meta_inf = if dist_folder.join("original").join("META-INF").exists() {
+        dist_folder.join("original").join("META-INF")
+    } else {
+        dist_folder.join("META-INF")
+    };
+
+    let signers = match signer_fingerprints(&meta_inf) {
+        Ok(signers) => signers,
+        Err(e) => {
+            print_warning(format!(
+                "could not read the signing certificates of the application: {}",
+                e
+            ));
+            return;
+        }
+    };
+    if signers.is_empty() {
+        if config.is_verbose() {
+            println!(
+                "The application has no JAR signature, so its signers can't be tracked in the \
+                 history."
+            );
+        }
+        return;
+    }
+    results.set_app_signers(signers.clone());
+
+    if let Err(e) = record_signers(config, results, signers) {
+        print_warning(format!(
+            "could not update the history database with the signers of the application: {}",
+            e
+        ));
+    }
+}
+
+/// Records the signers in the history database, reporting a change of signers.
+fn record_signers(
+    config: &Config,
+    results: &mut Results,
+    signers: Vec<String>,
+) -> Result<(), Error> {
+    fs::create_dir_all(config.results_folder())?;
+    let history_path = config.results_folder().join(HISTORY_FILE);
+    let mut history = History::load(&history_path)?;
+
+    let entry = Entry::new(results.app_version(), results.app_version_num(), signers);
+    let previous = history.record(results.app_package(), entry.clone());
+    history.save(&history_path)?;
+
+    // Versions recorded without signers, such as the ones without a JAR signature, are not
+    // compared.
+    if let Some(previous) = previous {
+        if !previous.signers().is_empty() && previous.signers() != entry.signers() {
+            let criticality = Criticality::Critical;
+            if criticality >= config.min_criticality() {
+                let description = format!(
+                    "The application is signed with different certificates than version {} \
+                     ({}). Android would not install it as an update, and it could mean that \
+                     the application has been tampered with or that the signing keys have been \
+                     compromised. Previous signers: {}. Current signers: {}.",
+                    previous.version(),
+                    previous.version_number(),
+                    previous.signers().join(", "),
+                    entry.signers().join(", ")
+                );
+                results.add_vulnerability(Vulnerability::new(
+                    criticality,
+                    "Signing certificate changed",
+                    description.as_str(),
+                    None::<String>,
+                    None,
+                    None,
+                    None::<String>,
+                ));
+                print_vulnerability(description, criticality);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::pkcs7_certificates;
+
+    /// Checks that the certificates are extracted from a PKCS#7 signed data structure.
+    #[test]
+    fn it_pkcs7_certificates() {
+        let certificate_1 = [0x30, 0x03, 0x02, 0x01, 0x01];
+        let certificate_2 = [0x30, 0x03, 0x02, 0x01, 0x02];
+
+        let mut certificates = vec![0xA0, 0x0A];
+        certificates.extend_from_slice(&certificate_1);
+        certificates.extend_from_slice(&certificate_2);
+
+        // version, digestAlgorithms, contentInfo, certificates and signerInfos.
+        let mut signed_data = vec![0x02, 0x01, 0x01, 0x31, 0x00, 0x30, 0x00];
+        signed_data.extend_from_slice(&certificates);
+        signed_data.extend_from_slice(&[0x31, 0x00]);
+
+        let mut content = vec![0xA0, signed_data.len() as u8 + 2, 0x30];
+        content.push(signed_data.len() as u8);
+        content.extend_from_slice(&signed_data);
+
+        // signedData OID (1.2.840.113549.1.7.2).
+        let mut content_info = vec![
+            0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02,
+        ];
+        content_info.extend_from_slice(&content);
+
+        // Long form length.
+        let mut data = vec![0x30, 0x81, content_info.len() as u8];
+        data.extend_from_slice(&content_info);
+
+        let found = pkcs7_certificates(&data).unwrap();
+        assert_eq!(found, vec![&certificate_1[..], &certificate_2[..]]);
+
+        assert!(pkcs7_certificates(&data[..20]).is_err());
+        assert!(pkcs7_certificates(&[0x31, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Spyware indicator analysis module.
+//!
+//! Some capabilities are rarely seen outside of spyware: listening to every change of the
+//! clipboard, where users paste passwords and one-time codes, in the same class that talks to the
+//! network, or capturing the screen with the `MediaProjection` API in an application that has
+//! nothing to do with screen sharing. This module searches the code for them and adds them to
+//! the capability summary of the report, marked as spyware indicators.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::code::get_line_for;
+use crate::{
+    print_warning,
+    results::{Capability, Results},
+    static_analysis::manifest::Manifest,
+    utils::add_java_files,
+    Config,
+};
+
+/// Classes and calls that send data to the network.
+const NETWORK_SINKS: [&str; 10] = [
+    "HttpURLConnection",
+    "HttpsURLConnection",
+    "openConnection(",
+    "OkHttpClient",
+    "newCall(",
+    "new Socket(",
+    "Retrofit",
+    "HttpPost",
+    "WebSocket",
+    "RequestQueue",
+];
+
+/// Words in the package or the label of screen sharing applications.
+const SCREEN_SHARING_KEYWORDS: [&str; 10] = [
+    "screen",
+    "record",
+    "cast",
+    "mirror",
+    "remote",
+    "meet",
+    "conference",
+    "stream",
+    "capture",
+    "share",
+];
+
+/// Code folders of the SDKs used by screen sharing applications.
+const SCREEN_SHARING_SDKS: [&str; 7] = [
+    "org/webrtc",
+    "us/zoom",
+    "com/twilio/video",
+    "io/agora",
+    "com/teamviewer",
+    "com/hbisoft/hbrecorder",
+    "com/jitsi",
+];
+
+/// Evidence of a capability in the code.
+#[derive(Debug)]
+struct Evidence {
+    /// Path of the file, relative to the dist folder.
+    path: PathBuf,
+    /// Line of the evidence in the file.
+    line: usize,
+}
+
+/// Searches the code for clipboard monitoring combined with network access, and for screen
+/// capture in applications that don't share their screen, and adds them to the capability
+/// summary.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: Option<&Manifest>,
+    results: &mut Results,
+) {
+    lazy_static! {
+        static ref MEDIA_PROJECTION: Regex =
+            Regex::new(r"\b(?:createScreenCaptureIntent|getMediaProjection)\s*\(").unwrap();
+    }
+
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let mut files = Vec::new();
+    if let Err(e) = add_java_files(&dist_folder.join("classes"), &mut files) {
+        print_warning(format!(
+            "there was an error searching the code for spyware indicators: {}",
+            e
+        ));
+    }
+
+    let mut clipboard: Option<(Evidence, bool)> = None;
+    let mut projection: Option<Evidence> = None;
+    for file in files {
+        let code = match fs::read_to_string(&file) {
+            Ok(code) => code,
+            Err(e) => {
+                print_warning(format!(
+                    "could not read {} searching for spyware indicators: {}",
+                    file.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+        let path = file.strip_prefix(&dist_folder).unwrap_or(&file);
+
+        // The listeners that share a class with a network sink are the most relevant evidence.
+        if let Some((index, network)) = check_clipboard(&code) {
+            if clipboard
+                .as_ref()
+                .map_or(true, |(_, found)| network && !found)
+            {
+                let evidence = Evidence {
+                    path: path.to_path_buf(),
+                    line: get_line_for(index, &code),
+                };
+                clipboard = Some((evidence, network));
+            }
+        }
+        if projection.is_none() {
+            if let Some(call) = MEDIA_PROJECTION.find(&code) {
+                projection = Some(Evidence {
+                    path: path.to_path_buf(),
+                    line: get_line_for(call.start(), &code),
+                });
+            }
+        }
+    }
+
+    if let Some((evidence, network)) = clipboard {
+        let capability = if network {
+            Capability::new(
+                "Clipboard monitoring",
+                "The application listens to every change of the clipboard with \
+                 `addPrimaryClipChangedListener()`, in a class that also sends data to the \
+                 network. Users paste passwords, one-time codes and wallet addresses, so this is \
+                 a common way of stealing them.",
+            )
+            .spyware_indicator()
+        } else {
+            Capability::new(
+                "Clipboard monitoring",
+                "The application listens to every change of the clipboard with \
+                 `addPrimaryClipChangedListener()`.",
+            )
+        };
+        add_capability(config, results, capability, &evidence);
+    }
+
+    if let Some(evidence) = projection {
+        let capability = if is_screen_sharing(manifest, &dist_folder) {
+            Capability::new(
+                "Screen capture",
+                "The application captures the screen with the `MediaProjection` API, as expected \
+                 from a screen sharing application.",
+            )
+        } else {
+            Capability::new(
+                "Screen capture",
+                "The application captures the screen with the `MediaProjection` API, but it does \
+                 not look like a screen sharing or recording application. Screen captures can \
+                 leak everything that the user sees, including the data of other applications.",
+            )
+            .spyware_indicator()
+        };
+        add_capability(config, results, capability, &evidence);
+    }
+}
+
+/// Checks if the given code listens to the changes of the clipboard.
+///
+/// If it does, it returns the index of the listener and whether the code also sends data to the
+/// network.
+fn check_clipboard(code: &str) -> Option<(usize, bool)> {
+    let index = code.find("addPrimaryClipChangedListener(")?;
+    let network = NETWORK_SINKS.iter().any(|sink| code.contains(sink));
+    Some((index, network))
+}
+
+/// Checks if the application looks like a screen sharing or recording application, from its
+/// package, its label and its SDKs.
+fn is_screen_sharing(manifest: Option<&Manifest>, dist_folder: &Path) -> bool {
+    let named = manifest.map_or(false, |manifest| {
+        has_screen_sharing_keyword(manifest.package())
+            || has_screen_sharing_keyword(manifest.label())
+    });
+    named
+        || SCREEN_SHARING_SDKS
+            .iter()
+            .any(|folder| dist_folder.join("classes").join(folder).is_dir())
+}
+
+/// Checks if the given package or label contains a word of screen sharing applications.
+fn has_screen_sharing_keyword(text: &str) -> bool {
+    let text = text.to_lowercase();
+    SCREEN_SHARING_KEYWORDS
+        .iter()
+        .any(|keyword| text.contains(keyword))
+}
+
+/// Adds a capability to the summary, with its evidence.
+fn add_capability(
+    config: &Config,
+    results: &mut Results,
+    capability: Capability,
+    evidence: &Evidence,
+) {
+    if config.is_verbose() && capability.is_spyware_indicator() {
+        println!("Spyware indicator found: {}.", capability.name());
+    }
+    results.add_capability(capability.location(&evidence.path, evidence.line));
+}
+
+/// Spyware indicator analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{check_clipboard, has_screen_sharing_keyword};
+
+    /// Checks the detection of clipboard listeners, with and without network sinks.
+    #[test]
+    fn it_check_clipboard() {
+        let listener = "manager.addPrimaryClipChangedListener(this);\n";
+        assert_eq!(check_clipboard(listener), Some((8, false)));
+
+        let exfiltration = format!(
+            "{}HttpURLConnection connection = (HttpURLConnection) url.openConnection();\n",
+            listener
+        );
+        assert_eq!(check_clipboard(&exfiltration), Some((8, true)));
+        assert_eq!(check_clipboard("manager.getPrimaryClip();"), None);
+    }
+
+    /// Checks the detection of screen sharing applications by their name.
+    #[test]
+    fn it_screen_sharing_keyword() {
+        assert!(has_screen_sharing_keyword("com.example.screenrecorder"));
+        assert!(has_screen_sharing_keyword("Remote Support"));
+        assert!(!has_screen_sharing_keyword("com.example.flashlight"));
+    }
+}
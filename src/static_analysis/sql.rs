@@ -0,0 +1,384 @@
+//! SQL injection analysis module.
+//!
+//! SQLite queries built by concatenating strings let an attacker that controls any of the
+//! concatenated values change the query: read other tables, bypass conditions or modify the
+//! database. This module searches the decompiled code for `rawQuery()` and `execSQL()` calls whose
+//! query is concatenated with variables, directly in the call or in a local variable of the same
+//! method, and for content providers whose `query()` method passes the selection of the caller to
+//! the database, since the selection is an SQL expression written by other applications. Each
+//! finding points to the offending call, with the code of the method up to it.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{arguments, enclosing_method, mask, methods, Method},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+/// CWE of the SQL injection findings: improper neutralization of special elements used in an SQL
+/// command.
+const SQL_INJECTION_CWE: u32 = 89;
+
+/// Criticality of the SQL injection findings.
+const SQL_INJECTION_CRITICALITY: Criticality = Criticality::High;
+
+/// Maximum number of lines of the method shown before the offending call.
+const MAX_SNIPPET_LINES: usize = 10;
+
+lazy_static! {
+    /// Calls that run SQL queries as they are.
+    static ref RAW_QUERY: Regex = Regex::new(r"\.\s*(?:rawQuery|execSQL)\s*\(").unwrap();
+    /// Classes that implement a content provider.
+    static ref CONTENT_PROVIDER: Regex = Regex::new(r"\bextends\s+ContentProvider\b").unwrap();
+    /// Calls that query the database from a content provider.
+    static ref PROVIDER_QUERY: Regex = Regex::new(r"\b(?:query|rawQuery)\s*\(").unwrap();
+    /// Query builders in strict mode, that reject malicious selections.
+    static ref STRICT_BUILDER: Regex =
+        Regex::new(r"\.\s*setStrict(?:Grammar)?\s*\(\s*true\s*\)").unwrap();
+    /// Constants, by the Java naming conventions.
+    static ref CONSTANT: Regex = Regex::new(r"^(?:[\w$]+\.)*[A-Z][A-Z0-9_]*$").unwrap();
+    /// Java identifiers.
+    static ref IDENTIFIER: Regex = Regex::new(r"^[A-Za-z_$][\w$]*$").unwrap();
+}
+
+/// Kind of SQL injection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Raw query concatenated with variables in the call.
+    Concatenation,
+    /// Raw query stored in a local variable concatenated with variables.
+    ConcatenatedVariable,
+    /// Selection of the caller of a content provider passed to the database.
+    ProviderSelection,
+}
+
+impl CodeIssue for Kind {
+    fn id(&self) -> &'static str {
+        match *self {
+            Kind::Concatenation => "sql-raw-query-concatenation",
+            Kind::ConcatenatedVariable => "sql-raw-query-concatenated-variable",
+            Kind::ProviderSelection => "sql-provider-selection",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Kind::Concatenation | Kind::ConcatenatedVariable => "SQL injection in raw query",
+            Kind::ProviderSelection => "SQL injection in content provider",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        SQL_INJECTION_CWE
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        match *self {
+            Kind::Concatenation | Kind::ConcatenatedVariable => "MASVS-CODE-4",
+            // The injection is reachable through the interface of the provider.
+            Kind::ProviderSelection => "MASVS-PLATFORM-1",
+        }
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            Kind::Concatenation => Confidence::High,
+            // The variable could be assigned again before the call, and the provider could be
+            // private.
+            Kind::ConcatenatedVariable | Kind::ProviderSelection => Confidence::Medium,
+        }
+    }
+}
+
+impl Kind {
+    /// Gets the description of the findings of the kind, in the given method.
+    fn description(self, method: &str) -> String {
+        match self {
+            Kind::Concatenation | Kind::ConcatenatedVariable => format!(
+                "The `{}()` method runs an SQL query built by concatenating variables. If any of \
+                 them can be controlled by an attacker, such as the extras of an intent or the \
+                 data of a server, the attacker can change the query to read or modify any data \
+                 of the database. Use `?` placeholders and pass the values in the selection \
+                 arguments instead.",
+                method
+            ),
+            Kind::ProviderSelection => format!(
+                "The `{}()` method of a content provider passes the selection of the caller to \
+                 the database. The selection is an SQL expression written by the applications \
+                 that query the provider, so if the provider is exported they can inject SQL to \
+                 read the tables that the provider does not expose. Build the selection in the \
+                 provider, or use an `SQLiteQueryBuilder` in strict mode.",
+                method
+            ),
+        }
+    }
+}
+
+/// SQL injection found in the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Injection {
+    /// Kind of the injection.
+    kind: Kind,
+    /// Name of the method with the offending call.
+    method: String,
+    /// Index of the declaration of the method in the code.
+    method_start: usize,
+    /// Index of the offending call in the code.
+    index: usize,
+}
+
+/// Scanner of the SQL injections.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SqlScanner;
+
+impl Scanner for SqlScanner {
+    fn check(&self) -> Check {
+        Check::Sql
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["injection"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_injections(code)
+            .into_iter()
+            .map(|injection| {
+                let line = get_line_for(injection.index, code);
+                let method_line = get_line_for(injection.method_start, code);
+                let start_line = if line - method_line <= MAX_SNIPPET_LINES {
+                    method_line
+                } else {
+                    line
+                };
+                Finding::new(
+                    &injection.kind,
+                    SQL_INJECTION_CRITICALITY,
+                    injection.kind.description(&injection.method),
+                    start_line,
+                    line,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Finds the SQL injections in the given code.
+fn find_injections(code: &str) -> Vec<Injection> {
+    let masked = mask(code);
+    let methods = methods(&masked);
+    let mut injections = Vec::new();
+
+    for call in RAW_QUERY.find_iter(&masked) {
+        let method = match enclosing_method(&methods, call.start()) {
+            Some(method) => method,
+            None => continue,
+        };
+        let query = match arguments(&masked, call.end()).into_iter().next() {
+            Some(query) => masked[query].trim(),
+            None => continue,
+        };
+        let kind = if is_concatenated(query) {
+            Kind::Concatenation
+        } else if IDENTIFIER.is_match(query)
+            && is_concatenated_variable(&masked, method, query, call.start())
+        {
+            Kind::ConcatenatedVariable
+        } else {
+            continue;
+        };
+        injections.push(Injection {
+            kind,
+            method: method.name().to_owned(),
+            method_start: method.start(),
+            index: call.start(),
+        });
+    }
+
+    if CONTENT_PROVIDER.is_match(&masked) {
+        injections.extend(
+            methods
+                .iter()
+                .filter_map(|method| provider_selection(&masked, method)),
+        );
+    }
+
+    injections.sort_by_key(|injection| injection.index);
+    injections
+}
+
+/// Checks if the given expression concatenates a variable.
+///
+/// String and number literals are not variables, and neither are the constants named following
+/// the Java conventions, such as `TABLE_NAME`.
+fn is_concatenated(expression: &str) -> bool {
+    let operands = operands(expression);
+    operands.len() > 1
+        && operands.iter().any(|operand| {
+            !operand.starts_with('"')
+                && !operand.starts_with('\'')
+                && !operand.starts_with(|c: char| c.is_ascii_digit())
+                && !CONSTANT.is_match(operand)
+        })
+}
+
+/// Checks if the given local variable of the method is assigned a concatenation of variables, or
+/// has variables appended, before the given index.
+fn is_concatenated_variable(masked: &str, method: &Method, variable: &str, index: usize) -> bool {
+    let assignment = Regex::new(&format!(
+        r"\b{}\s*(\+?)=\s*([^;]*);",
+        regex::escape(variable)
+    ))
+    .unwrap();
+    let body = method.body();
+    let code = &masked[body.start..index.max(body.start)];
+    // The captures borrow the regular expression, so they can't be the tail expression.
+    let concatenated = assignment.captures_iter(code).any(|captures| {
+        let value = captures[2].trim();
+        if captures[1].is_empty() {
+            is_concatenated(value)
+        } else {
+            is_concatenated(&format!("{} + {}", variable, value))
+        }
+    });
+    concatenated
+}
+
+/// Gets the offending call of the given method, if it's the `query()` method of a content
+/// provider that passes the selection of the caller to the database.
+fn provider_selection(masked: &str, method: &Method) -> Option<Injection> {
+    let types = method.parameter_types();
+    if method.name() != "query"
+        || types.len() < 3
+        || !types[0].ends_with("Uri")
+        || types[2] != "String"
+    {
+        return None;
+    }
+    let selection = Regex::new(&format!(
+        r"\b{}\b",
+        regex::escape(method.parameter_names()[2])
+    ))
+    .ok()?;
+    let body = &masked[method.body()];
+    if STRICT_BUILDER.is_match(body) {
+        return None;
+    }
+
+    PROVIDER_QUERY
+        .find_iter(body)
+        .find(|call| {
+            arguments(body, call.end())
+                .into_iter()
+                .any(|argument| selection.is_match(&body[argument]))
+        })
+        .map(|call| Injection {
+            kind: Kind::ProviderSelection,
+            method: method.name().to_owned(),
+            method_start: method.start(),
+            index: method.body().start + call.start(),
+        })
+}
+
+/// Gets the operands of the top-level concatenations of the given expression.
+fn operands(expression: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    for (i, c) in expression.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '+' if depth == 0 => {
+                operands.push(expression[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(expression[start..].trim());
+    operands.retain(|operand| !operand.is_empty());
+    operands
+}
+
+/// SQL injection analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_injections, get_line_for, Kind};
+
+    /// Checks the raw queries concatenated with variables.
+    #[test]
+    fn it_raw_query_injections() {
+        let code = "public Cursor findUser(SQLiteDatabase db, String name) {\n\
+                    return db.rawQuery(\"SELECT * FROM users WHERE name = '\" + name + \"'\", \
+                    null);\n\
+                    }\n\
+                    public void deleteUser(SQLiteDatabase db, Intent intent) {\n\
+                    String sql = \"DELETE FROM users WHERE id = \";\n\
+                    sql += intent.getStringExtra(\"id\");\n\
+                    db.execSQL(sql);\n\
+                    }";
+
+        let injections = find_injections(code)
+            .into_iter()
+            .map(|injection| {
+                (
+                    injection.kind,
+                    injection.method,
+                    get_line_for(injection.index, code),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            injections,
+            vec![
+                (Kind::Concatenation, "findUser".to_owned(), 1),
+                (Kind::ConcatenatedVariable, "deleteUser".to_owned(), 6),
+            ]
+        );
+    }
+
+    /// Checks the content providers that pass the selection of the caller to the database.
+    #[test]
+    fn it_provider_selection() {
+        let code = "public class UsersProvider extends ContentProvider {\n\
+                    public Cursor query(Uri uri, String[] projection, String where, \
+                    String[] args, String sortOrder) {\n\
+                    SQLiteDatabase db = helper.getReadableDatabase();\n\
+                    return db.query(\"users\", projection, where, args, null, null, sortOrder);\n\
+                    }\n\
+                    }";
+
+        let injections = find_injections(code);
+        assert_eq!(injections.len(), 1);
+        assert_eq!(injections[0].kind, Kind::ProviderSelection);
+        assert_eq!(injections[0].method, "query");
+        assert_eq!(get_line_for(injections[0].index, code), 3);
+        assert_eq!(get_line_for(injections[0].method_start, code), 1);
+    }
+
+    /// Checks that parameterized queries and constants are not reported.
+    #[test]
+    fn it_safe_queries() {
+        let code = "public class UsersProvider extends ContentProvider {\n\
+                    public Cursor find(SQLiteDatabase db, String name) {\n\
+                    db.execSQL(\"DELETE FROM \" + TABLE_NAME + \" WHERE age > \" + 18);\n\
+                    String sql = \"SELECT * FROM \" + Users.TABLE + \" WHERE name = ?\";\n\
+                    // db.rawQuery(\"SELECT * FROM users WHERE name = \" + name, null);\n\
+                    return db.rawQuery(sql, new String[] { name });\n\
+                    }\n\
+                    public Cursor query(Uri uri, String[] projection, String selection, \
+                    String[] args, String sortOrder) {\n\
+                    SQLiteQueryBuilder builder = new SQLiteQueryBuilder();\n\
+                    builder.setStrict(true);\n\
+                    return builder.query(db, projection, selection, args, null, null, null);\n\
+                    }\n\
+                    }";
+
+        assert!(find_injections(code).is_empty());
+    }
+}
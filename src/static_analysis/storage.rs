@@ -0,0 +1,299 @@
+//! Data storage analysis module.
+//!
+//! Files in the external storage can be read by any application with the storage permissions, and
+//! files created with the world readable or writeable modes can be read or modified by any
+//! application. The default rules find the storage APIs and the named modes, but decompilers
+//! usually replace the modes with their numeric values, and the name of a file is what tells if
+//! it's sensitive. This module searches the decompiled code for files with sensitive names created
+//! in the external storage, and for files, preferences and databases created with numeric world
+//! accessible modes. All the findings are grouped in the *Data storage* section of the report.
+
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{arguments, enclosing_method, mask, methods},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+/// Report section of the data storage findings.
+const DATA_STORAGE_SECTION: &str = "Data storage";
+
+/// Bit of the world readable mode of the files.
+const MODE_WORLD_READABLE: u32 = 0x1;
+/// Bit of the world writeable mode of the files.
+const MODE_WORLD_WRITEABLE: u32 = 0x2;
+
+lazy_static! {
+    /// Folders of the external storage.
+    static ref EXTERNAL_STORAGE: Regex = Regex::new(
+        r"\b(?:getExternalStorageDirectory\s*\(\s*\)|getExternalStoragePublicDirectory\s*\(|getExternalFilesDirs?\s*\()"
+    )
+    .unwrap();
+    /// File names that usually contain sensitive information.
+    static ref SENSITIVE_NAME: Regex = Regex::new(
+        r"(?i)passw(?:or)?d|secret|token|credential|private.?key|session|auth|account|\.(?:db|sqlite|jks|bks|keystore|p12|pfx|pem|key)$"
+    )
+    .unwrap();
+    /// Assignment of the result of an expression to a variable, at the end of the given code.
+    static ref ASSIGNMENT: Regex = Regex::new(r"\b([A-Za-z_$][\w$]*)\s*=\s*[^=]*$").unwrap();
+    /// Calls that create files, preferences or databases with a mode as their second argument.
+    static ref MODE_CALL: Regex = Regex::new(
+        r"\b(openFileOutput|getSharedPreferences|openOrCreateDatabase|getDir)\s*\("
+    )
+    .unwrap();
+}
+
+/// Insecure storage of data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// File with a sensitive name in the external storage.
+    SensitiveExternalFile,
+    /// File, preferences or database created with a numeric world readable mode.
+    WorldReadableMode,
+    /// File, preferences or database created with a numeric world writeable mode.
+    WorldWriteableMode,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::SensitiveExternalFile => "storage-sensitive-external-file",
+            Issue::WorldReadableMode => "storage-world-readable-mode",
+            Issue::WorldWriteableMode => "storage-world-writeable-mode",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::SensitiveExternalFile => "Sensitive file in external storage",
+            Issue::WorldReadableMode => "World readable file mode",
+            Issue::WorldWriteableMode => "World writeable file mode",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Insecure storage of sensitive information.
+            Issue::SensitiveExternalFile => 922,
+            // Incorrect permission assignment for critical resource.
+            Issue::WorldReadableMode | Issue::WorldWriteableMode => 732,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        "MASVS-STORAGE-2"
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            // The name of a file does not prove that its content is sensitive.
+            Issue::SensitiveExternalFile => Confidence::Medium,
+            Issue::WorldReadableMode | Issue::WorldWriteableMode => Confidence::High,
+        }
+    }
+}
+
+impl Issue {
+    /// Gets the description of the findings of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            Issue::SensitiveExternalFile => {
+                "The application creates a file with a name that suggests sensitive information, \
+                 such as credentials, keys or a database, in the external storage. Any \
+                 application with the storage permissions can read and modify the files of the \
+                 external storage. Store sensitive files in the internal storage of the \
+                 application."
+            }
+            Issue::WorldReadableMode => {
+                "The application creates a file, preferences or a database with the numeric value \
+                 of the `MODE_WORLD_READABLE` mode, so any application in the device can read it. \
+                 Use `MODE_PRIVATE`, and share data with other applications through a content \
+                 provider."
+            }
+            Issue::WorldWriteableMode => {
+                "The application creates a file, preferences or a database with the numeric value \
+                 of the `MODE_WORLD_WRITEABLE` mode, so any application in the device can modify \
+                 it. Use `MODE_PRIVATE`, and share data with other applications through a content \
+                 provider."
+            }
+        }
+    }
+}
+
+/// Scanner of the insecure storage of data.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StorageScanner;
+
+impl Scanner for StorageScanner {
+    fn check(&self) -> Check {
+        Check::Storage
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["storage"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_issues(code)
+            .into_iter()
+            .map(|(issue, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(&issue, Criticality::High, issue.description(), line, line)
+                    .section(DATA_STORAGE_SECTION)
+            })
+            .collect()
+    }
+}
+
+/// Finds the insecure storage of data in the given code, with their index in it.
+fn find_issues(code: &str) -> Vec<(Issue, usize)> {
+    let masked = mask(code);
+    let mut issues = Vec::new();
+
+    let methods = methods(&masked);
+    for folder in EXTERNAL_STORAGE.find_iter(&masked) {
+        let start = statement_start(&masked, folder.start());
+        let end = statement_end(&masked, folder.end());
+        if has_sensitive_name(code, &masked, start..end) {
+            issues.push((Issue::SensitiveExternalFile, folder.start()));
+            continue;
+        }
+
+        // The folder can be stored in a variable and used in the next statements of the method.
+        let variable = match ASSIGNMENT.captures(&masked[start..folder.start()]) {
+            Some(captures) => captures[1].to_owned(),
+            None => continue,
+        };
+        let usage = Regex::new(&format!(r"\b{}\b", regex::escape(&variable))).unwrap();
+        let scope_end = enclosing_method(&methods, folder.start())
+            .map_or(masked.len(), |method| method.body().end);
+        let mut statement = end;
+        while statement < scope_end {
+            let next = statement_end(&masked, statement + 1).min(scope_end);
+            if usage.is_match(&masked[statement..next])
+                && has_sensitive_name(code, &masked, statement..next)
+            {
+                let index = statement + usage.find(&masked[statement..next]).unwrap().start();
+                issues.push((Issue::SensitiveExternalFile, index));
+                break;
+            }
+            statement = next;
+        }
+    }
+
+    for call in MODE_CALL.captures_iter(&masked) {
+        let call_match = call.get(0).unwrap();
+        let mode = match arguments(&masked, call_match.end()).into_iter().nth(1) {
+            Some(mode) => masked[mode].trim(),
+            None => continue,
+        };
+        // The world readable and writeable modes of `openFileOutput()` are found by the rules.
+        if &call[1] == "openFileOutput" && (mode == "1" || mode == "2") {
+            continue;
+        }
+        let value = match parse_mode(mode) {
+            Some(value) => value,
+            None => continue,
+        };
+        if value & MODE_WORLD_READABLE != 0 {
+            issues.push((Issue::WorldReadableMode, call_match.start()));
+        }
+        if value & MODE_WORLD_WRITEABLE != 0 {
+            issues.push((Issue::WorldWriteableMode, call_match.start()));
+        }
+    }
+
+    issues.sort_by_key(|&(_, index)| index);
+    issues
+}
+
+/// Checks if any of the string literals in the given range of the code has a sensitive file name.
+///
+/// The literals are found in the masked code, so the ones in comments are ignored.
+fn has_sensitive_name(code: &str, masked: &str, range: Range<usize>) -> bool {
+    let quotes = masked[range.clone()]
+        .match_indices('"')
+        .map(|(i, _)| range.start + i)
+        .collect::<Vec<_>>();
+    quotes
+        .chunks(2)
+        .filter(|quotes| quotes.len() == 2)
+        .any(|quotes| SENSITIVE_NAME.is_match(&code[quotes[0] + 1..quotes[1]]))
+}
+
+/// Gets the index of the start of the statement that contains the given index of the masked code.
+fn statement_start(masked: &str, index: usize) -> usize {
+    masked[..index]
+        .rfind(|c| c == ';' || c == '{' || c == '}')
+        .map_or(0, |i| i + 1)
+}
+
+/// Gets the index of the end of the statement that contains the given index of the masked code.
+fn statement_end(masked: &str, index: usize) -> usize {
+    let index = index.min(masked.len());
+    masked[index..]
+        .find(';')
+        .map_or(masked.len(), |i| index + i + 1)
+}
+
+/// Parses a numeric file mode, in decimal or hexadecimal.
+fn parse_mode(mode: &str) -> Option<u32> {
+    if mode.starts_with("0x") || mode.starts_with("0X") {
+        u32::from_str_radix(&mode[2..], 16).ok()
+    } else {
+        mode.parse().ok()
+    }
+}
+
+/// Data storage analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_issues, get_line_for, Issue};
+
+    /// Checks the issues found in the code, in order.
+    #[test]
+    fn it_find_issues() {
+        let code = "File keys = new File(Environment.getExternalStorageDirectory(), \
+                    \"keys.jks\");\n\
+                    File folder = Environment.getExternalStorageDirectory();\n\
+                    Log.d(TAG, \"Saving\");\n\
+                    FileWriter writer = new FileWriter(new File(folder, \"passwords.txt\"));\n\
+                    FileOutputStream out = openFileOutput(\"cache\", 32769);\n\
+                    SharedPreferences prefs = getSharedPreferences(\"settings\", 3);";
+
+        let issues = find_issues(code)
+            .into_iter()
+            .map(|(issue, index)| (issue, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Issue::SensitiveExternalFile, 0),
+                (Issue::SensitiveExternalFile, 3),
+                (Issue::WorldReadableMode, 4),
+                (Issue::WorldReadableMode, 5),
+                (Issue::WorldWriteableMode, 5),
+            ]
+        );
+    }
+
+    /// Checks that the secure storage of data is not reported.
+    #[test]
+    fn it_secure_storage() {
+        let code = "File photo = new File(Environment.getExternalStoragePublicDirectory(\
+                    Environment.DIRECTORY_PICTURES), \"photo.jpg\"); // token.db\n\
+                    File keys = new File(getFilesDir(), \"keys.jks\");\n\
+                    FileOutputStream out = openFileOutput(\"passwords.txt\", 0);\n\
+                    FileOutputStream log = openFileOutput(\"log.txt\", 32768);\n\
+                    SharedPreferences prefs = getSharedPreferences(\"settings\", 4);\n\
+                    FileOutputStream shared = openFileOutput(\"shared.txt\", 1);";
+
+        assert!(find_issues(code).is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Task hijacking analysis module.
+//!
+//! Android groups activities in tasks, and places each activity in the task of its affinity,
+//! the package of the application by default. A malicious application can declare activities
+//! with the affinity of another application, so that they get placed in its task, on top of its
+//! activities, and show a fake screen to the user (StrandHogg). Activities launched as the root
+//! of their own task, with the `singleTask` or `singleInstance` launch modes, are the easiest
+//! targets, and custom affinities can leak the intents of the activities to other applications.
+//! Both risks are higher in applications that target SDK versions older than Android 9.
+
+use super::code::get_line_for;
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, VulnerabilityBuilder},
+    static_analysis::manifest::{get_line, ComponentKind, Manifest},
+    Config,
+};
+
+/// CWE of the task hijacking findings: improper restriction of rendered UI layers or frames.
+const TASK_HIJACKING_CWE: u32 = 1021;
+
+/// SDK version from which the task settings are less exposed (Android 9).
+const TASK_HARDENED_SDK: u32 = 28;
+
+/// Task setting that exposes an activity to task hijacking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// The application or the activity declares a custom task affinity.
+    TaskAffinity,
+    /// The exported activity is launched as the root of its own task.
+    TaskRoot,
+}
+
+impl Issue {
+    /// Gets the name of the finding of the issue.
+    fn name(self) -> &'static str {
+        match self {
+            Issue::TaskAffinity => "Custom task affinity",
+            Issue::TaskRoot => "Task hijacking",
+        }
+    }
+
+    /// Gets the criticality of the issue, for an application that targets the given SDK version.
+    fn criticality(self, target_sdk: u32) -> Criticality {
+        match self {
+            Issue::TaskAffinity if target_sdk < TASK_HARDENED_SDK => Criticality::Medium,
+            Issue::TaskAffinity => Criticality::Low,
+            Issue::TaskRoot if target_sdk < TASK_HARDENED_SDK => Criticality::High,
+            Issue::TaskRoot => Criticality::Medium,
+        }
+    }
+}
+
+/// Reports the task affinities and launch modes of the activities that expose them to task
+/// hijacking.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    // The target SDK version defaults to the minimum SDK version.
+    let target_sdk = manifest.target_sdk().unwrap_or_else(|| manifest.min_sdk());
+
+    if let Some(affinity) = manifest.task_affinity() {
+        if is_custom_affinity(manifest.package(), affinity) {
+            let description = format!(
+                "The application sets the task affinity of its activities to `{}`. Other \
+                 applications can declare the same affinity to place their activities in the \
+                 tasks of this application and read the intents that start them. Remove the \
+                 `android:taskAffinity` attribute, or set it to an empty string.",
+                affinity
+            );
+            let line = get_line(
+                manifest.code(),
+                &format!("android:taskAffinity=\"{}\"", affinity),
+            )
+            .ok();
+            report(
+                config,
+                manifest,
+                results,
+                Issue::TaskAffinity,
+                target_sdk,
+                description,
+                line,
+            );
+        }
+    }
+
+    for activity in manifest
+        .components()
+        .iter()
+        .filter(|component| component.kind() == ComponentKind::Activity)
+    {
+        if let Some(affinity) = activity.task_affinity() {
+            if is_custom_affinity(manifest.package(), affinity) {
+                let description = format!(
+                    "The `{}` activity sets its task affinity to `{}`. Other applications can \
+                     declare the same affinity to place their activities in its task and read \
+                     the intents that start it. Remove the `android:taskAffinity` attribute, or \
+                     set it to an empty string.",
+                    activity.name(),
+                    affinity
+                );
+                let line = attribute_line(manifest.code(), activity.name(), "android:taskAffinity");
+                report(
+                    config,
+                    manifest,
+                    results,
+                    Issue::TaskAffinity,
+                    target_sdk,
+                    description,
+                    line,
+                );
+            }
+        }
+
+        // Activities without affinity can't share their task with other applications.
+        let affinity = activity
+            .task_affinity()
+            .or_else(|| manifest.task_affinity())
+            .unwrap_or_else(|| manifest.package());
+        if activity.is_exported(manifest.min_sdk())
+            && activity.is_task_root()
+            && !affinity.is_empty()
+        {
+            let description = format!(
+                "The exported `{}` activity uses the `{}` launch mode, so it's the root of its own \
+                 task. A malicious application can declare an activity with the same task \
+                 affinity to get it placed on top of this one, and show a fake screen to the \
+                 user (StrandHogg). Use the `standard` launch mode, or set \
+                 `android:taskAffinity=\"\"` in the activity or in the application{}.",
+                activity.name(),
+                activity.launch_mode().unwrap_or_default(),
+                if target_sdk < TASK_HARDENED_SDK {
+                    ", and target Android 9 or newer"
+                } else {
+                    ""
+                }
+            );
+            let line = attribute_line(manifest.code(), activity.name(), "android:launchMode");
+            report(
+                config,
+                manifest,
+                results,
+                Issue::TaskRoot,
+                target_sdk,
+                description,
+                line,
+            );
+        }
+    }
+}
+
+/// Checks if the given task affinity differs from the default affinity of the application. An
+/// empty affinity means that the activities don't have any affinity.
+fn is_custom_affinity(package: &str, affinity: &str) -> bool {
+    !affinity.is_empty() && affinity != package
+}
+
+/// Gets the line of the given attribute in the element of the component with the given name, or
+/// the line of its name if the attribute is not found.
+fn attribute_line(code: &str, name: &str, attribute: &str) -> Option<usize> {
+    let name_index = code.find(&format!("android:name=\"{}\"", name))?;
+    let start = code[..name_index].rfind('<').unwrap_or(0);
+    let end = code[name_index..]
+        .find('>')
+        .map_or(code.len(), |end| name_index + end);
+    let index = code[start..end]
+        .find(attribute)
+        .map_or(name_index, |index| start + index);
+    Some(get_line_for(index, code))
+}
+
+/// Reports a task hijacking finding of an application that targets the given SDK version, in the
+/// given line of the manifest, if any.
+fn report(
+    config: &Config,
+    manifest: &Manifest,
+    results: &mut Results,
+    issue: Issue,
+    target_sdk: u32,
+    description: String,
+    line: Option<usize>,
+) {
+    let criticality = issue.criticality(target_sdk);
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let mut vulnerability =
+        VulnerabilityBuilder::new(criticality, issue.name(), description.as_str())
+            .file("AndroidManifest.xml")
+            .cwe(TASK_HIJACKING_CWE);
+    if let Some(line) = line {
+        let code = get_code(manifest.code(), line, line);
+        vulnerability = vulnerability.lines(line, line).code(code);
+    }
+    results.add_vulnerability(vulnerability.build());
+    print_vulnerability(description, criticality);
+}
+
+/// Task hijacking analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{attribute_line, is_custom_affinity, Issue};
+    use crate::criticality::Criticality;
+
+    /// Checks that the criticality of the issues scales with the target SDK version.
+    #[test]
+    fn it_criticality() {
+        assert_eq!(Issue::TaskRoot.criticality(27), Criticality::High);
+        assert_eq!(Issue::TaskRoot.criticality(28), Criticality::Medium);
+        assert_eq!(Issue::TaskAffinity.criticality(27), Criticality::Medium);
+        assert_eq!(Issue::TaskAffinity.criticality(33), Criticality::Low);
+    }
+
+    /// Checks the detection of custom task affinities.
+    #[test]
+    fn it_custom_affinity() {
+        assert!(is_custom_affinity("com.example", "com.example.login"));
+        assert!(is_custom_affinity("com.example", "com.bank"));
+        assert!(!is_custom_affinity("com.example", "com.example"));
+        assert!(!is_custom_affinity("com.example", ""));
+    }
+
+    /// Checks the lines of the attributes of the components.
+    #[test]
+    fn it_attribute_line() {
+        let code = "<application>\n\
+                    <activity android:name=\".MainActivity\" />\n\
+                    <activity\n\
+                    android:name=\".LoginActivity\"\n\
+                    android:launchMode=\"singleTask\" />\n\
+                    </application>";
+
+        assert_eq!(
+            attribute_line(code, ".LoginActivity", "android:launchMode"),
+            Some(4)
+        );
+        assert_eq!(
+            attribute_line(code, ".MainActivity", "android:launchMode"),
+            Some(1)
+        );
+        assert_eq!(
+            attribute_line(code, ".SettingsActivity", "android:launchMode"),
+            None
+        );
+    }
+}
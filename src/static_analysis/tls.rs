@@ -0,0 +1,242 @@
+//! TLS validation bypass analysis module.
+//!
+//! Applications that disable the validation of the TLS certificates accept the connections of
+//! anyone in the network, even if they use HTTPS. The usual bypasses are trust managers that don't
+//! check the server certificates, hostname verifiers that accept any host and the
+//! `ALLOW_ALL_HOSTNAME_VERIFIER` of the legacy Apache client. This module finds them from the
+//! structure of the decompiled code, checking the bodies of the implemented methods instead of
+//! single lines, so that comments, strings and implementations that do check something are not
+//! reported.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{mask, methods, Method},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+lazy_static! {
+    /// Uses of the hostname verifiers of the legacy Apache client that accept any host.
+    static ref ALLOW_ALL_HOSTNAME_VERIFIER: Regex =
+        Regex::new(r"\bALLOW_ALL_HOSTNAME_VERIFIER\b|\bnew\s+AllowAllHostnameVerifier\s*\(")
+            .unwrap();
+}
+
+/// Bypass of the TLS certificate validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bypass {
+    /// Trust manager with an empty `checkServerTrusted()` method.
+    EmptyServerTrustCheck,
+    /// Hostname verifier whose `verify()` method always returns `true`.
+    AcceptAllHostnames,
+    /// Use of the `ALLOW_ALL_HOSTNAME_VERIFIER` of the legacy Apache client.
+    AllowAllHostnameVerifier,
+}
+
+impl CodeIssue for Bypass {
+    fn id(&self) -> &'static str {
+        match *self {
+            Bypass::EmptyServerTrustCheck => "tls-empty-trust-manager",
+            Bypass::AcceptAllHostnames => "tls-accept-all-hostnames",
+            Bypass::AllowAllHostnameVerifier => "tls-allow-all-hostname-verifier",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Bypass::EmptyServerTrustCheck => "Trust manager accepts all certificates",
+            Bypass::AcceptAllHostnames => "Hostname verifier accepts all hosts",
+            Bypass::AllowAllHostnameVerifier => "Allow all hostname verifier",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Improper certificate validation.
+            Bypass::EmptyServerTrustCheck => 295,
+            // Improper validation of certificate with host mismatch.
+            Bypass::AcceptAllHostnames | Bypass::AllowAllHostnameVerifier => 297,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        "MASVS-NETWORK-1"
+    }
+
+    fn confidence(&self) -> Confidence {
+        // The bodies of the implemented methods are checked, not single lines.
+        Confidence::High
+    }
+}
+
+impl Bypass {
+    /// Gets the criticality of the findings of the bypass.
+    fn criticality(self) -> Criticality {
+        match self {
+            Bypass::EmptyServerTrustCheck => Criticality::Critical,
+            Bypass::AcceptAllHostnames | Bypass::AllowAllHostnameVerifier => Criticality::High,
+        }
+    }
+
+    /// Gets the description of the findings of the bypass.
+    fn description(self) -> &'static str {
+        match self {
+            Bypass::EmptyServerTrustCheck => {
+                "The application implements an `X509TrustManager` whose `checkServerTrusted()` \
+                 method is empty, so the connections that use it accept any certificate, \
+                 including self-signed ones. Anyone in the network can intercept and modify the \
+                 traffic with a Man in the Middle attack. Use the default trust manager, or a \
+                 network security configuration to trust specific certificates."
+            }
+            Bypass::AcceptAllHostnames => {
+                "The application implements a `HostnameVerifier` whose `verify()` method always \
+                 returns `true`, so the connections that use it accept the certificates issued \
+                 for any other host. Anyone with a valid certificate for any domain can intercept \
+                 the traffic. Use the default hostname verifier."
+            }
+            Bypass::AllowAllHostnameVerifier => {
+                "The application uses the `ALLOW_ALL_HOSTNAME_VERIFIER` of the legacy Apache \
+                 client, that accepts the certificates issued for any host. Anyone with a valid \
+                 certificate for any domain can intercept the traffic. Use the default hostname \
+                 verifier."
+            }
+        }
+    }
+}
+
+/// Scanner of the bypasses of the TLS certificate validation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TlsScanner;
+
+impl Scanner for TlsScanner {
+    fn check(&self) -> Check {
+        Check::Tls
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["tls"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_bypasses(code)
+            .into_iter()
+            .map(|(bypass, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(
+                    &bypass,
+                    bypass.criticality(),
+                    bypass.description(),
+                    line,
+                    line,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Finds the bypasses of the TLS certificate validation in the given code, with their index in
+/// it.
+fn find_bypasses(code: &str) -> Vec<(Bypass, usize)> {
+    let masked = mask(code);
+    let mut bypasses = Vec::new();
+
+    let trust_manager = masked.contains("TrustManager");
+    let hostname_verifier = masked.contains("HostnameVerifier");
+    if trust_manager || hostname_verifier {
+        for method in methods(&masked) {
+            if trust_manager && is_empty_server_trust_check(&method, &masked) {
+                bypasses.push((Bypass::EmptyServerTrustCheck, method.start()));
+            } else if hostname_verifier && is_accept_all_verify(&method, &masked) {
+                bypasses.push((Bypass::AcceptAllHostnames, method.start()));
+            }
+        }
+    }
+    bypasses.extend(
+        ALLOW_ALL_HOSTNAME_VERIFIER
+            .find_iter(&masked)
+            .map(|m| (Bypass::AllowAllHostnameVerifier, m.start())),
+    );
+
+    bypasses.sort_by_key(|&(_, index)| index);
+    bypasses
+}
+
+/// Checks if the method is a `checkServerTrusted()` implementation that doesn't check anything.
+fn is_empty_server_trust_check(method: &Method, masked: &str) -> bool {
+    method.name() == "checkServerTrusted"
+        && method
+            .parameter_types()
+            .first()
+            .map_or(false, |chain| chain.ends_with("X509Certificate[]"))
+        && ["", "return;"].contains(&method.statements(masked).as_str())
+}
+
+/// Checks if the method is a `verify()` implementation of a hostname verifier that always
+/// returns `true`.
+fn is_accept_all_verify(method: &Method, masked: &str) -> bool {
+    method.name() == "verify"
+        && method
+            .parameter_types()
+            .last()
+            .map_or(false, |session| session.ends_with("SSLSession"))
+        && method.statements(masked) == "returntrue;"
+}
+
+/// TLS validation bypass analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_bypasses, get_line_for, Bypass};
+
+    /// Checks the bypasses found in the code, in order.
+    #[test]
+    fn it_find_bypasses() {
+        let code = "TrustManager[] managers = { new X509TrustManager() {\n\
+                    public void checkClientTrusted(X509Certificate[] chain, String type) {}\n\
+                    public void checkServerTrusted(X509Certificate[] chain, String type)\n\
+                    throws CertificateException {\n\
+                    // Trust everyone.\n\
+                    }\n\
+                    public X509Certificate[] getAcceptedIssuers() { return null; }\n\
+                    } };\n\
+                    HttpsURLConnection.setDefaultHostnameVerifier(new HostnameVerifier() {\n\
+                    public boolean verify(String hostname, SSLSession session) {\n\
+                    return true;\n\
+                    }\n\
+                    });\n\
+                    factory.setHostnameVerifier(SSLSocketFactory.ALLOW_ALL_HOSTNAME_VERIFIER);";
+
+        let bypasses = find_bypasses(code)
+            .into_iter()
+            .map(|(bypass, index)| (bypass, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bypasses,
+            vec![
+                (Bypass::EmptyServerTrustCheck, 2),
+                (Bypass::AcceptAllHostnames, 9),
+                (Bypass::AllowAllHostnameVerifier, 13),
+            ]
+        );
+    }
+
+    /// Checks that implementations that validate the certificates are not reported.
+    #[test]
+    fn it_validating_implementations() {
+        let code = "public void checkServerTrusted(X509Certificate[] chain, String type)\n\
+                    throws CertificateException {\n\
+                    defaultTrustManager.checkServerTrusted(chain, type);\n\
+                    }\n\
+                    public boolean verify(String hostname, SSLSession session) {\n\
+                    return HttpsURLConnection.getDefaultHostnameVerifier()\n\
+                    .verify(\"api.example.com\", session);\n\
+                    }\n\
+                    // factory.setHostnameVerifier(ALLOW_ALL_HOSTNAME_VERIFIER);\n\
+                    String name = \"new AllowAllHostnameVerifier()\";";
+
+        assert!(find_bypasses(code).is_empty());
+    }
+}
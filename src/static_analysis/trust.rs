@@ -0,0 +1,183 @@
+//! Inter-application trust analysis module.
+//!
+//! Applications can establish trust relationships with other applications: sharing their user
+//! ID, granting signature permissions to the applications signed with the same or with known
+//! certificates, or declaring the packages that they need to see. This module reports those
+//! relationships, since a vulnerability or a malicious update in any of the trusted applications
+//! can compromise this one.
+
+use crate::{
+    criticality::Criticality,
+    get_code, print_vulnerability,
+    results::{Results, Vulnerability},
+    static_analysis::manifest::{get_line, Manifest},
+    Config,
+};
+
+/// Permission that makes every installed application visible.
+const QUERY_ALL_PACKAGES: &str = "android.permission.QUERY_ALL_PACKAGES";
+
+/// Reports the trust relationships that the application establishes with other applications.
+pub fn analysis(config: &Config, manifest: &Manifest, results: &mut Results) {
+    if let Some(shared_user_id) = manifest.shared_user_id() {
+        let description = format!(
+            "The application shares the user ID `{}` with the other applications signed with the \
+             same certificate. They can run in the same process, access the data of each other \
+             and they are granted the permissions of all of them, so a vulnerability in any of \
+             them compromises all of them. Shared user IDs are deprecated since Android 10.",
+            shared_user_id
+        );
+        report(
+            config,
+            manifest,
+            results,
+            Criticality::Medium,
+            "Shared user ID",
+            description,
+            "sharedUserId",
+        );
+    }
+
+    for permission in manifest.declared_permissions() {
+        if permission.is_known_signer() {
+            let description = format!(
+                "The application declares the `{}` permission, that is granted to the \
+                 applications signed with the known certificates{}. Those applications can \
+                 access the components protected by the permission without asking the user, so \
+                 check that every certificate belongs to a trusted developer.",
+                permission.name(),
+                permission
+                    .known_certs()
+                    .map(|certs| format!(" in `{}`", certs))
+                    .unwrap_or_default()
+            );
+            report(
+                config,
+                manifest,
+                results,
+                Criticality::Low,
+                "Permission granted to known signers",
+                description,
+                permission.name(),
+            );
+        } else if permission.is_signature() {
+            let description = format!(
+                "The application declares the `{}` signature permission. Other applications \
+                 signed with the same certificate are granted it without asking the user, and \
+                 can access the components protected by it.",
+                permission.name()
+            );
+            report(
+                config,
+                manifest,
+                results,
+                Criticality::Warning,
+                "Signature permission",
+                description,
+                permission.name(),
+            );
+        }
+    }
+
+    if manifest
+        .requested_permissions()
+        .iter()
+        .any(|permission| permission == QUERY_ALL_PACKAGES)
+    {
+        report(
+            config,
+            manifest,
+            results,
+            Criticality::Medium,
+            "Query all packages",
+            "The application can see every application installed in the device. The list of \
+             installed applications reveals sensitive information about the user, and it's only \
+             allowed in Google Play for applications that need it for their core functionality.",
+            QUERY_ALL_PACKAGES,
+        );
+    }
+
+    let visible: Vec<String> = manifest
+        .queried_packages()
+        .iter()
+        .map(|package| format!("package `{}`", package))
+        .chain(
+            manifest
+                .queried_authorities()
+                .iter()
+                .map(|authority| format!("provider `{}`", authority)),
+        )
+        .chain(
+            manifest
+                .queried_actions()
+                .iter()
+                .map(|action| format!("action `{}`", action)),
+        )
+        .collect();
+    if !visible.is_empty() {
+        let description = format!(
+            "The application declares that it interacts with the applications that match the \
+             following queries: {}. Check that the data sent to and received from them is \
+             validated, since any application can use those packages, authorities or actions.",
+            visible.join(", ")
+        );
+        report(
+            config,
+            manifest,
+            results,
+            Criticality::Warning,
+            "Package visibility",
+            description,
+            "<queries",
+        );
+    }
+
+    if !manifest.protected_broadcasts().is_empty() {
+        let description = format!(
+            "The application declares the following protected broadcasts: {}. Only system \
+             applications can protect broadcasts, so in any other application the declaration \
+             is ignored and any application can send them to the receivers that rely on it.",
+            manifest.protected_broadcasts().join(", ")
+        );
+        report(
+            config,
+            manifest,
+            results,
+            Criticality::Medium,
+            "Protected broadcasts",
+            description,
+            "protected-broadcast",
+        );
+    }
+}
+
+/// Reports a trust relationship, pointing to the first line of the manifest with the given text.
+fn report<D: Into<String>>(
+    config: &Config,
+    manifest: &Manifest,
+    results: &mut Results,
+    criticality: Criticality,
+    name: &str,
+    description: D,
+    needle: &str,
+) {
+    if criticality < config.min_criticality() {
+        return;
+    }
+
+    let description = description.into();
+    let line = get_line(manifest.code(), needle).ok();
+    let code = line.map(|l| get_code(manifest.code(), l, l));
+
+    let vulnerability = Vulnerability::new(
+        criticality,
+        name,
+        description.as_str(),
+        Some("AndroidManifest.xml"),
+        line,
+        line,
+        code,
+    );
+    results.add_vulnerability(vulnerability);
+    print_vulnerability(description, criticality);
+}
@@ -0,0 +1,399 @@
+//! User interface protection analysis module.
+//!
+//! Screens that show sensitive data can leak it outside of the application: the system takes
+//! screenshots of them for the recent applications list and any application with screen capture
+//! access can record them, overlays drawn by other applications can trick the user into tapping
+//! on them, and the data copied to the clipboard can be read by other applications. This module
+//! checks that the activities declared in the manifest that handle sensitive data set
+//! `FLAG_SECURE` and filter the touches received while they are obscured, and scans the
+//! decompiled code for sensitive data copied to the clipboard. The findings are reported with
+//! the configured `ui_protection_criticality`, in the *Privacy* section of the report.
+
+use std::{fs, io, path::Path};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use super::{
+    capture::PRIVACY_SECTION,
+    check::Check,
+    code::get_line_for,
+    java::{arguments, mask},
+    logging::sensitive_identifier,
+    manifest::{ComponentKind, Manifest},
+    scanner::{self, CodeIssue, Finding, Scanner},
+};
+use crate::{
+    print_warning,
+    results::{Confidence, Results},
+    Config,
+};
+
+lazy_static! {
+    /// Password input fields.
+    static ref PASSWORD_INPUT: Regex = Regex::new(
+        r"\b(?:PasswordTransformationMethod|TYPE_TEXT_VARIATION_(?:WEB_)?PASSWORD|TYPE_NUMBER_VARIATION_PASSWORD)\b|\bsetInputType\s*\(\s*(?:129|225|18)\s*\)"
+    )
+    .unwrap();
+    /// Window flags that prevent screenshots, by name or by value.
+    static ref FLAG_SECURE: Regex =
+        Regex::new(r"\bFLAG_SECURE\b|\b(?:setFlags|addFlags)\s*\(\s*8192\b").unwrap();
+    /// Code that discards the touches received while the view is obscured.
+    static ref TOUCH_FILTER: Regex = Regex::new(
+        r"\bsetFilterTouchesWhenObscured\s*\(\s*true\s*\)|\bonFilterTouchEventForSecurity\b"
+    )
+    .unwrap();
+    /// Layouts referenced in the code, with their name.
+    static ref LAYOUT: Regex = Regex::new(r"\bR\s*\.\s*layout\s*\.\s*([\w$]+)").unwrap();
+    /// Layout attribute that discards the touches received while the view is obscured.
+    static ref LAYOUT_TOUCH_FILTER: Regex =
+        Regex::new(r#"\bfilterTouchesWhenObscured\s*=\s*"true""#).unwrap();
+    /// Calls that copy data to the clipboard.
+    static ref CLIPBOARD_WRITE: Regex = Regex::new(
+        r"\b(?:(ClipData\s*\.\s*new(?:PlainText|HtmlText))|[\w$]*[Cc]lipboard[\w$]*\s*\.\s*setText)\s*\("
+    )
+    .unwrap();
+}
+
+/// User interface protection issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// Sensitive activity that can be captured in screenshots and screen recordings.
+    MissingFlagSecure,
+    /// Sensitive activity that accepts touches while other windows are drawn over it.
+    MissingTouchFilter,
+    /// Sensitive data copied to the clipboard.
+    ClipboardWrite,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::MissingFlagSecure => "ui-missing-flag-secure",
+            Issue::MissingTouchFilter => "ui-missing-touch-filter",
+            Issue::ClipboardWrite => "ui-clipboard-write",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::MissingFlagSecure => "Missing screenshot protection",
+            Issue::MissingTouchFilter => "Missing tapjacking protection",
+            Issue::ClipboardWrite => "Sensitive data copied to clipboard",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Exposure of sensitive information to an unauthorized actor.
+            Issue::MissingFlagSecure | Issue::ClipboardWrite => 200,
+            // Improper restriction of rendered UI layers or frames.
+            Issue::MissingTouchFilter => 1021,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        match *self {
+            Issue::MissingFlagSecure | Issue::MissingTouchFilter => "MASVS-PLATFORM-3",
+            Issue::ClipboardWrite => "MASVS-STORAGE-2",
+        }
+    }
+
+    fn confidence(&self) -> Confidence {
+        // The sensitive data is detected by the names in the code, and the protections could be
+        // set in a parent class or in code that could not be decompiled.
+        Confidence::Medium
+    }
+}
+
+impl Issue {
+    /// Gets the description of the findings of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            Issue::MissingFlagSecure => {
+                "An activity that handles sensitive data does not set `FLAG_SECURE` in its \
+                 window. The system keeps a screenshot of it in the recent applications list, \
+                 and applications with screen capture access can record it. Add \
+                 `WindowManager.LayoutParams.FLAG_SECURE` to the flags of the window before \
+                 showing the sensitive data."
+            }
+            Issue::MissingTouchFilter => {
+                "An activity that handles sensitive data accepts the touches received while \
+                 other applications draw over it. A malicious overlay can hide the real content \
+                 of the screen and trick the user into tapping on it. Set \
+                 `android:filterTouchesWhenObscured=\"true\"` in the sensitive views of the \
+                 layout, or call `setFilterTouchesWhenObscured(true)` on them."
+            }
+            Issue::ClipboardWrite => {
+                "The application copies data that seems sensitive to the clipboard. Other \
+                 applications can read the clipboard, and since Android 13 the system shows its \
+                 content in a preview. Don't copy sensitive data to the clipboard, or mark it \
+                 with `ClipDescription.EXTRA_IS_SENSITIVE` and clear it after a short time."
+            }
+        }
+    }
+}
+
+/// Scanner of the sensitive data copied to the clipboard.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClipboardScanner;
+
+impl Scanner for ClipboardScanner {
+    fn check(&self) -> Check {
+        Check::UiProtection
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["privacy"]
+    }
+
+    fn scan(&self, config: &Config, code: &str) -> Vec<Finding> {
+        let issue = Issue::ClipboardWrite;
+        find_clipboard_writes(code, config.sensitive_identifiers())
+            .into_iter()
+            .map(|index| {
+                let line = get_line_for(index, code);
+                Finding::new(
+                    &issue,
+                    config.ui_protection_criticality(),
+                    issue.description(),
+                    line,
+                    line,
+                )
+                .section(PRIVACY_SECTION)
+            })
+            .collect()
+    }
+}
+
+/// Searches the activities of the application that handle sensitive data for missing screenshot
+/// and tapjacking protections.
+///
+/// The sensitive data copied to the clipboard is searched by the `ClipboardScanner`, with the rest
+/// of the code scanners.
+pub fn analysis<S: AsRef<str>>(
+    config: &Config,
+    package: S,
+    manifest: &Manifest,
+    results: &mut Results,
+) {
+    if config.ui_protection_criticality() < config.min_criticality() {
+        return;
+    }
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let sink = results.sink();
+
+    for activity in manifest.components_of(ComponentKind::Activity) {
+        let path = Path::new("classes").join(format!(
+            "{}.java",
+            activity.class_name(manifest.package()).replace('.', "/")
+        ));
+        let code = match fs::read_to_string(dist_folder.join(&path)) {
+            Ok(code) => code,
+            // The class can be missing if it could not be decompiled, or if it's obfuscated.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                print_warning(format!(
+                    "could not read {} searching for user interface protections: {}",
+                    path.display(),
+                    e
+                ));
+                continue;
+            }
+        };
+
+        let issues = check_activity(&code, config.sensitive_identifiers(), |layout| {
+            layout_filters_touches(&dist_folder, layout)
+        });
+        for (issue, index) in issues {
+            let line = get_line_for(index, &code);
+            let description = format!("{} Activity: `{}`.", issue.description(), activity.name());
+            let finding = Finding::new(
+                &issue,
+                config.ui_protection_criticality(),
+                description,
+                line,
+                line,
+            )
+            .section(PRIVACY_SECTION);
+            scanner::report(
+                config,
+                &sink,
+                Some(manifest.package()),
+                &path,
+                &code,
+                finding,
+            );
+        }
+    }
+}
+
+/// Checks the protections of the activity with the given code, if it handles sensitive data,
+/// and returns the missing ones, with the index of the sensitive data in the code.
+///
+/// The given function checks if the layout with the given name filters the obscured touches.
+fn check_activity<S, F>(
+    code: &str,
+    sensitive_identifiers: &[S],
+    layout_filters_touches: F,
+) -> Vec<(Issue, usize)>
+where
+    S: AsRef<str>,
+    F: Fn(&str) -> bool,
+{
+    let masked = mask(code);
+    let index = match PASSWORD_INPUT.find(&masked) {
+        Some(input) => input.start(),
+        None => {
+            // Layout names, such as `activity_token`, are not sensitive data themselves.
+            let searched =
+                LAYOUT.replace_all(&masked, |layout: &Captures<'_>| " ".repeat(layout[0].len()));
+            let index =
+                sensitive_identifier(&searched, sensitive_identifiers).and_then(|identifier| {
+                    Regex::new(&format!(r"\b{}\b", regex::escape(identifier)))
+                        .unwrap()
+                        .find(&searched)
+                        .map(|m| m.start())
+                });
+            match index {
+                Some(index) => index,
+                None => return Vec::new(),
+            }
+        }
+    };
+
+    let mut issues = Vec::new();
+    if !FLAG_SECURE.is_match(&masked) {
+        issues.push((Issue::MissingFlagSecure, index));
+    }
+    let filters_touches = TOUCH_FILTER.is_match(&masked)
+        || LAYOUT
+            .captures_iter(&masked)
+            .any(|layout| layout_filters_touches(&layout[1]));
+    if !filters_touches {
+        issues.push((Issue::MissingTouchFilter, index));
+    }
+    issues
+}
+
+/// Checks if the layout with the given name, in the given folder of the decompiled application,
+/// filters the touches received while it's obscured.
+fn layout_filters_touches(dist_folder: &Path, layout: &str) -> bool {
+    let path = dist_folder
+        .join("res")
+        .join("layout")
+        .join(format!("{}.xml", layout));
+    fs::read_to_string(path)
+        .map(|layout| LAYOUT_TOUCH_FILTER.is_match(&layout))
+        .unwrap_or(false)
+}
+
+/// Finds the clipboard writes of sensitive data in the given code, and returns their index in
+/// it.
+fn find_clipboard_writes<S: AsRef<str>>(code: &str, sensitive_identifiers: &[S]) -> Vec<usize> {
+    let masked = mask(code);
+    CLIPBOARD_WRITE
+        .captures_iter(&masked)
+        .filter_map(|captures| {
+            let call = captures.get(0).unwrap();
+            // The first argument of the clip data is its label, not the copied data.
+            let skipped = if captures.get(1).is_some() { 1 } else { 0 };
+            let sensitive = arguments(&masked, call.end())
+                .into_iter()
+                .skip(skipped)
+                .any(|argument| {
+                    sensitive_identifier(&masked[argument], sensitive_identifiers).is_some()
+                });
+            if sensitive {
+                Some(call.start())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// User interface protection analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{check_activity, find_clipboard_writes, get_line_for, Issue};
+
+    /// Sensitive identifiers used in the tests.
+    const SENSITIVE_IDENTIFIERS: [&str; 2] = ["password", "token"];
+
+    /// Checks the missing protections of the activities that handle sensitive data.
+    #[test]
+    fn it_check_activity() {
+        let code = "public class LoginActivity extends Activity {\n\
+                    protected void onCreate(Bundle savedInstanceState) {\n\
+                    setContentView(R.layout.activity_login);\n\
+                    pin.setTransformationMethod(PasswordTransformationMethod.getInstance());\n\
+                    }\n\
+                    }";
+        let issues = check_activity(code, &SENSITIVE_IDENTIFIERS, |_| false)
+            .into_iter()
+            .map(|(issue, index)| (issue, get_line_for(index, code)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (Issue::MissingFlagSecure, 3),
+                (Issue::MissingTouchFilter, 3),
+            ]
+        );
+
+        let code = "public class AccountActivity extends Activity {\n\
+                    protected void onCreate(Bundle savedInstanceState) {\n\
+                    getWindow().setFlags(8192, 8192);\n\
+                    setContentView(R.layout.activity_token);\n\
+                    tokenView.setText(accessToken);\n\
+                    }\n\
+                    }";
+        let issues = check_activity(code, &SENSITIVE_IDENTIFIERS, |_| false);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].0, Issue::MissingTouchFilter);
+        assert_eq!(get_line_for(issues[0].1, code), 4);
+        assert!(check_activity(code, &SENSITIVE_IDENTIFIERS, |layout| {
+            layout == "activity_token"
+        })
+        .is_empty());
+    }
+
+    /// Checks that the protected activities and the activities without sensitive data are not
+    /// reported.
+    #[test]
+    fn it_protected_activities() {
+        let code = "public class LoginActivity extends Activity {\n\
+                    protected void onCreate(Bundle savedInstanceState) {\n\
+                    getWindow().addFlags(WindowManager.LayoutParams.FLAG_SECURE);\n\
+                    passwordField.setFilterTouchesWhenObscured(true);\n\
+                    }\n\
+                    }";
+        assert!(check_activity(code, &SENSITIVE_IDENTIFIERS, |_| false).is_empty());
+
+        let code = "public class AboutActivity extends Activity {\n\
+                    protected void onCreate(Bundle savedInstanceState) {\n\
+                    setContentView(R.layout.activity_about);\n\
+                    // Never shows the password.\n\
+                    title.setText(\"Token generator\");\n\
+                    }\n\
+                    }";
+        assert!(check_activity(code, &SENSITIVE_IDENTIFIERS, |_| false).is_empty());
+    }
+
+    /// Checks the clipboard writes of sensitive data found in the code.
+    #[test]
+    fn it_find_clipboard_writes() {
+        let code = "ClipData clip = ClipData.newPlainText(\"label\", user.getPassword());\n\
+                    clipboard.setPrimaryClip(clip);\n\
+                    clipboardManager.setText(authToken);\n\
+                    ClipData.newPlainText(\"password\", shareUrl);\n\
+                    ClipData.newPlainText(\"link\", shareUrl);\n\
+                    textView.setText(password);";
+        let lines = find_clipboard_writes(code, &SENSITIVE_IDENTIFIERS)
+            .into_iter()
+            .map(|index| get_line_for(index, code))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![0, 2]);
+    }
+}
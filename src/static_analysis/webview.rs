@@ -0,0 +1,261 @@
+//! WebView security analysis module.
+//!
+//! WebViews run the web content that they load with the permissions of the application, so their
+//! settings decide what a malicious page, or a page modified in transit, can do. This module
+//! searches the decompiled code for the settings that expose the application: JavaScript
+//! interfaces added to WebViews with JavaScript enabled, access from `file://` URLs to other files
+//! or to any origin, and `onReceivedSslError()` handlers that proceed with invalid certificates.
+//! All the findings are grouped in the *WebView* section of the report.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    check::Check,
+    code::get_line_for,
+    java::{block_end, mask},
+    scanner::{CodeIssue, Finding, Scanner},
+};
+use crate::{criticality::Criticality, results::Confidence, Config};
+
+/// Report section of the WebView findings.
+const WEBVIEW_SECTION: &str = "WebView";
+
+lazy_static! {
+    /// JavaScript enabled in the settings of a WebView.
+    static ref JAVASCRIPT_ENABLED: Regex =
+        Regex::new(r"\.\s*setJavaScriptEnabled\(\s*true\s*\)").unwrap();
+    /// JavaScript interfaces added to a WebView.
+    static ref JAVASCRIPT_INTERFACE: Regex =
+        Regex::new(r"\.\s*addJavascriptInterface\(").unwrap();
+    /// Access from `file://` URLs to other files.
+    static ref FILE_ACCESS: Regex =
+        Regex::new(r"\.\s*setAllowFileAccessFromFileURLs\(\s*true\s*\)").unwrap();
+    /// Access from `file://` URLs to any origin.
+    static ref UNIVERSAL_ACCESS: Regex =
+        Regex::new(r"\.\s*setAllowUniversalAccessFromFileURLs\(\s*true\s*\)").unwrap();
+    /// Declarations of the handlers of the SSL errors of a WebView.
+    static ref SSL_ERROR_HANDLER: Regex =
+        Regex::new(r"\bvoid\s+onReceivedSslError\s*\(\s*WebView\b[^)]*\)[^{;]*\{").unwrap();
+    /// Calls that proceed with the request after an SSL error.
+    static ref PROCEED: Regex = Regex::new(r"\.\s*proceed\(\s*\)").unwrap();
+}
+
+/// Insecure use of a WebView.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Issue {
+    /// JavaScript interface added to a WebView with JavaScript enabled.
+    JavascriptInterface,
+    /// Access from `file://` URLs to other files allowed.
+    FileAccessFromFileUrls,
+    /// Access from `file://` URLs to any origin allowed.
+    UniversalAccessFromFileUrls,
+    /// SSL errors ignored by proceeding with the request.
+    SslErrorsIgnored,
+}
+
+impl CodeIssue for Issue {
+    fn id(&self) -> &'static str {
+        match *self {
+            Issue::JavascriptInterface => "webview-js-interface",
+            Issue::FileAccessFromFileUrls => "webview-file-url-file-access",
+            Issue::UniversalAccessFromFileUrls => "webview-file-url-universal-access",
+            Issue::SslErrorsIgnored => "webview-ssl-error-proceed",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Issue::JavascriptInterface => "WebView JavaScript interface",
+            Issue::FileAccessFromFileUrls => "WebView file access from file URLs",
+            Issue::UniversalAccessFromFileUrls => "WebView universal access from file URLs",
+            Issue::SslErrorsIgnored => "WebView ignores SSL errors",
+        }
+    }
+
+    fn cwe(&self) -> u32 {
+        match *self {
+            // Exposed dangerous method or function.
+            Issue::JavascriptInterface => 749,
+            // Exposure of sensitive information to an unauthorized actor.
+            Issue::FileAccessFromFileUrls | Issue::UniversalAccessFromFileUrls => 200,
+            // Improper certificate validation.
+            Issue::SslErrorsIgnored => 295,
+        }
+    }
+
+    fn owasp_masvs(&self) -> &'static str {
+        match *self {
+            Issue::SslErrorsIgnored => "MASVS-NETWORK-1",
+            _ => "MASVS-PLATFORM-2",
+        }
+    }
+
+    fn confidence(&self) -> Confidence {
+        match *self {
+            // JavaScript could be enabled in a different WebView of the same file.
+            Issue::JavascriptInterface => Confidence::Medium,
+            _ => Confidence::High,
+        }
+    }
+}
+
+impl Issue {
+    /// Gets the criticality of the findings of the issue.
+    fn criticality(self) -> Criticality {
+        match self {
+            Issue::SslErrorsIgnored => Criticality::Critical,
+            Issue::JavascriptInterface | Issue::UniversalAccessFromFileUrls => Criticality::High,
+            Issue::FileAccessFromFileUrls => Criticality::Medium,
+        }
+    }
+
+    /// Gets the description of the findings of the issue.
+    fn description(self) -> &'static str {
+        match self {
+            Issue::JavascriptInterface => {
+                "The application adds a JavaScript interface to a WebView with JavaScript \
+                 enabled. Any page loaded in the WebView, including pages injected in transit or \
+                 through a cross-site scripting vulnerability, can call the methods of the \
+                 interface and, in Android versions older than 4.2, execute code in the \
+                 application. Only add interfaces to WebViews that load trusted content over \
+                 HTTPS."
+            }
+            Issue::FileAccessFromFileUrls => {
+                "The application allows the JavaScript of the pages loaded from `file://` URLs in \
+                 a WebView to read other local files. A malicious HTML file can read the private \
+                 files of the application. Don't enable `setAllowFileAccessFromFileURLs()`."
+            }
+            Issue::UniversalAccessFromFileUrls => {
+                "The application allows the JavaScript of the pages loaded from `file://` URLs in \
+                 a WebView to access content from any origin. A malicious HTML file can read the \
+                 private files of the application and send them to any server. Don't enable \
+                 `setAllowUniversalAccessFromFileURLs()`."
+            }
+            Issue::SslErrorsIgnored => {
+                "The application handles the SSL errors of a WebView by proceeding with the \
+                 request, so the WebView accepts any certificate. An attacker in the network can \
+                 intercept and modify the content loaded in the WebView. Cancel the request in \
+                 `onReceivedSslError()`."
+            }
+        }
+    }
+}
+
+/// Scanner of the insecure uses of WebViews.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WebviewScanner;
+
+impl Scanner for WebviewScanner {
+    fn check(&self) -> Check {
+        Check::Webview
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["injection"]
+    }
+
+    fn scan(&self, _: &Config, code: &str) -> Vec<Finding> {
+        find_issues(code)
+            .into_iter()
+            .map(|(issue, index)| {
+                let line = get_line_for(index, code);
+                Finding::new(&issue, issue.criticality(), issue.description(), line, line)
+                    .section(WEBVIEW_SECTION)
+            })
+            .collect()
+    }
+}
+
+/// Finds the insecure uses of WebViews in the given code, with their index in it.
+fn find_issues(code: &str) -> Vec<(Issue, usize)> {
+    // Commented settings and strings are not reported.
+    let masked = mask(code);
+    let mut issues = Vec::new();
+
+    // Interfaces are only reachable from the pages if JavaScript is enabled.
+    if JAVASCRIPT_ENABLED.is_match(&masked) {
+        issues.extend(
+            JAVASCRIPT_INTERFACE
+                .find_iter(&masked)
+                .map(|m| (Issue::JavascriptInterface, m.start())),
+        );
+    }
+    issues.extend(
+        FILE_ACCESS
+            .find_iter(&masked)
+            .map(|m| (Issue::FileAccessFromFileUrls, m.start())),
+    );
+    issues.extend(
+        UNIVERSAL_ACCESS
+            .find_iter(&masked)
+            .map(|m| (Issue::UniversalAccessFromFileUrls, m.start())),
+    );
+
+    // Only the handlers that proceed in their own body are reported.
+    for handler in SSL_ERROR_HANDLER.find_iter(&masked) {
+        let body = &masked[handler.end()..block_end(&masked, handler.end())];
+        if PROCEED.is_match(body) {
+            issues.push((Issue::SslErrorsIgnored, handler.start()));
+        }
+    }
+
+    issues.sort_by_key(|&(_, index)| index);
+    issues
+}
+
+/// WebView security analysis testing module.
+#[cfg(test)]
+mod test {
+    use super::{find_issues, Issue};
+
+    /// Checks the issues found in the code, in order.
+    #[test]
+    fn it_find_issues() {
+        let code = "WebSettings settings = webView.getSettings();\n\
+                    settings.setJavaScriptEnabled(true);\n\
+                    settings.setAllowFileAccessFromFileURLs(true);\n\
+                    settings.setAllowUniversalAccessFromFileURLs(true);\n\
+                    webView.addJavascriptInterface(new Bridge(), \"bridge\");\n\
+                    webView.setWebViewClient(new WebViewClient() {\n\
+                    public void onReceivedSslError(WebView view, SslErrorHandler handler, \
+                    SslError error) {\n\
+                    if (BuildConfig.DEBUG) { Log.d(TAG, \"SSL error\"); }\n\
+                    handler.proceed();\n\
+                    }\n\
+                    });";
+
+        let issues = find_issues(code)
+            .into_iter()
+            .map(|(issue, _)| issue)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                Issue::FileAccessFromFileUrls,
+                Issue::UniversalAccessFromFileUrls,
+                Issue::JavascriptInterface,
+                Issue::SslErrorsIgnored,
+            ]
+        );
+    }
+
+    /// Checks that the secure uses of WebViews are not reported.
+    #[test]
+    fn it_secure_uses() {
+        let code = "WebSettings settings = webView.getSettings();\n\
+                    settings.setJavaScriptEnabled(false);\n\
+                    settings.setAllowFileAccessFromFileURLs(false);\n\
+                    settings.setAllowUniversalAccessFromFileURLs(false);\n\
+                    webView.addJavascriptInterface(new Bridge(), \"bridge\");\n\
+                    webView.setWebViewClient(new WebViewClient() {\n\
+                    public void onReceivedSslError(WebView view, SslErrorHandler handler, \
+                    SslError error) {\n\
+                    handler.cancel();\n\
+                    }\n\
+                    });\n\
+                    request.proceed();";
+
+        assert!(find_issues(code).is_empty());
+    }
+}
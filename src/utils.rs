@@ -1,9 +1,15 @@
 //! General utilities module.
 
-use std::{fmt, fs, path::Path, thread::sleep, time::Duration};
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::Duration,
+};
 
 use colored::Colorize;
-use failure::Error;
+use failure::{Error, ResultExt};
 use lazy_static::lazy_static;
 use log::Level::Debug;
 use xml::{
@@ -11,7 +17,7 @@ use xml::{
     ParserConfig,
 };
 
-use crate::{config::Config, criticality::Criticality};
+use crate::{arsc::ResourceTable, config::Config, criticality::Criticality};
 
 /// Configuration for the XML parser.
 lazy_static! {
@@ -75,6 +81,63 @@ pub fn get_package_name<P: AsRef<Path>>(path: P) -> String {
         .into_owned()
 }
 
+/// Adds the Java files in the given folder to the vector, recursively.
+pub fn add_java_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    add_files_with_extensions(path, &["java"], files)
+}
+
+/// Adds the files with any of the given extensions in the given folder to the vector,
+/// recursively.
+pub fn add_files_with_extensions(
+    path: &Path,
+    extensions: &[&str],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    if !path.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            add_files_with_extensions(&entry_path, extensions, files)?;
+        } else if entry_path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map_or(false, |e| extensions.contains(&e))
+        {
+            files.push(entry_path);
+        }
+    }
+    Ok(())
+}
+
+/// Kind of the package given to the analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageKind {
+    /// Android application package, with its manifest and resources.
+    Apk,
+    /// Bare DEX file, such as a payload extracted from an application.
+    Dex,
+    /// Bare JAR file, such as an SDK artifact.
+    Jar,
+}
+
+impl PackageKind {
+    /// Gets the kind of the package at the given path, from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(OsStr::to_str) {
+            Some(extension) if extension.eq_ignore_ascii_case("dex") => PackageKind::Dex,
+            Some(extension) if extension.eq_ignore_ascii_case("jar") => PackageKind::Jar,
+            _ => PackageKind::Apk,
+        }
+    }
+
+    /// Checks if the package is a full application, with its manifest and resources.
+    pub fn is_apk(self) -> bool {
+        self == PackageKind::Apk
+    }
+}
+
 /// Gets the code snippet near the start and end lines.
 ///
 /// It will return 5 lines above and 5 lines below the vulnerability.
@@ -93,15 +156,17 @@ pub fn get_code<S: AsRef<str>>(code: S, s_line: usize, e_line: usize) -> String
 }
 
 /// Gets a string from the strings XML file.
+///
+/// If the string can't be found in the decoded resources, it will be searched in the compiled
+/// resource table, since resource decoding fails often on protected applications.
 pub fn get_string<L: AsRef<str>, P: AsRef<str>>(
     label: L,
     config: &Config,
     package: P,
 ) -> Result<String, Error> {
-    let code = fs::read_to_string({
-        let path = config
-            .dist_folder()
-            .join(package.as_ref())
+    let dist_folder = config.dist_folder().join(package.as_ref());
+    let xml_result = fs::read_to_string({
+        let path = dist_folder
             .join("res")
             .join("values-en")
             .join("strings.xml");
@@ -109,15 +174,36 @@ pub fn get_string<L: AsRef<str>, P: AsRef<str>>(
         if path.exists() {
             path
         } else {
-            config
-                .dist_folder()
-                .join(package.as_ref())
-                .join("res")
-                .join("values")
-                .join("strings.xml")
+            dist_folder.join("res").join("values").join("strings.xml")
+        }
+    });
+
+    match xml_result {
+        Ok(code) => {
+            if let Some(string) = get_xml_string(label.as_ref(), code) {
+                return Ok(string);
+            }
+        }
+        Err(e) => {
+            if !dist_folder.join("resources.arsc").exists() {
+                return Err(e.into());
+            }
+        }
+    }
+
+    let arsc_path = dist_folder.join("resources.arsc");
+    if arsc_path.exists() {
+        let table = ResourceTable::from_file(&arsc_path)
+            .context("could not parse the resources.arsc file")?;
+        if let Some(string) = table.string(label.as_ref()) {
+            return Ok(string.to_owned());
         }
-    })?;
+    }
+    Ok(String::new())
+}
 
+/// Gets a string from the contents of a strings XML file.
+fn get_xml_string(label: &str, code: String) -> Option<String> {
     let bytes = code.into_bytes();
     let parser = EventReader::new_with_config(bytes.as_slice(), PARSER_CONFIG.clone());
 
@@ -129,7 +215,7 @@ pub fn get_string<L: AsRef<str>, P: AsRef<str>>(
             }) => {
                 if let "string" = name.local_name.as_str() {
                     for attr in attributes {
-                        if attr.name.local_name == "name" && attr.value == label.as_ref() {
+                        if attr.name.local_name == "name" && attr.value == label {
                             found = true;
                         }
                     }
@@ -137,13 +223,13 @@ pub fn get_string<L: AsRef<str>, P: AsRef<str>>(
             }
             Ok(XmlEvent::Characters(data)) => {
                 if found {
-                    return Ok(data);
+                    return Some(data);
                 }
             }
             _ => {}
         }
     }
-    Ok(String::new())
+    None
 }
 
 /// Structure to store a benchmark information.
@@ -179,8 +265,27 @@ impl fmt::Display for Benchmark {
 
 #[cfg(test)]
 mod test {
+    use super::PackageKind;
     use crate::get_code;
 
+    /// Checks the kinds of the packages given to the analysis.
+    #[test]
+    fn it_package_kind() {
+        assert_eq!(
+            PackageKind::from_path("downloads/com.example.apk"),
+            PackageKind::Apk
+        );
+        assert_eq!(
+            PackageKind::from_path("downloads/payload.dex"),
+            PackageKind::Dex
+        );
+        assert_eq!(
+            PackageKind::from_path("downloads/analytics-sdk.JAR"),
+            PackageKind::Jar
+        );
+        assert!(PackageKind::from_path("downloads/com.example").is_apk());
+    }
+
     #[test]
     fn it_get_code() {
         let code = "Lorem ipsum dolor sit amet, consectetur adipiscing elit.\nCurabitur tortor. \
@@ -0,0 +1,100 @@
+//! Analysis workspace module.
+//!
+//! The external tools write scratch files while they decode and decompile an application: the
+//! shards of a parallel decompilation, the APK files extracted from bundles, the framework files
+//! of _Apktool_ and the temporary files of the Java runtime. Every analysis gets its own
+//! temporary workspace for them, in the configured temporary folder, so that analyses running at
+//! the same time, even of different files with the same package name, never read or remove the
+//! scratch files of each other. The workspace is removed when the analysis ends.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use failure::{Error, ResultExt};
+
+use crate::{print_warning, Config};
+
+/// Counter used to give each workspace of the process a different name.
+static WORKSPACE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary workspace of an analysis, removed when dropped.
+#[derive(Debug)]
+pub(crate) struct Workspace {
+    /// Absolute path of the workspace.
+    path: PathBuf,
+}
+
+impl Workspace {
+    /// Creates a new empty workspace for the analysis of the given package, in the configured
+    /// temporary folder.
+    pub(crate) fn create<S: AsRef<str>>(config: &Config, package_name: S) -> Result<Self, Error> {
+        let tmp_folder = if config.tmp_folder().is_absolute() {
+            config.tmp_folder().to_path_buf()
+        } else {
+            env::current_dir()
+                .context("could not get the current working directory")?
+                .join(config.tmp_folder())
+        };
+        let path = tmp_folder.join(format!(
+            "super-workspace-{}-{}-{}",
+            package_name.as_ref(),
+            process::id(),
+            WORKSPACE_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        // A previous process with the same ID could have been killed before removing it.
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path).context(format!(
+            "could not create the analysis workspace at {}",
+            path.display()
+        ))?;
+        Ok(Self { path })
+    }
+
+    /// Gets the absolute path of the workspace.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            print_warning(format!(
+                "the analysis workspace at {} could not be removed: {}",
+                self.path.display(),
+                e
+            ));
+        }
+    }
+}
+
+/// Analysis workspace testing module.
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::Workspace;
+    use crate::Config;
+
+    /// Checks that every workspace is a different folder, removed when dropped.
+    #[test]
+    fn it_workspace() {
+        let config = Config::default();
+        let first = Workspace::create(&config, "com.example").unwrap();
+        let second = Workspace::create(&config, "com.example").unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().starts_with(env::temp_dir()));
+        assert!(first.path().is_dir());
+
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().is_dir());
+    }
+}